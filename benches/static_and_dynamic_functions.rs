@@ -146,6 +146,28 @@ pub fn run_basic_dynamic_function(store: &Store, compiler_name: &str, c: &mut Cr
     );
 }
 
+static TRAP_WAT: &str = r#"(module
+    (func $die unreachable)
+    (func (export "trap") call $die)
+)"#;
+
+/// Calls a function trapping two frames deep, to measure what a
+/// failing call costs, and what formatting its error adds.
+pub fn run_trapping_function(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(&store, TRAP_WAT).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let f: NativeFunc<(), ()> = instance.exports.get_native_function("trap").unwrap();
+
+    c.bench_function(&format!("trapping static func {}", compiler_name), |b| {
+        b.iter(|| black_box(f.call().unwrap_err()))
+    });
+
+    c.bench_function(
+        &format!("trapping static func formatted {}", compiler_name),
+        |b| b.iter(|| black_box(f.call().unwrap_err().to_string())),
+    );
+}
+
 fn run_static_benchmarks(_c: &mut Criterion) {
     #[cfg(feature = "llvm")]
     {
@@ -190,6 +212,64 @@ fn run_dynamic_benchmarks(_c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, run_static_benchmarks, run_dynamic_benchmarks);
+/// The same benchmarks as `run_static_benchmarks`, with epoch
+/// interruption enabled, to compare the overhead of its checks.
+#[allow(unused_variables)]
+fn run_epoch_benchmarks(c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        let mut compiler = wasmer_compiler_llvm::LLVM::new();
+        compiler.epoch_interruption(true);
+        let store = Store::new(&Universal::new(compiler).engine());
+        run_basic_static_function(&store, "llvm with epoch interruption", c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        let mut compiler = wasmer_compiler_cranelift::Cranelift::new();
+        compiler.epoch_interruption(true);
+        let store = Store::new(&Universal::new(compiler).engine());
+        run_basic_static_function(&store, "cranelift with epoch interruption", c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        let mut compiler = wasmer_compiler_singlepass::Singlepass::new();
+        compiler.epoch_interruption(true);
+        let store = Store::new(&Universal::new(compiler).engine());
+        run_basic_static_function(&store, "singlepass with epoch interruption", c);
+    }
+}
+
+#[allow(unused_variables)]
+fn run_trap_benchmarks(c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        let store = Store::new(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine());
+        run_trapping_function(&store, "llvm", c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        let store =
+            Store::new(&Universal::new(wasmer_compiler_cranelift::Cranelift::new()).engine());
+        run_trapping_function(&store, "cranelift", c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        let store =
+            Store::new(&Universal::new(wasmer_compiler_singlepass::Singlepass::new()).engine());
+        run_trapping_function(&store, "singlepass", c);
+    }
+}
+
+criterion_group!(
+    benches,
+    run_static_benchmarks,
+    run_dynamic_benchmarks,
+    run_epoch_benchmarks,
+    run_trap_benchmarks
+);
 
 criterion_main!(benches);
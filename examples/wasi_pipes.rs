@@ -11,6 +11,7 @@
 //!
 //! Ready?
 
+use std::io::{Read, Write};
 use wasmer::{Instance, Module, Store};
 use wasmer_compiler_cranelift::Cranelift;
 use wasmer_engine_universal::Universal;
@@ -36,11 +37,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Creating `WasiEnv`...");
     // First, we create the `WasiEnv` with the stdio pipes
-    let input = Pipe::new();
-    let output = Pipe::new();
+    let (stdin_reader, mut stdin_writer) = Pipe::new();
+    let (mut stdout_reader, stdout_writer) = Pipe::new();
     let mut wasi_env = WasiState::new("hello")
-        .stdin(Box::new(input))
-        .stdout(Box::new(output))
+        .stdin(Box::new(stdin_reader))
+        .stdout(Box::new(stdout_writer))
         .finalize()?;
 
     println!("Instantiating module with WASI imports...");
@@ -51,16 +52,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let msg = "racecar go zoom";
     println!("Writing \"{}\" to the WASI stdin...", msg);
-    // To write to the stdin, we need a mutable reference to the pipe
-    //
-    // We access WasiState in a nested scope to ensure we're not holding
-    // the mutex after we need it.
-    {
-        let mut state = wasi_env.state();
-        let wasi_stdin = state.fs.stdin_mut()?.as_mut().unwrap();
-        // Then we can write to it!
-        writeln!(wasi_stdin, "{}", msg)?;
-    }
+    // To write to the stdin, we write to the other end of its pipe.
+    writeln!(stdin_writer, "{}", msg)?;
 
     println!("Call WASI `_start` function...");
     // And we just call the `_start` function!
@@ -68,12 +61,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     start.call(&[])?;
 
     println!("Reading from the WASI stdout...");
-    // To read from the stdout, we again need a mutable reference to the pipe
-    let mut state = wasi_env.state();
-    let wasi_stdout = state.fs.stdout_mut()?.as_mut().unwrap();
-    // Then we can read from it!
+    // To read from the stdout, we read from the other end of its pipe.
     let mut buf = String::new();
-    wasi_stdout.read_to_string(&mut buf)?;
+    stdout_reader.read_to_string(&mut buf)?;
     println!("Read \"{}\" from the WASI stdout!", buf.trim());
 
     Ok(())
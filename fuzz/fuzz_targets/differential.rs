@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasmer::fuzzing::{run_differential, FuzzBackend, FuzzConfig};
+use wasmer::{Features, Pages};
+
+fuzz_target!(|bytes: &[u8]| {
+    let config = |backend| FuzzConfig {
+        backend,
+        features: Features::default(),
+        max_points: 100_000,
+        max_memory: Pages(16),
+    };
+    let outcome = run_differential(
+        bytes,
+        &config(FuzzBackend::Singlepass),
+        &config(FuzzBackend::Cranelift),
+    );
+    assert!(outcome.agree(), "{:?}", outcome);
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasmer::fuzzing::{run_arbitrary, FuzzBackend, FuzzConfig};
+use wasmer::{Features, Pages};
+
+fuzz_target!(|bytes: &[u8]| {
+    let config = FuzzConfig {
+        backend: FuzzBackend::Cranelift,
+        features: Features::default(),
+        max_points: 100_000,
+        max_memory: Pages(16),
+    };
+    run_arbitrary(bytes, &config);
+});
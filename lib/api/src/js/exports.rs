@@ -7,6 +7,7 @@ use indexmap::IndexMap;
 use std::fmt;
 use std::iter::{ExactSizeIterator, FromIterator};
 use thiserror::Error;
+use wasmer_types::{ExternType, FunctionType};
 
 /// The `ExportError` can happen when trying to get a specific
 /// export [`Extern`] from the [`Instance`] exports.
@@ -49,13 +50,43 @@ use thiserror::Error;
 pub enum ExportError {
     /// An error than occurs when the exported type and the expected type
     /// are incompatible.
-    #[error("Incompatible Export Type")]
-    IncompatibleType,
+    #[error("Incompatible export type: expected a {expected}, found a {found}")]
+    IncompatibleType {
+        /// The kind of extern expected, e.g. `memory`, or the type of
+        /// the function for native functions.
+        expected: String,
+        /// The type of the export.
+        found: ExternType,
+    },
     /// This error arises when an export is missing
     #[error("Missing export {0}")]
     Missing(String),
 }
 
+impl ExportError {
+    /// The error for an export `found` which isn't a `expected`.
+    pub(crate) fn incompatible(expected: &str, found: &Extern) -> Self {
+        Self::IncompatibleType {
+            expected: expected.to_string(),
+            found: found.ty(),
+        }
+    }
+
+    /// The error for a function whose type doesn't match the one of
+    /// a `NativeFunc<Args, Rets>`.
+    pub(crate) fn incompatible_native<Args, Rets>(function: &Function) -> Self
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        let expected = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+        Self::IncompatibleType {
+            expected: ExternType::Function(expected).to_string(),
+            found: ExternType::Function(function.ty().clone()),
+        }
+    }
+}
+
 /// Exports is a special kind of map that allows easily unwrapping
 /// the types of instances.
 ///
@@ -144,9 +175,10 @@ impl Exports {
         Args: WasmTypeList,
         Rets: WasmTypeList,
     {
-        self.get_function(name)?
+        let function = self.get_function(name)?;
+        function
             .native()
-            .map_err(|_| ExportError::IncompatibleType)
+            .map_err(|_| ExportError::incompatible_native::<Args, Rets>(function))
     }
 
     /// Hack to get this working with nativefunc too
@@ -614,7 +614,7 @@ impl<'a> Exportable<'a> for Function {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Function(func) => Ok(func),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("function", _extern)),
         }
     }
 }
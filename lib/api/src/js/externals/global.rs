@@ -236,7 +236,7 @@ impl<'a> Exportable<'a> for Global {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Global(global) => Ok(global),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("global", _extern)),
         }
     }
 }
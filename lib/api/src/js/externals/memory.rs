@@ -328,7 +328,7 @@ impl<'a> Exportable<'a> for Memory {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Memory(memory) => Ok(memory),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("memory", _extern)),
         }
     }
 }
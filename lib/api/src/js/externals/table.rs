@@ -161,7 +161,7 @@ impl<'a> Exportable<'a> for Table {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Table(table) => Ok(table),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("table", _extern)),
         }
     }
 }
@@ -71,8 +71,8 @@ pub use crate::js::trap::RuntimeError;
 
 pub use crate::js::store::{Store, StoreObject};
 pub use crate::js::types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Val, ValType,
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, IndexType, MemoryType,
+    Mutability, TableType, Val, ValType,
 };
 pub use crate::js::types::{Val as Value, ValType as Type};
 pub use crate::js::utils::is_wasm;
@@ -82,8 +82,5 @@ pub use wasmer_types::{
     WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
-#[cfg(feature = "wat")]
-pub use wat::parse_bytes as wat2wasm;
-
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -384,11 +384,11 @@ pub fn parse_import_section<'data>(
                     unimplemented!("64bit memory not implemented yet");
                 }
                 module_info.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(initial as u32),
-                        maximum: maximum.map(|p| Pages(p as u32)),
+                    MemoryType::new(
+                        Pages(initial as u32),
+                        maximum.map(|p| Pages(p as u32)),
                         shared,
-                    },
+                    ),
                     module_name,
                     field_name.unwrap_or_default(),
                 )?;
@@ -471,11 +471,11 @@ pub fn parse_memory_section(
         if memory64 {
             unimplemented!("64bit memory not implemented yet");
         }
-        module_info.declare_memory(MemoryType {
-            minimum: Pages(initial as u32),
-            maximum: maximum.map(|p| Pages(p as u32)),
+        module_info.declare_memory(MemoryType::new(
+            Pages(initial as u32),
+            maximum.map(|p| Pages(p as u32)),
             shared,
-        })?;
+        ))?;
     }
 
     Ok(())
@@ -114,7 +114,8 @@ macro_rules! impl_native_traits {
         {
             fn get_self_from_extern_with_generics(_extern: &crate::js::externals::Extern) -> Result<Self, crate::js::exports::ExportError> {
                 use crate::js::exports::Exportable;
-                crate::js::Function::get_self_from_extern(_extern)?.native().map_err(|_| crate::js::exports::ExportError::IncompatibleType)
+                let function = crate::js::Function::get_self_from_extern(_extern)?;
+                function.native().map_err(|_| crate::js::exports::ExportError::incompatible_native::<( $( $x ),* ), Rets>(function))
             }
         }
     };
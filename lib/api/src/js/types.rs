@@ -4,8 +4,8 @@ use crate::js::externals::Function;
 use wasm_bindgen::JsValue;
 use wasmer_types::Value;
 pub use wasmer_types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Type as ValType,
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, IndexType, MemoryType,
+    Mutability, TableType, Type as ValType,
 };
 
 /// WebAssembly computations manipulate values of basic value types:
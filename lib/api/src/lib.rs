@@ -471,3 +471,9 @@ mod js;
 
 #[cfg(feature = "js")]
 pub use js::*;
+
+#[cfg(feature = "wat")]
+mod wat;
+
+#[cfg(feature = "wat")]
+pub use crate::wat::{wat2wasm, WatError};
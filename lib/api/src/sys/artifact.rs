@@ -0,0 +1,61 @@
+//! Patching the embedder metadata of serialized artifacts.
+//!
+//! Embedders attach named metadata sections to the modules serialized
+//! with the Universal engine, e.g. a release id, and rotate them
+//! without compiling or deserializing the modules again: the sections
+//! are stored after the serialized module, which is left
+//! byte-identical.
+//!
+//! # Example
+//!
+//! ```
+//! # use wasmer::{Module, Store};
+//! # fn main() -> anyhow::Result<()> {
+//! # let store = Store::default();
+//! let module = Module::new(&store, "(module)")?;
+//! let serialized = module.serialize()?;
+//!
+//! let patched = wasmer::artifact::patch_metadata(&serialized, &[("release", &b"42"[..])])?;
+//! assert_eq!(
+//!     wasmer::artifact::metadata(&patched)?,
+//!     vec![("release".to_string(), b"42".to_vec())]
+//! );
+//! let _module = unsafe { Module::deserialize(&store, &patched)? };
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+use wasmer_engine_universal::UniversalArtifact;
+
+pub use wasmer_engine_universal::PatchError;
+
+/// Returns the embedder metadata sections of the serialized module
+/// `bytes`, in order.
+pub fn metadata(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, PatchError> {
+    UniversalArtifact::embedder_metadata(bytes)
+}
+
+/// Returns a copy of the serialized module `bytes` whose embedder
+/// metadata sections named in `sections` are replaced, or added after
+/// the existing ones, with the given data.
+///
+/// The serialized module is copied as is: the patched artifact
+/// deserializes to the same module.
+pub fn patch_metadata(bytes: &[u8], sections: &[(&str, &[u8])]) -> Result<Vec<u8>, PatchError> {
+    UniversalArtifact::patch_embedder_metadata(bytes, sections)
+}
+
+/// Like [`patch_metadata`], but patches the module serialized in the
+/// file at `path` in place, e.g. by [`Module::serialize_to_file`].
+///
+/// Only the metadata at the end of the file is read and rewritten,
+/// whatever the size of the serialized module.
+///
+/// [`Module::serialize_to_file`]: crate::Module::serialize_to_file
+pub fn patch_metadata_file(
+    path: impl AsRef<Path>,
+    sections: &[(&str, &[u8])],
+) -> Result<(), PatchError> {
+    UniversalArtifact::patch_embedder_metadata_file(path, sections)
+}
@@ -0,0 +1,87 @@
+//! The instance calling a host function, see [`Caller`].
+
+use crate::sys::env::{HostEnvInitError, LazyInit, WasmerEnv};
+use crate::sys::exports::{Exportable, Exports};
+use crate::sys::externals::{Extern, Memory};
+use crate::sys::instance::Instance;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{ExportIndex, MemoryIndex};
+
+/// A handle to the instance calling a host function created with
+/// [`Function::new_with_caller`].
+///
+/// A host function imported by several instances gets a `Caller`
+/// for each of them, and so always sees the instance that invoked
+/// it. The handle doesn't keep the instance alive.
+///
+/// [`Function::new_with_caller`]: crate::Function::new_with_caller
+#[derive(Clone, Debug)]
+pub struct Caller {
+    exports: Exports,
+    memories: Vec<Memory>,
+}
+
+impl Caller {
+    fn new(instance: &Instance) -> Self {
+        let exports = instance
+            .exports
+            .iter()
+            .map(|(name, extern_)| {
+                let mut extern_ = extern_.clone();
+                extern_.into_weak_instance_ref();
+                (name.clone(), extern_)
+            })
+            .collect();
+        let memories = (0..instance.module().info().memories.len())
+            .map(|index| {
+                match instance.lookup_by_declaration(&ExportIndex::Memory(MemoryIndex::new(index)))
+                {
+                    Extern::Memory(mut memory) => {
+                        memory.into_weak_instance_ref();
+                        memory
+                    }
+                    _ => unreachable!("a memory index refers to a memory"),
+                }
+            })
+            .collect();
+        Self { exports, memories }
+    }
+
+    /// Returns the export `name` of the calling instance.
+    pub fn get_export(&self, name: &str) -> Option<&Extern> {
+        self.exports.get_extern(name)
+    }
+
+    /// Returns the memory of index `index` of the calling instance,
+    /// whether it is exported or not. Imported memories come first.
+    pub fn memory(&self, index: u32) -> Option<&Memory> {
+        self.memories.get(index as usize)
+    }
+}
+
+/// The environment of a host function created with
+/// [`Function::new_with_caller`], which records the instance importing
+/// it next to the user environment.
+///
+/// [`Function::new_with_caller`]: crate::Function::new_with_caller
+#[derive(Clone)]
+pub(crate) struct WithCaller<Env> {
+    pub(crate) env: Env,
+    pub(crate) caller: LazyInit<Caller>,
+}
+
+impl<Env> WithCaller<Env> {
+    pub(crate) fn new(env: Env) -> Self {
+        Self {
+            env,
+            caller: LazyInit::new(),
+        }
+    }
+}
+
+impl<Env: WasmerEnv> WasmerEnv for WithCaller<Env> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.caller.initialize(Caller::new(instance));
+        self.env.init_with_instance(instance)
+    }
+}
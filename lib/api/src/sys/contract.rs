@@ -0,0 +1,428 @@
+//! Import contracts: the imports and the exports of a module, with
+//! their types, in a stable JSON format, see [`ImportContract`].
+
+use crate::sys::import_object::ImportObject;
+use crate::sys::types::{ExportType, ExternType, ImportType};
+use crate::sys::{FunctionType, GlobalType, IndexType, MemoryType, Mutability, TableType, Type};
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wasmer_engine::Export;
+use wasmer_types::Pages;
+
+/// The imports and the exports of a module, with their types, as
+/// returned by [`Module::export_import_contract`].
+///
+/// With the `enable-serde` feature, a contract is serialized to a
+/// stable JSON format with [`ImportContract::to_json`], so that the
+/// contract of a guest can be checked against the imports a host
+/// provides, with
+/// [`ImportContract::verify_against`], or against the contract of a
+/// previous release of the guest, with [`ImportContract::diff`],
+/// without having the code of the other side.
+///
+/// [`Module::export_import_contract`]: crate::Module::export_import_contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ImportContract {
+    /// The version of the format of the contract, [`ImportContract::VERSION`].
+    pub version: u32,
+    /// The imports of the module, in order.
+    pub imports: Vec<ContractImport>,
+    /// The exports of the module, in order.
+    pub exports: Vec<ContractExport>,
+}
+
+/// An import of an [`ImportContract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ContractImport {
+    /// The namespace of the import.
+    pub module: String,
+    /// The name of the import.
+    pub name: String,
+    /// The type of the import.
+    #[cfg_attr(feature = "enable-serde", serde(rename = "type"))]
+    pub ty: ContractType,
+}
+
+/// An export of an [`ImportContract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ContractExport {
+    /// The name of the export.
+    pub name: String,
+    /// The type of the export.
+    #[cfg_attr(feature = "enable-serde", serde(rename = "type"))]
+    pub ty: ContractType,
+}
+
+/// The type of an import or an export of an [`ImportContract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "enable-serde",
+    serde(tag = "kind", rename_all = "lowercase")
+)]
+pub enum ContractType {
+    /// A function.
+    Function {
+        /// The types of the parameters.
+        params: Vec<ContractValueType>,
+        /// The types of the results.
+        results: Vec<ContractValueType>,
+    },
+    /// A global.
+    Global {
+        /// The type of the value.
+        #[cfg_attr(feature = "enable-serde", serde(rename = "type"))]
+        ty: ContractValueType,
+        /// Whether the global can be set.
+        mutable: bool,
+    },
+    /// A table.
+    Table {
+        /// The type of the elements.
+        element: ContractValueType,
+        /// The minimum number of elements.
+        minimum: u32,
+        /// The maximum number of elements, if any.
+        maximum: Option<u32>,
+    },
+    /// A memory.
+    Memory {
+        /// The minimum number of pages.
+        minimum: u32,
+        /// The maximum number of pages, if any.
+        maximum: Option<u32>,
+        /// Whether the memory is shared between threads.
+        shared: bool,
+        /// Whether the memory is addressed with `i64`s, see the
+        /// memory64 proposal.
+        #[cfg_attr(feature = "enable-serde", serde(default))]
+        memory64: bool,
+    },
+}
+
+/// A value type of a [`ContractType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "enable-serde", serde(rename_all = "lowercase"))]
+pub enum ContractValueType {
+    /// `i32`.
+    I32,
+    /// `i64`.
+    I64,
+    /// `f32`.
+    F32,
+    /// `f64`.
+    F64,
+    /// `v128`.
+    V128,
+    /// `externref`.
+    ExternRef,
+    /// `funcref`.
+    FuncRef,
+}
+
+/// An import provided by a host which doesn't satisfy an
+/// [`ImportContract`], as returned by
+/// [`ImportContract::verify_against`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ContractViolation {
+    /// The import isn't provided.
+    #[error("missing import `{module}`.`{name}`")]
+    Missing {
+        /// The namespace of the import.
+        module: String,
+        /// The name of the import.
+        name: String,
+    },
+    /// The import is provided with an incompatible type.
+    #[error("incompatible import `{module}`.`{name}`: expected {expected:?}, found {found:?}")]
+    Incompatible {
+        /// The namespace of the import.
+        module: String,
+        /// The name of the import.
+        name: String,
+        /// The type in the contract.
+        expected: ContractType,
+        /// The type of the provided import.
+        found: ContractType,
+    },
+}
+
+/// A difference between two [`ImportContract`]s of a module, as
+/// returned by [`ImportContract::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractChange {
+    /// The newer module has an import the older doesn't have.
+    ImportAdded(ContractImport),
+    /// The newer module doesn't have an import of the older.
+    ImportRemoved(ContractImport),
+    /// The import has another type in the newer module.
+    ImportChanged {
+        /// The import of the older module.
+        before: ContractImport,
+        /// The import of the newer module.
+        after: ContractImport,
+    },
+    /// The newer module has an export the older doesn't have.
+    ExportAdded(ContractExport),
+    /// The newer module doesn't have an export of the older.
+    ExportRemoved(ContractExport),
+    /// The export has another type in the newer module.
+    ExportChanged {
+        /// The export of the older module.
+        before: ContractExport,
+        /// The export of the newer module.
+        after: ContractExport,
+    },
+}
+
+impl ContractChange {
+    /// Whether the change breaks the hosts running the older module:
+    /// they may not provide an added or a changed import, and may use
+    /// a removed or a changed export. Removing an import or adding an
+    /// export is compatible.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::ImportAdded(_) | Self::ImportChanged { .. } => true,
+            Self::ExportRemoved(_) | Self::ExportChanged { .. } => true,
+            Self::ImportRemoved(_) | Self::ExportAdded(_) => false,
+        }
+    }
+}
+
+/// An error reading an [`ImportContract`] from JSON.
+#[derive(Error, Debug)]
+#[error("invalid import contract: {0}")]
+pub struct ImportContractError(String);
+
+impl ImportContract {
+    /// The version of the format written by this version of Wasmer.
+    pub const VERSION: u32 = 1;
+
+    pub(crate) fn new(
+        imports: impl Iterator<Item = ImportType>,
+        exports: impl Iterator<Item = ExportType>,
+    ) -> Self {
+        Self {
+            version: Self::VERSION,
+            imports: imports
+                .map(|import| ContractImport {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    ty: import.ty().into(),
+                })
+                .collect(),
+            exports: exports
+                .map(|export| ContractExport {
+                    name: export.name().to_string(),
+                    ty: export.ty().into(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Serializes the contract to JSON.
+    #[cfg(feature = "enable-serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a contract can always be serialized")
+    }
+
+    /// Deserializes a contract from JSON, as written by
+    /// [`ImportContract::to_json`].
+    #[cfg(feature = "enable-serde")]
+    pub fn from_json(json: &str) -> Result<Self, ImportContractError> {
+        let contract: Self =
+            serde_json::from_str(json).map_err(|e| ImportContractError(e.to_string()))?;
+        if contract.version != Self::VERSION {
+            return Err(ImportContractError(format!(
+                "unsupported version {}, expected {}",
+                contract.version,
+                Self::VERSION
+            )));
+        }
+        Ok(contract)
+    }
+
+    /// Checks that `imports` provides every import of the contract,
+    /// with a compatible type, as instantiating the module would.
+    /// Returns the imports which aren't satisfied, in order.
+    pub fn verify_against(&self, imports: &ImportObject) -> Vec<ContractViolation> {
+        self.imports
+            .iter()
+            .filter_map(|import| {
+                let export = match imports.get_export(&import.module, &import.name) {
+                    Some(export) => export,
+                    None => {
+                        return Some(ContractViolation::Missing {
+                            module: import.module.clone(),
+                            name: import.name.clone(),
+                        })
+                    }
+                };
+                let found = export_type(&export);
+                if found.is_compatible_with(&import.ty.to_extern_type()) {
+                    None
+                } else {
+                    Some(ContractViolation::Incompatible {
+                        module: import.module.clone(),
+                        name: import.name.clone(),
+                        expected: import.ty.clone(),
+                        found: (&found).into(),
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Lists the differences between the contracts of an `older` and
+    /// a `newer` release of a module: the imports, then the exports,
+    /// of `older` which were removed or changed, in order, followed
+    /// by the ones added in `newer`.
+    pub fn diff(older: &Self, newer: &Self) -> Vec<ContractChange> {
+        let mut changes = Vec::new();
+        for before in &older.imports {
+            match newer
+                .imports
+                .iter()
+                .find(|after| after.module == before.module && after.name == before.name)
+            {
+                None => changes.push(ContractChange::ImportRemoved(before.clone())),
+                Some(after) if after.ty != before.ty => {
+                    changes.push(ContractChange::ImportChanged {
+                        before: before.clone(),
+                        after: after.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for after in &newer.imports {
+            if !older
+                .imports
+                .iter()
+                .any(|before| before.module == after.module && before.name == after.name)
+            {
+                changes.push(ContractChange::ImportAdded(after.clone()));
+            }
+        }
+        for before in &older.exports {
+            match newer.exports.iter().find(|after| after.name == before.name) {
+                None => changes.push(ContractChange::ExportRemoved(before.clone())),
+                Some(after) if after.ty != before.ty => {
+                    changes.push(ContractChange::ExportChanged {
+                        before: before.clone(),
+                        after: after.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for after in &newer.exports {
+            if !older.exports.iter().any(|before| before.name == after.name) {
+                changes.push(ContractChange::ExportAdded(after.clone()));
+            }
+        }
+        changes
+    }
+}
+
+impl ContractType {
+    /// The `ExternType` this type describes.
+    pub fn to_extern_type(&self) -> ExternType {
+        match self {
+            Self::Function { params, results } => ExternType::Function(FunctionType::new(
+                params.iter().map(|ty| Type::from(*ty)).collect::<Vec<_>>(),
+                results.iter().map(|ty| Type::from(*ty)).collect::<Vec<_>>(),
+            )),
+            Self::Global { ty, mutable } => {
+                ExternType::Global(GlobalType::new((*ty).into(), Mutability::from(*mutable)))
+            }
+            Self::Table {
+                element,
+                minimum,
+                maximum,
+            } => ExternType::Table(TableType::new((*element).into(), *minimum, *maximum)),
+            Self::Memory {
+                minimum,
+                maximum,
+                shared,
+                memory64,
+            } => {
+                let new = if *memory64 {
+                    MemoryType::new64
+                } else {
+                    MemoryType::new
+                };
+                ExternType::Memory(new(Pages(*minimum), maximum.map(Pages), *shared))
+            }
+        }
+    }
+}
+
+impl From<&ExternType> for ContractType {
+    fn from(ty: &ExternType) -> Self {
+        match ty {
+            ExternType::Function(ty) => Self::Function {
+                params: ty.params().iter().map(|ty| (*ty).into()).collect(),
+                results: ty.results().iter().map(|ty| (*ty).into()).collect(),
+            },
+            ExternType::Global(ty) => Self::Global {
+                ty: ty.ty.into(),
+                mutable: ty.mutability.is_mutable(),
+            },
+            ExternType::Table(ty) => Self::Table {
+                element: ty.ty.into(),
+                minimum: ty.minimum,
+                maximum: ty.maximum,
+            },
+            ExternType::Memory(ty) => Self::Memory {
+                minimum: ty.minimum.0,
+                maximum: ty.maximum.map(|maximum| maximum.0),
+                shared: ty.shared,
+                memory64: ty.index_type == IndexType::I64,
+            },
+        }
+    }
+}
+
+impl From<Type> for ContractValueType {
+    fn from(ty: Type) -> Self {
+        match ty {
+            Type::I32 => Self::I32,
+            Type::I64 => Self::I64,
+            Type::F32 => Self::F32,
+            Type::F64 => Self::F64,
+            Type::V128 => Self::V128,
+            Type::ExternRef => Self::ExternRef,
+            Type::FuncRef => Self::FuncRef,
+        }
+    }
+}
+
+impl From<ContractValueType> for Type {
+    fn from(ty: ContractValueType) -> Self {
+        match ty {
+            ContractValueType::I32 => Self::I32,
+            ContractValueType::I64 => Self::I64,
+            ContractValueType::F32 => Self::F32,
+            ContractValueType::F64 => Self::F64,
+            ContractValueType::V128 => Self::V128,
+            ContractValueType::ExternRef => Self::ExternRef,
+            ContractValueType::FuncRef => Self::FuncRef,
+        }
+    }
+}
+
+/// The type of an export provided to an instantiation.
+fn export_type(export: &Export) -> ExternType {
+    match export {
+        Export::Function(f) => ExternType::Function(f.vm_function.signature.clone()),
+        Export::Table(t) => ExternType::Table(*t.ty()),
+        Export::Memory(m) => ExternType::Memory(m.ty()),
+        Export::Global(g) => ExternType::Global(*g.from.ty()),
+    }
+}
@@ -1,4 +1,7 @@
 use crate::sys::{ExportError, Instance};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::thread::{self, ThreadId};
 use thiserror::Error;
 
 /// An error while initializing the user supplied host env with the `WasmerEnv` trait.
@@ -133,6 +136,143 @@ impl<T: WasmerEnv> WasmerEnv for ::std::sync::Arc<::std::sync::Mutex<T>> {
     }
 }
 
+/// The host-side handle to the environment of a host function created
+/// with [`Function::new_native_with_env_mut`], which gets it mutably.
+///
+/// The environment is owned by Wasmer, and shared by the clones of the
+/// function: the host gets a handle with [`Function::shared_env`], to
+/// read or update the environment with [`SharedEnv::with`] while the
+/// function is in use. The environment is initialized with
+/// [`WasmerEnv::init_with_instance`] for every instance importing the
+/// function.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::{Function, SharedEnv, Store, WasmerEnv};
+/// # let store = Store::default();
+/// #[derive(WasmerEnv, Clone, Default)]
+/// struct Counter {
+///     count: i32,
+/// }
+///
+/// let increment = Function::new_native_with_env_mut(
+///     &store,
+///     Counter::default(),
+///     |counter: &mut Counter, by: i32| counter.count += by,
+/// );
+/// let counter: SharedEnv<Counter> = increment.shared_env().unwrap();
+///
+/// increment.native::<i32, ()>().unwrap().call(3).unwrap();
+/// assert_eq!(counter.with(|counter| counter.count), 3);
+/// ```
+///
+/// [`Function::new_native_with_env_mut`]: crate::Function::new_native_with_env_mut
+/// [`Function::shared_env`]: crate::Function::shared_env
+pub struct SharedEnv<Env> {
+    inner: Arc<SharedEnvInner<Env>>,
+}
+
+struct SharedEnvInner<Env> {
+    env: Mutex<Env>,
+    /// The thread currently holding `env`, to detect reentrant
+    /// borrows rather than deadlock on them.
+    borrower: Mutex<Option<ThreadId>>,
+}
+
+impl<Env> SharedEnv<Env> {
+    /// Creates a new shared environment.
+    pub(crate) fn new(env: Env) -> Self {
+        Self {
+            inner: Arc::new(SharedEnvInner {
+                env: Mutex::new(env),
+                borrower: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Calls `f` with the environment, waiting for the other threads
+    /// using it to be done.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the environment is already borrowed by this thread,
+    /// e.g. when called from a host function using it.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Env) -> R) -> R {
+        self.try_with(f)
+            .expect("the environment is already borrowed by this thread")
+    }
+
+    /// Like [`SharedEnv::with`], but fails instead of panicking if the
+    /// environment is already borrowed by this thread, e.g. by a host
+    /// function which called back into WebAssembly, which called it
+    /// again.
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut Env) -> R) -> Result<R, SharedEnvBorrowError> {
+        let current = thread::current().id();
+        let mut env = match self.inner.env.try_lock() {
+            Ok(env) => env,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                if *self.borrower() == Some(current) {
+                    return Err(SharedEnvBorrowError);
+                }
+                self.inner
+                    .env
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+            }
+        };
+        *self.borrower() = Some(current);
+        // Forget the borrower before releasing `env`, even on panic.
+        struct Release<'a, Env>(&'a SharedEnv<Env>);
+        impl<Env> Drop for Release<'_, Env> {
+            fn drop(&mut self) {
+                *self.0.borrower() = None;
+            }
+        }
+        let _release = Release(self);
+        Ok(f(&mut env))
+    }
+
+    fn borrower(&self) -> MutexGuard<Option<ThreadId>> {
+        self.inner
+            .borrower
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<Env> Clone for SharedEnv<Env> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Env: fmt::Debug> fmt::Debug for SharedEnv<Env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("SharedEnv");
+        match self.inner.env.try_lock() {
+            Ok(env) => debug.field("env", &*env),
+            Err(_) => debug.field("env", &"<borrowed>"),
+        };
+        debug.finish()
+    }
+}
+
+impl<Env: WasmerEnv> WasmerEnv for SharedEnv<Env> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.with(|env| env.init_with_instance(instance))
+    }
+}
+
+/// The error of [`SharedEnv::try_with`] when the environment is
+/// already borrowed by the current thread.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the environment is already borrowed by this thread")]
+pub struct SharedEnvBorrowError;
+
 /// Lazily init an item
 pub struct LazyInit<T: Sized> {
     /// The data to be initialized
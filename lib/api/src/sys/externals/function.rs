@@ -1,3 +1,4 @@
+use crate::sys::caller::{Caller, WithCaller};
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
 use crate::sys::store::Store;
@@ -5,19 +6,24 @@ use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::FunctionType;
 use crate::sys::NativeFunc;
 use crate::sys::RuntimeError;
-use crate::sys::WasmerEnv;
-pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithoutEnv};
+use crate::sys::{SharedEnv, WasmerEnv};
+pub use inner::{
+    FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithEnvMut, WithoutEnv,
+};
 
 use loupe::MemoryUsage;
+use std::any::Any;
 use std::cmp::max;
+use std::convert::TryFrom;
 use std::ffi::c_void;
 use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, ImportInitializerFuncPtr,
-    VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody,
-    VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
+    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, CallInterrupt,
+    ImportInitializerFuncPtr, InstanceRef, VMCallerCheckedAnyfunc, VMDynamicFunctionContext,
+    VMFuncRef, VMFunction, VMFunctionBody, VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
 };
 
 /// A WebAssembly `function` instance.
@@ -37,10 +43,20 @@ use wasmer_vm::{
 ///   with native functions. Attempting to create a native `Function` with one will
 ///   result in a panic.
 ///   [Closures as host functions tracking issue](https://github.com/wasmerio/wasmer/issues/1840)
-#[derive(PartialEq, MemoryUsage)]
+#[derive(MemoryUsage)]
 pub struct Function {
     pub(crate) store: Store,
     pub(crate) exported: ExportFunction,
+    /// The [`SharedEnv`] of a function created with
+    /// [`Function::new_native_with_env_mut`].
+    #[loupe(skip)]
+    pub(crate) env: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.store == other.store && self.exported == other.exported
+    }
 }
 
 impl wasmer_types::WasmValueType for Function {
@@ -231,6 +247,7 @@ impl Function {
         let vmctx = VMFunctionEnvironment { host_env };
 
         Self {
+            env: None,
             store: store.clone(),
             exported: ExportFunction {
                 metadata: Some(Arc::new(metadata)),
@@ -246,6 +263,67 @@ impl Function {
         }
     }
 
+    /// Creates a new host `Function` (dynamic) with the provided
+    /// signature and environment, which also receives the [`Caller`],
+    /// the instance calling it.
+    ///
+    /// The caller gives access to the exports and to the memories of
+    /// the instance that imported the function, and its exports can
+    /// be called back. When the function is imported by several
+    /// instances, each call sees the instance it comes from. Calling
+    /// the function from the host, rather than through an import,
+    /// fails since there is no calling instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{imports, Function, FunctionType, Instance, Module, Store, Type, Value, WasmerEnv};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///     base: usize,
+    /// }
+    ///
+    /// let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+    /// let env = Env { base: 8 };
+    /// let read = Function::new_with_caller(&store, &signature, env, |caller, env, args| {
+    ///     let memory = caller.memory(0).unwrap();
+    ///     let byte = memory.view::<u8>()[env.base + args[0].unwrap_i32() as usize].get();
+    ///     Ok(vec![Value::I32(byte as i32)])
+    /// });
+    ///
+    /// let module = Module::new(&store, r#"
+    ///     (module
+    ///         (import "env" "read" (func $read (param i32) (result i32)))
+    ///         (memory 1)
+    ///         (data (i32.const 8) "\2a")
+    ///         (func (export "run") (result i32)
+    ///             (call $read (i32.const 0))))
+    /// "#)?;
+    /// let instance = Instance::new(&module, &imports! { "env" => { "read" => read } })?;
+    /// let run = instance.exports.get_function("run")?;
+    /// assert_eq!(run.call(&[])?.to_vec(), vec![Value::I32(42)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_caller<FT, F, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Caller, &Env, &[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        let wrapped_func = move |env: &WithCaller<Env>,
+                                 args: &[Val]|
+              -> Result<Vec<Val>, RuntimeError> {
+            let caller = env.caller.get_ref().ok_or_else(|| {
+                RuntimeError::new("the function must be called through an import of an instance")
+            })?;
+            func(caller, &env.env, args)
+        };
+        Self::new_with_env(store, ty, WithCaller::new(env), wrapped_func)
+    }
+
     /// Creates a new host `Function` from a native function.
     ///
     /// The function signature is automatically retrieved using the
@@ -281,6 +359,7 @@ impl Function {
         let signature = function.ty();
 
         Self {
+            env: None,
             store: store.clone(),
             exported: ExportFunction {
                 // TODO: figure out what's going on in this function: it takes an `Env`
@@ -341,6 +420,79 @@ impl Function {
         let signature = function.ty();
 
         Self {
+            env: None,
+            store: store.clone(),
+            exported: ExportFunction {
+                metadata: Some(Arc::new(metadata)),
+                vm_function: VMFunction {
+                    address,
+                    kind: VMFunctionKind::Static,
+                    vmctx,
+                    signature,
+                    call_trampoline: None,
+                    instance_ref: None,
+                },
+            },
+        }
+    }
+
+    /// Creates a new host `Function` from a native function getting a
+    /// mutable reference to its environment.
+    ///
+    /// Wasmer owns `env`: the clones of the function, including the
+    /// ones of the instances importing it, all share it, and the host
+    /// reads it back through the [`SharedEnv`] handle returned by
+    /// [`Function::shared_env`], without wrapping it in a `Mutex`
+    /// itself. A function which calls back into WebAssembly, which
+    /// calls a function of the same environment, traps instead of
+    /// deadlocking.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Function, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///     total: i32,
+    /// };
+    ///
+    /// fn add(env: &mut Env, a: i32) -> i32 {
+    ///     env.total += a;
+    ///     env.total
+    /// }
+    ///
+    /// let f = Function::new_native_with_env_mut(&store, Env { total: 0 }, add);
+    /// f.native::<i32, i32>().unwrap().call(2).unwrap();
+    ///
+    /// let env = f.shared_env::<Env>().unwrap();
+    /// assert_eq!(env.with(|env| env.total), 2);
+    /// ```
+    pub fn new_native_with_env_mut<F, Args, Rets, Env>(store: &Store, env: Env, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithEnvMut, Env>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        if std::mem::size_of::<F>() != 0 {
+            Self::closures_unsupported_panic();
+        }
+        let function = inner::Function::<Args, Rets>::new(func);
+        let address = function.address();
+
+        let env = SharedEnv::new(env);
+        let (host_env, metadata) = build_export_function_metadata::<SharedEnv<Env>>(
+            env.clone(),
+            SharedEnv::<Env>::init_with_instance,
+        );
+
+        let vmctx = VMFunctionEnvironment { host_env };
+        let signature = function.ty();
+
+        Self {
+            env: Some(Arc::new(env)),
             store: store.clone(),
             exported: ExportFunction {
                 metadata: Some(Arc::new(metadata)),
@@ -428,6 +580,11 @@ impl Function {
             }
         }
 
+        let _guard = self
+            .store
+            .enter_instance(&self.exported.vm_function.instance_ref)?;
+        let _call_depth_guard = self.store.enter_call_depth();
+
         // Call the trampoline.
         if let Err(error) = unsafe {
             wasmer_call_trampoline(
@@ -544,8 +701,117 @@ impl Function {
         }
     }
 
+    /// Call the `Function` like [`Function::call`], interrupting it if
+    /// it hasn't returned after `timeout`.
+    ///
+    /// The call is interrupted like with
+    /// [`Function::call_interruptible`], and so only in code compiled
+    /// with epoch interruption enabled (see
+    /// [`CompilerConfig::epoch_interruption`]), at a function entry or
+    /// loop header. A host function running when the timeout expires
+    /// isn't interrupted: the call is interrupted once the host
+    /// function returns to WebAssembly.
+    ///
+    /// Each call has its own timeout: a call made with a timeout by a
+    /// host function called with another one is interrupted by the
+    /// timeout expiring first, and only reports a [`CallTimeout`] if
+    /// it's its own. The calls into the store made by other threads
+    /// meanwhile aren't interrupted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the call, or a [`CallTimeout`] error, which
+    /// can be checked with [`RuntimeError::is`], if the timeout expired.
+    ///
+    /// Fails without calling the function if it isn't defined by a
+    /// module compiled with epoch interruption, since the call could
+    /// never be interrupted.
+    ///
+    /// [`CompilerConfig::epoch_interruption`]: crate::CompilerConfig::epoch_interruption
+    /// [`CallTimeout`]: crate::CallTimeout
+    pub fn call_with_timeout(
+        &self,
+        params: &[Val],
+        timeout: Duration,
+    ) -> Result<Box<[Val]>, RuntimeError> {
+        if !self.is_interruptible() {
+            return Err(RuntimeError::new(
+                "the function can't be called with a timeout: it isn't defined by a module compiled with epoch interruption",
+            ));
+        }
+        self.store.call_with_timeout(timeout, || self.call(params))
+    }
+
+    /// Call the `Function` like [`Function::call`], interrupted by
+    /// `interrupt`, which another thread can use to stop the call with
+    /// [`CallInterrupt::interrupt`].
+    ///
+    /// The call is only interrupted in code compiled with epoch
+    /// interruption enabled (see [`CompilerConfig::epoch_interruption`]),
+    /// at a function entry or loop header, where it traps with
+    /// [`TrapCode::EpochDeadlineExceeded`]. Neither the epoch deadline
+    /// of the store nor the epoch of the engine are changed, and the
+    /// calls into the store made by other threads meanwhile aren't
+    /// interrupted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the call.
+    ///
+    /// Fails without calling the function if it isn't defined by a
+    /// module compiled with epoch interruption, since the call could
+    /// never be interrupted, or if `interrupt` wasn't created by
+    /// [`Store::new_call_interrupt`] for the store of the function.
+    ///
+    /// [`CompilerConfig::epoch_interruption`]: crate::CompilerConfig::epoch_interruption
+    /// [`CallInterrupt::interrupt`]: crate::CallInterrupt::interrupt
+    /// [`TrapCode::EpochDeadlineExceeded`]: crate::TrapCode::EpochDeadlineExceeded
+    pub fn call_interruptible(
+        &self,
+        params: &[Val],
+        interrupt: &Arc<CallInterrupt>,
+    ) -> Result<Box<[Val]>, RuntimeError> {
+        if !self.is_interruptible() {
+            return Err(RuntimeError::new(
+                "the function can't be interrupted: it isn't defined by a module compiled with epoch interruption",
+            ));
+        }
+        if !self.store.is_call_interrupt_of(interrupt) {
+            return Err(RuntimeError::new(
+                "the call interrupt belongs to another store",
+            ));
+        }
+        interrupt.run(|| self.call(params))
+    }
+
+    /// Whether the calls to the function can be interrupted, see
+    /// [`Function::call_interruptible`] and
+    /// [`Function::call_with_timeout`]: the function is defined by a
+    /// module compiled with epoch interruption.
+    pub fn is_interruptible(&self) -> bool {
+        self.exported
+            .vm_function
+            .instance_ref
+            .clone()
+            .and_then(|instance_ref| InstanceRef::try_from(instance_ref).ok())
+            .map_or(false, |instance_ref| {
+                instance_ref.module_ref().epoch_interruption
+            })
+    }
+
+    /// Returns the handle to the environment of a function created
+    /// with [`Function::new_native_with_env_mut`], or of one of its
+    /// clones, to read or update it from the host.
+    ///
+    /// Returns `None` if the function has no such environment, e.g.
+    /// if it's an export of an instance, or if it isn't an `Env`.
+    pub fn shared_env<Env: 'static>(&self) -> Option<SharedEnv<Env>> {
+        self.env.as_ref()?.downcast_ref::<SharedEnv<Env>>().cloned()
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportFunction) -> Self {
         Self {
+            env: None,
             store: store.clone(),
             exported: wasmer_export,
         }
@@ -698,7 +964,7 @@ impl<'a> Exportable<'a> for Function {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Function(func) => Ok(func),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("function", _extern)),
         }
     }
 
@@ -719,6 +985,7 @@ impl Clone for Function {
         Self {
             store: self.store.clone(),
             exported,
+            env: self.env.clone(),
         }
     }
 }
@@ -843,6 +1110,7 @@ impl<T: VMDynamicFunction> VMDynamicFunctionCall<T> for VMDynamicFunctionContext
 /// This private inner module contains the low-level implementation
 /// for `Function` and its siblings.
 mod inner {
+    use crate::sys::SharedEnv;
     use std::array::TryFromSliceError;
     use std::convert::{Infallible, TryInto};
     use std::error::Error;
@@ -1145,6 +1413,13 @@ mod inner {
 
     impl HostFunctionKind for WithEnv {}
 
+    /// An empty struct to help Rust typing to determine
+    /// when a `HostFunction` does have a mutable environment, shared
+    /// with a `SharedEnv`.
+    pub struct WithEnvMut;
+
+    impl HostFunctionKind for WithEnvMut {}
+
     /// An empty struct to help Rust typing to determine
     /// when a `HostFunction` does not have an environment.
     #[derive(Clone)]
@@ -1374,6 +1649,55 @@ mod inner {
                     func_wrapper::< $( $x, )* Rets, RetsAsResult, Env, Self > as *const VMFunctionBody
                 }
             }
+
+            // Implement `HostFunction` for a function that has the same arity than the tuple.
+            // This specific function has a mutable environment.
+            #[allow(unused_parens)]
+            impl< $( $x, )* Rets, RetsAsResult, Env, Func >
+                HostFunction<( $( $x ),* ), Rets, WithEnvMut, Env>
+            for
+                Func
+            where
+                $( $x: FromToNativeWasmType, )*
+                Rets: WasmTypeList,
+                RetsAsResult: IntoResult<Rets>,
+                Env: Sized,
+                Func: Fn(&mut Env, $( $x , )*) -> RetsAsResult + Send + 'static,
+            {
+                #[allow(non_snake_case)]
+                fn function_body_ptr(self) -> *const VMFunctionBody {
+                    /// This is a function that wraps the real host
+                    /// function. Its address will be used inside the
+                    /// runtime.
+                    extern fn func_wrapper<$( $x, )* Rets, RetsAsResult, Env, Func>( env: &SharedEnv<Env>, $( $x: $x::Native, )* ) -> Rets::CStruct
+                    where
+                        $( $x: FromToNativeWasmType, )*
+                        Rets: WasmTypeList,
+                        RetsAsResult: IntoResult<Rets>,
+                        Env: Sized,
+                        Func: Fn(&mut Env, $( $x ),* ) -> RetsAsResult + 'static
+                    {
+                        let func: &Func = unsafe { &*(&() as *const () as *const Func) };
+
+                        let result = on_host_stack(|| {
+                            panic::catch_unwind(AssertUnwindSafe(|| {
+                                env.try_with(|env| {
+                                    func(env, $( FromToNativeWasmType::from_native($x) ),* ).into_result()
+                                })
+                            }))
+                        });
+
+                        match result {
+                            Ok(Ok(Ok(result))) => return result.into_c_struct(),
+                            Ok(Ok(Err(trap))) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Ok(Err(borrowed)) => unsafe { raise_user_trap(Box::new(borrowed)) },
+                            Err(panic) => unsafe { resume_panic(panic) },
+                        }
+                    }
+
+                    func_wrapper::< $( $x, )* Rets, RetsAsResult, Env, Self > as *const VMFunctionBody
+                }
+            }
         };
     }
 
@@ -72,11 +72,13 @@ impl Global {
                 .set_unchecked(val.clone())
                 .map_err(|e| RuntimeError::new(format!("create global for {:?}: {}", val, e)))?;
         };
+        let global = Arc::new(global);
+        store.externref_roots().lock().unwrap().add_global(&global);
 
         Ok(Self {
             store: store.clone(),
             vm_global: VMGlobal {
-                from: Arc::new(global),
+                from: global,
                 instance_ref: None,
             },
         })
@@ -252,7 +254,7 @@ impl<'a> Exportable<'a> for Global {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Global(global) => Ok(global),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("global", _extern)),
         }
     }
 
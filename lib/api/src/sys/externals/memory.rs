@@ -1,15 +1,20 @@
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
 use crate::sys::store::Store;
-use crate::sys::{MemoryType, MemoryView};
+use crate::sys::{ExternType, MemoryType, MemoryView};
 use loupe::MemoryUsage;
 use std::convert::TryInto;
+use std::ptr;
 use std::slice;
 use std::sync::Arc;
 use wasmer_engine::Export;
 use wasmer_types::{Pages, ValueType};
 use wasmer_vm::{MemoryError, VMMemory};
 
+mod atomics;
+
+pub use self::atomics::{AtomicWaitResult, MemoryAccessError};
+
 /// A WebAssembly `memory` instance.
 ///
 /// A memory instance is the runtime representation of a linear memory.
@@ -23,6 +28,12 @@ use wasmer_vm::{MemoryError, VMMemory};
 /// A memory created by the host or in WebAssembly code will be accessible and
 /// mutable from both host and WebAssembly.
 ///
+/// The host can access a shared memory concurrently with WebAssembly
+/// with the atomic operations, e.g. [`Memory::atomic_add_u32`], and
+/// wait and notify like `memory.atomic.wait32` and
+/// `memory.atomic.notify` with [`Memory::atomic_wait32`] and
+/// [`Memory::atomic_notify`].
+///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#memory-instances>
 #[derive(Debug, MemoryUsage)]
 pub struct Memory {
@@ -60,6 +71,43 @@ impl Memory {
         })
     }
 
+    /// Creates a new host `Memory` satisfying the memory import of
+    /// type `ty`, e.g. one returned by [`Module::imported_memories`].
+    ///
+    /// The memory has the minimum, the maximum and the sharing of the
+    /// import. It is checked to satisfy the import once created, since
+    /// the tunables of the store may change its type.
+    ///
+    /// [`Module::imported_memories`]: crate::Module::imported_memories
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, r#"(module (import "env" "memory" (memory 1 2)))"#)?;
+    /// for import in module.imported_memories() {
+    ///     let memory = Memory::new_for_import(&store, import.ty())?;
+    ///     assert_eq!(memory.ty(), *import.ty());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_for_import(store: &Store, ty: &MemoryType) -> Result<Self, MemoryError> {
+        let memory = Self::new(store, *ty)?;
+        let created = memory.ty();
+        if !ExternType::Memory(created).is_compatible_with(&ExternType::Memory(*ty)) {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the memory created, {}, doesn't satisfy the import {}",
+                    created, ty
+                ),
+            });
+        }
+        Ok(memory)
+    }
+
     /// Returns the [`MemoryType`] of the `Memory`.
     ///
     /// # Example
@@ -134,6 +182,18 @@ impl Memory {
         def.current_length.try_into().unwrap()
     }
 
+    /// Returns the size (in bytes) of the `Memory`, read atomically.
+    ///
+    /// With the threads proposal, a shared memory can grow while the
+    /// host reads it. The size is read with the ordering the memory
+    /// is grown with, so that it's never torn, and the memory is at
+    /// least that large when [`Memory::data_ptr`] is read afterwards.
+    pub fn data_size_atomic(&self) -> u64 {
+        let definition = self.vm_memory.from.vmmemory();
+        let def = unsafe { definition.as_ref() };
+        def.load_current_length().try_into().unwrap()
+    }
+
     /// Returns the size (in [`Pages`]) of the `Memory`.
     ///
     /// # Example
@@ -187,6 +247,187 @@ impl Memory {
         self.vm_memory.from.grow(delta.into())
     }
 
+    /// Register a callback to be called with the previous and the new
+    /// size of the memory every time it grows, be it from the host
+    /// with [`Memory::grow`] or from WebAssembly with `memory.grow`.
+    ///
+    /// The callback is called synchronously, before the growing
+    /// function returns. It isn't called when growing fails, or when
+    /// growing by zero pages. It must not register other callbacks on
+    /// the same memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Pages, Store};
+    /// # use std::sync::{Arc, Mutex};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, Some(3), false)).unwrap();
+    /// let grown = Arc::new(Mutex::new(vec![]));
+    /// let grown_clone = grown.clone();
+    /// m.on_grow(move |old, new| grown_clone.lock().unwrap().push((old, new)))
+    ///     .unwrap();
+    ///
+    /// m.grow(2).unwrap();
+    /// assert!(m.grow(1).is_err());
+    ///
+    /// assert_eq!(*grown.lock().unwrap(), vec![(Pages(1), Pages(3))]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the memory, e.g. a custom one created by
+    /// [`Tunables`](crate::Tunables), doesn't support callbacks.
+    pub fn on_grow<F>(&self, callback: F) -> Result<(), MemoryError>
+    where
+        F: Fn(Pages, Pages) + Send + Sync + 'static,
+    {
+        self.vm_memory.from.on_grow(Box::new(callback))
+    }
+
+    /// Copies the `len` bytes of the source memory `src` starting at
+    /// `src_offset` to the destination memory `dst` at `dst_offset`,
+    /// like `memory.copy`.
+    ///
+    /// The memories may be the same, and the ranges may overlap, in
+    /// which case the bytes are copied as if through an intermediate
+    /// buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without copying anything, if the range is out
+    /// of bounds of either the source or destination memories.
+    pub fn copy(
+        dst: &Self,
+        dst_offset: u32,
+        src: &Self,
+        src_offset: u32,
+        len: u32,
+    ) -> Result<(), MemoryError> {
+        src.check_range(src_offset, len)?;
+        dst.check_range(dst_offset, len)?;
+        unsafe {
+            ptr::copy(
+                src.data_ptr().add(src_offset as usize),
+                dst.data_ptr().add(dst_offset as usize),
+                len as usize,
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the `len` bytes starting at `offset` to `val`, like
+    /// `memory.fill`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without setting anything, if the range is out
+    /// of bounds of the memory.
+    pub fn fill(&self, offset: u32, val: u8, len: u32) -> Result<(), MemoryError> {
+        self.check_range(offset, len)?;
+        unsafe { ptr::write_bytes(self.data_ptr().add(offset as usize), val, len as usize) };
+        Ok(())
+    }
+
+    /// Copies the `len` bytes of `segment_data` starting at
+    /// `src_offset` to the memory at `dst_offset`, like `memory.init`
+    /// with `segment_data` as the data segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without copying anything, if the range is out
+    /// of bounds of `segment_data` or of the memory.
+    pub fn init_from_data_segment(
+        &self,
+        dst_offset: u32,
+        segment_data: &[u8],
+        src_offset: u32,
+        len: u32,
+    ) -> Result<(), MemoryError> {
+        let size = segment_data.len() as u64;
+        if u64::from(src_offset) + u64::from(len) > size {
+            return Err(MemoryError::AccessOutOfBounds {
+                offset: src_offset,
+                len,
+                size,
+            });
+        }
+        self.check_range(dst_offset, len)?;
+        // The data may be a view of this very memory.
+        unsafe {
+            ptr::copy(
+                segment_data.as_ptr().add(src_offset as usize),
+                self.data_ptr().add(dst_offset as usize),
+                len as usize,
+            );
+        }
+        Ok(())
+    }
+
+    /// Retrieve the `count` values of type `T` starting at `offset`
+    /// as a slice of the memory contents, without copying them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the memory is shared, if the values are out
+    /// of bounds of the memory, or if `offset` isn't aligned for `T`.
+    ///
+    /// # Safety
+    ///
+    /// Until the returned slice is dropped, it is undefined behaviour to
+    /// modify the memory contents in any way including by calling a wasm
+    /// function that writes to the memory or by resizing the memory, like
+    /// with [`Memory::data_unchecked`].
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+    /// let values: &[f64] = unsafe { memory.view_as(8, 4)? };
+    /// assert_eq!(values.iter().sum::<f64>(), 0.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn view_as<T: ValueType>(
+        &self,
+        offset: u32,
+        count: u32,
+    ) -> Result<&[T], MemoryError> {
+        if self.ty().shared {
+            return Err(MemoryError::InvalidMemory {
+                reason: "a shared memory can't be viewed as a slice".to_string(),
+            });
+        }
+        let len = u64::from(count) * std::mem::size_of::<T>() as u64;
+        let size = self.data_size();
+        if u64::from(offset) + len > size {
+            return Err(MemoryError::AccessOutOfBounds {
+                offset,
+                len: len.try_into().unwrap_or(u32::MAX),
+                size,
+            });
+        }
+        let data = self.data_ptr().add(offset as usize);
+        let align = std::mem::align_of::<T>();
+        if data as usize % align != 0 {
+            return Err(MemoryError::MisalignedAccess { offset, align });
+        }
+        Ok(slice::from_raw_parts(data as *const T, count as usize))
+    }
+
+    /// Check that the `len` bytes starting at `offset` are in bounds.
+    fn check_range(&self, offset: u32, len: u32) -> Result<(), MemoryError> {
+        let size = self.data_size();
+        if u64::from(offset) + u64::from(len) > size {
+            return Err(MemoryError::AccessOutOfBounds { offset, len, size });
+        }
+        Ok(())
+    }
+
     /// Return a "view" of the currently accessible memory. By
     /// default, the view is unsynchronized, using regular memory
     /// accesses. You can force a memory view to use atomic accesses
@@ -223,7 +464,7 @@ impl Memory {
 
         let length = self.size().bytes().0 / std::mem::size_of::<T>();
 
-        unsafe { MemoryView::new(base as _, length as u32) }
+        unsafe { MemoryView::new64(base as _, length as u64) }
     }
 
     /// A shortcut to [`Self::view::<u8>`][self::view].
@@ -290,7 +531,7 @@ impl<'a> Exportable<'a> for Memory {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Memory(memory) => Ok(memory),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("memory", _extern)),
         }
     }
 
@@ -0,0 +1,258 @@
+//! Atomic accesses to a [`Memory`] from the host, matching the
+//! instructions of the WebAssembly threads proposal.
+
+use super::Memory;
+use std::mem;
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+use wasmer_vm::WaitResult;
+
+/// An error produced by the atomic operations of a [`Memory`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MemoryAccessError {
+    /// The accessed bytes are not all in the memory.
+    #[error("out of bounds atomic access of {size} bytes at offset {offset}")]
+    OutOfBounds {
+        /// The offset of the access.
+        offset: u32,
+        /// The size of the access, in bytes.
+        size: u32,
+    },
+    /// The offset is not a multiple of the size of the access.
+    #[error("unaligned atomic access of {size} bytes at offset {offset}")]
+    Unaligned {
+        /// The offset of the access.
+        offset: u32,
+        /// The size of the access, in bytes.
+        size: u32,
+    },
+    /// Waiting is only possible on a shared memory.
+    #[error("cannot wait on a memory that isn't shared")]
+    Unshared,
+}
+
+/// The outcome of [`Memory::atomic_wait32`] and
+/// [`Memory::atomic_wait64`], i.e. the value returned by
+/// `memory.atomic.wait32` and `memory.atomic.wait64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AtomicWaitResult {
+    /// The waiter was woken by [`Memory::atomic_notify`] (`0`).
+    Woken = 0,
+    /// The loaded value didn't match the expected one, so the waiter
+    /// didn't wait (`1`).
+    NotEqual = 1,
+    /// The timeout expired before the waiter was woken (`2`).
+    TimedOut = 2,
+}
+
+macro_rules! atomic_operations {
+    ($($ty:ty, $atomic:ty {
+        $load:ident,
+        $store:ident,
+        $add:ident,
+        $sub:ident,
+        $and:ident,
+        $or:ident,
+        $xor:ident,
+        $xchg:ident,
+        $cmpxchg:ident,
+    })*) => {
+        impl Memory {
+            $(
+                #[doc = concat!("Atomically load the `", stringify!($ty), "` at `offset`.")]
+                pub fn $load(&self, offset: u32) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.load(Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically store the `", stringify!($ty), "` `value` at `offset`.")]
+                pub fn $store(&self, offset: u32, value: $ty) -> Result<(), MemoryAccessError> {
+                    self.atomic::<$atomic>(offset)?.store(value, Ordering::SeqCst);
+                    Ok(())
+                }
+
+                #[doc = concat!("Atomically add `value` to the `", stringify!($ty), "` at `offset`, wrapping around on overflow, and return the previous value.")]
+                pub fn $add(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.fetch_add(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically subtract `value` from the `", stringify!($ty), "` at `offset`, wrapping around on overflow, and return the previous value.")]
+                pub fn $sub(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.fetch_sub(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically apply a bitwise and with `value` to the `", stringify!($ty), "` at `offset`, and return the previous value.")]
+                pub fn $and(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.fetch_and(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically apply a bitwise or with `value` to the `", stringify!($ty), "` at `offset`, and return the previous value.")]
+                pub fn $or(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.fetch_or(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically apply a bitwise xor with `value` to the `", stringify!($ty), "` at `offset`, and return the previous value.")]
+                pub fn $xor(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.fetch_xor(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically replace the `", stringify!($ty), "` at `offset` with `value`, and return the previous value.")]
+                pub fn $xchg(&self, offset: u32, value: $ty) -> Result<$ty, MemoryAccessError> {
+                    Ok(self.atomic::<$atomic>(offset)?.swap(value, Ordering::SeqCst))
+                }
+
+                #[doc = concat!("Atomically replace the `", stringify!($ty), "` at `offset` with `replacement` if it is equal to `expected`, and return the previous value.")]
+                ///
+                /// The exchange happened if the returned value is equal to
+                /// `expected`.
+                pub fn $cmpxchg(
+                    &self,
+                    offset: u32,
+                    expected: $ty,
+                    replacement: $ty,
+                ) -> Result<$ty, MemoryAccessError> {
+                    let atomic = self.atomic::<$atomic>(offset)?;
+                    Ok(match atomic.compare_exchange(
+                        expected,
+                        replacement,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    ) {
+                        Ok(previous) | Err(previous) => previous,
+                    })
+                }
+            )*
+        }
+    };
+}
+
+atomic_operations! {
+    u8, AtomicU8 {
+        atomic_load_u8,
+        atomic_store_u8,
+        atomic_add_u8,
+        atomic_sub_u8,
+        atomic_and_u8,
+        atomic_or_u8,
+        atomic_xor_u8,
+        atomic_xchg_u8,
+        atomic_compare_exchange_u8,
+    }
+    u16, AtomicU16 {
+        atomic_load_u16,
+        atomic_store_u16,
+        atomic_add_u16,
+        atomic_sub_u16,
+        atomic_and_u16,
+        atomic_or_u16,
+        atomic_xor_u16,
+        atomic_xchg_u16,
+        atomic_compare_exchange_u16,
+    }
+    u32, AtomicU32 {
+        atomic_load_u32,
+        atomic_store_u32,
+        atomic_add_u32,
+        atomic_sub_u32,
+        atomic_and_u32,
+        atomic_or_u32,
+        atomic_xor_u32,
+        atomic_xchg_u32,
+        atomic_compare_exchange_u32,
+    }
+    u64, AtomicU64 {
+        atomic_load_u64,
+        atomic_store_u64,
+        atomic_add_u64,
+        atomic_sub_u64,
+        atomic_and_u64,
+        atomic_or_u64,
+        atomic_xor_u64,
+        atomic_xchg_u64,
+        atomic_compare_exchange_u64,
+    }
+}
+
+impl Memory {
+    /// Wait until another thread calls [`Memory::atomic_notify`] on
+    /// `offset`, like `memory.atomic.wait32`, if the `u32` at `offset`
+    /// is equal to `expected`.
+    ///
+    /// Without a `timeout`, waits forever. The memory must be shared.
+    pub fn atomic_wait32(
+        &self,
+        offset: u32,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> Result<AtomicWaitResult, MemoryAccessError> {
+        let atomic = self.atomic::<AtomicU32>(offset)?;
+        self.wait(
+            offset,
+            || atomic.load(Ordering::SeqCst) == expected,
+            timeout,
+        )
+    }
+
+    /// Wait until another thread calls [`Memory::atomic_notify`] on
+    /// `offset`, like `memory.atomic.wait64`, if the `u64` at `offset`
+    /// is equal to `expected`.
+    ///
+    /// Without a `timeout`, waits forever. The memory must be shared.
+    pub fn atomic_wait64(
+        &self,
+        offset: u32,
+        expected: u64,
+        timeout: Option<Duration>,
+    ) -> Result<AtomicWaitResult, MemoryAccessError> {
+        let atomic = self.atomic::<AtomicU64>(offset)?;
+        self.wait(
+            offset,
+            || atomic.load(Ordering::SeqCst) == expected,
+            timeout,
+        )
+    }
+
+    /// Wake up to `count` of the threads waiting on `offset`, in the
+    /// order they started waiting, like `memory.atomic.notify`, and
+    /// return the number of threads woken up.
+    ///
+    /// Nobody can wait on a memory that isn't shared, so 0 is
+    /// returned for such memories.
+    pub fn atomic_notify(&self, offset: u32, count: u32) -> Result<u32, MemoryAccessError> {
+        self.atomic::<AtomicU32>(offset)?;
+        let addr = self.data_ptr() as usize + offset as usize;
+        Ok(wasmer_vm::atomic_notify(addr, count))
+    }
+
+    fn wait(
+        &self,
+        offset: u32,
+        is_expected: impl FnOnce() -> bool,
+        timeout: Option<Duration>,
+    ) -> Result<AtomicWaitResult, MemoryAccessError> {
+        if !self.ty().shared {
+            return Err(MemoryAccessError::Unshared);
+        }
+        let addr = self.data_ptr() as usize + offset as usize;
+        Ok(match wasmer_vm::atomic_wait(addr, is_expected, timeout) {
+            WaitResult::Woken => AtomicWaitResult::Woken,
+            WaitResult::NotEqual => AtomicWaitResult::NotEqual,
+            WaitResult::TimedOut => AtomicWaitResult::TimedOut,
+        })
+    }
+
+    /// The atomic of type `T` at `offset`, checking that it is in
+    /// bounds and aligned.
+    fn atomic<T>(&self, offset: u32) -> Result<&T, MemoryAccessError> {
+        let size = mem::size_of::<T>() as u32;
+        if offset % size != 0 {
+            return Err(MemoryAccessError::Unaligned { offset, size });
+        }
+        if u64::from(offset) + u64::from(size) > self.data_size() {
+            return Err(MemoryAccessError::OutOfBounds { offset, size });
+        }
+        // The memory is aligned on a page boundary, so `offset` being
+        // aligned is enough for the atomic to be aligned.
+        Ok(unsafe { &*(self.data_ptr().add(offset as usize) as *const T) })
+    }
+}
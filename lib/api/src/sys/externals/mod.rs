@@ -4,12 +4,12 @@ mod memory;
 mod table;
 
 pub use self::function::{
-    FromToNativeWasmType, Function, HostFunction, WasmTypeList, WithEnv, WithoutEnv,
+    FromToNativeWasmType, Function, HostFunction, WasmTypeList, WithEnv, WithEnvMut, WithoutEnv,
 };
 
 pub use self::global::Global;
-pub use self::memory::Memory;
-pub use self::table::Table;
+pub use self::memory::{AtomicWaitResult, Memory, MemoryAccessError};
+pub use self::table::{Table, TableError};
 
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::store::{Store, StoreObject};
@@ -1,14 +1,44 @@
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
-use crate::sys::store::Store;
+use crate::sys::store::{Store, StoreObject};
 use crate::sys::types::{Val, ValFuncRef};
 use crate::sys::RuntimeError;
-use crate::sys::TableType;
+use crate::sys::{ExternType, TableType};
 use loupe::MemoryUsage;
+use std::fmt;
 use std::sync::Arc;
+use thiserror::Error;
 use wasmer_engine::Export;
+use wasmer_types::Type;
 use wasmer_vm::{Table as RuntimeTable, TableElement, VMTable};
 
+/// An error produced by the bulk operations of a [`Table`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TableError {
+    /// The range of elements is out of bounds of a table, or of the
+    /// elements given to [`Table::init`].
+    #[error("out of bounds access of {len} elements at index {index}, out of {size} elements")]
+    OutOfBounds {
+        /// The start of the range.
+        index: u32,
+        /// The number of elements of the range.
+        len: u32,
+        /// The number of elements of the accessed table or slice.
+        size: u32,
+    },
+    /// A value or a table doesn't have the element type of the table.
+    #[error("incompatible element type: expected {expected}, found {found}")]
+    IncompatibleType {
+        /// The element type of the table.
+        expected: Type,
+        /// The type of the value or of the elements of the other table.
+        found: Type,
+    },
+    /// The tables or the values come from different stores.
+    #[error("cross-`Store` table operations are not supported")]
+    CrossStore,
+}
+
 /// A WebAssembly `table` instance.
 ///
 /// The `Table` struct is an array-like structure representing a WebAssembly Table,
@@ -51,6 +81,7 @@ impl Table {
         for i in 0..num_elements {
             set_table_item(table.as_ref(), i, item.clone())?;
         }
+        store.externref_roots().lock().unwrap().add_table(&table);
 
         Ok(Self {
             store: store.clone(),
@@ -61,6 +92,31 @@ impl Table {
         })
     }
 
+    /// Creates a new `Table` satisfying the table import of type `ty`,
+    /// e.g. one returned by [`Module::imported_tables`], with all its
+    /// elements set to null.
+    ///
+    /// The table has the element type, the minimum and the maximum of
+    /// the import. It is checked to satisfy the import once created,
+    /// since the tunables of the store may change its type.
+    ///
+    /// [`Module::imported_tables`]: crate::Module::imported_tables
+    pub fn new_for_import(store: &Store, ty: &TableType) -> Result<Self, RuntimeError> {
+        let init = match ty.ty {
+            Type::FuncRef => Val::FuncRef(None),
+            _ => Val::null(),
+        };
+        let table = Self::new(store, *ty, init)?;
+        let created = table.ty();
+        if !ExternType::Table(*created).is_compatible_with(&ExternType::Table(*ty)) {
+            return Err(RuntimeError::new(format!(
+                "the table created, {}, doesn't satisfy the import {}",
+                created, ty
+            )));
+        }
+        Ok(table)
+    }
+
     /// Returns the [`TableType`] of the `Table`.
     pub fn ty(&self) -> &TableType {
         self.vm_table.from.ty()
@@ -106,24 +162,34 @@ impl Table {
     }
 
     /// Copies the `len` elements of `src_table` starting at `src_index`
-    /// to the destination table `dst_table` at index `dst_index`.
+    /// to the destination table `dst_table` at index `dst_index`, like
+    /// `table.copy`.
+    ///
+    /// The tables may be the same, and the ranges may overlap.
     ///
     /// # Errors
     ///
-    /// Returns an error if the range is out of bounds of either the source or
-    /// destination tables.
+    /// Returns an error, without copying anything, if the range is out
+    /// of bounds of either the source or destination tables, or if the
+    /// tables have different element types.
     pub fn copy(
         dst_table: &Self,
         dst_index: u32,
         src_table: &Self,
         src_index: u32,
         len: u32,
-    ) -> Result<(), RuntimeError> {
+    ) -> Result<(), TableError> {
         if !Store::same(&dst_table.store, &src_table.store) {
-            return Err(RuntimeError::new(
-                "cross-`Store` table copies are not supported",
-            ));
+            return Err(TableError::CrossStore);
+        }
+        if dst_table.ty().ty != src_table.ty().ty {
+            return Err(TableError::IncompatibleType {
+                expected: dst_table.ty().ty,
+                found: src_table.ty().ty,
+            });
         }
+        src_table.check_range(src_index, len)?;
+        dst_table.check_range(dst_index, len)?;
         RuntimeTable::copy(
             dst_table.vm_table.from.as_ref(),
             src_table.vm_table.from.as_ref(),
@@ -131,10 +197,89 @@ impl Table {
             src_index,
             len,
         )
-        .map_err(RuntimeError::from_trap)?;
+        .expect("the ranges are in bounds");
         Ok(())
     }
 
+    /// Sets the `len` elements starting at `start` to `val`, like
+    /// `table.fill`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without setting anything, if the range is out
+    /// of bounds of the table, or if `val` doesn't have the element type
+    /// of the table.
+    pub fn fill(&self, start: u32, val: Val, len: u32) -> Result<(), TableError> {
+        let item = self.table_element(&val)?;
+        self.check_range(start, len)?;
+        for index in start..start + len {
+            set_table_item(self.vm_table.from.as_ref(), index, item.clone())
+                .expect("the range is in bounds");
+        }
+        Ok(())
+    }
+
+    /// Copies the `len` values of `elements` starting at `src_offset`
+    /// to the table at index `dst_offset`, like `table.init` with
+    /// `elements` as the element segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without setting anything, if the range is out
+    /// of bounds of `elements` or of the table, or if a value doesn't
+    /// have the element type of the table.
+    pub fn init(
+        &self,
+        dst_offset: u32,
+        elements: &[Val],
+        src_offset: u32,
+        len: u32,
+    ) -> Result<(), TableError> {
+        let size = elements.len() as u32;
+        if src_offset.checked_add(len).map_or(true, |end| end > size) {
+            return Err(TableError::OutOfBounds {
+                index: src_offset,
+                len,
+                size,
+            });
+        }
+        self.check_range(dst_offset, len)?;
+        let items = elements[src_offset as usize..(src_offset + len) as usize]
+            .iter()
+            .map(|val| self.table_element(val))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (index, item) in (dst_offset..).zip(items) {
+            set_table_item(self.vm_table.from.as_ref(), index, item)
+                .expect("the range is in bounds");
+        }
+        Ok(())
+    }
+
+    /// Check that the `len` elements starting at `index` are in bounds.
+    fn check_range(&self, index: u32, len: u32) -> Result<(), TableError> {
+        let size = self.size();
+        if index.checked_add(len).map_or(true, |end| end > size) {
+            return Err(TableError::OutOfBounds { index, len, size });
+        }
+        Ok(())
+    }
+
+    /// Convert `val` to an element of this table.
+    fn table_element(&self, val: &Val) -> Result<TableElement, TableError> {
+        if !val.comes_from_same_store(&self.store) {
+            return Err(TableError::CrossStore);
+        }
+        if val.ty() != self.ty().ty {
+            return Err(TableError::IncompatibleType {
+                expected: self.ty().ty,
+                found: val.ty(),
+            });
+        }
+        Ok(val
+            .into_table_reference(&self.store)
+            .expect("the value is a reference from the same store"))
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_table: VMTable) -> Self {
         Self {
             store: store.clone(),
@@ -172,6 +317,16 @@ impl Clone for Table {
     }
 }
 
+impl fmt::Debug for Table {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Table")
+            .field("ty", &self.ty())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
 impl<'a> Exportable<'a> for Table {
     fn to_export(&self) -> Export {
         self.vm_table.clone().into()
@@ -180,7 +335,7 @@ impl<'a> Exportable<'a> for Table {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
             Extern::Table(table) => Ok(table),
-            _ => Err(ExportError::IncompatibleType),
+            _ => Err(ExportError::incompatible("table", _extern)),
         }
     }
 
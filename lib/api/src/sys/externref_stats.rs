@@ -0,0 +1,240 @@
+//! Diagnostics of the [`ExternRef`]s kept alive by the tables and
+//! globals of a [`Store`], see [`Store::externref_stats`] and
+//! [`Store::find_externref_holders`].
+
+use crate::sys::externals::{Global, Table};
+use crate::sys::store::Store;
+#[cfg(feature = "debug-externref")]
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use wasmer_types::{ExternRef, VMExternRef};
+use wasmer_vm::{
+    ExternRefRegistry, Global as RuntimeGlobal, Table as RuntimeTable, TableAddress, VMGlobal,
+    VMTable,
+};
+
+/// The [`ExternRef`]s held by the tables and globals of a [`Store`],
+/// as returned by [`Store::externref_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternRefStats {
+    /// The number of distinct non-null references held.
+    pub live: usize,
+    /// The sum of the strong counts of these references, including
+    /// the handles kept by the host.
+    pub strong_references: usize,
+    /// The number of distinct references held, by type of their
+    /// data.
+    #[cfg(feature = "debug-externref")]
+    pub by_type: HashMap<TypeId, usize>,
+}
+
+/// Something keeping an [`ExternRef`] alive, as returned by
+/// [`Store::find_externref_holders`].
+#[derive(Debug, Clone)]
+pub enum HolderInfo {
+    /// An element of a table.
+    Table {
+        /// The table, to be compared with [`Table::same`].
+        table: Table,
+        /// The index of the element.
+        index: u32,
+    },
+    /// A global, to be compared with [`Global::same`].
+    Global(Global),
+    /// The strong references which aren't held by the tables and
+    /// globals of the store: host handles, values on the way in or
+    /// out of a call, or the tables and globals of other stores.
+    Host {
+        /// The number of such references.
+        references: usize,
+    },
+}
+
+/// The tables and globals created in a store, tracked weakly so
+/// that tracking them doesn't keep them alive.
+///
+/// The tables record the references held by their elements in the
+/// registry of the store as they are written, see
+/// [`RuntimeTable::set_externref_registry`]. The globals are read
+/// instead, since compiled code writes them directly.
+#[derive(Default)]
+pub(crate) struct ExternRefRoots {
+    registry: Arc<ExternRefRegistry>,
+    tables: Vec<Weak<dyn RuntimeTable>>,
+    globals: Vec<Weak<RuntimeGlobal>>,
+}
+
+impl ExternRefRoots {
+    /// Tracks `table`, unless it's already tracked, recording its
+    /// references in the registry of the store from now on.
+    ///
+    /// A table shared by several stores records its references in
+    /// the registry of the last one tracking it.
+    pub(crate) fn add_table(&mut self, table: &Arc<dyn RuntimeTable>) {
+        self.tables.retain(|weak| weak.strong_count() > 0);
+        table.set_externref_registry(&self.registry);
+        let weak = Arc::downgrade(table);
+        if !self.tables.iter().any(|other| other.ptr_eq(&weak)) {
+            self.tables.push(weak);
+        }
+    }
+
+    /// Tracks `global`, unless it's already tracked.
+    pub(crate) fn add_global(&mut self, global: &Arc<RuntimeGlobal>) {
+        self.globals.retain(|weak| weak.strong_count() > 0);
+        let weak = Arc::downgrade(global);
+        if !self.globals.iter().any(|other| other.ptr_eq(&weak)) {
+            self.globals.push(weak);
+        }
+    }
+
+    /// Calls `f` with every non-null reference held by the tables and
+    /// the tracked globals still alive, with its strong count and its
+    /// holder.
+    fn for_each(roots: &Mutex<Self>, mut f: impl FnMut(VMExternRef, usize, Holder)) {
+        // The roots are unlocked before the registry is locked, since
+        // `add_table` locks them the other way around.
+        let (registry, tables, globals) = {
+            let roots = roots.lock().unwrap();
+            let tables = roots
+                .tables
+                .iter()
+                .filter_map(Weak::upgrade)
+                .map(|table| (Arc::as_ptr(&table) as *const () as TableAddress, table))
+                .collect::<HashMap<_, _>>();
+            let globals = roots
+                .globals
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>();
+            (roots.registry.clone(), tables, globals)
+        };
+
+        // The references of the registry can't be released while it's
+        // locked, their strong count is read meanwhile.
+        registry.for_each(|extern_ref, elements| {
+            let strong_count = extern_ref.strong_count();
+            let mut elements = elements
+                .iter()
+                .filter_map(|(address, index)| Some((tables.get(address)?, *index)))
+                .collect::<Vec<_>>();
+            elements.sort_by_key(|(_, index)| *index);
+            for (table, index) in elements {
+                f(
+                    extern_ref,
+                    strong_count,
+                    Holder::Table {
+                        table: table.clone(),
+                        index,
+                    },
+                );
+            }
+        });
+
+        for global in globals {
+            if global.ty().ty == wasmer_types::Type::ExternRef {
+                // Reading the definition doesn't take a reference, the
+                // global keeps holding it.
+                let extern_ref = unsafe { global.vmglobal().as_ref().to_externref() };
+                if !extern_ref.is_null() {
+                    f(
+                        extern_ref,
+                        extern_ref.strong_count(),
+                        Holder::Global(global.clone()),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The holder of a reference found by [`ExternRefRoots::for_each`].
+enum Holder {
+    Table {
+        table: Arc<dyn RuntimeTable>,
+        index: u32,
+    },
+    Global(Arc<RuntimeGlobal>),
+}
+
+impl Store {
+    /// Returns statistics about the [`ExternRef`]s held by the tables
+    /// and globals of this store.
+    ///
+    /// The tables and globals are the ones created with [`Table::new`]
+    /// and [`Global::new`], and the ones of the instances of this
+    /// store, for as long as they are alive. A reference which is
+    /// still counted once nothing should hold it anymore is leaked
+    /// somewhere, which [`Store::find_externref_holders`] can help to
+    /// locate.
+    ///
+    /// With the `debug-externref` feature, the references are also
+    /// counted by type of their data.
+    pub fn externref_stats(&self) -> ExternRefStats {
+        let mut strong_counts = HashMap::new();
+        #[cfg(feature = "debug-externref")]
+        let mut by_type = HashMap::new();
+        ExternRefRoots::for_each(self.externref_roots(), |extern_ref, strong_count, _| {
+            if strong_counts.insert(extern_ref, strong_count).is_none() {
+                #[cfg(feature = "debug-externref")]
+                if let Some(type_id) = extern_ref.data_type_id() {
+                    *by_type.entry(type_id).or_insert(0) += 1;
+                }
+            }
+        });
+
+        ExternRefStats {
+            live: strong_counts.len(),
+            strong_references: strong_counts.values().sum(),
+            #[cfg(feature = "debug-externref")]
+            by_type,
+        }
+    }
+
+    /// Returns what keeps `extern_ref` alive: the elements of the
+    /// tables and the globals of this store holding it (see
+    /// [`Store::externref_stats`]), followed by the number of the
+    /// other strong references, including `extern_ref` itself.
+    ///
+    /// A null reference has no holders.
+    pub fn find_externref_holders(&self, extern_ref: &ExternRef) -> Vec<HolderInfo> {
+        if extern_ref.is_null() {
+            return Vec::new();
+        }
+        let target = VMExternRef::from(extern_ref.clone());
+        // Leave out the clone taken above.
+        let strong_count = target.strong_count() - 1;
+        let mut holders = Vec::new();
+        ExternRefRoots::for_each(self.externref_roots(), |extern_ref, _, holder| {
+            if extern_ref != target {
+                return;
+            }
+            holders.push(match holder {
+                Holder::Table { table, index } => HolderInfo::Table {
+                    table: Table::from_vm_export(
+                        self,
+                        VMTable {
+                            from: table,
+                            instance_ref: None,
+                        },
+                    ),
+                    index,
+                },
+                Holder::Global(global) => HolderInfo::Global(Global::from_vm_export(
+                    self,
+                    VMGlobal {
+                        from: global,
+                        instance_ref: None,
+                    },
+                )),
+            });
+        });
+
+        drop(ExternRef::from(target));
+
+        let references = strong_count.saturating_sub(holders.len());
+        holders.push(HolderInfo::Host { references });
+        holders
+    }
+}
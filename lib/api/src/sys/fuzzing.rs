@@ -0,0 +1,455 @@
+//! Entry points to fuzz the whole compile-instantiate-execute
+//! pipeline, see [`run_arbitrary`] and [`run_differential`].
+//!
+//! The fuzz targets of the `fuzz/` directory are built on them, and
+//! they are meant to be usable from any other `cargo fuzz` harness:
+//! any bytes can be fed to them, and the work they do is bounded by
+//! the budgets of [`FuzzConfig`]. A panic is always a bug of Wasmer.
+
+use crate::sys::exports::Exports;
+use crate::sys::externals::{Extern, Function, Global, Memory, Table};
+use crate::sys::import_object::ImportObject;
+use crate::sys::instance::{Instance, InstantiationError};
+use crate::sys::module::Module;
+use crate::sys::store::Store;
+use crate::sys::tunables::{BaseTunables, LimitingTunables};
+use crate::sys::types::{ExternType, Val, ValType};
+use crate::sys::{
+    CompilerConfig, Features, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware, Target,
+};
+use loupe::MemoryUsage;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use wasmer_compiler::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer_engine_universal::Universal;
+use wasmer_types::{
+    ExportIndex, ExternRef, GlobalIndex, GlobalInit, GlobalType, LocalFunctionIndex, ModuleInfo,
+    Mutability, Pages,
+};
+use wasmer_vm::TrapCode;
+
+/// The maximum number of elements of each table of a module run by
+/// [`run_arbitrary`], so that a table can't exhaust the memory of the
+/// host.
+///
+/// It applies to the tables the module defines and to the stubs of
+/// the tables it imports: a module declaring a table with a larger
+/// minimum is rejected, and `table.grow` fails past this number of
+/// elements, like it does past the maximum of the table.
+pub const MAX_TABLE_ELEMENTS: u32 = 1 << 16;
+
+/// The compiler a module is fuzzed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzBackend {
+    /// The Singlepass compiler.
+    #[cfg(feature = "singlepass")]
+    Singlepass,
+    /// The Cranelift compiler.
+    #[cfg(feature = "cranelift")]
+    Cranelift,
+    /// The LLVM compiler.
+    #[cfg(feature = "llvm")]
+    LLVM,
+}
+
+impl FuzzBackend {
+    fn compiler_config(self) -> Box<dyn CompilerConfig> {
+        match self {
+            #[cfg(feature = "singlepass")]
+            Self::Singlepass => Box::new(crate::sys::Singlepass::default()),
+            #[cfg(feature = "cranelift")]
+            Self::Cranelift => Box::new(crate::sys::Cranelift::default()),
+            #[cfg(feature = "llvm")]
+            Self::LLVM => Box::new(crate::sys::LLVM::default()),
+        }
+    }
+}
+
+/// How a module is run by [`run_arbitrary`]: the backend compiling
+/// it, the proposals it may use, and the budgets bounding the work it
+/// can do.
+///
+/// The budgets must be small enough for a fuzzer to run many modules
+/// per second, e.g. a thousand points and a few pages of memory. Two
+/// configurations only differing by their backend are expected to
+/// give the same outcome, see [`run_differential`].
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::fuzzing::*;
+/// # use wasmer::{Features, Pages};
+/// # fn config(backend: FuzzBackend) -> FuzzConfig {
+/// FuzzConfig {
+///     backend,
+///     features: Features::default(),
+///     max_points: 1000,
+///     max_memory: Pages(16),
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    /// The compiler the module is compiled with.
+    pub backend: FuzzBackend,
+    /// The WebAssembly features the module is validated with. The
+    /// modules using other proposals are rejected.
+    pub features: Features,
+    /// The points the module can spend running, shared by the start
+    /// function and all the calls. A point is spent on each function
+    /// call and each loop iteration, which bounds the number of
+    /// instructions run: running out of points traps as
+    /// `unreachable`.
+    pub max_points: u64,
+    /// The maximum size of each memory, defined or imported. The
+    /// modules declaring a larger minimum size are rejected, and
+    /// `memory.grow` fails past it.
+    pub max_memory: Pages,
+}
+
+/// A value returned by a function, in a form which can be compared
+/// across backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzValue {
+    /// A 32-bit integer.
+    I32(i32),
+    /// A 64-bit integer.
+    I64(i64),
+    /// The bits of a 32-bit float.
+    F32(u32),
+    /// The bits of a 64-bit float.
+    F64(u64),
+    /// A 128-bit vector.
+    V128(u128),
+    /// A function reference, which is either null or not.
+    FuncRef {
+        /// Whether the reference is null.
+        is_null: bool,
+    },
+    /// An `externref`, which is either null or not.
+    ExternRef {
+        /// Whether the reference is null.
+        is_null: bool,
+    },
+}
+
+impl From<&Val> for FuzzValue {
+    fn from(value: &Val) -> Self {
+        match value {
+            Val::I32(value) => Self::I32(*value),
+            Val::I64(value) => Self::I64(*value),
+            Val::F32(value) => Self::F32(value.to_bits()),
+            Val::F64(value) => Self::F64(value.to_bits()),
+            Val::V128(value) => Self::V128(*value),
+            Val::FuncRef(function) => Self::FuncRef {
+                is_null: function.is_none(),
+            },
+            Val::ExternRef(extern_ref) => Self::ExternRef {
+                is_null: extern_ref.is_null(),
+            },
+        }
+    }
+}
+
+/// What happened to a module run by [`run_arbitrary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOutcome {
+    /// The module is invalid, or doesn't fit in the budgets.
+    Rejected(String),
+    /// The module is valid, but the backend failed to compile it.
+    CompileError(String),
+    /// The start function or an exported function trapped. Running
+    /// out of points traps as `unreachable`.
+    Trap {
+        /// The exported function which trapped, or `None` for the
+        /// start function.
+        function: Option<String>,
+        /// The code of the trap, or `None` for a trap which doesn't
+        /// come from WebAssembly code.
+        code: Option<TrapCode>,
+    },
+    /// Every exported function returned, with these results.
+    Completed(Vec<(String, Vec<FuzzValue>)>),
+}
+
+/// Validates, compiles and instantiates the module `bytes`, then
+/// calls each of its exported functions in order, until one traps.
+///
+/// The imports of the module are stubs: functions returning default
+/// values, and new memories, tables and globals. The exported
+/// functions are called with default arguments: zeros and null
+/// references.
+///
+/// The execution is bounded by the points of `config`, and the
+/// memories by its maximum size. Floating-point NaNs are canonicalized,
+/// so that the outcome is the same whatever the backend.
+///
+/// Any bytes can be passed, typically the input of a fuzzer or a
+/// module generated from it: the errors are part of the
+/// [`FuzzOutcome`], and this function never panics, unless Wasmer has
+/// a bug. Each call compiles the module with a new engine, so that
+/// the runs don't depend on each other.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::fuzzing::*;
+/// # fn check(bytes: &[u8], config: &FuzzConfig) {
+/// match run_arbitrary(bytes, config) {
+///     FuzzOutcome::Rejected(_) | FuzzOutcome::CompileError(_) => {}
+///     FuzzOutcome::Trap { function, code } => println!("{:?} trapped: {:?}", function, code),
+///     FuzzOutcome::Completed(results) => println!("{:?}", results),
+/// }
+/// # }
+/// ```
+pub fn run_arbitrary(bytes: &[u8], config: &FuzzConfig) -> FuzzOutcome {
+    let mut compiler_config = config.backend.compiler_config();
+    compiler_config.canonicalize_nans(true);
+    compiler_config.push_middleware(Arc::new(Fuel::new(config.max_points)));
+    let engine = Universal::new(compiler_config)
+        .features(config.features.clone())
+        .engine();
+    let tunables = LimitingTunables::new(
+        BaseTunables::for_target(&Target::default()),
+        config.max_memory,
+        MAX_TABLE_ELEMENTS,
+    );
+    if let Err(error) = Module::validate_with_engine(&engine, bytes) {
+        return FuzzOutcome::Rejected(error.to_string());
+    }
+    let store = Store::new_with_tunables(&engine, tunables);
+    let module = match Module::new(&store, bytes) {
+        Ok(module) => module,
+        Err(error) => return FuzzOutcome::CompileError(error.to_string()),
+    };
+    let import_object = match stub_imports(&module) {
+        Ok(import_object) => import_object,
+        Err(error) => return FuzzOutcome::Rejected(error),
+    };
+    let instance = match Instance::new(&module, &import_object) {
+        Ok(instance) => instance,
+        Err(InstantiationError::Start(error)) => {
+            return FuzzOutcome::Trap {
+                function: None,
+                code: error.to_trap(),
+            }
+        }
+        Err(error) => return FuzzOutcome::Rejected(error.to_string()),
+    };
+
+    let mut results = Vec::new();
+    for export in module.exports() {
+        let function = match instance.exports.get_function(export.name()) {
+            Ok(function) => function,
+            Err(_) => continue,
+        };
+        let params = function
+            .ty()
+            .params()
+            .iter()
+            .map(|ty| default_value(*ty))
+            .collect::<Vec<_>>();
+        match function.call(&params) {
+            Ok(values) => results.push((
+                export.name().to_string(),
+                values.iter().map(FuzzValue::from).collect(),
+            )),
+            Err(error) => {
+                return FuzzOutcome::Trap {
+                    function: Some(export.name().to_string()),
+                    code: error.to_trap(),
+                }
+            }
+        }
+    }
+    FuzzOutcome::Completed(results)
+}
+
+/// The outcomes of the same module run with two configurations by
+/// [`run_differential`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DifferentialOutcome {
+    /// The outcome with the first configuration.
+    pub first: FuzzOutcome,
+    /// The outcome with the second configuration.
+    pub second: FuzzOutcome,
+}
+
+impl DifferentialOutcome {
+    /// Returns whether the two configurations agree on what the
+    /// module does.
+    ///
+    /// A backend failing to compile a module, e.g. because it doesn't
+    /// support one of its features, doesn't count as a disagreement.
+    pub fn agree(&self) -> bool {
+        match (&self.first, &self.second) {
+            (FuzzOutcome::CompileError(_), _) | (_, FuzzOutcome::CompileError(_)) => true,
+            (first, second) => first == second,
+        }
+    }
+}
+
+/// Runs the module `bytes` with [`run_arbitrary`] twice, with `first`
+/// and `second`, typically two backends with the same budgets, so
+/// that their outcomes can be compared with
+/// [`DifferentialOutcome::agree`].
+pub fn run_differential(
+    bytes: &[u8],
+    first: &FuzzConfig,
+    second: &FuzzConfig,
+) -> DifferentialOutcome {
+    DifferentialOutcome {
+        first: run_arbitrary(bytes, first),
+        second: run_arbitrary(bytes, second),
+    }
+}
+
+/// The default value of type `ty`: zero or a null reference.
+fn default_value(ty: ValType) -> Val {
+    match ty {
+        ValType::I32 => Val::I32(0),
+        ValType::I64 => Val::I64(0),
+        ValType::F32 => Val::F32(0.0),
+        ValType::F64 => Val::F64(0.0),
+        ValType::V128 => Val::V128(0),
+        ValType::ExternRef => Val::ExternRef(ExternRef::null()),
+        ValType::FuncRef => Val::FuncRef(None),
+    }
+}
+
+/// Creates a stub for each import of `module`, or describes the import
+/// which can't be created within the budgets.
+fn stub_imports(module: &Module) -> Result<ImportObject, String> {
+    let store = module.store();
+    let mut namespaces = BTreeMap::<String, Exports>::new();
+    for import in module.imports() {
+        let stub: Extern = match import.ty() {
+            ExternType::Function(ty) => {
+                let results = ty.results().iter().map(|ty| default_value(*ty)).collect();
+                Function::new(store, ty, move |_| Ok(Vec::clone(&results))).into()
+            }
+            ExternType::Memory(ty) => Memory::new(store, *ty)
+                .map_err(|error| error.to_string())?
+                .into(),
+            ExternType::Table(ty) => Table::new(store, *ty, default_value(ty.ty))
+                .map_err(|error| error.to_string())?
+                .into(),
+            ExternType::Global(ty) => {
+                let value = default_value(ty.ty);
+                match ty.mutability {
+                    Mutability::Const => Global::new(store, value),
+                    Mutability::Var => Global::new_mut(store, value),
+                }
+                .into()
+            }
+        };
+        namespaces
+            .entry(import.module().to_string())
+            .or_default()
+            .insert(import.name(), stub);
+    }
+
+    let mut import_object = ImportObject::new();
+    for (namespace, exports) in namespaces {
+        import_object.register(namespace, exports);
+    }
+    Ok(import_object)
+}
+
+/// A middleware spending a point on each function call and each loop
+/// iteration, and trapping once the points are exhausted.
+///
+/// The code run between two points is straight-line, so that the
+/// points bound the number of instructions run. The points are kept
+/// in a global of the instance.
+#[derive(Debug, MemoryUsage)]
+struct Fuel {
+    /// The points the instance starts with.
+    points: u64,
+    /// The global holding the remaining points.
+    global: Mutex<Option<GlobalIndex>>,
+}
+
+impl Fuel {
+    fn new(points: u64) -> Self {
+        Self {
+            points,
+            global: Mutex::new(None),
+        }
+    }
+}
+
+impl ModuleMiddleware for Fuel {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionFuel {
+            global: self.global.lock().unwrap().unwrap(),
+            entered: false,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let global = module_info
+            .globals
+            .push(GlobalType::new(ValType::I64, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(self.points as i64));
+        module_info.exports.insert(
+            "wasmer_fuzzing_remaining_points".to_string(),
+            ExportIndex::Global(global),
+        );
+        *self.global.lock().unwrap() = Some(global);
+    }
+}
+
+/// The function-level [`Fuel`] middleware.
+#[derive(Debug)]
+struct FunctionFuel {
+    /// The global holding the remaining points.
+    global: GlobalIndex,
+    /// Whether the entry of the function has been instrumented.
+    entered: bool,
+}
+
+impl FunctionFuel {
+    /// Spends a point, trapping if there is none left.
+    fn spend_point(&self, state: &mut MiddlewareReaderState) {
+        let global_index = self.global.as_u32();
+        state.extend(&[
+            // if globals[global] == 0 { throw(); }
+            Operator::GlobalGet { global_index },
+            Operator::I64Eqz,
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::Unreachable,
+            Operator::End,
+            // globals[global] -= 1;
+            Operator::GlobalGet { global_index },
+            Operator::I64Const { value: 1 },
+            Operator::I64Sub,
+            Operator::GlobalSet { global_index },
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionFuel {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            self.spend_point(state);
+        }
+        let is_loop = matches!(operator, Operator::Loop { .. });
+        state.push_operator(operator);
+        // The point is spent inside the loop, on each iteration.
+        if is_loop {
+            self.spend_point(state);
+        }
+        Ok(())
+    }
+}
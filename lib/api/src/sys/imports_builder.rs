@@ -0,0 +1,297 @@
+//! A builder of [`ImportObject`]s that checks the imports against the
+//! ones a [`Module`] expects.
+
+use crate::sys::exports::Exports;
+use crate::sys::externals::Extern;
+use crate::sys::import_object::ImportObject;
+use crate::sys::module::Module;
+use indexmap::IndexMap;
+use thiserror::Error;
+use wasmer_types::ExternType;
+
+/// An error produced by [`ImportsBuilder::build`], identifying the
+/// offending import.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ImportsBuilderError {
+    /// The module imports something that hasn't been defined.
+    #[error("missing import `{namespace}`.`{name}`: the module expects a {expected}")]
+    Missing {
+        /// The namespace of the import.
+        namespace: String,
+        /// The name of the import.
+        name: String,
+        /// The type the module expects.
+        expected: ExternType,
+    },
+    /// An import has been defined with a type that doesn't match the
+    /// one the module expects.
+    #[error(
+        "incompatible import `{namespace}`.`{name}`: the module expects a {expected}, but a {found} was defined"
+    )]
+    IncompatibleType {
+        /// The namespace of the import.
+        namespace: String,
+        /// The name of the import.
+        name: String,
+        /// The type the module expects.
+        expected: ExternType,
+        /// The type of the definition.
+        found: ExternType,
+    },
+}
+
+/// Build an [`ImportObject`] one import at a time.
+///
+/// Unlike the [`imports!`] macro, a builder created with
+/// [`ImportsBuilder::from_module`] knows which imports the module
+/// expects, so that a missing import or an import of the wrong type
+/// is reported by [`ImportsBuilder::build`] rather than when
+/// instantiating the module.
+///
+/// [`imports!`]: crate::imports
+///
+/// # Usage
+///
+/// ```
+/// # use wasmer::*;
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let module = Module::new(&store, r#"
+///     (module
+///         (import "env" "memory" (memory 1))
+///         (import "env" "log" (func (param i32))))
+/// "#)?;
+///
+/// let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+/// let log = Function::new_native(&store, |_: i32| {});
+/// let import_object = ImportsBuilder::from_module(&module)
+///     .define("env", "memory", memory)
+///     .define("env", "log", log)
+///     .build()?;
+/// let instance = Instance::new(&module, &import_object)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ImportsBuilder {
+    /// The imports the module expects, if the builder has been created
+    /// with [`ImportsBuilder::from_module`].
+    expected: IndexMap<(String, String), ExternType>,
+    definitions: IndexMap<(String, String), Extern>,
+}
+
+impl ImportsBuilder {
+    /// Create an empty builder, which doesn't check the imports.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a builder for the imports of `module`.
+    ///
+    /// The imports the module expects can be listed with
+    /// [`ImportsBuilder::expected`] and
+    /// [`ImportsBuilder::undefined`], and are checked by
+    /// [`ImportsBuilder::build`].
+    pub fn from_module(module: &Module) -> Self {
+        let expected = module
+            .imports()
+            .map(|import| {
+                (
+                    (import.module().to_string(), import.name().to_string()),
+                    import.ty().clone(),
+                )
+            })
+            .collect();
+        Self {
+            expected,
+            definitions: IndexMap::new(),
+        }
+    }
+
+    /// Define the import `name` of `namespace`, replacing any previous
+    /// definition.
+    pub fn define<N, S, E>(mut self, namespace: N, name: S, value: E) -> Self
+    where
+        N: Into<String>,
+        S: Into<String>,
+        E: Into<Extern>,
+    {
+        self.definitions
+            .insert((namespace.into(), name.into()), value.into());
+        self
+    }
+
+    /// The imports the module expects, as `(namespace, name, type)`, in
+    /// the order of its import section.
+    pub fn expected(&self) -> impl Iterator<Item = (&str, &str, &ExternType)> {
+        self.expected
+            .iter()
+            .map(|((namespace, name), ty)| (namespace.as_str(), name.as_str(), ty))
+    }
+
+    /// The imports the module expects which haven't been defined yet.
+    pub fn undefined(&self) -> impl Iterator<Item = (&str, &str, &ExternType)> {
+        self.expected()
+            .filter(move |(namespace, name, _)| !self.is_defined(namespace, name))
+    }
+
+    /// Returns true if the import `name` of `namespace` is defined.
+    pub fn is_defined(&self, namespace: &str, name: &str) -> bool {
+        self.definitions
+            .contains_key(&(namespace.to_string(), name.to_string()))
+    }
+
+    /// Build the [`ImportObject`].
+    ///
+    /// If the builder was created with [`ImportsBuilder::from_module`],
+    /// every import of the module must be defined with a compatible
+    /// type. Definitions the module doesn't import are kept.
+    pub fn build(self) -> Result<ImportObject, ImportsBuilderError> {
+        for ((namespace, name), expected) in self.expected.iter() {
+            let definition = self.definitions.get(&(namespace.clone(), name.clone()));
+            let found = match definition {
+                Some(definition) => definition.ty(),
+                None => {
+                    return Err(ImportsBuilderError::Missing {
+                        namespace: namespace.clone(),
+                        name: name.clone(),
+                        expected: expected.clone(),
+                    })
+                }
+            };
+            if !found.is_compatible_with(expected) {
+                return Err(ImportsBuilderError::IncompatibleType {
+                    namespace: namespace.clone(),
+                    name: name.clone(),
+                    expected: expected.clone(),
+                    found,
+                });
+            }
+        }
+
+        let mut namespaces: IndexMap<String, Exports> = IndexMap::new();
+        for ((namespace, name), value) in self.definitions {
+            namespaces
+                .entry(namespace)
+                .or_insert_with(Exports::new)
+                .insert(name, value);
+        }
+        let mut import_object = ImportObject::new();
+        for (namespace, exports) in namespaces {
+            import_object.register(namespace, exports);
+        }
+        Ok(import_object)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sys::{Function, Global, Instance, Store, Val};
+    use wasmer_types::{FunctionType, GlobalType, Mutability, Type};
+
+    const WAT: &str = r#"
+        (module
+            (import "env" "log" (func (param i32)))
+            (import "env" "counter" (global (mut i32))))
+    "#;
+
+    #[test]
+    fn from_module_lists_expected_imports() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        let counter = Global::new_mut(&store, Val::I32(0));
+
+        let builder = ImportsBuilder::from_module(&module).define("env", "counter", counter);
+        assert_eq!(
+            builder
+                .expected()
+                .map(|(namespace, name, _)| (namespace, name))
+                .collect::<Vec<_>>(),
+            vec![("env", "log"), ("env", "counter")]
+        );
+        assert_eq!(
+            builder.undefined().collect::<Vec<_>>(),
+            vec![(
+                "env",
+                "log",
+                &ExternType::Function(FunctionType::new(vec![Type::I32], vec![]))
+            )]
+        );
+    }
+
+    #[test]
+    fn build_and_instantiate() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let import_object = ImportsBuilder::from_module(&module)
+            .define("env", "log", Function::new_native(&store, |_: i32| {}))
+            .define("env", "counter", Global::new_mut(&store, Val::I32(0)))
+            .define("other", "unused", Global::new(&store, Val::I64(0)))
+            .build()
+            .unwrap();
+        assert!(import_object.contains_namespace("other"));
+        Instance::new(&module, &import_object).unwrap();
+    }
+
+    #[test]
+    fn build_reports_missing_imports() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let error = ImportsBuilder::from_module(&module)
+            .define("env", "log", Function::new_native(&store, |_: i32| {}))
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            ImportsBuilderError::Missing { namespace, name, .. } if namespace == "env" && name == "counter"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "missing import `env`.`counter`: the module expects a global I32 (mutable)"
+        );
+    }
+
+    #[test]
+    fn build_reports_type_mismatches() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let error = ImportsBuilder::from_module(&module)
+            .define("env", "log", Function::new_native(&store, |_: i64| {}))
+            .define("env", "counter", Global::new_mut(&store, Val::I32(0)))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible import `env`.`log`: the module expects a function [I32] -> [], but a function [I64] -> [] was defined"
+        );
+
+        let error = ImportsBuilder::from_module(&module)
+            .define("env", "log", Function::new_native(&store, |_: i32| {}))
+            .define("env", "counter", Global::new(&store, Val::I32(0)))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ImportsBuilderError::IncompatibleType {
+                namespace: "env".to_string(),
+                name: "counter".to_string(),
+                expected: ExternType::Global(GlobalType::new(Type::I32, Mutability::Var)),
+                found: ExternType::Global(GlobalType::new(Type::I32, Mutability::Const)),
+            }
+        );
+    }
+
+    #[test]
+    fn new_does_not_check_imports() {
+        let store = Store::default();
+        let import_object = ImportsBuilder::new()
+            .define("env", "counter", Global::new(&store, Val::I32(0)))
+            .build()
+            .unwrap();
+        assert!(import_object.get_export("env", "counter").is_some());
+    }
+}
@@ -1,13 +1,19 @@
 use crate::sys::exports::Exports;
-use crate::sys::externals::Extern;
+use crate::sys::externals::{Extern, Memory};
 use crate::sys::module::Module;
 use crate::sys::store::Store;
-use crate::sys::{HostEnvInitError, LinkError, RuntimeError};
+use crate::sys::{CompileError, HostEnvInitError, LinkError, RuntimeError};
 use loupe::MemoryUsage;
+use std::any::Any;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::sync::{Arc, Mutex};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
 use thiserror::Error;
-use wasmer_engine::Resolver;
+use wasmer_engine::{InstanceDescriptor, LiveInstance, Resolver};
+use wasmer_types::ExportIndex;
 use wasmer_vm::{InstanceHandle, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
@@ -24,6 +30,10 @@ pub struct Instance {
     module: Module,
     /// The exports for an instance.
     pub exports: Exports,
+    #[loupe(skip)]
+    metadata: Arc<InstanceMetadata>,
+    #[loupe(skip)]
+    host_data: Arc<Mutex<Option<Box<dyn Any + Send>>>>,
 }
 
 #[cfg(test)]
@@ -66,6 +76,11 @@ pub enum InstantiationError {
     /// Error occurred when initializing the host environment.
     #[error(transparent)]
     HostEnvInitialization(HostEnvInitError),
+
+    /// The module, compiled on its first instantiation, failed to
+    /// compile.
+    #[error(transparent)]
+    Compile(CompileError),
 }
 
 impl From<wasmer_engine::InstantiationError> for InstantiationError {
@@ -74,6 +89,7 @@ impl From<wasmer_engine::InstantiationError> for InstantiationError {
             wasmer_engine::InstantiationError::Link(e) => Self::Link(e),
             wasmer_engine::InstantiationError::Start(e) => Self::Start(e),
             wasmer_engine::InstantiationError::CpuFeature(e) => Self::CpuFeature(e),
+            wasmer_engine::InstantiationError::Compile(e) => Self::Compile(e),
         }
     }
 }
@@ -134,10 +150,37 @@ impl Instance {
             })
             .collect::<Exports>();
 
+        let memories = module
+            .info()
+            .memories
+            .keys()
+            .filter_map(|index| {
+                let export = handle.lookup_by_declaration(&ExportIndex::Memory(index));
+                match Extern::from_vm_export(store, export.into()) {
+                    Extern::Memory(memory) => Some(memory),
+                    _ => None,
+                }
+            })
+            .collect();
+        let metadata = Arc::new(InstanceMetadata {
+            module_hash: module.hash().map(str::to_string),
+            module_name: module.name().map(str::to_string),
+            memories,
+            tags: Mutex::new(BTreeMap::new()),
+            created_at: SystemTime::now(),
+        });
+        let live_instance: Arc<dyn LiveInstance> = metadata.clone();
+        store
+            .engine()
+            .instance_registry()
+            .register(Arc::downgrade(&live_instance));
+
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
             exports,
+            metadata,
+            host_data: Arc::new(Mutex::new(None)),
         };
 
         // # Safety
@@ -169,12 +212,155 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the number of calls into this instance currently on
+    /// the stack.
+    ///
+    /// It is `0` when the instance is idle, `1` while one of its
+    /// exports runs, and grows by one each time a host function
+    /// called by the instance calls back into it. The depth can be
+    /// limited with [`Store::max_reentrancy_depth`].
+    pub fn reentrancy_depth(&self) -> usize {
+        self.handle.lock().unwrap().reentrancy_depth()
+    }
+
+    /// Tags this instance with `value` under `key`, returning the
+    /// previous value of the tag, if any.
+    ///
+    /// The tags are shared by the clones of this instance, and are
+    /// listed by [`Engine::live_instances`].
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// instance.set_tag("tenant", "acme");
+    /// assert_eq!(instance.get_tag("tenant").as_deref(), Some("acme"));
+    /// assert_eq!(instance.get_tag("region"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Engine::live_instances`]: crate::Engine::live_instances
+    pub fn set_tag(&self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.metadata
+            .tags
+            .lock()
+            .unwrap()
+            .insert(key.into(), value.into())
+    }
+
+    /// Returns the value of the tag `key` of this instance, see
+    /// [`Instance::set_tag`].
+    pub fn get_tag(&self, key: &str) -> Option<String> {
+        self.metadata.tags.lock().unwrap().get(key).cloned()
+    }
+
+    /// Attaches `data` of the embedder to this instance, returning
+    /// the data previously attached, if any.
+    ///
+    /// The data is shared by the clones of this instance, and dropped
+    /// with the last of them.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// struct Session {
+    ///     user: String,
+    ///     requests: u32,
+    /// }
+    ///
+    /// let module = Module::new(&store, "(module)")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// instance.set_host_data(Box::new(Session { user: "ferris".to_string(), requests: 0 }));
+    ///
+    /// instance.host_data::<Session>().unwrap().requests += 1;
+    /// let session = instance.host_data::<Session>().unwrap();
+    /// assert_eq!((session.user.as_str(), session.requests), ("ferris", 1));
+    /// assert!(instance.host_data::<String>().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_host_data(&self, data: Box<dyn Any + Send>) -> Option<Box<dyn Any + Send>> {
+        self.host_data.lock().unwrap().replace(data)
+    }
+
+    /// Returns the data attached to this instance with
+    /// [`Instance::set_host_data`], if it is a `T`.
+    ///
+    /// The data is locked until the returned [`HostData`] is dropped.
+    pub fn host_data<T: Any + Send>(&self) -> Option<HostData<'_, T>> {
+        let guard = self.host_data.lock().unwrap();
+        if guard.as_ref()?.is::<T>() {
+            Some(HostData {
+                guard,
+                marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the entity of this instance declared at `index`,
+    /// whether it is exported or not.
+    pub(crate) fn lookup_by_declaration(&self, index: &ExportIndex) -> Extern {
+        let export = self.handle.lock().unwrap().lookup_by_declaration(index);
+        Extern::from_vm_export(self.store(), export.into())
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
     }
 }
 
+/// What an [`Instance`] shares with the instance registry of its
+/// engine, which only holds it weakly.
+struct InstanceMetadata {
+    module_hash: Option<String>,
+    module_name: Option<String>,
+    memories: Vec<Memory>,
+    tags: Mutex<BTreeMap<String, String>>,
+    created_at: SystemTime,
+}
+
+impl LiveInstance for InstanceMetadata {
+    fn describe(&self) -> InstanceDescriptor {
+        InstanceDescriptor {
+            module_hash: self.module_hash.clone(),
+            module_name: self.module_name.clone(),
+            tags: self.tags.lock().unwrap().clone(),
+            memories: self.memories.len(),
+            memory_bytes: self.memories.iter().map(Memory::data_size).sum(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// The data of the embedder attached to an [`Instance`], locked
+/// while borrowed, see [`Instance::host_data`].
+pub struct HostData<'a, T> {
+    guard: MutexGuard<'a, Option<Box<dyn Any + Send>>>,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<T: Any> Deref for HostData<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // The type of the data was checked by `Instance::host_data`.
+        self.guard.as_ref().unwrap().downcast_ref().unwrap()
+    }
+}
+
+impl<T: Any> DerefMut for HostData<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap().downcast_mut().unwrap()
+    }
+}
+
 impl fmt::Debug for Instance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Instance")
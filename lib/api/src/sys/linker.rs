@@ -0,0 +1,377 @@
+//! A [`Linker`] instantiating modules by resolving their imports by
+//! name.
+
+use crate::sys::exports::Exports;
+use crate::sys::externals::{Extern, Function};
+use crate::sys::import_object::ImportObject;
+use crate::sys::instance::{Instance, InstantiationError};
+use crate::sys::module::Module;
+use crate::sys::store::Store;
+use crate::sys::types::Val;
+use crate::sys::RuntimeError;
+use indexmap::IndexMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+use wasmer_types::{ExternType, FunctionType, ImportType};
+
+/// A resolver consulted by a [`Linker`] for the imports it has no
+/// definition for, see [`Linker::fallback`].
+type Fallback = dyn Fn(&ImportType) -> Option<Extern> + Send + Sync;
+
+/// An error produced by a [`Linker`].
+#[derive(Error, Debug)]
+pub enum LinkerError {
+    /// An import of the module is neither defined nor resolved by the
+    /// fallback.
+    #[error("unresolved import `{namespace}`.`{name}`: the module expects a {expected}")]
+    Unresolved {
+        /// The namespace of the import.
+        namespace: String,
+        /// The name of the import.
+        name: String,
+        /// The type the module expects.
+        expected: ExternType,
+    },
+    /// An import resolved to a value whose type doesn't match the one
+    /// the module expects.
+    #[error(
+        "incompatible import `{namespace}`.`{name}`: the module expects a {expected}, but a {found} was resolved"
+    )]
+    IncompatibleType {
+        /// The namespace of the import.
+        namespace: String,
+        /// The name of the import.
+        name: String,
+        /// The type the module expects.
+        expected: ExternType,
+        /// The type of the resolved value.
+        found: ExternType,
+    },
+    /// [`Linker::alias`] was given a name that isn't defined.
+    #[error("cannot alias `{namespace}`.`{name}`: it isn't defined")]
+    UnknownAlias {
+        /// The namespace of the aliased definition.
+        namespace: String,
+        /// The name of the aliased definition.
+        name: String,
+    },
+    /// The imports were resolved but the instantiation failed.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+}
+
+/// Instantiate modules by resolving their imports by name.
+///
+/// Unlike an [`ImportObject`], which is built up front, a linker
+/// resolves the imports of each module it instantiates when
+/// [`Linker::instantiate`] is called: first from its definitions,
+/// then from its [fallback](Linker::fallback), so that imports whose
+/// names are only known at runtime, such as those of plugins, can be
+/// provided on demand. The first import which can't be resolved is
+/// reported by its namespace and name.
+///
+/// A linker can instantiate any number of modules, the definitions
+/// are shared by their instances.
+///
+/// # Usage
+///
+/// ```
+/// # use wasmer::*;
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let module = Module::new(&store, r#"
+///     (module
+///         (import "env" "add" (func $add (param i32 i32) (result i32)))
+///         (import "host" "offset" (global i32))
+///         (func (export "run") (result i32)
+///             (call $add (global.get 0) (i32.const 2))))
+/// "#)?;
+///
+/// let mut linker = Linker::new(&store);
+/// linker
+///     .define_func(
+///         "env",
+///         "add",
+///         FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]),
+///         |args| Ok(vec![Value::I32(args[0].unwrap_i32() + args[1].unwrap_i32())]),
+///     )
+///     .define("env", "offset", Global::new(&store, Value::I32(40)))
+///     .alias("env", "offset", "host", "offset")?;
+///
+/// let instance = linker.instantiate(&module)?;
+/// let run = instance.exports.get_native_function::<(), i32>("run")?;
+/// assert_eq!(run.call()?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Linker {
+    store: Store,
+    definitions: IndexMap<(String, String), Extern>,
+    fallback: Option<Arc<Fallback>>,
+}
+
+impl Linker {
+    /// Create a linker without definitions, whose host functions are
+    /// created in `store`.
+    pub fn new(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            definitions: IndexMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Define `name` of `namespace`, replacing any previous definition.
+    pub fn define<N, S, E>(&mut self, namespace: N, name: S, value: E) -> &mut Self
+    where
+        N: Into<String>,
+        S: Into<String>,
+        E: Into<Extern>,
+    {
+        self.definitions
+            .insert((namespace.into(), name.into()), value.into());
+        self
+    }
+
+    /// Define `name` of `namespace` as a host function of type `ty`
+    /// calling `func`, replacing any previous definition.
+    ///
+    /// `func` can capture its environment, see [`Function::new`].
+    pub fn define_func<N, S, FT, F>(&mut self, namespace: N, name: S, ty: FT, func: F) -> &mut Self
+    where
+        N: Into<String>,
+        S: Into<String>,
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
+    {
+        let function = Function::new(&self.store, ty, func);
+        self.define(namespace, name, function)
+    }
+
+    /// Define `as_name` of `as_namespace` as the current definition of
+    /// `name` of `namespace`.
+    ///
+    /// Redefining either name later doesn't change the other.
+    pub fn alias(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        as_namespace: &str,
+        as_name: &str,
+    ) -> Result<&mut Self, LinkerError> {
+        let value =
+            self.get(namespace, name)
+                .cloned()
+                .ok_or_else(|| LinkerError::UnknownAlias {
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                })?;
+        Ok(self.define(as_namespace, as_name, value))
+    }
+
+    /// Resolve the imports without a definition with `fallback`,
+    /// replacing any previous fallback.
+    ///
+    /// `fallback` is called with each such import on every
+    /// instantiation, and returns `None` for the imports it doesn't
+    /// know, which are then reported as [`LinkerError::Unresolved`].
+    pub fn fallback<F>(&mut self, fallback: F) -> &mut Self
+    where
+        F: Fn(&ImportType) -> Option<Extern> + 'static + Send + Sync,
+    {
+        self.fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// Returns the definition of `name` of `namespace`, if any.
+    pub fn get(&self, namespace: &str, name: &str) -> Option<&Extern> {
+        self.definitions
+            .get(&(namespace.to_string(), name.to_string()))
+    }
+
+    /// Returns the value `import` resolves to, checking its type.
+    fn resolve(&self, import: &ImportType) -> Result<Extern, LinkerError> {
+        let value = match self.get(import.module(), import.name()) {
+            Some(value) => Some(value.clone()),
+            None => self.fallback.as_ref().and_then(|fallback| fallback(import)),
+        };
+        let value = value.ok_or_else(|| LinkerError::Unresolved {
+            namespace: import.module().to_string(),
+            name: import.name().to_string(),
+            expected: import.ty().clone(),
+        })?;
+        let found = value.ty();
+        if !found.is_compatible_with(import.ty()) {
+            return Err(LinkerError::IncompatibleType {
+                namespace: import.module().to_string(),
+                name: import.name().to_string(),
+                expected: import.ty().clone(),
+                found,
+            });
+        }
+        Ok(value)
+    }
+
+    /// Resolve the imports of `module`, in the order of its import
+    /// section, and instantiate it.
+    pub fn instantiate(&self, module: &Module) -> Result<Instance, LinkerError> {
+        let mut namespaces: IndexMap<String, Exports> = IndexMap::new();
+        for import in module.imports() {
+            let value = self.resolve(&import)?;
+            namespaces
+                .entry(import.module().to_string())
+                .or_insert_with(Exports::new)
+                .insert(import.name(), value);
+        }
+        let mut import_object = ImportObject::new();
+        for (namespace, exports) in namespaces {
+            import_object.register(namespace, exports);
+        }
+        Ok(Instance::new(module, &import_object)?)
+    }
+}
+
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("definitions", &self.definitions)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sys::{Global, Type};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const WAT: &str = r#"
+        (module
+            (import "env" "double" (func $double (param i32) (result i32)))
+            (import "env" "base" (global i32))
+            (func (export "run") (result i32)
+                (call $double (global.get 0))))
+    "#;
+
+    fn double_type() -> FunctionType {
+        FunctionType::new(vec![Type::I32], vec![Type::I32])
+    }
+
+    fn run(instance: &Instance) -> i32 {
+        instance
+            .exports
+            .get_native_function::<(), i32>("run")
+            .unwrap()
+            .call()
+            .unwrap()
+    }
+
+    #[test]
+    fn define_and_instantiate() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut linker = Linker::new(&store);
+        let counter = calls.clone();
+        linker
+            .define_func("env", "double", double_type(), move |args| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(vec![Val::I32(args[0].unwrap_i32() * 2)])
+            })
+            .define("env", "base", Global::new(&store, Val::I32(21)));
+
+        assert_eq!(run(&linker.instantiate(&module).unwrap()), 42);
+        assert_eq!(run(&linker.instantiate(&module).unwrap()), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn alias() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let mut linker = Linker::new(&store);
+        linker
+            .define_func("host", "twice", double_type(), |args| {
+                Ok(vec![Val::I32(args[0].unwrap_i32() * 2)])
+            })
+            .define("env", "base", Global::new(&store, Val::I32(4)));
+        linker.alias("host", "twice", "env", "double").unwrap();
+        assert_eq!(run(&linker.instantiate(&module).unwrap()), 8);
+
+        let error = linker
+            .alias("host", "unknown", "env", "double")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot alias `host`.`unknown`: it isn't defined"
+        );
+    }
+
+    #[test]
+    fn fallback() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let mut linker = Linker::new(&store);
+        let fallback_store = store.clone();
+        linker
+            .define_func("env", "double", double_type(), |args| {
+                Ok(vec![Val::I32(args[0].unwrap_i32() * 2)])
+            })
+            .fallback(move |import| match (import.module(), import.name()) {
+                ("env", "base") => Some(Global::new(&fallback_store, Val::I32(5)).into()),
+                _ => None,
+            });
+        assert_eq!(run(&linker.instantiate(&module).unwrap()), 10);
+
+        // Definitions take precedence over the fallback.
+        linker.define("env", "base", Global::new(&store, Val::I32(7)));
+        assert_eq!(run(&linker.instantiate(&module).unwrap()), 14);
+    }
+
+    #[test]
+    fn reports_unresolved_imports() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let mut linker = Linker::new(&store);
+        linker
+            .define_func("env", "double", double_type(), |args| Ok(args.to_vec()))
+            .fallback(|_| None);
+        let error = linker.instantiate(&module).unwrap_err();
+        assert!(matches!(
+            &error,
+            LinkerError::Unresolved { namespace, name, .. } if namespace == "env" && name == "base"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "unresolved import `env`.`base`: the module expects a global I32 (constant)"
+        );
+    }
+
+    #[test]
+    fn reports_type_mismatches() {
+        let store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+
+        let mut linker = Linker::new(&store);
+        linker
+            .define_func(
+                "env",
+                "double",
+                FunctionType::new(vec![Type::I64], vec![Type::I64]),
+                |args| Ok(args.to_vec()),
+            )
+            .define("env", "base", Global::new(&store, Val::I32(1)));
+        let error = linker.instantiate(&module).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible import `env`.`double`: the module expects a function [I32] -> [I32], but a function [I64] -> [I64] was resolved"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! The initial contents of the memory of a [`Module`], see
+//! [`Module::memory_init_image`].
+
+use crate::sys::module::Module;
+use std::ops::Range;
+use wasmer_types::{MemoryIndex, WASM_PAGE_SIZE};
+
+/// The initial contents of the memory of a [`Module`], with its data
+/// segments merged, as returned by [`Module::memory_init_image`].
+///
+/// The memory of a new instance holds `data` from `offset`, and zeros
+/// everywhere else. Both the offset and the length of `data` are
+/// multiples of [`WASM_PAGE_SIZE`], and so of the size of the host
+/// pages, so the image can be mapped copy-on-write as is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryInitImage {
+    /// The offset of the image in the memory.
+    pub offset: usize,
+    /// The contents of the memory from `offset`.
+    pub data: Vec<u8>,
+}
+
+impl MemoryInitImage {
+    /// The range of the memory covered by the image.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.data.len()
+    }
+}
+
+impl Module {
+    /// Returns the initial contents of the memory defined by this
+    /// module, as the data segments initialize it when the module is
+    /// instantiated.
+    ///
+    /// Returns `None` when the contents depend on the instantiation:
+    /// when the module doesn't define its memory, and when a segment
+    /// is placed at an offset read from a global or doesn't fit in the
+    /// initial size of the memory, failing the instantiation. The
+    /// passive data segments, only copied by `memory.init`, aren't
+    /// part of the image.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(
+    ///     &store,
+    ///     r#"(module
+    ///         (memory 2)
+    ///         (data (i32.const 65540) "hello"))"#,
+    /// )?;
+    ///
+    /// let image = module.memory_init_image().unwrap();
+    /// assert_eq!(image.range(), 65536..131072);
+    /// assert_eq!(&image.data[4..9], b"hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn memory_init_image(&self) -> Option<MemoryInitImage> {
+        let info = self.info();
+        let memory_index = MemoryIndex::from_u32(0);
+        let memory = info.memories.get(memory_index)?;
+        if info.is_imported_memory(memory_index) {
+            return None;
+        }
+        let initializers = self.artifact().data_initializers();
+        if initializers.iter().any(|initializer| {
+            initializer.location.memory_index != memory_index || initializer.location.base.is_some()
+        }) {
+            return None;
+        }
+
+        let memory_len = memory.minimum.bytes().0;
+        let mut start = usize::MAX;
+        let mut end = 0;
+        for initializer in initializers.iter() {
+            let segment_end = initializer
+                .location
+                .offset
+                .checked_add(initializer.data.len())?;
+            if segment_end > memory_len {
+                return None;
+            }
+            if !initializer.data.is_empty() {
+                start = start.min(initializer.location.offset);
+                end = end.max(segment_end);
+            }
+        }
+        if start >= end {
+            return Some(MemoryInitImage::default());
+        }
+
+        // Align the image to the pages, which the memory is made of.
+        let offset = start / WASM_PAGE_SIZE * WASM_PAGE_SIZE;
+        let len = (end - offset + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE * WASM_PAGE_SIZE;
+        let mut data = vec![0; len];
+        // The segments are applied in order, the later ones overwriting
+        // the earlier ones.
+        for initializer in initializers.iter().filter(|i| !i.data.is_empty()) {
+            let segment_start = initializer.location.offset - offset;
+            data[segment_start..segment_start + initializer.data.len()]
+                .copy_from_slice(&initializer.data);
+        }
+        Some(MemoryInitImage { offset, data })
+    }
+}
@@ -1,12 +1,25 @@
+#[cfg(feature = "universal")]
+pub mod artifact;
+mod caller;
 mod cell;
+mod contract;
 mod env;
 mod exports;
 mod externals;
+mod externref_stats;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 mod import_object;
+mod imports_builder;
 mod instance;
+mod linker;
+mod memory_init_image;
 mod module;
 mod native;
 mod ptr;
+#[cfg(unix)]
+mod sandbox;
+mod snapshot;
 mod store;
 mod tunables;
 mod types;
@@ -24,40 +37,56 @@ pub mod internals {
     //! `wasmer-vm`. Please don't use any of this types directly, as
     //! they might change frequently or be removed in the future.
 
-    pub use crate::sys::externals::{WithEnv, WithoutEnv};
+    pub use crate::sys::externals::{WithEnv, WithEnvMut, WithoutEnv};
 }
 
+pub use crate::sys::caller::Caller;
 pub use crate::sys::cell::WasmCell;
-pub use crate::sys::env::{HostEnvInitError, LazyInit, WasmerEnv};
+pub use crate::sys::contract::{
+    ContractChange, ContractExport, ContractImport, ContractType, ContractValueType,
+    ContractViolation, ImportContract, ImportContractError,
+};
+pub use crate::sys::env::{HostEnvInitError, LazyInit, SharedEnv, SharedEnvBorrowError, WasmerEnv};
 pub use crate::sys::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::sys::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    AtomicWaitResult, Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory,
+    MemoryAccessError, Table, TableError, WasmTypeList,
 };
+pub use crate::sys::externref_stats::{ExternRefStats, HolderInfo};
 pub use crate::sys::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
-pub use crate::sys::instance::{Instance, InstantiationError};
-pub use crate::sys::module::Module;
+pub use crate::sys::imports_builder::{ImportsBuilder, ImportsBuilderError};
+pub use crate::sys::instance::{HostData, Instance, InstantiationError};
+pub use crate::sys::linker::{Linker, LinkerError};
+pub use crate::sys::memory_init_image::MemoryInitImage;
+pub use crate::sys::module::{IoCompileError, Module};
 pub use crate::sys::native::NativeFunc;
 pub use crate::sys::ptr::{Array, Item, WasmPtr};
-pub use crate::sys::store::{Store, StoreObject};
-pub use crate::sys::tunables::BaseTunables;
+#[cfg(unix)]
+pub use crate::sys::sandbox::{sandboxed_compile, SandboxError, SandboxLimits};
+pub use crate::sys::snapshot::{InstanceSnapshot, SnapshotError, SnapshotValue};
+pub use crate::sys::store::{CallTimeout, Store, StoreObject};
+pub use crate::sys::tunables::{BaseTunables, LimitingTunables};
 pub use crate::sys::types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Val, ValType,
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, IndexType, MemoryType,
+    Mutability, TableType, Val, ValType,
 };
 pub use crate::sys::types::{Val as Value, ValType as Type};
 pub use crate::sys::utils::is_wasm;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
-    ModuleMiddleware,
+    features_required, wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError,
+    MiddlewareReaderState, ModuleMiddleware, OptLevel,
 };
 pub use wasmer_compiler::{
-    CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
+    CompilationMetrics, CompileError, CpuFeature, Features, ParseCpuFeatureError, PerfDiagnostic,
+    PerfDiagnosticCode, StackMap, Target, ValidationError, WasmError, WasmResult,
 };
 pub use wasmer_engine::{
-    ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
-    NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
+    ChainableNamedResolver, DeserializeError, Engine, EngineEpochController, Export, FrameInfo,
+    InstanceDescriptor, InstanceRegistry, InstantiationEvent, InstantiationObserver,
+    InstantiationPhase, LinkError, NamedResolver, NamedResolverChain, Resolver, RuntimeError,
+    SerializeError, Tunables,
 };
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
@@ -67,19 +96,20 @@ pub use wasmer_types::{
 };
 
 // TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError};
+#[cfg(feature = "memory-access-tracing")]
+pub use wasmer_types::MemoryIndex;
+pub use wasmer_vm::{raise_user_trap, CallInterrupt, GrowGuard, MemoryError, TrapCode};
+#[cfg(feature = "memory-access-tracing")]
+pub use wasmer_vm::{AccessKind, MemoryAccessTracer};
 pub mod vm {
     //! The `vm` module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
+        GrowGuard, Memory, MemoryError, MemoryGrowCallback, MemoryStyle, Table, TableStyle,
+        VMExtern, VMMemoryDefinition, VMTableDefinition,
     };
 }
 
-#[cfg(feature = "wat")]
-pub use wat::parse_bytes as wat2wasm;
-
 // The compilers are mutually exclusive
 #[cfg(any(
     all(
@@ -101,10 +131,10 @@ let store = Store::new(&engine);
 );
 
 #[cfg(feature = "singlepass")]
-pub use wasmer_compiler_singlepass::Singlepass;
+pub use wasmer_compiler_singlepass::{Singlepass, SinglepassConfig};
 
 #[cfg(feature = "cranelift")]
-pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
+pub use wasmer_compiler_cranelift::{Cranelift, CraneliftBranchProtection, CraneliftOptLevel};
 
 #[cfg(feature = "llvm")]
 pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
@@ -1,19 +1,36 @@
+use crate::sys::contract::ImportContract;
+use crate::sys::instance::Instance;
 use crate::sys::store::Store;
-use crate::sys::types::{ExportType, ImportType};
+use crate::sys::types::{ExportType, FunctionType, ImportType, MemoryType, TableType};
 use crate::sys::InstantiationError;
+#[cfg(feature = "streaming")]
+use futures_io::AsyncRead;
+use indexmap::IndexSet;
 use loupe::MemoryUsage;
 use std::fmt;
+#[cfg(feature = "streaming")]
+use std::future::Future;
 use std::io;
 use std::path::Path;
+#[cfg(feature = "streaming")]
+use std::pin::Pin;
 use std::sync::Arc;
+#[cfg(feature = "streaming")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_types::{ExportsIterator, ImportsIterator, ModuleInfo};
-use wasmer_vm::InstanceHandle;
+use wasmer_compiler::{
+    CompilationMetrics, CompileError, CpuFeature, Features, PerfDiagnostic, StackMap,
+    ValidationError,
+};
+use wasmer_engine::{Artifact, DeserializeError, Engine, Resolver, SerializeError};
+use wasmer_types::{ExportIndex, ExportsIterator, ImportsIterator, LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::{InstanceHandle, VMExtern};
 
+/// An error while reading or compiling a module, see
+/// [`Module::from_file`].
 #[derive(Error, Debug)]
 pub enum IoCompileError {
     /// An IO error
@@ -115,7 +132,7 @@ impl Module {
     #[allow(unreachable_code)]
     pub fn new(store: &Store, bytes: impl AsRef<[u8]>) -> Result<Self, CompileError> {
         #[cfg(feature = "wat")]
-        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
+        let bytes = crate::wat2wasm(bytes.as_ref()).map_err(|e| {
             CompileError::Wasm(WasmError::Generic(format!(
                 "Error when converting wat: {}",
                 e
@@ -125,6 +142,50 @@ impl Module {
         Self::from_binary(store, bytes.as_ref())
     }
 
+    /// Creates a new WebAssembly Module like [`Module::new`], and
+    /// returns the statistics reported by the compiler about the code
+    /// it generated.
+    ///
+    /// The compilation time only covers the code generation, not the
+    /// parsing and validation of the module. The time spent on each
+    /// function is only measured when enabled with
+    /// [`CompilerConfig::enable_function_timings`].
+    ///
+    /// Engines which don't report metrics, like the `Dylib` engine,
+    /// produce an error.
+    ///
+    /// [`CompilerConfig::enable_function_timings`]: crate::CompilerConfig::enable_function_timings
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let (module, metrics) = Module::new_with_metrics(&store, "(module (func))")?;
+    /// assert_eq!(metrics.function_count, 1);
+    /// println!(
+    ///     "{} bytes of code generated in {:?}",
+    ///     metrics.total_code_bytes, metrics.compile_time
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_metrics(
+        store: &Store,
+        bytes: impl AsRef<[u8]>,
+    ) -> Result<(Self, CompilationMetrics), CompileError> {
+        let module = Self::new(store, bytes)?;
+        let metrics = module
+            .artifact
+            .compilation_metrics()
+            .cloned()
+            .ok_or_else(|| {
+                CompileError::Codegen("the engine doesn't report compilation metrics".to_string())
+            })?;
+        Ok((module, metrics))
+    }
+
     /// Creates a new WebAssembly module from a file path.
     pub fn from_file(store: &Store, file: impl AsRef<Path>) -> Result<Self, IoCompileError> {
         let file_ref = file.as_ref();
@@ -168,19 +229,134 @@ impl Module {
     ///
     /// This validation is normally pretty fast and checks the enabled
     /// WebAssembly features in the Store Engine to assure deterministic
-    /// validation of the Module.
+    /// validation of the Module. See [`Module::validate_with_engine`]
+    /// for the offset where the module is invalid.
     pub fn validate(store: &Store, binary: &[u8]) -> Result<(), CompileError> {
-        store.engine().validate(binary)
+        Self::validate_with_engine(store.engine().as_ref(), binary)?;
+        Ok(())
+    }
+
+    /// Validates a WebAssembly module with the features enabled in
+    /// `engine`, without compiling it.
+    ///
+    /// This runs the full validation of the module (well-formedness,
+    /// type checking of the function bodies, validity of the imports
+    /// and exports…) and is much faster than [`Module::new`]. It is
+    /// useful to reject invalid modules before spending time compiling
+    /// them.
+    ///
+    /// The returned [`ValidationError`] holds the offset in `binary`
+    /// where the module is invalid.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # let store = Store::default();
+    /// let error = Module::validate_with_engine(store.engine().as_ref(), b"\0wat\x01\0\0\0").unwrap_err();
+    /// assert_eq!(error.offset, 0);
+    /// ```
+    pub fn validate_with_engine<E>(engine: &E, binary: &[u8]) -> Result<(), ValidationError>
+    where
+        E: Engine + ?Sized,
+    {
+        #[cfg(feature = "compiler")]
+        {
+            wasmer_compiler::validate_module(&engine.features(), binary)
+        }
+
+        // Without the translator, the engine is the only one able to
+        // validate the module.
+        #[cfg(not(feature = "compiler"))]
+        {
+            engine
+                .validate(binary)
+                .map_err(|e| ValidationError::new(e.to_string(), 0))
+        }
+    }
+
+    /// Creates a new WebAssembly module from a binary read from
+    /// `reader`, compiling it while it is received.
+    ///
+    /// When the engine supports it, the binary is parsed and validated
+    /// as it arrives, and each function is compiled in the background
+    /// as soon as its body is received, so that most of the
+    /// compilation overlaps with reading from a slow source such as a
+    /// network connection. Otherwise the binary is read entirely and
+    /// compiled with [`Module::from_binary`].
+    ///
+    /// Like [`Module::from_binary`], this function doesn't accept the
+    /// WebAssembly text format.
+    ///
+    /// Once the whole binary is read, the returned future blocks the
+    /// executor until the compilation of the remaining functions is
+    /// done.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # async fn f(store: Store, stream: impl futures_io::AsyncRead + Unpin) -> Result<(), IoCompileError> {
+    /// let module = Module::new_streaming(&store, stream).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "streaming")]
+    pub async fn new_streaming<R>(store: &Store, mut reader: R) -> Result<Self, IoCompileError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buffer = vec![0; STREAMING_CHUNK_SIZE];
+        let tunables = store.shared_tunables();
+        let mut compilation = match store.engine().compile_streaming(tunables) {
+            Some(compilation) => compilation,
+            None => {
+                let mut binary = Vec::new();
+                loop {
+                    let read = ReadChunk::new(&mut reader, &mut buffer).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    binary.extend_from_slice(&buffer[..read]);
+                }
+                return Ok(Self::from_binary(store, &binary)?);
+            }
+        };
+
+        #[cfg(feature = "module-hash")]
+        let mut hasher = blake3::Hasher::new();
+        loop {
+            let read = ReadChunk::new(&mut reader, &mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            #[cfg(feature = "module-hash")]
+            hasher.update(&buffer[..read]);
+            compilation.feed(&buffer[..read])?;
+        }
+        let artifact = compilation.finish()?;
+        let module = Self::from_artifact(store, artifact);
+        #[cfg(feature = "module-hash")]
+        let module = module.with_hash(hasher.finalize());
+        Ok(module)
     }
 
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
         let artifact = store.engine().compile(binary, store.tunables())?;
-        Ok(Self::from_artifact(store, artifact))
+        let module = Self::from_artifact(store, artifact);
+        #[cfg(feature = "module-hash")]
+        let module = module.with_hash(blake3::hash(binary));
+        Ok(module)
     }
 
     /// Serializes a module into a binary representation that the `Engine`
     /// can later process via [`Module::deserialize`].
     ///
+    /// Compiling the same binary with the same engine, compiler
+    /// configuration and features serializes to byte-identical
+    /// artifacts, even across runs of the process: the compilers make
+    /// no nondeterministic choices, and the maps of the module are
+    /// serialized in the order of their keys. There is no seed to set
+    /// for reproducible artifacts.
+    ///
     /// # Usage
     ///
     /// ```ignore
@@ -227,6 +403,11 @@ impl Module {
     ///
     /// And as such, the `deserialize` method is unsafe.
     ///
+    /// The module must have been compiled for CPU features the host
+    /// supports, otherwise [`DeserializeError::IncompatibleTarget`] is
+    /// returned. See [`Module::deserialize_unchecked_target`] to skip
+    /// this check.
+    ///
     /// # Usage
     ///
     /// ```ignore
@@ -238,6 +419,26 @@ impl Module {
     /// # }
     /// ```
     pub unsafe fn deserialize(store: &Store, bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let module = Self::deserialize_unchecked_target(store, bytes)?;
+        module.check_target()?;
+        Ok(module)
+    }
+
+    /// Deserializes a serialized Module binary into a `Module`, like
+    /// [`Module::deserialize`], without checking that the host supports
+    /// the CPU features the module was compiled for.
+    ///
+    /// The module can be inspected, or serialized again, but
+    /// [`Instance::new`](crate::Instance::new) still fails with
+    /// [`InstantiationError::CpuFeature`] on such a host.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize`].
+    pub unsafe fn deserialize_unchecked_target(
+        store: &Store,
+        bytes: &[u8],
+    ) -> Result<Self, DeserializeError> {
         let artifact = store.engine().deserialize(bytes)?;
         Ok(Self::from_artifact(store, artifact))
     }
@@ -264,7 +465,23 @@ impl Module {
         path: impl AsRef<Path>,
     ) -> Result<Self, DeserializeError> {
         let artifact = store.engine().deserialize_from_file(path.as_ref())?;
-        Ok(Self::from_artifact(store, artifact))
+        let module = Self::from_artifact(store, artifact);
+        module.check_target()?;
+        Ok(module)
+    }
+
+    /// Check that the host supports the CPU features the module was
+    /// compiled for.
+    fn check_target(&self) -> Result<(), DeserializeError> {
+        let required = self.artifact.cpu_features();
+        let available = CpuFeature::for_host();
+        if !available.is_superset(required) {
+            return Err(DeserializeError::IncompatibleTarget {
+                required,
+                available,
+            });
+        }
+        Ok(())
     }
 
     fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
@@ -274,29 +491,111 @@ impl Module {
         }
     }
 
+    /// Records the hash of the binary in the info of the module, so
+    /// that it is serialized with it.
+    #[cfg(feature = "module-hash")]
+    fn with_hash(mut self, hash: blake3::Hash) -> Self {
+        if let Some(module_info) =
+            Arc::get_mut(&mut self.artifact).and_then(|artifact| artifact.module_mut())
+        {
+            module_info.hash = Some(hash.to_hex().to_string());
+        }
+        self
+    }
+
+    /// Instantiates this module like [`Instance::new`], and returns
+    /// the time the instantiation took.
+    ///
+    /// The time covers the resolution of the imports, the creation
+    /// of the memories, tables and globals, the initialization of the
+    /// data and element segments, and the `start` function, but not
+    /// the compilation of the module, reported by
+    /// [`Module::new_with_metrics`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module (memory 1) (data (i32.const 0) "hello"))"#;
+    /// let (module, metrics) = Module::new_with_metrics(&store, wat)?;
+    /// let (instance, instantiation_time) = module.time_instantiation(&imports! {})?;
+    /// let total_time = metrics.compile_time + instantiation_time;
+    /// # assert!(total_time >= instantiation_time);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn time_instantiation(
+        &self,
+        resolver: &(dyn Resolver + Send + Sync),
+    ) -> Result<(Instance, Duration), InstantiationError> {
+        let start = Instant::now();
+        let instance = Instance::new(self, resolver)?;
+        Ok((instance, start.elapsed()))
+    }
+
     pub(crate) fn instantiate(
         &self,
         resolver: &dyn Resolver,
     ) -> Result<InstanceHandle, InstantiationError> {
+        let observer = self.store.instantiation_observer();
         unsafe {
             let instance_handle = self.artifact.instantiate(
                 self.store.tunables(),
                 resolver,
                 Box::new(self.clone()),
+                observer.as_deref(),
             )?;
 
+            // The instance keeps a clone of this module, and so of the
+            // store and its engine, alive: both atomics outlive it.
+            instance_handle.set_epoch(
+                self.store.epoch_counter_ptr(),
+                self.store.epoch_deadline_ptr(),
+            );
+            instance_handle.set_call_depth_left(self.store.call_depth_left_ptr());
+            #[cfg(feature = "memory-access-tracing")]
+            instance_handle.set_memory_access_tracer(self.store.memory_access_tracer());
+            self.track_externref_roots(&instance_handle);
+
             // After the instance handle is created, we need to initialize
             // the data, call the start function and so. However, if any
             // of this steps traps, we still need to keep the instance alive
             // as some of the Instance elements may have placed in other
             // instance tables.
-            self.artifact
-                .finish_instantiation(&self.store, &instance_handle)?;
+            let _call_depth_guard = self.store.enter_call_depth();
+            self.artifact.finish_instantiation(
+                &self.store,
+                &instance_handle,
+                observer.as_deref(),
+            )?;
 
             Ok(instance_handle)
         }
     }
 
+    /// Tracks the tables and globals of a new instance, see
+    /// [`Store::externref_stats`].
+    fn track_externref_roots(&self, instance_handle: &InstanceHandle) {
+        let info = self.artifact.module_ref();
+        let mut roots = self.store.externref_roots().lock().unwrap();
+        for index in info.tables.keys() {
+            if let VMExtern::Table(table) =
+                instance_handle.lookup_by_declaration(&ExportIndex::Table(index))
+            {
+                roots.add_table(&table.from);
+            }
+        }
+        for index in info.globals.keys() {
+            if let VMExtern::Global(global) =
+                instance_handle.lookup_by_declaration(&ExportIndex::Global(index))
+            {
+                roots.add_global(&global.from);
+            }
+        }
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
@@ -318,6 +617,36 @@ impl Module {
         self.artifact.module_ref().name.as_deref()
     }
 
+    /// Returns the hexadecimal BLAKE3 hash of the binary this module
+    /// was compiled from.
+    ///
+    /// The hash is computed when the module is compiled with the
+    /// `module-hash` feature, and serialized with the module, so that
+    /// a deserialized module has the hash of its original binary.
+    /// Otherwise `None` is returned. The `Dylib` and `Staticlib`
+    /// engines write the info of the module in the object file they
+    /// compile, before the hash is known: their deserialized modules
+    /// have no hash.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// assert_eq!(module.hash().map(str::len), Some(64));
+    ///
+    /// let serialized = module.serialize()?;
+    /// let deserialized = unsafe { Module::deserialize(&store, &serialized)? };
+    /// assert_eq!(deserialized.hash(), module.hash());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hash(&self) -> Option<&str> {
+        self.artifact.module_ref().hash.as_deref()
+    }
+
     /// Sets the name of the current module.
     /// This is normally useful for stacktraces and debugging.
     ///
@@ -404,6 +733,89 @@ impl Module {
         self.artifact.module_ref().exports()
     }
 
+    /// Returns the memories imported by the module, with the types
+    /// they must satisfy, see [`Memory::new_for_import`].
+    ///
+    /// [`Memory::new_for_import`]: crate::Memory::new_for_import
+    pub fn imported_memories<'a>(&'a self) -> impl Iterator<Item = ImportType<MemoryType>> + 'a {
+        self.imports().filter_map(|import| {
+            let ty = *import.ty().memory()?;
+            Some(ImportType::new(import.module(), import.name(), ty))
+        })
+    }
+
+    /// Returns the tables imported by the module, with the types they
+    /// must satisfy, see [`Table::new_for_import`].
+    ///
+    /// [`Table::new_for_import`]: crate::Table::new_for_import
+    pub fn imported_tables<'a>(&'a self) -> impl Iterator<Item = ImportType<TableType>> + 'a {
+        self.imports().filter_map(|import| {
+            let ty = *import.ty().table()?;
+            Some(ImportType::new(import.module(), import.name(), ty))
+        })
+    }
+
+    /// Returns the distinct function types declared in the type
+    /// section of the module, in the order of their first declaration.
+    ///
+    /// The signatures shared by several imports, exports or functions
+    /// appear once, so binding generators can declare them once and
+    /// reference them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (type (func (param i32) (result i32)))
+    ///     (type (func))
+    ///     (type (func (param i32) (result i32)))
+    ///     (import "host" "double" (func (type 2)))
+    ///     (func (export "run") (type 1))
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(
+    ///     module.types(),
+    ///     vec![
+    ///         FunctionType::new(vec![Type::I32], vec![Type::I32]),
+    ///         FunctionType::new(vec![], vec![]),
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn types(&self) -> Vec<FunctionType> {
+        let types: IndexSet<&FunctionType> = self.info().signatures.values().collect();
+        types.into_iter().cloned().collect()
+    }
+
+    /// Returns the import contract of the module: its imports and its
+    /// exports, with their types, see [`ImportContract`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (import "host" "log" (func (param i32)))
+    ///     (memory (export "memory") 1)
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let contract = module.export_import_contract();
+    /// assert_eq!(contract.imports[0].name, "log");
+    /// # #[cfg(feature = "enable-serde")]
+    /// assert_eq!(ImportContract::from_json(&contract.to_json())?, contract);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_import_contract(&self) -> ImportContract {
+        ImportContract::new(self.imports(), self.exports())
+    }
+
     /// Get the custom sections of the module given a `name`.
     ///
     /// # Important
@@ -415,6 +827,69 @@ impl Module {
         self.artifact.module_ref().custom_sections(name)
     }
 
+    /// Returns the performance diagnostics reported by the compiler
+    /// for the functions of the module.
+    ///
+    /// Diagnostics are only reported when enabled with
+    /// [`CompilerConfig::enable_perf_diagnostics`], and are not kept
+    /// when the module is serialized.
+    ///
+    /// [`CompilerConfig::enable_perf_diagnostics`]: crate::CompilerConfig::enable_perf_diagnostics
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// for diagnostic in module.perf_diagnostics() {
+    ///     println!("{}: {}", diagnostic.code, diagnostic.message);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn perf_diagnostics(&self) -> &[PerfDiagnostic] {
+        self.artifact.perf_diagnostics()
+    }
+
+    /// Returns the stack maps of a function of the module, describing
+    /// the stack slots holding live references at each of its calls.
+    ///
+    /// Stack maps are only emitted when enabled with
+    /// [`CompilerConfig::enable_stack_maps`], and are not kept when the
+    /// module is serialized.
+    ///
+    /// [`CompilerConfig::enable_stack_maps`]: crate::CompilerConfig::enable_stack_maps
+    pub fn stack_maps(&self, function: LocalFunctionIndex) -> &[StackMap] {
+        self.artifact.stack_maps(function)
+    }
+
+    /// Returns the exact binary the module was compiled from.
+    ///
+    /// The binary is only kept when enabled with
+    /// [`CompilerConfig::retain_wasm_bytes`], in which case it is
+    /// serialized with the module and available after
+    /// deserialization too. Modules compiled from the text format
+    /// return the binary it was converted to.
+    ///
+    /// [`CompilerConfig::retain_wasm_bytes`]: crate::CompilerConfig::retain_wasm_bytes
+    pub fn wasm_bytes(&self) -> Option<&[u8]> {
+        self.artifact.wasm_bytes()
+    }
+
+    /// Returns the features the module requires.
+    ///
+    /// Unlike `features_required`, which scans the binary of the
+    /// module, this works for modules that have been deserialized, but
+    /// only knows about the features found in the types, memories,
+    /// tables and segments of the module: the proposals only used by
+    /// instructions, like the atomics of the threads proposal or tail
+    /// calls, are not reported.
+    pub fn required_features(&self) -> Features {
+        self.info().required_features()
+    }
+
     /// Returns the [`Store`] where the `Instance` belongs.
     pub fn store(&self) -> &Store {
         &self.store
@@ -441,6 +916,34 @@ impl Module {
     }
 }
 
+/// The size of the reads done by [`Module::new_streaming`].
+#[cfg(feature = "streaming")]
+const STREAMING_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A future reading the next bytes available in an [`AsyncRead`].
+#[cfg(feature = "streaming")]
+struct ReadChunk<'a, R> {
+    reader: &'a mut R,
+    buffer: &'a mut [u8],
+}
+
+#[cfg(feature = "streaming")]
+impl<'a, R> ReadChunk<'a, R> {
+    fn new(reader: &'a mut R, buffer: &'a mut [u8]) -> Self {
+        Self { reader, buffer }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<R: AsyncRead + Unpin> Future for ReadChunk<'_, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        Pin::new(&mut *this.reader).poll_read(cx, this.buffer)
+    }
+}
+
 impl fmt::Debug for Module {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Module")
@@ -119,6 +119,7 @@ where
         Self {
             store: other.store,
             exported: other.exported,
+            env: None,
         }
     }
 }
@@ -153,6 +154,8 @@ macro_rules! impl_native_traits {
                         }
                         rets_list.as_mut()
                     };
+                    let _guard = self.store.enter_instance(&self.exported.vm_function.instance_ref)?;
+                    let _call_depth_guard = self.store.enter_call_depth();
                     unsafe {
                         wasmer_vm::wasmer_call_trampoline(
                             &self.store,
@@ -230,7 +233,8 @@ macro_rules! impl_native_traits {
         {
             fn get_self_from_extern_with_generics(_extern: &crate::sys::externals::Extern) -> Result<Self, crate::sys::exports::ExportError> {
                 use crate::sys::exports::Exportable;
-                crate::Function::get_self_from_extern(_extern)?.native().map_err(|_| crate::sys::exports::ExportError::IncompatibleType)
+                let function = crate::Function::get_self_from_extern(_extern)?;
+                function.native().map_err(|_| crate::sys::exports::ExportError::incompatible_native::<( $( $x ),* ), Rets>(function))
             }
 
             fn into_weak_instance_ref(&mut self) {
@@ -0,0 +1,202 @@
+//! Compilation of untrusted WebAssembly modules in a separate process.
+//!
+//! Compiling a module runs a lot of code over attacker-controlled
+//! input. [`sandboxed_compile`] moves that work into a forked child
+//! process with resource limits applied, so that a compiler crash,
+//! an out-of-memory condition or a runaway compilation takes down the
+//! child rather than the host.
+
+use crate::sys::module::Module;
+use crate::sys::store::Store;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::FromRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Resource limits applied to the process compiling a module with
+/// [`sandboxed_compile`].
+///
+/// A limit set to `None` is inherited from the host process.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxLimits {
+    /// The maximum size of the address space of the compiler
+    /// process, in bytes.
+    pub max_memory: Option<u64>,
+    /// The maximum CPU time the compiler process may consume.
+    pub max_cpu_time: Option<Duration>,
+}
+
+/// An error produced by [`sandboxed_compile`].
+#[derive(Error, Debug)]
+pub enum SandboxError {
+    /// The module failed to compile or serialize.
+    #[error("compilation failed: {0}")]
+    Compile(String),
+    /// The compiler process was killed by a signal, e.g. because it
+    /// crashed or exceeded one of its resource limits.
+    #[error("the compiler process was terminated by signal {0}")]
+    Signaled(i32),
+    /// The compiler process exited without producing an artifact.
+    #[error("the compiler process exited unexpectedly with status {0}")]
+    Exited(i32),
+    /// An IO error while setting up or talking to the compiler process.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Status byte written by the child before the payload.
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+
+/// Compiles `bytes` with the engine of `store` in a forked child
+/// process, and returns the serialized artifact.
+///
+/// The result can be loaded with [`Module::deserialize`] on a store
+/// using a compatible engine. Any failure in the child, including a
+/// crash of the compiler itself, is reported as a [`SandboxError`]
+/// instead of affecting the calling process.
+///
+/// # Caveats
+///
+/// The child is created with `fork`, so only the calling thread is
+/// duplicated. The compilers, whose thread pools aren't duplicated
+/// either, compile the functions on a single thread in the child.
+/// Compilation must not depend on other locks held by other threads
+/// of the host at the time of the call, such as the ones of the
+/// middlewares.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::*;
+/// # use std::time::Duration;
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let limits = SandboxLimits {
+///     max_cpu_time: Some(Duration::from_secs(10)),
+///     ..Default::default()
+/// };
+/// let artifact = sandboxed_compile(&store, "(module)", &limits)?;
+/// let module = unsafe { Module::deserialize(&store, &artifact)? };
+/// # Ok(())
+/// # }
+/// ```
+pub fn sandboxed_compile(
+    store: &Store,
+    bytes: impl AsRef<[u8]>,
+    limits: &SandboxLimits,
+) -> Result<Vec<u8>, SandboxError> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    match unsafe { libc::fork() } {
+        -1 => {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::close(read_fd);
+                libc::close(write_fd);
+            }
+            Err(error.into())
+        }
+        0 => {
+            unsafe { libc::close(read_fd) };
+            let output = unsafe { File::from_raw_fd(write_fd) };
+            // A panic must not unwind into the code of the host, which
+            // would go on running in the child.
+            let status = panic::catch_unwind(AssertUnwindSafe(|| {
+                compile_in_child(store, bytes.as_ref(), limits, output)
+            }))
+            .unwrap_or(1);
+            unsafe { libc::_exit(status) }
+        }
+        pid => {
+            unsafe { libc::close(write_fd) };
+            let mut input = unsafe { File::from_raw_fd(read_fd) };
+            let mut output = vec![];
+            let read = input.read_to_end(&mut output);
+            drop(input);
+            let status = wait_for_child(pid)?;
+            read?;
+
+            if libc::WIFSIGNALED(status) {
+                return Err(SandboxError::Signaled(libc::WTERMSIG(status)));
+            }
+            match output.split_first() {
+                Some((&STATUS_OK, artifact)) if libc::WEXITSTATUS(status) == 0 => {
+                    Ok(artifact.to_vec())
+                }
+                Some((&STATUS_ERROR, message)) => Err(SandboxError::Compile(
+                    String::from_utf8_lossy(message).into_owned(),
+                )),
+                _ => Err(SandboxError::Exited(libc::WEXITSTATUS(status))),
+            }
+        }
+    }
+}
+
+/// Runs in the child process: applies the limits, compiles the module
+/// and writes the outcome to `output`. Returns the exit status.
+fn compile_in_child(store: &Store, bytes: &[u8], limits: &SandboxLimits, mut output: File) -> i32 {
+    if let Err(e) = apply_limits(limits) {
+        let _ = output.write_all(&[STATUS_ERROR]);
+        let _ = output.write_all(format!("failed to apply resource limits: {}", e).as_bytes());
+        return 1;
+    }
+
+    let (status, payload) = match Module::new(store, bytes)
+        .map_err(|e| e.to_string())
+        .and_then(|module| module.serialize().map_err(|e| e.to_string()))
+    {
+        Ok(artifact) => (STATUS_OK, artifact),
+        Err(message) => (STATUS_ERROR, message.into_bytes()),
+    };
+    match output
+        .write_all(&[status])
+        .and_then(|_| output.write_all(&payload))
+    {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn apply_limits(limits: &SandboxLimits) -> io::Result<()> {
+    if let Some(max_memory) = limits.max_memory {
+        if unsafe { libc::setrlimit(libc::RLIMIT_AS, &rlimit(max_memory)) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    if let Some(max_cpu_time) = limits.max_cpu_time {
+        // The limit has a granularity of one second; round up so that
+        // sub-second limits don't turn into "no time at all".
+        let seconds = max_cpu_time.as_secs() + u64::from(max_cpu_time.subsec_nanos() > 0);
+        if unsafe { libc::setrlimit(libc::RLIMIT_CPU, &rlimit(seconds)) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn rlimit(value: u64) -> libc::rlimit {
+    libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    }
+}
+
+fn wait_for_child(pid: libc::pid_t) -> io::Result<libc::c_int> {
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+            return Ok(status);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
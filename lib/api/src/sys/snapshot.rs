@@ -0,0 +1,395 @@
+//! Snapshots of the mutable state of an [`Instance`], see
+//! [`Instance::snapshot`] and [`Instance::restore`].
+
+use crate::sys::externals::{Extern, Function, Global, Memory, Table};
+use crate::sys::instance::Instance;
+use crate::sys::types::Val;
+use crate::sys::{MemoryError, Mutability, RuntimeError, Type};
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wasmer_types::{
+    ExportIndex, ExternRef, FunctionIndex, GlobalIndex, MemoryIndex, Pages, TableIndex,
+    WASM_PAGE_SIZE,
+};
+
+/// The mutable state of an [`Instance`]: the contents of its linear
+/// memories and tables, and the values of its mutable globals, as
+/// returned by [`Instance::snapshot`].
+///
+/// With the `enable-serde` feature, a snapshot can be serialized with
+/// `serde` to be persisted, except for the [`ExternRef`]s it holds,
+/// which only live as long as the process: a deserialized snapshot
+/// holding non-null `externref`s can't be restored.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct InstanceSnapshot {
+    /// The contents of the memories, by memory index.
+    pub memories: Vec<Vec<u8>>,
+    /// The elements of the tables, by table index.
+    pub tables: Vec<Vec<SnapshotValue>>,
+    /// The values of the mutable globals, with their global index.
+    pub globals: Vec<(u32, SnapshotValue)>,
+    /// The non-null `externref`s referred to by
+    /// [`SnapshotValue::ExternRef`].
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    externrefs: Vec<ExternRef>,
+}
+
+/// A value held by a table or a global, in an [`InstanceSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum SnapshotValue {
+    /// A 32-bit integer.
+    I32(i32),
+    /// A 64-bit integer.
+    I64(i64),
+    /// A 32-bit float.
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
+    /// A 128-bit vector.
+    V128(u128),
+    /// A function of the instance, by its index in the module,
+    /// imported functions included.
+    FuncRef(Option<u32>),
+    /// An `externref`, by its index in the references kept by the
+    /// snapshot.
+    ExternRef(Option<u32>),
+}
+
+/// An error while taking or restoring an [`InstanceSnapshot`].
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// A table element or a global holds a function which isn't one
+    /// of the functions of the instance, and can't be referred to by
+    /// index.
+    #[error("{0} holds a function from outside the instance")]
+    ForeignFunction(String),
+
+    /// The snapshot doesn't match the memories, tables or globals of
+    /// the instance it's restored to.
+    #[error("the snapshot doesn't match the instance: {0}")]
+    Mismatch(String),
+
+    /// The snapshot refers to an `externref` it doesn't hold anymore,
+    /// because it was deserialized, with the `enable-serde` feature.
+    #[error("the snapshot holds an `externref` which didn't survive its serialization")]
+    MissingExternRef,
+
+    /// A memory couldn't grow back to its size in the snapshot.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+
+    /// A table or a global couldn't be written.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl Instance {
+    /// Captures the mutable state of this instance: the contents of
+    /// its linear memories and tables, and the values of its mutable
+    /// globals, whether they are exported or not.
+    ///
+    /// Imported memories, tables and globals are captured too. When
+    /// they are shared with other instances or with the host, they
+    /// are captured by value at the time of the snapshot: restoring
+    /// it overwrites them for everyone sharing them.
+    ///
+    /// # Errors
+    ///
+    /// Tables and globals can only hold the functions of the
+    /// instance, which are captured by index, and the snapshot fails
+    /// with [`SnapshotError::ForeignFunction`] otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Instance, Module, NativeFunc, Store, imports};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, r#"(module
+    ///     (global $counter (mut i32) (i32.const 0))
+    ///     (func (export "increment") (result i32)
+    ///         (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    ///         (global.get $counter)))"#)?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// let increment: NativeFunc<(), i32> = instance.exports.get_native_function("increment")?;
+    ///
+    /// let snapshot = instance.snapshot()?;
+    /// assert_eq!(increment.call()?, 1);
+    /// instance.restore(&snapshot)?;
+    /// assert_eq!(increment.call()?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        let info = self.module().info();
+        let functions = info
+            .functions
+            .keys()
+            .map(|index| self.function(index))
+            .collect::<Vec<_>>();
+        let mut snapshot = InstanceSnapshot {
+            memories: Vec::with_capacity(info.memories.len()),
+            tables: Vec::with_capacity(info.tables.len()),
+            globals: Vec::new(),
+            externrefs: Vec::new(),
+        };
+
+        for index in info.memories.keys() {
+            let memory = self.memory(index);
+            snapshot
+                .memories
+                .push(unsafe { memory.data_unchecked() }.to_vec());
+        }
+
+        for index in info.tables.keys() {
+            let table = self.table(index);
+            let mut elements = Vec::with_capacity(table.size() as usize);
+            for element in 0..table.size() {
+                let value = table.get(element).expect("in bounds");
+                let value = snapshot.capture(value, &functions).ok_or_else(|| {
+                    SnapshotError::ForeignFunction(format!(
+                        "element {} of table {}",
+                        element,
+                        index.as_u32()
+                    ))
+                })?;
+                elements.push(value);
+            }
+            snapshot.tables.push(elements);
+        }
+
+        for (index, ty) in info.globals.iter() {
+            if ty.mutability == Mutability::Var {
+                let value = self.global(index).get();
+                let value = snapshot.capture(value, &functions).ok_or_else(|| {
+                    SnapshotError::ForeignFunction(format!("global {}", index.as_u32()))
+                })?;
+                snapshot.globals.push((index.as_u32(), value));
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Writes back the state captured by [`Instance::snapshot`] into
+    /// the memories, tables and mutable globals of this instance, or
+    /// of another instance of the same module.
+    ///
+    /// Memories and tables which have grown since the snapshot
+    /// can't shrink back, and are reported as a
+    /// [`SnapshotError::Mismatch`], while the ones which are smaller
+    /// are grown to their size in the snapshot. The snapshot is
+    /// checked against the instance before anything is written.
+    pub fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        let info = self.module().info();
+        let functions = info
+            .functions
+            .keys()
+            .map(|index| self.function(index))
+            .collect::<Vec<_>>();
+        if snapshot.memories.len() != info.memories.len()
+            || snapshot.tables.len() != info.tables.len()
+        {
+            return Err(SnapshotError::Mismatch(format!(
+                "the snapshot has {} memories and {} tables, the instance {} and {}",
+                snapshot.memories.len(),
+                snapshot.tables.len(),
+                info.memories.len(),
+                info.tables.len()
+            )));
+        }
+
+        let memories = info
+            .memories
+            .keys()
+            .zip(&snapshot.memories)
+            .map(|(index, data)| {
+                let memory = self.memory(index);
+                if data.len() % WASM_PAGE_SIZE != 0 {
+                    return Err(SnapshotError::Mismatch(format!(
+                        "memory {} isn't made of whole pages",
+                        index.as_u32()
+                    )));
+                }
+                let pages = Pages((data.len() / WASM_PAGE_SIZE) as u32);
+                if memory.size() > pages {
+                    return Err(SnapshotError::Mismatch(format!(
+                        "memory {} has grown to {} pages since the snapshot of {} pages",
+                        index.as_u32(),
+                        memory.size().0,
+                        pages.0
+                    )));
+                }
+                Ok((memory, pages, data))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tables = info
+            .tables
+            .iter()
+            .zip(&snapshot.tables)
+            .map(|((index, ty), elements)| {
+                let table = self.table(index);
+                if table.size() as usize > elements.len() {
+                    return Err(SnapshotError::Mismatch(format!(
+                        "table {} has grown to {} elements since the snapshot of {}",
+                        index.as_u32(),
+                        table.size(),
+                        elements.len()
+                    )));
+                }
+                let elements = elements
+                    .iter()
+                    .map(|value| snapshot.value(*value, ty.ty, &functions))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((table, ty.ty, elements))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let globals = snapshot
+            .globals
+            .iter()
+            .map(|(index, value)| {
+                let index = GlobalIndex::from_u32(*index);
+                match info.globals.get(index) {
+                    Some(ty) if ty.mutability == Mutability::Var => Ok((
+                        self.global(index),
+                        snapshot.value(*value, ty.ty, &functions)?,
+                    )),
+                    _ => Err(SnapshotError::Mismatch(format!(
+                        "global {} isn't a mutable global of the instance",
+                        index.as_u32()
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (memory, pages, data) in memories {
+            if memory.size() < pages {
+                memory.grow(pages - memory.size())?;
+            }
+            unsafe { memory.data_unchecked_mut() }.copy_from_slice(data);
+        }
+        for (table, ty, elements) in tables {
+            let size = table.size();
+            if (size as usize) < elements.len() {
+                table.grow(elements.len() as u32 - size, null(ty))?;
+            }
+            for (index, value) in elements.into_iter().enumerate() {
+                table.set(index as u32, value)?;
+            }
+        }
+        for (global, value) in globals {
+            global.set(value)?;
+        }
+        Ok(())
+    }
+
+    fn function(&self, index: FunctionIndex) -> Function {
+        match self.lookup_by_declaration(&ExportIndex::Function(index)) {
+            Extern::Function(function) => function,
+            _ => unreachable!("function index refers to a function"),
+        }
+    }
+
+    fn memory(&self, index: MemoryIndex) -> Memory {
+        match self.lookup_by_declaration(&ExportIndex::Memory(index)) {
+            Extern::Memory(memory) => memory,
+            _ => unreachable!("memory index refers to a memory"),
+        }
+    }
+
+    fn table(&self, index: TableIndex) -> Table {
+        match self.lookup_by_declaration(&ExportIndex::Table(index)) {
+            Extern::Table(table) => table,
+            _ => unreachable!("table index refers to a table"),
+        }
+    }
+
+    fn global(&self, index: GlobalIndex) -> Global {
+        match self.lookup_by_declaration(&ExportIndex::Global(index)) {
+            Extern::Global(global) => global,
+            _ => unreachable!("global index refers to a global"),
+        }
+    }
+}
+
+impl InstanceSnapshot {
+    /// Captures `value`, keeping its `externref` if it's one, or
+    /// returns `None` for a function which isn't one of `functions`.
+    fn capture(&mut self, value: Val, functions: &[Function]) -> Option<SnapshotValue> {
+        Some(match value {
+            Val::I32(value) => SnapshotValue::I32(value),
+            Val::I64(value) => SnapshotValue::I64(value),
+            Val::F32(value) => SnapshotValue::F32(value),
+            Val::F64(value) => SnapshotValue::F64(value),
+            Val::V128(value) => SnapshotValue::V128(value),
+            Val::FuncRef(None) => SnapshotValue::FuncRef(None),
+            Val::FuncRef(Some(function)) => {
+                let index = functions.iter().position(|other| same(other, &function))?;
+                SnapshotValue::FuncRef(Some(index as u32))
+            }
+            Val::ExternRef(extern_ref) if extern_ref.is_null() => SnapshotValue::ExternRef(None),
+            Val::ExternRef(extern_ref) => {
+                self.externrefs.push(extern_ref);
+                SnapshotValue::ExternRef(Some(self.externrefs.len() as u32 - 1))
+            }
+        })
+    }
+
+    /// Converts back the captured `value`, checking it has type `ty`.
+    fn value(
+        &self,
+        value: SnapshotValue,
+        ty: Type,
+        functions: &[Function],
+    ) -> Result<Val, SnapshotError> {
+        let value = match value {
+            SnapshotValue::I32(value) => Val::I32(value),
+            SnapshotValue::I64(value) => Val::I64(value),
+            SnapshotValue::F32(value) => Val::F32(value),
+            SnapshotValue::F64(value) => Val::F64(value),
+            SnapshotValue::V128(value) => Val::V128(value),
+            SnapshotValue::FuncRef(None) => Val::FuncRef(None),
+            SnapshotValue::FuncRef(Some(index)) => {
+                let function = functions.get(index as usize).ok_or_else(|| {
+                    SnapshotError::Mismatch(format!("the instance has no function {}", index))
+                })?;
+                Val::FuncRef(Some(function.clone()))
+            }
+            SnapshotValue::ExternRef(None) => Val::ExternRef(ExternRef::null()),
+            SnapshotValue::ExternRef(Some(index)) => Val::ExternRef(
+                self.externrefs
+                    .get(index as usize)
+                    .cloned()
+                    .ok_or(SnapshotError::MissingExternRef)?,
+            ),
+        };
+        if value.ty() != ty {
+            return Err(SnapshotError::Mismatch(format!(
+                "a value of type {} is restored where a {} is expected",
+                value.ty(),
+                ty
+            )));
+        }
+        Ok(value)
+    }
+}
+
+/// Whether `a` and `b` are the same function, whichever way they
+/// were obtained.
+fn same(a: &Function, b: &Function) -> bool {
+    a.exported.vm_function.address == b.exported.vm_function.address
+        && a.exported.vm_function.vmctx == b.exported.vm_function.vmctx
+}
+
+/// The null reference of the reference type `ty`.
+fn null(ty: Type) -> Val {
+    match ty {
+        Type::ExternRef => Val::ExternRef(ExternRef::null()),
+        _ => Val::FuncRef(None),
+    }
+}
@@ -1,11 +1,25 @@
-use crate::sys::tunables::BaseTunables;
+use crate::sys::externref_stats::ExternRefRoots;
+use crate::sys::tunables::{BaseTunables, LimitingTunables};
+use crate::sys::RuntimeError;
 use loupe::MemoryUsage;
+use std::convert::TryFrom;
 use std::fmt;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
-use wasmer_engine::{Engine, Tunables};
-use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn};
+use wasmer_engine::{Engine, EngineEpochController, InstantiationObserver, Tunables};
+use wasmer_types::Features;
+#[cfg(feature = "memory-access-tracing")]
+use wasmer_vm::MemoryAccessTracer;
+use wasmer_vm::{
+    init_traps, CallInterrupt, EpochDeadline, GrowGuard, InstanceRef, ReentrancyGuard, TrapCode,
+    TrapHandler, TrapHandlerFn, WeakOrStrongInstanceRef,
+};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -20,9 +34,27 @@ use wasmer_vm::{init_traps, TrapHandler, TrapHandlerFn};
 #[derive(Clone, MemoryUsage)]
 pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
-    tunables: Arc<dyn Tunables + Send + Sync>,
+    features: Features,
+    tunables: Arc<LimitingTunables<dyn Tunables + Send + Sync>>,
     #[loupe(skip)]
     trap_handler: Arc<RwLock<Option<Box<TrapHandlerFn>>>>,
+    /// The epoch counter of the engine, or one following it.
+    epoch: EngineEpochController,
+    #[loupe(skip)]
+    epoch_deadline: Arc<EpochDeadline>,
+    #[loupe(skip)]
+    max_reentrancy_depth: Arc<AtomicUsize>,
+    #[loupe(skip)]
+    max_call_depth: Arc<AtomicU64>,
+    #[loupe(skip)]
+    call_depth_left: Arc<AtomicU64>,
+    #[loupe(skip)]
+    externref_roots: Arc<Mutex<ExternRefRoots>>,
+    #[loupe(skip)]
+    instantiation_observer: Arc<RwLock<Option<Arc<dyn InstantiationObserver>>>>,
+    #[cfg(feature = "memory-access-tracing")]
+    #[loupe(skip)]
+    memory_access_tracer: Arc<RwLock<Option<Arc<dyn MemoryAccessTracer>>>>,
 }
 
 impl Store {
@@ -34,12 +66,327 @@ impl Store {
         Self::new_with_tunables(engine, BaseTunables::for_target(engine.target()))
     }
 
+    /// Creates a new `Store` with a specific [`Engine`], whose epoch
+    /// starts at `initial_epoch`.
+    ///
+    /// The store has an epoch counter of its own, incremented along
+    /// with the one of the engine by the [`EngineEpochController`]
+    /// returned by [`Engine::epoch_controller`]. The epoch of the
+    /// engine and of its other stores is left as is.
+    ///
+    /// [`EngineEpochController`]: crate::EngineEpochController
+    pub fn new_with_epoch<E>(engine: &E, initial_epoch: u64) -> Self
+    where
+        E: Engine + ?Sized,
+    {
+        let mut store = Self::new(engine);
+        store.epoch = engine.epoch_controller().follower(initial_epoch);
+        store
+    }
+
+    /// Sets the epoch deadline of this store.
+    ///
+    /// Once the epoch counter of the store goes past the deadline,
+    /// code compiled with epoch interruption enabled (see
+    /// [`CompilerConfig::epoch_interruption`]) traps with
+    /// [`TrapCode::EpochDeadlineExceeded`] at the next function entry or
+    /// loop header. By default, a store has no deadline.
+    ///
+    /// The deadline applies to all the instances of this store,
+    /// including those created before the call.
+    ///
+    /// [`CompilerConfig::epoch_interruption`]: crate::CompilerConfig::epoch_interruption
+    /// [`TrapCode::EpochDeadlineExceeded`]: crate::TrapCode::EpochDeadlineExceeded
+    pub fn set_epoch_deadline(&self, epoch: u64) {
+        self.epoch_deadline.set(epoch);
+    }
+
+    /// Returns the epoch deadline of this store, see
+    /// [`Store::set_epoch_deadline`]. A store without a deadline
+    /// returns `u64::MAX`.
+    pub fn epoch_deadline(&self) -> u64 {
+        self.epoch_deadline.get()
+    }
+
+    /// Sets the epoch deadline of this store `ticks` epochs after its
+    /// current epoch, see [`Store::set_epoch_deadline`].
+    ///
+    /// With a watchdog calling [`Engine::increment_epoch`] periodically,
+    /// the code running in this store is interrupted after `ticks` to
+    /// `ticks + 1` periods.
+    pub fn set_epoch_deadline_after(&self, ticks: u64) {
+        let current = self.epoch.current();
+        self.set_epoch_deadline(current.saturating_add(ticks));
+    }
+
+    /// Returns the current epoch of this store: the epoch of the
+    /// engine, or of the counter following it if the store was
+    /// created with [`Store::new_with_epoch`].
+    pub fn current_epoch(&self) -> u64 {
+        self.epoch.current()
+    }
+
+    /// Returns a pointer to the epoch counter, to be handed to
+    /// instances.
+    pub(crate) fn epoch_counter_ptr(&self) -> *const AtomicU64 {
+        self.epoch.as_ptr()
+    }
+
+    /// Returns a pointer to the epoch deadline, to be handed to
+    /// instances.
+    pub(crate) fn epoch_deadline_ptr(&self) -> *const EpochDeadline {
+        &*self.epoch_deadline
+    }
+
+    /// Creates a flag interrupting a single call into this store, see
+    /// [`Function::call_interruptible`].
+    ///
+    /// [`Function::call_interruptible`]: crate::Function::call_interruptible
+    pub fn new_call_interrupt(&self) -> Arc<CallInterrupt> {
+        Arc::new(CallInterrupt::new(self.epoch_deadline.clone()))
+    }
+
+    /// Whether `interrupt` interrupts calls into this store.
+    pub(crate) fn is_call_interrupt_of(&self, interrupt: &CallInterrupt) -> bool {
+        Arc::ptr_eq(interrupt.deadline(), &self.epoch_deadline)
+    }
+
+    /// Runs `call`, interrupting it once `timeout` has elapsed, see
+    /// [`Function::call_with_timeout`].
+    ///
+    /// A watchdog thread interrupts the call through a
+    /// [`CallInterrupt`] when the timeout expires: neither the epoch
+    /// deadline of the store nor the epoch of the engine are changed.
+    /// The calls nested in `call` with timeouts of their own are
+    /// interrupted by whichever timeout expires first.
+    ///
+    /// [`Function::call_with_timeout`]: crate::Function::call_with_timeout
+    pub(crate) fn call_with_timeout<T>(
+        &self,
+        timeout: Duration,
+        call: impl FnOnce() -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        let interrupt = self.new_call_interrupt();
+        let (done, watchdog_done) = mpsc::channel::<()>();
+        let watchdog = {
+            let interrupt = interrupt.clone();
+            thread::spawn(move || {
+                watchdog_done.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout)
+                    && interrupt.interrupt()
+            })
+        };
+
+        let result = interrupt.run(call);
+        drop(done);
+        let expired = watchdog.join().unwrap();
+        match result {
+            Err(error) if expired && is_epoch_trap(&error) => {
+                Err(RuntimeError::user(Box::new(CallTimeout { timeout })))
+            }
+            result => result,
+        }
+    }
+
+    /// Limits the number of calls into the same instance that can be
+    /// active at once.
+    ///
+    /// A host function called by an instance may call back into it,
+    /// which nests a new call into the instance. Once `depth` calls
+    /// are nested, calling into the instance again fails with
+    /// [`TrapCode::ReentrancyLimitExceeded`] instead of exhausting the
+    /// stack. By default, there is no limit.
+    ///
+    /// The limit applies to all the instances of this store, see
+    /// [`Instance::reentrancy_depth`].
+    ///
+    /// [`TrapCode::ReentrancyLimitExceeded`]: crate::TrapCode::ReentrancyLimitExceeded
+    /// [`Instance::reentrancy_depth`]: crate::Instance::reentrancy_depth
+    pub fn max_reentrancy_depth(&self, depth: usize) {
+        self.max_reentrancy_depth.store(depth, SeqCst);
+    }
+
+    /// Limits the number of wasm frames that can be active at once
+    /// in this store.
+    ///
+    /// Code compiled with the call depth limit enabled (see
+    /// [`CompilerConfig::call_depth_limit`]) counts the wasm functions
+    /// being executed, and traps with [`TrapCode::CallDepthExceeded`]
+    /// when calling a function would make `depth + 1` of them active.
+    /// Unlike the size of the native stack, the limit doesn't depend
+    /// on the compiler or on the platform. By default, there is no
+    /// limit.
+    ///
+    /// The frames are counted across all the instances of this store,
+    /// including the ones created before the call. Calls into the
+    /// store are expected to be made from one thread at a time.
+    ///
+    /// [`CompilerConfig::call_depth_limit`]: crate::CompilerConfig::call_depth_limit
+    /// [`TrapCode::CallDepthExceeded`]: crate::TrapCode::CallDepthExceeded
+    pub fn max_call_depth(&self, depth: u64) {
+        let active = self.active_call_depth();
+        self.max_call_depth.store(depth, SeqCst);
+        self.call_depth_left
+            .store(depth.saturating_sub(active), SeqCst);
+    }
+
+    /// Returns the number of wasm frames active in this store, as
+    /// counted by code compiled with the call depth limit enabled.
+    fn active_call_depth(&self) -> u64 {
+        self.max_call_depth
+            .load(SeqCst)
+            .saturating_sub(self.call_depth_left.load(SeqCst))
+    }
+
+    /// Returns a pointer to the call depth left, to be handed to
+    /// instances.
+    pub(crate) fn call_depth_left_ptr(&self) -> *const AtomicU64 {
+        &*self.call_depth_left
+    }
+
+    /// Records the call depth when calling into wasm, and restores it
+    /// when the returned guard is dropped.
+    ///
+    /// The frames unwound by a trap don't return, and so aren't
+    /// removed from the count by the compiled code itself.
+    pub(crate) fn enter_call_depth(&self) -> CallDepthGuard<'_> {
+        CallDepthGuard {
+            store: self,
+            active: self.active_call_depth(),
+        }
+    }
+
+    /// Sets the size in bytes of the offset guard of the memories of
+    /// this store, overriding the one chosen by its [`Tunables`].
+    ///
+    /// The offset guard is an inaccessible region reserved after each
+    /// memory. Accesses landing in it trap with
+    /// [`TrapCode::HeapAccessOutOfBounds`], which lets compiled code
+    /// skip the bounds checks of accesses whose constant offset is
+    /// smaller than the guard. A larger guard helps code doing large
+    /// constant-offset accesses, at the cost of more reserved address
+    /// space per memory. The size is rounded up to a multiple of
+    /// [`WASM_PAGE_SIZE`].
+    ///
+    /// The size only applies to the modules compiled, and to the
+    /// memories created, after the call.
+    ///
+    /// [`TrapCode::HeapAccessOutOfBounds`]: crate::TrapCode::HeapAccessOutOfBounds
+    /// [`WASM_PAGE_SIZE`]: crate::WASM_PAGE_SIZE
+    pub fn memory_guard_size(&mut self, bytes: u64) {
+        self.tunables = Arc::new(self.tunables.with_offset_guard_size(bytes));
+    }
+
+    /// Installs a [`GrowGuard`] on the memories of this store.
+    ///
+    /// The guard is consulted before any of them grows, from the host
+    /// with [`Memory::grow`] or from a `memory.grow` instruction, and
+    /// can deny the growth even within the maximum of the memory. A
+    /// guard keeping its state behind a lock or atomics can enforce a
+    /// budget that changes at runtime.
+    ///
+    /// The guard only applies to the memories created after the call,
+    /// replacing the previous guard, if any.
+    ///
+    /// [`Memory::grow`]: crate::Memory::grow
+    pub fn set_grow_guard(&mut self, guard: Arc<dyn GrowGuard>) {
+        self.tunables = Arc::new(self.tunables.with_grow_guard(guard));
+    }
+
+    /// Limits the total size of the memories of this store to
+    /// `max_bytes`.
+    ///
+    /// Creating a memory, or instantiating a module defining one, fails
+    /// when its minimum doesn't fit in the limit. Growing a memory past
+    /// it fails with [`MemoryError::MemoryLimitExceeded`], and a
+    /// `memory.grow` instruction returns `-1` to the guest. A memory
+    /// stops counting against the limit once it's dropped.
+    ///
+    /// The limit only applies to the memories created after the call,
+    /// which share a new budget of `max_bytes`.
+    ///
+    /// [`MemoryError::MemoryLimitExceeded`]: crate::MemoryError::MemoryLimitExceeded
+    pub fn set_memory_limit(&mut self, max_bytes: u64) {
+        self.tunables = Arc::new(self.tunables.with_memory_limit(max_bytes));
+    }
+
+    /// Records a call into the instance owning a function, for as long
+    /// as the returned guard is alive.
+    ///
+    /// Host functions don't belong to an instance, and aren't tracked.
+    pub(crate) fn enter_instance(
+        &self,
+        instance_ref: &Option<WeakOrStrongInstanceRef>,
+    ) -> Result<Option<ReentrancyGuard>, RuntimeError> {
+        let instance_ref = match instance_ref
+            .clone()
+            .and_then(|instance_ref| InstanceRef::try_from(instance_ref).ok())
+        {
+            Some(instance_ref) => instance_ref,
+            None => return Ok(None),
+        };
+
+        instance_ref
+            .enter(self.max_reentrancy_depth.load(SeqCst))
+            .map(Some)
+            .map_err(RuntimeError::from_trap)
+    }
+
+    /// Returns the tables and globals of this store, which may hold
+    /// [`ExternRef`]s, see [`Store::externref_stats`].
+    ///
+    /// [`ExternRef`]: crate::ExternRef
+    pub(crate) fn externref_roots(&self) -> &Mutex<ExternRefRoots> {
+        &self.externref_roots
+    }
+
     /// Set the trap handler in this store.
     pub fn set_trap_handler(&self, handler: Option<Box<TrapHandlerFn>>) {
         let mut m = self.trap_handler.write().unwrap();
         *m = handler;
     }
 
+    /// Set the observer of the instantiations in this store.
+    ///
+    /// The observer receives an [`InstantiationEvent`] at the end of
+    /// each phase of the instantiations started after the call,
+    /// including the phase which fails, with its error.
+    ///
+    /// [`InstantiationEvent`]: crate::InstantiationEvent
+    pub fn set_instantiation_observer(&self, observer: Option<Arc<dyn InstantiationObserver>>) {
+        let mut m = self.instantiation_observer.write().unwrap();
+        *m = observer;
+    }
+
+    /// Returns the observer of the instantiations in this store, see
+    /// [`Store::set_instantiation_observer`].
+    pub(crate) fn instantiation_observer(&self) -> Option<Arc<dyn InstantiationObserver>> {
+        self.instantiation_observer.read().unwrap().clone()
+    }
+
+    /// Set the tracer of the memory accesses of the instances created
+    /// in this store after the call.
+    ///
+    /// Only the loads and stores of the code compiled with
+    /// [`CompilerConfig::memory_access_tracing`], which only
+    /// Singlepass supports, are reported. Each of them calls into the
+    /// host, which makes the code several times slower: the tracer is
+    /// a debugging aid, it can't deny an access and isn't a security
+    /// boundary.
+    ///
+    /// [`CompilerConfig::memory_access_tracing`]: crate::CompilerConfig::memory_access_tracing
+    #[cfg(feature = "memory-access-tracing")]
+    pub fn set_memory_access_tracer(&self, tracer: Option<Arc<dyn MemoryAccessTracer>>) {
+        let mut m = self.memory_access_tracer.write().unwrap();
+        *m = tracer;
+    }
+
+    /// Returns the tracer of the memory accesses in this store, see
+    /// [`Store::set_memory_access_tracer`].
+    #[cfg(feature = "memory-access-tracing")]
+    pub(crate) fn memory_access_tracer(&self) -> Option<Arc<dyn MemoryAccessTracer>> {
+        self.memory_access_tracer.read().unwrap().clone()
+    }
+
     /// Creates a new `Store` with a specific [`Engine`] and [`Tunables`].
     pub fn new_with_tunables<E>(engine: &E, tunables: impl Tunables + Send + Sync + 'static) -> Self
     where
@@ -49,10 +396,21 @@ impl Store {
         // This is required for handling traps.
         init_traps();
 
+        let tunables: Arc<dyn Tunables + Send + Sync> = Arc::new(tunables);
         Self {
             engine: engine.cloned(),
-            tunables: Arc::new(tunables),
+            features: engine.features(),
+            tunables: Arc::new(LimitingTunables::wrap(tunables)),
             trap_handler: Arc::new(RwLock::new(None)),
+            epoch: engine.epoch_controller(),
+            epoch_deadline: Arc::new(EpochDeadline::new()),
+            max_reentrancy_depth: Arc::new(AtomicUsize::new(usize::MAX)),
+            max_call_depth: Arc::new(AtomicU64::new(u64::MAX)),
+            call_depth_left: Arc::new(AtomicU64::new(u64::MAX)),
+            externref_roots: Arc::new(Mutex::new(ExternRefRoots::default())),
+            instantiation_observer: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "memory-access-tracing")]
+            memory_access_tracer: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -61,11 +419,28 @@ impl Store {
         self.tunables.as_ref()
     }
 
+    /// Returns the [`Tunables`], to be shared with a compilation
+    /// outliving the borrow of the store.
+    #[cfg(feature = "streaming")]
+    pub(crate) fn shared_tunables(&self) -> Arc<dyn Tunables + Send + Sync> {
+        self.tunables.clone()
+    }
+
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Arc<dyn Engine + Send + Sync> {
         &self.engine
     }
 
+    /// Returns the WebAssembly [`Features`] enabled in the engine of
+    /// this store.
+    ///
+    /// The modules are compiled, and the artifacts deserialized, with
+    /// these features: an embedder can compare them with the features
+    /// an artifact was produced with before loading it.
+    pub fn features(&self) -> &Features {
+        &self.features
+    }
+
     /// Checks whether two stores are identical. A store is considered
     /// equal to another store if both have the same engine. The
     /// tunables are excluded from the logic.
@@ -145,6 +520,39 @@ impl fmt::Debug for Store {
     }
 }
 
+/// Restores the call depth of a [`Store`] when dropped, see
+/// [`Store::enter_call_depth`].
+pub(crate) struct CallDepthGuard<'a> {
+    store: &'a Store,
+    active: u64,
+}
+
+impl Drop for CallDepthGuard<'_> {
+    fn drop(&mut self) {
+        let max = self.store.max_call_depth.load(SeqCst);
+        self.store
+            .call_depth_left
+            .store(max.saturating_sub(self.active), SeqCst);
+    }
+}
+
+/// Whether `error` is the trap raised once the epoch deadline is
+/// reached.
+fn is_epoch_trap(error: &RuntimeError) -> bool {
+    error.clone().to_trap() == Some(TrapCode::EpochDeadlineExceeded)
+}
+
+/// The error of a call interrupted because it took longer than its
+/// timeout, see [`Function::call_with_timeout`].
+///
+/// [`Function::call_with_timeout`]: crate::Function::call_with_timeout
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("call timed out after {} ms", timeout.as_millis())]
+pub struct CallTimeout {
+    /// The timeout of the call.
+    pub timeout: Duration,
+}
+
 /// A trait represinting any object that lives in the `Store`.
 pub trait StoreObject {
     /// Return true if the object `Store` is the same as the provided `Store`.
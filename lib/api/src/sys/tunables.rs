@@ -1,14 +1,14 @@
-use crate::sys::{MemoryType, Pages, TableType};
+use crate::sys::{GlobalType, IndexType, MemoryType, Pages, TableType, WASM_PAGE_SIZE};
 use loupe::MemoryUsage;
 use std::ptr::NonNull;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use target_lexicon::PointerWidth;
 use wasmer_compiler::Target;
 use wasmer_engine::Tunables;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
-    VMTableDefinition,
+    Global, GrowGuard, LinearMemory, LinearTable, Memory, MemoryGrowCallback, MemoryStyle, Table,
+    TableStyle, VMMemoryDefinition, VMTableDefinition,
 };
 
 /// Tunable parameters for WebAssembly compilation.
@@ -74,8 +74,10 @@ impl Tunables for BaseTunables {
         // tunables make it static.
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
+        // The addresses of 64-bit memories aren't covered by the guard
+        // pages of a static heap, so they are always dynamic.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if memory.index_type == IndexType::I32 && maximum <= self.static_memory_bound {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
@@ -148,6 +150,344 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// Wraps [`Tunables`] to limit the size of the memories and the
+/// tables they create.
+///
+/// Creating a memory or a table whose minimum exceeds its limit
+/// fails, and so does instantiating a module which defines one. The
+/// maximum of the other memories and tables is lowered to the limit,
+/// so that `memory.grow` and `table.grow` return -1 to the guest
+/// instead of growing past it.
+///
+/// ```
+/// # use wasmer::{BaseTunables, LimitingTunables, Pages, Store, Target};
+/// # let engine = Store::default().engine().clone();
+/// let base = BaseTunables::for_target(&Target::default());
+/// let tunables = LimitingTunables::new(base, Pages(24), 1000);
+/// let store = Store::new_with_tunables(&*engine, tunables);
+/// ```
+///
+/// A store wraps its tunables in one as well, to apply the
+/// adjustments made with [`Store::memory_guard_size`],
+/// [`Store::set_grow_guard`] and [`Store::set_memory_limit`].
+///
+/// [`Store::memory_guard_size`]: crate::Store::memory_guard_size
+/// [`Store::set_grow_guard`]: crate::Store::set_grow_guard
+/// [`Store::set_memory_limit`]: crate::Store::set_memory_limit
+#[derive(MemoryUsage)]
+pub struct LimitingTunables<T: Tunables + ?Sized> {
+    base: Arc<T>,
+    max_memory_pages: Option<Pages>,
+    max_table_elements: Option<u32>,
+    offset_guard_size: Option<u64>,
+    #[loupe(skip)]
+    grow_guard: Option<Arc<dyn GrowGuard>>,
+    #[loupe(skip)]
+    memory_limit: Option<Arc<MemoryLimit>>,
+}
+
+impl<T: Tunables> LimitingTunables<T> {
+    /// Wraps `base`, limiting the memories to `max_memory_pages` and
+    /// the tables to `max_table_elements`.
+    pub fn new(base: T, max_memory_pages: Pages, max_table_elements: u32) -> Self {
+        Self {
+            max_memory_pages: Some(max_memory_pages),
+            max_table_elements: Some(max_table_elements),
+            ..Self::wrap(Arc::new(base))
+        }
+    }
+}
+
+impl<T: Tunables + ?Sized> LimitingTunables<T> {
+    /// Wraps `base` without limiting or adjusting anything.
+    pub(crate) fn wrap(base: Arc<T>) -> Self {
+        Self {
+            base,
+            max_memory_pages: None,
+            max_table_elements: None,
+            offset_guard_size: None,
+            grow_guard: None,
+            memory_limit: None,
+        }
+    }
+
+    /// Uses `offset_guard_size` bytes of offset guard for the
+    /// memories, rounded up to a multiple of the WebAssembly page
+    /// size.
+    pub(crate) fn with_offset_guard_size(&self, offset_guard_size: u64) -> Self {
+        let page_size = WASM_PAGE_SIZE as u64;
+        Self {
+            offset_guard_size: Some(
+                offset_guard_size.saturating_add(page_size - 1) / page_size * page_size,
+            ),
+            ..self.clone()
+        }
+    }
+
+    /// Installs `guard` on the memories, replacing the previous guard.
+    pub(crate) fn with_grow_guard(&self, guard: Arc<dyn GrowGuard>) -> Self {
+        Self {
+            grow_guard: Some(guard),
+            ..self.clone()
+        }
+    }
+
+    /// Limits the total size of the memories to `max_bytes`, in a new
+    /// budget.
+    pub(crate) fn with_memory_limit(&self, max_bytes: u64) -> Self {
+        Self {
+            memory_limit: Some(Arc::new(MemoryLimit {
+                max_bytes,
+                used: Mutex::new(0),
+            })),
+            ..self.clone()
+        }
+    }
+
+    /// Lowers the maximum of `ty` to the limit, checking that its
+    /// minimum doesn't exceed it.
+    fn limit_memory(&self, ty: &MemoryType) -> Result<MemoryType, MemoryError> {
+        match self.max_memory_pages {
+            Some(max_memory_pages) if ty.minimum > max_memory_pages => {
+                Err(MemoryError::MinimumMemoryTooLarge {
+                    min_requested: ty.minimum,
+                    max_allowed: max_memory_pages,
+                })
+            }
+            _ => Ok(self.adjust_memory(ty)),
+        }
+    }
+
+    fn adjust_memory(&self, ty: &MemoryType) -> MemoryType {
+        let max_memory_pages = match self.max_memory_pages {
+            Some(max_memory_pages) => max_memory_pages,
+            None => return *ty,
+        };
+        let maximum = ty
+            .maximum
+            .map_or(max_memory_pages, |maximum| maximum.min(max_memory_pages));
+        MemoryType {
+            maximum: Some(maximum),
+            ..*ty
+        }
+    }
+
+    /// Lowers the maximum of `ty` to the limit, checking that its
+    /// minimum doesn't exceed it.
+    fn limit_table(&self, ty: &TableType) -> Result<TableType, String> {
+        let max_table_elements = match self.max_table_elements {
+            Some(max_table_elements) => max_table_elements,
+            None => return Ok(*ty),
+        };
+        if ty.minimum > max_table_elements {
+            return Err(format!(
+                "The minimum requested ({} elements) table is greater than the maximum allowed table ({} elements)",
+                ty.minimum, max_table_elements
+            ));
+        }
+        let maximum = ty.maximum.map_or(max_table_elements, |maximum| {
+            maximum.min(max_table_elements)
+        });
+        Ok(TableType {
+            maximum: Some(maximum),
+            ..*ty
+        })
+    }
+
+    /// Creates a memory of type `ty` with `create`, reserving its
+    /// minimum in the budget and installing the grow guard.
+    fn create_memory(
+        &self,
+        ty: &MemoryType,
+        create: impl FnOnce(&MemoryType) -> Result<Arc<dyn Memory>, MemoryError>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        let ty = self.limit_memory(ty)?;
+        let memory = match &self.memory_limit {
+            Some(limit) => {
+                limit.reserve(Pages(0), ty.minimum)?;
+                match create(&ty) {
+                    Ok(memory) => Arc::new(LimitedMemory {
+                        inner: memory,
+                        limit: limit.clone(),
+                    }),
+                    Err(error) => {
+                        limit.release(ty.minimum);
+                        return Err(error);
+                    }
+                }
+            }
+            None => create(&ty)?,
+        };
+        if let Some(guard) = &self.grow_guard {
+            memory.set_grow_guard(guard.clone())?;
+        }
+        Ok(memory)
+    }
+}
+
+impl<T: Tunables + ?Sized> Clone for LimitingTunables<T> {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            max_memory_pages: self.max_memory_pages,
+            max_table_elements: self.max_table_elements,
+            offset_guard_size: self.offset_guard_size,
+            grow_guard: self.grow_guard.clone(),
+            memory_limit: self.memory_limit.clone(),
+        }
+    }
+}
+
+impl<T: Tunables + ?Sized> Tunables for LimitingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        let style = self.base.memory_style(&self.adjust_memory(memory));
+        match (style, self.offset_guard_size) {
+            (MemoryStyle::Static { bound, .. }, Some(offset_guard_size)) => MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            },
+            (MemoryStyle::Dynamic { .. }, Some(offset_guard_size)) => {
+                MemoryStyle::Dynamic { offset_guard_size }
+            }
+            (style, None) => style,
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.create_memory(ty, |ty| self.base.create_host_memory(ty, style))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.create_memory(ty, |ty| {
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)
+        })
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base.create_host_table(&self.limit_table(ty)?, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.base
+            .create_vm_table(&self.limit_table(ty)?, style, vm_definition_location)
+    }
+
+    fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
+        self.base.create_global(ty)
+    }
+}
+
+/// The bytes used by the memories created by a [`LimitingTunables`],
+/// out of its limit.
+#[derive(Debug)]
+struct MemoryLimit {
+    max_bytes: u64,
+    used: Mutex<u64>,
+}
+
+impl MemoryLimit {
+    /// Reserves the bytes of `delta` pages for a memory of `current`
+    /// pages, unless they would exceed the limit.
+    fn reserve(&self, current: Pages, delta: Pages) -> Result<(), MemoryError> {
+        let mut used = self.used.lock().unwrap();
+        let delta_bytes = delta.bytes().0 as u64;
+        match used.checked_add(delta_bytes) {
+            Some(new_used) if new_used <= self.max_bytes => {
+                *used = new_used;
+                Ok(())
+            }
+            _ => Err(MemoryError::MemoryLimitExceeded {
+                current,
+                attempted_delta: delta,
+                limit: self.max_bytes,
+            }),
+        }
+    }
+
+    /// Releases the bytes of `pages` pages.
+    fn release(&self, pages: Pages) {
+        let mut used = self.used.lock().unwrap();
+        *used = used.saturating_sub(pages.bytes().0 as u64);
+    }
+}
+
+/// A memory whose size counts against a [`MemoryLimit`], until it's
+/// dropped.
+#[derive(Debug, MemoryUsage)]
+struct LimitedMemory {
+    inner: Arc<dyn Memory>,
+    #[loupe(skip)]
+    limit: Arc<MemoryLimit>,
+}
+
+impl Memory for LimitedMemory {
+    fn ty(&self) -> MemoryType {
+        self.inner.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.inner.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.inner.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        if delta.0 == 0 {
+            return self.inner.grow(delta);
+        }
+        // The budget isn't locked while growing, so that the grow
+        // callbacks can grow the other memories of the store.
+        self.limit.reserve(self.inner.size(), delta)?;
+        self.inner.grow(delta).map_err(|error| {
+            self.limit.release(delta);
+            error
+        })
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.inner.vmmemory()
+    }
+
+    fn on_grow(&self, callback: MemoryGrowCallback) -> Result<(), MemoryError> {
+        self.inner.on_grow(callback)
+    }
+
+    fn set_grow_guard(&self, guard: Arc<dyn GrowGuard>) -> Result<(), MemoryError> {
+        self.inner.set_grow_guard(guard)
+    }
+}
+
+impl Drop for LimitedMemory {
+    fn drop(&mut self) {
+        self.limit.release(self.inner.size());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,5 +529,48 @@ mod tests {
             }
             s => panic!("Unexpected memory style: {:?}", s),
         }
+
+        // Small maximum, 64-bit
+        let requested = MemoryType::new64(3, Some(16), false);
+        let style = tunables.memory_style(&requested);
+        match style {
+            MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn adjustments_compose() {
+        let base = BaseTunables {
+            static_memory_bound: Pages(2048),
+            static_memory_offset_guard_size: 128,
+            dynamic_memory_offset_guard_size: 256,
+        };
+        let tunables = LimitingTunables::new(base, Pages(4), 10)
+            .with_offset_guard_size(1)
+            .with_memory_limit(3 * WASM_PAGE_SIZE as u64);
+
+        let ty = MemoryType::new(2, None, false);
+        let style = tunables.memory_style(&ty);
+        match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } => {
+                assert_eq!(bound, Pages(2048));
+                assert_eq!(offset_guard_size, WASM_PAGE_SIZE as u64);
+            }
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
+
+        // The maximum is lowered to the limit of pages, and the
+        // memories share the limit of bytes.
+        let memory = tunables.create_host_memory(&ty, &style).unwrap();
+        assert_eq!(memory.ty().maximum, Some(Pages(4)));
+        assert!(tunables.create_host_memory(&ty, &style).is_err());
+        assert!(matches!(memory.grow(Pages(1)), Ok(Pages(2))));
+        assert!(memory.grow(Pages(1)).is_err());
+        drop(memory);
+        assert!(tunables.create_host_memory(&ty, &style).is_ok());
     }
 }
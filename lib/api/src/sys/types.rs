@@ -3,8 +3,8 @@ use crate::sys::store::{Store, StoreObject};
 use crate::sys::RuntimeError;
 use wasmer_types::Value;
 pub use wasmer_types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Type as ValType,
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, IndexType, MemoryType,
+    Mutability, TableType, Type as ValType,
 };
 use wasmer_vm::VMFuncRef;
 
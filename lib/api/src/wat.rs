@@ -0,0 +1,113 @@
+//! Conversion of the WebAssembly text format to the binary format,
+//! see [`wat2wasm`].
+
+use std::borrow::Cow;
+use std::str;
+use thiserror::Error;
+use wast::parser::{self, ParseBuffer};
+use wast::Span;
+
+/// An error produced by [`wat2wasm`], locating the offending text.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} (at line {line}, column {column}: `{snippet}`)")]
+pub struct WatError {
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// The column of the error in its line, starting at 1. The column
+    /// counts characters, not bytes.
+    pub column: usize,
+    /// The line of the error.
+    pub snippet: String,
+}
+
+impl WatError {
+    fn new(message: String, text: &str, span: Span) -> Self {
+        let (line, byte_column) = span.linecol_in(text);
+        let snippet = text
+            .split_terminator('\n')
+            .nth(line)
+            .unwrap_or("")
+            .trim_end_matches('\r');
+        let column = snippet
+            .get(..byte_column)
+            .map_or(byte_column, |prefix| prefix.chars().count());
+        Self {
+            message,
+            line: line + 1,
+            column: column + 1,
+            snippet: snippet.to_string(),
+        }
+    }
+}
+
+/// Parses in-memory bytes as either the [WebAssembly text format][wat],
+/// or a binary WebAssembly module, which is returned as is.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::wat2wasm;
+/// assert_eq!(&*wat2wasm(b"(module)").unwrap(), b"\0asm\x01\0\0\0");
+///
+/// let error = wat2wasm("(module\n  (func (result i32)\n    (i32.konst 42)))".as_bytes())
+///     .unwrap_err();
+/// assert_eq!((error.line, error.column), (3, 6));
+/// assert_eq!(error.snippet, "    (i32.konst 42)))");
+/// ```
+///
+/// [wat]: https://webassembly.github.io/spec/core/text/index.html
+pub fn wat2wasm(bytes: &[u8]) -> Result<Cow<'_, [u8]>, WatError> {
+    if bytes.starts_with(b"\0asm") {
+        return Ok(bytes.into());
+    }
+    let text = str::from_utf8(bytes).map_err(|e| {
+        let valid = str::from_utf8(&bytes[..e.valid_up_to()]).unwrap();
+        WatError::new(
+            "input bytes aren't valid utf-8".to_string(),
+            valid,
+            Span::from_offset(valid.len()),
+        )
+    })?;
+    parse(text)
+        .map(Cow::Owned)
+        .map_err(|e| WatError::new(e.message(), text, e.span()))
+}
+
+fn parse(text: &str) -> Result<Vec<u8>, wast::Error> {
+    let buffer = ParseBuffer::new(text)?;
+    let mut wat = parser::parse::<wast::Wat>(&buffer)?;
+    wat.module.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binaries_are_returned_as_is() {
+        let binary = b"\0asm\x01\0\0\0";
+        assert!(matches!(wat2wasm(binary), Ok(Cow::Borrowed(b)) if b == binary));
+        assert_eq!(&*wat2wasm(b"(module)").unwrap(), binary);
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let error = wat2wasm("(module\n  (func (; é⁂ ;) (i32.konst 1)))".as_bytes()).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 19));
+        assert_eq!(error.snippet, "  (func (; é⁂ ;) (i32.konst 1)))");
+    }
+
+    #[test]
+    fn invalid_utf8_is_located() {
+        let error = wat2wasm(b"(module\r\n  (func)) \xff").unwrap_err();
+        assert_eq!(error.message, "input bytes aren't valid utf-8");
+        assert_eq!((error.line, error.column), (2, 11));
+        assert_eq!(error.snippet, "  (func)) ");
+        assert_eq!(
+            error.to_string(),
+            "input bytes aren't valid utf-8 (at line 2, column 11: `  (func)) `)"
+        );
+    }
+}
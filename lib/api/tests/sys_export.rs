@@ -336,4 +336,76 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn typed_getters_distinguish_missing_and_incompatible_exports() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+              (memory (export "mem") 1)
+              (table (export "table") 1 funcref)
+              (global (export "glob") i32 (i32.const 7))
+              (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))
+            "#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let exports = &instance.exports;
+
+        assert_eq!(exports.get_memory("mem")?.size(), Pages(1));
+        assert_eq!(exports.get_table("table")?.size(), 1);
+        assert_eq!(exports.get_global("glob")?.get(), Val::I32(7));
+        assert_eq!(
+            exports
+                .get_function("add")?
+                .call(&[Val::I32(1), Val::I32(2)])?[0],
+            Val::I32(3)
+        );
+
+        assert!(matches!(
+            exports.get_memory("unknown"),
+            Err(ExportError::Missing(name)) if name == "unknown"
+        ));
+
+        let error = exports.get_memory("add").unwrap_err();
+        assert!(matches!(
+            &error,
+            ExportError::IncompatibleType { expected, found: ExternType::Function(_) }
+                if expected == "memory"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Incompatible export type: expected a memory, found a function [I32, I32] -> [I32]"
+        );
+        assert!(matches!(
+            exports.get_table("mem"),
+            Err(ExportError::IncompatibleType {
+                found: ExternType::Memory(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            exports.get_global("table"),
+            Err(ExportError::IncompatibleType {
+                found: ExternType::Table(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            exports.get_function("glob"),
+            Err(ExportError::IncompatibleType {
+                found: ExternType::Global(_),
+                ..
+            })
+        ));
+
+        let error = exports.get_native_function::<i64, i64>("add").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Incompatible export type: expected a function [I64] -> [I64], found a function [I32, I32] -> [I32]"
+        );
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 #[cfg(feature = "sys")]
 mod sys {
     use anyhow::Result;
+    use std::sync::atomic::{AtomicU32, Ordering::SeqCst};
+    use std::sync::{Arc, Mutex};
     use wasmer::*;
 
     #[test]
@@ -129,10 +131,205 @@ mod sys {
         Ok(())
     }
 
+    /// A funcref table whose element `i` is a function returning `i`,
+    /// and the value returned by the function of each element, if any.
+    fn numbered_table(store: &Store, size: u32) -> Result<(Table, Vec<Value>)> {
+        let table_type = TableType::new(Type::FuncRef, size, None);
+        let table = Table::new(store, table_type, Value::FuncRef(None))?;
+        let functions = (0..size as i32)
+            .map(|i| Value::FuncRef(Some(Function::new_native(store, move || i))))
+            .collect::<Vec<_>>();
+        table.init(0, &functions, 0, size)?;
+        Ok((table, functions))
+    }
+
+    fn table_numbers(table: &Table) -> Result<Vec<Option<i32>>> {
+        (0..table.size())
+            .map(|i| match table.get(i) {
+                Some(Value::FuncRef(Some(f))) => Ok(Some(f.call(&[])?[0].unwrap_i32())),
+                Some(Value::FuncRef(None)) => Ok(None),
+                other => panic!("unexpected element {:?}", other),
+            })
+            .collect()
+    }
+
     #[test]
-    #[ignore]
     fn table_copy() -> Result<()> {
-        // TODO: table copy test not yet implemented
+        let store = Store::default();
+        let (table, _) = numbered_table(&store, 6)?;
+
+        // Overlapping copies, in both directions.
+        Table::copy(&table, 2, &table, 0, 3)?;
+        assert_eq!(
+            table_numbers(&table)?,
+            vec![Some(0), Some(1), Some(0), Some(1), Some(2), Some(5)]
+        );
+        Table::copy(&table, 0, &table, 1, 4)?;
+        assert_eq!(
+            table_numbers(&table)?,
+            vec![Some(1), Some(0), Some(1), Some(2), Some(2), Some(5)]
+        );
+
+        // Copies between tables, up to their ends.
+        let (other, _) = numbered_table(&store, 3)?;
+        Table::copy(&other, 1, &table, 4, 2)?;
+        assert_eq!(table_numbers(&other)?, vec![Some(0), Some(2), Some(5)]);
+        Table::copy(&table, 5, &other, 0, 0)?;
+        Table::copy(&table, 6, &other, 0, 0)?;
+
+        assert_eq!(
+            Table::copy(&other, 2, &table, 0, 2),
+            Err(TableError::OutOfBounds {
+                index: 2,
+                len: 2,
+                size: 3
+            })
+        );
+        assert_eq!(
+            Table::copy(&other, 0, &table, 5, 2),
+            Err(TableError::OutOfBounds {
+                index: 5,
+                len: 2,
+                size: 6
+            })
+        );
+        assert_eq!(table_numbers(&other)?, vec![Some(0), Some(2), Some(5)]);
+
+        // Tables of different element types.
+        let externrefs = Table::new(
+            &store,
+            TableType::new(Type::ExternRef, 6, None),
+            Value::null(),
+        )?;
+        assert_eq!(
+            Table::copy(&externrefs, 0, &table, 0, 1),
+            Err(TableError::IncompatibleType {
+                expected: Type::ExternRef,
+                found: Type::FuncRef
+            })
+        );
+        assert_eq!(
+            Table::copy(&table, 0, &externrefs, 0, 0),
+            Err(TableError::IncompatibleType {
+                expected: Type::FuncRef,
+                found: Type::ExternRef
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn table_fill() -> Result<()> {
+        let store = Store::default();
+        let (table, functions) = numbered_table(&store, 4)?;
+
+        // Up to the last element.
+        table.fill(2, Value::FuncRef(None), 2)?;
+        assert_eq!(table_numbers(&table)?, vec![Some(0), Some(1), None, None]);
+        table.fill(0, functions[3].clone(), 1)?;
+        assert_eq!(table_numbers(&table)?, vec![Some(3), Some(1), None, None]);
+        table.fill(4, Value::FuncRef(None), 0)?;
+
+        // One beyond the last element.
+        assert_eq!(
+            table.fill(1, Value::FuncRef(None), 4),
+            Err(TableError::OutOfBounds {
+                index: 1,
+                len: 4,
+                size: 4
+            })
+        );
+        assert_eq!(
+            table.fill(5, Value::FuncRef(None), 0),
+            Err(TableError::OutOfBounds {
+                index: 5,
+                len: 0,
+                size: 4
+            })
+        );
+        assert_eq!(
+            table.fill(0, Value::null(), 1),
+            Err(TableError::IncompatibleType {
+                expected: Type::FuncRef,
+                found: Type::ExternRef
+            })
+        );
+        assert_eq!(table_numbers(&table)?, vec![Some(3), Some(1), None, None]);
+        Ok(())
+    }
+
+    #[test]
+    fn table_bulk_operations_are_seen_by_wasm() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (type $number_t (func (result i32)))
+      (table (export "table") 4 funcref)
+      (func $one (result i32) (i32.const 1))
+      (func $two (result i32) (i32.const 2))
+      (elem (i32.const 0) $one $two)
+      (func (export "call") (param $index i32) (result i32)
+        (call_indirect (type $number_t) (local.get $index))))
+"#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let table = instance.exports.get_table("table")?;
+        let call = instance.exports.get_native_function::<i32, i32>("call")?;
+
+        // The guest sees the elements copied and filled by the host.
+        Table::copy(table, 1, table, 0, 3)?;
+        assert_eq!(call.call(1)?, 1);
+        assert_eq!(call.call(2)?, 2);
+        table.fill(0, table.get(2).unwrap(), 1)?;
+        assert_eq!(call.call(0)?, 2);
+        table.fill(3, Value::FuncRef(None), 1)?;
+        assert!(call.call(3).is_err());
+
+        // The host reads the same elements.
+        assert_eq!(table_numbers(table)?, vec![Some(2), Some(1), Some(2), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn table_init() -> Result<()> {
+        let store = Store::default();
+        let (table, functions) = numbered_table(&store, 4)?;
+
+        table.init(1, &functions, 2, 2)?;
+        assert_eq!(
+            table_numbers(&table)?,
+            vec![Some(0), Some(2), Some(3), Some(3)]
+        );
+
+        assert_eq!(
+            table.init(0, &functions, 3, 2),
+            Err(TableError::OutOfBounds {
+                index: 3,
+                len: 2,
+                size: 4
+            })
+        );
+        assert_eq!(
+            table.init(3, &functions, 0, 2),
+            Err(TableError::OutOfBounds {
+                index: 3,
+                len: 2,
+                size: 4
+            })
+        );
+        assert_eq!(
+            table.init(0, &[Value::FuncRef(None), Value::I32(0)], 0, 2),
+            Err(TableError::IncompatibleType {
+                expected: Type::FuncRef,
+                found: Type::I32
+            })
+        );
+        assert_eq!(
+            table_numbers(&table)?,
+            vec![Some(0), Some(2), Some(3), Some(3)]
+        );
         Ok(())
     }
 
@@ -143,6 +340,7 @@ mod sys {
             shared: false,
             minimum: Pages(0),
             maximum: Some(Pages(10)),
+            index_type: IndexType::I32,
         };
         let memory = Memory::new(&store, memory_type)?;
         assert_eq!(memory.size(), Pages(0));
@@ -179,6 +377,403 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn memory_data_size_atomic() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(64), true))?;
+
+        // The sizes read while another thread grows the memory are
+        // whole pages, and never go backwards.
+        let growing = memory.clone();
+        let grower = std::thread::spawn(move || {
+            for _ in 1..64 {
+                growing.grow(1).unwrap();
+            }
+        });
+        // The reads stop at the full size, or after a while if the
+        // other thread failed to grow the memory.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let mut previous = 0;
+        while previous < 64 * WASM_PAGE_SIZE as u64 && std::time::Instant::now() < deadline {
+            let size = memory.data_size_atomic();
+            assert_eq!(size % WASM_PAGE_SIZE as u64, 0);
+            assert!(size >= previous);
+            previous = size;
+        }
+        grower.join().unwrap();
+        assert_eq!(memory.data_size_atomic(), 64 * WASM_PAGE_SIZE as u64);
+        assert_eq!(memory.data_size_atomic(), memory.data_size());
+        Ok(())
+    }
+
+    fn memory_bytes(memory: &Memory, len: usize) -> Vec<u8> {
+        unsafe { memory.data_unchecked()[..len].to_vec() }
+    }
+
+    #[test]
+    fn memory_bulk_operations() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+
+        memory.fill(1, 0xaa, 3)?;
+        memory.fill(2, 0xbb, 1)?;
+        assert_eq!(memory_bytes(&memory, 8), [0, 0xaa, 0xbb, 0xaa, 0, 0, 0, 0]);
+
+        // Overlapping copies, in both directions.
+        Memory::copy(&memory, 3, &memory, 1, 4)?;
+        assert_eq!(
+            memory_bytes(&memory, 8),
+            [0, 0xaa, 0xbb, 0xaa, 0xbb, 0xaa, 0, 0]
+        );
+        Memory::copy(&memory, 0, &memory, 2, 4)?;
+        assert_eq!(
+            memory_bytes(&memory, 8),
+            [0xbb, 0xaa, 0xbb, 0xaa, 0xbb, 0xaa, 0, 0]
+        );
+
+        // Copies between memories, up to their ends.
+        let other = Memory::new(&store, MemoryType::new(1, None, false))?;
+        Memory::copy(&other, 0xfffe, &memory, 0, 2)?;
+        Memory::copy(&memory, 0x1_0000, &other, 0, 0)?;
+        assert_eq!(unsafe { &other.data_unchecked()[0xfffe..] }, [0xbb, 0xaa]);
+
+        memory.init_from_data_segment(6, b"hello", 3, 2)?;
+        assert_eq!(memory_bytes(&memory, 8)[6..], *b"lo");
+
+        // One beyond the end.
+        assert_eq!(
+            Memory::copy(&other, 0xffff, &memory, 0, 2),
+            Err(MemoryError::AccessOutOfBounds {
+                offset: 0xffff,
+                len: 2,
+                size: 0x1_0000
+            })
+        );
+        assert_eq!(
+            memory.fill(0x1_0001, 0, 0),
+            Err(MemoryError::AccessOutOfBounds {
+                offset: 0x1_0001,
+                len: 0,
+                size: 0x1_0000
+            })
+        );
+        assert_eq!(
+            memory.fill(u32::MAX, 0, 2),
+            Err(MemoryError::AccessOutOfBounds {
+                offset: u32::MAX,
+                len: 2,
+                size: 0x1_0000
+            })
+        );
+        assert_eq!(
+            memory.init_from_data_segment(0, b"hello", 4, 2),
+            Err(MemoryError::AccessOutOfBounds {
+                offset: 4,
+                len: 2,
+                size: 5
+            })
+        );
+        assert_eq!(
+            memory_bytes(&memory, 8),
+            [0xbb, 0xaa, 0xbb, 0xaa, 0xbb, 0xaa, b'l', b'o']
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn memory_view_as() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "write") (param $count i32)
+                    (local $i i32)
+                    (block $done
+                        (loop $loop
+                            (br_if $done (i32.ge_u (local.get $i) (local.get $count)))
+                            (f64.store offset=16
+                                (i32.shl (local.get $i) (i32.const 3))
+                                (f64.mul (f64.convert_i32_u (local.get $i)) (f64.const 0.5)))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br $loop)))))
+            "#,
+        )?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let write: NativeFunc<u32, ()> = instance.exports.get_native_function("write")?;
+
+        write.call(1000)?;
+        let values: &[f64] = unsafe { memory.view_as(16, 1000)? };
+        assert_eq!(values.as_ptr() as usize, memory.data_ptr() as usize + 16);
+        assert_eq!(values.iter().sum::<f64>(), 249750.0);
+
+        // Up to the end of the memory.
+        assert_eq!(unsafe { memory.view_as::<f64>(0xfff8, 1)? }, [0.0]);
+        assert!(unsafe { memory.view_as::<f64>(0x1_0000, 0)? }.is_empty());
+        assert_eq!(
+            unsafe { memory.view_as::<f64>(0xfff8, 2) },
+            Err(MemoryError::AccessOutOfBounds {
+                offset: 0xfff8,
+                len: 16,
+                size: 0x1_0000
+            })
+        );
+        assert_eq!(
+            unsafe { memory.view_as::<u64>(0, u32::MAX) },
+            Err(MemoryError::AccessOutOfBounds {
+                offset: 0,
+                len: u32::MAX,
+                size: 0x1_0000
+            })
+        );
+        assert_eq!(
+            unsafe { memory.view_as::<f64>(4, 1) },
+            Err(MemoryError::MisalignedAccess {
+                offset: 4,
+                align: std::mem::align_of::<f64>()
+            })
+        );
+
+        let shared = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+        assert!(matches!(
+            unsafe { shared.view_as::<f64>(0, 1) },
+            Err(MemoryError::InvalidMemory { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn memory_on_grow() -> Result<()> {
+        let default_store = Store::default();
+        let engine = default_store.engine().as_ref();
+        let wat = r#"(module
+    (memory (export "memory") 1 4)
+    (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0))))"#;
+
+        // A large bound makes the memory static, a zero one makes it
+        // dynamic.
+        for static_memory_bound in &[Pages(0x1_0000), Pages(0)] {
+            let tunables = BaseTunables {
+                static_memory_bound: *static_memory_bound,
+                ..BaseTunables::for_target(engine.target())
+            };
+            let style = tunables.memory_style(&MemoryType::new(1, Some(4), false));
+            assert_eq!(
+                matches!(style, vm::MemoryStyle::Static { .. }),
+                static_memory_bound.0 > 0
+            );
+
+            let store = Store::new_with_tunables(engine, tunables);
+            let module = Module::new(&store, wat)?;
+            let instance = Instance::new(&module, &imports! {})?;
+            let memory = instance.exports.get_memory("memory")?;
+            let grow: NativeFunc<i32, i32> = instance.exports.get_native_function("grow")?;
+
+            let grown = Arc::new(Mutex::new(vec![]));
+            let grown_clone = grown.clone();
+            memory.on_grow(move |old, new| grown_clone.lock().unwrap().push((old, new)))?;
+
+            assert_eq!(grow.call(1)?, 1);
+            // Neither empty nor failed grows are notified.
+            assert_eq!(grow.call(0)?, 2);
+            assert_eq!(grow.call(10)?, -1);
+            memory.grow(1)?;
+
+            assert_eq!(
+                *grown.lock().unwrap(),
+                vec![(Pages(1), Pages(2)), (Pages(2), Pages(3))]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_grow_guard() -> Result<()> {
+        /// Allows growing up to a budget of pages, which can change at
+        /// runtime.
+        struct Budget(AtomicU32);
+
+        impl GrowGuard for Budget {
+            fn check_grow(&self, current: Pages, delta: Pages) -> Result<(), ()> {
+                if current.0 + delta.0 <= self.0.load(SeqCst) {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            }
+        }
+
+        let budget = Arc::new(Budget(AtomicU32::new(3)));
+        let mut store = Store::default();
+        store.set_grow_guard(budget.clone());
+
+        // The module doesn't declare a maximum.
+        let wat = r#"(module
+    (memory (export "memory") 1)
+    (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let grow: NativeFunc<i32, i32> = instance.exports.get_native_function("grow")?;
+
+        assert_eq!(grow.call(1)?, 1);
+        assert_eq!(grow.call(2)?, -1);
+        assert_eq!(
+            memory.grow(2),
+            Err(MemoryError::GrowDenied {
+                current: Pages(2),
+                attempted_delta: Pages(2),
+            })
+        );
+        assert_eq!(memory.grow(1)?, Pages(2));
+        assert_eq!(memory.size(), Pages(3));
+
+        // The budget changes at runtime.
+        budget.0.store(5, SeqCst);
+        assert_eq!(grow.call(2)?, 3);
+        assert_eq!(memory.size(), Pages(5));
+
+        // Host memories are guarded too.
+        let host_memory = Memory::new(&store, MemoryType::new(1, Some(10), false))?;
+        assert!(matches!(
+            host_memory.grow(5),
+            Err(MemoryError::GrowDenied { .. })
+        ));
+        assert_eq!(host_memory.grow(4)?, Pages(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn store_memory_limit() -> Result<()> {
+        let mut store = Store::default();
+        store.set_memory_limit(6 * WASM_PAGE_SIZE as u64);
+
+        // Two memories, without a maximum, sharing a budget of 6 pages.
+        let wat = r#"(module
+    (memory (export "memory") 1)
+    (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0))))"#;
+        let module = Module::new(&store, wat)?;
+        let first = Instance::new(&module, &imports! {})?;
+        let first_grow: NativeFunc<i32, i32> = first.exports.get_native_function("grow")?;
+        let second = Memory::new(&store, MemoryType::new(1, None, false))?;
+
+        // Each memory can grow independently within the limit.
+        assert_eq!(first_grow.call(2)?, 1);
+        assert_eq!(second.grow(1)?, Pages(1));
+        assert_eq!(first_grow.call(1)?, 3);
+
+        // 6 pages are used: any growth crosses the limit.
+        assert_eq!(first_grow.call(1)?, -1);
+        assert_eq!(
+            second.grow(1),
+            Err(MemoryError::MemoryLimitExceeded {
+                current: Pages(2),
+                attempted_delta: Pages(1),
+                limit: 6 * WASM_PAGE_SIZE as u64,
+            })
+        );
+        assert_eq!(first_grow.call(0)?, 4);
+        assert_eq!(second.size(), Pages(2));
+
+        // So does creating a memory.
+        assert!(matches!(
+            Memory::new(&store, MemoryType::new(1, None, false)),
+            Err(MemoryError::MemoryLimitExceeded { .. })
+        ));
+
+        // Dropping a memory gives its pages back to the budget.
+        drop(second);
+        assert_eq!(first_grow.call(2)?, 4);
+        assert_eq!(first_grow.call(1)?, -1);
+
+        Ok(())
+    }
+
+    fn limiting_store(max_memory_pages: u32, max_table_elements: u32) -> Store {
+        let engine = Store::default().engine().clone();
+        let base = BaseTunables::for_target(engine.target());
+        let tunables = LimitingTunables::new(base, Pages(max_memory_pages), max_table_elements);
+        Store::new_with_tunables(&*engine, tunables)
+    }
+
+    #[test]
+    fn limiting_tunables_reject_large_minimums() -> Result<()> {
+        let store = limiting_store(4, 10);
+
+        let module = Module::new(&store, "(module (memory 5))")?;
+        let error = Instance::new(&module, &imports! {}).unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::Link(LinkError::Resource(ref message))
+                if message.contains("minimum requested (5 pages)")
+        ));
+
+        let module = Module::new(&store, "(module (table 11 funcref))")?;
+        let error = Instance::new(&module, &imports! {}).unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::Link(LinkError::Resource(ref message))
+                if message.contains("minimum requested (11 elements)")
+        ));
+
+        // Host memories and tables are limited too.
+        assert!(matches!(
+            Memory::new(&store, MemoryType::new(5, None, false)),
+            Err(MemoryError::MinimumMemoryTooLarge { .. })
+        ));
+        assert!(Table::new(
+            &store,
+            TableType::new(Type::FuncRef, 11, None),
+            Value::FuncRef(None)
+        )
+        .is_err());
+
+        // Minimums within the limits are fine.
+        let module = Module::new(&store, "(module (memory 4) (table 10 funcref))")?;
+        Instance::new(&module, &imports! {})?;
+        Ok(())
+    }
+
+    #[test]
+    fn limiting_tunables_clamp_growth() -> Result<()> {
+        let store = limiting_store(4, 10);
+        let wat = r#"(module
+    (memory (export "memory") 1 100)
+    (table (export "table") 1 funcref)
+    (func (export "grow_memory") (param i32) (result i32)
+        (memory.grow (local.get 0)))
+    (func (export "grow_table") (param i32) (result i32)
+        (table.grow (ref.null func) (local.get 0))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let table = instance.exports.get_table("table")?;
+        let grow_memory: NativeFunc<i32, i32> =
+            instance.exports.get_native_function("grow_memory")?;
+        let grow_table: NativeFunc<i32, i32> =
+            instance.exports.get_native_function("grow_table")?;
+
+        assert_eq!(memory.ty().maximum, Some(Pages(4)));
+        assert_eq!(grow_memory.call(4)?, -1);
+        assert_eq!(grow_memory.call(3)?, 1);
+        assert_eq!(grow_memory.call(1)?, -1);
+        assert_eq!(memory.size(), Pages(4));
+
+        assert_eq!(table.ty().maximum, Some(10));
+        assert_eq!(grow_table.call(10)?, -1);
+        assert_eq!(grow_table.call(9)?, 1);
+        assert_eq!(grow_table.call(1)?, -1);
+        assert_eq!(table.size(), 10);
+        Ok(())
+    }
+
     #[test]
     fn function_new() -> Result<()> {
         let store = Store::default();
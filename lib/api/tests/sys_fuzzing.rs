@@ -0,0 +1,108 @@
+#[cfg(all(feature = "sys", feature = "fuzzing"))]
+mod sys {
+    use anyhow::Result;
+    use std::fs;
+    use wasmer::fuzzing::*;
+    use wasmer::{Features, Pages, TrapCode};
+
+    /// The binaries of `fuzz/regressions`, with the outcome they are
+    /// expected to have with [`fuzz_config`].
+    fn regressions() -> Vec<(&'static str, FuzzOutcome)> {
+        let rejected = FuzzOutcome::Rejected(String::new());
+        let out_of_points = |function: Option<&str>| FuzzOutcome::Trap {
+            function: function.map(str::to_string),
+            code: Some(TrapCode::UnreachableCodeReached),
+        };
+        let completed = |function: &str, value| {
+            FuzzOutcome::Completed(vec![(function.to_string(), vec![FuzzValue::I32(value)])])
+        };
+        vec![
+            ("overlong_leb.wasm", rejected.clone()),
+            ("padded_leb.wasm", completed("answer", 42)),
+            ("huge_section.wasm", rejected.clone()),
+            ("huge_locals.wasm", rejected.clone()),
+            ("deep_nesting.wasm", completed("deep", 7)),
+            ("infinite_loop.wasm", out_of_points(Some("spin"))),
+            ("infinite_recursion.wasm", out_of_points(Some("recurse"))),
+            ("infinite_start.wasm", out_of_points(None)),
+            ("huge_memory.wasm", rejected.clone()),
+            ("huge_table.wasm", rejected),
+            ("grow_memory.wasm", completed("grow", 16)),
+            ("stubbed_imports.wasm", completed("sum", 0)),
+        ]
+    }
+
+    fn regression(name: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(format!(
+            "{}/../../fuzz/regressions/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))?)
+    }
+
+    fn fuzz_config(backend: FuzzBackend) -> FuzzConfig {
+        FuzzConfig {
+            backend,
+            features: Features::default(),
+            max_points: 1000,
+            max_memory: Pages(16),
+        }
+    }
+
+    /// The backends enabled in this build.
+    fn backends() -> Vec<FuzzBackend> {
+        let mut backends = Vec::new();
+        #[cfg(feature = "singlepass")]
+        backends.push(FuzzBackend::Singlepass);
+        #[cfg(feature = "cranelift")]
+        backends.push(FuzzBackend::Cranelift);
+        #[cfg(feature = "llvm")]
+        backends.push(FuzzBackend::LLVM);
+        backends
+    }
+
+    #[test]
+    fn run_arbitrary_regressions() -> Result<()> {
+        for backend in backends() {
+            let fuzz_config = fuzz_config(backend);
+            for (name, expected) in regressions() {
+                let outcome = run_arbitrary(&regression(name)?, &fuzz_config);
+                match (&outcome, &expected) {
+                    // The messages come from the validator.
+                    (FuzzOutcome::Rejected(_), FuzzOutcome::Rejected(_)) => {}
+                    _ => assert_eq!(outcome, expected, "{} with {:?}", name, backend),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn run_arbitrary_any_bytes() -> Result<()> {
+        let bytes = regression("stubbed_imports.wasm")?;
+        for backend in backends() {
+            let fuzz_config = fuzz_config(backend);
+            // Every prefix of a module is handled without panicking.
+            for len in 0..bytes.len() {
+                let _ = run_arbitrary(&bytes[..len], &fuzz_config);
+            }
+            assert!(matches!(
+                run_arbitrary(b"\0asm\x01\0\0\0", &fuzz_config),
+                FuzzOutcome::Completed(results) if results.is_empty()
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(all(feature = "singlepass", feature = "cranelift"))]
+    #[test]
+    fn run_differential_regressions() -> Result<()> {
+        let first = fuzz_config(FuzzBackend::Singlepass);
+        let second = fuzz_config(FuzzBackend::Cranelift);
+        for (name, _) in regressions() {
+            let outcome = run_differential(&regression(name)?, &first, &second);
+            assert!(outcome.agree(), "{}: {:?}", name, outcome);
+        }
+        Ok(())
+    }
+}
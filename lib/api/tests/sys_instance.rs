@@ -67,4 +67,553 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn function_with_caller_sees_each_importing_instance() -> Result<()> {
+        let store = Store::default();
+        // `run` asks the host for `id`, which the host gets by calling
+        // back the `id` export of the caller. Both instances keep their
+        // id in their own, unexported, memory.
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "env" "host" (func $host (result i32)))
+      (memory 1)
+      (func (export "set_id") (param i32)
+        (i32.store (i32.const 0) (local.get 0)))
+      (func (export "id") (result i32)
+        (i32.load (i32.const 0)))
+      (func (export "run") (result i32)
+        (call $host)))
+"#,
+        )?;
+
+        let host = Function::new_with_caller(
+            &store,
+            FunctionType::new(vec![], vec![Type::I32]),
+            0u32,
+            |caller, _env, _args| {
+                let id = match caller.get_export("id") {
+                    Some(Extern::Function(id)) => id.call(&[])?[0].unwrap_i32(),
+                    _ => panic!("`id` is exported"),
+                };
+                let memory = caller.memory(0).unwrap();
+                assert_eq!(memory.view::<u32>()[0].get(), id as u32);
+                assert!(caller.memory(1).is_none());
+                Ok(vec![Val::I32(id)])
+            },
+        );
+        let import_object = imports! {
+            "env" => {
+                "host" => host.clone(),
+            },
+        };
+        let first = Instance::new(&module, &import_object)?;
+        let second = Instance::new(&module, &import_object)?;
+        first.exports.get_function("set_id")?.call(&[Val::I32(1)])?;
+        second
+            .exports
+            .get_function("set_id")?
+            .call(&[Val::I32(2)])?;
+
+        let run = |instance: &Instance| -> Result<Box<[Val]>> {
+            Ok(instance.exports.get_function("run")?.call(&[])?)
+        };
+        assert_eq!(run(&second)?, vec![Val::I32(2)].into_boxed_slice());
+        assert_eq!(run(&first)?, vec![Val::I32(1)].into_boxed_slice());
+
+        // Called from the host, there is no caller.
+        assert!(host.call(&[]).is_err());
+
+        Ok(())
+    }
+
+    const SNAPSHOT_WAT: &str = r#"(module
+        (memory (export "memory") 1)
+        (table (export "funcs") 2 funcref)
+        (table (export "externs") 1 externref)
+        (global (export "i32") (mut i32) (i32.const 1))
+        (global (export "i64") (mut i64) (i64.const 2))
+        (global (export "f32") (mut f32) (f32.const 3))
+        (global (export "f64") (mut f64) (f64.const 4))
+        (global (export "v128") (mut v128) (v128.const i64x2 5 6))
+        (global (export "const") i32 (i32.const 7))
+        (func $one (export "one") (result i32) (i32.const 1))
+        (func $two (export "two") (result i32) (i32.const 2))
+        (elem (i32.const 0) $one))"#;
+
+    /// Calls the function at `index` in the `funcs` table of `instance`.
+    fn call_element(instance: &Instance, index: u32) -> Result<Box<[Val]>> {
+        let funcs = instance.exports.get_table("funcs")?;
+        match funcs.get(index) {
+            Some(Val::FuncRef(Some(function))) => Ok(function.call(&[])?),
+            element => panic!("element {} is {:?}", index, element),
+        }
+    }
+
+    #[test]
+    fn snapshot_restores_memories_tables_and_globals() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, SNAPSHOT_WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let funcs = instance.exports.get_table("funcs")?;
+        let externs = instance.exports.get_table("externs")?;
+        let two = instance.exports.get_function("two")?;
+        let global = |name: &str| instance.exports.get_global(name).unwrap();
+
+        // The memory grows past its size at instantiation.
+        memory.grow(1)?;
+        memory.view::<u8>()[WASM_PAGE_SIZE + 1].set(42);
+        funcs.set(1, Val::FuncRef(Some(two.clone())))?;
+        externs.set(0, Val::ExternRef(ExternRef::new(3u32)))?;
+        global("i32").set(Val::I32(10))?;
+        global("v128").set(Val::V128(11))?;
+
+        let snapshot = instance.snapshot()?;
+        assert_eq!(snapshot.memories[0].len(), 2 * WASM_PAGE_SIZE);
+        assert_eq!(snapshot.tables[0].len(), 2);
+        // The constant global isn't captured.
+        assert_eq!(snapshot.globals.len(), 5);
+
+        memory.view::<u8>()[WASM_PAGE_SIZE + 1].set(0);
+        funcs.set(0, Val::FuncRef(None))?;
+        externs.set(0, Val::ExternRef(ExternRef::null()))?;
+        global("i32").set(Val::I32(0))?;
+        global("f64").set(Val::F64(0.0))?;
+        instance.restore(&snapshot)?;
+
+        assert_eq!(memory.view::<u8>()[WASM_PAGE_SIZE + 1].get(), 42);
+        assert_eq!(
+            call_element(&instance, 0)?,
+            vec![Val::I32(1)].into_boxed_slice()
+        );
+        assert_eq!(
+            call_element(&instance, 1)?,
+            vec![Val::I32(2)].into_boxed_slice()
+        );
+        match externs.get(0) {
+            Some(Val::ExternRef(extern_ref)) => assert_eq!(extern_ref.downcast::<u32>(), Some(&3)),
+            element => panic!("element 0 is {:?}", element),
+        }
+        assert_eq!(global("i32").get(), Val::I32(10));
+        assert_eq!(global("i64").get(), Val::I64(2));
+        assert_eq!(global("f32").get(), Val::F32(3.0));
+        assert_eq!(global("f64").get(), Val::F64(4.0));
+        assert_eq!(global("v128").get(), Val::V128(11));
+
+        // Another instance of the module grows to the size of the
+        // snapshot.
+        let other = Instance::new(&module, &imports! {})?;
+        other.restore(&snapshot)?;
+        assert_eq!(other.exports.get_memory("memory")?.size(), Pages(2));
+        assert_eq!(
+            call_element(&other, 1)?,
+            vec![Val::I32(2)].into_boxed_slice()
+        );
+
+        // A memory can't shrink back to the size of the snapshot.
+        memory.grow(1)?;
+        assert!(matches!(
+            instance.restore(&snapshot),
+            Err(SnapshotError::Mismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "enable-serde")]
+    #[test]
+    fn snapshot_is_serializable() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, SNAPSHOT_WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let global = instance.exports.get_global("f32")?;
+
+        global.set(Val::F32(1.5))?;
+        let snapshot = instance.snapshot()?;
+        let bytes = bincode::serialize(&snapshot)?;
+        global.set(Val::F32(0.0))?;
+        let deserialized: InstanceSnapshot = bincode::deserialize(&bytes)?;
+        instance.restore(&deserialized)?;
+        assert_eq!(global.get(), Val::F32(1.5));
+
+        // `externref`s don't survive the serialization.
+        let externs = instance.exports.get_table("externs")?;
+        externs.set(0, Val::ExternRef(ExternRef::new(3u32)))?;
+        let snapshot = instance.snapshot()?;
+        let deserialized: InstanceSnapshot = bincode::deserialize(&bincode::serialize(&snapshot)?)?;
+        assert!(matches!(
+            instance.restore(&deserialized),
+            Err(SnapshotError::MissingExternRef)
+        ));
+        instance.restore(&snapshot)?;
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_rejects_foreign_functions() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, SNAPSHOT_WAT)?;
+        let instance = Instance::new(&module, &imports! {})?;
+
+        // Tables can't hold functions from outside the instance.
+        let host = Function::new_native(&store, || 3);
+        instance
+            .exports
+            .get_table("funcs")?
+            .set(1, Val::FuncRef(Some(host)))?;
+        assert!(matches!(
+            instance.snapshot(),
+            Err(SnapshotError::ForeignFunction(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn live_instances_are_listed_with_their_tags() -> Result<()> {
+        let store = Store::default();
+        let engine = store.engine();
+        let first = Module::new(&store, r#"(module $first (memory (export "memory") 1))"#)?;
+        let second = Module::new(&store, "(module $second)")?;
+        assert_ne!(first.hash(), second.hash());
+
+        let a = Instance::new(&first, &imports! {})?;
+        assert_eq!(a.set_tag("tenant", "a"), None);
+        let b = Instance::new(&first, &imports! {})?;
+        b.set_tag("tenant", "b");
+        let c = Instance::new(&second, &imports! {})?;
+        c.set_tag("tenant", "a");
+        assert_eq!(c.set_tag("tenant", "c"), Some("a".to_string()));
+        let not_tagged = Instance::new(&second, &imports! {})?;
+
+        let live = engine.live_instances();
+        assert_eq!(live.len(), 4);
+        let tenants = live
+            .iter()
+            .map(|instance| instance.tags.get("tenant").map(String::as_str))
+            .collect::<Vec<_>>();
+        assert_eq!(tenants, vec![Some("a"), Some("b"), Some("c"), None]);
+        assert_eq!(
+            live.iter()
+                .filter(|instance| instance.module_hash.as_deref() == first.hash())
+                .count(),
+            2
+        );
+        assert_eq!(live[0].module_name.as_deref(), Some("first"));
+        assert_eq!((live[0].memories, live[0].memory_bytes), (1, 65536));
+        assert_eq!((live[2].memories, live[2].memory_bytes), (0, 0));
+        assert!(live[0].created_at <= live[3].created_at);
+
+        // The memory stats are up to date.
+        a.exports.get_memory("memory")?.grow(1)?;
+        assert_eq!(engine.live_instances()[0].memory_bytes, 2 * 65536);
+
+        // An instance stays listed while one of its clones is alive.
+        let a_clone = a.clone();
+        drop(a);
+        assert_eq!(engine.live_instances().len(), 4);
+        assert_eq!(a_clone.get_tag("tenant").as_deref(), Some("a"));
+        drop(a_clone);
+        drop(b);
+        drop(not_tagged);
+
+        let live = engine.live_instances();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].tags.get("tenant").map(String::as_str), Some("c"));
+
+        // Instances of other engines aren't listed.
+        assert!(Store::default().engine().live_instances().is_empty());
+        drop(c);
+        assert!(engine.live_instances().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn live_instances_tolerate_concurrent_drops() -> Result<()> {
+        let store = Store::default();
+        let engine = store.engine().clone();
+        let module = Module::new(&store, "(module (memory 1))")?;
+        let instances = (0..64)
+            .map(|index| {
+                let instance = Instance::new(&module, &imports! {})?;
+                instance.set_tag("index", index.to_string());
+                Ok(instance)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let dropper = std::thread::spawn(move || {
+            for instance in instances {
+                drop(instance);
+            }
+        });
+
+        // The instances are dropped in order: once an instance is
+        // listed, the previous ones are dropped and never listed again.
+        let mut last_len = usize::MAX;
+        let mut first_alive = 0;
+        loop {
+            let live = engine.live_instances();
+            assert!(live.len() <= last_len);
+            let indices = live
+                .iter()
+                .map(|instance| instance.tags["index"].parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(indices.iter().all(|&index| index >= first_alive));
+            assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+            if let Some(&first) = indices.first() {
+                first_alive = first;
+            }
+            last_len = live.len();
+            if live.is_empty() {
+                break;
+            }
+        }
+        dropper.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn shared_env_is_updated_by_wasm_and_read_by_the_host() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "host" "increment" (func $increment (param i32) (result i32)))
+      (func (export "run") (param i32) (result i32)
+        (drop (call $increment (i32.const 1)))
+        (call $increment (local.get 0))))
+"#,
+        )?;
+
+        #[derive(WasmerEnv, Clone, Default)]
+        struct Counter {
+            count: i32,
+        }
+
+        fn increment(counter: &mut Counter, by: i32) -> i32 {
+            counter.count += by;
+            counter.count
+        }
+
+        let increment = Function::new_native_with_env_mut(&store, Counter::default(), increment);
+        let counter: SharedEnv<Counter> = increment.shared_env().unwrap();
+        assert!(increment.shared_env::<i32>().is_none());
+        let import_object = imports! {
+            "host" => {
+                "increment" => increment,
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.exports.get_native_function::<i32, i32>("run")?;
+
+        assert_eq!(run.call(10)?, 11);
+        assert_eq!(counter.with(|counter| counter.count), 11);
+
+        // The instance and the host share the same counter.
+        counter.with(|counter| counter.count = 100);
+        assert_eq!(run.call(2)?, 103);
+        assert_eq!(
+            instance
+                .clone()
+                .exports
+                .get_native_function::<i32, i32>("run")?
+                .call(0)?,
+            104
+        );
+        assert_eq!(counter.with(|counter| counter.count), 104);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_env_reentrant_calls_trap() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (import "host" "call_back" (func $call_back))
+      (func (export "run") (call $call_back)))
+"#,
+        )?;
+
+        #[derive(WasmerEnv, Clone, Default)]
+        struct Env {
+            calls: u32,
+            #[wasmer(export(name = "run"))]
+            run: LazyInit<Function>,
+        }
+
+        fn call_back(env: &mut Env) -> Result<(), RuntimeError> {
+            env.calls += 1;
+            env.run_ref().unwrap().call(&[])?;
+            Ok(())
+        }
+
+        let call_back = Function::new_native_with_env_mut(&store, Env::default(), call_back);
+        let env: SharedEnv<Env> = call_back.shared_env().unwrap();
+        let import_object = imports! {
+            "host" => {
+                "call_back" => call_back,
+            },
+        };
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.exports.get_function("run")?;
+
+        let error = run.call(&[]).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "the environment is already borrowed by this thread"
+        );
+        // The environment is released after the trap.
+        assert_eq!(env.with(|env| env.calls), 1);
+        assert_eq!(env.try_with(|env| env.calls), Ok(1));
+
+        Ok(())
+    }
+
+    /// Collects the events of the instantiations.
+    #[derive(Default)]
+    struct CollectingObserver {
+        events: std::sync::Mutex<Vec<(InstantiationPhase, Option<String>)>>,
+    }
+
+    impl InstantiationObserver for CollectingObserver {
+        fn on_event(&self, event: &InstantiationEvent<'_>) {
+            self.events
+                .lock()
+                .unwrap()
+                .push((event.phase, event.error.map(|error| error.to_string())));
+        }
+    }
+
+    #[test]
+    fn instantiation_observer_receives_every_phase() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            format!(
+                r#"
+    (module
+      (memory 2)
+      (table 3 funcref)
+      (func $start)
+      (elem (i32.const 0) $start $start)
+      (data (i32.const 0) "{}")
+      (data (i32.const 65536) "hello")
+      (start $start))
+"#,
+                "\\00".repeat(40000)
+            ),
+        )?;
+        let observer = std::sync::Arc::new(CollectingObserver::default());
+        store.set_instantiation_observer(Some(observer.clone()));
+
+        Instance::new(&module, &imports! {})?;
+
+        let phases = observer
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(phase, error)| {
+                assert_eq!(error, &None);
+                *phase
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases,
+            vec![
+                InstantiationPhase::ResolveImports,
+                InstantiationPhase::AllocateMemories,
+                InstantiationPhase::AllocateTables,
+                InstantiationPhase::ApplyElementSegments { elements: 2 },
+                InstantiationPhase::ApplyDataSegments { bytes: 40005 },
+                InstantiationPhase::Start,
+            ]
+        );
+
+        // The observer is only called while installed.
+        store.set_instantiation_observer(None);
+        Instance::new(&module, &imports! {})?;
+        assert_eq!(observer.events.lock().unwrap().len(), 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn instantiation_observer_receives_the_failing_phase() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+    (module
+      (memory 1)
+      (func $start unreachable)
+      (data (i32.const 0) "hello")
+      (start $start))
+"#,
+        )?;
+        let observer = std::sync::Arc::new(CollectingObserver::default());
+        store.set_instantiation_observer(Some(observer.clone()));
+
+        let error = Instance::new(&module, &imports! {}).unwrap_err();
+        assert!(matches!(error, InstantiationError::Start(_)));
+
+        let events = observer.events.lock().unwrap();
+        assert_eq!(
+            events.iter().map(|(phase, _)| *phase).collect::<Vec<_>>(),
+            vec![
+                InstantiationPhase::ResolveImports,
+                InstantiationPhase::AllocateMemories,
+                InstantiationPhase::AllocateTables,
+                InstantiationPhase::ApplyElementSegments { elements: 0 },
+                InstantiationPhase::ApplyDataSegments { bytes: 5 },
+                InstantiationPhase::Start,
+            ]
+        );
+        assert!(events[..5].iter().all(|(_, error)| error.is_none()));
+        assert_eq!(events[5].1.as_deref(), Some(error.to_string().as_str()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_data_is_typed_and_shared_by_clones() -> Result<()> {
+        #[derive(Debug, PartialEq)]
+        struct Context {
+            tenant: &'static str,
+            calls: u32,
+        }
+
+        let store = Store::default();
+        let module = Module::new(&store, "(module)")?;
+        let instance = Instance::new(&module, &imports! {})?;
+        assert!(instance.host_data::<Context>().is_none());
+
+        let previous = instance.set_host_data(Box::new(Context {
+            tenant: "acme",
+            calls: 0,
+        }));
+        assert!(previous.is_none());
+        let clone = instance.clone();
+        clone.host_data::<Context>().unwrap().calls += 1;
+        assert_eq!(
+            *instance.host_data::<Context>().unwrap(),
+            Context {
+                tenant: "acme",
+                calls: 1
+            }
+        );
+        assert!(instance.host_data::<u32>().is_none());
+
+        let previous = clone.set_host_data(Box::new(42u32)).unwrap();
+        assert_eq!(previous.downcast_ref::<Context>().unwrap().calls, 1);
+        assert_eq!(*instance.host_data::<u32>().unwrap(), 42);
+        assert!(instance.host_data::<Context>().is_none());
+
+        Ok(())
+    }
 }
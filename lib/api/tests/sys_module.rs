@@ -248,4 +248,1003 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn validate_accepts_valid_modules() -> Result<()> {
+        let store = Store::default();
+        let wasm = wat2wasm(
+            br#"(module
+    (func (export "add_one") (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        i32.add))"#,
+        )?;
+        Module::validate(&store, &wasm)?;
+        Module::validate_with_engine(store.engine().as_ref(), &wasm)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_rejects_malformed_modules() -> Result<()> {
+        let store = Store::default();
+        let engine = store.engine().as_ref();
+
+        let bad_magic = Module::validate_with_engine(engine, b"\0wat\x01\0\0\0").unwrap_err();
+        assert_eq!(bad_magic.offset, 0);
+
+        let mut errors = vec![bad_magic];
+        for wat in &[
+            // Type-mismatched call.
+            &br#"(module
+    (func $f (param i32))
+    (func (call $f (i64.const 0))))"#[..],
+            // Out-of-bounds table index.
+            &br#"(module
+    (type (func))
+    (table 1 funcref)
+    (func (call_indirect (table 1) (type 0) (i32.const 0))))"#[..],
+            // Invalid memory limits.
+            &br#"(module (memory 2 1))"#[..],
+        ] {
+            let wasm = wat2wasm(wat)?;
+            let error = Module::validate_with_engine(engine, &wasm).unwrap_err();
+            assert!(error.offset > 0 && error.offset < wasm.len());
+            errors.push(error);
+        }
+
+        for (i, a) in errors.iter().enumerate() {
+            for b in &errors[i + 1..] {
+                assert_ne!(a.message, b.message);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_does_not_need_a_compiler() -> Result<()> {
+        let engine = Universal::headless().engine();
+        let wasm = wat2wasm(br#"(module (memory 2 1))"#)?;
+        assert!(Module::validate_with_engine(&engine, &wasm).is_err());
+        Module::validate_with_engine(&engine, &wat2wasm(br#"(module (memory 1 2))"#)?)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_contract_verify() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "host" "log" (func (param i32)))
+    (import "host" "memory" (memory 1))
+    (global (export "answer") i32 (i32.const 42)))"#,
+        )?;
+        let contract = module.export_import_contract();
+        assert_eq!(
+            contract.imports[0],
+            ContractImport {
+                module: "host".to_string(),
+                name: "log".to_string(),
+                ty: ContractType::Function {
+                    params: vec![ContractValueType::I32],
+                    results: vec![],
+                },
+            }
+        );
+        assert_eq!(contract.exports[0].name, "answer");
+        #[cfg(feature = "enable-serde")]
+        let contract = ImportContract::from_json(&contract.to_json())?;
+
+        let memory = Memory::new(&store, MemoryType::new(2, None, false))?;
+        let satisfying = imports! {
+            "host" => {
+                "log" => Function::new_native(&store, |_: i32| {}),
+                "memory" => memory,
+            }
+        };
+        assert!(contract.verify_against(&satisfying).is_empty());
+
+        let unsatisfying = imports! {
+            "host" => {
+                "log" => Function::new_native(&store, |_: i64| {}),
+            }
+        };
+        let violations = contract.verify_against(&unsatisfying);
+        assert_eq!(violations.len(), 2);
+        assert!(matches!(
+            &violations[0],
+            ContractViolation::Incompatible { name, found: ContractType::Function { params, .. }, .. }
+                if name == "log" && params == &[ContractValueType::I64]
+        ));
+        assert_eq!(
+            violations[1],
+            ContractViolation::Missing {
+                module: "host".to_string(),
+                name: "memory".to_string(),
+            }
+        );
+
+        #[cfg(feature = "enable-serde")]
+        assert!(ImportContract::from_json("{}").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_contract_diff() -> Result<()> {
+        let store = Store::default();
+        let contract = |wat: &str| -> Result<ImportContract> {
+            Ok(Module::new(&store, wat)?.export_import_contract())
+        };
+        let older = contract(
+            r#"(module
+    (import "host" "log" (func (param i32)))
+    (import "host" "exit" (func (param i32)))
+    (func (export "run")))"#,
+        )?;
+        assert!(ImportContract::diff(&older, &older).is_empty());
+
+        let newer = contract(
+            r#"(module
+    (import "host" "log" (func (param i64)))
+    (import "host" "time" (func (result i64)))
+    (func (export "run")))"#,
+        )?;
+        let changes = ImportContract::diff(&older, &newer);
+        assert_eq!(changes.len(), 3);
+        assert!(
+            matches!(&changes[0], ContractChange::ImportChanged { after, .. } if after.name == "log")
+        );
+        assert!(
+            matches!(&changes[1], ContractChange::ImportRemoved(import) if import.name == "exit")
+        );
+        assert!(
+            matches!(&changes[2], ContractChange::ImportAdded(import) if import.name == "time")
+        );
+        assert!(changes[0].is_breaking());
+        assert!(!changes[1].is_breaking());
+        assert!(changes[2].is_breaking());
+
+        // Removing an import only is compatible.
+        let newer = contract(
+            r#"(module
+    (import "host" "log" (func (param i32)))
+    (func (export "run")))"#,
+        )?;
+        let changes = ImportContract::diff(&older, &newer);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes.iter().any(ContractChange::is_breaking));
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_contract_memory64() -> Result<()> {
+        let memory64 = ExternType::Memory(MemoryType::new64(1, Some(2), false));
+        let ty = ContractType::from(&memory64);
+        assert_eq!(
+            ty,
+            ContractType::Memory {
+                minimum: 1,
+                maximum: Some(2),
+                shared: false,
+                memory64: true,
+            }
+        );
+        assert_eq!(ty.to_extern_type(), memory64);
+
+        // A 32-bit memory doesn't satisfy a 64-bit one.
+        let memory32 = ExternType::Memory(MemoryType::new(1, Some(2), false));
+        assert_ne!(ContractType::from(&memory32), ty);
+        assert!(!memory32.is_compatible_with(&ty.to_extern_type()));
+
+        // The contracts written before memory64 are 32-bit.
+        #[cfg(feature = "enable-serde")]
+        {
+            let contract = ImportContract::from_json(
+                r#"{"version": 1, "imports": [{"module": "host", "name": "memory",
+                    "type": {"kind": "memory", "minimum": 1, "maximum": null, "shared": false}}],
+                    "exports": []}"#,
+            )?;
+            assert!(matches!(
+                contract.imports[0].ty,
+                ContractType::Memory {
+                    memory64: false,
+                    ..
+                }
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn patch_artifact_metadata() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (memory (export "memory") 1)
+    (data (i32.const 0) "a large data segment, a large data segment")
+    (func (export "first") (result i32)
+        i32.const 0
+        i32.load8_u))"#;
+        let module = Module::new(&store, wat)?;
+        let artifact = module.serialize()?;
+        assert!(artifact::metadata(&artifact)?.is_empty());
+
+        let patched = artifact::patch_metadata(
+            &artifact,
+            &[("license", &b"key-1"[..]), ("build", &b"42"[..])],
+        )?;
+        let patched = artifact::patch_metadata(&patched, &[("license", &b"key-2"[..])])?;
+        // The serialized module is left as is.
+        assert_eq!(&patched[..artifact.len()], &artifact[..]);
+        assert_eq!(
+            artifact::metadata(&patched)?,
+            vec![
+                ("license".to_string(), b"key-2".to_vec()),
+                ("build".to_string(), b"42".to_vec()),
+            ]
+        );
+
+        let module = unsafe { Module::deserialize(&store, &patched)? };
+        let instance = Instance::new(&module, &imports! {})?;
+        let first: NativeFunc<(), i32> = instance.exports.get_native_function("first")?;
+        assert_eq!(first.call()?, b'a' as i32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn patch_artifact_metadata_file_in_place() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, r#"(module (func (export "f")))"#)?;
+        let artifact = module.serialize()?;
+        let file = tempfile::NamedTempFile::new()?;
+        module.serialize_to_file(file.path())?;
+
+        artifact::patch_metadata_file(file.path(), &[("release", &b"a long release id"[..])])?;
+        artifact::patch_metadata_file(file.path(), &[("release", &b"short"[..])])?;
+        let patched = std::fs::read(file.path())?;
+        // The file is truncated after the shorter metadata.
+        assert_eq!(
+            patched,
+            artifact::patch_metadata(&artifact, &[("release", &b"short"[..])])?
+        );
+        assert_eq!(&patched[..artifact.len()], &artifact[..]);
+
+        let module = unsafe { Module::deserialize_from_file(&store, file.path())? };
+        Instance::new(&module, &imports! {})?;
+
+        std::fs::write(file.path(), b"not an artifact")?;
+        assert!(matches!(
+            artifact::patch_metadata_file(file.path(), &[]),
+            Err(artifact::PatchError::Incompatible(_))
+        ));
+        assert!(matches!(
+            artifact::patch_metadata_file(file.path().with_extension("missing"), &[]),
+            Err(artifact::PatchError::Io(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn patch_artifact_metadata_errors() -> Result<()> {
+        let store = Store::default();
+        let wasm = wat2wasm(b"(module)")?;
+        assert!(matches!(
+            artifact::patch_metadata(&wasm, &[]),
+            Err(artifact::PatchError::Incompatible(_))
+        ));
+
+        let artifact = Module::new(&store, &wasm)?.serialize()?;
+        let mut patched = artifact::patch_metadata(&artifact, &[("license", &b"key"[..])])?;
+        let last = patched.len() - 1;
+        patched[last] ^= 1;
+        assert!(matches!(
+            artifact::metadata(&patched),
+            Err(artifact::PatchError::Corrupted(_))
+        ));
+        let mut trailing = artifact.to_vec();
+        trailing.extend_from_slice(b"garbage");
+        assert!(matches!(
+            artifact::patch_metadata(&trailing, &[]),
+            Err(artifact::PatchError::Corrupted(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sandboxed_compile_produces_artifact() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (func (export "add_one") (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        i32.add))"#;
+        let artifact = sandboxed_compile(&store, wat, &SandboxLimits::default())?;
+        let module = unsafe { Module::deserialize(&store, &artifact)? };
+        let instance = Instance::new(&module, &imports! {})?;
+        let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+        assert_eq!(add_one.call(41)?, 42);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sandboxed_compile_reports_failures() -> Result<()> {
+        let store = Store::default();
+        match sandboxed_compile(
+            &store,
+            "(module (func (result i32)))",
+            &SandboxLimits::default(),
+        ) {
+            Err(SandboxError::Compile(_)) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        // An address space limit this low makes the compiler abort on
+        // its first allocation; the crash must stay in the child.
+        let limits = SandboxLimits {
+            max_memory: Some(1),
+            ..Default::default()
+        };
+        match sandboxed_compile(&store, "(module)", &limits) {
+            Err(SandboxError::Signaled(_)) | Err(SandboxError::Compile(_)) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        Ok(())
+    }
+
+    /// A middleware crashing the compiler on the functions it
+    /// instruments.
+    #[cfg(all(unix, feature = "cranelift"))]
+    #[derive(Debug, loupe::MemoryUsage)]
+    struct Crash;
+
+    #[cfg(all(unix, feature = "cranelift"))]
+    impl ModuleMiddleware for Crash {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            std::process::abort()
+        }
+    }
+
+    #[test]
+    #[cfg(all(unix, feature = "cranelift"))]
+    fn sandboxed_compile_survives_compiler_crashes() -> Result<()> {
+        let mut compiler = Cranelift::default();
+        compiler.push_middleware(std::sync::Arc::new(Crash));
+        let store = Store::new(&Universal::new(compiler).engine());
+        match sandboxed_compile(
+            &store,
+            r#"(module (func (export "crash")))"#,
+            &SandboxLimits::default(),
+        ) {
+            Err(SandboxError::Signaled(signal)) => assert_eq!(signal, libc::SIGABRT),
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, feature = "cranelift"))]
+    fn sandboxed_compile_after_compiling_in_the_host() -> Result<()> {
+        // Enough functions for the compiler to compile them in
+        // parallel, in the thread pools whose threads the child
+        // doesn't have.
+        let wat = format!(
+            "(module {})",
+            (0..64)
+                .map(|i| format!(r#"(func (export "f{}") (result i32) i32.const {})"#, i, i))
+                .collect::<String>()
+        );
+        for threads in [None, Some(2)] {
+            let mut compiler = Cranelift::default();
+            if let Some(threads) = threads {
+                compiler.num_compile_threads(threads);
+            }
+            let store = Store::new(&Universal::new(compiler).engine());
+            Module::new(&store, &wat)?;
+            let artifact = sandboxed_compile(&store, &wat, &SandboxLimits::default())?;
+            let module = unsafe { Module::deserialize(&store, &artifact)? };
+            let instance = Instance::new(&module, &imports! {})?;
+            let f63: NativeFunc<(), i32> = instance.exports.get_native_function("f63")?;
+            assert_eq!(f63.call()?, 63);
+        }
+
+        Ok(())
+    }
+
+    /// A reader handing out at most 1 KB per read, waiting a bit before
+    /// each of them like a slow network connection would.
+    #[cfg(feature = "streaming")]
+    struct SlowReader<'a> {
+        bytes: &'a [u8],
+    }
+
+    #[cfg(feature = "streaming")]
+    impl SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> usize {
+            std::thread::sleep(std::time::Duration::from_micros(500));
+            let len = buf.len().min(self.bytes.len()).min(1024);
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            len
+        }
+    }
+
+    #[cfg(feature = "streaming")]
+    impl futures_io::AsyncRead for SlowReader<'_> {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::task::Poll::Ready(Ok(self.read(buf)))
+        }
+    }
+
+    /// Runs a future to completion on the current thread.
+    #[cfg(feature = "streaming")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        use std::thread::{self, Thread};
+
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "streaming")]
+    fn new_streaming() -> Result<()> {
+        use std::time::Instant;
+
+        let mut wat = String::from("(module (memory 1)");
+        for i in 0..500 {
+            wat.push_str(&format!(
+                "(func (export \"f{}\") (param i32) (result i32) local.get 0",
+                i
+            ));
+            for _ in 0..100 {
+                wat.push_str(" i32.const 3 i32.mul i32.const 1023 i32.and i32.load offset=8");
+            }
+            wat.push(')');
+        }
+        wat.push_str("(data (i32.const 0) \"hello\"))");
+        let bytes = wat2wasm(wat.as_bytes())?.into_owned();
+        let store = Store::default();
+
+        // Download the whole binary, then compile it.
+        let start = Instant::now();
+        let mut reader = SlowReader { bytes: &bytes };
+        let mut downloaded = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let read = reader.read(&mut buf);
+            if read == 0 {
+                break;
+            }
+            downloaded.extend_from_slice(&buf[..read]);
+        }
+        let module = Module::new(&store, &downloaded)?;
+        let buffered = start.elapsed();
+
+        // Compile the binary while it is downloaded.
+        let start = Instant::now();
+        let streamed_module =
+            block_on(Module::new_streaming(&store, SlowReader { bytes: &bytes }))?;
+        let streamed = start.elapsed();
+
+        assert_eq!(
+            streamed_module.exports().collect::<Vec<_>>(),
+            module.exports().collect::<Vec<_>>()
+        );
+        assert!(
+            streamed < buffered,
+            "streaming took {:?}, downloading then compiling took {:?}",
+            streamed,
+            buffered
+        );
+
+        let instance = Instance::new(&streamed_module, &imports! {})?;
+        let f0: NativeFunc<i32, i32> = instance.exports.get_native_function("f0")?;
+        f0.call(0)?;
+
+        Ok(())
+    }
+
+    /// A middleware exporting a global of its own, which, like
+    /// metering, transforms a single module.
+    #[cfg(all(feature = "streaming", feature = "cranelift"))]
+    #[derive(Debug, Default, loupe::MemoryUsage)]
+    struct ExportGlobal {
+        transformed: std::sync::atomic::AtomicBool,
+    }
+
+    #[cfg(all(feature = "streaming", feature = "cranelift"))]
+    #[derive(Debug)]
+    struct Passthrough;
+
+    #[cfg(all(feature = "streaming", feature = "cranelift"))]
+    impl FunctionMiddleware for Passthrough {}
+
+    #[cfg(all(feature = "streaming", feature = "cranelift"))]
+    impl ModuleMiddleware for ExportGlobal {
+        fn transform_module_info(&self, module_info: &mut wasmer_types::ModuleInfo) {
+            use std::sync::atomic::Ordering;
+
+            assert!(
+                !self.transformed.swap(true, Ordering::SeqCst),
+                "transformed a second module"
+            );
+            let index = module_info
+                .globals
+                .push(GlobalType::new(Type::I32, Mutability::Const));
+            module_info
+                .global_initializers
+                .push(GlobalInit::I32Const(7));
+            module_info
+                .exports
+                .insert("middleware".to_string(), ExportIndex::Global(index));
+        }
+
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            Box::new(Passthrough)
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "streaming", feature = "cranelift"))]
+    fn new_streaming_transforms_the_module_once() -> Result<()> {
+        let bytes = wat2wasm(
+            br#"(module $streamed (func $answer (export "answer") (result i32) i32.const 42))"#,
+        )?
+        .into_owned();
+        let mut compiler = Cranelift::default();
+        compiler.push_middleware(std::sync::Arc::new(ExportGlobal::default()));
+        let store = Store::new(&Universal::new(compiler).engine());
+        let module = block_on(Module::new_streaming(&store, SlowReader { bytes: &bytes }))?;
+
+        // The names come after the code section.
+        assert_eq!(module.name(), Some("streamed"));
+        let instance = Instance::new(&module, &imports! {})?;
+        assert_eq!(
+            instance.exports.get_global("middleware")?.get(),
+            Value::I32(7)
+        );
+        let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+        assert_eq!(answer.call()?, 42);
+
+        Ok(())
+    }
+
+    /// A reader compiling another module with the store of the
+    /// streamed one before each read.
+    #[cfg(feature = "streaming")]
+    struct CompilingReader<'a> {
+        reader: SlowReader<'a>,
+        store: &'a Store,
+    }
+
+    #[cfg(feature = "streaming")]
+    impl futures_io::AsyncRead for CompilingReader<'_> {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            Module::new(self.store, "(module (func (export \"f\")))").unwrap();
+            std::task::Poll::Ready(Ok(self.reader.read(buf)))
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "streaming")]
+    fn new_streaming_leaves_the_engine_unlocked() -> Result<()> {
+        // Enough code for the bodies to span many reads.
+        let mut wat = String::from("(module");
+        for i in 0..100 {
+            wat.push_str(&format!("(func (export \"f{}\") (result i32)", i));
+            for _ in 0..20 {
+                wat.push_str(" i32.const 1 i32.const 2 i32.add drop");
+            }
+            wat.push_str(" i32.const 0)");
+        }
+        wat.push(')');
+        let bytes = wat2wasm(wat.as_bytes())?.into_owned();
+        let store = Store::default();
+
+        // The modules compiled while the bodies are awaited don't wait
+        // for the download to end.
+        let reader = CompilingReader {
+            reader: SlowReader { bytes: &bytes },
+            store: &store,
+        };
+        let module = block_on(Module::new_streaming(&store, reader))?;
+        assert_eq!(module.exports().count(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "streaming")]
+    fn new_streaming_rejects_invalid_modules() {
+        let bytes = wat2wasm(b"(module (func (result i32)))").unwrap();
+        let store = Store::default();
+        match block_on(Module::new_streaming(&store, SlowReader { bytes: &bytes })) {
+            Err(IoCompileError::Compile(CompileError::Validate(_))) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    /// A module exercising each proposal, with the features it
+    /// requires.
+    fn proposal_modules() -> Vec<(&'static str, Features)> {
+        let with = |enable: fn(&mut Features)| {
+            let mut features = Features::none();
+            enable(&mut features);
+            features
+        };
+        vec![
+            (
+                "(module (func (result v128) (v128.const i32x4 0 0 0 0)))",
+                with(|f| f.simd = true),
+            ),
+            (
+                "(module (memory 1 1 shared) (func (drop (i32.atomic.load (i32.const 0)))))",
+                with(|f| f.threads = true),
+            ),
+            (
+                "(module (memory 1) (func (memory.copy (i32.const 0) (i32.const 0) (i32.const 0))))",
+                with(|f| f.bulk_memory = true),
+            ),
+            (
+                "(module (func (param externref)))",
+                with(|f| f.reference_types = true),
+            ),
+            (
+                "(module (func (result i32 i32) (i32.const 0) (i32.const 1)))",
+                with(|f| f.multi_value = true),
+            ),
+            (
+                "(module (func $f (return_call $f)))",
+                with(|f| f.tail_call = true),
+            ),
+            ("(module (memory i64 1))", with(|f| f.memory64 = true)),
+            // Sign-extension operators are part of the baseline.
+            (
+                "(module (func (param i32) (result i32) (i32.extend8_s (local.get 0))))",
+                Features::none(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn features_required_by_each_proposal() -> Result<()> {
+        for (wat, expected) in proposal_modules() {
+            let wasm = wat2wasm(wat.as_bytes())?;
+            assert_eq!(features_required(&wasm)?, expected, "{}", wat);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn features_required_agree_with_validation() -> Result<()> {
+        let validate = |features: &Features, wasm: &[u8]| {
+            let engine = Universal::headless().features(features.clone()).engine();
+            Module::validate_with_engine(&engine, wasm)
+        };
+
+        for (wat, _) in proposal_modules() {
+            let wasm = wat2wasm(wat.as_bytes())?;
+            let required = features_required(&wasm)?;
+            validate(&required, &wasm)?;
+            // The module is rejected as soon as a feature is missing.
+            if required != Features::none() {
+                assert!(validate(&Features::none(), &wasm).is_err(), "{}", wat);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn features_required_by_a_kitchen_sink_module() -> Result<()> {
+        let wasm = wat2wasm(
+            br#"(module
+    (memory 1 1 shared)
+    (func $f (param externref) (result i32 v128)
+        (memory.copy (i32.const 0) (i32.const 0) (i32.const 0))
+        (i32.atomic.load (i32.const 0))
+        (v128.const i64x2 0 0))
+    (func $g (param externref) (result i32 v128)
+        (return_call $f (local.get 0))))"#,
+        )?;
+        let required = features_required(&wasm)?;
+
+        let mut expected = Features::none();
+        expected.threads = true;
+        expected.simd = true;
+        expected.bulk_memory = true;
+        expected.reference_types = true;
+        expected.multi_value = true;
+        expected.tail_call = true;
+        assert_eq!(required, expected);
+
+        let engine = Universal::headless().features(required.clone()).engine();
+        Module::validate_with_engine(&engine, &wasm)?;
+        let mut without_tail_calls = required;
+        without_tail_calls.tail_call = false;
+        let engine = Universal::headless().features(without_tail_calls).engine();
+        assert!(Module::validate_with_engine(&engine, &wasm).is_err());
+
+        assert!(features_required(b"\0asm\x01\0\0\0\x01").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn required_features_of_a_compiled_module() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (global v128 (v128.const i64x2 0 0))
+    (data "passive")
+    (func (param externref) (result i32 i32)
+        (i32.const 0)
+        (i32.const 1)))"#,
+        )?;
+
+        let mut expected = Features::none();
+        expected.simd = true;
+        expected.bulk_memory = true;
+        expected.reference_types = true;
+        expected.multi_value = true;
+        assert_eq!(module.required_features(), expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn types_are_distinct_and_survive_serialization() -> Result<()> {
+        let store = Store::default();
+        assert!(Module::new(&store, "(module)")?.types().is_empty());
+
+        let module = Module::new(
+            &store,
+            r#"(module
+    (import "host" "log" (func (param i32)))
+    (func (export "first") (param i32))
+    (func (export "second") (param f64) (result f64) (local.get 0))
+    (func (export "third") (param i32)))"#,
+        )?;
+        let expected = vec![
+            FunctionType::new(vec![Type::I32], vec![]),
+            FunctionType::new(vec![Type::F64], vec![Type::F64]),
+        ];
+        assert_eq!(module.types(), expected);
+
+        let module = unsafe { Module::deserialize(&store, &module.serialize()?)? };
+        assert_eq!(module.types(), expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn imported_memories_and_tables_are_satisfied_automatically() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (import "env" "small" (memory 1 2))
+    (import "env" "func" (func))
+    (import "env" "functions" (table 2 funcref))
+    (import "host" "references" (table 1 8 externref))
+    (func (export "size") (result i32) (memory.size)))"#;
+        let module = Module::new(&store, wat)?;
+
+        let memories = module.imported_memories().collect::<Vec<_>>();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].module(), "env");
+        assert_eq!(memories[0].name(), "small");
+        assert_eq!(*memories[0].ty(), MemoryType::new(1, Some(2), false));
+        let tables = module.imported_tables().collect::<Vec<_>>();
+        assert_eq!(
+            tables
+                .iter()
+                .map(|table| (table.module(), table.name(), *table.ty()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("env", "functions", TableType::new(Type::FuncRef, 2, None)),
+                (
+                    "host",
+                    "references",
+                    TableType::new(Type::ExternRef, 1, Some(8))
+                ),
+            ]
+        );
+
+        let mut namespaces = std::collections::HashMap::new();
+        for import in module.imported_memories() {
+            let memory = Memory::new_for_import(&store, import.ty())?;
+            namespaces
+                .entry(import.module().to_string())
+                .or_insert_with(Exports::new)
+                .insert(import.name(), memory);
+        }
+        for import in module.imported_tables() {
+            let table = Table::new_for_import(&store, import.ty())?;
+            namespaces
+                .entry(import.module().to_string())
+                .or_insert_with(Exports::new)
+                .insert(import.name(), table);
+        }
+        namespaces
+            .get_mut("env")
+            .unwrap()
+            .insert("func", Function::new_native(&store, || {}));
+        let mut import_object = ImportObject::new();
+        for (namespace, exports) in namespaces {
+            import_object.register(namespace, exports);
+        }
+
+        let instance = Instance::new(&module, &import_object)?;
+        let size: NativeFunc<(), i32> = instance.exports.get_native_function("size")?;
+        assert_eq!(size.call()?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn incompatible_memory_imports_are_named() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, r#"(module (import "env" "bounded" (memory 1 2)))"#)?;
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let import_object = imports! {
+            "env" => {
+                "bounded" => memory,
+            },
+        };
+
+        let error = Instance::new(&module, &import_object).unwrap_err();
+        assert!(
+            error.to_string().contains(r#""env"."bounded""#),
+            "{}",
+            error
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn store_features_are_the_features_of_its_engine() -> Result<()> {
+        let store = Store::default();
+        assert_eq!(store.features(), &store.engine().features());
+        assert_eq!(store.clone().features(), store.features());
+
+        // The modules are compiled with the features of the store.
+        let simd = r#"(module (global v128 (v128.const i64x2 0 0)))"#;
+        assert_eq!(Module::new(&store, simd).is_ok(), store.features().simd);
+
+        Ok(())
+    }
+
+    #[test]
+    fn time_instantiation_of_a_data_heavy_module() -> Result<()> {
+        let store = Store::default();
+        // 4 MiB of data segments to copy at each instantiation.
+        let data = "x".repeat(1 << 20);
+        let wat = format!(
+            r#"(module
+    (memory (export "memory") 65)
+    (data (i32.const 0) "{data}")
+    (data (i32.const 0x100000) "{data}")
+    (data (i32.const 0x200000) "{data}")
+    (data (i32.const 0x300000) "{data}"))"#,
+            data = data
+        );
+        let module = Module::new(&store, wat)?;
+
+        let start = std::time::Instant::now();
+        let (instance, instantiation_time) = module.time_instantiation(&imports! {})?;
+        assert!(instantiation_time > std::time::Duration::from_secs(0));
+        assert!(instantiation_time <= start.elapsed());
+
+        // The data segments were copied during the instantiation.
+        let memory = instance.exports.get_memory("memory")?;
+        let view = memory.view::<u8>();
+        assert_eq!(view[0].get(), b'x');
+        assert_eq!(view[(4 << 20) - 1].get(), b'x');
+        assert_eq!(view[4 << 20].get(), 0);
+
+        // The module is compiled once, and can be instantiated again.
+        let (_, second_time) = module.time_instantiation(&imports! {})?;
+        assert!(second_time > std::time::Duration::from_secs(0));
+        Ok(())
+    }
+
+    #[test]
+    fn memory_init_image_matches_instantiation() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+    (memory (export "memory") 4)
+    (data (i32.const 0x10010) "abcdef")
+    (data (i32.const 0x10012) "XY")
+    (data (i32.const 0x2fffe) "end")
+    (data (i32.const 0x30000) "")
+    (data "passive"))"#,
+        )?;
+
+        let image = module.memory_init_image().unwrap();
+        assert_eq!(image.range(), 0x10000..0x40000);
+
+        let instance = Instance::new(&module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let contents = memory
+            .view::<u8>()
+            .iter()
+            .map(|cell| cell.get())
+            .collect::<Vec<_>>();
+        assert_eq!(&contents[image.range()], &image.data[..]);
+        assert_eq!(&image.data[0x10..0x16], b"abXYef");
+        assert!(contents[..image.offset].iter().all(|byte| *byte == 0));
+        assert!(contents[image.range().end..].iter().all(|byte| *byte == 0));
+
+        assert_eq!(
+            Module::new(&store, "(module (memory 1))")?.memory_init_image(),
+            Some(MemoryInitImage::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn memory_init_image_depends_on_instantiation() -> Result<()> {
+        let store = Store::default();
+        for wat in [
+            "(module)",
+            r#"(module (import "env" "memory" (memory 1)) (data (i32.const 0) "a"))"#,
+            r#"(module
+    (import "env" "base" (global i32))
+    (memory 1)
+    (data (global.get 0) "a"))"#,
+            r#"(module (memory 1) (data (i32.const 0xffff) "ab"))"#,
+        ] {
+            let module = Module::new(&store, wat)?;
+            assert_eq!(module.memory_init_image(), None, "{}", wat);
+        }
+        Ok(())
+    }
 }
@@ -341,6 +341,47 @@ mod sys {
         Ok(())
     }
 
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_string_through_table() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (table $table (export "table") 2 externref)
+    (func (export "store") (param $er externref) (param $idx i32)
+          (table.set $table (local.get $idx) (local.get $er)))
+    (func (export "load") (param $idx i32) (result externref)
+          (table.get $table (local.get $idx)))
+)"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let store_fn = instance.exports.get_function("store")?;
+        let load_fn = instance.exports.get_function("load")?;
+        let table: &Table = instance.exports.get_table("table")?;
+
+        let er = ExternRef::new("hello, externref".to_string());
+        store_fn.call(&[Val::ExternRef(er.clone()), Val::I32(1)])?;
+
+        let stored = table.get(1).unwrap().externref().unwrap();
+        assert_eq!(
+            stored.downcast::<String>().map(String::as_str),
+            Some("hello, externref")
+        );
+        // The other element is still the null reference.
+        assert!(table.get(0).unwrap().externref().unwrap().is_null());
+
+        match &load_fn.call(&[Val::I32(1)])?[..] {
+            [Val::ExternRef(loaded)] => {
+                assert_eq!(loaded.downcast::<String>(), er.downcast::<String>());
+                assert!(loaded.downcast::<u32>().is_none());
+            }
+            results => panic!("unexpected results {:?}", results),
+        }
+        drop(stored);
+        assert_eq!(er.strong_count(), 2);
+
+        Ok(())
+    }
+
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     #[test]
     // TODO(reftypes): reenable this test
@@ -497,4 +538,100 @@ mod sys {
 
         Ok(())
     }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_stats_after_table_overwrite() -> Result<()> {
+        let store = Store::default();
+        let table = Table::new(
+            &store,
+            TableType::new(Type::ExternRef, 4, None),
+            Value::ExternRef(ExternRef::null()),
+        )?;
+        assert_eq!(store.externref_stats(), ExternRefStats::default());
+
+        let er = ExternRef::new(3usize);
+        table.set(1, Value::ExternRef(er.clone()))?;
+        table.set(3, Value::ExternRef(er.clone()))?;
+        let stats = store.externref_stats();
+        assert_eq!(stats.live, 1);
+        assert_eq!(stats.strong_references, 3);
+
+        let holders = store.find_externref_holders(&er);
+        assert_eq!(holders.len(), 3);
+        assert!(matches!(&holders[0], HolderInfo::Table { table: t, index: 1 } if t.same(&table)));
+        assert!(matches!(&holders[1], HolderInfo::Table { table: t, index: 3 } if t.same(&table)));
+        assert!(matches!(holders[2], HolderInfo::Host { references: 1 }));
+        drop(holders);
+
+        table.set(1, Value::ExternRef(ExternRef::null()))?;
+        table.set(3, Value::ExternRef(ExternRef::null()))?;
+        assert_eq!(store.externref_stats(), ExternRefStats::default());
+        assert!(matches!(
+            store.find_externref_holders(&er)[..],
+            [HolderInfo::Host { references: 1 }]
+        ));
+        assert_eq!(er.strong_count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    #[test]
+    fn extern_ref_stats_after_instance_drop() -> Result<()> {
+        let store = Store::default();
+        let wat = r#"(module
+    (table $table (export "table") 2 externref)
+    (func (export "store") (param $er externref)
+        (table.set $table (i32.const 0) (local.get $er))
+        (table.set $table (i32.const 1) (local.get $er))))"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let store_ref: NativeFunc<ExternRef, ()> = instance.exports.get_native_function("store")?;
+
+        let er = ExternRef::new(String::from("leak?"));
+        store_ref.call(er.clone())?;
+        let stats = store.externref_stats();
+        assert_eq!(stats.live, 1);
+        assert_eq!(stats.strong_references, 3);
+        let table = instance.exports.get_table("table")?;
+        let holders = store.find_externref_holders(&er);
+        assert!(matches!(&holders[0], HolderInfo::Table { table: t, index: 0 } if t.same(table)));
+        assert!(matches!(&holders[1], HolderInfo::Table { table: t, index: 1 } if t.same(table)));
+        assert!(matches!(holders[2], HolderInfo::Host { references: 1 }));
+        drop(holders);
+
+        drop(store_ref);
+        drop(instance);
+        assert_eq!(store.externref_stats(), ExternRefStats::default());
+        assert_eq!(er.strong_count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(all(
+        feature = "experimental-reference-types-extern-ref",
+        feature = "debug-externref"
+    ))]
+    #[test]
+    fn extern_ref_stats_by_type() -> Result<()> {
+        use std::any::TypeId;
+
+        let store = Store::default();
+        let table = Table::new(
+            &store,
+            TableType::new(Type::ExternRef, 3, None),
+            Value::ExternRef(ExternRef::null()),
+        )?;
+        table.set(0, Value::ExternRef(ExternRef::new(1usize)))?;
+        table.set(1, Value::ExternRef(ExternRef::new(2usize)))?;
+        table.set(2, Value::ExternRef(ExternRef::new(String::new())))?;
+
+        let by_type = store.externref_stats().by_type;
+        assert_eq!(by_type.len(), 2);
+        assert_eq!(by_type[&TypeId::of::<usize>()], 2);
+        assert_eq!(by_type[&TypeId::of::<String>()], 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,103 @@
+#[cfg(all(feature = "sys", feature = "enable-serde"))]
+mod sys {
+    use anyhow::Result;
+    use wasmer::*;
+
+    /// Serializes `value` to JSON and to bincode, and deserializes it
+    /// back from both.
+    fn round_trip(value: &Value) -> Result<[Value; 2]> {
+        let json: Value = serde_json::from_str(&serde_json::to_string(value)?)?;
+        let binary: Value = bincode::deserialize(&bincode::serialize(value)?)?;
+        Ok([json, binary])
+    }
+
+    #[test]
+    fn values_round_trip() -> Result<()> {
+        let values = [
+            Value::I32(0),
+            Value::I32(i32::MIN),
+            Value::I64(-1),
+            Value::I64(i64::MAX),
+            Value::F32(1.5),
+            Value::F64(-2.25),
+            Value::V128(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210),
+            Value::V128(u128::MAX),
+        ];
+        for value in values.iter() {
+            for deserialized in round_trip(value)?.iter() {
+                assert_eq!(deserialized, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn float_values_round_trip_bit_for_bit() -> Result<()> {
+        let f32s = [
+            f32::NAN,
+            -f32::NAN,
+            f32::from_bits(0x7fa0_0001),
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            -0.0,
+        ];
+        for value in f32s.iter() {
+            for deserialized in round_trip(&Value::F32(*value))?.iter() {
+                assert_eq!(deserialized.unwrap_f32().to_bits(), value.to_bits());
+            }
+        }
+
+        let f64s = [
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(0x7ff4_0000_0000_0001),
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            -0.0,
+        ];
+        for value in f64s.iter() {
+            for deserialized in round_trip(&Value::F64(*value))?.iter() {
+                assert_eq!(deserialized.unwrap_f64().to_bits(), value.to_bits());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn references_are_deserialized_as_null() -> Result<()> {
+        let store = Store::default();
+        let function = Function::new_native(&store, || {});
+
+        for deserialized in round_trip(&Value::FuncRef(Some(function)))?.iter() {
+            assert!(matches!(deserialized, Value::FuncRef(None)));
+        }
+        for deserialized in round_trip(&Value::ExternRef(ExternRef::null()))?.iter() {
+            assert!(matches!(deserialized, Value::ExternRef(r) if r.is_null()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn types_round_trip() -> Result<()> {
+        let types = [
+            ExternType::Function(FunctionType::new(
+                vec![Type::I32, Type::F64, Type::V128],
+                vec![Type::ExternRef, Type::FuncRef],
+            )),
+            ExternType::Global(GlobalType::new(Type::F32, Mutability::Var)),
+            ExternType::Memory(MemoryType::new(1, Some(2), true)),
+            ExternType::Table(TableType::new(Type::FuncRef, 0, None)),
+        ];
+        for ty in types.iter() {
+            let json: ExternType = serde_json::from_str(&serde_json::to_string(ty)?)?;
+            assert_eq!(&json, ty);
+            let binary: ExternType = bincode::deserialize(&bincode::serialize(ty)?)?;
+            assert_eq!(&binary, ty);
+        }
+
+        Ok(())
+    }
+}
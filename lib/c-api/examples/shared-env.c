@@ -0,0 +1,134 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include "wasmer.h"
+
+// The environment shared by the host functions, allocated on the
+// heap so that leaking it is reported by `make run-valgrind`.
+typedef struct {
+    int counter;
+} counter_env;
+
+static int finalized = 0;
+
+void finalize(void* data) {
+    free(data);
+    finalized += 1;
+}
+
+wasm_trap_t* increment(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+    counter_env* counter = (counter_env*) env;
+    counter->counter += args->data[0].of.i32;
+
+    return NULL;
+}
+
+wasm_trap_t* get(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+    counter_env* counter = (counter_env*) env;
+    results->data[0].kind = WASM_I32;
+    results->data[0].of.i32 = counter->counter;
+
+    return NULL;
+}
+
+int main(int argc, const char* argv[]) {
+    const char *wat_string =
+        "(module\n"
+        "  (import \"host\" \"increment\" (func $increment (param i32)))\n"
+        "  (import \"host\" \"get\" (func $get (result i32)))\n"
+        "  (func (export \"run\") (param i32) (result i32)\n"
+        "    (call $increment (local.get 0))\n"
+        "    (call $get)))";
+
+    wasm_byte_vec_t wat;
+    wasm_byte_vec_new(&wat, strlen(wat_string), wat_string);
+    wasm_byte_vec_t wasm_bytes;
+    wat2wasm(&wat, &wasm_bytes);
+    wasm_byte_vec_delete(&wat);
+
+    printf("Creating the store...\n");
+    wasm_engine_t* engine = wasm_engine_new();
+    wasm_store_t* store = wasm_store_new(engine);
+
+    printf("Compiling module...\n");
+    wasm_module_t* module = wasm_module_new(store, &wasm_bytes);
+
+    if (!module) {
+        printf("> Error compiling module!\n");
+
+        return 1;
+    }
+
+    wasm_byte_vec_delete(&wasm_bytes);
+
+    printf("Creating the shared environment...\n");
+    counter_env* data = malloc(sizeof(counter_env));
+    data->counter = 0;
+    wasmer_func_env_t* env = wasmer_func_env_new(data, finalize);
+
+    printf("Creating the host functions...\n");
+    wasm_functype_t* increment_type = wasm_functype_new_1_0(wasm_valtype_new_i32());
+    wasm_func_t* increment_func = wasm_func_new_with_shared_env(store, increment_type, increment, env);
+    wasm_functype_delete(increment_type);
+
+    wasm_functype_t* get_type = wasm_functype_new_0_1(wasm_valtype_new_i32());
+    wasm_func_t* get_func = wasm_func_new_with_shared_env(store, get_type, get, env);
+    wasm_functype_delete(get_type);
+
+    // The functions hold the environment from now on.
+    wasmer_func_env_delete(env);
+
+    wasm_extern_t* externs[] = { wasm_func_as_extern(increment_func), wasm_func_as_extern(get_func) };
+    wasm_extern_vec_t import_object = WASM_ARRAY_VEC(externs);
+
+    for (int i = 1; i <= 3; i++) {
+        printf("Instantiating module (%d)...\n", i);
+        wasm_instance_t* instance = wasm_instance_new(store, module, &import_object, NULL);
+
+        if (!instance) {
+            printf("> Error instantiating module!\n");
+
+            return 1;
+        }
+
+        wasm_extern_vec_t exports;
+        wasm_instance_exports(instance, &exports);
+        wasm_func_t* run_func = wasm_extern_as_func(exports.data[0]);
+
+        printf("Calling `run` function...\n");
+        wasm_val_t args_val[1] = { WASM_I32_VAL(i) };
+        wasm_val_t results_val[1] = { WASM_INIT_VAL };
+        wasm_val_vec_t args = WASM_ARRAY_VEC(args_val);
+        wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+
+        if (wasm_func_call(run_func, &args, &results)) {
+            printf("> Error calling the `run` function!\n");
+
+            return 1;
+        }
+
+        printf("Counter: %d\n", results_val[0].of.i32);
+
+        wasm_extern_vec_delete(&exports);
+        wasm_instance_delete(instance);
+    }
+
+    if (finalized != 0) {
+        printf("> The environment was finalized while in use!\n");
+
+        return 1;
+    }
+
+    printf("Deleting the host functions...\n");
+    wasm_func_delete(increment_func);
+    wasm_func_delete(get_func);
+
+    if (finalized != 1) {
+        printf("> The environment was finalized %d times instead of once!\n", finalized);
+
+        return 1;
+    }
+
+    wasm_module_delete(module);
+    wasm_store_delete(store);
+    wasm_engine_delete(engine);
+}
@@ -106,9 +106,29 @@ pub struct wasm_config_t {
     compiler: wasmer_compiler_t,
     #[cfg(feature = "middlewares")]
     pub(super) middlewares: Vec<wasmer_middleware_t>,
-    pub(super) nan_canonicalization: bool,
+    /// Whether the NaNs are canonicalized, `None` keeps the default
+    /// of the compiler.
+    pub(super) nan_canonicalization: Option<bool>,
+    pub(super) perf_diagnostics: bool,
+    pub(super) retain_wasm_bytes: bool,
+    pub(super) epoch_interruption: bool,
+    pub(super) deterministic: bool,
+    pub(super) compile_threads: Option<u32>,
     pub(super) features: Option<Box<wasmer_features_t>>,
     pub(super) target: Option<Box<wasmer_target_t>>,
+    pub(super) limits: StoreLimits,
+}
+
+/// The limits of the memories and the tables of the stores of an
+/// engine, see [`wasm_config_set_max_memory_pages`] and
+/// [`wasm_config_set_max_table_elements`].
+///
+/// [`wasm_config_set_max_memory_pages`]: super::unstable::engine::wasm_config_set_max_memory_pages
+/// [`wasm_config_set_max_table_elements`]: super::unstable::engine::wasm_config_set_max_table_elements
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct StoreLimits {
+    pub(crate) max_memory_pages: Option<u32>,
+    pub(crate) max_table_elements: Option<u32>,
 }
 
 /// Create a new default Wasmer configuration.
@@ -284,6 +304,7 @@ pub extern "C" fn wasm_config_set_engine(config: &mut wasm_config_t, engine: was
 #[repr(C)]
 pub struct wasm_engine_t {
     pub(crate) inner: Arc<dyn Engine + Send + Sync>,
+    pub(crate) limits: StoreLimits,
 }
 
 #[cfg(feature = "compiler")]
@@ -317,7 +338,10 @@ cfg_if! {
         pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
             let compiler_config: Box<dyn CompilerConfig> = get_default_compiler_config();
             let engine: Arc<dyn Engine + Send + Sync> = Arc::new(Universal::new(compiler_config).engine());
-            Box::new(wasm_engine_t { inner: engine })
+            Box::new(wasm_engine_t {
+                inner: engine,
+                limits: StoreLimits::default(),
+            })
         }
     } else if #[cfg(feature = "universal")] {
         /// Creates a new headless Universal engine.
@@ -330,7 +354,10 @@ cfg_if! {
         #[no_mangle]
         pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
             let engine: Arc<dyn Engine + Send + Sync> = Arc::new(Universal::headless().engine());
-            Box::new(wasm_engine_t { inner: engine })
+            Box::new(wasm_engine_t {
+                inner: engine,
+                limits: StoreLimits::default(),
+            })
         }
     } else if #[cfg(all(feature = "dylib", feature = "compiler"))] {
         /// Creates a new Dylib engine with the default compiler.
@@ -344,7 +371,10 @@ cfg_if! {
         pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
             let compiler_config: Box<dyn CompilerConfig> = get_default_compiler_config();
             let engine: Arc<dyn Engine + Send + Sync> = Arc::new(Dylib::new(compiler_config).engine());
-            Box::new(wasm_engine_t { inner: engine })
+            Box::new(wasm_engine_t {
+                inner: engine,
+                limits: StoreLimits::default(),
+            })
         }
     } else if #[cfg(feature = "dylib")] {
         /// Creates a new headless Dylib engine.
@@ -357,7 +387,10 @@ cfg_if! {
         #[no_mangle]
         pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
             let engine: Arc<dyn Engine + Send + Sync> = Arc::new(Dylib::headless().engine());
-            Box::new(wasm_engine_t { inner: engine })
+            Box::new(wasm_engine_t {
+                inner: engine,
+                limits: StoreLimits::default(),
+            })
         }
     }
     // There are currently no uses of the Staticlib engine + compiler from the C API.
@@ -373,7 +406,10 @@ cfg_if! {
         #[no_mangle]
         pub extern "C" fn wasm_engine_new() -> Box<wasm_engine_t> {
             let engine: Arc<dyn Engine + Send + Sync> = Arc::new(Staticlib::headless().engine());
-            Box::new(wasm_engine_t { inner: engine })
+            Box::new(wasm_engine_t {
+                inner: engine,
+                limits: StoreLimits::default(),
+            })
         }
     } else {
         /// Creates a new unknown engine, i.e. it will panic with an error message.
@@ -479,15 +515,32 @@ pub extern "C" fn wasm_engine_new_with_config(
                 compiler_config.push_middleware(middleware.inner);
             }
 
-            if config.nan_canonicalization {
-                compiler_config.canonicalize_nans(true);
+            if let Some(enable) = config.nan_canonicalization {
+                compiler_config.canonicalize_nans(enable);
+            }
+
+            if config.perf_diagnostics {
+                compiler_config.enable_perf_diagnostics(true);
+            }
+
+            if config.retain_wasm_bytes {
+                compiler_config.retain_wasm_bytes(true);
+            }
+
+            if config.epoch_interruption {
+                compiler_config.epoch_interruption(true);
             }
 
             let inner: Arc<dyn Engine + Send + Sync> = match config.engine {
                 wasmer_engine_t::UNIVERSAL => {
                     cfg_if! {
                         if #[cfg(feature = "universal")] {
-                            let mut builder = Universal::new(compiler_config);
+                            let mut builder = Universal::new(compiler_config)
+                                .deterministic(config.deterministic);
+
+                            if let Some(threads) = config.compile_threads {
+                                builder = builder.num_compile_threads(threads as usize);
+                            }
 
                             if let Some(target) = config.target {
                                 builder = builder.target(target.inner);
@@ -506,7 +559,12 @@ pub extern "C" fn wasm_engine_new_with_config(
                 wasmer_engine_t::DYLIB => {
                     cfg_if! {
                         if #[cfg(feature = "dylib")] {
-                            let mut builder = Dylib::new(compiler_config);
+                            let mut builder = Dylib::new(compiler_config)
+                                .deterministic(config.deterministic);
+
+                            if let Some(threads) = config.compile_threads {
+                                builder = builder.num_compile_threads(threads as usize);
+                            }
 
                             if let Some(target) = config.target {
                                 builder = builder.target(target.inner);
@@ -544,7 +602,10 @@ pub extern "C" fn wasm_engine_new_with_config(
                     }
                 },
             };
-            Some(Box::new(wasm_engine_t { inner }))
+            Some(Box::new(wasm_engine_t {
+                inner,
+                limits: config.limits,
+            }))
         } else {
             let inner: Arc<dyn Engine + Send + Sync> = match config.engine {
                 wasmer_engine_t::UNIVERSAL => {
@@ -605,7 +666,10 @@ pub extern "C" fn wasm_engine_new_with_config(
                     }
                 },
             };
-            Some(Box::new(wasm_engine_t { inner }))
+            Some(Box::new(wasm_engine_t {
+                inner,
+                limits: config.limits,
+            }))
         }
     }
 }
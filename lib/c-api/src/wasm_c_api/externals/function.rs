@@ -103,31 +103,62 @@ pub unsafe extern "C" fn wasm_func_new_with_env(
     let function_type = function_type?;
     let callback = callback?;
 
+    let function = new_with_env(
+        store,
+        function_type,
+        callback,
+        Arc::new(FuncEnv::new(env, env_finalizer)),
+    );
+
+    Some(Box::new(wasm_func_t::new(function)))
+}
+
+/// The environment of host functions, finalized once the last
+/// function using it is dropped.
+pub(crate) struct FuncEnv {
+    data: *mut c_void,
+    finalizer: Option<wasm_env_finalizer_t>,
+}
+
+impl FuncEnv {
+    pub(crate) fn new(data: *mut c_void, finalizer: Option<wasm_env_finalizer_t>) -> Self {
+        Self { data, finalizer }
+    }
+}
+
+// Only relevant when using multiple threads in the C API;
+// Synchronization will be done via the C API / on the C side.
+unsafe impl Send for FuncEnv {}
+unsafe impl Sync for FuncEnv {}
+
+impl Drop for FuncEnv {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.data) }
+        }
+    }
+}
+
+/// Creates a host function calling `callback` with the data of `env`.
+///
+/// The clones of the function, including the ones held by the
+/// instances importing it, share `env`.
+pub(crate) unsafe fn new_with_env(
+    store: &wasm_store_t,
+    function_type: &wasm_functype_t,
+    callback: wasm_func_callback_with_env_t,
+    env: Arc<FuncEnv>,
+) -> Function {
     let func_sig = &function_type.inner().function_type;
     let num_rets = func_sig.results().len();
 
     #[derive(Clone)]
-    #[repr(C)]
     struct WrapperEnv {
-        env: *mut c_void,
-        env_finalizer: Arc<Option<wasm_env_finalizer_t>>,
+        env: Arc<FuncEnv>,
     }
 
     impl wasmer_api::WasmerEnv for WrapperEnv {}
 
-    // Only relevant when using multiple threads in the C API;
-    // Synchronization will be done via the C API / on the C side.
-    unsafe impl Send for WrapperEnv {}
-    unsafe impl Sync for WrapperEnv {}
-
-    impl Drop for WrapperEnv {
-        fn drop(&mut self) {
-            if let Some(env_finalizer) = *self.env_finalizer {
-                unsafe { (env_finalizer)(self.env as _) }
-            }
-        }
-    }
-
     let trampoline = move |env: &WrapperEnv, args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
         let processed_args: wasm_val_vec_t = args
             .into_iter()
@@ -145,7 +176,7 @@ pub unsafe extern "C" fn wasm_func_new_with_env(
         ]
         .into();
 
-        let trap = callback(env.env, &processed_args, &mut results);
+        let trap = callback(env.env.data, &processed_args, &mut results);
 
         if let Some(trap) = trap {
             return Err(trap.inner);
@@ -161,17 +192,7 @@ pub unsafe extern "C" fn wasm_func_new_with_env(
         Ok(processed_results)
     };
 
-    let function = Function::new_with_env(
-        &store.inner,
-        func_sig,
-        WrapperEnv {
-            env,
-            env_finalizer: Arc::new(env_finalizer),
-        },
-        trampoline,
-    );
-
-    Some(Box::new(wasm_func_t::new(function)))
+    Function::new_with_env(&store.inner, func_sig, WrapperEnv { env }, trampoline)
 }
 
 #[no_mangle]
@@ -187,6 +208,18 @@ pub unsafe extern "C" fn wasm_func_call(
     func: Option<&wasm_func_t>,
     args: Option<&wasm_val_vec_t>,
     results: &mut wasm_val_vec_t,
+) -> Option<Box<wasm_trap_t>> {
+    call_with(func, args, results, |function, params| {
+        function.call(params)
+    })
+}
+
+/// Calls `func` with `call`, converting its arguments and results.
+pub(crate) unsafe fn call_with(
+    func: Option<&wasm_func_t>,
+    args: Option<&wasm_val_vec_t>,
+    results: &mut wasm_val_vec_t,
+    call: impl FnOnce(&Function, &[Val]) -> Result<Box<[Val]>, RuntimeError>,
 ) -> Option<Box<wasm_trap_t>> {
     let func = func?;
     let args = args?;
@@ -199,15 +232,9 @@ pub unsafe extern "C" fn wasm_func_call(
         .collect::<Result<Vec<Val>, _>>()
         .expect("Arguments conversion failed");
 
-    match func.inner.call(&params) {
+    match call(&func.inner, &params) {
         Ok(wasm_results) => {
-            for (slot, val) in results
-                .as_uninit_slice()
-                .iter_mut()
-                .zip(wasm_results.into_iter())
-            {
-                *slot = MaybeUninit::new(val.try_into().expect("Results conversion failed"));
-            }
+            write_results(results, wasm_results.into_vec());
 
             None
         }
@@ -215,6 +242,17 @@ pub unsafe extern "C" fn wasm_func_call(
     }
 }
 
+/// Writes the results of a call to `results`.
+pub(crate) fn write_results(results: &mut wasm_val_vec_t, wasm_results: Vec<Val>) {
+    for (slot, val) in results
+        .as_uninit_slice()
+        .iter_mut()
+        .zip(wasm_results.into_iter())
+    {
+        *slot = MaybeUninit::new(val.try_into().expect("Results conversion failed"));
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_param_arity(func: &wasm_func_t) -> usize {
     func.inner.ty().params().len()
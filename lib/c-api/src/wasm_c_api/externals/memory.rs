@@ -53,7 +53,20 @@ pub unsafe extern "C" fn wasm_memory_type(
     Some(Box::new(wasm_memorytype_t::new(memory.inner.ty().clone())))
 }
 
-// get a raw pointer into bytes
+/// Returns a pointer to the bytes of the memory.
+///
+/// A memory whose maximum size isn't reserved upfront (a dynamic
+/// memory) is relocated when it grows, which invalidates the pointer:
+/// it must be read again after any grow, including the ones done by
+/// a WebAssembly instance running `memory.grow`.
+///
+/// Under the threads proposal, a shared memory can grow while the
+/// host reads it. Shared memories are never relocated, so the pointer
+/// stays valid, but the size must be read with
+/// [`wasm_memory_data_size_atomic`] rather than
+/// `wasm_memory_data_size`: the bytes up to the size it returns can
+/// be accessed, preferably with atomic operations since the guest may
+/// write them concurrently.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memory_data(memory: &mut wasm_memory_t) -> *mut u8 {
     mem::transmute::<&[std::cell::Cell<u8>], &[u8]>(&memory.inner.view()[..]) as *const [u8]
@@ -66,6 +79,52 @@ pub unsafe extern "C" fn wasm_memory_data_size(memory: &wasm_memory_t) -> usize
     memory.inner.size().bytes().0
 }
 
+/// Unstable non-standard Wasmer-specific API to get the size in bytes
+/// of the memory, read atomically.
+///
+/// It's the size to use while the memory can be grown by another
+/// thread, e.g. a shared memory under the threads proposal: it's read
+/// with the ordering the runtime grows memories with, so that it's
+/// never torn, and the memory is at least that large when
+/// [`wasm_memory_data`] is called afterwards.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_limits_t limits = { 1, 3 };
+///     wasm_memorytype_t* memory_type = wasm_memorytype_new(&limits);
+///     wasm_memory_t* memory = wasm_memory_new(store, memory_type);
+///     assert(wasm_memory_data_size_atomic(memory) == 0x10000);
+///
+///     assert(wasm_memory_grow(memory, 2));
+///     assert(wasm_memory_data_size_atomic(memory) == 3 * 0x10000);
+///     assert(wasm_memory_data_size_atomic(memory) == wasm_memory_data_size(memory));
+///
+///     wasm_memory_delete(memory);
+///     wasm_memorytype_delete(memory_type);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_memory_data_size_atomic(memory: &wasm_memory_t) -> usize {
+    memory.inner.data_size_atomic() as _
+}
+
 // size in pages
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memory_size(memory: &wasm_memory_t) -> u32 {
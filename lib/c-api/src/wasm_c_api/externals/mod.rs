@@ -3,6 +3,7 @@ mod global;
 mod memory;
 mod table;
 
+use super::types::wasm_ref_t;
 pub use function::*;
 pub use global::*;
 pub use memory::*;
@@ -211,6 +212,21 @@ pub extern "C" fn wasm_table_as_extern(table: Option<&wasm_table_t>) -> Option<&
     unsafe { mem::transmute::<Option<&wasm_table_t>, Option<&wasm_extern_t>>(table) }
 }
 
+/// Views a function as a reference, e.g. to install it in a table
+/// with `wasm_table_set`. The reference is owned by the function.
+#[no_mangle]
+pub extern "C" fn wasm_func_as_ref(func: Option<&wasm_func_t>) -> Option<&wasm_ref_t> {
+    unsafe { mem::transmute::<Option<&wasm_func_t>, Option<&wasm_ref_t>>(func) }
+}
+
+/// Views a reference as a function, e.g. to call an element of a
+/// table read with `wasm_table_get`. The function is owned by the
+/// reference.
+#[no_mangle]
+pub extern "C" fn wasm_ref_as_func(reference: Option<&wasm_ref_t>) -> Option<&wasm_func_t> {
+    reference.map(|reference| &reference.inner)
+}
+
 #[no_mangle]
 pub extern "C" fn wasm_extern_as_func(r#extern: Option<&wasm_extern_t>) -> Option<&wasm_func_t> {
     let r#extern = r#extern?;
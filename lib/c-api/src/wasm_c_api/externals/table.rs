@@ -1,7 +1,7 @@
 use super::super::store::wasm_store_t;
 use super::super::types::{wasm_ref_t, wasm_table_size_t, wasm_tabletype_t};
 use super::CApiExternTag;
-use wasmer_api::Table;
+use wasmer_api::{ExternRef, Table, Val, ValType};
 
 #[allow(non_camel_case_types)]
 #[repr(C)]
@@ -20,13 +20,30 @@ impl wasm_table_t {
     }
 }
 
+/// The value of an element of a table of type `ty` holding
+/// `reference`, the null reference being `None`.
+fn element(ty: ValType, reference: Option<&wasm_ref_t>) -> Val {
+    match reference {
+        Some(reference) => Val::FuncRef(Some((*reference.inner.inner).clone())),
+        None if ty == ValType::ExternRef => Val::ExternRef(ExternRef::null()),
+        None => Val::FuncRef(None),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_new(
-    _store: Option<&wasm_store_t>,
-    _table_type: Option<&wasm_tabletype_t>,
-    _init: *const wasm_ref_t,
+    store: Option<&wasm_store_t>,
+    table_type: Option<&wasm_tabletype_t>,
+    init: Option<&wasm_ref_t>,
 ) -> Option<Box<wasm_table_t>> {
-    todo!("get val from init somehow");
+    let store = store?;
+    let table_type = table_type?;
+
+    let table_type = table_type.inner()._table_type.clone();
+    let init = element(table_type.ty, init);
+    let table = c_try!(Table::new(&store.inner, table_type, init));
+
+    Some(Box::new(wasm_table_t::new(table)))
 }
 
 #[no_mangle]
@@ -48,13 +65,178 @@ pub unsafe extern "C" fn wasm_table_size(table: &wasm_table_t) -> usize {
     table.inner.size() as _
 }
 
+/// Reads the element of a table at `index`, a function reference.
+///
+/// Returns `NULL` if the element is a null reference, or if `index`
+/// is out of bounds. Otherwise, the reference is owned by the caller,
+/// who must delete it with `wasm_ref_delete`. Only the tables of
+/// `funcref`s are supported, the elements of the other tables are
+/// read as `NULL`.
+///
+/// # Example
+///
+/// See [`wasm_table_set`].
+#[no_mangle]
+pub unsafe extern "C" fn wasm_table_get(
+    table: &wasm_table_t,
+    index: wasm_table_size_t,
+) -> Option<Box<wasm_ref_t>> {
+    match table.inner.get(index)? {
+        Val::FuncRef(Some(function)) => Some(Box::new(wasm_ref_t::new(function))),
+        _ => None,
+    }
+}
+
+/// Replaces the element of a table at `index` with `reference`, a
+/// function reference, or the null reference if `reference` is
+/// `NULL`. The reference stays owned by the caller.
+///
+/// Returns `false` if `index` is out of bounds, or if the reference
+/// can't be an element of the table, for instance if the function
+/// comes from another store.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// wasm_trap_t* answer(const wasm_val_vec_t* arguments, wasm_val_vec_t* results) {
+///     wasm_val_t value = WASM_I32_VAL(42);
+///     results->data[0] = value;
+///
+///     return NULL;
+/// }
+///
+/// // Calls the function at `index` of the table through `call_indirect`,
+/// // returning -1 if it traps.
+/// int32_t call_at(const wasm_func_t* call, int32_t index) {
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(index) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     wasm_trap_t* trap = wasm_func_call(call, &arguments_as_array, &results_as_array);
+///
+///     if (trap) {
+///         wasm_trap_delete(trap);
+///
+///         return -1;
+///     }
+///
+///     return results[0].of.i32;
+/// }
+///
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (type $get (func (result i32)))\n"
+///         "  (table (export \"table\") 2 funcref)\n"
+///         "  (func $one (result i32) (i32.const 1))\n"
+///         "  (func $two (result i32) (i32.const 2))\n"
+///         "  (elem (i32.const 0) $one $two)\n"
+///         "  (func (export \"call\") (param i32) (result i32)\n"
+///         "    (call_indirect (type $get) (local.get 0))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     wasm_table_t* table = wasm_extern_as_table(exports.data[0]);
+///     const wasm_func_t* call = wasm_extern_as_func(exports.data[1]);
+///     assert(table && call);
+///
+///     assert(call_at(call, 0) == 1);
+///
+///     // Swap the first element for the second one.
+///     wasm_ref_t* two = wasm_table_get(table, 1);
+///     assert(two);
+///     assert(wasm_table_set(table, 0, two));
+///     wasm_ref_delete(two);
+///
+///     assert(call_at(call, 0) == 2);
+///
+///     // Install a host function.
+///     wasm_functype_t* answer_type = wasm_functype_new_0_1(wasm_valtype_new_i32());
+///     wasm_func_t* answer_function = wasm_func_new(store, answer_type, answer);
+///     assert(wasm_table_set(table, 0, wasm_func_as_ref(answer_function)));
+///
+///     assert(call_at(call, 0) == 42);
+///
+///     // Out-of-bounds indices are reported, not trapped.
+///     assert(!wasm_table_get(table, 2));
+///     assert(!wasm_table_set(table, 2, wasm_func_as_ref(answer_function)));
+///
+///     // Grow the table with the host function, then with null references.
+///     assert(wasm_table_grow(table, 1, wasm_func_as_ref(answer_function)));
+///     assert(wasm_table_grow(table, 1, NULL));
+///     assert(wasm_table_size(table) == 4);
+///
+///     assert(call_at(call, 2) == 42);
+///     assert(!wasm_table_get(table, 3));
+///     assert(call_at(call, 3) == -1);
+///
+///     wasm_func_delete(answer_function);
+///     wasm_functype_delete(answer_type);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_table_set(
+    table: &mut wasm_table_t,
+    index: wasm_table_size_t,
+    reference: Option<&wasm_ref_t>,
+) -> bool {
+    let value = element(table.inner.ty().ty, reference);
+    c_try!(table.inner.set(index, value); otherwise false);
+
+    true
+}
+
+/// Grows a table by `delta` elements, initialized with `init`, a
+/// function reference, or the null reference if `init` is `NULL`.
+/// The reference stays owned by the caller.
+///
+/// Returns `false` if the table can't grow that much.
+///
+/// # Example
+///
+/// See [`wasm_table_set`].
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_grow(
-    _table: &mut wasm_table_t,
-    _delta: wasm_table_size_t,
-    _init: *mut wasm_ref_t,
+    table: &mut wasm_table_t,
+    delta: wasm_table_size_t,
+    init: Option<&wasm_ref_t>,
 ) -> bool {
-    // TODO: maybe need to look at result to return `true`; also maybe report error here
-    //wasm_table.inner.grow(delta, init).is_ok()
-    todo!("Blocked on transforming ExternRef into a val type")
+    let init = element(table.inner.ty().ty, init);
+    c_try!(table.inner.grow(delta, init); otherwise false);
+
+    true
 }
@@ -3,6 +3,7 @@ use super::module::wasm_module_t;
 use super::store::wasm_store_t;
 use super::trap::wasm_trap_t;
 use crate::ordered_resolver::OrderedResolver;
+use std::os::raw::c_void;
 use std::sync::Arc;
 use wasmer_api::{Extern, Instance, InstantiationError};
 
@@ -10,6 +11,24 @@ use wasmer_api::{Extern, Instance, InstantiationError};
 #[allow(non_camel_case_types)]
 pub struct wasm_instance_t {
     pub(crate) inner: Arc<Instance>,
+    pub(crate) finalizer: Option<InstanceFinalizer>,
+}
+
+/// The finalizer of a [`wasm_instance_t`], set with
+/// [`wasmer_instance_set_finalizer`].
+///
+/// [`wasmer_instance_set_finalizer`]: super::unstable::instance::wasmer_instance_set_finalizer
+pub(crate) struct InstanceFinalizer {
+    pub(crate) callback: extern "C" fn(ctx: *mut c_void),
+    pub(crate) ctx: *mut c_void,
+}
+
+impl Drop for wasm_instance_t {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer.take() {
+            (finalizer.callback)(finalizer.ctx);
+        }
+    }
 }
 
 /// Creates a new instance from a WebAssembly module and a
@@ -79,6 +98,12 @@ pub unsafe extern "C" fn wasm_instance_new(
             return None;
         }
 
+        Err(e @ InstantiationError::Compile(_)) => {
+            crate::error::update_last_error(e);
+
+            return None;
+        }
+
         Err(InstantiationError::HostEnvInitialization(error)) => {
             crate::error::update_last_error(error);
 
@@ -86,7 +111,10 @@ pub unsafe extern "C" fn wasm_instance_new(
         }
     };
 
-    Some(Box::new(wasm_instance_t { inner: instance }))
+    Some(Box::new(wasm_instance_t {
+        inner: instance,
+        finalizer: None,
+    }))
 }
 
 /// Deletes an instance.
@@ -380,6 +380,14 @@ pub unsafe extern "C" fn wasm_module_imports(
 /// Note: the module has to be serialized before with the
 /// `wasm_module_serialize` function.
 ///
+/// The bytes have the format of the Rust `Module::serialize` API.
+///
+/// It returns `NULL` if the bytes aren't a serialized module, if they
+/// have been serialized by another version of Wasmer or by another
+/// engine, or if the module has been compiled for another target or
+/// for CPU features the host doesn't support; the reason is reported
+/// by `wasmer_last_error_message`.
+///
 /// # Safety
 ///
 /// This function is inherently **unsafe** as the provided bytes:
@@ -866,4 +874,100 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_module_deserialize_and_call() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (func (export \"add\") (param i32 i32) (result i32)\n"
+                    "    (i32.add (local.get 0) (local.get 1))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_byte_vec_t serialized_module;
+                wasm_module_serialize(module, &serialized_module);
+                wasm_module_delete(module);
+
+                wasm_module_t* deserialized_module = wasm_module_deserialize(
+                    store,
+                    &serialized_module
+                );
+                wasm_byte_vec_delete(&serialized_module);
+                assert(deserialized_module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = NULL;
+                wasm_instance_t* instance = wasm_instance_new(store, deserialized_module, &imports, &trap);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                const wasm_func_t* add = wasm_extern_as_func(exports.data[0]);
+                assert(add);
+
+                wasm_val_t arguments[2] = { WASM_I32_VAL(40), WASM_I32_VAL(2) };
+                wasm_val_t results[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+                wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+
+                trap = wasm_func_call(add, &arguments_as_array, &results_as_array);
+                assert(trap == NULL);
+                assert(results[0].of.i32 == 42);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(deserialized_module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_module_deserialize_incompatible() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // A WebAssembly binary isn't a serialized module.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, "(module)");
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_deserialize(store, &wasm);
+                assert(module == NULL);
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
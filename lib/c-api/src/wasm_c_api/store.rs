@@ -1,5 +1,5 @@
 use super::engine::wasm_engine_t;
-use wasmer_api::Store;
+use wasmer_api::{BaseTunables, LimitingTunables, Pages, Store};
 
 /// Opaque type representing a WebAssembly store.
 #[allow(non_camel_case_types)]
@@ -17,7 +17,18 @@ pub unsafe extern "C" fn wasm_store_new(
     engine: Option<&wasm_engine_t>,
 ) -> Option<Box<wasm_store_t>> {
     let engine = engine?;
-    let store = Store::new(&*engine.inner);
+    let limits = engine.limits;
+    let store = if limits.max_memory_pages.is_none() && limits.max_table_elements.is_none() {
+        Store::new(&*engine.inner)
+    } else {
+        let base = BaseTunables::for_target(engine.inner.target());
+        let tunables = LimitingTunables::new(
+            base,
+            limits.max_memory_pages.map_or_else(Pages::max_value, Pages),
+            limits.max_table_elements.unwrap_or(u32::MAX),
+        );
+        Store::new_with_tunables(&*engine.inner, tunables)
+    };
 
     Some(Box::new(wasm_store_t { inner: store }))
 }
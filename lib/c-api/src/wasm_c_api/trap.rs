@@ -120,13 +120,18 @@ pub unsafe extern "C" fn wasm_trap_message(
     out.set_buffer(byte_vec);
 }
 
-/// Gets the origin frame attached to the trap.
+/// Gets the origin frame attached to the trap, i.e. the innermost
+/// Wasm frame, or `NULL` if the trap has no Wasm frame.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_origin(trap: &wasm_trap_t) -> Option<Box<wasm_frame_t>> {
     trap.inner.trace().first().map(Into::into).map(Box::new)
 }
 
-/// Gets the trace (as a list of frames) attached to the trap.
+/// Gets the trace (as a list of frames) attached to the trap, from
+/// the innermost Wasm frame to the outermost one.
+///
+/// When the trap is raised by a host function, the trace has the
+/// Wasm frames which led to the host function being called.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_trace(
     trap: &wasm_trap_t,
@@ -209,4 +214,187 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_trap_trace() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            wasm_trap_t* nop_callback(const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+                return NULL;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"env\" \"nop\" (func))\n"
+                    "  (func (export \"run\") (call 2))\n"
+                    "  (func unreachable))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_functype_t* nop_type = wasm_functype_new_0_0();
+                wasm_func_t* nop = wasm_func_new(store, nop_type, nop_callback);
+                wasm_functype_delete(nop_type);
+
+                wasm_extern_t* externs[] = { wasm_func_as_extern(nop) };
+                wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                const wasm_func_t* run = wasm_extern_as_func(exports.data[0]);
+
+                wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = wasm_func_call(run, &arguments, &results);
+                assert(trap);
+
+                // The function indices count the imported function.
+                wasm_frame_t* origin = wasm_trap_origin(trap);
+                assert(origin);
+                assert(wasm_frame_func_index(origin) == 2);
+                wasm_frame_delete(origin);
+
+                wasm_frame_vec_t trace;
+                wasm_trap_trace(trap, &trace);
+                assert(trace.size == 2);
+                assert(wasm_frame_func_index(trace.data[0]) == 2);
+                assert(wasm_frame_func_index(trace.data[1]) == 1);
+                assert(wasm_frame_module_offset(trace.data[0]) > wasm_frame_module_offset(trace.data[1]));
+                wasm_frame_vec_delete(&trace);
+
+                wasm_trap_delete(trap);
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_func_delete(nop);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_trap_trace_function_names() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            void assert_frame(const wasm_frame_t* frame, uint32_t func_index, const char* func_name) {
+                assert(wasm_frame_func_index(frame) == func_index);
+                assert(wasm_frame_module_offset(frame) > wasm_frame_func_offset(frame));
+
+                wasm_name_t name;
+                wasmer_frame_func_name(frame, &name);
+                wasmer_assert_name(&name, func_name);
+                wasm_byte_vec_delete(&name);
+            }
+
+            wasm_trap_t* host_fail(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+                wasm_message_t message;
+                wasm_name_new_from_string_nt(&message, "host failure");
+                wasm_trap_t* trap = wasm_trap_new((wasm_store_t*) env, &message);
+                wasm_name_delete(&message);
+
+                return trap;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"env\" \"fail\" (func $fail))\n"
+                    "  (func $first (export \"run\") (param i32) (call $second (local.get 0)))\n"
+                    "  (func $second (param i32) (call $third (local.get 0)))\n"
+                    "  (func $third (param i32)\n"
+                    "    (if (local.get 0) (then (call $fail)) (else unreachable))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_functype_t* fail_type = wasm_functype_new_0_0();
+                wasm_func_t* fail = wasm_func_new_with_env(store, fail_type, host_fail, store, NULL);
+                wasm_functype_delete(fail_type);
+
+                wasm_extern_t* externs[] = { wasm_func_as_extern(fail) };
+                wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                const wasm_func_t* run = wasm_extern_as_func(exports.data[0]);
+
+                // Trap in Wasm, three calls deep.
+                wasm_val_t trap_in_wasm[] = { WASM_I32_VAL(0) };
+                wasm_val_vec_t arguments = WASM_ARRAY_VEC(trap_in_wasm);
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = wasm_func_call(run, &arguments, &results);
+                assert(trap);
+
+                wasm_frame_t* origin = wasm_trap_origin(trap);
+                assert(origin);
+                assert_frame(origin, 3, "third");
+                wasm_frame_delete(origin);
+
+                wasm_frame_vec_t trace;
+                wasm_trap_trace(trap, &trace);
+                assert(trace.size == 3);
+                assert_frame(trace.data[0], 3, "third");
+                assert_frame(trace.data[1], 2, "second");
+                assert_frame(trace.data[2], 1, "first");
+                wasm_frame_vec_delete(&trace);
+                wasm_trap_delete(trap);
+
+                // Trap in the host function, the Wasm frames below it
+                // are in the trace.
+                wasm_val_t trap_in_host[] = { WASM_I32_VAL(1) };
+                arguments = (wasm_val_vec_t) WASM_ARRAY_VEC(trap_in_host);
+                trap = wasm_func_call(run, &arguments, &results);
+                assert(trap);
+
+                wasm_trap_trace(trap, &trace);
+                assert(trace.size == 3);
+                assert_frame(trace.data[0], 3, "third");
+                assert_frame(trace.data[1], 2, "second");
+                assert_frame(trace.data[2], 1, "first");
+                wasm_frame_vec_delete(&trace);
+                wasm_trap_delete(trap);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_func_delete(fail);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
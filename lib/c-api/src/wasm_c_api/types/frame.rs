@@ -4,7 +4,7 @@ use wasmer_api::FrameInfo;
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone)]
 pub struct wasm_frame_t {
-    info: FrameInfo,
+    pub(crate) info: FrameInfo,
 }
 
 impl<'a> From<&'a FrameInfo> for wasm_frame_t {
@@ -32,16 +32,22 @@ pub unsafe extern "C" fn wasm_frame_instance(_frame: &wasm_frame_t) -> *const wa
     std::ptr::null()
 }
 
+/// Gets the index of the function of the frame, in the function
+/// index space of its module, i.e. counting the imported functions.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_frame_func_index(frame: &wasm_frame_t) -> u32 {
     frame.info.func_index()
 }
 
+/// Gets the offset of the frame's instruction from the beginning of
+/// the body of its function.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_frame_func_offset(frame: &wasm_frame_t) -> usize {
     frame.info.func_offset()
 }
 
+/// Gets the offset of the frame's instruction from the beginning of
+/// the Wasm binary of its module.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_frame_module_offset(frame: &wasm_frame_t) -> usize {
     frame.info.module_offset()
@@ -52,6 +52,14 @@ wasm_declare_boxed_vec!(memorytype);
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memorytype_new(limits: &wasm_limits_t) -> Box<wasm_memorytype_t> {
+    Box::new(wasm_memorytype_t::new(memory_type_from_limits(
+        limits, false,
+    )))
+}
+
+/// The memory type with the given `limits`, shared between threads
+/// or not.
+pub(crate) fn memory_type_from_limits(limits: &wasm_limits_t, shared: bool) -> MemoryType {
     let min_pages = Pages(limits.min as _);
     let max_pages = if limits.max == LIMITS_MAX_SENTINEL {
         None
@@ -59,9 +67,7 @@ pub unsafe extern "C" fn wasm_memorytype_new(limits: &wasm_limits_t) -> Box<wasm
         Some(Pages(limits.max as _))
     };
 
-    Box::new(wasm_memorytype_t::new(MemoryType::new(
-        min_pages, max_pages, false,
-    )))
+    MemoryType::new(min_pages, max_pages, shared)
 }
 
 #[no_mangle]
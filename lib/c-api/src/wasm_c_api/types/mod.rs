@@ -20,6 +20,9 @@ pub use mutability::*;
 pub use table::*;
 pub use value::*;
 
+use super::externals::wasm_func_t;
+use wasmer_api::Function;
+
 #[allow(non_camel_case_types)]
 pub type wasm_byte_t = u8;
 
@@ -34,9 +37,39 @@ impl From<String> for wasm_name_t {
     }
 }
 
-// opaque type over `ExternRef`?
+/// A reference to a function, the only kind of reference supported
+/// so far.
+///
+/// It has the layout of a `wasm_func_t`, so that a function can be
+/// viewed as a reference and back, see [`wasm_func_as_ref`] and
+/// [`wasm_ref_as_func`].
+///
+/// [`wasm_func_as_ref`]: super::externals::wasm_func_as_ref
+/// [`wasm_ref_as_func`]: super::externals::wasm_ref_as_func
 #[allow(non_camel_case_types)]
-pub struct wasm_ref_t;
+#[repr(transparent)]
+#[derive(Debug, Clone)]
+pub struct wasm_ref_t {
+    pub(crate) inner: wasm_func_t,
+}
+
+impl wasm_ref_t {
+    pub(crate) fn new(function: Function) -> Self {
+        Self {
+            inner: wasm_func_t::new(function),
+        }
+    }
+}
+
+/// Copy a `wasm_ref_t`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_copy(reference: &wasm_ref_t) -> Box<wasm_ref_t> {
+    Box::new(reference.clone())
+}
+
+/// Delete a reference, as returned by `wasm_table_get`.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_delete(_reference: Option<Box<wasm_ref_t>>) {}
 
 #[allow(non_camel_case_types)]
 pub type wasm_message_t = wasm_byte_vec_t;
@@ -3,10 +3,16 @@
 
 #[cfg(feature = "compiler")]
 use super::super::engine::wasmer_compiler_t;
-use super::super::engine::{wasm_config_t, wasmer_engine_t};
+use super::super::engine::{wasm_config_t, wasm_engine_t, wasmer_engine_t};
 
+use super::super::types::wasm_byte_vec_t;
 use super::features::wasmer_features_t;
 use super::target_lexicon::wasmer_target_t;
+#[cfg(feature = "json")]
+use serde_json::json;
+#[cfg(feature = "json")]
+use std::time::UNIX_EPOCH;
+use wasmer_api::Engine;
 
 /// Unstable non-standard Wasmer-specific API to update the
 /// configuration to specify a particular target for the engine.
@@ -98,7 +104,15 @@ pub extern "C" fn wasm_config_set_features(
     config.features = Some(features);
 }
 
-/// Updates the configuration to enable NaN canonicalization.
+/// Updates the configuration to enable or disable NaN
+/// canonicalization.
+///
+/// When enabled, the NaNs produced by the `f32` and `f64`
+/// instructions, the lanes of the `f32x4` and `f64x2` ones, and the
+/// values passed to and returned from the host are the canonical
+/// quiet NaN, whichever compiler is selected. When this function
+/// isn't called, the default of the compiler is kept: Singlepass
+/// canonicalizes, Cranelift and LLVM don't.
 ///
 /// This is a Wasmer-specific function.
 ///
@@ -134,7 +148,431 @@ pub extern "C" fn wasm_config_set_features(
 /// ```
 #[no_mangle]
 pub extern "C" fn wasm_config_canonicalize_nans(config: &mut wasm_config_t, enable: bool) {
-    config.nan_canonicalization = enable;
+    config.nan_canonicalization = Some(enable);
+}
+
+/// Unstable non-standard Wasmer-specific API to enable the
+/// deterministic mode, for executions giving the same results on
+/// every machine and with every compiler.
+///
+/// The NaNs produced by the floating-point instructions are
+/// canonicalized, as with [`wasm_config_canonicalize_nans`], and the
+/// modules using the threads or the relaxed SIMD proposals are
+/// rejected, even if the features of the configuration enable them:
+/// their instructions may give different results on different
+/// machines. The limits of the host, such as how far memories can
+/// grow, aren't covered.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasmer_features_t* features = wasmer_features_new();
+///     wasmer_features_threads(features, true);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_features(config, features);
+///     wasm_config_set_deterministic(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Shared memories come from the threads proposal.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (memory 1 1 shared))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     assert(!wasm_module_new(store, &wasm));
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///
+///     // Dividing zero by zero gives the canonical NaN.
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"nan\") (param f32) (result i32)\n"
+///         "    (i32.reinterpret_f32 (f32.div (local.get 0) (local.get 0)))))"
+///     );
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* nan = wasm_extern_as_func(exports.data[0]);
+///     assert(nan);
+///
+///     wasm_val_t arguments[1] = { WASM_F32_VAL(0) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     trap = wasm_func_call(nan, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 0x7fc00000);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasm_config_set_deterministic(config: &mut wasm_config_t, enable: bool) {
+    config.deterministic = enable;
+}
+
+/// Unstable non-standard Wasmer-specific API to bound the number of
+/// threads compiling the functions of each module to `threads`,
+/// instead of using all the cores.
+///
+/// The functions of a module are compiled in a thread pool of its
+/// own, the thread creating the module waiting for them. `0` compiles
+/// the functions on the thread creating the module, without a pool.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_compile_threads(config, 1);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func $one (result i32) (i32.const 1))\n"
+///         "  (func (export \"two\") (result i32)\n"
+///         "    (i32.add (call $one) (call $one))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasm_config_set_compile_threads(config: &mut wasm_config_t, threads: u32) {
+    config.compile_threads = Some(threads);
+}
+
+/// Unstable non-standard Wasmer-specific API to enable the
+/// performance diagnostics of the compiler, which can then be read
+/// with [`wasmer_module_perf_diagnostics`].
+///
+/// [`wasmer_module_perf_diagnostics`]: super::module::wasmer_module_perf_diagnostics
+#[no_mangle]
+pub extern "C" fn wasm_config_enable_perf_diagnostics(config: &mut wasm_config_t, enable: bool) {
+    config.perf_diagnostics = enable;
+}
+
+/// Unstable non-standard Wasmer-specific API to keep the binary of
+/// the modules, which can then be read with
+/// [`wasmer_module_wasm_bytes`], also after serialization.
+///
+/// [`wasmer_module_wasm_bytes`]: super::module::wasmer_module_wasm_bytes
+#[no_mangle]
+pub extern "C" fn wasm_config_retain_wasm_bytes(config: &mut wasm_config_t, retain: bool) {
+    config.retain_wasm_bytes = retain;
+}
+
+/// Unstable non-standard Wasmer-specific API to enable epoch-based
+/// interruption: the compiled code traps once the epoch counter of
+/// the engine, incremented with [`wasmer_engine_increment_epoch`],
+/// goes past the deadline of the store, set with
+/// [`wasmer_store_set_epoch_deadline_after`].
+///
+/// # Example
+///
+/// See [`wasmer_store_set_epoch_deadline_after`].
+///
+/// [`wasmer_store_set_epoch_deadline_after`]: super::store::wasmer_store_set_epoch_deadline_after
+#[no_mangle]
+pub extern "C" fn wasm_config_enable_epoch_interruption(config: &mut wasm_config_t, enable: bool) {
+    config.epoch_interruption = enable;
+}
+
+/// Unstable non-standard Wasmer-specific API to limit the memories
+/// of the stores of the engine to `max_memory_pages` pages of 64 KiB.
+///
+/// Instantiating a module defining a memory with a larger minimum
+/// fails, and `memory.grow` returns -1 to the guest instead of
+/// growing a memory past the limit.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_max_memory_pages(config, 2);
+///     wasm_config_set_max_table_elements(config, 10);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // A module whose memory starts above the limit can't be
+///     // instantiated.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (memory 3))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     assert(wasm_instance_new(store, module, &imports, &trap) == NULL);
+///     assert(wasmer_last_error_length() > 0);
+///
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///
+///     // A module whose memory starts below the limit can't grow it
+///     // past the limit.
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1)\n"
+///         "  (func (export \"grow\") (param i32) (result i32)\n"
+///         "    (memory.grow (local.get 0))))"
+///     );
+///     wat2wasm(&wat, &wasm);
+///
+///     module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* grow = wasm_extern_as_func(exports.data[0]);
+///     assert(grow);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(2) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     assert(wasm_func_call(grow, &arguments_as_array, &results_as_array) == NULL);
+///     assert(results[0].of.i32 == -1);
+///
+///     arguments[0].of.i32 = 1;
+///     assert(wasm_func_call(grow, &arguments_as_array, &results_as_array) == NULL);
+///     assert(results[0].of.i32 == 1);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasm_config_set_max_memory_pages(
+    config: &mut wasm_config_t,
+    max_memory_pages: u32,
+) {
+    config.limits.max_memory_pages = Some(max_memory_pages);
+}
+
+/// Unstable non-standard Wasmer-specific API to limit the tables of
+/// the stores of the engine to `max_table_elements` elements.
+///
+/// Instantiating a module defining a table with a larger minimum
+/// fails, and `table.grow` returns -1 to the guest instead of growing
+/// a table past the limit.
+///
+/// # Example
+///
+/// See [`wasm_config_set_max_memory_pages`].
+#[no_mangle]
+pub extern "C" fn wasm_config_set_max_table_elements(
+    config: &mut wasm_config_t,
+    max_table_elements: u32,
+) {
+    config.limits.max_table_elements = Some(max_table_elements);
+}
+
+/// Unstable non-standard Wasmer-specific API to increment the epoch
+/// counter of an engine, shared by all its stores.
+///
+/// It can be called from another thread than the one running the
+/// WebAssembly code, e.g. by a watchdog interrupting long-running
+/// code.
+///
+/// # Example
+///
+/// See [`wasmer_store_set_epoch_deadline_after`].
+///
+/// [`wasmer_store_set_epoch_deadline_after`]: super::store::wasmer_store_set_epoch_deadline_after
+#[no_mangle]
+pub extern "C" fn wasmer_engine_increment_epoch(engine: &wasm_engine_t) {
+    engine.inner.increment_epoch();
+}
+
+/// Unstable non-standard Wasmer-specific API to describe the live
+/// instances of an engine, as a JSON array written to `out`.
+///
+/// Each instance is described by an object with the following keys:
+///
+/// * `module_hash`: the hexadecimal BLAKE3 hash of the binary of its
+///   module, or `null` if it's unknown,
+/// * `module_name`: the name of its module, or `null`,
+/// * `tags`: its tags, set with `wasmer_instance_set_tag`,
+/// * `memories`: its number of memories,
+/// * `memory_bytes`: the sum of the sizes of its memories,
+/// * `created_at_ms`: when it was created, in milliseconds since the
+///   Unix epoch.
+///
+/// This function is only available with the `json` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module $tenant_module (memory 1))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Create a tagged instance.
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_name_t key;
+///     wasm_name_new_from_string(&key, "tenant");
+///     wasm_name_t value;
+///     wasm_name_new_from_string(&value, "acme");
+///     wasmer_instance_set_tag(instance, &key, &value);
+///
+///     // It is listed.
+///     wasm_byte_vec_t json;
+///     wasmer_engine_live_instances_json(engine, &json);
+///     wasm_byte_vec_t json_string;
+///     wasm_byte_vec_new_uninitialized(&json_string, json.size + 1);
+///     memcpy(json_string.data, json.data, json.size);
+///     json_string.data[json.size] = 0;
+///
+///     assert(strstr(json_string.data, "\"module_name\":\"tenant_module\""));
+///     assert(strstr(json_string.data, "\"memory_bytes\":65536"));
+///     assert(strstr(json_string.data, "\"tags\":{\"tenant\":\"acme\"}"));
+///
+///     wasm_byte_vec_delete(&json_string);
+///     wasm_byte_vec_delete(&json);
+///
+///     // Once deleted, it isn't listed anymore.
+///     wasm_instance_delete(instance);
+///     wasmer_engine_live_instances_json(engine, &json);
+///     wasmer_assert_name(&json, "[]");
+///
+///     wasm_byte_vec_delete(&json);
+///     wasm_byte_vec_delete(&value);
+///     wasm_byte_vec_delete(&key);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+#[cfg(feature = "json")]
+pub extern "C" fn wasmer_engine_live_instances_json(
+    engine: &wasm_engine_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) {
+    let instances = engine
+        .inner
+        .live_instances()
+        .into_iter()
+        .map(|instance| {
+            let created_at_ms = instance
+                .created_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis() as u64);
+            json!({
+                "module_hash": instance.module_hash,
+                "module_name": instance.module_name,
+                "tags": instance.tags,
+                "memories": instance.memories,
+                "memory_bytes": instance.memory_bytes,
+                "created_at_ms": created_at_ms,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    out.set_buffer(serde_json::Value::Array(instances).to_string().into_bytes());
 }
 
 /// Check whether the given compiler is available, i.e. part of this
@@ -32,6 +32,8 @@
 //! To go further, see
 //! [`wasm_config_set_features`](super::engine::wasm_config_set_features).
 
+#[cfg(feature = "compiler")]
+use super::super::types::wasm_byte_vec_t;
 use wasmer_types::Features;
 
 /// Controls which experimental features will be enabled.
@@ -314,7 +316,62 @@ pub extern "C" fn wasmer_features_module_linking(
 ///
 /// # Example
 ///
-/// See the module's documentation.
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasmer_features_t* features = wasmer_features_new();
+///     wasmer_features_multi_memory(features, true);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_features(config, features);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory (export \"small\") 1)\n"
+///         "  (memory (export \"large\") 2))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+///     assert(instance);
+///
+///     // Each exported memory is a distinct `wasm_memory_t`.
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     assert(exports.size == 2);
+///     wasm_memory_t* small = wasm_extern_as_memory(exports.data[0]);
+///     wasm_memory_t* large = wasm_extern_as_memory(exports.data[1]);
+///     assert(wasm_memory_size(small) == 1);
+///     assert(wasm_memory_size(large) == 2);
+///     assert(wasm_memory_data(small) != wasm_memory_data(large));
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
 #[no_mangle]
 pub extern "C" fn wasmer_features_multi_memory(
     features: Option<&mut wasmer_features_t>,
@@ -362,3 +419,140 @@ pub extern "C" fn wasmer_features_memory64(
 
     true
 }
+
+/// Find the features a WebAssembly module uses, without validating
+/// it.
+///
+/// The returned [`wasmer_features_t`] can be read with
+/// `wasmer_features_get_*`, e.g. [`wasmer_features_get_simd`], or
+/// passed to [`wasm_config_set_features`] to create an engine able to
+/// run the module. It must be deleted with
+/// [`wasmer_features_delete`].
+///
+/// The sign-extension operators and the non-trapping float-to-int
+/// conversions are always enabled, and are not reported.
+///
+/// Returns `NULL` if the module is malformed; the error can be read
+/// with `wasmer_last_error_message`.
+///
+/// [`wasm_config_set_features`]: super::engine::wasm_config_set_features
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1 1 shared)\n"
+///         "  (func (result v128) (v128.const i32x4 0 0 0 0)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasmer_features_t* features = wasmer_features_required(&wasm);
+///     assert(features);
+///     assert(wasmer_features_get_simd(features));
+///     assert(wasmer_features_get_threads(features));
+///     assert(!wasmer_features_get_reference_types(features));
+///
+///     wasmer_features_delete(features);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[cfg(feature = "compiler")]
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_features_required(
+    wasm: Option<&wasm_byte_vec_t>,
+) -> Option<Box<wasmer_features_t>> {
+    let wasm = wasm?;
+    let features = c_try!(wasmer_api::features_required(wasm.as_slice()));
+
+    Some(Box::new(wasmer_features_t { inner: features }))
+}
+
+/// Check whether the WebAssembly threads proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_threads(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.threads)
+}
+
+/// Check whether the WebAssembly reference types proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_reference_types(
+    features: Option<&wasmer_features_t>,
+) -> bool {
+    features.map_or(false, |features| features.inner.reference_types)
+}
+
+/// Check whether the WebAssembly SIMD proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_simd(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.simd)
+}
+
+/// Check whether the WebAssembly bulk memory operations proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_bulk_memory(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.bulk_memory)
+}
+
+/// Check whether the WebAssembly multi-value proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_multi_value(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.multi_value)
+}
+
+/// Check whether the WebAssembly tail call proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_tail_call(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.tail_call)
+}
+
+/// Check whether the WebAssembly module linking proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_module_linking(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.module_linking)
+}
+
+/// Check whether the WebAssembly multi-memory proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_multi_memory(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.multi_memory)
+}
+
+/// Check whether the WebAssembly 64-bit memory proposal is enabled.
+///
+/// Returns `false` if `features` is `NULL`.
+#[no_mangle]
+pub extern "C" fn wasmer_features_get_memory64(features: Option<&wasmer_features_t>) -> bool {
+    features.map_or(false, |features| features.inner.memory64)
+}
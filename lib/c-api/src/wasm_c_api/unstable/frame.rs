@@ -0,0 +1,93 @@
+//! Unstable non-standard Wasmer-specific extensions to the frames of
+//! a trap trace.
+
+use super::super::types::{wasm_frame_t, wasm_name_t};
+use std::ptr;
+
+/// Unstable non-standard Wasmer-specific API to get the name of the
+/// function of a frame, as found in the name section of its module,
+/// otherwise `out->size` is set to `0` and `out->data` to `NULL`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module whose function traps.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module (func $explode (export \"explode\") unreachable))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Instantiate the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* explode = wasm_extern_as_func(exports.data[0]);
+///
+///     // Call the function, and read the name of the frame it traps in.
+///     wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = wasm_func_call(explode, &arguments, &results);
+///     assert(trap);
+///
+///     wasm_frame_t* origin = wasm_trap_origin(trap);
+///     assert(origin);
+///
+///     wasm_name_t name;
+///     wasmer_frame_func_name(origin, &name);
+///     wasmer_assert_name(&name, "explode");
+///
+///     // Free everything.
+///     wasm_byte_vec_delete(&name);
+///     wasm_frame_delete(origin);
+///     wasm_trap_delete(trap);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_func_name(
+    frame: &wasm_frame_t,
+    // own
+    out: &mut wasm_name_t,
+) {
+    let name = match frame.info.function_name() {
+        Some(name) => name,
+        None => {
+            out.data = ptr::null_mut();
+            out.size = 0;
+
+            return;
+        }
+    };
+
+    out.set_buffer(name.as_bytes().to_vec());
+}
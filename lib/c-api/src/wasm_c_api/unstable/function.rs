@@ -0,0 +1,579 @@
+//! Unstable non-standard Wasmer-specific API for the
+//! `wasm_func_t`.
+
+use super::super::externals::{
+    call_with, new_with_env, wasm_env_finalizer_t, wasm_func_callback_with_env_t, wasm_func_t,
+    write_results, FuncEnv,
+};
+use super::super::store::wasm_store_t;
+use super::super::trap::wasm_trap_t;
+use super::super::types::wasm_functype_t;
+use super::super::value::wasm_val_vec_t;
+use crate::error::update_last_error;
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wasmer_api::{CallInterrupt, RuntimeError, TrapCode, Val};
+
+/// Unstable non-standard Wasmer-specific API to call a function like
+/// `wasm_func_call`, interrupting it if it hasn't returned after
+/// `timeout_ms` milliseconds.
+///
+/// The call is only interrupted in code compiled with
+/// [`wasm_config_enable_epoch_interruption`], at a function entry or
+/// loop header, and so only once a host function running when the
+/// timeout expires returns. A call made with a timeout by a host
+/// function called with another one is interrupted by the timeout
+/// expiring first.
+///
+/// The message of the trap returned when the timeout expires starts
+/// with `call timed out`. The call fails without running if the
+/// function isn't defined by a module compiled with epoch
+/// interruption.
+///
+/// [`wasm_config_enable_epoch_interruption`]: super::engine::wasm_config_enable_epoch_interruption
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_enable_epoch_interruption(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a module with a function looping forever.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"spin\")\n"
+///         "    (loop $continue\n"
+///         "      (br $continue))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* spin = wasm_extern_as_func(exports.data[0]);
+///     assert(spin);
+///
+///     // The infinite loop is interrupted after 10 ms.
+///     wasm_val_vec_t arguments_as_array = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results_as_array = WASM_EMPTY_VEC;
+///     trap = wasmer_func_call_with_timeout(spin, &arguments_as_array, &results_as_array, 10);
+///     assert(trap != NULL);
+///
+///     wasm_message_t message;
+///     wasm_trap_message(trap, &message);
+///     assert(strncmp(message.data, "call timed out", 14) == 0);
+///
+///     wasm_byte_vec_delete(&message);
+///     wasm_trap_delete(trap);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_func_call_with_timeout(
+    func: Option<&wasm_func_t>,
+    args: Option<&wasm_val_vec_t>,
+    results: &mut wasm_val_vec_t,
+    timeout_ms: u64,
+) -> Option<Box<wasm_trap_t>> {
+    call_with(func, args, results, |function, params| {
+        function.call_with_timeout(params, Duration::from_millis(timeout_ms))
+    })
+}
+
+/// Unstable non-standard Wasmer-specific environment of host
+/// functions, which can be shared by several of them.
+///
+/// The environment is reference counted: it is held by the
+/// `wasmer_func_env_t` returned by [`wasmer_func_env_new`], and by the
+/// functions created with it by [`wasm_func_new_with_shared_env`],
+/// including the copies of these functions held by the instances
+/// importing them. Its finalizer runs exactly once, when the last of
+/// them is deleted.
+#[derive(Clone)]
+#[allow(non_camel_case_types)]
+pub struct wasmer_func_env_t {
+    inner: Arc<FuncEnv>,
+}
+
+/// Unstable non-standard Wasmer-specific API to create an environment
+/// of host functions, holding `data`.
+///
+/// `finalizer`, if not null, is called with `data` once the
+/// environment is no longer used, see [`wasmer_func_env_t`].
+///
+/// # Example
+///
+/// See [`wasm_func_new_with_shared_env`].
+#[no_mangle]
+pub extern "C" fn wasmer_func_env_new(
+    data: *mut c_void,
+    finalizer: Option<wasm_env_finalizer_t>,
+) -> Box<wasmer_func_env_t> {
+    Box::new(wasmer_func_env_t {
+        inner: Arc::new(FuncEnv::new(data, finalizer)),
+    })
+}
+
+/// Unstable non-standard Wasmer-specific API to delete an environment
+/// of host functions.
+///
+/// The functions created with it keep using it, and it is only
+/// finalized once they are deleted too.
+///
+/// # Example
+///
+/// See [`wasm_func_new_with_shared_env`].
+#[no_mangle]
+pub extern "C" fn wasmer_func_env_delete(_env: Option<Box<wasmer_func_env_t>>) {}
+
+/// Unstable non-standard Wasmer-specific API to create a host
+/// function like `wasm_func_new_with_env`, but with an environment
+/// which can be shared with other functions, see
+/// [`wasmer_func_env_t`].
+///
+/// `callback` is called with the data of `env`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// static int finalized = 0;
+///
+/// void finalize(void* data) {
+///     finalized += 1;
+/// }
+///
+/// wasm_trap_t* increment(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+///     int* counter = (int*) env;
+///     *counter += args->data[0].of.i32;
+///     return NULL;
+/// }
+///
+/// wasm_trap_t* get(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+///     int* counter = (int*) env;
+///     results->data[0].kind = WASM_I32;
+///     results->data[0].of.i32 = *counter;
+///     return NULL;
+/// }
+///
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (import \"host\" \"increment\" (func $increment (param i32)))\n"
+///         "  (import \"host\" \"get\" (func $get (result i32)))\n"
+///         "  (func (export \"run\") (result i32)\n"
+///         "    (call $increment (i32.const 2))\n"
+///         "    (call $increment (i32.const 3))\n"
+///         "    (call $get)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Both host functions share the same counter.
+///     int counter = 0;
+///     wasmer_func_env_t* env = wasmer_func_env_new(&counter, finalize);
+///
+///     wasm_functype_t* increment_type = wasm_functype_new_1_0(wasm_valtype_new_i32());
+///     wasm_func_t* increment_func = wasm_func_new_with_shared_env(store, increment_type, increment, env);
+///     wasm_functype_t* get_type = wasm_functype_new_0_1(wasm_valtype_new_i32());
+///     wasm_func_t* get_func = wasm_func_new_with_shared_env(store, get_type, get, env);
+///     assert(increment_func && get_func);
+///
+///     // The functions keep the environment alive.
+///     wasmer_func_env_delete(env);
+///     assert(finalized == 0);
+///
+///     wasm_extern_t* externs[] = { wasm_func_as_extern(increment_func), wasm_func_as_extern(get_func) };
+///     wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     // And so does the instance importing them.
+///     wasm_func_delete(increment_func);
+///     wasm_func_delete(get_func);
+///     assert(finalized == 0);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* run = wasm_extern_as_func(exports.data[0]);
+///
+///     wasm_val_t results_val[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results_val);
+///     trap = wasm_func_call(run, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results_val[0].of.i32 == 5);
+///     assert(counter == 5);
+///
+///     // The environment is finalized once, with the instance.
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     assert(finalized == 1);
+///
+///     wasm_functype_delete(increment_type);
+///     wasm_functype_delete(get_type);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     assert(finalized == 1);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_func_new_with_shared_env(
+    store: Option<&wasm_store_t>,
+    function_type: Option<&wasm_functype_t>,
+    callback: Option<wasm_func_callback_with_env_t>,
+    env: Option<&wasmer_func_env_t>,
+) -> Option<Box<wasm_func_t>> {
+    let store = store?;
+    let function_type = function_type?;
+    let callback = callback?;
+    let env = env?;
+
+    let function = new_with_env(store, function_type, callback, env.inner.clone());
+
+    Some(Box::new(wasm_func_t::new(function)))
+}
+
+/// Unstable non-standard Wasmer-specific state of a call made with
+/// [`wasm_func_call_async`], returned by [`wasm_call_future_poll`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum wasm_call_future_state_t {
+    /// The call hasn't returned yet.
+    WASM_CALL_PENDING = 0,
+
+    /// The call returned, its results are written.
+    WASM_CALL_READY = 1,
+
+    /// The call trapped, or has been cancelled.
+    WASM_CALL_TRAPPED = 2,
+}
+
+/// The result of a call, once it has returned, shared with the thread
+/// running it.
+type AsyncCallResult = Arc<Mutex<Option<Result<Box<[Val]>, RuntimeError>>>>;
+
+/// Unstable non-standard Wasmer-specific handle of a call made with
+/// [`wasm_func_call_async`].
+#[allow(non_camel_case_types)]
+pub struct wasm_call_future_t {
+    /// Whether the function can be interrupted.
+    interruptible: bool,
+    interrupt: Arc<CallInterrupt>,
+    result: AsyncCallResult,
+    /// The thread running the call, joined or detached when the
+    /// handle is deleted.
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Unstable non-standard Wasmer-specific API to call a function
+/// without blocking, the call running on a thread of its own.
+///
+/// The arguments are copied, `args` can be deleted once the call is
+/// made. The call is polled with [`wasm_call_future_poll`], cancelled
+/// with [`wasm_call_future_cancel`], and its handle must be deleted
+/// with [`wasm_call_future_delete`].
+///
+/// # Threading model
+///
+/// The function, and the host functions it calls, run on the new
+/// thread, never on the thread polling the call: polling doesn't
+/// enter WebAssembly. Host functions must therefore be safe to call
+/// from another thread, and synchronize their accesses to data shared
+/// with the rest of the program. While the call is pending, the
+/// instance of the function and its memories, tables and globals must
+/// not be used from other threads, other than by polling or
+/// cancelling the call.
+///
+/// Returns null if `func` or `args` is null.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_enable_epoch_interruption(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"add\") (param i32 i32) (result i32)\n"
+///         "    (i32.add (local.get 0) (local.get 1)))\n"
+///         "  (func (export \"spin\")\n"
+///         "    (loop $continue\n"
+///         "      (br $continue))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* add = wasm_extern_as_func(exports.data[0]);
+///     const wasm_func_t* spin = wasm_extern_as_func(exports.data[1]);
+///
+///     // Poll a call until it returns.
+///     wasm_val_t arguments[2] = { WASM_I32_VAL(1), WASM_I32_VAL(2) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///     wasm_call_future_t* future = wasm_func_call_async(add, &arguments_as_array);
+///     assert(future);
+///
+///     wasm_call_future_state_t state;
+///     while ((state = wasm_call_future_poll(future, &results_as_array, &trap)) == WASM_CALL_PENDING) {}
+///     assert(state == WASM_CALL_READY);
+///     assert(results[0].of.i32 == 3);
+///     wasm_call_future_delete(future);
+///
+///     // Cancel a call looping forever.
+///     wasm_val_vec_t no_values = WASM_EMPTY_VEC;
+///     future = wasm_func_call_async(spin, &no_values);
+///     assert(wasm_call_future_poll(future, &no_values, &trap) == WASM_CALL_PENDING);
+///     assert(wasm_call_future_cancel(future));
+///     while ((state = wasm_call_future_poll(future, &no_values, &trap)) == WASM_CALL_PENDING) {}
+///     assert(state == WASM_CALL_TRAPPED);
+///
+///     wasm_message_t message;
+///     wasm_trap_message(trap, &message);
+///     assert(strncmp(message.data, "call cancelled", 14) == 0);
+///     wasm_byte_vec_delete(&message);
+///     wasm_trap_delete(trap);
+///     wasm_call_future_delete(future);
+///
+///     // The store can be called again once the call is cancelled.
+///     future = wasm_func_call_async(add, &arguments_as_array);
+///     while ((state = wasm_call_future_poll(future, &results_as_array, &trap)) == WASM_CALL_PENDING) {}
+///     assert(state == WASM_CALL_READY);
+///     wasm_call_future_delete(future);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_func_call_async(
+    func: Option<&wasm_func_t>,
+    args: Option<&wasm_val_vec_t>,
+) -> Option<Box<wasm_call_future_t>> {
+    let function = (*func?.inner).clone();
+    let params = args?
+        .as_slice()
+        .iter()
+        .cloned()
+        .map(TryInto::try_into)
+        .collect::<Result<Vec<Val>, _>>()
+        .expect("Arguments conversion failed");
+
+    let interruptible = function.is_interruptible();
+    let interrupt = function.store().new_call_interrupt();
+    let result = AsyncCallResult::default();
+    let thread_interrupt = interrupt.clone();
+    let thread_result = result.clone();
+    let thread = thread::Builder::new()
+        .name("wasmer-call".to_string())
+        .spawn(move || {
+            let call = || {
+                if interruptible {
+                    function.call_interruptible(&params, &thread_interrupt)
+                } else {
+                    function.call(&params)
+                }
+            };
+            let result = match panic::catch_unwind(AssertUnwindSafe(call)) {
+                Err(_) => Err(RuntimeError::new("call panicked")),
+                Ok(Err(error))
+                    if thread_interrupt.is_interrupted()
+                        && error.clone().to_trap() == Some(TrapCode::EpochDeadlineExceeded) =>
+                {
+                    Err(RuntimeError::new("call cancelled"))
+                }
+                Ok(result) => result,
+            };
+            *thread_result.lock().unwrap() = Some(result);
+        })
+        .ok()?;
+
+    Some(Box::new(wasm_call_future_t {
+        interruptible,
+        interrupt,
+        result,
+        thread: Some(thread),
+    }))
+}
+
+/// Unstable non-standard Wasmer-specific API to poll a call made with
+/// [`wasm_func_call_async`], without blocking.
+///
+/// Once the call has returned, its results are written to `results`
+/// and `WASM_CALL_READY` is returned. If it trapped, `trap` is set to
+/// a new trap, to be deleted by the caller, and `WASM_CALL_TRAPPED` is
+/// returned. A call which has returned can be polled again, with the
+/// same outcome.
+///
+/// # Example
+///
+/// See [`wasm_func_call_async`].
+#[no_mangle]
+pub unsafe extern "C" fn wasm_call_future_poll(
+    future: &wasm_call_future_t,
+    results: &mut wasm_val_vec_t,
+    trap: &mut *mut wasm_trap_t,
+) -> wasm_call_future_state_t {
+    let result = future.result.lock().unwrap();
+    match &*result {
+        None => wasm_call_future_state_t::WASM_CALL_PENDING,
+        Some(Ok(values)) => {
+            write_results(results, values.to_vec());
+            wasm_call_future_state_t::WASM_CALL_READY
+        }
+        Some(Err(error)) => {
+            *trap = Box::into_raw(Box::new(error.clone().into()));
+            wasm_call_future_state_t::WASM_CALL_TRAPPED
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to cancel a call made
+/// with [`wasm_func_call_async`].
+///
+/// The call then traps with a message starting with `call cancelled`,
+/// see [`wasm_call_future_poll`]. Cancelling a call twice does
+/// nothing more.
+///
+/// The call is only interrupted in code compiled with
+/// [`wasm_config_enable_epoch_interruption`], at a function entry or
+/// loop header, and so only once a host function running when the
+/// call is cancelled returns. The call unwinds like on any trap, the
+/// instance can be called again afterwards. The other calls in the
+/// same store aren't interrupted, and the epoch of the engine isn't
+/// changed.
+///
+/// Returns `false`, and sets the last error, if the function isn't
+/// defined by a module compiled with epoch interruption, or if the
+/// call has already returned.
+///
+/// [`wasm_config_enable_epoch_interruption`]: super::engine::wasm_config_enable_epoch_interruption
+///
+/// # Example
+///
+/// See [`wasm_func_call_async`].
+#[no_mangle]
+pub extern "C" fn wasm_call_future_cancel(future: &wasm_call_future_t) -> bool {
+    if !future.interruptible {
+        update_last_error(
+            "the call can't be cancelled: the function isn't defined by a module compiled with epoch interruption",
+        );
+        return false;
+    }
+    if !future.interrupt.interrupt() {
+        update_last_error("the call can't be cancelled: it has already returned");
+        return false;
+    }
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to delete the handle of
+/// a call made with [`wasm_func_call_async`].
+///
+/// A pending call is cancelled, see [`wasm_call_future_cancel`], and
+/// this function blocks until it returns and its thread is joined. A
+/// call which can't be cancelled isn't waited for: its thread is
+/// detached, and runs until the call returns.
+///
+/// # Example
+///
+/// See [`wasm_func_call_async`].
+#[no_mangle]
+pub extern "C" fn wasm_call_future_delete(future: Option<Box<wasm_call_future_t>>) {
+    if let Some(mut future) = future {
+        let thread = future.thread.take().unwrap();
+        if !future.interruptible {
+            // Dropping the handle detaches the thread.
+            drop(thread);
+            return;
+        }
+        future.interrupt.interrupt();
+        let _ = thread.join();
+    }
+}
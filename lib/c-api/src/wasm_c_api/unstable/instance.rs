@@ -0,0 +1,558 @@
+//! Unstable non-standard Wasmer-specific extensions to the Wasm C API.
+
+use super::super::externals::wasm_extern_vec_t;
+use super::super::instance::{wasm_instance_t, InstanceFinalizer};
+use super::super::module::wasm_module_t;
+use super::super::types::wasm_name_t;
+use super::super::value::wasm_val_t;
+use super::named_extern::{wasmer_named_extern_t, wasmer_named_extern_vec_t};
+use crate::error::update_last_error;
+use std::convert::TryInto;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::str;
+use wasmer_api::{ExternType, Global, Mutability, Val};
+
+/// Unstable non-standard Wasmer-specific API to build the imports of
+/// the `consumer` module from the exports of an instance.
+///
+/// Each import of `consumer` is matched, by name, against the exports
+/// of `instance`, whatever the namespace of the import. The matched
+/// exports are written to `out_imports`, in the order of the imports,
+/// ready to be given to `wasm_instance_new`.
+///
+/// If an import has no export of the same name and kind, `false` is
+/// returned and `out_imports` is left untouched. The error, naming the
+/// namespace and the name of the import, can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create and instantiate a module exporting a function.
+///     wasm_byte_vec_t producer_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &producer_wat,
+///         "(module\n"
+///         "  (memory (export \"memory\") 1)\n"
+///         "  (func (export \"add_one\") (param i32) (result i32)\n"
+///         "    (i32.add (local.get 0) (i32.const 1))))"
+///     );
+///     wasm_byte_vec_t producer_wasm;
+///     wat2wasm(&producer_wat, &producer_wasm);
+///
+///     wasm_module_t* producer_module = wasm_module_new(store, &producer_wasm);
+///     assert(producer_module);
+///
+///     wasm_extern_vec_t no_imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* producer = wasm_instance_new(store, producer_module, &no_imports, &trap);
+///     assert(producer);
+///
+///     // Create a module importing it.
+///     wasm_byte_vec_t consumer_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &consumer_wat,
+///         "(module\n"
+///         "  (import \"env\" \"add_one\" (func $add_one (param i32) (result i32)))\n"
+///         "  (func (export \"add_two\") (param i32) (result i32)\n"
+///         "    (call $add_one (call $add_one (local.get 0)))))"
+///     );
+///     wasm_byte_vec_t consumer_wasm;
+///     wat2wasm(&consumer_wat, &consumer_wasm);
+///
+///     wasm_module_t* consumer_module = wasm_module_new(store, &consumer_wasm);
+///     assert(consumer_module);
+///
+///     // Link the consumer to the exports of the producer.
+///     wasm_extern_vec_t imports;
+///     assert(wasm_instance_exports_as_imports(producer, consumer_module, &imports));
+///     assert(imports.size == 1);
+///
+///     wasm_instance_t* consumer = wasm_instance_new(store, consumer_module, &imports, &trap);
+///     assert(consumer);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(consumer, &exports);
+///     const wasm_func_t* add_two = wasm_extern_as_func(exports.data[0]);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(40) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     trap = wasm_func_call(add_two, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 42);
+///
+///     // A module importing something the producer doesn't export
+///     // can't be linked to it.
+///     wasm_byte_vec_t missing_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &missing_wat,
+///         "(module\n"
+///         "  (import \"env\" \"add_one\" (func (param i32) (result i32)))\n"
+///         "  (import \"env\" \"add_three\" (func (param i32) (result i32))))"
+///     );
+///     wasm_byte_vec_t missing_wasm;
+///     wat2wasm(&missing_wat, &missing_wasm);
+///
+///     wasm_module_t* missing_module = wasm_module_new(store, &missing_wasm);
+///     assert(missing_module);
+///
+///     wasm_extern_vec_t missing_imports;
+///     assert(!wasm_instance_exports_as_imports(producer, missing_module, &missing_imports));
+///
+///     int error_length = wasmer_last_error_length();
+///     char* error_message = malloc(error_length);
+///     wasmer_last_error_message(error_message, error_length);
+///     assert(strstr(error_message, "`env`.`add_three`"));
+///     free(error_message);
+///
+///     wasm_module_delete(missing_module);
+///     wasm_byte_vec_delete(&missing_wasm);
+///     wasm_byte_vec_delete(&missing_wat);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(consumer);
+///     wasm_extern_vec_delete(&imports);
+///     wasm_module_delete(consumer_module);
+///     wasm_byte_vec_delete(&consumer_wasm);
+///     wasm_byte_vec_delete(&consumer_wat);
+///     wasm_instance_delete(producer);
+///     wasm_module_delete(producer_module);
+///     wasm_byte_vec_delete(&producer_wasm);
+///     wasm_byte_vec_delete(&producer_wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_exports_as_imports(
+    instance: &wasm_instance_t,
+    consumer: &wasm_module_t,
+    // own
+    out_imports: &mut wasm_extern_vec_t,
+) -> bool {
+    let exports = &instance.inner.exports;
+    let mut imports = Vec::new();
+
+    for import in consumer.inner.imports() {
+        let r#extern = match exports.get_extern(import.name()) {
+            Some(r#extern) => r#extern,
+            None => {
+                update_last_error(format!(
+                    "missing import `{}`.`{}`: the instance has no export named `{}`",
+                    import.module(),
+                    import.name(),
+                    import.name(),
+                ));
+                return false;
+            }
+        };
+
+        let expected = kind(import.ty());
+        let found = kind(&r#extern.ty());
+        if expected != found {
+            update_last_error(format!(
+                "incompatible import `{}`.`{}`: expected a {}, the instance exports a {}",
+                import.module(),
+                import.name(),
+                expected,
+                found,
+            ));
+            return false;
+        }
+
+        imports.push(Some(Box::new(r#extern.clone().into())));
+    }
+
+    out_imports.set_buffer(imports);
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to get the exports of an
+/// instance with their names.
+///
+/// The exports are written to `out`, in the same order as
+/// `wasm_instance_exports`. Each `wasmer_named_extern_t` has the name
+/// of the export, read with `wasmer_named_extern_name`, and an empty
+/// module name.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create and instantiate a module exporting two functions.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"add_one\") (param i32) (result i32)\n"
+///         "    (i32.add (local.get 0) (i32.const 1)))\n"
+///         "  (func (export \"forty_two\") (result i32)\n"
+///         "    (i32.const 42)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     // Find the `forty_two` export by name.
+///     wasmer_named_extern_vec_t exports;
+///     wasmer_instance_exports_named(instance, &exports);
+///     assert(exports.size == 2);
+///
+///     wasmer_assert_name(wasmer_named_extern_name(exports.data[0]), "add_one");
+///     wasmer_assert_name(wasmer_named_extern_name(exports.data[1]), "forty_two");
+///     assert(wasmer_named_extern_module(exports.data[1])->size == 0);
+///
+///     const wasm_func_t* forty_two = wasm_extern_as_func(
+///         (wasm_extern_t*) wasmer_named_extern_unwrap(exports.data[1])
+///     );
+///     assert(forty_two);
+///
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     trap = wasm_func_call(forty_two, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 42);
+///
+///     wasmer_named_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_exports_named(
+    instance: &wasm_instance_t,
+    // own
+    out: &mut wasmer_named_extern_vec_t,
+) {
+    let exports = instance
+        .inner
+        .exports
+        .iter()
+        .map(|(name, r#extern)| {
+            Some(Box::new(wasmer_named_extern_t {
+                module: String::new().into(),
+                name: name.clone().into(),
+                r#extern: Box::new(r#extern.clone().into()),
+            }))
+        })
+        .collect();
+
+    out.set_buffer(exports);
+}
+
+/// Unstable non-standard Wasmer-specific API to tag an instance with
+/// `value` under `key`.
+///
+/// The tags are listed by `wasmer_engine_live_instances_json`. The key
+/// and the value must be valid UTF-8, otherwise `false` is returned and
+/// the instance is left untouched.
+///
+/// # Example
+///
+/// See [`wasmer_engine_live_instances_json`].
+///
+/// [`wasmer_engine_live_instances_json`]: super::engine::wasmer_engine_live_instances_json
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_set_tag(
+    instance: &wasm_instance_t,
+    key: &wasm_name_t,
+    value: &wasm_name_t,
+) -> bool {
+    let (key, value) = match (
+        str::from_utf8(key.as_slice()),
+        str::from_utf8(value.as_slice()),
+    ) {
+        (Ok(key), Ok(value)) => (key, value),
+        _ => return false,
+    };
+    instance.inner.set_tag(key, value);
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to set a finalizer on
+/// an instance, called with `ctx` when the instance is deleted with
+/// `wasm_instance_delete`, to clean up the resources associated with
+/// it.
+///
+/// The finalizer is called exactly once. Setting another finalizer
+/// replaces it, without calling it.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// void finalize(void* ctx) {
+///     int* finalized = (int*) ctx;
+///     *finalized += 1;
+/// }
+///
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module)");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     int finalized = 0;
+///     wasmer_instance_set_finalizer(instance, finalize, &finalized);
+///     assert(finalized == 0);
+///
+///     // The finalizer runs once the instance is deleted.
+///     wasm_instance_delete(instance);
+///     assert(finalized == 1);
+///
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     assert(finalized == 1);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_instance_set_finalizer(
+    instance: &mut wasm_instance_t,
+    finalizer: extern "C" fn(ctx: *mut c_void),
+    ctx: *mut c_void,
+) {
+    instance.finalizer = Some(InstanceFinalizer {
+        callback: finalizer,
+        ctx,
+    });
+}
+
+pub(super) fn kind(ty: &ExternType) -> &'static str {
+    match ty {
+        ExternType::Function(_) => "function",
+        ExternType::Global(_) => "global",
+        ExternType::Table(_) => "table",
+        ExternType::Memory(_) => "memory",
+    }
+}
+
+/// Returns the global exported by `instance` under the
+/// null-terminated `name`, or updates the last error.
+unsafe fn exported_global<'a>(
+    instance: &'a wasm_instance_t,
+    name: *const c_char,
+) -> Option<&'a Global> {
+    if name.is_null() {
+        update_last_error("the name of the global is null");
+        return None;
+    }
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(error) => {
+            update_last_error(error);
+            return None;
+        }
+    };
+    match instance.inner.exports.get_global(name) {
+        Ok(global) => Some(global),
+        Err(error) => {
+            update_last_error(error);
+            None
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to read the value of the
+/// global exported by `instance` under the null-terminated `name`,
+/// without looking it up in the exports of the instance.
+///
+/// Returns `false`, and leaves `out` untouched, if there is no such
+/// export or if it isn't a global. The error message can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (global (export \"counter\") (mut i32) (i32.const 1))\n"
+///         "  (global (export \"version\") i64 (i64.const 7))\n"
+///         "  (func (export \"get\") (result i32) (global.get 0)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     // Read and write the globals by name.
+///     wasm_val_t value;
+///     assert(wasm_instance_get_global(instance, "counter", &value));
+///     assert(value.kind == WASM_I32 && value.of.i32 == 1);
+///
+///     wasm_val_t new_value = WASM_I32_VAL(42);
+///     assert(wasm_instance_set_global(instance, "counter", &new_value));
+///     assert(wasm_instance_get_global(instance, "counter", &value));
+///     assert(value.of.i32 == 42);
+///
+///     // Missing, non-global and immutable globals are errors.
+///     assert(!wasm_instance_get_global(instance, "missing", &value));
+///     assert(wasmer_last_error_length() > 0);
+///     assert(!wasm_instance_get_global(instance, "get", &value));
+///     assert(!wasm_instance_set_global(instance, "version", &new_value));
+///     assert(wasm_instance_get_global(instance, "version", &value));
+///     assert(value.kind == WASM_I64 && value.of.i64 == 7);
+///
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_get_global(
+    instance: &wasm_instance_t,
+    name: *const c_char,
+    // own
+    out: &mut wasm_val_t,
+) -> bool {
+    let global = match exported_global(instance, name) {
+        Some(global) => global,
+        None => return false,
+    };
+    match global.get().try_into() {
+        Ok(value) => {
+            *out = value;
+            true
+        }
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to write the value of the
+/// mutable global exported by `instance` under the null-terminated
+/// `name`.
+///
+/// Returns `false`, and leaves the global untouched, if there is no
+/// such export, if it isn't a global, if the global is immutable, or
+/// if `val` is of another type. The error message can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See [`wasm_instance_get_global`].
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_set_global(
+    instance: &wasm_instance_t,
+    name: *const c_char,
+    val: &wasm_val_t,
+) -> bool {
+    let global = match exported_global(instance, name) {
+        Some(global) => global,
+        None => return false,
+    };
+    if global.ty().mutability == Mutability::Const {
+        update_last_error(format!(
+            "the global `{}` is immutable",
+            CStr::from_ptr(name).to_string_lossy()
+        ));
+        return false;
+    }
+    let value: Val = match val.try_into() {
+        Ok(value) => value,
+        Err(error) => {
+            update_last_error(error);
+            return false;
+        }
+    };
+    match global.set(value) {
+        Ok(()) => true,
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Unstable non-standard Wasmer-specific extensions to the memory
+//! types.
+
+use super::super::types::{memory_type_from_limits, wasm_limits_t, wasm_memorytype_t};
+use wasmer_api::IndexType;
+
+/// Unstable non-standard Wasmer-specific API to create a memory type
+/// shared between threads, as declared with the threads proposal.
+///
+/// Shared memories must have a maximum, and their modules need the
+/// threads feature, see [`wasmer_features_threads`].
+///
+/// [`wasmer_features_threads`]: super::features::wasmer_features_threads
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_limits_t limits = { 1, 2 };
+///     wasm_memorytype_t* memory_type = wasmer_memorytype_new_shared(&limits);
+///     assert(wasmer_memorytype_is_shared(memory_type));
+///
+///     wasm_memory_t* memory = wasm_memory_new(store, memory_type);
+///     assert(memory);
+///     wasm_memorytype_t* actual_type = wasm_memory_type(memory);
+///     assert(wasmer_memorytype_is_shared(actual_type));
+///     assert(wasm_memorytype_limits(actual_type)->max == 2);
+///
+///     wasm_memorytype_delete(actual_type);
+///     wasm_memory_delete(memory);
+///     wasm_memorytype_delete(memory_type);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_memorytype_new_shared(limits: &wasm_limits_t) -> Box<wasm_memorytype_t> {
+    Box::new(wasm_memorytype_t::new(memory_type_from_limits(
+        limits, true,
+    )))
+}
+
+/// Unstable non-standard Wasmer-specific API to know whether a
+/// memory type is shared between threads, as declared with the
+/// threads proposal.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasmer_features_t* features = wasmer_features_new();
+///     wasmer_features_threads(features, true);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_features(config, features);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (import \"env\" \"shared\" (memory 1 2 shared))\n"
+///         "  (memory (export \"private\") 3))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_importtype_vec_t imports;
+///     wasm_module_imports(module, &imports);
+///     assert(imports.size == 1);
+///     const wasm_memorytype_t* shared = wasm_externtype_as_memorytype_const(
+///         wasm_importtype_type(imports.data[0])
+///     );
+///     assert(wasm_memorytype_limits(shared)->min == 1);
+///     assert(wasm_memorytype_limits(shared)->max == 2);
+///     assert(wasmer_memorytype_is_shared(shared));
+///
+///     wasm_exporttype_vec_t exports;
+///     wasm_module_exports(module, &exports);
+///     assert(exports.size == 1);
+///     const wasm_memorytype_t* private = wasm_externtype_as_memorytype_const(
+///         wasm_exporttype_type(exports.data[0])
+///     );
+///     assert(wasm_memorytype_limits(private)->min == 3);
+///     assert(!wasmer_memorytype_is_shared(private));
+///
+///     wasm_exporttype_vec_delete(&exports);
+///     wasm_importtype_vec_delete(&imports);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_memorytype_is_shared(memory_type: &wasm_memorytype_t) -> bool {
+    memory_type.inner().memory_type.shared
+}
+
+/// Unstable non-standard Wasmer-specific API to know whether a
+/// memory type is addressed with 64-bit indices, as declared with the
+/// memory64 proposal.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasmer_features_t* features = wasmer_features_new();
+///     wasmer_features_multi_memory(features, true);
+///     wasmer_features_memory64(features, true);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_features(config, features);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (import \"env\" \"large\" (memory i64 1 2))\n"
+///         "  (memory (export \"small\") 3))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_importtype_vec_t imports;
+///     wasm_module_imports(module, &imports);
+///     assert(imports.size == 1);
+///     const wasm_memorytype_t* large = wasm_externtype_as_memorytype_const(
+///         wasm_importtype_type(imports.data[0])
+///     );
+///     assert(wasm_memorytype_limits(large)->min == 1);
+///     assert(wasm_memorytype_limits(large)->max == 2);
+///     assert(wasmer_memorytype_is_64(large));
+///     assert(!wasmer_memorytype_is_shared(large));
+///
+///     wasm_exporttype_vec_t exports;
+///     wasm_module_exports(module, &exports);
+///     assert(exports.size == 1);
+///     const wasm_memorytype_t* small = wasm_externtype_as_memorytype_const(
+///         wasm_exporttype_type(exports.data[0])
+///     );
+///     assert(wasm_memorytype_limits(small)->min == 3);
+///     assert(!wasmer_memorytype_is_64(small));
+///
+///     // The memory types created by the standard API are 32-bit.
+///     wasm_limits_t limits = { 1, 2 };
+///     wasm_memorytype_t* created = wasm_memorytype_new(&limits);
+///     assert(!wasmer_memorytype_is_64(created));
+///     wasm_memorytype_delete(created);
+///
+///     wasm_exporttype_vec_delete(&exports);
+///     wasm_importtype_vec_delete(&imports);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_memorytype_is_64(memory_type: &wasm_memorytype_t) -> bool {
+    memory_type.inner().memory_type.index_type == IndexType::I64
+}
@@ -134,10 +134,14 @@
 use super::super::super::instance::wasm_instance_t;
 use super::super::parser::operator::wasmer_parser_operator_t;
 use super::wasmer_middleware_t;
+use std::os::raw::c_void;
 use std::sync::Arc;
 use wasmer_api::wasmparser::Operator;
 use wasmer_middlewares::{
-    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    metering::{
+        get_remaining_points, set_refill_callback, set_remaining_points, ExhaustionDecision,
+        MeteringPoints, OnExhaustion, RefillCallback,
+    },
     Metering,
 };
 
@@ -169,21 +173,142 @@ pub type wasmer_metering_cost_function_t =
 /// cost), in addition to a cost function. The cost function defines
 /// the cost of an operation, that will decrease the initial limit.
 ///
+/// If `cost_function` is `NULL`, every operator costs 1 point.
+///
+/// Once the points are exhausted, calling a function of the instance
+/// returns a trap, and the instance can be used again after setting
+/// new points with [`wasmer_metering_set_remaining_points`].
+///
 /// # Example
 ///
-/// See module's documentation.
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create a metering middleware with the default cost function,
+///     // and the engine and the store using it.
+///     wasmer_metering_t* metering = wasmer_metering_new(100, NULL);
+///     wasmer_middleware_t* middleware = wasmer_metering_as_middleware(metering);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_push_middleware(config, middleware);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a module summing the integers up to its parameter
+///     // in a loop, and instantiate it.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"sum\") (param $n i32) (result i32) (local $sum i32)\n"
+///         "    (loop $continue\n"
+///         "      (local.set $sum (i32.add (local.get $sum) (local.get $n)))\n"
+///         "      (local.set $n (i32.sub (local.get $n) (i32.const 1)))\n"
+///         "      (br_if $continue (local.get $n)))\n"
+///         "    (local.get $sum)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* sum = wasm_extern_as_func(exports.data[0]);
+///     assert(sum);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(100) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     // The 100 points run out in the middle of the loop: the call
+///     // returns a trap.
+///     trap = wasm_func_call(sum, &arguments_as_array, &results_as_array);
+///     assert(trap != NULL);
+///     assert(wasmer_metering_points_are_exhausted(instance));
+///     wasm_trap_delete(trap);
+///
+///     // Top up the points, and call the function again.
+///     wasmer_metering_set_remaining_points(instance, 10000);
+///     assert(!wasmer_metering_points_are_exhausted(instance));
+///
+///     trap = wasm_func_call(sum, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 5050);
+///     assert(wasmer_metering_get_remaining_points(instance) < 10000);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
 #[no_mangle]
 pub extern "C" fn wasmer_metering_new(
     initial_limit: u64,
-    cost_function: wasmer_metering_cost_function_t,
+    cost_function: Option<wasmer_metering_cost_function_t>,
 ) -> Box<wasmer_metering_t> {
-    let cost_function = move |operator: &Operator| -> u64 { cost_function(operator.into()) };
+    Box::new(wasmer_metering_t {
+        inner: Arc::new(Metering::new(
+            initial_limit,
+            boxed_cost_function(cost_function),
+        )),
+    })
+}
 
+/// Creates a new metering middleware like [`wasmer_metering_new`],
+/// except that once the points are exhausted, the refill callback of
+/// the instance is called to decide whether the execution continues.
+///
+/// The callback is set with [`wasmer_metering_set_refill_callback`].
+/// Without a callback, the execution traps.
+///
+/// # Example
+///
+/// See [`wasmer_metering_set_refill_callback`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_new_with_refill(
+    initial_limit: u64,
+    cost_function: Option<wasmer_metering_cost_function_t>,
+) -> Box<wasmer_metering_t> {
     Box::new(wasmer_metering_t {
-        inner: Arc::new(Metering::new(initial_limit, Box::new(cost_function))),
+        inner: Arc::new(
+            Metering::new(initial_limit, boxed_cost_function(cost_function))
+                .on_exhaustion(OnExhaustion::Refill),
+        ),
     })
 }
 
+fn boxed_cost_function(
+    cost_function: Option<wasmer_metering_cost_function_t>,
+) -> Box<dyn Fn(&Operator) -> u64 + Send + Sync> {
+    match cost_function {
+        Some(cost_function) => {
+            Box::new(move |operator: &Operator| -> u64 { cost_function(operator.into()) })
+        }
+        None => Box::new(|_: &Operator| -> u64 { 1 }),
+    }
+}
+
 /// Deletes a [`wasmer_metering_t`].
 ///
 /// # Example
@@ -298,6 +423,165 @@ pub extern "C" fn wasmer_metering_set_remaining_points(instance: &wasm_instance_
     set_remaining_points(&instance.inner, new_limit);
 }
 
+/// Opaque type representing the refill callback of an instance,
+/// returned by [`wasmer_metering_set_refill_callback`].
+///
+/// The callback is removed from the instance when this value is
+/// deleted with [`wasmer_metering_refill_delete`].
+#[allow(non_camel_case_types)]
+pub struct wasmer_metering_refill_t {
+    _inner: RefillCallback,
+}
+
+/// Function type to represent a refill callback implemented in C.
+///
+/// It receives the points the execution needs to continue, the
+/// points left, the points consumed since the callback was set, and
+/// the `env` given to [`wasmer_metering_set_refill_callback`]. It
+/// returns the points to add to the remaining points, or 0 to stop
+/// the execution with a trap.
+#[allow(non_camel_case_types)]
+pub type wasmer_metering_refill_callback_t = extern "C" fn(
+    required_points: u64,
+    remaining_points: u64,
+    consumed_points: u64,
+    env: *mut c_void,
+) -> u64;
+
+/// Sets the refill callback of an instance compiled with a metering
+/// middleware created by [`wasmer_metering_new_with_refill`].
+///
+/// The callback is called right where the execution runs out of
+/// points, and the execution continues from there if it returns
+/// points. It is called again if the points are still too few.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// // Grant 100 more points each time, and count the refills.
+/// uint64_t refill(uint64_t required, uint64_t remaining, uint64_t consumed, void* env) {
+///     int* refills = (int*) env;
+///     *refills += 1;
+///     return 100;
+/// }
+///
+/// int main() {
+///     wasmer_metering_t* metering = wasmer_metering_new_with_refill(100, NULL);
+///     wasmer_middleware_t* middleware = wasmer_metering_as_middleware(metering);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_push_middleware(config, middleware);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"sum\") (param $n i32) (result i32) (local $sum i32)\n"
+///         "    (loop $continue\n"
+///         "      (local.set $sum (i32.add (local.get $sum) (local.get $n)))\n"
+///         "      (local.set $n (i32.sub (local.get $n) (i32.const 1)))\n"
+///         "      (br_if $continue (local.get $n)))\n"
+///         "    (local.get $sum)))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* sum = wasm_extern_as_func(exports.data[0]);
+///     assert(sum);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(100) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     // The points are refilled as the loop runs: the call succeeds.
+///     int refills = 0;
+///     wasmer_metering_refill_t* callback =
+///         wasmer_metering_set_refill_callback(instance, refill, &refills);
+///
+///     trap = wasm_func_call(sum, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 5050);
+///     assert(refills > 0);
+///
+///     // Without the callback, the execution traps again.
+///     wasmer_metering_refill_delete(callback);
+///     wasmer_metering_set_remaining_points(instance, 100);
+///
+///     trap = wasm_func_call(sum, &arguments_as_array, &results_as_array);
+///     assert(trap != NULL);
+///     assert(wasmer_metering_points_are_exhausted(instance));
+///     wasm_trap_delete(trap);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_metering_set_refill_callback(
+    instance: &wasm_instance_t,
+    callback: wasmer_metering_refill_callback_t,
+    env: *mut c_void,
+) -> Box<wasmer_metering_refill_t> {
+    struct Env(*mut c_void);
+
+    // Only relevant when using multiple threads in the C API;
+    // Synchronization will be done via the C API / on the C side.
+    unsafe impl Send for Env {}
+    unsafe impl Sync for Env {}
+
+    let env = Env(env);
+    let inner = set_refill_callback(&instance.inner, move |exhaustion| {
+        match callback(
+            exhaustion.required,
+            exhaustion.remaining,
+            exhaustion.consumed,
+            env.0,
+        ) {
+            0 => ExhaustionDecision::Trap,
+            points => ExhaustionDecision::Refill(points),
+        }
+    });
+
+    Box::new(wasmer_metering_refill_t { _inner: inner })
+}
+
+/// Deletes a [`wasmer_metering_refill_t`], removing the refill
+/// callback from its instance.
+///
+/// # Example
+///
+/// See [`wasmer_metering_set_refill_callback`].
+#[no_mangle]
+pub extern "C" fn wasmer_metering_refill_delete(_refill: Option<Box<wasmer_metering_refill_t>>) {}
+
 /// Transforms a [`wasmer_metering_t`] into a generic
 /// [`wasmer_middleware_t`], to then be pushed in the configuration with
 /// [`wasm_config_push_middleware`][super::wasm_config_push_middleware].
@@ -1,10 +1,16 @@
 pub mod engine;
 pub mod features;
+pub mod frame;
+pub mod function;
+pub mod instance;
+pub mod memory;
 #[cfg(feature = "middlewares")]
 pub mod middlewares;
 pub mod module;
+pub mod named_extern;
 #[cfg(feature = "compiler")]
 pub mod parser;
+pub mod store;
 pub mod target_lexicon;
 #[cfg(feature = "wasi")]
 pub mod wasi;
@@ -1,10 +1,22 @@
 //! Unstable non-standard Wasmer-specific extensions to the Wasm C API.
 
+use super::super::externals::wasm_extern_vec_t;
 use super::super::module::wasm_module_t;
-use super::super::types::wasm_name_t;
+use super::super::store::wasm_store_t;
+use super::super::types::{wasm_byte_vec_t, wasm_name_t};
+use super::instance::kind;
+use super::named_extern::wasmer_named_extern_vec_t;
+use crate::error::update_last_error;
+#[cfg(feature = "json")]
+use serde_json::json;
+#[cfg(feature = "json")]
+use std::collections::HashMap;
 use std::ptr;
 use std::str;
 use std::sync::Arc;
+#[cfg(feature = "json")]
+use wasmer_api::{Exports, ImportContract, ImportObject};
+use wasmer_api::{Extern, Module};
 
 /// Unstable non-standard Wasmer-specific API to get the module's
 /// name, otherwise `out->size` is set to `0` and `out->data` to
@@ -154,3 +166,719 @@ pub unsafe extern "C" fn wasmer_module_set_name(
         None => false,
     }
 }
+
+/// Unstable non-standard Wasmer-specific API to get the exact binary
+/// the module was compiled from, when it was retained with
+/// [`wasm_config_retain_wasm_bytes`].
+///
+/// Returns `false` and leaves `out` untouched if the binary wasn't
+/// retained.
+///
+/// [`wasm_config_retain_wasm_bytes`]: super::engine::wasm_config_retain_wasm_bytes
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// # #include <string.h>
+/// #
+/// int main() {
+///     // Create the engine and the store, retaining the binaries.
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_retain_wasm_bytes(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"f\")))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module, and round-trip it through serialization.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///     wasm_byte_vec_t serialized;
+///     wasm_module_serialize(module, &serialized);
+///     wasm_module_t* deserialized = wasm_module_deserialize(store, &serialized);
+///     assert(deserialized);
+///
+///     // The binary is the same, byte for byte.
+///     wasm_byte_vec_t bytes;
+///     assert(wasmer_module_wasm_bytes(deserialized, &bytes));
+///     assert(bytes.size == wasm.size);
+///     assert(memcmp(bytes.data, wasm.data, wasm.size) == 0);
+///
+///     // Free everything.
+///     wasm_byte_vec_delete(&bytes);
+///     wasm_module_delete(deserialized);
+///     wasm_byte_vec_delete(&serialized);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_wasm_bytes(
+    module: &wasm_module_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) -> bool {
+    match module.inner.wasm_bytes() {
+        Some(bytes) => {
+            out.set_buffer(bytes.to_vec());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to get the performance
+/// diagnostics of the compiler for the module, as a JSON array.
+///
+/// Each diagnostic is an object with a `func_index`, a `code` (e.g.
+/// `"spilled-values"`), a human readable `message` and a `weight`.
+/// The array is empty unless the diagnostics have been enabled with
+/// [`wasm_config_enable_perf_diagnostics`].
+///
+/// This function is only available with the `json` feature.
+///
+/// [`wasm_config_enable_perf_diagnostics`]: super::engine::wasm_config_enable_perf_diagnostics
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// # #include <stdlib.h>
+/// # #include <string.h>
+/// #
+/// int main() {
+///     // Only Cranelift reports bounds checks that can't be elided.
+///     if (!wasmer_is_compiler_available(CRANELIFT)) {
+///         return 0;
+///     }
+///
+///     // Create the engine and the store, with the diagnostics enabled.
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_compiler(config, CRANELIFT);
+///     wasm_config_enable_perf_diagnostics(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition, with a
+///     // load whose offset goes past the guard pages.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1)\n"
+///         "  (func (param i32) (result i32)\n"
+///         "    (i32.load offset=0x80000000 (local.get 0))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Read the diagnostics.
+///     wasm_byte_vec_t diagnostics;
+///     wasmer_module_perf_diagnostics(module, &diagnostics);
+///
+///     // It works!
+///     char* json = malloc(diagnostics.size + 1);
+///     memcpy(json, diagnostics.data, diagnostics.size);
+///     json[diagnostics.size] = 0;
+///     assert(strstr(json, "\"func_index\":0"));
+///     assert(strstr(json, "\"code\":\"bounds-check-not-elided\""));
+///
+///     // Free everything.
+///     free(json);
+///     wasm_byte_vec_delete(&diagnostics);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+#[cfg(feature = "json")]
+pub unsafe extern "C" fn wasmer_module_perf_diagnostics(
+    module: &wasm_module_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) {
+    let diagnostics = module
+        .inner
+        .perf_diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            json!({
+                "func_index": diagnostic.func_index.as_u32(),
+                "code": diagnostic.code.to_string(),
+                "message": diagnostic.message,
+                "weight": diagnostic.weight,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    out.set_buffer(
+        serde_json::Value::Array(diagnostics)
+            .to_string()
+            .into_bytes(),
+    );
+}
+
+/// Unstable non-standard Wasmer-specific API to set the embedder
+/// metadata section `name` of `artifact`, a module serialized by
+/// `wasm_module_serialize` with the Universal engine, to `data`.
+///
+/// The patched artifact is written in `out`: only the metadata is
+/// rewritten, the module isn't compiled again and deserializes as
+/// before. The function returns `false` if `artifact` isn't a
+/// serialized module of this version of Wasmer or if its metadata is
+/// corrupted, the reason is reported by `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"f\")))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create and serialize the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     wasm_byte_vec_t artifact;
+///     wasm_module_serialize(module, &artifact);
+///
+///     // Attach a license to the artifact.
+///     wasm_name_t name;
+///     wasmer_byte_vec_new_from_string(&name, "license");
+///     wasm_byte_vec_t data;
+///     wasmer_byte_vec_new_from_string(&data, "key-2");
+///     wasm_byte_vec_t patched;
+///     assert(wasmer_artifact_patch_metadata(&artifact, &name, &data, &patched));
+///     assert(patched.size > artifact.size);
+///
+///     // The patched artifact still deserializes.
+///     wasm_module_t* deserialized = wasm_module_deserialize(store, &patched);
+///     assert(deserialized);
+///
+///     // Anything else isn't patched.
+///     wasm_byte_vec_t not_patched;
+///     assert(!wasmer_artifact_patch_metadata(&wasm, &name, &data, &not_patched));
+///
+///     // Free everything.
+///     wasm_module_delete(deserialized);
+///     wasm_byte_vec_delete(&patched);
+///     wasm_byte_vec_delete(&data);
+///     wasm_byte_vec_delete(&name);
+///     wasm_byte_vec_delete(&artifact);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[cfg(feature = "universal")]
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_artifact_patch_metadata(
+    artifact: &wasm_byte_vec_t,
+    name: &wasm_name_t,
+    data: &wasm_byte_vec_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) -> bool {
+    let name = match str::from_utf8(name.as_slice()) {
+        Ok(name) => name,
+        Err(error) => {
+            update_last_error(error);
+            return false;
+        }
+    };
+
+    match wasmer_api::artifact::patch_metadata(artifact.as_slice(), &[(name, data.as_slice())]) {
+        Ok(patched) => {
+            out.set_buffer(patched);
+            true
+        }
+        Err(error) => {
+            update_last_error(error);
+            false
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to build the imports of
+/// a module from externs given with their module name and name.
+///
+/// Each import of `module` is matched against the `named_imports`
+/// with the same module name and name, in any order. The matched
+/// externs are written to `out_imports`, in the order of the imports,
+/// ready to be given to `wasm_instance_new`. The named imports which
+/// aren't imported by `module` are ignored.
+///
+/// If an import has no named import of the same module name, name and
+/// kind, `false` is returned and `out_imports` is left untouched. The
+/// error, naming the module name and the name of the import, can be
+/// read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create and instantiate a module exporting two functions.
+///     wasm_byte_vec_t producer_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &producer_wat,
+///         "(module\n"
+///         "  (func (export \"one\") (result i32) (i32.const 1))\n"
+///         "  (func (export \"two\") (result i32) (i32.const 2)))"
+///     );
+///     wasm_byte_vec_t producer_wasm;
+///     wat2wasm(&producer_wat, &producer_wasm);
+///
+///     wasm_module_t* producer_module = wasm_module_new(store, &producer_wasm);
+///     wasm_extern_vec_t no_imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* producer = wasm_instance_new(store, producer_module, &no_imports, &trap);
+///     assert(producer);
+///
+///     // Create a module importing them, in the other order.
+///     wasm_byte_vec_t consumer_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &consumer_wat,
+///         "(module\n"
+///         "  (import \"\" \"two\" (func $two (result i32)))\n"
+///         "  (import \"\" \"one\" (func $one (result i32)))\n"
+///         "  (func (export \"twenty_one\") (result i32)\n"
+///         "    (i32.add (i32.mul (call $two) (i32.const 10)) (call $one))))"
+///     );
+///     wasm_byte_vec_t consumer_wasm;
+///     wat2wasm(&consumer_wat, &consumer_wasm);
+///
+///     wasm_module_t* consumer_module = wasm_module_new(store, &consumer_wasm);
+///     assert(consumer_module);
+///
+///     // The exports of the producer are named with an empty module
+///     // name, they are reordered for the consumer.
+///     wasmer_named_extern_vec_t named_imports;
+///     wasmer_instance_exports_named(producer, &named_imports);
+///
+///     wasm_extern_vec_t imports;
+///     assert(wasmer_module_imports_named(consumer_module, &named_imports, &imports));
+///     assert(imports.size == 2);
+///
+///     wasm_instance_t* consumer = wasm_instance_new(store, consumer_module, &imports, &trap);
+///     assert(consumer);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(consumer, &exports);
+///     const wasm_func_t* twenty_one = wasm_extern_as_func(exports.data[0]);
+///
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     trap = wasm_func_call(twenty_one, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 21);
+///
+///     // The producer doesn't export anything to import from `env`.
+///     wasm_byte_vec_t missing_wat;
+///     wasmer_byte_vec_new_from_string(
+///         &missing_wat,
+///         "(module (import \"env\" \"one\" (func (result i32))))"
+///     );
+///     wasm_byte_vec_t missing_wasm;
+///     wat2wasm(&missing_wat, &missing_wasm);
+///
+///     wasm_module_t* missing_module = wasm_module_new(store, &missing_wasm);
+///     wasm_extern_vec_t missing_imports;
+///     assert(!wasmer_module_imports_named(missing_module, &named_imports, &missing_imports));
+///
+///     int error_length = wasmer_last_error_length();
+///     char* error_message = malloc(error_length);
+///     wasmer_last_error_message(error_message, error_length);
+///     assert(strstr(error_message, "`env`.`one`"));
+///     free(error_message);
+///
+///     wasm_module_delete(missing_module);
+///     wasm_byte_vec_delete(&missing_wasm);
+///     wasm_byte_vec_delete(&missing_wat);
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(consumer);
+///     wasm_extern_vec_delete(&imports);
+///     wasmer_named_extern_vec_delete(&named_imports);
+///     wasm_module_delete(consumer_module);
+///     wasm_byte_vec_delete(&consumer_wasm);
+///     wasm_byte_vec_delete(&consumer_wat);
+///     wasm_instance_delete(producer);
+///     wasm_module_delete(producer_module);
+///     wasm_byte_vec_delete(&producer_wasm);
+///     wasm_byte_vec_delete(&producer_wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_imports_named(
+    module: &wasm_module_t,
+    named_imports: &wasmer_named_extern_vec_t,
+    // own
+    out_imports: &mut wasm_extern_vec_t,
+) -> bool {
+    let named_imports = named_imports
+        .as_slice()
+        .iter()
+        .filter_map(|named_import| named_import.as_deref())
+        .collect::<Vec<_>>();
+    let mut imports = Vec::new();
+
+    for import in module.inner.imports() {
+        let named_import = match named_imports.iter().find(|named_import| {
+            named_import.module.as_slice() == import.module().as_bytes()
+                && named_import.name.as_slice() == import.name().as_bytes()
+        }) {
+            Some(named_import) => named_import,
+            None => {
+                update_last_error(format!(
+                    "missing import `{}`.`{}`",
+                    import.module(),
+                    import.name(),
+                ));
+                return false;
+            }
+        };
+
+        let r#extern = Extern::from(named_import.r#extern.as_ref().clone());
+        let expected = kind(import.ty());
+        let found = kind(&r#extern.ty());
+        if expected != found {
+            update_last_error(format!(
+                "incompatible import `{}`.`{}`: expected a {}, found a {}",
+                import.module(),
+                import.name(),
+                expected,
+                found,
+            ));
+            return false;
+        }
+
+        imports.push(Some(Box::new(r#extern.into())));
+    }
+
+    out_imports.set_buffer(imports);
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to get the import
+/// contract of a module, as JSON: its imports and its exports, with
+/// their types.
+///
+/// The contract can be checked against the imports provided by a host
+/// with `wasmer_contract_verify`, see the Rust `ImportContract` API
+/// for the format.
+///
+/// This function is only available with the `json` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// wasm_trap_t* log_callback(const wasm_val_vec_t* arguments, wasm_val_vec_t* results) {
+///     return NULL;
+/// }
+///
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (import \"host\" \"log\" (func (param i32)))\n"
+///         "  (func (export \"run\")))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Write the contract of the guest.
+///     wasm_byte_vec_t contract;
+///     wasmer_module_import_contract_json(module, &contract);
+///     assert(contract.size > 0);
+///
+///     // No import satisfies nothing.
+///     wasmer_named_extern_vec_t no_imports = WASM_EMPTY_VEC;
+///     assert(!wasmer_contract_verify(&contract, &no_imports));
+///
+///     int error_length = wasmer_last_error_length();
+///     char* error_message = malloc(error_length);
+///     wasmer_last_error_message(error_message, error_length);
+///     assert(strstr(error_message, "`host`.`log`"));
+///     free(error_message);
+///
+///     // A host function with the right type does.
+///     wasm_valtype_vec_t params;
+///     wasm_valtype_vec_new_uninitialized(&params, 1);
+///     params.data[0] = wasm_valtype_new_i32();
+///     wasm_valtype_vec_t results = WASM_EMPTY_VEC;
+///     wasm_functype_t* log_type = wasm_functype_new(&params, &results);
+///     wasm_func_t* log = wasm_func_new(store, log_type, log_callback);
+///
+///     wasm_byte_vec_t namespace;
+///     wasmer_byte_vec_new_from_string(&namespace, "host");
+///     wasm_byte_vec_t name;
+///     wasmer_byte_vec_new_from_string(&name, "log");
+///     wasmer_named_extern_t* named_log = wasmer_named_extern_new(
+///         &namespace,
+///         &name,
+///         wasm_func_as_extern(log)
+///     );
+///
+///     wasmer_named_extern_vec_t imports;
+///     wasmer_named_extern_vec_new(&imports, 1, &named_log);
+///     assert(wasmer_contract_verify(&contract, &imports));
+///
+///     wasmer_named_extern_vec_delete(&imports);
+///     wasm_byte_vec_delete(&name);
+///     wasm_byte_vec_delete(&namespace);
+///     wasm_func_delete(log);
+///     wasm_functype_delete(log_type);
+///     wasm_byte_vec_delete(&contract);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+#[cfg(feature = "json")]
+pub unsafe extern "C" fn wasmer_module_import_contract_json(
+    module: &wasm_module_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) {
+    out.set_buffer(module.inner.export_import_contract().to_json().into_bytes());
+}
+
+/// Unstable non-standard Wasmer-specific API to check that
+/// `named_imports` satisfy the import contract `contract`, as written
+/// by `wasmer_module_import_contract_json`.
+///
+/// Returns `false` if an import of the contract isn't provided, or is
+/// provided with an incompatible type, or if `contract` isn't a valid
+/// contract. The error, listing the unsatisfied imports, can be read
+/// with `wasmer_last_error_message`.
+///
+/// This function is only available with the `json` feature.
+///
+/// # Example
+///
+/// See [`wasmer_module_import_contract_json`].
+#[no_mangle]
+#[cfg(feature = "json")]
+pub unsafe extern "C" fn wasmer_contract_verify(
+    contract: &wasm_byte_vec_t,
+    named_imports: &wasmer_named_extern_vec_t,
+) -> bool {
+    let contract = match str::from_utf8(contract.as_slice())
+        .map_err(|e| e.to_string())
+        .and_then(|json| ImportContract::from_json(json).map_err(|e| e.to_string()))
+    {
+        Ok(contract) => contract,
+        Err(error) => {
+            update_last_error(error);
+            return false;
+        }
+    };
+
+    let mut namespaces = HashMap::<String, Exports>::new();
+    for named_import in named_imports
+        .as_slice()
+        .iter()
+        .filter_map(|named_import| named_import.as_deref())
+    {
+        namespaces
+            .entry(String::from_utf8_lossy(named_import.module.as_slice()).into_owned())
+            .or_default()
+            .insert(
+                String::from_utf8_lossy(named_import.name.as_slice()).into_owned(),
+                Extern::from(named_import.r#extern.as_ref().clone()),
+            );
+    }
+    let mut import_object = ImportObject::new();
+    for (namespace, exports) in namespaces {
+        import_object.register(namespace, exports);
+    }
+
+    let violations = contract.verify_against(&import_object);
+    if violations.is_empty() {
+        return true;
+    }
+    update_last_error(
+        violations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    );
+    false
+}
+
+/// Unstable non-standard Wasmer-specific API to validate the binary
+/// of a module, like `wasm_module_validate`, but telling why it's
+/// invalid.
+///
+/// The module is only validated, against the features of the engine
+/// of `store`, not compiled, which makes this cheap enough to check
+/// modules as they are received.
+///
+/// Returns `true` if the module is valid, and `out` is then empty:
+/// `out->size` is set to `0` and `out->data` to `NULL`. Otherwise
+/// returns `false` and `out` holds a human-readable description of the
+/// first error, i.e. the rule of the specification the binary breaks,
+/// and the offset of the offending byte in the binary.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // A valid module.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (result i32) (i32.const 1)))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_byte_vec_t error;
+///     assert(wasm_module_validate_detailed(store, &wasm, &error));
+///     assert(error.size == 0);
+///
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///
+///     // A module whose function doesn't return its result.
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (result i32)))");
+///     wat2wasm(&wat, &wasm);
+///
+///     assert(!wasm_module_validate_detailed(store, &wasm, &error));
+///
+///     // The error is e.g. `type mismatch: expected i32 but nothing on
+///     // stack (at offset 24)`.
+///     wasm_byte_vec_t message;
+///     wasm_byte_vec_new_uninitialized(&message, error.size + 1);
+///     memcpy(message.data, error.data, error.size);
+///     message.data[error.size] = 0;
+///     assert(strstr(message.data, "type mismatch"));
+///     assert(strstr(message.data, "(at offset "));
+///
+///     wasm_byte_vec_delete(&message);
+///
+///     // Free everything.
+///     wasm_byte_vec_delete(&error);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_validate_detailed(
+    store: &wasm_store_t,
+    binary: &wasm_byte_vec_t,
+    // own
+    out: &mut wasm_byte_vec_t,
+) -> bool {
+    match Module::validate_with_engine(store.inner.engine().as_ref(), binary.as_slice()) {
+        Ok(()) => {
+            out.data = ptr::null_mut();
+            out.size = 0;
+
+            true
+        }
+        Err(error) => {
+            out.set_buffer(error.to_string().into_bytes());
+
+            false
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Unstable non-standard Wasmer-specific type to pair an extern with
+//! its module name and name.
+
+use super::super::{externals::wasm_extern_t, types::wasm_name_t};
+
+/// Unstable non-standard type wrapping `wasm_extern_t` with the
+/// addition of two `wasm_name_t` respectively for the module name and
+/// the name of the extern. This non-standard type is used by the
+/// unstable non-standard `wasi_get_unordered_imports`,
+/// `wasmer_instance_exports_named` and `wasmer_module_imports_named`
+/// functions.
+///
+/// The `module`, `name` and `extern` fields are all owned by this type.
+#[allow(non_camel_case_types)]
+#[derive(Clone)]
+pub struct wasmer_named_extern_t {
+    pub(crate) module: wasm_name_t,
+    pub(crate) name: wasm_name_t,
+    pub(crate) r#extern: Box<wasm_extern_t>,
+}
+
+wasm_declare_boxed_vec!(named_extern, wasmer);
+
+/// So. Let's explain a dirty hack. `cbindgen` reads the code and
+/// collects symbols. What symbols do we need? None of the one
+/// declared in `wasm.h`, but for non-standard API, we need to collect
+/// all of them. The problem is that `wasmer_named_extern_t` is the only
+/// non-standard type where extra symbols are generated by a macro
+/// (`wasm_declare_boxed_vec!`). If we want those macro-generated
+/// symbols to be collected by `cbindgen`, we need to _expand_ the
+/// crate (i.e. running something like `rustc -- -Zunstable-options
+/// --pretty=expanded`). Expanding code is unstable and available only
+/// on nightly compiler. We _don't want_ to use a nightly compiler
+/// only for that. So how can we help `cbindgen` to _see_ those
+/// symbols?
+///
+/// First solution: We write the C code directly in a file, which is
+/// then included in the generated header file with the `cbindgen`
+/// API. Problem, it's super easy to get it outdated, and it makes the
+/// build process more complex.
+///
+/// Second solution: We write those symbols in a custom module, that
+/// is just here for `cbindgen`, never used by our Rust code
+/// (otherwise it's duplicated code), with no particular
+/// implementation.
+///
+/// And that's why we have the following `cbindgen_hack`
+/// module.
+///
+/// But this module must not be compiled by `rustc`. How to force
+/// `rustc` to ignore a module? With conditional compilation. Because
+/// `cbindgen` does not support conditional compilation, it will
+/// always _ignore_ the `#[cfg]` attribute, and will always read the
+/// content of the module.
+///
+/// Sorry.
+#[doc(hidden)]
+#[cfg(__cbindgen_hack__ = "yes")]
+mod __cbindgen_hack__ {
+    use super::*;
+
+    #[repr(C)]
+    pub struct wasmer_named_extern_vec_t {
+        pub size: usize,
+        pub data: *mut *mut wasmer_named_extern_t,
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_named_extern_vec_new(
+        out: *mut wasmer_named_extern_vec_t,
+        length: usize,
+        init: *const *mut wasmer_named_extern_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_named_extern_vec_new_uninitialized(
+        out: *mut wasmer_named_extern_vec_t,
+        length: usize,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_named_extern_vec_copy(
+        out_ptr: &mut wasmer_named_extern_vec_t,
+        in_ptr: &wasmer_named_extern_vec_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_named_extern_vec_delete(
+        ptr: Option<&mut wasmer_named_extern_vec_t>,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_named_extern_vec_new_empty(
+        out: *mut wasmer_named_extern_vec_t,
+    ) {
+        unimplemented!()
+    }
+}
+
+/// Non-standard function to create a `wasmer_named_extern_t` from a
+/// module name, a name and an extern.
+///
+/// The arguments are copied, they are still owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_named_extern_new(
+    module: &wasm_name_t,
+    name: &wasm_name_t,
+    r#extern: &wasm_extern_t,
+) -> Box<wasmer_named_extern_t> {
+    Box::new(wasmer_named_extern_t {
+        module: module.clone(),
+        name: name.clone(),
+        r#extern: Box::new(r#extern.clone()),
+    })
+}
+
+/// Non-standard function to get the module name of a
+/// `wasmer_named_extern_t`.
+///
+/// The returned value isn't owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_named_extern_module(
+    named_extern: Option<&wasmer_named_extern_t>,
+) -> Option<&wasm_name_t> {
+    Some(&named_extern?.module)
+}
+
+/// Non-standard function to get the name of a `wasmer_named_extern_t`.
+///
+/// The returned value isn't owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_named_extern_name(
+    named_extern: Option<&wasmer_named_extern_t>,
+) -> Option<&wasm_name_t> {
+    Some(&named_extern?.name)
+}
+
+/// Non-standard function to get the wrapped extern of a
+/// `wasmer_named_extern_t`.
+///
+/// The returned value isn't owned by the caller.
+#[no_mangle]
+pub extern "C" fn wasmer_named_extern_unwrap(
+    named_extern: Option<&wasmer_named_extern_t>,
+) -> Option<&wasm_extern_t> {
+    Some(named_extern?.r#extern.as_ref())
+}
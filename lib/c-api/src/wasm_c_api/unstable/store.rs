@@ -0,0 +1,535 @@
+//! Unstable non-standard Wasmer-specific API for the
+//! `wasm_store_t`.
+
+use super::super::store::wasm_store_t;
+use super::features::wasmer_features_t;
+use std::convert::TryInto;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::Arc;
+use wasmer_api::{InstantiationEvent, InstantiationObserver, InstantiationPhase};
+
+/// Unstable non-standard Wasmer-specific API to set the epoch
+/// deadline of a store `ticks` epochs after the current epoch of its
+/// engine.
+///
+/// Once the epoch counter of the engine, incremented with
+/// [`wasmer_engine_increment_epoch`], goes past the deadline, code
+/// compiled with [`wasm_config_enable_epoch_interruption`] traps at
+/// the next function entry or loop header. By default, a store has
+/// no deadline.
+///
+/// [`wasmer_engine_increment_epoch`]: super::engine::wasmer_engine_increment_epoch
+/// [`wasm_config_enable_epoch_interruption`]: super::engine::wasm_config_enable_epoch_interruption
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_enable_epoch_interruption(config, true);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a module with a function looping forever.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func (export \"spin\") (param $n i32)\n"
+///         "    (loop $continue\n"
+///         "      (br_if $continue (local.get $n)))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* spin = wasm_extern_as_func(exports.data[0]);
+///     assert(spin);
+///
+///     // The deadline is the current epoch: without looping, the
+///     // function returns.
+///     wasmer_store_set_epoch_deadline_after(store, 0);
+///
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(0) };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_EMPTY_VEC;
+///
+///     trap = wasm_func_call(spin, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///
+///     // Once the epoch goes past the deadline, the infinite loop is
+///     // interrupted.
+///     wasmer_engine_increment_epoch(engine);
+///     arguments[0].of.i32 = 1;
+///
+///     trap = wasm_func_call(spin, &arguments_as_array, &results_as_array);
+///     assert(trap != NULL);
+///     wasm_trap_delete(trap);
+///
+///     // The deadline can be set to an epoch too: the epoch is 1, which
+///     // doesn't go past a deadline of 1, but goes past 0.
+///     arguments[0].of.i32 = 0;
+///     wasmer_store_set_epoch_deadline(store, 1);
+///     trap = wasm_func_call(spin, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///
+///     wasmer_store_set_epoch_deadline(store, 0);
+///     trap = wasm_func_call(spin, &arguments_as_array, &results_as_array);
+///     assert(trap != NULL);
+///     wasm_trap_delete(trap);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_store_set_epoch_deadline_after(store: &wasm_store_t, ticks: u64) {
+    store.inner.set_epoch_deadline_after(ticks);
+}
+
+/// Unstable non-standard Wasmer-specific API to set the epoch
+/// deadline of a store to the epoch `deadline`: code compiled with
+/// [`wasm_config_enable_epoch_interruption`] traps once the epoch
+/// counter of the engine goes past it. `UINT64_MAX` removes the
+/// deadline.
+///
+/// [`wasm_config_enable_epoch_interruption`]: super::engine::wasm_config_enable_epoch_interruption
+///
+/// # Example
+///
+/// See [`wasmer_store_set_epoch_deadline_after`].
+#[no_mangle]
+pub extern "C" fn wasmer_store_set_epoch_deadline(store: &wasm_store_t, deadline: u64) {
+    store.inner.set_epoch_deadline(deadline);
+}
+
+/// Unstable non-standard Wasmer-specific API to limit the total size
+/// in bytes of the memories of a store.
+///
+/// Growing a memory past the limit fails, and `memory.grow` returns
+/// `-1` to the guest. Creating a memory, or instantiating a module
+/// defining one, fails when its minimum doesn't fit in the limit. The
+/// limit only applies to the memories created after the call.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Limit the memories to 3 pages of 64KiB.
+///     wasmer_store_set_memory_limit(store, 3 * 65536);
+///
+///     // Create a module with a memory of 1 page.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1)\n"
+///         "  (func (export \"grow\") (param $delta i32) (result i32)\n"
+///         "    (memory.grow (local.get $delta))))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     wasm_extern_vec_t exports;
+///     wasm_instance_exports(instance, &exports);
+///     const wasm_func_t* grow = wasm_extern_as_func(exports.data[0]);
+///     assert(grow);
+///
+///     // Growing by 2 pages fits in the limit.
+///     wasm_val_t arguments[1] = { WASM_I32_VAL(2) };
+///     wasm_val_t results[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+///     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+///
+///     trap = wasm_func_call(grow, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == 1);
+///
+///     // Growing by 1 more page doesn't.
+///     arguments[0].of.i32 = 1;
+///
+///     trap = wasm_func_call(grow, &arguments_as_array, &results_as_array);
+///     assert(trap == NULL);
+///     assert(results[0].of.i32 == -1);
+///
+///     wasm_extern_vec_delete(&exports);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_store_set_memory_limit(store: &mut wasm_store_t, max_bytes: u64) {
+    store.inner.set_memory_limit(max_bytes);
+}
+
+/// Unstable non-standard Wasmer-specific type of the statistics about
+/// the `externref`s held by the tables and globals of a store, see
+/// [`wasmer_store_externref_stats`].
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct wasmer_externref_stats_t {
+    /// The number of distinct non-null references held.
+    pub live: usize,
+    /// The sum of the strong counts of these references, including
+    /// the handles kept by the host.
+    pub strong_references: usize,
+}
+
+/// Unstable non-standard Wasmer-specific API to get statistics about
+/// the `externref`s held by the tables and globals of a store, to
+/// find leaked references.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create an instance with a table of null references.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (table 2 externref))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance);
+///
+///     // Null references aren't counted.
+///     wasmer_externref_stats_t stats;
+///     wasmer_store_externref_stats(store, &stats);
+///     assert(stats.live == 0);
+///     assert(stats.strong_references == 0);
+///
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_store_externref_stats(
+    store: &wasm_store_t,
+    stats: &mut wasmer_externref_stats_t,
+) {
+    let store_stats = store.inner.externref_stats();
+    stats.live = store_stats.live;
+    stats.strong_references = store_stats.strong_references;
+}
+
+/// Unstable non-standard Wasmer-specific type of the phases of an
+/// instantiation, see [`wasmer_instantiation_event_t`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum wasmer_instantiation_phase_t {
+    /// The imports are resolved and checked against the types the
+    /// module expects.
+    RESOLVE_IMPORTS = 0,
+
+    /// The memories defined by the module are allocated.
+    ALLOCATE_MEMORIES = 1,
+
+    /// The tables defined by the module are allocated.
+    ALLOCATE_TABLES = 2,
+
+    /// The element segments are written into the tables.
+    APPLY_ELEMENT_SEGMENTS = 3,
+
+    /// The data segments are written into the memories.
+    APPLY_DATA_SEGMENTS = 4,
+
+    /// The start function of the module, if any, is invoked.
+    START = 5,
+}
+
+/// Unstable non-standard Wasmer-specific type of a phase of an
+/// instantiation which ended, given to the callback set with
+/// [`wasmer_store_set_instantiation_observer`].
+#[derive(Debug)]
+#[repr(C)]
+pub struct wasmer_instantiation_event_t {
+    /// The phase which ended.
+    pub phase: wasmer_instantiation_phase_t,
+    /// How long the phase took, in nanoseconds.
+    pub duration_ns: u64,
+    /// The number of elements of the element segments for
+    /// `APPLY_ELEMENT_SEGMENTS`, the number of bytes of the data
+    /// segments for `APPLY_DATA_SEGMENTS`, 0 otherwise.
+    pub count: u64,
+    /// The message of the error the phase failed with, or `NULL` if
+    /// it succeeded. It's only valid during the callback.
+    pub error: *const c_char,
+}
+
+/// Function type to represent an instantiation observer implemented
+/// in C.
+///
+/// It receives the event of a phase which ended, and the `env` given
+/// to [`wasmer_store_set_instantiation_observer`].
+#[allow(non_camel_case_types)]
+pub type wasmer_instantiation_observer_callback_t =
+    extern "C" fn(event: &wasmer_instantiation_event_t, env: *mut c_void);
+
+/// The observer calling a [`wasmer_instantiation_observer_callback_t`].
+struct CallbackObserver {
+    callback: wasmer_instantiation_observer_callback_t,
+    env: *mut c_void,
+}
+
+// Only relevant when using multiple threads in the C API;
+// Synchronization will be done via the C API / on the C side.
+unsafe impl Send for CallbackObserver {}
+unsafe impl Sync for CallbackObserver {}
+
+impl InstantiationObserver for CallbackObserver {
+    fn on_event(&self, event: &InstantiationEvent<'_>) {
+        let (phase, count) = match event.phase {
+            InstantiationPhase::ResolveImports => {
+                (wasmer_instantiation_phase_t::RESOLVE_IMPORTS, 0)
+            }
+            InstantiationPhase::AllocateMemories => {
+                (wasmer_instantiation_phase_t::ALLOCATE_MEMORIES, 0)
+            }
+            InstantiationPhase::AllocateTables => {
+                (wasmer_instantiation_phase_t::ALLOCATE_TABLES, 0)
+            }
+            InstantiationPhase::ApplyElementSegments { elements } => (
+                wasmer_instantiation_phase_t::APPLY_ELEMENT_SEGMENTS,
+                elements,
+            ),
+            InstantiationPhase::ApplyDataSegments { bytes } => {
+                (wasmer_instantiation_phase_t::APPLY_DATA_SEGMENTS, bytes)
+            }
+            InstantiationPhase::Start => (wasmer_instantiation_phase_t::START, 0),
+        };
+        let error = event.error.map(|error| {
+            CString::new(error.to_string().replace('\0', ""))
+                .expect("the interior nul bytes are removed")
+        });
+
+        (self.callback)(
+            &wasmer_instantiation_event_t {
+                phase,
+                duration_ns: event.duration.as_nanos().try_into().unwrap_or(u64::MAX),
+                count: count as u64,
+                error: error
+                    .as_ref()
+                    .map_or(std::ptr::null(), |error| error.as_ptr()),
+            },
+            self.env,
+        );
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to observe the
+/// instantiations of a store.
+///
+/// The callback is called with `env` at the end of each phase of the
+/// instantiations started after the call, including the phase which
+/// fails. Passing a `NULL` callback removes the observer.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// typedef struct {
+///     int events;
+///     uint64_t data_bytes;
+///     int failed_phase;
+/// } observed_t;
+///
+/// void observe(const wasmer_instantiation_event_t* event, void* env) {
+///     observed_t* observed = (observed_t*) env;
+///     observed->events += 1;
+///     if (event->phase == APPLY_DATA_SEGMENTS) {
+///         observed->data_bytes = event->count;
+///     }
+///     if (event->error != NULL) {
+///         observed->failed_phase = event->phase;
+///     }
+/// }
+///
+/// int main() {
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     observed_t observed = { 0, 0, -1 };
+///     wasmer_store_set_instantiation_observer(store, observe, &observed);
+///
+///     // Create a module with data segments and a trapping start
+///     // function.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1)\n"
+///         "  (data (i32.const 0) \"hello\")\n"
+///         "  (data (i32.const 16) \"world!\")\n"
+///         "  (func $start unreachable)\n"
+///         "  (start $start))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = NULL;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+///     assert(instance == NULL);
+///     wasm_trap_delete(trap);
+///
+///     // Every phase is reported, the start function failed.
+///     assert(observed.events == 6);
+///     assert(observed.data_bytes == 11);
+///     assert(observed.failed_phase == START);
+///
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_store_set_instantiation_observer(
+    store: &wasm_store_t,
+    callback: Option<wasmer_instantiation_observer_callback_t>,
+    env: *mut c_void,
+) {
+    store
+        .inner
+        .set_instantiation_observer(callback.map(|callback| {
+            Arc::new(CallbackObserver { callback, env }) as Arc<dyn InstantiationObserver>
+        }));
+}
+
+/// Unstable non-standard Wasmer-specific API to get the WebAssembly
+/// features enabled in the engine of a store.
+///
+/// The modules are compiled, and the artifacts deserialized, with
+/// these features: they can be compared with the features an artifact
+/// was produced with, or with [`wasmer_features_required`] by a
+/// module, before loading it.
+///
+/// The returned features must be freed with
+/// [`wasmer_features_delete`].
+///
+/// [`wasmer_features_required`]: super::features::wasmer_features_required
+/// [`wasmer_features_delete`]: super::features::wasmer_features_delete
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasmer_features_t* features = wasmer_features_new();
+///     wasmer_features_simd(features, false);
+///     wasmer_features_bulk_memory(features, true);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_set_features(config, features);
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasmer_features_t* enabled = wasmer_store_features(store);
+///     assert(!wasmer_features_get_simd(enabled));
+///     assert(wasmer_features_get_bulk_memory(enabled));
+///
+///     wasmer_features_delete(enabled);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_store_features(store: &wasm_store_t) -> Box<wasmer_features_t> {
+    Box::new(wasmer_features_t {
+        inner: store.inner.features().clone(),
+    })
+}
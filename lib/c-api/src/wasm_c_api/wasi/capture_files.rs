@@ -4,7 +4,18 @@ use std::collections::VecDeque;
 use std::io::{self, Read, Seek, Write};
 use wasmer_wasi::{WasiFile, WasiFsError};
 
-/// For capturing stdout/stderr. Stores all output in a string.
+/// The maximum number of bytes an [`OutputCapturer`] holds before it
+/// starts rejecting writes.
+pub const CAPTURE_BUFFER_CAPACITY: usize = 1 << 20;
+
+/// For capturing stdout/stderr. Stores the output in a bounded buffer
+/// until it is read by the host.
+///
+/// Once the buffer holds [`CAPTURE_BUFFER_CAPACITY`] bytes, writes
+/// are cut short to what still fits, and fail (the guest sees
+/// `EAGAIN`) when nothing fits anymore, until the host reads some of
+/// the output. The guest is never blocked, and the accepted output is
+/// never dropped.
 #[derive(Debug)]
 pub struct OutputCapturer {
     pub(crate) buffer: VecDeque<u8>,
@@ -80,14 +91,18 @@ impl Seek for OutputCapturer {
 }
 impl Write for OutputCapturer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.buffer.extend(buf);
-        Ok(buf.len())
+        let free = CAPTURE_BUFFER_CAPACITY - self.buffer.len();
+        if free == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "the capture buffer is full",
+            ));
+        }
+        let written = buf.len().min(free);
+        self.buffer.extend(&buf[..written]);
+        Ok(written)
     }
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.buffer.extend(buf);
-        Ok(())
-    }
 }
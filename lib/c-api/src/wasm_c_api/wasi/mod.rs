@@ -3,6 +3,7 @@
 //! This API will be superseded by a standard WASI API when/if such a standard is created.
 
 mod capture_files;
+pub mod pipe;
 
 pub use super::unstable::wasi::wasi_get_unordered_imports;
 use super::{
@@ -12,15 +13,20 @@ use super::{
     store::wasm_store_t,
 };
 use crate::error::update_last_error;
+use pipe::wasmer_pipe_t;
 use std::cmp::min;
 use std::convert::TryFrom;
 use std::ffi::CStr;
+use std::io::{Read, Write};
 use std::os::raw::c_char;
+use std::path::Path;
 use std::slice;
+use std::sync::TryLockError;
 use wasmer_api::{Extern, NamedResolver};
+use wasmer_vfs::{mem_fs, FileSystem, FsError};
 use wasmer_wasi::{
-    generate_import_object_from_env, get_wasi_version, WasiEnv, WasiFile, WasiState,
-    WasiStateBuilder, WasiVersion,
+    generate_import_object_for_module, get_wasi_version, WasiEnv, WasiFile, WasiState,
+    WasiStateBuilder, WasiSyscall, WasiVersion,
 };
 
 #[derive(Debug)]
@@ -29,6 +35,11 @@ pub struct wasi_config_t {
     inherit_stdout: bool,
     inherit_stderr: bool,
     inherit_stdin: bool,
+    stdout: Option<pipe::PipeWriter>,
+    stderr: Option<pipe::PipeWriter>,
+    stdin: Option<pipe::PipeReader>,
+    mapped_dir_aliases: Vec<String>,
+    use_memfs: bool,
     state_builder: WasiStateBuilder,
 }
 
@@ -45,6 +56,11 @@ pub unsafe extern "C" fn wasi_config_new(
         inherit_stdout: true,
         inherit_stderr: true,
         inherit_stdin: true,
+        stdout: None,
+        stderr: None,
+        stdin: None,
+        mapped_dir_aliases: vec![],
+        use_memfs: false,
         state_builder: WasiState::new(prog_name),
     }))
 }
@@ -99,6 +115,45 @@ pub unsafe extern "C" fn wasi_config_preopen_dir(
     true
 }
 
+/// Preopens a host directory with read-only access.
+///
+/// The guest can list and read the files of the directory, but any
+/// attempt to create, modify or remove entries fails with
+/// `__WASI_EACCES`. It can be combined with
+/// [`wasi_config_preopen_dir`] to give a guest both read-only and
+/// read-write directories.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_preopen_dir_readonly(
+    config: &mut wasi_config_t,
+    dir: *const c_char,
+) -> bool {
+    let dir_cstr = CStr::from_ptr(dir);
+    let dir_bytes = dir_cstr.to_bytes();
+    let dir_str = match std::str::from_utf8(dir_bytes) {
+        Ok(dir_str) => dir_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    if let Err(e) = config
+        .state_builder
+        .preopen(|p| p.directory(dir_str).read(true))
+    {
+        update_last_error(e);
+        return false;
+    }
+
+    true
+}
+
+/// Preopens the host directory `dir` and exposes it to the guest as
+/// `alias`, hiding the layout of the host filesystem.
+///
+/// Returns `false` and sets the last error if `alias` isn't an
+/// absolute path, if it has already been mapped, or if `dir` isn't an
+/// existing directory.
 #[no_mangle]
 pub unsafe extern "C" fn wasi_config_mapdir(
     config: &mut wasi_config_t,
@@ -125,32 +180,139 @@ pub unsafe extern "C" fn wasi_config_mapdir(
         }
     };
 
+    if !alias_str.starts_with('/') {
+        update_last_error(format!(
+            "the alias `{}` must be an absolute path",
+            alias_str
+        ));
+        return false;
+    }
+
+    // The alias is mounted at the root regardless of its leading
+    // slashes, so compare aliases without them.
+    let alias_key = alias_str.trim_start_matches('/').to_string();
+    if config.mapped_dir_aliases.contains(&alias_key) {
+        update_last_error(format!("the alias `{}` is already mapped", alias_str));
+        return false;
+    }
+
+    if !std::path::Path::new(dir_str).is_dir() {
+        update_last_error(format!("the host directory `{}` doesn't exist", dir_str));
+        return false;
+    }
+
     if let Err(e) = config.state_builder.map_dir(alias_str, dir_str) {
         update_last_error(e);
         return false;
     }
+    config.mapped_dir_aliases.push(alias_key);
 
     true
 }
 
+/// Deny the WASI syscall named `name` (e.g. `"random_get"`) to the
+/// guest: calling it returns `__WASI_ENOTCAPABLE` without doing
+/// anything.
+///
+/// Returns `false` and sets the last error if `name` isn't a WASI
+/// syscall.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_deny_syscall(
+    config: &mut wasi_config_t,
+    name: *const c_char,
+) -> bool {
+    let name_cstr = CStr::from_ptr(name);
+    let name_str = match name_cstr.to_str() {
+        Ok(name_str) => name_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    match WasiSyscall::from_name(name_str) {
+        Some(syscall) => {
+            config.state_builder.deny_syscalls(&[syscall]);
+            true
+        }
+        None => {
+            update_last_error(format!("`{}` is not a WASI syscall", name_str));
+            false
+        }
+    }
+}
+
+/// Back the whole filesystem of the guest with an in-memory
+/// filesystem instead of the one of the host, and preopen its root
+/// `/`.
+///
+/// The host can seed the files of the guest with
+/// [`wasi_env_memfs_write_file`] and collect the files it produced
+/// with [`wasi_env_memfs_read_file`]. The directories preopened with
+/// [`wasi_config_preopen_dir`] or mapped with [`wasi_config_mapdir`]
+/// are then directories of the in-memory filesystem.
+#[no_mangle]
+pub extern "C" fn wasi_config_use_memfs(config: &mut wasi_config_t) {
+    if config.use_memfs {
+        return;
+    }
+    config.use_memfs = true;
+    config
+        .state_builder
+        .set_fs(Box::new(mem_fs::FileSystem::default()))
+        .preopen_dir("/")
+        .expect("`/` is a valid directory to preopen");
+}
+
+/// Capture the `stdout` of the guest instead of inheriting the one of
+/// the host. The captured output can be read with
+/// [`wasi_env_read_stdout`] or [`wasi_env_read_stdout_available`].
+///
+/// At most `1 MiB` of output is buffered: writes of the guest are cut
+/// short to what still fits, and fail with `EAGAIN` once the buffer
+/// is full, until the host reads from it.
 #[no_mangle]
 pub extern "C" fn wasi_config_capture_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = false;
+    config.stdout = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = true;
+    config.stdout = None;
+}
+
+/// Send the `stdout` of the guest to `pipe`, where it can be read
+/// with [`wasmer_pipe_read`](pipe::wasmer_pipe_read) while the guest is running.
+///
+/// The pipe reaches its end once the WASI environment and the
+/// instances using it are deleted, see [`wasmer_pipe_eof`](pipe::wasmer_pipe_eof). The pipe
+/// itself can be deleted at any time.
+#[no_mangle]
+pub extern "C" fn wasi_config_set_stdout(config: &mut wasi_config_t, pipe: &wasmer_pipe_t) {
+    config.inherit_stdout = false;
+    config.stdout = Some(pipe.writer());
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_capture_stderr(config: &mut wasi_config_t) {
     config.inherit_stderr = false;
+    config.stderr = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stderr(config: &mut wasi_config_t) {
     config.inherit_stderr = true;
+    config.stderr = None;
+}
+
+/// Send the `stderr` of the guest to `pipe`, see
+/// [`wasi_config_set_stdout`].
+#[no_mangle]
+pub extern "C" fn wasi_config_set_stderr(config: &mut wasi_config_t, pipe: &wasmer_pipe_t) {
+    config.inherit_stderr = false;
+    config.stderr = Some(pipe.writer());
 }
 
 //#[no_mangle]
@@ -161,6 +323,41 @@ pub extern "C" fn wasi_config_inherit_stderr(config: &mut wasi_config_t) {
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdin(config: &mut wasi_config_t) {
     config.inherit_stdin = true;
+    config.stdin = None;
+}
+
+/// Feed `bytes_len` bytes from `bytes` to the `stdin` of the guest,
+/// which reaches its end once they are all read.
+///
+/// The bytes are copied, `bytes` can be freed after the call.
+///
+/// # Safety
+///
+/// `bytes` must point to at least `bytes_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_set_stdin_bytes(
+    config: &mut wasi_config_t,
+    bytes: *const c_char,
+    bytes_len: usize,
+) {
+    let bytes = slice::from_raw_parts(bytes as *const u8, bytes_len);
+
+    config.inherit_stdin = false;
+    config.stdin = Some(pipe::PipeReader::from_bytes(bytes));
+}
+
+/// Read the `stdin` of the guest from `pipe`, where it can be written
+/// with [`wasmer_pipe_write`](pipe::wasmer_pipe_write) while the guest
+/// is running.
+///
+/// Reads of the guest block until data is available. The guest sees
+/// the end of its `stdin` once the pipe is closed with
+/// [`wasmer_pipe_close`](pipe::wasmer_pipe_close) or deleted, and the
+/// bytes already written have been read.
+#[no_mangle]
+pub extern "C" fn wasi_config_set_stdin_pipe(config: &mut wasi_config_t, pipe: &wasmer_pipe_t) {
+    config.inherit_stdin = false;
+    config.stdin = Some(pipe.reader());
 }
 
 #[allow(non_camel_case_types)]
@@ -174,19 +371,25 @@ pub struct wasi_env_t {
 /// It take ownership over the `wasi_config_t`.
 #[no_mangle]
 pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<wasi_env_t>> {
-    if !config.inherit_stdout {
+    if let Some(stdout) = config.stdout.take() {
+        config.state_builder.stdout(Box::new(stdout));
+    } else if !config.inherit_stdout {
         config
             .state_builder
             .stdout(Box::new(capture_files::OutputCapturer::new()));
     }
 
-    if !config.inherit_stderr {
+    if let Some(stderr) = config.stderr.take() {
+        config.state_builder.stderr(Box::new(stderr));
+    } else if !config.inherit_stderr {
         config
             .state_builder
             .stderr(Box::new(capture_files::OutputCapturer::new()));
     }
 
-    // TODO: impl capturer for stdin
+    if let Some(stdin) = config.stdin.take() {
+        config.state_builder.stdin(Box::new(stdin));
+    }
 
     let wasi_state = c_try!(config.state_builder.build());
 
@@ -199,48 +402,233 @@ pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<was
 #[no_mangle]
 pub extern "C" fn wasi_env_delete(_state: Option<Box<wasi_env_t>>) {}
 
+/// Read the output captured from the `stdout` of the guest, without
+/// waiting.
+///
+/// Up to `buffer_len` bytes are moved out of the capture buffer into
+/// `buffer`. Returns the number of bytes read, which is zero when
+/// nothing is available, including when the guest is in the middle
+/// of a WASI call on another thread. Returns `-1` and sets the last
+/// error if `stdout` isn't captured, see
+/// [`wasi_config_capture_stdout`] and [`wasi_config_set_stdout`].
 #[no_mangle]
 pub unsafe extern "C" fn wasi_env_read_stdout(
-    env: &mut wasi_env_t,
+    env: &wasi_env_t,
     buffer: *mut c_char,
     buffer_len: usize,
 ) -> isize {
     let inner_buffer = slice::from_raw_parts_mut(buffer as *mut _, buffer_len as usize);
-    let mut state = env.inner.state();
+    read_captured(env, CapturedStream::Stdout, inner_buffer)
+}
 
-    let stdout = if let Ok(stdout) = state.fs.stdout_mut() {
-        if let Some(stdout) = stdout.as_mut() {
-            stdout
-        } else {
-            update_last_error("could not find a file handle for `stdout`");
-            return -1;
+/// Read the output captured from the `stderr` of the guest, without
+/// waiting.
+///
+/// This is the `stderr` twin of [`wasi_env_read_stdout`].
+#[no_mangle]
+pub unsafe extern "C" fn wasi_env_read_stderr(
+    env: &wasi_env_t,
+    buffer: *mut c_char,
+    buffer_len: usize,
+) -> isize {
+    let inner_buffer = slice::from_raw_parts_mut(buffer as *mut _, buffer_len as usize);
+    read_captured(env, CapturedStream::Stderr, inner_buffer)
+}
+
+/// Read the output currently captured from the `stdout` of the guest,
+/// without waiting.
+///
+/// Up to `buffer_len` bytes are moved out of the capture buffer into
+/// `buffer`, and `bytes_read` is set to their number. It is set to
+/// zero when nothing is available, including when the guest is in
+/// the middle of a WASI call on another thread. This allows
+/// streaming the output of a guest that is still running.
+///
+/// Returns `false` and sets the last error if `stdout` isn't
+/// captured, see [`wasi_config_capture_stdout`].
+#[no_mangle]
+pub unsafe extern "C" fn wasi_env_read_stdout_available(
+    env: &wasi_env_t,
+    buffer: *mut c_char,
+    buffer_len: usize,
+    bytes_read: &mut usize,
+) -> bool {
+    *bytes_read = 0;
+    match wasi_env_read_stdout(env, buffer, buffer_len) {
+        -1 => false,
+        read => {
+            *bytes_read = read as usize;
+            true
+        }
+    }
+}
+
+/// Write the `bytes_len` bytes from `bytes` to the file at the
+/// absolute `path` of the in-memory filesystem of the guest, see
+/// [`wasi_config_use_memfs`].
+///
+/// The file is created or truncated, and so are the missing
+/// directories leading to it. Returns `false` and sets the last
+/// error if the file can't be written.
+///
+/// # Safety
+///
+/// `bytes` must point to at least `bytes_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_env_memfs_write_file(
+    env: &wasi_env_t,
+    path: *const c_char,
+    bytes: *const c_char,
+    bytes_len: usize,
+) -> bool {
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => Path::new(path),
+        Err(e) => {
+            update_last_error(e);
+            return false;
         }
+    };
+    let bytes = if bytes_len == 0 {
+        &[]
     } else {
-        return -1;
+        slice::from_raw_parts(bytes as *const u8, bytes_len)
     };
-    read_inner(stdout, inner_buffer)
+
+    with_memfs(env, |fs| {
+        if let Some(parent) = path.parent() {
+            let mut ancestors = parent.ancestors().collect::<Vec<_>>();
+            ancestors.reverse();
+            for directory in ancestors {
+                if fs.metadata(directory).is_err() {
+                    fs.create_dir(directory)?;
+                }
+            }
+        }
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.write_all(bytes)?;
+        Ok(())
+    })
+    .is_some()
 }
 
+/// Read the file at the absolute `path` of the in-memory filesystem
+/// of the guest, see [`wasi_config_use_memfs`].
+///
+/// Up to `buffer_len` bytes of the file are copied into `buffer`.
+/// Returns the size of the file, which may be larger than
+/// `buffer_len`: calling it with a `buffer_len` of zero gives the
+/// size of the buffer to allocate. Returns `-1` and sets the last
+/// error if the file can't be read.
+///
+/// # Safety
+///
+/// `buffer` must point to at least `buffer_len` writable bytes.
 #[no_mangle]
-pub unsafe extern "C" fn wasi_env_read_stderr(
-    env: &mut wasi_env_t,
+pub unsafe extern "C" fn wasi_env_memfs_read_file(
+    env: &wasi_env_t,
+    path: *const c_char,
     buffer: *mut c_char,
     buffer_len: usize,
 ) -> isize {
-    let inner_buffer = slice::from_raw_parts_mut(buffer as *mut _, buffer_len as usize);
-    let mut state = env.inner.state();
-    let stderr = if let Ok(stderr) = state.fs.stderr_mut() {
-        if let Some(stderr) = stderr.as_mut() {
-            stderr
-        } else {
-            update_last_error("could not find a file handle for `stderr`");
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => Path::new(path),
+        Err(e) => {
+            update_last_error(e);
             return -1;
         }
+    };
+
+    with_memfs(env, |fs| {
+        let mut file = fs.new_open_options().read(true).open(path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let copied = min(contents.len(), buffer_len);
+        if copied > 0 {
+            slice::from_raw_parts_mut(buffer as *mut u8, copied)
+                .copy_from_slice(&contents[..copied]);
+        }
+        Ok(contents.len() as isize)
+    })
+    .unwrap_or(-1)
+}
+
+/// Runs `f` with the in-memory filesystem of the guest, setting the
+/// last error if there is none or if `f` fails.
+fn with_memfs<T>(
+    env: &wasi_env_t,
+    f: impl FnOnce(&mem_fs::FileSystem) -> Result<T, FsError>,
+) -> Option<T> {
+    let state = match env.inner.state.lock() {
+        Ok(state) => state,
+        Err(_) => {
+            update_last_error("the WASI state is poisoned");
+            return None;
+        }
+    };
+    let fs = match state.fs.fs_backing.downcast_ref::<mem_fs::FileSystem>() {
+        Some(fs) => fs,
+        None => {
+            update_last_error("the filesystem isn't in memory, see `wasi_config_use_memfs`");
+            return None;
+        }
+    };
+
+    match f(fs) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            update_last_error(e);
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum CapturedStream {
+    Stdout,
+    Stderr,
+}
+
+fn read_captured(env: &wasi_env_t, stream: CapturedStream, inner_buffer: &mut [u8]) -> isize {
+    let name = match stream {
+        CapturedStream::Stdout => "stdout",
+        CapturedStream::Stderr => "stderr",
+    };
+
+    // The guest holds the lock for the duration of its WASI calls:
+    // don't wait for it, there is nothing to read yet anyway.
+    let mut state = match env.inner.state.try_lock() {
+        Ok(state) => state,
+        Err(TryLockError::WouldBlock) => return 0,
+        Err(TryLockError::Poisoned(_)) => {
+            update_last_error("the WASI state is poisoned");
+            return -1;
+        }
+    };
+    let wasi_file = match stream {
+        CapturedStream::Stdout => state.fs.stdout_mut(),
+        CapturedStream::Stderr => state.fs.stderr_mut(),
+    };
+    let wasi_file = if let Ok(Some(wasi_file)) = wasi_file {
+        wasi_file
     } else {
-        update_last_error("could not find a file handle for `stderr`");
+        update_last_error(format!("could not find a file handle for `{}`", name));
         return -1;
     };
-    read_inner(stderr, inner_buffer)
+
+    match read_inner(wasi_file, inner_buffer) {
+        -1 => {
+            update_last_error(format!("`{}` is not captured", name));
+            -1
+        }
+        read => read,
+    }
 }
 
 fn read_inner(wasi_file: &mut Box<dyn WasiFile>, inner_buffer: &mut [u8]) -> isize {
@@ -255,6 +643,8 @@ fn read_inner(wasi_file: &mut Box<dyn WasiFile>, inner_buffer: &mut [u8]) -> isi
         }
 
         total_to_read as isize
+    } else if let Some(pipe) = wasi_file.downcast_mut::<pipe::PipeWriter>() {
+        pipe.read_captured(inner_buffer) as isize
     } else {
         -1
     }
@@ -320,6 +710,11 @@ pub unsafe extern "C" fn wasi_get_wasi_version(module: &wasm_module_t) -> wasi_v
 
 /// Non-standard function to get the imports needed for the WASI
 /// implementation ordered as expected by the `wasm_module_t`.
+///
+/// Only the syscalls the module imports are built. If the module
+/// imports something else from the WASI namespace, this function
+/// returns `false` and the error, which suggests the syscall with the
+/// closest name, can be read with `wasmer_last_error_message`.
 #[no_mangle]
 pub unsafe extern "C" fn wasi_get_imports(
     store: Option<&wasm_store_t>,
@@ -345,7 +740,12 @@ fn wasi_get_imports_inner(
     let version = c_try!(get_wasi_version(&module.inner, false)
         .ok_or("could not detect a WASI version on the given module"));
 
-    let import_object = generate_import_object_from_env(store, wasi_env.inner.clone(), version);
+    let import_object = c_try!(generate_import_object_for_module(
+        store,
+        wasi_env.inner.clone(),
+        &module.inner,
+        version
+    ));
 
     imports.set_buffer(c_try!(module
         .inner
@@ -444,6 +844,754 @@ mod tests {
         .success();
     }
 
+    #[test]
+    fn test_wasi_preopen_dir_readonly() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `create_file` asks to create and write `wasmer-readonly-test`
+                // in the first preopened directory (fd 3 is the root).
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"path_open\"\n"
+                    "    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 16) \"wasmer-readonly-test\")\n"
+                    "  (func (export \"create_file\") (result i32)\n"
+                    "    (call $path_open (i32.const 4) (i32.const 0) (i32.const 16) (i32.const 20)\n"
+                    "      (i32.const 1) (i64.const 64) (i64.const 64) (i32.const 0) (i32.const 0))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                assert(wasi_config_preopen_dir_readonly(config, "."));
+
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* create_file = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        create_file = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(create_file);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(create_file, &args, &results) == NULL);
+
+                // `__WASI_EACCES`
+                assert(results_val[0].of.i32 == 2);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_mapdir() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <stdlib.h>
+            #include <unistd.h>
+
+            int main() {
+                // Prepare a host directory with a file in it.
+                char host_dir[] = "/tmp/wasmer-mapdir-XXXXXX";
+                assert(mkdtemp(host_dir));
+                char host_file[64];
+                snprintf(host_file, sizeof(host_file), "%s/hello.txt", host_dir);
+                FILE* file = fopen(host_file, "w");
+                assert(file);
+                fputs("Hello, mapdir!", file);
+                fclose(file);
+
+                wasi_config_t* config = wasi_config_new("test");
+
+                // Invalid mappings are rejected.
+                assert(!wasi_config_mapdir(config, "data", host_dir));
+                assert(wasmer_last_error_length() > 0);
+                assert(!wasi_config_mapdir(config, "/missing", "/tmp/wasmer-mapdir-does-not-exist"));
+                assert(wasmer_last_error_length() > 0);
+
+                assert(wasi_config_mapdir(config, "/data", host_dir));
+                assert(!wasi_config_mapdir(config, "/data", host_dir));
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `read_file` opens `/data/hello.txt` from the root (fd 3),
+                // reads it at offset 64 and returns the number of bytes read,
+                // or the negated errno on failure.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"path_open\"\n"
+                    "    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_read\"\n"
+                    "    (func $fd_read (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 16) \"data/hello.txt\")\n"
+                    "  (data (i32.const 32) \"\\40\\00\\00\\00\\40\\00\\00\\00\")\n"
+                    "  (func (export \"read_file\") (result i32)\n"
+                    "    (local $errno i32)\n"
+                    "    (local.set $errno (call $path_open (i32.const 3) (i32.const 0) (i32.const 16) (i32.const 14)\n"
+                    "      (i32.const 0) (i64.const 2) (i64.const 2) (i32.const 0) (i32.const 0)))\n"
+                    "    (if (local.get $errno) (then (return (i32.sub (i32.const 0) (local.get $errno)))))\n"
+                    "    (local.set $errno (call $fd_read (i32.load (i32.const 0)) (i32.const 32) (i32.const 1) (i32.const 8)))\n"
+                    "    (if (local.get $errno) (then (return (i32.sub (i32.const 0) (local.get $errno)))))\n"
+                    "    (i32.load (i32.const 8))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_memory_t* memory = NULL;
+                wasm_func_t* read_file = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        read_file = wasm_extern_as_func(exports.data[i]);
+                    } else if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_MEMORY) {
+                        memory = wasm_extern_as_memory(exports.data[i]);
+                    }
+                }
+                assert(read_file);
+                assert(memory);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(read_file, &args, &results) == NULL);
+
+                assert(results_val[0].of.i32 == 14);
+                assert(memcmp(wasm_memory_data(memory) + 64, "Hello, mapdir!", 14) == 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                unlink(host_file);
+                rmdir(host_dir);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_read_stdout_available() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `write` writes `hello` to `stdout`.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_write\"\n"
+                    "    (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 0) \"\\10\\00\\00\\00\\05\\00\\00\\00\")\n"
+                    "  (data (i32.const 16) \"hello\")\n"
+                    "  (func (export \"write\") (result i32)\n"
+                    "    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_config_capture_stdout(config);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* write = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        write = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(write);
+
+                char buffer[16];
+                size_t bytes_read = 42;
+
+                // Nothing has been written yet.
+                assert(wasi_env_read_stdout_available(wasi_env, buffer, sizeof(buffer), &bytes_read));
+                assert(bytes_read == 0);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(write, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+
+                // The output is read incrementally.
+                assert(wasi_env_read_stdout_available(wasi_env, buffer, 3, &bytes_read));
+                assert(bytes_read == 3);
+                assert(memcmp(buffer, "hel", 3) == 0);
+
+                assert(wasi_env_read_stdout_available(wasi_env, buffer, sizeof(buffer), &bytes_read));
+                assert(bytes_read == 2);
+                assert(memcmp(buffer, "lo", 2) == 0);
+
+                assert(wasi_env_read_stdout_available(wasi_env, buffer, sizeof(buffer), &bytes_read));
+                assert(bytes_read == 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_capture_stdout_full() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `write` writes 1 MiB + 16 bytes to `stdout` at once,
+                // and stores the number of bytes written at 8.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_write\"\n"
+                    "    (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 17)\n"
+                    "  (data (i32.const 0) \"\\10\\00\\00\\00\\10\\00\\10\\00\")\n"
+                    "  (func (export \"write\") (result i32)\n"
+                    "    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_config_capture_stdout(config);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* write = NULL;
+                wasm_memory_t* memory = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        write = wasm_extern_as_func(exports.data[i]);
+                    } else if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_MEMORY) {
+                        memory = wasm_extern_as_memory(exports.data[i]);
+                    }
+                }
+                assert(write);
+                assert(memory);
+                uint32_t* written = (uint32_t*) (wasm_memory_data(memory) + 8);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+
+                // The write is cut short to the 1 MiB that fit.
+                assert(wasm_func_call(write, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+                assert(*written == 1 << 20);
+
+                // Nothing fits anymore: `__WASI_EAGAIN`.
+                assert(wasm_func_call(write, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 6);
+
+                // Reading makes room again.
+                char buffer[16];
+                size_t bytes_read = 0;
+                assert(wasi_env_read_stdout_available(wasi_env, buffer, sizeof(buffer), &bytes_read));
+                assert(bytes_read == 16);
+                assert(wasm_func_call(write, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+                assert(*written == 16);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_stdout_pipe() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `write` writes `hello` to `stdout`.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_write\"\n"
+                    "    (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 0) \"\\10\\00\\00\\00\\05\\00\\00\\00\")\n"
+                    "  (data (i32.const 16) \"hello\")\n"
+                    "  (func (export \"write\") (result i32)\n"
+                    "    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasmer_pipe_t* pipe = wasmer_pipe_new();
+                assert(pipe);
+
+                // Nothing attached yet.
+                assert(!wasmer_pipe_eof(pipe));
+
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_config_set_stdout(config, pipe);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* write = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        write = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(write);
+
+                char buffer[16];
+                assert(wasmer_pipe_read(pipe, buffer, sizeof(buffer)) == 0);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(write, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+
+                // The output is read incrementally.
+                assert(wasmer_pipe_read(pipe, buffer, 3) == 3);
+                assert(memcmp(buffer, "hel", 3) == 0);
+                assert(wasmer_pipe_read(pipe, buffer, sizeof(buffer)) == 2);
+                assert(memcmp(buffer, "lo", 2) == 0);
+                assert(wasmer_pipe_read(pipe, buffer, sizeof(buffer)) == 0);
+
+                // The guest can still write.
+                assert(!wasmer_pipe_eof(pipe));
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+
+                // The guest is gone.
+                assert(wasmer_pipe_eof(pipe));
+
+                wasmer_pipe_delete(pipe);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_stdin_bytes() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include "tests/wasi-wc.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, WC_WAT);
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasmer_pipe_t* stdout_pipe = wasmer_pipe_new();
+
+                wasi_config_t* config = wasi_config_new("wc");
+                const char* input = "hello, world!\n";
+                wasi_config_set_stdin_bytes(config, input, strlen(input));
+                wasi_config_set_stdout(config, stdout_pipe);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* wc = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        wc = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(wc);
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(wc, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+
+                char buffer[16];
+                size_t read = wasmer_pipe_read(stdout_pipe, buffer, sizeof(buffer));
+                assert(read == 2);
+                assert(memcmp(buffer, "14", 2) == 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasmer_pipe_delete(stdout_pipe);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_stdin_pipe() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include "tests/wasi-wc.h"
+            #include <pthread.h>
+            #include <unistd.h>
+
+            // Write 1000 bytes to the pipe in chunks, while the guest
+            // is reading, then close it.
+            void* feed_stdin(void* pipe) {
+                char chunk[100];
+                memset(chunk, 'x', sizeof(chunk));
+
+                for (int i = 0; i < 10; ++i) {
+                    usleep(1000);
+                    assert(wasmer_pipe_write(pipe, chunk, sizeof(chunk)) == sizeof(chunk));
+                }
+
+                wasmer_pipe_close(pipe);
+                assert(wasmer_pipe_write(pipe, chunk, sizeof(chunk)) == 0);
+
+                return NULL;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, WC_WAT);
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasmer_pipe_t* stdin_pipe = wasmer_pipe_new();
+                wasmer_pipe_t* stdout_pipe = wasmer_pipe_new();
+
+                wasi_config_t* config = wasi_config_new("wc");
+                wasi_config_set_stdin_pipe(config, stdin_pipe);
+                wasi_config_set_stdout(config, stdout_pipe);
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* wc = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        wc = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(wc);
+
+                pthread_t feeder;
+                assert(pthread_create(&feeder, NULL, feed_stdin, stdin_pipe) == 0);
+
+                // The guest blocks on `stdin` until the pipe is closed.
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(wc, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 0);
+
+                assert(pthread_join(feeder, NULL) == 0);
+
+                char buffer[16];
+                size_t read = wasmer_pipe_read(stdout_pipe, buffer, sizeof(buffer));
+                assert(read == 4);
+                assert(memcmp(buffer, "1000", 4) == 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasmer_pipe_delete(stdout_pipe);
+                wasmer_pipe_delete(stdin_pipe);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_deny_syscall() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `random` fills 8 bytes with random data.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"random_get\"\n"
+                    "    (func $random_get (param i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (func (export \"random\") (result i32)\n"
+                    "    (call $random_get (i32.const 0) (i32.const 8))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                assert(!wasi_config_deny_syscall(config, "not_a_syscall"));
+                assert(wasmer_last_error_length() > 0);
+                assert(wasi_config_deny_syscall(config, "random_get"));
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                wasm_func_t* random = NULL;
+                for (size_t i = 0; i < exports.size; ++i) {
+                    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+                        random = wasm_extern_as_func(exports.data[i]);
+                    }
+                }
+                assert(random);
+
+                // `random_get` fails with `__WASI_ENOTCAPABLE`.
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                assert(wasm_func_call(random, &args, &results) == NULL);
+                assert(results_val[0].of.i32 == 76);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_get_imports_misspelled_syscall() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <string.h>
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(&wat, "(module (import \"wasi_snapshot_preview1\" \"fd_wirte\" (func (param i32 i32 i32 i32) (result i32))))");
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(!wasi_get_imports(store, module, wasi_env, &imports));
+
+                int error_length = wasmer_last_error_length();
+                char* error = malloc(error_length);
+                wasmer_last_error_message(error, error_length);
+                assert(strstr(error, "did you mean `fd_write`?"));
+
+                free(error);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
     #[test]
     fn test_wasi_get_wasi_version_invalid() {
         (assert_c! {
@@ -474,4 +1622,132 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_wasi_memfs() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <string.h>
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // `_start` reads `/input.txt`, converts it to upper case,
+                // and writes it to `/output.txt` (fd 3 is the root).
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"path_open\"\n"
+                    "    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_read\"\n"
+                    "    (func $fd_read (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_write\"\n"
+                    "    (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 16) \"input.txt\")\n"
+                    "  (data (i32.const 32) \"output.txt\")\n"
+                    "  (func (export \"_start\") (local $len i32) (local $i i32)\n"
+                    "    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 16) (i32.const 9)\n"
+                    "          (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 48))\n"
+                    "      (then unreachable))\n"
+                    "    (i32.store (i32.const 56) (i32.const 1024))\n"
+                    "    (i32.store (i32.const 60) (i32.const 1024))\n"
+                    "    (if (call $fd_read (i32.load (i32.const 48)) (i32.const 56) (i32.const 1) (i32.const 64))\n"
+                    "      (then unreachable))\n"
+                    "    (local.set $len (i32.load (i32.const 64)))\n"
+                    "    (block $done\n"
+                    "      (loop $next\n"
+                    "        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))\n"
+                    "        (if (i32.le_u (i32.sub (i32.load8_u offset=1024 (local.get $i)) (i32.const 97)) (i32.const 25))\n"
+                    "          (then (i32.store8 offset=1024 (local.get $i)\n"
+                    "            (i32.sub (i32.load8_u offset=1024 (local.get $i)) (i32.const 32)))))\n"
+                    "        (local.set $i (i32.add (local.get $i) (i32.const 1)))\n"
+                    "        (br $next)))\n"
+                    "    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 32) (i32.const 10)\n"
+                    "          (i32.const 9) (i64.const 64) (i64.const 0) (i32.const 0) (i32.const 48))\n"
+                    "      (then unreachable))\n"
+                    "    (i32.store (i32.const 60) (local.get $len))\n"
+                    "    (if (call $fd_write (i32.load (i32.const 48)) (i32.const 56) (i32.const 1) (i32.const 64))\n"
+                    "      (then unreachable))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_config_use_memfs(config);
+
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                // The directories leading to a file are created.
+                assert(wasi_env_memfs_write_file(wasi_env, "/input.txt", "hello, memfs!", 13));
+                assert(wasi_env_memfs_write_file(wasi_env, "/a/b/c.txt", "nested", 6));
+
+                char buffer[32] = { 0 };
+                assert(wasi_env_memfs_read_file(wasi_env, "/a/b/c.txt", buffer, sizeof(buffer)) == 6);
+                assert(memcmp(buffer, "nested", 6) == 0);
+                assert(wasi_env_memfs_read_file(wasi_env, "/missing.txt", buffer, sizeof(buffer)) == -1);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_func_t* start = wasi_get_start_function(instance);
+                assert(start);
+
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                assert(wasm_func_call(start, &args, &results) == NULL);
+
+                // The size of the file is returned even if the buffer is
+                // too small.
+                assert(wasi_env_memfs_read_file(wasi_env, "/output.txt", buffer, 5) == 13);
+                assert(memcmp(buffer, "HELLO", 5) == 0);
+                assert(wasi_env_memfs_read_file(wasi_env, "/output.txt", buffer, sizeof(buffer)) == 13);
+                assert(memcmp(buffer, "HELLO, MEMFS!", 13) == 0);
+
+                wasm_func_delete(start);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasi_memfs_requires_memfs() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasi_config_t* config = wasi_config_new("test");
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                // The host filesystem is never written.
+                assert(!wasi_env_memfs_write_file(wasi_env, "/tmp/wasmer-memfs-test", "", 0));
+                assert(wasmer_last_error_length() > 0);
+
+                wasi_env_delete(wasi_env);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
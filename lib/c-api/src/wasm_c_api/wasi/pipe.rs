@@ -0,0 +1,340 @@
+//! In-memory pipes to stream data between the host and the
+//! `stdin`/`stdout`/`stderr` of a WASI program.
+
+use super::capture_files::CAPTURE_BUFFER_CAPACITY;
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, Write};
+use std::os::raw::c_char;
+use std::slice;
+use std::sync::{Arc, Condvar, Mutex};
+use wasmer_wasi::{WasiFile, WasiFsError};
+
+#[derive(Debug, Default)]
+struct PipeBuffer {
+    bytes: VecDeque<u8>,
+    /// The number of live writing ends.
+    writers: usize,
+    /// Whether a writing end has ever been attached.
+    attached: bool,
+    /// Whether the host closed the pipe, see [`wasmer_pipe_close`].
+    closed: bool,
+}
+
+impl PipeBuffer {
+    fn read(&mut self, buffer: &mut [u8]) -> usize {
+        let total_to_read = min(buffer.len(), self.bytes.len());
+
+        for (address, value) in buffer.iter_mut().zip(self.bytes.drain(..total_to_read)) {
+            *address = value;
+        }
+
+        total_to_read
+    }
+
+    fn is_eof(&self) -> bool {
+        self.attached && self.writers == 0 && self.bytes.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Pipe {
+    buffer: Mutex<PipeBuffer>,
+    /// Signaled when bytes are written by the host, or when the host
+    /// closes the pipe.
+    readable: Condvar,
+}
+
+/// An in-memory pipe, either to capture the `stdout` or `stderr` of a
+/// WASI program with [`wasi_config_set_stdout`] or
+/// [`wasi_config_set_stderr`], or to feed its `stdin` with
+/// [`wasi_config_set_stdin_pipe`].
+///
+/// The pipe can be read with [`wasmer_pipe_read`], or written with
+/// [`wasmer_pipe_write`], from any thread, including while the guest
+/// is still running.
+///
+/// [`wasi_config_set_stdout`]: super::wasi_config_set_stdout
+/// [`wasi_config_set_stderr`]: super::wasi_config_set_stderr
+/// [`wasi_config_set_stdin_pipe`]: super::wasi_config_set_stdin_pipe
+#[allow(non_camel_case_types)]
+pub struct wasmer_pipe_t {
+    inner: Arc<Pipe>,
+}
+
+impl wasmer_pipe_t {
+    pub(super) fn writer(&self) -> PipeWriter {
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        buffer.writers += 1;
+        buffer.attached = true;
+
+        PipeWriter {
+            inner: self.inner.clone(),
+        }
+    }
+
+    pub(super) fn reader(&self) -> PipeReader {
+        PipeReader {
+            inner: self.inner.clone(),
+        }
+    }
+
+    fn close(&self) {
+        self.inner.buffer.lock().unwrap().closed = true;
+        self.inner.readable.notify_all();
+    }
+}
+
+impl Drop for wasmer_pipe_t {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Create a new, empty [`wasmer_pipe_t`].
+#[no_mangle]
+pub extern "C" fn wasmer_pipe_new() -> Box<wasmer_pipe_t> {
+    Box::new(wasmer_pipe_t {
+        inner: Arc::new(Pipe::default()),
+    })
+}
+
+/// Delete a [`wasmer_pipe_t`].
+///
+/// The WASI environments it is attached to keep writing to it, but
+/// the output can't be read anymore. Deleting a pipe also closes it,
+/// see [`wasmer_pipe_close`].
+#[no_mangle]
+pub extern "C" fn wasmer_pipe_delete(_pipe: Option<Box<wasmer_pipe_t>>) {}
+
+/// Read the bytes currently available in the pipe, without waiting.
+///
+/// Up to `buffer_len` bytes are moved out of the pipe into `buffer`.
+/// Returns the number of bytes read, which is zero when nothing is
+/// available (see [`wasmer_pipe_eof`] to tell whether more output can
+/// come).
+///
+/// The pipe buffers at most `1 MiB` of output: writes of the guest
+/// are cut short to what still fits, and fail with `EAGAIN` once it
+/// is full, until the host reads from it.
+///
+/// # Safety
+///
+/// `buffer` must point to at least `buffer_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_pipe_read(
+    pipe: &wasmer_pipe_t,
+    buffer: *mut c_char,
+    buffer_len: usize,
+) -> usize {
+    let inner_buffer = slice::from_raw_parts_mut(buffer as *mut _, buffer_len);
+
+    pipe.inner.buffer.lock().unwrap().read(inner_buffer)
+}
+
+/// Write `bytes_len` bytes from `bytes` to the pipe, to be read by
+/// the `stdin` of a WASI program.
+///
+/// Returns the number of bytes written, which is zero once the pipe
+/// is closed.
+///
+/// # Safety
+///
+/// `bytes` must point to at least `bytes_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_pipe_write(
+    pipe: &wasmer_pipe_t,
+    bytes: *const c_char,
+    bytes_len: usize,
+) -> usize {
+    let bytes = slice::from_raw_parts(bytes as *const u8, bytes_len);
+
+    let mut buffer = pipe.inner.buffer.lock().unwrap();
+    if buffer.closed {
+        return 0;
+    }
+    buffer.bytes.extend(bytes);
+    pipe.inner.readable.notify_all();
+
+    bytes_len
+}
+
+/// Close the pipe: the `stdin` of a WASI program reading from it
+/// reaches its end once the bytes already written have been read.
+#[no_mangle]
+pub extern "C" fn wasmer_pipe_close(pipe: &wasmer_pipe_t) {
+    pipe.close();
+}
+
+/// Check whether the pipe reached its end: it has been attached to a
+/// WASI environment, the environment is gone, and all its output has
+/// been read.
+///
+/// The environment is gone once the `wasi_env_t` and the instances
+/// using it have been deleted.
+#[no_mangle]
+pub extern "C" fn wasmer_pipe_eof(pipe: &wasmer_pipe_t) -> bool {
+    pipe.inner.buffer.lock().unwrap().is_eof()
+}
+
+/// The writing end of a [`wasmer_pipe_t`], used as the `stdout` or
+/// `stderr` of a WASI program.
+#[derive(Debug)]
+pub(super) struct PipeWriter {
+    inner: Arc<Pipe>,
+}
+
+impl PipeWriter {
+    /// Read from the pipe, for `wasi_env_read_stdout` and friends.
+    pub(super) fn read_captured(&self, buffer: &mut [u8]) -> usize {
+        self.inner.buffer.lock().unwrap().read(buffer)
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.inner.buffer.lock().unwrap().writers -= 1;
+    }
+}
+
+impl WasiFile for PipeWriter {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(CAPTURE_BUFFER_CAPACITY - self.inner.buffer.lock().unwrap().bytes.len())
+    }
+}
+
+impl Read for PipeWriter {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not read from the writing end of a pipe",
+        ))
+    }
+}
+
+impl Seek for PipeWriter {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek in a pipe",
+        ))
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        let free = CAPTURE_BUFFER_CAPACITY - buffer.bytes.len();
+        if free == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "the pipe is full",
+            ));
+        }
+        let written = buf.len().min(free);
+        buffer.bytes.extend(&buf[..written]);
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The reading end of a [`wasmer_pipe_t`], used as the `stdin` of a
+/// WASI program.
+///
+/// Reads block until the host writes to the pipe or closes it.
+#[derive(Debug)]
+pub(super) struct PipeReader {
+    inner: Arc<Pipe>,
+}
+
+impl PipeReader {
+    /// A reader of `bytes`, reaching its end once they are all read.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Self {
+        let pipe = Pipe::default();
+        {
+            let mut buffer = pipe.buffer.lock().unwrap();
+            buffer.bytes.extend(bytes);
+            buffer.closed = true;
+        }
+
+        Self {
+            inner: Arc::new(pipe),
+        }
+    }
+}
+
+impl WasiFile for PipeReader {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(self.inner.buffer.lock().unwrap().bytes.len())
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        while buffer.bytes.is_empty() && !buffer.closed && !buf.is_empty() {
+            buffer = self.inner.readable.wait(buffer).unwrap();
+        }
+
+        Ok(buffer.read(buf))
+    }
+}
+
+impl Seek for PipeReader {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek in a pipe",
+        ))
+    }
+}
+
+impl Write for PipeReader {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not write to the reading end of a pipe",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
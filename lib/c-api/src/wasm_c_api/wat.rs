@@ -1,9 +1,20 @@
 use super::types::wasm_byte_vec_t;
+use libc::c_int;
+use std::cell::Cell;
+
+thread_local! {
+    /// The line and column of the error of the last failed call to
+    /// `wat2wasm` on this thread.
+    static LAST_ERROR_LOCATION: Cell<Option<(usize, usize)>> = Cell::new(None);
+}
 
 /// Parses in-memory bytes as either the WAT format, or a binary Wasm
 /// module. This is wasmer-specific.
 ///
-/// In case of failure, `wat2wasm` sets the `out->data = NULL` and `out->size = 0`.
+/// In case of failure, `wat2wasm` sets the `out->data = NULL` and
+/// `out->size = 0`. The error message, which contains the offending
+/// line, can be read with `wasmer_last_error_message`, and its
+/// location with [`wat2wasm_error_location`].
 ///
 /// # Example
 ///
@@ -12,8 +23,12 @@ use super::types::wasm_byte_vec_t;
 #[no_mangle]
 pub unsafe extern "C" fn wat2wasm(wat: &wasm_byte_vec_t, out: &mut wasm_byte_vec_t) {
     match wasmer_api::wat2wasm(wat.as_slice()) {
-        Ok(val) => out.set_buffer(val.into_owned()),
+        Ok(val) => {
+            LAST_ERROR_LOCATION.with(|location| location.set(None));
+            out.set_buffer(val.into_owned());
+        }
         Err(err) => {
+            LAST_ERROR_LOCATION.with(|location| location.set(Some((err.line, err.column))));
             crate::error::update_last_error(err);
             out.data = std::ptr::null_mut();
             out.size = 0;
@@ -22,6 +37,119 @@ pub unsafe extern "C" fn wat2wasm(wat: &wasm_byte_vec_t, out: &mut wasm_byte_vec
     };
 }
 
+/// Prints a binary Wasm module in the WAT format. This is
+/// wasmer-specific.
+///
+/// The functions, locals and other items named in the name section
+/// of the module are printed with their names. The text isn't
+/// null-terminated, `out->size` is its length in bytes.
+///
+/// In case of failure, e.g. if the binary is malformed, `wasm2wat`
+/// sets `out->data = NULL` and `out->size = 0`, and the error message
+/// can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// # #include <stdlib.h>
+/// # #include <string.h>
+/// #
+/// int main() {
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func $answer (result i32) (i32.const 42)))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Print the module back.
+///     wasm_byte_vec_t printed;
+///     wasm2wat(&wasm, &printed);
+///     assert(printed.data);
+///
+///     // It works, and the function keeps its name!
+///     char* text = malloc(printed.size + 1);
+///     memcpy(text, printed.data, printed.size);
+///     text[printed.size] = 0;
+///     assert(strstr(text, "(func $answer"));
+///     assert(strstr(text, "i32.const 42"));
+///
+///     free(text);
+///     wasm_byte_vec_delete(&printed);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[cfg(feature = "wat")]
+#[no_mangle]
+pub unsafe extern "C" fn wasm2wat(wasm: &wasm_byte_vec_t, out: &mut wasm_byte_vec_t) {
+    match wasmprinter::print_bytes(wasm.as_slice()) {
+        Ok(wat) => out.set_buffer(wat.into_bytes()),
+        Err(err) => {
+            crate::error::update_last_error(err);
+            out.data = std::ptr::null_mut();
+            out.size = 0;
+        }
+    }
+}
+
+/// Gets the location of the error of the last call to [`wat2wasm`] on
+/// the current thread, if it failed. This is wasmer-specific.
+///
+/// The line and the column start at 1, and the column counts
+/// characters, not bytes. Returns false, and leaves `line` and
+/// `column` untouched, if the last call succeeded or if there was
+/// none.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module\n  (func (; é ;) (i32.konst 1)))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///     assert(!wasm.data);
+///
+///     int line = 0;
+///     int column = 0;
+///     assert(wat2wasm_error_location(&line, &column));
+///     assert(line == 2);
+///     assert(column == 18);
+///
+///     wasm_byte_vec_delete(&wat);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[cfg(feature = "wat")]
+#[no_mangle]
+pub extern "C" fn wat2wasm_error_location(line: &mut c_int, column: &mut c_int) -> bool {
+    match LAST_ERROR_LOCATION.with(Cell::get) {
+        Some((error_line, error_column)) => {
+            *line = error_line as c_int;
+            *column = error_column as c_int;
+            true
+        }
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use inline_c::assert_c;
@@ -39,6 +167,10 @@ mod tests {
 
                 assert(wasm.data);
                 assert(wasm.size == 8);
+
+                int line = 0;
+                int column = 0;
+                assert(!wat2wasm_error_location(&line, &column));
                 assert(
                     wasm.data[0] == 0 &&
                         wasm.data[1] == 'a' &&
@@ -73,6 +205,12 @@ mod tests {
                 assert(!wasm.data);
                 assert(wasmer_last_error_length() > 0);
 
+                int line = 0;
+                int column = 0;
+                assert(wat2wasm_error_location(&line, &column));
+                assert(line == 1);
+                assert(column == 8);
+
                 wasm_byte_vec_delete(&wat);
 
                 return 0;
@@ -80,4 +218,125 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_wasm2wat_round_trip() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <string.h>
+
+            int main() {
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module $round_trip\n"
+                    "  (import \"env\" \"log\" (func $log (param i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (global $counter (mut i32) (i32.const 0))\n"
+                    "  (data (i32.const 16) \"hello\")\n"
+                    "  (func $add (export \"add\") (param $a i32) (param $b i32) (result i32)\n"
+                    "    (local $sum i32)\n"
+                    "    (local.set $sum (i32.add (local.get $a) (local.get $b)))\n"
+                    "    (call $log (local.get $sum))\n"
+                    "    (global.set $counter (i32.add (global.get $counter) (i32.const 1)))\n"
+                    "    (local.get $sum)))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+                assert(wasm.data);
+
+                wasm_byte_vec_t printed;
+                wasm2wat(&wasm, &printed);
+                assert(printed.data);
+
+                wasm_byte_vec_t second_wasm;
+                wat2wasm(&printed, &second_wasm);
+                assert(second_wasm.data);
+
+                // The names are printed, and the second binary is
+                // byte-identical to the first one.
+                assert(second_wasm.size == wasm.size);
+                assert(memcmp(second_wasm.data, wasm.data, wasm.size) == 0);
+
+                wasm_byte_vec_delete(&second_wasm);
+                wasm_byte_vec_delete(&printed);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasm2wat_large_module() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <stdio.h>
+            #include <stdlib.h>
+            #include <string.h>
+
+            int main() {
+                // A module with 20,000 functions.
+                size_t functions = 20000;
+                size_t capacity = 64 + functions * 64;
+                char* source = malloc(capacity);
+                size_t length = sprintf(source, "(module");
+                for (size_t i = 0; i < functions; ++i) {
+                    length += sprintf(source + length, " (func $f%zu (result i32) (i32.const %zu))", i, i);
+                }
+                length += sprintf(source + length, ")");
+
+                wasm_byte_vec_t wat;
+                wasm_byte_vec_new(&wat, length, source);
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+                assert(wasm.data);
+
+                wasm_byte_vec_t printed;
+                wasm2wat(&wasm, &printed);
+                assert(printed.data);
+
+                wasm_byte_vec_t second_wasm;
+                wat2wasm(&printed, &second_wasm);
+                assert(second_wasm.size == wasm.size);
+                assert(memcmp(second_wasm.data, wasm.data, wasm.size) == 0);
+
+                wasm_byte_vec_delete(&second_wasm);
+                wasm_byte_vec_delete(&printed);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                free(source);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_wasm2wat_failed() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                // A module whose section is truncated.
+                wasm_byte_vec_t wasm;
+                wasm_byte_vec_new(&wasm, 10, "\0asm\x01\0\0\0\x01\x05");
+
+                wasm_byte_vec_t wat;
+                wasm2wat(&wasm, &wat);
+
+                assert(!wat.data);
+                assert(wat.size == 0);
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_byte_vec_delete(&wasm);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
@@ -0,0 +1,33 @@
+// The `wc` fixture of the WASI tests of the C API.
+//
+// `wc` counts the bytes of `stdin` and prints the count to `stdout`.
+
+#ifndef WASMER_TESTS_WASI_WC_H
+#define WASMER_TESTS_WASI_WC_H
+
+static const char* WC_WAT =
+    "(module\n"
+    "  (import \"wasi_snapshot_preview1\" \"fd_read\"\n"
+    "    (func $fd_read (param i32 i32 i32 i32) (result i32)))\n"
+    "  (import \"wasi_snapshot_preview1\" \"fd_write\"\n"
+    "    (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+    "  (memory (export \"memory\") 1)\n"
+    "  (data (i32.const 0) \"\\40\\00\\00\\00\\40\\00\\00\\00\")\n"
+    "  (func (export \"wc\") (result i32)\n"
+    "    (local $count i32) (local $n i32) (local $pos i32)\n"
+    "    (block $done\n"
+    "      (loop $read\n"
+    "        (br_if $done (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))\n"
+    "        (br_if $done (i32.eqz (local.tee $n (i32.load (i32.const 8)))))\n"
+    "        (local.set $count (i32.add (local.get $count) (local.get $n)))\n"
+    "        (br $read)))\n"
+    "    (local.set $pos (i32.const 160))\n"
+    "    (loop $digit\n"
+    "      (local.set $pos (i32.sub (local.get $pos) (i32.const 1)))\n"
+    "      (i32.store8 (local.get $pos) (i32.add (i32.const 48) (i32.rem_u (local.get $count) (i32.const 10))))\n"
+    "      (br_if $digit (local.tee $count (i32.div_u (local.get $count) (i32.const 10)))))\n"
+    "    (i32.store (i32.const 16) (local.get $pos))\n"
+    "    (i32.store (i32.const 20) (i32.sub (i32.const 160) (local.get $pos)))\n"
+    "    (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 24))))";
+
+#endif /* WASMER_TESTS_WASI_WC_H */
@@ -1,9 +1,10 @@
 #![cfg_attr(not(feature = "filesystem"), allow(unused))]
 use crate::cache::Cache;
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use std::fs::{self, create_dir_all, File};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use wasmer::{DeserializeError, Module, SerializeError, Store};
 
 /// Representation of a directory that contains compiled wasm artifacts.
@@ -31,9 +32,14 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 ///     Ok(())
 /// }
 /// ```
+///
+/// Modules can also be stored and loaded by the content of their
+/// WebAssembly binary, see [`FileSystemCache::store_module`] and
+/// [`FileSystemCache::load_module`].
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    ttl: Option<Duration>,
 }
 
 #[cfg(feature = "filesystem")]
@@ -45,7 +51,11 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self {
+                        path,
+                        ext: None,
+                        ttl: None,
+                    })
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -72,7 +82,11 @@ impl FileSystemCache {
                     format!("failed to create cache directory: {}", path.display()),
                 ))
             } else {
-                Ok(Self { path, ext: None })
+                Ok(Self {
+                    path,
+                    ext: None,
+                    ttl: None,
+                })
             }
         }
     }
@@ -84,6 +98,117 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
+
+    /// Set the time to live of the cached artifacts: the artifacts
+    /// stored longer than `ttl` ago are evicted when loaded with
+    /// [`FileSystemCache::load_module`], or by
+    /// [`FileSystemCache::evict_expired`]. By default, artifacts never
+    /// expire.
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    /// Store a [`Module`] into the cache, keyed by the content of the
+    /// WebAssembly binary it was compiled from, see
+    /// [`FileSystemCache::load_module`].
+    ///
+    /// The key also covers the version of Wasmer, the target and the
+    /// features of the engine of the module, and its
+    /// [`Engine::deterministic_id`]: the kind of engine, the compiler and
+    /// its configuration, middlewares included. Upgrading Wasmer or
+    /// changing any of them doesn't load stale artifacts.
+    ///
+    /// [`Engine::deterministic_id`]: wasmer::Engine::deterministic_id
+    pub fn store_module(
+        &mut self,
+        module: &Module,
+        wasm_bytes: &[u8],
+    ) -> Result<(), SerializeError> {
+        let key = content_key(module.store(), wasm_bytes);
+        self.store(key, module)
+    }
+
+    /// Load the [`Module`] compiled from the WebAssembly binary
+    /// `wasm_bytes`, stored with [`FileSystemCache::store_module`].
+    ///
+    /// Returns `None` on a cache miss: when no artifact was stored for
+    /// this binary with this version of Wasmer and an engine like the
+    /// one of `store`, see [`FileSystemCache::store_module`], when the
+    /// artifact expired and was evicted, see
+    /// [`FileSystemCache::set_ttl`], or when it can't be deserialized,
+    /// e.g. because it was truncated.
+    ///
+    /// # Safety
+    /// This function is unsafe as the cache store could be tampered with.
+    pub unsafe fn load_module(&self, store: &Store, wasm_bytes: &[u8]) -> Option<Module> {
+        let key = content_key(store, wasm_bytes);
+        let path = self.path.join(self.filename(key));
+        if !path.is_file() || self.evict_if_expired(&path).unwrap_or(false) {
+            return None;
+        }
+        Module::deserialize_from_file(&store, path).ok()
+    }
+
+    /// Remove the artifacts stored longer than the time to live ago,
+    /// see [`FileSystemCache::set_ttl`], returning how many were
+    /// removed.
+    pub fn evict_expired(&self) -> io::Result<usize> {
+        let mut evicted = 0;
+        if self.ttl.is_some() {
+            for entry in fs::read_dir(&self.path)? {
+                let path = entry?.path();
+                if path.is_file() && self.evict_if_expired(&path)? {
+                    evicted += 1;
+                }
+            }
+        }
+        Ok(evicted)
+    }
+
+    /// Removes the artifact at `path` if it expired, returning whether
+    /// it was removed.
+    fn evict_if_expired(&self, path: &Path) -> io::Result<bool> {
+        let ttl = match self.ttl {
+            Some(ttl) => ttl,
+            None => return Ok(false),
+        };
+        let modified = path.metadata()?.modified()?;
+        // An artifact modified in the future isn't expired.
+        let expired = modified.elapsed().map_or(false, |age| age >= ttl);
+        if expired {
+            fs::remove_file(path)?;
+        }
+        Ok(expired)
+    }
+
+    fn filename(&self, key: Hash) -> String {
+        if let Some(ref ext) = self.ext {
+            format!("{}.{}", key.to_string(), ext)
+        } else {
+            key.to_string()
+        }
+    }
+}
+
+/// The key of the artifact compiled from `wasm_bytes` by the engine
+/// of `store`, covering the version of Wasmer, the target, the
+/// features and the deterministic identifier of the engine.
+fn content_key(store: &Store, wasm_bytes: &[u8]) -> Hash {
+    let engine = store.engine();
+    let target = engine.target();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(b"\0");
+    hasher.update(target.triple().to_string().as_bytes());
+    hasher.update(b"\0");
+    hasher.update(format!("{:?}", target.cpu_features()).as_bytes());
+    hasher.update(b"\0");
+    hasher.update(format!("{:?}", engine.features()).as_bytes());
+    hasher.update(b"\0");
+    hasher.update(engine.deterministic_id().as_bytes());
+    hasher.update(b"\0");
+    hasher.update(wasm_bytes);
+    Hash::new(hasher.finalize().into())
 }
 
 #[cfg(feature = "filesystem")]
@@ -92,22 +217,12 @@ impl Cache for FileSystemCache {
     type SerializeError = SerializeError;
 
     unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
+        let path = self.path.join(self.filename(key));
         Module::deserialize_from_file(&store, path)
     }
 
     fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
+        let path = self.path.join(self.filename(key));
         let mut file = File::create(path)?;
 
         let buffer = module.serialize()?;
@@ -0,0 +1,108 @@
+#![cfg(feature = "filesystem")]
+
+use std::time::Duration;
+use tempfile::TempDir;
+use wasmer::{ImportObject, Instance, Module, Store};
+use wasmer_cache::FileSystemCache;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_universal::Universal;
+
+/// `(module (func (export "answer") (result i32) i32.const 42))`
+const ANSWER: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic and version
+    0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // type section
+    0x03, 0x02, 0x01, 0x00, // function section
+    0x07, 0x0a, 0x01, 0x06, b'a', b'n', b's', b'w', b'e', b'r', 0x00, 0x00, // export section
+    0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b, // code section
+];
+
+fn store() -> Store {
+    Store::new(&Universal::new(Singlepass::default()).engine())
+}
+
+fn answer(module: &Module) -> i32 {
+    let instance = Instance::new(module, &ImportObject::new()).unwrap();
+    let answer = instance
+        .exports
+        .get_native_function::<(), i32>("answer")
+        .unwrap();
+    answer.call().unwrap()
+}
+
+#[test]
+fn modules_are_loaded_by_the_content_of_their_binary() {
+    let dir = TempDir::new().unwrap();
+    let mut cache = FileSystemCache::new(dir.path()).unwrap();
+    let store = store();
+
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_none());
+    let module = Module::new(&store, ANSWER).unwrap();
+    cache.store_module(&module, ANSWER).unwrap();
+
+    let cached = unsafe { cache.load_module(&store, ANSWER) }
+        .expect("the second compilation hits the cache");
+    assert_eq!(answer(&cached), 42);
+
+    // A single byte of difference, `i32.const 43`, misses the cache.
+    let mut changed = ANSWER.to_vec();
+    let last = changed.len() - 2;
+    changed[last] = 0x2b;
+    assert!(unsafe { cache.load_module(&store, &changed) }.is_none());
+}
+
+#[test]
+fn modules_are_keyed_by_the_compiler_configuration() {
+    let dir = TempDir::new().unwrap();
+    let mut cache = FileSystemCache::new(dir.path()).unwrap();
+    let module = Module::new(&store(), ANSWER).unwrap();
+    cache.store_module(&module, ANSWER).unwrap();
+
+    let mut compiler = Singlepass::default();
+    compiler.canonicalize_nans(false);
+    let other = Store::new(&Universal::new(compiler).engine());
+    assert!(unsafe { cache.load_module(&other, ANSWER) }.is_none());
+    assert!(unsafe { cache.load_module(&store(), ANSWER) }.is_some());
+}
+
+#[test]
+fn corrupted_modules_miss_the_cache() {
+    let dir = TempDir::new().unwrap();
+    let mut cache = FileSystemCache::new(dir.path()).unwrap();
+    let store = store();
+    let module = Module::new(&store, ANSWER).unwrap();
+    cache.store_module(&module, ANSWER).unwrap();
+
+    let entry = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    std::fs::write(entry.path(), b"truncated").unwrap();
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_none());
+
+    // Storing the module again repairs the entry.
+    cache.store_module(&module, ANSWER).unwrap();
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_some());
+}
+
+#[test]
+fn expired_modules_are_evicted() {
+    let dir = TempDir::new().unwrap();
+    let mut cache = FileSystemCache::new(dir.path()).unwrap();
+    let store = store();
+    let module = Module::new(&store, ANSWER).unwrap();
+
+    cache.store_module(&module, ANSWER).unwrap();
+    cache.set_ttl(Some(Duration::from_secs(60 * 60)));
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_some());
+    assert_eq!(cache.evict_expired().unwrap(), 0);
+
+    cache.set_ttl(Some(Duration::from_secs(0)));
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_none());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    cache.store_module(&module, ANSWER).unwrap();
+    assert_eq!(cache.evict_expired().unwrap(), 1);
+    cache.set_ttl(None);
+    assert!(unsafe { cache.load_module(&store, ANSWER) }.is_none());
+}
@@ -1,7 +1,10 @@
 use crate::store::StoreOptions;
 use anyhow::{Context, Result};
 use bytesize::ByteSize;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 use wasmer::*;
 
@@ -14,6 +17,31 @@ pub struct Inspect {
 
     #[structopt(flatten)]
     store: StoreOptions,
+
+    /// The output format: `text` or `json`
+    #[structopt(long = "format", default_value = "text")]
+    format: InspectFormat,
+}
+
+/// The output formats of the `inspect` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InspectFormat {
+    /// A human-readable listing.
+    Text,
+    /// A JSON document, see [`ModuleDescription`].
+    Json,
+}
+
+impl FromStr for InspectFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format `{}`, expected `text` or `json`", s)),
+        }
+    }
 }
 
 impl Inspect {
@@ -26,6 +54,11 @@ impl Inspect {
         let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
         let module = Module::new(&store, &module_contents)?;
+        if self.format == InspectFormat::Json {
+            let description = ModuleDescription::new(&module, &module_contents)?;
+            println!("{}", serde_json::to_string_pretty(&description)?);
+            return Ok(());
+        }
         println!(
             "Type: {}",
             if !is_wasm(&module_contents) {
@@ -72,3 +105,224 @@ impl Inspect {
         Ok(())
     }
 }
+
+/// The JSON document printed by `wasmer inspect --format json`.
+///
+/// The keys are stable: new ones may be added, but the existing ones
+/// keep their meaning.
+#[derive(Serialize)]
+struct ModuleDescription {
+    /// `wasm` or `wat`.
+    #[serde(rename = "type")]
+    format: &'static str,
+    /// The size of the file, in bytes.
+    size: usize,
+    /// The WebAssembly proposals the module relies on, by name.
+    required_features: Vec<&'static str>,
+    imports: Vec<ImportDescription>,
+    exports: Vec<ExportDescription>,
+    custom_sections: Vec<CustomSectionDescription>,
+}
+
+#[derive(Serialize)]
+struct ImportDescription {
+    module: String,
+    name: String,
+    #[serde(flatten)]
+    ty: ExternDescription,
+}
+
+#[derive(Serialize)]
+struct ExportDescription {
+    name: String,
+    #[serde(flatten)]
+    ty: ExternDescription,
+}
+
+/// The kind and the type of an import or an export.
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "type", rename_all = "lowercase")]
+enum ExternDescription {
+    Function {
+        params: Vec<&'static str>,
+        results: Vec<&'static str>,
+    },
+    Memory {
+        /// In pages.
+        minimum: u32,
+        /// In pages.
+        maximum: Option<u32>,
+        shared: bool,
+    },
+    Table {
+        element: &'static str,
+        minimum: u32,
+        maximum: Option<u32>,
+    },
+    Global {
+        value: &'static str,
+        mutable: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct CustomSectionDescription {
+    name: String,
+    /// The size of the contents of the section, after its name.
+    size: usize,
+}
+
+impl ModuleDescription {
+    fn new(module: &Module, contents: &[u8]) -> Result<Self> {
+        let wasm = if is_wasm(contents) {
+            Cow::Borrowed(contents)
+        } else {
+            #[cfg(feature = "wat")]
+            {
+                wat2wasm(contents)?
+            }
+            #[cfg(not(feature = "wat"))]
+            {
+                anyhow::bail!("the file isn't a WebAssembly binary")
+            }
+        };
+
+        // Scanning the binary also finds the proposals only used by
+        // the instructions.
+        #[cfg(feature = "compiler")]
+        let features = wasmer_compiler::features_required(&wasm)?;
+        #[cfg(not(feature = "compiler"))]
+        let features = module.required_features();
+
+        Ok(Self {
+            format: if is_wasm(contents) { "wasm" } else { "wat" },
+            size: contents.len(),
+            required_features: feature_names(&features),
+            imports: module
+                .imports()
+                .map(|import| ImportDescription {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    ty: ExternDescription::new(import.ty()),
+                })
+                .collect(),
+            exports: module
+                .exports()
+                .map(|export| ExportDescription {
+                    name: export.name().to_string(),
+                    ty: ExternDescription::new(export.ty()),
+                })
+                .collect(),
+            custom_sections: custom_sections(&wasm),
+        })
+    }
+}
+
+impl ExternDescription {
+    fn new(ty: &ExternType) -> Self {
+        match ty {
+            ExternType::Function(ty) => Self::Function {
+                params: ty.params().iter().copied().map(type_name).collect(),
+                results: ty.results().iter().copied().map(type_name).collect(),
+            },
+            ExternType::Memory(ty) => Self::Memory {
+                minimum: ty.minimum.0,
+                maximum: ty.maximum.map(|maximum| maximum.0),
+                shared: ty.shared,
+            },
+            ExternType::Table(ty) => Self::Table {
+                element: type_name(ty.ty),
+                minimum: ty.minimum,
+                maximum: ty.maximum,
+            },
+            ExternType::Global(ty) => Self::Global {
+                value: type_name(ty.ty),
+                mutable: ty.mutability.is_mutable(),
+            },
+        }
+    }
+}
+
+/// The name of `ty` in the WebAssembly text format.
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::V128 => "v128",
+        Type::ExternRef => "externref",
+        Type::FuncRef => "funcref",
+    }
+}
+
+/// The names of the proposals enabled in `features`.
+fn feature_names(features: &Features) -> Vec<&'static str> {
+    let proposals = [
+        ("threads", features.threads),
+        ("reference-types", features.reference_types),
+        ("simd", features.simd),
+        ("bulk-memory", features.bulk_memory),
+        ("multi-value", features.multi_value),
+        ("tail-call", features.tail_call),
+        ("module-linking", features.module_linking),
+        ("multi-memory", features.multi_memory),
+        ("memory64", features.memory64),
+        ("exceptions", features.exceptions),
+        ("relaxed-simd", features.relaxed_simd),
+        ("extended-const", features.extended_const),
+    ];
+    proposals
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Lists the custom sections of a valid binary, in order, including
+/// the `name` section.
+fn custom_sections(wasm: &[u8]) -> Vec<CustomSectionDescription> {
+    let mut sections = Vec::new();
+    // Skip the magic number and the version.
+    let mut offset = 8;
+    while offset < wasm.len() {
+        let id = wasm[offset];
+        offset += 1;
+        let end = match read_u32(wasm, &mut offset) {
+            Some(size) => offset + size as usize,
+            None => break,
+        };
+        if id == 0 {
+            let name =
+                read_u32(wasm, &mut offset).and_then(|len| wasm.get(offset..offset + len as usize));
+            if let Some(name) = name {
+                let contents = offset + name.len();
+                sections.push(CustomSectionDescription {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    size: end.saturating_sub(contents),
+                });
+            }
+        }
+        offset = end;
+    }
+    sections
+}
+
+/// Reads an unsigned LEB128 integer at `offset`, and moves `offset`
+/// past it.
+fn read_u32(bytes: &[u8], offset: &mut usize) -> Option<u32> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*offset)?;
+        *offset += 1;
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+        shift += 7;
+        if shift > 28 {
+            return None;
+        }
+    }
+}
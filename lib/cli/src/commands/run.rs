@@ -96,6 +96,8 @@ impl Run {
     }
 
     fn inner_execute(&self) -> Result<()> {
+        #[cfg(feature = "wasi")]
+        let stdio = self.wasi.open_stdio()?;
         let module = self.get_module()?;
         #[cfg(feature = "emscripten")]
         {
@@ -179,7 +181,7 @@ impl Run {
                         })
                         .unwrap_or_default();
                     self.wasi
-                        .instantiate(&module, program_name, self.args.clone())
+                        .instantiate(&module, program_name, self.args.clone(), stdio)
                         .with_context(|| "failed to instantiate WASI module")?
                 }
                 // not WASI
@@ -374,7 +376,7 @@ impl Run {
                         ExportError::Missing(_) => {
                             anyhow!("No export `{}` found in the module.\n{}", name, suggestion)
                         }
-                        ExportError::IncompatibleType => anyhow!(
+                        ExportError::IncompatibleType { .. } => anyhow!(
                             "Export `{}` found, but is not a function.\n{}",
                             name,
                             suggestion
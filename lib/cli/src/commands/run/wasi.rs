@@ -1,8 +1,10 @@
 use crate::utils::{parse_envvar, parse_mapdir};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::BTreeSet;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
 use wasmer::{Instance, Module, RuntimeError, Val};
+use wasmer_vfs::{host_fs, VirtualFile};
 use wasmer_wasi::{get_wasi_versions, WasiError, WasiState, WasiVersion};
 
 use structopt::StructOpt;
@@ -51,6 +53,30 @@ pub struct Wasi {
     /// Require WASI modules to only import 1 version of WASI.
     #[structopt(long = "deny-multiple-wasi-versions")]
     pub deny_multiple_wasi_versions: bool,
+
+    /// Read the standard input of the WASI module from a file
+    #[structopt(long = "stdin", name = "STDIN_PATH", parse(from_os_str))]
+    stdin: Option<PathBuf>,
+
+    /// Write the standard output of the WASI module to a file
+    #[structopt(long = "stdout", name = "STDOUT_PATH", parse(from_os_str))]
+    stdout: Option<PathBuf>,
+
+    /// Write the standard error of the WASI module to a file
+    #[structopt(long = "stderr", name = "STDERR_PATH", parse(from_os_str))]
+    stderr: Option<PathBuf>,
+
+    /// Append to the `--stdout` and `--stderr` files instead of truncating them
+    #[structopt(long = "append")]
+    append: bool,
+}
+
+/// The files redirecting the standard streams of a WASI module, as
+/// opened by [`Wasi::open_stdio`].
+pub struct Stdio {
+    stdin: Option<Box<dyn VirtualFile>>,
+    stdout: Option<Box<dyn VirtualFile>>,
+    stderr: Option<Box<dyn VirtualFile>>,
 }
 
 #[allow(dead_code)]
@@ -69,12 +95,55 @@ impl Wasi {
         get_wasi_versions(&module, false).is_some()
     }
 
+    /// Opens the files given to `--stdin`, `--stdout` and `--stderr`,
+    /// so that they can be reported before compiling the module.
+    pub fn open_stdio(&self) -> Result<Stdio> {
+        let stdin = self
+            .stdin
+            .as_deref()
+            .map(|path| {
+                let file = File::open(path).with_context(|| {
+                    format!("failed to open the stdin file `{}`", path.display())
+                })?;
+                Ok(host_file(file, path, true, false, false))
+            })
+            .transpose()?;
+        let open_output = |path: &Path, stream: &str| {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(!self.append)
+                .append(self.append)
+                .open(path)
+                .with_context(|| {
+                    format!("failed to open the {} file `{}`", stream, path.display())
+                })?;
+            Ok::<_, anyhow::Error>(host_file(file, path, false, true, self.append))
+        };
+        let stdout = self
+            .stdout
+            .as_deref()
+            .map(|path| open_output(path, "stdout"))
+            .transpose()?;
+        let stderr = self
+            .stderr
+            .as_deref()
+            .map(|path| open_output(path, "stderr"))
+            .transpose()?;
+        Ok(Stdio {
+            stdin,
+            stdout,
+            stderr,
+        })
+    }
+
     /// Helper function for instantiating a module with Wasi imports for the `Run` command.
     pub fn instantiate(
         &self,
         module: &Module,
         program_name: String,
         args: Vec<String>,
+        stdio: Stdio,
     ) -> Result<Instance> {
         let args = args.iter().cloned().map(|arg| arg.into_bytes());
 
@@ -84,6 +153,15 @@ impl Wasi {
             .envs(self.env_vars.clone())
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
+        if let Some(stdin) = stdio.stdin {
+            wasi_state_builder.stdin(stdin);
+        }
+        if let Some(stdout) = stdio.stdout {
+            wasi_state_builder.stdout(stdout);
+        }
+        if let Some(stderr) = stdio.stderr {
+            wasi_state_builder.stderr(stderr);
+        }
 
         #[cfg(feature = "experimental-io-devices")]
         {
@@ -130,3 +208,19 @@ impl Wasi {
         })
     }
 }
+
+fn host_file(
+    file: File,
+    path: &Path,
+    read: bool,
+    write: bool,
+    append: bool,
+) -> Box<dyn VirtualFile> {
+    Box::new(host_fs::File::new(
+        file,
+        path.to_path_buf(),
+        read,
+        write,
+        append,
+    ))
+}
@@ -28,16 +28,23 @@ static void pass_mapdir_arg(wasi_config_t *wasi_config, char *mapdir) {
     exit(-1);
   }
 
-  char *alias = (char *)malloc(colon_location + 1);
-  memcpy(alias, mapdir, colon_location);
-  alias[colon_location] = '\0';
+  // `wasi_config_mapdir` expects an absolute alias.
+  int alias_offset = mapdir[0] == '/' ? 0 : 1;
+  char *alias = (char *)malloc(alias_offset + colon_location + 1);
+  alias[0] = '/';
+  memcpy(&alias[alias_offset], mapdir, colon_location);
+  alias[alias_offset + colon_location] = '\0';
 
   int dir_len = strlen(mapdir) - colon_location;
   char *dir = (char *)malloc(dir_len + 1);
   memcpy(dir, &mapdir[colon_location + 1], dir_len);
   dir[dir_len] = '\0';
 
-  wasi_config_mapdir(wasi_config, alias, dir);
+  if (!wasi_config_mapdir(wasi_config, alias, dir)) {
+    fprintf(stderr, "Failed to map directory `%s` to `%s`\n", dir, alias);
+    print_wasmer_error();
+    exit(-1);
+  }
   free(alias);
   free(dir);
 }
@@ -4,7 +4,9 @@ use crate::address_map::get_function_address_map;
 use crate::config::Cranelift;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
-use crate::func_environ::{get_function_name, FuncEnvironment};
+use crate::func_environ::{
+    get_function_name, FuncEnvironment, CALL_DEPTH_EXCEEDED_USER_CODE, UNALIGNED_ATOMIC_USER_CODE,
+};
 use crate::trampoline::{
     make_trampoline_dynamic_function, make_trampoline_function_call, FunctionBuilderContext,
 };
@@ -15,12 +17,12 @@ use crate::translator::{
 use cranelift_codegen::ir::ExternalName;
 use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::{ir, MachReloc};
-use cranelift_codegen::{Context, MachTrap};
+use cranelift_codegen::{Context, MachStackMap, MachTrap};
 #[cfg(feature = "unwind")]
-use gimli::write::{Address, EhFrame, FrameTable};
+use gimli::write::{Address, EhFrame, FrameDescriptionEntry, FrameTable};
 use loupe::MemoryUsage;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use wasmer_compiler::{
     CallingConvention, ModuleTranslationState, RelocationTarget, Target, TrapInformation,
 };
@@ -28,9 +30,9 @@ use wasmer_compiler::{
     Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
     CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBinaryReader, FunctionBody,
     FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware, ModuleMiddlewareChain,
-    SectionIndex,
+    PerfDiagnostic, PerfDiagnosticCode, SectionIndex, StackMap, StreamedFunctionBody,
 };
-use wasmer_compiler::{CompileError, Relocation};
+use wasmer_compiler::{CompileError, CompileThreadPool, Relocation};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, ModuleInfo, SignatureIndex};
 use wasmer_vm::TrapCode;
@@ -40,12 +42,17 @@ use wasmer_vm::TrapCode;
 #[derive(MemoryUsage)]
 pub struct CraneliftCompiler {
     config: Cranelift,
+    thread_pool: CompileThreadPool,
 }
 
 impl CraneliftCompiler {
     /// Creates a new Cranelift compiler
     pub fn new(config: Cranelift) -> Self {
-        Self { config }
+        let thread_pool = CompileThreadPool::new("cranelift", config.num_compile_threads);
+        Self {
+            config,
+            thread_pool,
+        }
     }
 
     /// Gets the WebAssembly features for this Compiler
@@ -54,21 +61,73 @@ impl CraneliftCompiler {
     }
 }
 
-impl Compiler for CraneliftCompiler {
-    /// Get the middlewares for this compiler
-    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
-        &self.config.middlewares
+/// The unwind information of a function, inserted in the dwarf
+/// frametable.
+#[cfg(feature = "unwind")]
+type FunctionFde = FrameDescriptionEntry;
+/// This is a bit hacky, but necessary since gimli is not available
+/// when the "unwind" feature is disabled.
+#[cfg(not(feature = "unwind"))]
+type FunctionFde = ();
+
+/// A compiled function, with what the compiler collected about it.
+struct CompiledParts {
+    function: CompiledFunction,
+    fde: Option<FunctionFde>,
+    perf_diagnostic: Option<PerfDiagnostic>,
+    /// Only measured if enabled in the config.
+    compile_time: Option<Duration>,
+    /// Empty unless enabled in the config.
+    stack_maps: Vec<StackMap>,
+}
+
+/// Compiles a single function, with a `FuncTranslator` owned by the
+/// calling thread.
+type CompileFunction<'a> = dyn Fn(
+        &mut FuncTranslator,
+        LocalFunctionIndex,
+        &FunctionBodyData<'_>,
+    ) -> Result<CompiledParts, CompileError>
+    + Sync
+    + 'a;
+
+impl CraneliftCompiler {
+    /// Compile a module, with `compile_functions` driving the
+    /// compilation of its local functions, in order, by calling the
+    /// function it is given.
+    ///
+    /// The module is compiled in the global thread pool, or in the pool
+    /// of the compiler if the number of threads is bounded, or on the
+    /// current thread with `0` threads, see
+    /// [`Cranelift::num_compile_threads`].
+    fn compile_module_with(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        compile_functions: impl FnOnce(&CompileFunction) -> Result<Vec<CompiledParts>, CompileError>
+            + Send,
+    ) -> Result<Compilation, CompileError> {
+        self.thread_pool.install(|| {
+            self.compile_module_in_pool(
+                target,
+                compile_info,
+                module_translation_state,
+                compile_functions,
+            )
+        })
     }
 
-    /// Compile the module using Cranelift, producing a compilation result with
-    /// associated relocations.
-    fn compile_module(
+    /// Compile a module as [`CraneliftCompiler::compile_module_with`]
+    /// does, in the thread pool the current thread belongs to.
+    fn compile_module_in_pool(
         &self,
         target: &Target,
         compile_info: &CompileModuleInfo,
         module_translation_state: &ModuleTranslationState,
-        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        compile_functions: impl FnOnce(&CompileFunction) -> Result<Vec<CompiledParts>, CompileError>,
     ) -> Result<Compilation, CompileError> {
+        let start = Instant::now();
         let isa = self
             .config()
             .isa(target)
@@ -85,7 +144,7 @@ impl Compiler for CraneliftCompiler {
 
         // Generate the frametable
         #[cfg(feature = "unwind")]
-        let dwarf_frametable = if function_body_inputs.is_empty() {
+        let dwarf_frametable = if module.functions.len() == module.num_imported_functions {
             // If we have no function body inputs, we don't need to
             // construct the `FrameTable`. Constructing it, with empty
             // FDEs will cause some issues in Linux.
@@ -109,106 +168,147 @@ impl Compiler for CraneliftCompiler {
 
         let mut custom_sections = PrimaryMap::new();
 
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .par_iter()
-            .map_init(FuncTranslator::new, |func_translator, (i, input)| {
-                let func_index = module.func_index(*i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
-                    module,
-                    &signatures,
-                    &memory_styles,
-                    &table_styles,
-                );
-                context.func.name = get_function_name(func_index);
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(
-                    self.config
-                        .middlewares
-                        .generate_function_middleware_chain(*i),
-                );
-
-                func_translator.translate(
-                    module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
-                    *i,
-                )?;
-
-                let mut code_buf: Vec<u8> = Vec::new();
-                context
-                    .compile_and_emit(&*isa, &mut code_buf)
-                    .map_err(|error| CompileError::Codegen(pretty_error(&context.func, error)))?;
-
-                let result = context.mach_compile_result.as_ref().unwrap();
-                let func_relocs = result
-                    .buffer
-                    .relocs()
-                    .into_iter()
-                    .map(|r| mach_reloc_to_reloc(module, r))
-                    .collect::<Vec<_>>();
+        let compile_function = |func_translator: &mut FuncTranslator,
+                                i: LocalFunctionIndex,
+                                input: &FunctionBodyData<'_>|
+         -> Result<CompiledParts, CompileError> {
+            let start = self.config.enable_function_timings.then(Instant::now);
+            let func_index = module.func_index(i);
+            let mut context = Context::new();
+            let mut func_env = FuncEnvironment::new(
+                isa.frontend_config(),
+                module,
+                &signatures,
+                &memory_styles,
+                &table_styles,
+                self.config.enable_epoch_interruption,
+                self.config.enable_call_depth_limit,
+                self.config.trap_unaligned_atomics,
+            );
+            context.func.name = get_function_name(func_index);
+            context.func.signature = signatures[module.functions[func_index]].clone();
+            // if generate_debug_info {
+            //     context.func.collect_debug_info();
+            // }
+            let mut reader =
+                MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+            reader.set_middleware_chain(
+                self.config
+                    .middlewares
+                    .generate_function_middleware_chain(i),
+            );
 
-                let traps = result
+            func_translator.translate(
+                module_translation_state,
+                &mut reader,
+                &mut context.func,
+                &mut func_env,
+                i,
+            )?;
+
+            let bounds_checks_not_elided = func_translator.bounds_checks_not_elided();
+            let perf_diagnostic = if self.config.enable_perf_diagnostics
+                && bounds_checks_not_elided > 0
+            {
+                Some(PerfDiagnostic {
+                    func_index,
+                    code: PerfDiagnosticCode::BoundsCheckNotElided,
+                    message: format!(
+                        "{} memory accesses have an offset larger than the guard pages and need an explicit bounds check",
+                        bounds_checks_not_elided
+                    ),
+                    weight: bounds_checks_not_elided,
+                })
+            } else {
+                None
+            };
+
+            let mut code_buf: Vec<u8> = Vec::new();
+            context
+                .compile_and_emit(&*isa, &mut code_buf)
+                .map_err(|error| CompileError::Codegen(pretty_error(&context.func, error)))?;
+
+            let result = context.mach_compile_result.as_ref().unwrap();
+            let func_relocs = result
+                .buffer
+                .relocs()
+                .into_iter()
+                .map(|r| mach_reloc_to_reloc(module, r))
+                .collect::<Vec<_>>();
+
+            let traps = result
+                .buffer
+                .traps()
+                .into_iter()
+                .map(mach_trap_to_trap)
+                .collect::<Vec<_>>();
+
+            let stack_maps = if self.config.enable_stack_maps {
+                result
                     .buffer
-                    .traps()
-                    .into_iter()
-                    .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
-
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::FDE(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
+                    .stack_maps()
+                    .iter()
+                    .map(|map| mach_stack_map_to_stack_map(map, isa.pointer_bytes()))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
+                #[cfg(feature = "unwind")]
+                CraneliftUnwindInfo::FDE(fde) => {
+                    if dwarf_frametable.is_some() {
+                        let fde = fde.to_fde(Address::Symbol {
+                            // The symbol is the kind of relocation.
+                            // "0" is used for functions
+                            symbol: WriterRelocate::FUNCTION_SYMBOL,
+                            // We use the addend as a way to specify the
+                            // function index
+                            addend: i.index() as _,
+                        });
+                        // The unwind information is inserted into the dwarf section
+                        (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
+                    } else {
+                        (None, None)
                     }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
-
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
-
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len());
-
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                }
+                #[cfg(feature = "unwind")]
+                other => (other.maybe_into_to_windows_unwind(), None),
+                #[cfg(not(feature = "unwind"))]
+                other => (other.maybe_into_to_windows_unwind(), None),
+            };
+
+            let range = reader.range();
+            let address_map = get_function_address_map(&context, range, code_buf.len());
+
+            Ok(CompiledParts {
+                function: CompiledFunction {
+                    body: FunctionBody {
+                        body: code_buf,
+                        unwind_info,
                     },
-                    fde,
-                ))
+                    relocations: func_relocs,
+                    frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                },
+                fde,
+                perf_diagnostic,
+                compile_time: start.map(|start| start.elapsed()),
+                stack_maps,
             })
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .unzip();
+        };
+
+        let mut functions = PrimaryMap::new();
+        let mut fdes = Vec::new();
+        let mut perf_diagnostics = Vec::new();
+        let mut function_compile_times = PrimaryMap::new();
+        let mut stack_maps = PrimaryMap::new();
+        for parts in compile_functions(&compile_function)? {
+            functions.push(parts.function);
+            fdes.push(parts.fde);
+            perf_diagnostics.extend(parts.perf_diagnostic);
+            function_compile_times.push(parts.compile_time.unwrap_or_default());
+            stack_maps.push(parts.stack_maps);
+        }
 
         #[cfg(feature = "unwind")]
         let dwarf = if let Some((mut dwarf_frametable, cie_id)) = dwarf_frametable {
@@ -230,14 +330,14 @@ impl Compiler for CraneliftCompiler {
         let dwarf = None;
 
         // function call trampolines (only for local functions, by signature)
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map_init(FunctionBuilderContext::new, |mut cx, sig| {
-                make_trampoline_function_call(&*isa, &mut cx, sig)
-            })
+        let function_call_trampolines = self
+            .thread_pool
+            .map_init(
+                module.signatures.values().collect(),
+                FunctionBuilderContext::new,
+                |mut cx, sig| make_trampoline_function_call(&*isa, &mut cx, sig),
+            )
+            .into_iter()
             .collect::<Result<Vec<FunctionBody>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<SignatureIndex, FunctionBody>>();
@@ -245,24 +345,143 @@ impl Compiler for CraneliftCompiler {
         use wasmer_vm::VMOffsets;
         let offsets = VMOffsets::new_for_trampolines(frontend_config.pointer_bytes());
         // dynamic function trampolines (only for imported functions)
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map_init(FunctionBuilderContext::new, |mut cx, func_type| {
-                make_trampoline_dynamic_function(&*isa, &offsets, &mut cx, &func_type)
-            })
+        let dynamic_function_trampolines = self
+            .thread_pool
+            .map_init(
+                module.imported_function_types().collect(),
+                FunctionBuilderContext::new,
+                |mut cx, func_type| {
+                    make_trampoline_dynamic_function(&*isa, &offsets, &mut cx, &func_type)
+                },
+            )
+            .into_iter()
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
+        let function_compile_times = if self.config.enable_function_timings {
+            Some(function_compile_times)
+        } else {
+            None
+        };
+        let stack_maps = if self.config.enable_stack_maps {
+            Some(stack_maps)
+        } else {
+            None
+        };
         Ok(Compilation::new(
-            functions.into_iter().collect(),
+            functions,
             custom_sections,
             function_call_trampolines,
             dynamic_function_trampolines,
             dwarf,
-        ))
+        )
+        .with_perf_diagnostics(perf_diagnostics)
+        .with_compile_time(start.elapsed(), function_compile_times)
+        .with_stack_maps(stack_maps))
+    }
+}
+
+impl Compiler for CraneliftCompiler {
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.config.middlewares
+    }
+
+    fn deterministic_id(&self) -> String {
+        let mut config = self.config.clone();
+        let middlewares = std::mem::take(&mut config.middlewares);
+        format!(
+            "cranelift-{:?}-[{}]",
+            config,
+            middlewares.deterministic_id()
+        )
+    }
+
+    fn retains_wasm_bytes(&self) -> bool {
+        self.config.retain_wasm_bytes
+    }
+
+    fn emits_epoch_checks(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
+
+    /// Compile the module using Cranelift, producing a compilation result with
+    /// associated relocations.
+    fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        self.compile_module_with(
+            target,
+            compile_info,
+            module_translation_state,
+            |compile_function| {
+                self.thread_pool
+                    .map_init(
+                        function_body_inputs.iter().collect(),
+                        FuncTranslator::new,
+                        |func_translator, (i, input)| compile_function(func_translator, i, input),
+                    )
+                    .into_iter()
+                    .collect()
+            },
+        )
+    }
+
+    /// Compile the module using Cranelift, each function being compiled
+    /// in the thread pool as soon as its body is received.
+    fn compile_module_streaming(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        function_bodies: &mut (dyn Iterator<Item = StreamedFunctionBody> + Send),
+    ) -> Result<Compilation, CompileError> {
+        self.compile_module_with(
+            target,
+            compile_info,
+            module_translation_state,
+            |compile_function| {
+                if self.thread_pool.is_current_thread() {
+                    let mut func_translator = FuncTranslator::new();
+                    return function_bodies
+                        .enumerate()
+                        .map(|(i, body)| {
+                            compile_function(
+                                &mut func_translator,
+                                LocalFunctionIndex::new(i),
+                                &body.as_function_body_data(),
+                            )
+                        })
+                        .collect();
+                }
+                let results = Mutex::new(Vec::new());
+                // The bodies are received on the current thread, so
+                // waiting for them doesn't hold a worker of the pool.
+                rayon::in_place_scope(|scope| {
+                    for (i, body) in function_bodies.enumerate() {
+                        let results = &results;
+                        scope.spawn(move |_| {
+                            let i = LocalFunctionIndex::new(i);
+                            let mut func_translator = FuncTranslator::new();
+                            let result = compile_function(
+                                &mut func_translator,
+                                i,
+                                &body.as_function_body_data(),
+                            );
+                            results.lock().unwrap().push((i, result));
+                        });
+                    }
+                });
+                let mut results = results.into_inner().unwrap();
+                results.sort_by_key(|(i, _)| *i);
+                results.into_iter().map(|(_, result)| result).collect()
+            },
+        )
     }
 }
 
@@ -306,6 +525,26 @@ fn mach_trap_to_trap(trap: &MachTrap) -> TrapInformation {
     }
 }
 
+fn mach_stack_map_to_stack_map(map: &MachStackMap, pointer_bytes: u8) -> StackMap {
+    let &MachStackMap {
+        offset: _,
+        offset_end,
+        ref stack_map,
+    } = map;
+    // Each bit of the map tells whether a word of the frame holds a
+    // live reference.
+    let bitmap = stack_map.as_slice();
+    let live_slots = (0..stack_map.mapped_words())
+        .filter(|&word| bitmap[word as usize / 32].contains(word % 32))
+        .map(|word| word * u32::from(pointer_bytes))
+        .collect();
+    StackMap {
+        code_offset: offset_end,
+        mapped_bytes: stack_map.mapped_words() * u32::from(pointer_bytes),
+        live_slots,
+    }
+}
+
 /// Translates the Cranelift IR TrapCode into generic Trap Code
 fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
     match trap {
@@ -319,9 +558,10 @@ fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
         ir::TrapCode::IntegerDivisionByZero => TrapCode::IntegerDivisionByZero,
         ir::TrapCode::BadConversionToInteger => TrapCode::BadConversionToInteger,
         ir::TrapCode::UnreachableCodeReached => TrapCode::UnreachableCodeReached,
-        ir::TrapCode::Interrupt => unimplemented!("Interrupts not supported"),
+        ir::TrapCode::Interrupt => TrapCode::EpochDeadlineExceeded,
+        ir::TrapCode::User(CALL_DEPTH_EXCEEDED_USER_CODE) => TrapCode::CallDepthExceeded,
+        ir::TrapCode::User(UNALIGNED_ATOMIC_USER_CODE) => TrapCode::UnalignedAtomic,
         ir::TrapCode::User(_user_code) => unimplemented!("User trap code not supported"),
-        // ir::TrapCode::Interrupt => TrapCode::Interrupt,
         // ir::TrapCode::User(user_code) => TrapCode::User(user_code),
     }
 }
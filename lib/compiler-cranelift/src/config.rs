@@ -5,7 +5,7 @@ use cranelift_codegen::CodegenResult;
 use loupe::MemoryUsage;
 use std::sync::Arc;
 use wasmer_compiler::{
-    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
+    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, OptLevel, Target,
 };
 
 // Runtime Environment
@@ -24,6 +24,22 @@ pub enum CraneliftOptLevel {
     SpeedAndSize,
 }
 
+/// The branch protections the Cranelift codegen backend can apply on
+/// aarch64, see [`Cranelift::branch_protection`].
+///
+/// Branch target identification (BTI) isn't one of them: this version
+/// of Cranelift doesn't emit its landing pads.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, MemoryUsage)]
+pub enum CraneliftBranchProtection {
+    /// No branch protection.
+    None,
+    /// The functions sign their return address on entry and
+    /// authenticate it before returning, with the pointer
+    /// authentication (PAC) instructions.
+    Pac,
+}
+
 /// Global configuration options used to create an
 /// `wasmer_engine::Engine` and customize its behavior.
 ///
@@ -34,6 +50,15 @@ pub struct Cranelift {
     enable_nan_canonicalization: bool,
     enable_verifier: bool,
     enable_pic: bool,
+    pub(crate) enable_epoch_interruption: bool,
+    pub(crate) enable_call_depth_limit: bool,
+    pub(crate) trap_unaligned_atomics: bool,
+    pub(crate) enable_perf_diagnostics: bool,
+    pub(crate) enable_function_timings: bool,
+    pub(crate) enable_stack_maps: bool,
+    branch_protection: CraneliftBranchProtection,
+    pub(crate) num_compile_threads: Option<usize>,
+    pub(crate) retain_wasm_bytes: bool,
     opt_level: CraneliftOptLevel,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
@@ -48,6 +73,15 @@ impl Cranelift {
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
+            enable_epoch_interruption: false,
+            enable_call_depth_limit: false,
+            trap_unaligned_atomics: true,
+            enable_perf_diagnostics: false,
+            enable_function_timings: false,
+            enable_stack_maps: false,
+            branch_protection: CraneliftBranchProtection::None,
+            num_compile_threads: None,
+            retain_wasm_bytes: false,
             middlewares: vec![],
         }
     }
@@ -61,6 +95,82 @@ impl Cranelift {
         self
     }
 
+    /// Enable epoch-based interruption.
+    ///
+    /// When enabled, the generated code checks the epoch counter of
+    /// the store at every function entry and loop header.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
+    /// Enable the call depth limit.
+    ///
+    /// When enabled, the generated code counts the wasm frames active
+    /// in the store on entry and return of every function.
+    pub fn call_depth_limit(&mut self, enable: bool) -> &mut Self {
+        self.enable_call_depth_limit = enable;
+        self
+    }
+
+    /// Trap on the atomic accesses to unaligned addresses, as the
+    /// threads proposal requires. Enabled by default.
+    ///
+    /// When disabled, the alignment of the atomic accesses isn't
+    /// checked, which is only meant for testing.
+    pub fn trap_unaligned_atomics(&mut self, enable: bool) -> &mut Self {
+        self.trap_unaligned_atomics = enable;
+        self
+    }
+
+    /// Enable performance diagnostics.
+    ///
+    /// When enabled, functions with memory accesses whose offset is too
+    /// large for their bounds check to be elided are reported.
+    pub fn perf_diagnostics(&mut self, enable: bool) -> &mut Self {
+        self.enable_perf_diagnostics = enable;
+        self
+    }
+
+    /// Enable measuring the time spent compiling each function.
+    pub fn function_timings(&mut self, enable: bool) -> &mut Self {
+        self.enable_function_timings = enable;
+        self
+    }
+
+    /// Enable stack maps.
+    ///
+    /// When enabled, the stack slots holding live references
+    /// (`externref`s and `funcref`s) at each call are reported.
+    pub fn stack_maps(&mut self, enable: bool) -> &mut Self {
+        self.enable_stack_maps = enable;
+        self
+    }
+
+    /// Compile the functions of a module in a thread pool of
+    /// `threads` threads rather than in the global thread pool, see
+    /// [`CompilerConfig::num_compile_threads`].
+    pub fn num_compile_threads(&mut self, threads: usize) -> &mut Self {
+        self.num_compile_threads = Some(threads);
+        self
+    }
+
+    /// Set the branch protection of the code compiled for aarch64.
+    ///
+    /// [`CraneliftBranchProtection::Pac`] signs the return addresses.
+    /// The PAC instructions are no-ops on the CPUs without pointer
+    /// authentication. The other architectures are left as they are.
+    ///
+    /// Branch target identification (BTI) isn't supported: this
+    /// version of Cranelift doesn't emit its landing pads. The code
+    /// memory isn't mapped with `PROT_BTI` either, so the compiled code
+    /// isn't subject to BTI enforcement, and runs on systems enforcing
+    /// it for the rest of the process.
+    pub fn branch_protection(&mut self, protection: CraneliftBranchProtection) -> &mut Self {
+        self.branch_protection = protection;
+        self
+    }
+
     /// The optimization levels when optimizing the IR.
     pub fn opt_level(&mut self, opt_level: CraneliftOptLevel) -> &mut Self {
         self.opt_level = opt_level;
@@ -118,6 +228,13 @@ impl Cranelift {
         if cpu_features.contains(CpuFeature::LZCNT) {
             builder.enable("has_lzcnt").expect("should be valid flag");
         }
+        if self.branch_protection == CraneliftBranchProtection::Pac
+            && matches!(target.triple().architecture, Architecture::Aarch64(_))
+        {
+            builder
+                .enable("sign_return_address")
+                .expect("should be valid flag");
+        }
 
         builder.finish(self.flags())
     }
@@ -200,6 +317,46 @@ impl CompilerConfig for Cranelift {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
+    fn call_depth_limit(&mut self, enable: bool) {
+        self.enable_call_depth_limit = enable;
+    }
+
+    fn trap_unaligned_atomics(&mut self, enable: bool) {
+        self.trap_unaligned_atomics = enable;
+    }
+
+    fn enable_perf_diagnostics(&mut self, enable: bool) {
+        self.enable_perf_diagnostics = enable;
+    }
+
+    fn enable_function_timings(&mut self, enable: bool) {
+        self.enable_function_timings = enable;
+    }
+
+    fn enable_stack_maps(&mut self, enable: bool) {
+        self.enable_stack_maps = enable;
+    }
+
+    fn num_compile_threads(&mut self, threads: usize) {
+        self.num_compile_threads = Some(threads);
+    }
+
+    fn retain_wasm_bytes(&mut self, retain: bool) {
+        self.retain_wasm_bytes = retain;
+    }
+
+    fn set_opt_level(&mut self, level: OptLevel) {
+        self.opt_level = match level {
+            OptLevel::None => CraneliftOptLevel::None,
+            OptLevel::Speed => CraneliftOptLevel::Speed,
+            OptLevel::Size => CraneliftOptLevel::SpeedAndSize,
+        };
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(CraneliftCompiler::new(*self))
@@ -18,13 +18,21 @@ use wasmer_compiler::{WasmError, WasmResult};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type as WasmerType,
+    FunctionIndex, FunctionType, GlobalIndex, IndexType, LocalFunctionIndex, MemoryIndex,
+    ModuleInfo, SignatureIndex, TableIndex, Type as WasmerType,
 };
 use wasmer_vm::VMBuiltinFunctionIndex;
 use wasmer_vm::VMOffsets;
 use wasmer_vm::{MemoryStyle, TableStyle};
 
+/// The user trap code of the trap of a call going past the maximum
+/// call depth.
+pub const CALL_DEPTH_EXCEEDED_USER_CODE: u16 = 0;
+
+/// The user trap code of the trap of an atomic access to an unaligned
+/// address.
+pub const UNALIGNED_ATOMIC_USER_CODE: u16 = 1;
+
 /// Compute an `ir::ExternalName` for a given wasm function index.
 pub fn get_function_name(func_index: FunctionIndex) -> ir::ExternalName {
     ir::ExternalName::user(0, func_index.as_u32())
@@ -82,6 +90,22 @@ pub struct FuncEnvironment<'module_environment> {
     /// (it's the same for both local and imported memories).
     memory_copy_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// between two different memories.
+    memory_copy_between_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait32`.
+    memory_atomic_wait32_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait64`.
+    memory_atomic_wait64_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.notify`.
+    memory_atomic_notify_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `memory.fill`
     /// (it's the same for both local and imported memories).
     memory_fill_sig: Option<ir::SigRef>,
@@ -109,6 +133,10 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The external function signature for implementing reference decrement for `extern.ref`.
     externref_dec_sig: Option<ir::SigRef>,
+
+    /// The external function signature for checking whether the epoch
+    /// deadline is reached.
+    epoch_deadline_reached_sig: Option<ir::SigRef>,
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -117,6 +145,15 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The table styles
     table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+
+    /// Whether to emit epoch deadline checks.
+    epoch_interruption: bool,
+
+    /// Whether to count the call depth.
+    call_depth_limit: bool,
+
+    /// Whether the atomic accesses to unaligned addresses trap.
+    trap_unaligned_atomics: bool,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -126,6 +163,9 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         signatures: &'module_environment PrimaryMap<SignatureIndex, ir::Signature>,
         memory_styles: &'module_environment PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+        epoch_interruption: bool,
+        call_depth_limit: bool,
+        trap_unaligned_atomics: bool,
     ) -> Self {
         Self {
             target_config,
@@ -141,6 +181,10 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             table_init_sig: None,
             elem_drop_sig: None,
             memory_copy_sig: None,
+            memory_copy_between_sig: None,
+            memory_atomic_wait32_sig: None,
+            memory_atomic_wait64_sig: None,
+            memory_atomic_notify_sig: None,
             memory_fill_sig: None,
             memory_init_sig: None,
             table_get_sig: None,
@@ -150,9 +194,13 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             table_fill_sig: None,
             externref_inc_sig: None,
             externref_dec_sig: None,
+            epoch_deadline_reached_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
+            epoch_interruption,
+            call_depth_limit,
+            trap_unaligned_atomics,
         }
     }
 
@@ -639,6 +687,89 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    fn get_memory_copy_between_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_copy_between_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Destination memory index.
+                    AbiParam::new(I32),
+                    // Source memory index.
+                    AbiParam::new(I32),
+                    // Destination address.
+                    AbiParam::new(I32),
+                    // Source address.
+                    AbiParam::new(I32),
+                    // Length.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_copy_between_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_atomic_wait_sig(
+        &mut self,
+        func: &mut Function,
+        expected: ir::Type,
+    ) -> ir::SigRef {
+        let cached_sig = if expected == I32 {
+            self.memory_atomic_wait32_sig
+        } else {
+            self.memory_atomic_wait64_sig
+        };
+        let sig = cached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I32),
+                    // Offset.
+                    AbiParam::new(I32),
+                    // Expected value.
+                    AbiParam::new(expected),
+                    // Timeout.
+                    AbiParam::new(I64),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        if expected == I32 {
+            self.memory_atomic_wait32_sig = Some(sig);
+        } else {
+            self.memory_atomic_wait64_sig = Some(sig);
+        }
+        sig
+    }
+
+    fn get_memory_atomic_notify_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_atomic_notify_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I32),
+                    // Offset.
+                    AbiParam::new(I32),
+                    // Count.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_atomic_notify_sig = Some(sig);
+        sig
+    }
+
     fn get_memory_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.memory_fill_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -757,6 +888,104 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
         (base, func_addr)
     }
+
+    fn get_epoch_deadline_reached_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.epoch_deadline_reached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.epoch_deadline_reached_sig = Some(sig);
+        sig
+    }
+
+    /// Emit a check of the epoch counter against the threshold of the
+    /// epoch deadline, calling the builtin trapping if the deadline is
+    /// reached or the call interrupted once the counter reaches it.
+    fn translate_epoch_check(&mut self, builder: &mut FunctionBuilder) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(&mut builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let counter_offset = i32::try_from(self.offsets.vmctx_epoch_counter_ptr()).unwrap();
+        let counter_ptr = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, counter_offset);
+        let deadline_offset = i32::try_from(self.offsets.vmctx_epoch_deadline_ptr()).unwrap();
+        let deadline_ptr = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, deadline_offset);
+
+        // The counter and the threshold are updated from outside of
+        // wasm, so they must be reloaded on every check.
+        let counter = builder
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), counter_ptr, 0);
+        let threshold = builder
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), deadline_ptr, 0);
+        let reached = builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, counter, threshold);
+
+        let reached_block = builder.create_block();
+        let continue_block = builder.create_block();
+        builder.ins().brnz(reached, reached_block, &[]);
+        builder.ins().jump(continue_block, &[]);
+
+        builder.switch_to_block(reached_block);
+        builder.seal_block(reached_block);
+        let func_sig = self.get_epoch_deadline_reached_sig(&mut builder.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+            &mut builder.cursor(),
+            VMBuiltinFunctionIndex::get_epoch_deadline_reached_index(),
+        );
+        builder.ins().call_indirect(func_sig, func_addr, &[vmctx]);
+        builder.ins().jump(continue_block, &[]);
+
+        builder.switch_to_block(continue_block);
+        builder.seal_block(continue_block);
+    }
+
+    /// Load the pointer to the call depth left, and its value.
+    fn load_call_depth_left(&mut self, pos: &mut FuncCursor<'_>) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(&mut pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let offset = i32::try_from(self.offsets.vmctx_call_depth_left_ptr()).unwrap();
+        let left_ptr = pos.ins().load(pointer_type, mem_flags, base, offset);
+        let left = pos.ins().load(I64, ir::MemFlags::trusted(), left_ptr, 0);
+        (left_ptr, left)
+    }
+
+    /// Emit the decrement of the call depth left, trapping if it is
+    /// already exhausted.
+    fn translate_call_depth_enter(&mut self, pos: &mut FuncCursor<'_>) {
+        let (left_ptr, left) = self.load_call_depth_left(pos);
+        pos.ins()
+            .trapz(left, ir::TrapCode::User(CALL_DEPTH_EXCEEDED_USER_CODE));
+        let left = pos.ins().iadd_imm(left, -1);
+        pos.ins().store(ir::MemFlags::trusted(), left, left_ptr, 0);
+    }
+
+    /// Emit the increment of the call depth left, when returning.
+    fn translate_call_depth_exit(&mut self, pos: &mut FuncCursor<'_>) {
+        let (left_ptr, left) = self.load_call_depth_left(pos);
+        let left = pos.ins().iadd_imm(left, 1);
+        pos.ins().store(ir::MemFlags::trusted(), left, left_ptr, 0);
+    }
 }
 
 impl<'module_environment> TargetEnvironment for FuncEnvironment<'module_environment> {
@@ -771,6 +1000,14 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         index >= 1
     }
 
+    fn unaligned_atomic_trap(&self) -> Option<ir::TrapCode> {
+        if self.trap_unaligned_atomics {
+            Some(ir::TrapCode::User(UNALIGNED_ATOMIC_USER_CODE))
+        } else {
+            None
+        }
+    }
+
     fn make_table(&mut self, func: &mut ir::Function, index: TableIndex) -> WasmResult<ir::Table> {
         let pointer_type = self.pointer_type();
 
@@ -1078,7 +1315,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type: match self.module.memories[index].index_type {
+                IndexType::I32 => I32,
+                IndexType::I64 => I64,
+            },
         }))
     }
 
@@ -1270,23 +1510,46 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         &mut self,
         mut pos: FuncCursor<'_>,
         index: MemoryIndex,
-        _heap: ir::Heap,
+        heap: ir::Heap,
         val: ir::Value,
     ) -> WasmResult<ir::Value> {
+        let is_memory64 = pos.func.heaps[heap].index_type == I64;
+        let val = if is_memory64 {
+            // The builtins grow by 32-bit numbers of pages: growing by
+            // more than `u32::MAX` pages fails like growing by
+            // `u32::MAX` pages does.
+            let too_large =
+                pos.ins()
+                    .icmp_imm(IntCC::UnsignedGreaterThan, val, i64::from(u32::MAX));
+            let val = pos.ins().ireduce(I32, val);
+            let max = pos.ins().iconst(I32, i64::from(u32::MAX));
+            pos.ins().select(too_large, max, val)
+        } else {
+            val
+        };
         let (func_sig, index_arg, func_idx) = self.get_memory_grow_func(&mut pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
         let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if !is_memory64 {
+            return Ok(result);
+        }
+        // A memory never has `u32::MAX` pages, which is returned when
+        // growing fails.
+        let failed = pos.ins().icmp_imm(IntCC::Equal, result, -1);
+        let result = pos.ins().uextend(I64, result);
+        let minus_one = pos.ins().iconst(I64, -1);
+        Ok(pos.ins().select(failed, minus_one, result))
     }
 
     fn translate_memory_size(
         &mut self,
         mut pos: FuncCursor<'_>,
         index: MemoryIndex,
-        _heap: ir::Heap,
+        heap: ir::Heap,
     ) -> WasmResult<ir::Value> {
         let (func_sig, index_arg, func_idx) = self.get_memory_size_func(&mut pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
@@ -1294,7 +1557,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if pos.func.heaps[heap].index_type == I64 {
+            Ok(pos.ins().uextend(I64, result))
+        } else {
+            Ok(result)
+        }
     }
 
     fn translate_memory_copy(
@@ -1302,12 +1570,28 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         mut pos: FuncCursor,
         src_index: MemoryIndex,
         _src_heap: ir::Heap,
-        _dst_index: MemoryIndex,
+        dst_index: MemoryIndex,
         _dst_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if src_index != dst_index {
+            let func_sig = self.get_memory_copy_between_sig(&mut pos.func);
+            let dst_index_arg = pos.ins().iconst(I32, dst_index.index() as i64);
+            let src_index_arg = pos.ins().iconst(I32, src_index.index() as i64);
+            let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+            );
+            pos.ins().call_indirect(
+                func_sig,
+                func_addr,
+                &[vmctx, dst_index_arg, src_index_arg, dst, src, len],
+            );
+            return Ok(());
+        }
+
         let (func_sig, src_index, func_idx) = self.get_memory_copy_func(&mut pos.func, src_index);
 
         let src_index_arg = pos.ins().iconst(I32, src_index as i64);
@@ -1472,29 +1756,78 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_atomic_wait(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _expected: ir::Value,
-        _timeout: ir::Value,
+        addr: ir::Value,
+        expected: ir::Value,
+        timeout: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_wait)".to_string(),
-        ))
+        let expected_type = pos.func.dfg.value_type(expected);
+        let func_sig = self.get_memory_atomic_wait_sig(&mut pos.func, expected_type);
+        let func_idx = if expected_type == I32 {
+            VMBuiltinFunctionIndex::get_memory_atomic_wait32_index()
+        } else {
+            VMBuiltinFunctionIndex::get_memory_atomic_wait64_index()
+        };
+        let memory_index = pos.ins().iconst(I32, index.index() as i64);
+        // The static offset was already added to `addr`.
+        let offset = pos.ins().iconst(I32, 0);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index, addr, offset, expected, timeout],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_atomic_notify(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _count: ir::Value,
+        addr: ir::Value,
+        count: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_notify)".to_string(),
-        ))
+        let func_sig = self.get_memory_atomic_notify_sig(&mut pos.func);
+        let memory_index = pos.ins().iconst(I32, index.index() as i64);
+        // The static offset was already added to `addr`.
+        let offset = pos.ins().iconst(I32, 0);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+        );
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index, addr, offset, count],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_function_entry(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        if self.epoch_interruption {
+            self.translate_epoch_check(builder);
+        }
+        if self.call_depth_limit {
+            self.translate_call_depth_enter(&mut builder.cursor());
+        }
+        Ok(())
+    }
+
+    fn translate_function_exit(&mut self, mut pos: FuncCursor) -> WasmResult<()> {
+        if self.call_depth_limit {
+            self.translate_call_depth_exit(&mut pos);
+        }
+        Ok(())
+    }
+
+    fn translate_loop_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        if self.epoch_interruption {
+            self.translate_epoch_check(builder);
+        }
+        Ok(())
     }
 
     fn get_global_type(&self, global_index: GlobalIndex) -> Option<WasmerType> {
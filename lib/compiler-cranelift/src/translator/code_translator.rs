@@ -321,7 +321,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 .extend_from_slice(builder.block_params(loop_body));
 
             builder.switch_to_block(loop_body);
-            environ.translate_loop_header(builder.cursor())?;
+            environ.translate_loop_header(builder)?;
         }
         Operator::If { ty } => {
             let (val, _) = state.pop1();
@@ -601,7 +601,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 });
                 bitcast_arguments(return_args, &return_types, builder);
                 match environ.return_mode() {
-                    ReturnMode::NormalReturns => builder.ins().return_(return_args),
+                    ReturnMode::NormalReturns => {
+                        environ.translate_function_exit(builder.cursor())?;
+                        builder.ins().return_(return_args)
+                    }
+                    // The exit block returns, after `translate_function_exit`.
                     ReturnMode::FallthroughReturn => canonicalise_then_jump(
                         builder,
                         br_destination,
@@ -1148,10 +1152,17 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             };
             let heap_index = MemoryIndex::from_u32(memarg.memory);
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+            check_memory32(op, heap, builder)?;
             let (timeout, _) = state.pop1(); // 64 (fixed)
             let (expected, _) = state.pop1(); // 32 or 64 (per the `Ixx` in `IxxAtomicWait`)
             let (addr, _) = state.pop1(); // 32 (fixed)
-            let addr = fold_atomic_mem_addr(addr, memarg, implied_ty, builder);
+            let addr = fold_atomic_mem_addr(
+                addr,
+                memarg,
+                implied_ty,
+                environ.unaligned_atomic_trap(),
+                builder,
+            );
             assert!(builder.func.dfg.value_type(expected) == implied_ty);
             // `fn translate_atomic_wait` can inspect the type of `expected` to figure out what
             // code it needs to generate, if it wants.
@@ -1168,9 +1179,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         Operator::MemoryAtomicNotify { memarg } => {
             let heap_index = MemoryIndex::from_u32(memarg.memory);
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+            check_memory32(op, heap, builder)?;
             let (count, _) = state.pop1(); // 32 (fixed)
             let (addr, _) = state.pop1(); // 32 (fixed)
-            let addr = fold_atomic_mem_addr(addr, memarg, I32, builder);
+            let addr =
+                fold_atomic_mem_addr(addr, memarg, I32, environ.unaligned_atomic_trap(), builder);
             let res =
                 environ.translate_atomic_notify(builder.cursor(), heap_index, heap, addr, count)?;
             state.push1(res);
@@ -1381,6 +1394,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let dst_index = MemoryIndex::from_u32(*dst);
             let src_heap = state.get_heap(builder.func, *src, environ)?;
             let dst_heap = state.get_heap(builder.func, *dst, environ)?;
+            check_memory32(op, src_heap, builder)?;
+            check_memory32(op, dst_heap, builder)?;
             let (len, _) = state.pop1();
             let (src_pos, _) = state.pop1();
             let (dst_pos, _) = state.pop1();
@@ -1398,6 +1413,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         Operator::MemoryFill { mem } => {
             let heap_index = MemoryIndex::from_u32(*mem);
             let heap = state.get_heap(builder.func, *mem, environ)?;
+            check_memory32(op, heap, builder)?;
             let (len, _) = state.pop1();
             let (val, _) = state.pop1();
             let (dest, _) = state.pop1();
@@ -1406,6 +1422,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         Operator::MemoryInit { segment, mem } => {
             let heap_index = MemoryIndex::from_u32(*mem);
             let heap = state.get_heap(builder.func, *mem, environ)?;
+            check_memory32(op, heap, builder)?;
             let (len, _) = state.pop1();
             let (src, _) = state.pop1();
             let (dest, _) = state.pop1();
@@ -2264,6 +2281,7 @@ fn get_heap_addr(
     width: u32,
     addr_ty: Type,
     builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
 ) -> (ir::Value, i32) {
     let offset_guard_size: u64 = builder.func.heaps[heap].offset_guard_size.into();
 
@@ -2325,6 +2343,14 @@ fn get_heap_addr(
     };
     debug_assert!(adjusted_offset > 0); // want to bounds check at least 1 byte
     let check_size = u32::try_from(adjusted_offset).unwrap_or(u32::MAX);
+    // Report the accesses that pay for an explicit bounds check only
+    // because their offset goes past the guard pages.
+    if offset_guard_size != 0
+        && !is_bounds_check_elided(&builder.func.heaps[heap], check_size)
+        && is_bounds_check_elided(&builder.func.heaps[heap], 1)
+    {
+        state.bounds_checks_not_elided += 1;
+    }
     let base = builder.ins().heap_addr(addr_ty, heap, addr32, check_size);
 
     // Native load/store instructions take a signed `Offset32` immediate, so adjust the base
@@ -2338,6 +2364,22 @@ fn get_heap_addr(
     }
 }
 
+/// Whether `heap_addr` omits the bounds check of an access to the
+/// first `check_size` bytes after the address, relying on the guard
+/// pages to trap instead. This mirrors the legalization of `heap_addr`
+/// for 32-bit addresses.
+fn is_bounds_check_elided(heap: &ir::HeapData, check_size: u32) -> bool {
+    match heap.style {
+        ir::HeapStyle::Static { bound } => {
+            let bound: u64 = bound.into();
+            bound
+                .checked_sub(u64::from(check_size))
+                .map_or(false, |limit| limit >= 0xffff_ffff)
+        }
+        ir::HeapStyle::Dynamic { .. } => false,
+    }
+}
+
 /// Prepare for a load; factors out common functionality between load and load_extend operations.
 fn prepare_load<FE: FuncEnvironment + ?Sized>(
     memarg: &MemoryImmediate,
@@ -2346,16 +2388,18 @@ fn prepare_load<FE: FuncEnvironment + ?Sized>(
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<(MemFlags, Value, Offset32)> {
-    let (addr32, _) = state.pop1();
+    let (addr, _) = state.pop1();
 
     let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let (addr, offset) = split_offset(addr, memarg.offset, builder);
     let (base, offset) = get_heap_addr(
         heap,
-        addr32,
-        memarg.offset as u32,
+        addr,
+        offset,
         loaded_bytes,
         environ.pointer_type(),
         builder,
+        state,
     );
 
     // Note that we don't set `is_aligned` here, even if the load instruction's
@@ -2397,17 +2441,19 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<()> {
-    let ((addr32, _), (val, _)) = state.pop2();
+    let ((addr, _), (val, _)) = state.pop2();
     let val_ty = builder.func.dfg.value_type(val);
 
     let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let (addr, offset) = split_offset(addr, memarg.offset, builder);
     let (base, offset) = get_heap_addr(
         heap,
-        addr32,
-        memarg.offset as u32,
+        addr,
+        offset,
         mem_op_size(opcode, val_ty),
         environ.pointer_type(),
         builder,
+        state,
     );
     // See the comments in `prepare_load` about the flags.
     let mut flags = MemFlags::new();
@@ -2418,6 +2464,36 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Splits the offset of an access into the part added to the address
+/// here, and the 32-bit part left to `get_heap_addr`. Only the offsets
+/// of 64-bit memories may exceed `u32::MAX`.
+fn split_offset(addr: Value, offset: u64, builder: &mut FunctionBuilder) -> (Value, u32) {
+    match u32::try_from(offset) {
+        Ok(offset) => (addr, offset),
+        Err(_) => (checked_add_offset(addr, offset, builder), 0),
+    }
+}
+
+/// Adds `offset` to the 64-bit address `addr`, trapping if the sum
+/// overflows instead of wrapping around to a valid address.
+fn checked_add_offset(addr: Value, offset: u64, builder: &mut FunctionBuilder) -> Value {
+    let sum = builder.ins().iadd_imm(addr, offset as i64);
+    let overflow = builder.ins().icmp(IntCC::UnsignedLessThan, sum, addr);
+    builder
+        .ins()
+        .trapnz(overflow, ir::TrapCode::HeapOutOfBounds);
+    sum
+}
+
+/// Rejects the operators that call builtins taking 32-bit addresses on
+/// the 64-bit memory `heap`.
+fn check_memory32(op: &Operator, heap: ir::Heap, builder: &FunctionBuilder) -> WasmResult<()> {
+    if builder.func.heaps[heap].index_type == I64 {
+        return Err(wasm_unsupported!("{:?} on a 64-bit memory", op));
+    }
+    Ok(())
+}
+
 fn mem_op_size(opcode: ir::Opcode, ty: Type) -> u32 {
     match opcode {
         ir::Opcode::Istore8 | ir::Opcode::Sload8 | ir::Opcode::Uload8 => 1,
@@ -2438,6 +2514,7 @@ fn fold_atomic_mem_addr(
     linear_mem_addr: Value,
     memarg: &MemoryImmediate,
     access_ty: Type,
+    unaligned_trap: Option<ir::TrapCode>,
     builder: &mut FunctionBuilder,
 ) -> Value {
     let access_ty_bytes = access_ty.bytes();
@@ -2458,15 +2535,15 @@ fn fold_atomic_mem_addr(
         linear_mem_addr
     };
     assert!(access_ty_bytes == 4 || access_ty_bytes == 8);
-    let final_lma_misalignment = builder
-        .ins()
-        .band_imm(final_lma, i64::from(access_ty_bytes - 1));
-    let f = builder
-        .ins()
-        .ifcmp_imm(final_lma_misalignment, i64::from(0));
-    builder
-        .ins()
-        .trapif(IntCC::NotEqual, f, ir::TrapCode::HeapMisaligned);
+    if let Some(unaligned_trap) = unaligned_trap {
+        let final_lma_misalignment = builder
+            .ins()
+            .band_imm(final_lma, i64::from(access_ty_bytes - 1));
+        let f = builder
+            .ins()
+            .ifcmp_imm(final_lma_misalignment, i64::from(0));
+        builder.ins().trapif(IntCC::NotEqual, f, unaligned_trap);
+    }
     final_lma
 }
 
@@ -2482,20 +2559,25 @@ fn finalise_atomic_mem_addr<FE: FuncEnvironment + ?Sized>(
 ) -> WasmResult<Value> {
     // Check the alignment of `linear_mem_addr`.
     let access_ty_bytes = access_ty.bytes();
-    let final_lma = builder
-        .ins()
-        .iadd_imm(linear_mem_addr, memarg.offset as i64);
-    if access_ty_bytes != 1 {
-        assert!(access_ty_bytes == 2 || access_ty_bytes == 4 || access_ty_bytes == 8);
-        let final_lma_misalignment = builder
-            .ins()
-            .band_imm(final_lma, i64::from(access_ty_bytes - 1));
-        let f = builder
-            .ins()
-            .ifcmp_imm(final_lma_misalignment, i64::from(0));
+    let final_lma = if builder.func.dfg.value_type(linear_mem_addr) == I64 {
+        checked_add_offset(linear_mem_addr, memarg.offset, builder)
+    } else {
         builder
             .ins()
-            .trapif(IntCC::NotEqual, f, ir::TrapCode::HeapMisaligned);
+            .iadd_imm(linear_mem_addr, memarg.offset as i64)
+    };
+    match environ.unaligned_atomic_trap() {
+        Some(unaligned_trap) if access_ty_bytes != 1 => {
+            assert!(access_ty_bytes == 2 || access_ty_bytes == 4 || access_ty_bytes == 8);
+            let final_lma_misalignment = builder
+                .ins()
+                .band_imm(final_lma, i64::from(access_ty_bytes - 1));
+            let f = builder
+                .ins()
+                .ifcmp_imm(final_lma_misalignment, i64::from(0));
+            builder.ins().trapif(IntCC::NotEqual, f, unaligned_trap);
+        }
+        _ => {}
     }
 
     // Compute the final effective address.
@@ -2507,6 +2589,7 @@ fn finalise_atomic_mem_addr<FE: FuncEnvironment + ?Sized>(
         access_ty.bytes(),
         environ.pointer_type(),
         builder,
+        state,
     );
 
     let final_effective_address = builder.ins().iadd_imm(base, i64::from(offset));
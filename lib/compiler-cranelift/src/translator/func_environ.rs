@@ -101,6 +101,12 @@ pub trait FuncEnvironment: TargetEnvironment {
         ReturnMode::NormalReturns
     }
 
+    /// The trap code of the atomic accesses to an unaligned address, or
+    /// `None` not to check their alignment.
+    fn unaligned_atomic_trap(&self) -> Option<ir::TrapCode> {
+        Some(ir::TrapCode::HeapMisaligned)
+    }
+
     /// Set up the necessary preamble definitions in `func` to access the global variable
     /// identified by `index`.
     ///
@@ -454,11 +460,29 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Emit code at the beginning of every wasm function, after the
+    /// locals have been declared.
+    ///
+    /// The code can branch: the builder is left in the block the
+    /// function body follows.
+    fn translate_function_entry(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
+        // By default, don't emit anything.
+        Ok(())
+    }
+
+    /// Emit code right before every return of a wasm function, with
+    /// the return values already computed.
+    fn translate_function_exit(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+        // By default, don't emit anything.
+        Ok(())
+    }
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
-    /// the beginnings of loops.
-    fn translate_loop_header(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+    /// the beginnings of loops. The code can branch: the builder is
+    /// left in the block the loop body follows.
+    fn translate_loop_header(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
         // By default, don't emit anything.
         Ok(())
     }
@@ -253,6 +253,10 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FunctionIndex, (ir::FuncRef, usize)>,
+
+    /// The number of memory accesses whose bounds check could not be
+    /// elided because of their offset, for the performance diagnostics.
+    pub(crate) bounds_checks_not_elided: u64,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -279,6 +283,7 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            bounds_checks_not_elided: 0,
         }
     }
 
@@ -291,6 +296,7 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.bounds_checks_not_elided = 0;
     }
 
     /// Initialize the state for compiling a function with the given signature.
@@ -110,6 +110,7 @@ impl FuncTranslator {
         self.state.initialize(&builder.func.signature, exit_block);
 
         parse_local_decls(reader, &mut builder, num_params, environ)?;
+        environ.translate_function_entry(&mut builder)?;
         parse_function_body(
             module_translation_state,
             reader,
@@ -121,6 +122,13 @@ impl FuncTranslator {
         builder.finalize();
         Ok(())
     }
+
+    /// The number of memory accesses of the last translated function
+    /// whose bounds check could not be elided because their offset is
+    /// larger than the guard pages.
+    pub fn bounds_checks_not_elided(&self) -> u64 {
+        self.state.bounds_checks_not_elided
+    }
 }
 
 /// Declare local variables for the signature parameters that correspond to WebAssembly locals.
@@ -265,6 +273,7 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
 
             // TODO: look into what `state.reachable` check above does as well as `!builder.is_unreachable`, do we need that too for ref counting?
 
+            environ.translate_function_exit(builder.cursor())?;
             match environ.return_mode() {
                 ReturnMode::NormalReturns => {
                     let return_types = wasm_param_types(&builder.func.signature.returns, |i| {
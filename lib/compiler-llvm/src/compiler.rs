@@ -8,16 +8,17 @@ use inkwell::module::{Linkage, Module};
 use inkwell::targets::FileType;
 use inkwell::DLLStorageClass;
 use loupe::MemoryUsage;
-use rayon::iter::ParallelBridge;
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
+use std::time::Instant;
 use wasmer_compiler::{
-    Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection, CustomSectionProtection,
-    Dwarf, FunctionBodyData, ModuleMiddleware, ModuleTranslationState, RelocationTarget,
-    SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
+    Compilation, CompileError, CompileModuleInfo, CompileThreadPool, Compiler, CustomSection,
+    CustomSectionProtection, Dwarf, FunctionBodyData, ModuleMiddleware, ModuleMiddlewareChain,
+    ModuleTranslationState, RelocationTarget, SectionBody, SectionIndex, Symbol, SymbolRegistry,
+    Target,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
-use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
+use wasmer_types::{FunctionIndex, IndexType, LocalFunctionIndex, ModuleInfo, SignatureIndex};
 
 //use std::sync::Mutex;
 
@@ -26,12 +27,17 @@ use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
 #[derive(MemoryUsage)]
 pub struct LLVMCompiler {
     config: LLVM,
+    thread_pool: CompileThreadPool,
 }
 
 impl LLVMCompiler {
     /// Creates a new LLVM compiler
     pub fn new(config: LLVM) -> LLVMCompiler {
-        LLVMCompiler { config }
+        let thread_pool = CompileThreadPool::new("llvm", config.num_compile_threads);
+        LLVMCompiler {
+            config,
+            thread_pool,
+        }
     }
 
     /// Gets the config for this Compiler
@@ -82,12 +88,12 @@ impl LLVMCompiler {
         symbol_registry: &dyn SymbolRegistry,
         wasmer_metadata: &[u8],
     ) -> Result<Vec<u8>, CompileError> {
+        check_memories(&compile_info.module)?;
         let target_machine = self.config().target_machine(target);
         let ctx = Context::create();
 
-        // TODO: https:/github.com/rayon-rs/rayon/issues/822
-
-        let merged_bitcode = function_body_inputs.into_iter().par_bridge().map_init(
+        let merged_bitcode = self.thread_pool.map_init(
+            function_body_inputs.iter().collect(),
             || {
                 let target_machine = self.config().target_machine(target);
                 FuncTranslator::new(target_machine)
@@ -107,7 +113,8 @@ impl LLVMCompiler {
             },
         );
 
-        let trampolines_bitcode = compile_info.module.signatures.iter().par_bridge().map_init(
+        let trampolines_bitcode = self.thread_pool.map_init(
+            compile_info.module.signatures.iter().collect(),
             || {
                 let target_machine = self.config().target_machine(target);
                 FuncTrampoline::new(target_machine)
@@ -119,38 +126,41 @@ impl LLVMCompiler {
             },
         );
 
-        let dynamic_trampolines_bitcode =
-            compile_info.module.functions.iter().par_bridge().map_init(
-                || {
-                    let target_machine = self.config().target_machine(target);
-                    (
-                        FuncTrampoline::new(target_machine),
-                        &compile_info.module.signatures,
-                    )
-                },
-                |(func_trampoline, signatures), (i, sig)| {
-                    let sig = &signatures[*sig];
-                    let name = symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(i));
-                    let module =
-                        func_trampoline.dynamic_trampoline_to_module(sig, self.config(), &name)?;
-                    Ok(module.write_bitcode_to_memory().as_slice().to_vec())
-                },
-            );
+        let signatures = &compile_info.module.signatures;
+        let dynamic_trampolines_bitcode = self.thread_pool.map_init(
+            compile_info.module.functions.iter().collect(),
+            || {
+                let target_machine = self.config().target_machine(target);
+                FuncTrampoline::new(target_machine)
+            },
+            |func_trampoline, (i, sig)| {
+                let sig = &signatures[*sig];
+                let name = symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(i));
+                let module =
+                    func_trampoline.dynamic_trampoline_to_module(sig, self.config(), &name)?;
+                Ok(module.write_bitcode_to_memory().as_slice().to_vec())
+            },
+        );
 
-        let merged_bitcode = merged_bitcode
+        let bitcode = merged_bitcode
+            .into_iter()
             .chain(trampolines_bitcode)
             .chain(dynamic_trampolines_bitcode)
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_par_iter()
-            .reduce_with(|bc1, bc2| {
-                let ctx = Context::create();
-                let membuf = MemoryBuffer::create_from_memory_range(&bc1, "");
-                let m1 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
-                let membuf = MemoryBuffer::create_from_memory_range(&bc2, "");
-                let m2 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
-                m1.link_in_module(m2).unwrap();
-                m1.write_bitcode_to_memory().as_slice().to_vec()
-            });
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let link = |bc1: Vec<u8>, bc2: Vec<u8>| {
+            let ctx = Context::create();
+            let membuf = MemoryBuffer::create_from_memory_range(&bc1, "");
+            let m1 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
+            let membuf = MemoryBuffer::create_from_memory_range(&bc2, "");
+            let m2 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
+            m1.link_in_module(m2).unwrap();
+            m1.write_bitcode_to_memory().as_slice().to_vec()
+        };
+        let merged_bitcode = if self.thread_pool.is_current_thread() {
+            bitcode.into_iter().reduce(link)
+        } else {
+            bitcode.into_par_iter().reduce_with(link)
+        };
         let merged_module = if let Some(bc) = merged_bitcode {
             let membuf = MemoryBuffer::create_from_memory_range(&bc, "");
             Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap()
@@ -188,12 +198,47 @@ impl LLVMCompiler {
     }
 }
 
+/// Rejects the memories the translator doesn't support: the memory
+/// accesses are only emitted for a single memory, with 32-bit
+/// addresses.
+fn check_memories(module: &ModuleInfo) -> Result<(), CompileError> {
+    if module
+        .memories
+        .values()
+        .any(|ty| ty.index_type == IndexType::I64)
+    {
+        return Err(CompileError::UnsupportedFeature(
+            "64-bit memories".to_string(),
+        ));
+    }
+    if module.memories.len() > 1 {
+        return Err(CompileError::UnsupportedFeature(
+            "multiple memories".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 impl Compiler for LLVMCompiler {
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
     }
 
+    fn deterministic_id(&self) -> String {
+        let mut config = self.config.clone();
+        let middlewares = std::mem::take(&mut config.middlewares);
+        format!("llvm-{:?}-[{}]", config, middlewares.deterministic_id())
+    }
+
+    fn retains_wasm_bytes(&self) -> bool {
+        self.config.retain_wasm_bytes
+    }
+
+    fn emits_epoch_checks(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
+
     fn experimental_native_compile_module<'data, 'module>(
         &self,
         target: &Target,
@@ -205,14 +250,16 @@ impl Compiler for LLVMCompiler {
         // The metadata to inject into the wasmer_metadata section of the object file.
         wasmer_metadata: &[u8],
     ) -> Option<Result<Vec<u8>, CompileError>> {
-        Some(self.compile_native_object(
-            target,
-            compile_info,
-            module_translation,
-            function_body_inputs,
-            symbol_registry,
-            wasmer_metadata,
-        ))
+        Some(self.thread_pool.install(|| {
+            self.compile_native_object(
+                target,
+                compile_info,
+                module_translation,
+                function_body_inputs,
+                symbol_registry,
+                wasmer_metadata,
+            )
+        }))
     }
 
     /// Compile the module using LLVM, producing a compilation result with
@@ -224,22 +271,44 @@ impl Compiler for LLVMCompiler {
         module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
     ) -> Result<Compilation, CompileError> {
+        self.thread_pool.install(|| {
+            self.compile_module_in_pool(
+                target,
+                compile_info,
+                module_translation,
+                function_body_inputs,
+            )
+        })
+    }
+}
+
+impl LLVMCompiler {
+    /// Compile the module, in the thread pool the current thread
+    /// belongs to.
+    fn compile_module_in_pool<'data, 'module>(
+        &self,
+        target: &Target,
+        compile_info: &'module CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+    ) -> Result<Compilation, CompileError> {
+        let start = Instant::now();
         //let data = Arc::new(Mutex::new(0));
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
 
         let module = &compile_info.module;
+        check_memories(module)?;
 
         // TODO: merge constants in sections.
 
         let mut module_custom_sections = PrimaryMap::new();
         let mut frame_section_bytes = vec![];
         let mut frame_section_relocations = vec![];
-        let functions = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .par_iter()
+        let functions = self
+            .thread_pool
             .map_init(
+                function_body_inputs.iter().collect(),
                 || {
                     let target_machine = self.config().target_machine(target);
                     FuncTranslator::new(target_machine)
@@ -250,7 +319,7 @@ impl Compiler for LLVMCompiler {
                     func_translator.translate(
                         module,
                         module_translation,
-                        i,
+                        &i,
                         input,
                         self.config(),
                         memory_styles,
@@ -259,6 +328,7 @@ impl Compiler for LLVMCompiler {
                     )
                 },
             )
+            .into_iter()
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .map(|mut compiled_function| {
@@ -329,27 +399,23 @@ impl Compiler for LLVMCompiler {
             None
         };
 
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .par_iter()
+        let function_call_trampolines = self
+            .thread_pool
             .map_init(
+                module.signatures.values().collect(),
                 || {
                     let target_machine = self.config().target_machine(target);
                     FuncTrampoline::new(target_machine)
                 },
                 |func_trampoline, sig| func_trampoline.trampoline(sig, self.config(), ""),
             )
-            .collect::<Vec<_>>()
             .into_iter()
             .collect::<Result<PrimaryMap<_, _>, CompileError>>()?;
 
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .par_iter()
+        let dynamic_function_trampolines = self
+            .thread_pool
             .map_init(
+                module.imported_function_types().collect(),
                 || {
                     let target_machine = self.config().target_machine(target);
                     FuncTrampoline::new(target_machine)
@@ -358,6 +424,7 @@ impl Compiler for LLVMCompiler {
                     func_trampoline.dynamic_trampoline(&func_type, self.config(), "")
                 },
             )
+            .into_iter()
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
@@ -368,6 +435,7 @@ impl Compiler for LLVMCompiler {
             function_call_trampolines,
             dynamic_function_trampolines,
             dwarf,
-        ))
+        )
+        .with_compile_time(start.elapsed(), None))
     }
 }
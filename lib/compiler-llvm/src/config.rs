@@ -9,7 +9,7 @@ use loupe::MemoryUsage;
 use std::fmt::Debug;
 use std::sync::Arc;
 use target_lexicon::Architecture;
-use wasmer_compiler::{Compiler, CompilerConfig, ModuleMiddleware, Target, Triple};
+use wasmer_compiler::{Compiler, CompilerConfig, ModuleMiddleware, OptLevel, Target, Triple};
 use wasmer_types::{FunctionType, LocalFunctionIndex};
 
 /// The InkWell ModuleInfo type
@@ -42,8 +42,14 @@ pub trait LLVMCallbacks: Debug + Send + Sync {
 pub struct LLVM {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_verifier: bool,
+    pub(crate) enable_epoch_interruption: bool,
+    pub(crate) enable_call_depth_limit: bool,
+    pub(crate) trap_unaligned_atomics: bool,
+    pub(crate) num_compile_threads: Option<usize>,
+    pub(crate) retain_wasm_bytes: bool,
     #[loupe(skip)]
     pub(crate) opt_level: LLVMOptLevel,
+    pub(crate) optimize_for_size: bool,
     is_pic: bool,
     #[loupe(skip)]
     pub(crate) callbacks: Option<Arc<dyn LLVMCallbacks>>,
@@ -58,19 +64,70 @@ impl LLVM {
         Self {
             enable_nan_canonicalization: false,
             enable_verifier: false,
+            enable_epoch_interruption: false,
+            enable_call_depth_limit: false,
+            trap_unaligned_atomics: true,
+            num_compile_threads: None,
+            retain_wasm_bytes: false,
             opt_level: LLVMOptLevel::Aggressive,
+            optimize_for_size: false,
             is_pic: false,
             callbacks: None,
             middlewares: vec![],
         }
     }
 
+    /// Enable epoch-based interruption.
+    ///
+    /// When enabled, the generated code checks the epoch counter of
+    /// the store at every function entry and loop header.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
+    /// Enable the call depth limit.
+    ///
+    /// When enabled, the generated code counts the wasm frames active
+    /// in the store on entry and return of every function.
+    pub fn call_depth_limit(&mut self, enable: bool) -> &mut Self {
+        self.enable_call_depth_limit = enable;
+        self
+    }
+
+    /// Trap on the atomic accesses to unaligned addresses, as the
+    /// threads proposal requires. Enabled by default.
+    ///
+    /// When disabled, the alignment of the atomic accesses isn't
+    /// checked, which is only meant for testing.
+    pub fn trap_unaligned_atomics(&mut self, enable: bool) -> &mut Self {
+        self.trap_unaligned_atomics = enable;
+        self
+    }
+
+    /// Compile the functions, and the modules they are linked into,
+    /// in a thread pool of `threads` threads rather than in the global
+    /// thread pool, see [`CompilerConfig::num_compile_threads`].
+    pub fn num_compile_threads(&mut self, threads: usize) -> &mut Self {
+        self.num_compile_threads = Some(threads);
+        self
+    }
+
     /// The optimization levels when optimizing the IR.
     pub fn opt_level(&mut self, opt_level: LLVMOptLevel) -> &mut Self {
         self.opt_level = opt_level;
         self
     }
 
+    /// Optimize the functions for size rather than speed.
+    ///
+    /// The functions are marked `optsize` and `minsize`, and the
+    /// passes growing the code, such as the vectorizers, are skipped.
+    pub fn optimize_for_size(&mut self, enable: bool) -> &mut Self {
+        self.optimize_for_size = enable;
+        self
+    }
+
     /// Callbacks that will triggered in the different compilation
     /// phases in LLVM.
     pub fn callbacks(&mut self, callbacks: Option<Arc<dyn LLVMCallbacks>>) -> &mut Self {
@@ -213,6 +270,38 @@ impl CompilerConfig for LLVM {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
+    fn call_depth_limit(&mut self, enable: bool) {
+        self.enable_call_depth_limit = enable;
+    }
+
+    fn trap_unaligned_atomics(&mut self, enable: bool) {
+        self.trap_unaligned_atomics = enable;
+    }
+
+    fn num_compile_threads(&mut self, threads: usize) {
+        self.num_compile_threads = Some(threads);
+    }
+
+    fn retain_wasm_bytes(&mut self, retain: bool) {
+        self.retain_wasm_bytes = retain;
+    }
+
+    fn set_opt_level(&mut self, level: OptLevel) {
+        // `-Oz` optimizes the IR for size and generates the code like
+        // `-O2`.
+        let (opt_level, optimize_for_size) = match level {
+            OptLevel::None => (LLVMOptLevel::None, false),
+            OptLevel::Speed => (LLVMOptLevel::Aggressive, false),
+            OptLevel::Size => (LLVMOptLevel::Default, true),
+        };
+        self.opt_level = opt_level;
+        self.optimize_for_size = optimize_for_size;
+    }
+
     /// Transform it into the compiler.
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(LLVMCompiler::new(*self))
@@ -6,7 +6,7 @@ use super::{
     state::{ControlFrame, ExtraInfo, IfElseState, State},
 };
 use inkwell::{
-    attributes::AttributeLoc,
+    attributes::{Attribute, AttributeLoc},
     builder::Builder,
     context::Context,
     module::{Linkage, Module},
@@ -104,6 +104,17 @@ impl FuncTranslator {
         }
 
         func.add_attribute(AttributeLoc::Function, intrinsics.stack_probe);
+        if config.optimize_for_size {
+            // What clang emits for `-Oz`, the passes and the code
+            // generation honor these attributes.
+            for attribute in ["optsize", "minsize"] {
+                func.add_attribute(
+                    AttributeLoc::Function,
+                    self.ctx
+                        .create_enum_attribute(Attribute::get_named_enum_kind_id(attribute), 0),
+                );
+            }
+        }
         func.set_personality_function(intrinsics.personality);
         func.as_global_value().set_section(FUNCTION_SECTION);
         func.set_linkage(Linkage::DLLExport);
@@ -212,6 +223,13 @@ impl FuncTranslator {
             &func_attrs,
         );
 
+        if config.enable_epoch_interruption {
+            fcg.check_epoch_deadline();
+        }
+        if config.enable_call_depth_limit {
+            fcg.enter_call_depth();
+        }
+
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
             let op = reader.read_operator()?;
@@ -242,10 +260,14 @@ impl FuncTranslator {
         pass_manager.add_cfg_simplification_pass();
         pass_manager.add_reassociate_pass();
         pass_manager.add_loop_rotate_pass();
-        pass_manager.add_loop_unswitch_pass();
+        if !config.optimize_for_size {
+            pass_manager.add_loop_unswitch_pass();
+        }
         pass_manager.add_ind_var_simplify_pass();
         pass_manager.add_licm_pass();
-        pass_manager.add_loop_vectorize_pass();
+        if !config.optimize_for_size {
+            pass_manager.add_loop_vectorize_pass();
+        }
         pass_manager.add_instruction_combining_pass();
         pass_manager.add_sccp_pass();
         pass_manager.add_reassociate_pass();
@@ -257,7 +279,9 @@ impl FuncTranslator {
         pass_manager.add_instruction_combining_pass();
         pass_manager.add_reassociate_pass();
         pass_manager.add_cfg_simplification_pass();
-        pass_manager.add_slp_vectorize_pass();
+        if !config.optimize_for_size {
+            pass_manager.add_slp_vectorize_pass();
+        }
         pass_manager.add_early_cse_pass();
 
         pass_manager.run_on(&module);
@@ -1170,8 +1194,71 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             .into_pointer_value())
     }
 
-    fn trap_if_misaligned(&self, memarg: &MemoryImmediate, ptr: PointerValue<'ctx>) {
-        let align = memarg.align;
+    /// Calls the builtin trapping if the epoch deadline is reached or
+    /// the call interrupted, once the epoch counter reaches the
+    /// threshold of the epoch deadline.
+    fn check_epoch_deadline(&mut self) {
+        let (counter_ptr, deadline_ptr) = self.ctx.epoch_ptrs(self.intrinsics);
+        // The counter and the threshold are updated from outside of
+        // wasm, so they must be reloaded on every check.
+        let counter = self.builder.build_load(counter_ptr, "epoch");
+        counter
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let threshold = self.builder.build_load(deadline_ptr, "epoch_threshold");
+        threshold
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let reached = self.builder.build_int_compare(
+            IntPredicate::UGE,
+            counter.into_int_value(),
+            threshold.into_int_value(),
+            "",
+        );
+        let reached = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    reached.into(),
+                    self.intrinsics.i1_ty.const_int(0, false).into(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "epoch_continue_block");
+        let reached_block = self
+            .context
+            .append_basic_block(self.function, "epoch_deadline_reached_block");
+        self.builder
+            .build_conditional_branch(reached, reached_block, continue_block);
+
+        self.builder.position_at_end(reached_block);
+        let reached_fn_ptr = self.ctx.epoch_deadline_reached(self.intrinsics);
+        let callable_func = inkwell::values::CallableValue::try_from(reached_fn_ptr).unwrap();
+        self.builder
+            .build_call(callable_func, &[self.ctx.basic().into()], "");
+        self.builder.build_unconditional_branch(continue_block);
+
+        self.builder.position_at_end(continue_block);
+    }
+
+    /// Traps with `TrapCode::UnalignedAtomic` if `ptr` isn't aligned on
+    /// `align` bytes, the size of the atomic access.
+    fn trap_if_misaligned(&self, ptr: PointerValue<'ctx>, align: u8) {
+        if !self.config.trap_unaligned_atomics || align == 1 {
+            return;
+        }
         let value = self
             .builder
             .build_ptr_to_int(ptr, self.intrinsics.i64_ty, "");
@@ -1218,9 +1305,76 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(continue_block);
     }
 
+    /// Decrements the call depth left, trapping if it is already
+    /// exhausted.
+    fn enter_call_depth(&mut self) {
+        let left_ptr = self.ctx.call_depth_left_ptr(self.intrinsics);
+        let left = self
+            .builder
+            .build_load(left_ptr, "call_depth_left")
+            .into_int_value();
+        let exhausted =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, left, self.intrinsics.i64_zero, "");
+        let exhausted = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    exhausted.into(),
+                    self.intrinsics.i1_ty.const_int(0, false).into(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "call_depth_continue_block");
+        let exceeded_block = self
+            .context
+            .append_basic_block(self.function, "call_depth_exceeded_trap_block");
+        self.builder
+            .build_conditional_branch(exhausted, exceeded_block, continue_block);
+
+        self.builder.position_at_end(exceeded_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_call_depth_exceeded.into()],
+            "throw",
+        );
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(continue_block);
+        let left = self
+            .builder
+            .build_int_sub(left, self.intrinsics.i64_ty.const_int(1, false), "");
+        self.builder.build_store(left_ptr, left);
+    }
+
+    /// Increments the call depth left, when returning.
+    fn exit_call_depth(&mut self) {
+        let left_ptr = self.ctx.call_depth_left_ptr(self.intrinsics);
+        let left = self
+            .builder
+            .build_load(left_ptr, "call_depth_left")
+            .into_int_value();
+        let left = self
+            .builder
+            .build_int_add(left, self.intrinsics.i64_ty.const_int(1, false), "");
+        self.builder.build_store(left_ptr, left);
+    }
+
     fn finalize(&mut self, wasm_fn_type: &FunctionType) -> Result<(), CompileError> {
         let func_type = self.function.get_type();
 
+        if self.config.enable_call_depth_limit {
+            self.exit_call_depth();
+        }
+
         let results = self.state.popn_save_extra(wasm_fn_type.results().len())?;
         let results = results
             .into_iter()
@@ -1516,6 +1670,10 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
+                if self.config.enable_epoch_interruption {
+                    self.check_epoch_deadline();
+                }
+
                 self.state.push_loop(loop_body, loop_next, loop_phis, phis);
             }
             Operator::Br { relative_depth } => {
@@ -8962,7 +9120,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let result = self.builder.build_load(effective_address, "");
                 let load = result.as_instruction_value().unwrap();
                 self.annotate_user_memaccess(memory_index, memarg, 4, load)?;
@@ -8980,7 +9138,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let result = self.builder.build_load(effective_address, "");
                 let load = result.as_instruction_value().unwrap();
                 self.annotate_user_memaccess(memory_index, memarg, 8, load)?;
@@ -8998,7 +9156,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_result = self
                     .builder
                     .build_load(effective_address, "")
@@ -9022,7 +9180,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_result = self
                     .builder
                     .build_load(effective_address, "")
@@ -9046,7 +9204,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_result = self
                     .builder
                     .build_load(effective_address, "")
@@ -9070,7 +9228,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_result = self
                     .builder
                     .build_load(effective_address, "")
@@ -9094,7 +9252,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_result = self
                     .builder
                     .build_load(effective_address, "")
@@ -9119,7 +9277,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let store = self.builder.build_store(effective_address, value);
                 self.annotate_user_memaccess(memory_index, memarg, 4, store)?;
                 store
@@ -9137,7 +9295,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let store = self.builder.build_store(effective_address, value);
                 self.annotate_user_memaccess(memory_index, memarg, 8, store)?;
                 store
@@ -9155,7 +9313,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9177,7 +9335,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9198,7 +9356,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -9219,7 +9377,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9254,7 +9412,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9289,7 +9447,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9318,7 +9476,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9353,7 +9511,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9388,7 +9546,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -9423,7 +9581,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9452,7 +9610,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9487,7 +9645,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9522,7 +9680,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9551,7 +9709,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9586,7 +9744,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9621,7 +9779,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -9656,7 +9814,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9685,7 +9843,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9720,7 +9878,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9755,7 +9913,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9784,7 +9942,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9819,7 +9977,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9854,7 +10012,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -9889,7 +10047,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -9918,7 +10076,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -9953,7 +10111,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -9988,7 +10146,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10020,7 +10178,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -10055,7 +10213,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -10090,7 +10248,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -10125,7 +10283,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10154,7 +10312,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -10189,7 +10347,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -10224,7 +10382,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10253,7 +10411,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -10288,7 +10446,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -10323,7 +10481,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -10358,7 +10516,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10387,7 +10545,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -10422,7 +10580,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -10457,7 +10615,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10486,7 +10644,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i8_ty, "");
@@ -10521,7 +10679,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i16_ty, "");
@@ -10556,7 +10714,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_value =
                     self.builder
                         .build_int_truncate(value, self.intrinsics.i32_ty, "");
@@ -10591,7 +10749,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_atomicrmw(
@@ -10623,7 +10781,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_cmp = self
                     .builder
                     .build_int_truncate(cmp, self.intrinsics.i8_ty, "");
@@ -10670,7 +10828,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_cmp = self
                     .builder
                     .build_int_truncate(cmp, self.intrinsics.i16_ty, "");
@@ -10717,7 +10875,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let old = self
                     .builder
                     .build_cmpxchg(
@@ -10751,7 +10909,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     1,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 1);
                 let narrow_cmp = self
                     .builder
                     .build_int_truncate(cmp, self.intrinsics.i8_ty, "");
@@ -10798,7 +10956,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     2,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 2);
                 let narrow_cmp = self
                     .builder
                     .build_int_truncate(cmp, self.intrinsics.i16_ty, "");
@@ -10845,7 +11003,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     4,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 4);
                 let narrow_cmp = self
                     .builder
                     .build_int_truncate(cmp, self.intrinsics.i32_ty, "");
@@ -10892,7 +11050,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     offset,
                     8,
                 )?;
-                self.trap_if_misaligned(memarg, effective_address);
+                self.trap_if_misaligned(effective_address, 8);
                 let old = self
                     .builder
                     .build_cmpxchg(
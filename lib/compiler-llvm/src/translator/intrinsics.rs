@@ -217,6 +217,7 @@ pub struct Intrinsics<'ctx> {
     pub trap_integer_division_by_zero: BasicValueEnum<'ctx>,
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
+    pub trap_call_depth_exceeded: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
 
     pub experimental_stackmap: FunctionValue<'ctx>,
@@ -257,6 +258,7 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory32_grow_ptr_ty: PointerType<'ctx>,
     pub memory32_size_ptr_ty: PointerType<'ctx>,
     pub imported_memory32_size_ptr_ty: PointerType<'ctx>,
+    pub epoch_deadline_reached_ptr_ty: PointerType<'ctx>,
 
     // Pointer to the VM.
     pub ctx_ptr_ty: PointerType<'ctx>,
@@ -781,6 +783,9 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_call_depth_exceeded: i32_ty
+                .const_int(TrapCode::CallDepthExceeded as _, false)
+                .as_basic_value_enum(),
 
             experimental_stackmap: module.add_function(
                 "llvm.experimental.stackmap",
@@ -1039,6 +1044,9 @@ impl<'ctx> Intrinsics<'ctx> {
             imported_memory32_size_ptr_ty: i32_ty
                 .fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false)
                 .ptr_type(AddressSpace::Generic),
+            epoch_deadline_reached_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty_basic_md], false)
+                .ptr_type(AddressSpace::Generic),
 
             ctx_ptr_ty,
         };
@@ -1099,6 +1107,9 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_epoch_ptrs: Option<(PointerValue<'ctx>, PointerValue<'ctx>)>,
+    cached_epoch_deadline_reached: Option<PointerValue<'ctx>>,
+    cached_call_depth_left_ptr: Option<PointerValue<'ctx>>,
 
     offsets: VMOffsets,
 }
@@ -1124,6 +1135,9 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_epoch_ptrs: None,
+            cached_epoch_deadline_reached: None,
+            cached_call_depth_left_ptr: None,
 
             // TODO: pointer width
             offsets: VMOffsets::new(8, &wasm_module),
@@ -1658,6 +1672,91 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         })
     }
 
+    /// Returns the pointers to the epoch counter and the epoch deadline,
+    /// whose first field is the threshold compared with the counter.
+    pub fn epoch_ptrs(
+        &mut self,
+        intrinsics: &Intrinsics<'ctx>,
+    ) -> (PointerValue<'ctx>, PointerValue<'ctx>) {
+        let (cached_epoch_ptrs, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_epoch_ptrs,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_epoch_ptrs.get_or_insert_with(|| {
+            let load_ptr = |offset: u32| {
+                let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+                let ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+                let ptr_ptr = cache_builder
+                    .build_bitcast(
+                        ptr_ptr,
+                        intrinsics.i64_ptr_ty.ptr_type(AddressSpace::Generic),
+                        "",
+                    )
+                    .into_pointer_value();
+                cache_builder.build_load(ptr_ptr, "").into_pointer_value()
+            };
+            (
+                load_ptr(offsets.vmctx_epoch_counter_ptr()),
+                load_ptr(offsets.vmctx_epoch_deadline_ptr()),
+            )
+        })
+    }
+
+    /// Returns the pointer to the builtin called once the epoch counter
+    /// reaches the threshold of the epoch deadline.
+    pub fn epoch_deadline_reached(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_epoch_deadline_reached, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_epoch_deadline_reached,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_epoch_deadline_reached.get_or_insert_with(|| {
+            let offset = offsets
+                .vmctx_builtin_function(VMBuiltinFunctionIndex::get_epoch_deadline_reached_index());
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let fn_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let fn_ptr_ptr = cache_builder
+                .build_bitcast(
+                    fn_ptr_ptr,
+                    intrinsics
+                        .epoch_deadline_reached_ptr_ty
+                        .ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(fn_ptr_ptr, "")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns the pointer to the call depth left.
+    pub fn call_depth_left_ptr(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_call_depth_left_ptr, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_call_depth_left_ptr,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_call_depth_left_ptr.get_or_insert_with(|| {
+            let offset = intrinsics
+                .i32_ty
+                .const_int(offsets.vmctx_call_depth_left_ptr().into(), false);
+            let ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let ptr_ptr = cache_builder
+                .build_bitcast(
+                    ptr_ptr,
+                    intrinsics.i64_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder.build_load(ptr_ptr, "").into_pointer_value()
+        })
+    }
+
     pub fn get_offsets(&self) -> &VMOffsets {
         &self.offsets
     }
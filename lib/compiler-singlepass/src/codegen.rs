@@ -14,18 +14,19 @@ use wasmer_compiler::wasmparser::{Operator, Type as WpType, TypeOrFuncType as Wp
 #[cfg(feature = "unwind")]
 use wasmer_compiler::CompiledFunctionUnwindInfo;
 use wasmer_compiler::{
-    CallingConvention, CompiledFunction, CompiledFunctionFrameInfo, FunctionBody, FunctionBodyData,
-    Relocation, RelocationTarget, SectionIndex,
+    CallingConvention, CodeOffset, CompiledFunction, CompiledFunctionFrameInfo, FunctionBody,
+    FunctionBodyData, PerfDiagnostic, PerfDiagnosticCode, Relocation, RelocationTarget,
+    SectionIndex, StackMap,
 };
 use wasmer_types::{
     entity::{EntityRef, PrimaryMap},
     FunctionType,
 };
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, LocalFunctionIndex, LocalMemoryIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type,
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, SignatureIndex,
+    TableIndex, Type,
 };
-use wasmer_vm::{MemoryStyle, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
+use wasmer_vm::{AccessKind, MemoryStyle, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
 
 /// The singlepass per-function code generator.
 pub struct FuncGen<'a, M: Machine> {
@@ -88,6 +89,126 @@ pub struct FuncGen<'a, M: Machine> {
 
     /// Calling convention to use.
     calling_convention: CallingConvention,
+
+    /// Counters backing the performance diagnostics.
+    perf_counters: PerfCounters,
+
+    /// Locations of the values of the value stack holding references,
+    /// when stack maps are enabled.
+    ref_locations: Vec<Location<M::GPR, M::SIMD>>,
+
+    /// The stack maps of the calls emitted so far.
+    stack_maps: Vec<StackMap>,
+}
+
+/// Above this many values spilled to the stack, a function is
+/// reported with `PerfDiagnosticCode::SpilledValues`.
+const SPILLED_VALUES_THRESHOLD: u64 = 32;
+
+/// Above this many bytes of stack frame, a function is reported with
+/// `PerfDiagnosticCode::LargeStackFrame`.
+const LARGE_STACK_FRAME_THRESHOLD: u64 = 16 * 1024;
+
+/// A function is reported with `PerfDiagnosticCode::SoftfloatDensity`
+/// when it has at least this many floating point operations lowered to
+/// software sequences...
+const SOFTFLOAT_OPERATORS_THRESHOLD: u64 = 16;
+
+/// ... and they make up at least this percentage of its operators.
+const SOFTFLOAT_DENSITY_THRESHOLD: u64 = 25;
+
+/// What the performance diagnostics of a function are computed from.
+#[derive(Debug, Default)]
+struct PerfCounters {
+    /// The number of operators of the function.
+    operators: u64,
+    /// The number of floating point operators that have no direct
+    /// machine instruction, see `is_softfloat_operator`.
+    softfloat_operators: u64,
+    /// The number of values put on the stack because no register was
+    /// available.
+    spilled_values: u64,
+    /// The highest stack offset reached, i.e. the size of the frame.
+    max_stack_offset: usize,
+}
+
+/// Whether singlepass lowers `op` to a branchy software sequence
+/// rather than to a single machine instruction: NaN-aware minimum and
+/// maximum, checked and saturating float-to-int truncations, and
+/// unsigned 64-bit int-to-float conversions.
+fn is_softfloat_operator(op: &Operator) -> bool {
+    matches!(
+        op,
+        Operator::F32Min
+            | Operator::F32Max
+            | Operator::F64Min
+            | Operator::F64Max
+            | Operator::I32TruncF32S
+            | Operator::I32TruncF32U
+            | Operator::I32TruncF64S
+            | Operator::I32TruncF64U
+            | Operator::I64TruncF32S
+            | Operator::I64TruncF32U
+            | Operator::I64TruncF64S
+            | Operator::I64TruncF64U
+            | Operator::I32TruncSatF32S
+            | Operator::I32TruncSatF32U
+            | Operator::I32TruncSatF64S
+            | Operator::I32TruncSatF64U
+            | Operator::I64TruncSatF32S
+            | Operator::I64TruncSatF32U
+            | Operator::I64TruncSatF64S
+            | Operator::I64TruncSatF64U
+            | Operator::F32ConvertI64U
+            | Operator::F64ConvertI64U
+    )
+}
+
+/// Returns the memory, the static offset, the size and the kind of the
+/// memory access of `op` if it's a load or a store, see
+/// [`Singlepass::memory_access_tracing`].
+fn memory_access(op: &Operator) -> Option<(u32, u32, u32, AccessKind)> {
+    let (memarg, len, kind) = match op {
+        Operator::I32Load8S { memarg }
+        | Operator::I32Load8U { memarg }
+        | Operator::I64Load8S { memarg }
+        | Operator::I64Load8U { memarg }
+        | Operator::I32AtomicLoad8U { memarg }
+        | Operator::I64AtomicLoad8U { memarg } => (memarg, 1, AccessKind::Load),
+        Operator::I32Load16S { memarg }
+        | Operator::I32Load16U { memarg }
+        | Operator::I64Load16S { memarg }
+        | Operator::I64Load16U { memarg }
+        | Operator::I32AtomicLoad16U { memarg }
+        | Operator::I64AtomicLoad16U { memarg } => (memarg, 2, AccessKind::Load),
+        Operator::I32Load { memarg }
+        | Operator::F32Load { memarg }
+        | Operator::I64Load32S { memarg }
+        | Operator::I64Load32U { memarg }
+        | Operator::I32AtomicLoad { memarg }
+        | Operator::I64AtomicLoad32U { memarg } => (memarg, 4, AccessKind::Load),
+        Operator::I64Load { memarg }
+        | Operator::F64Load { memarg }
+        | Operator::I64AtomicLoad { memarg } => (memarg, 8, AccessKind::Load),
+        Operator::I32Store8 { memarg }
+        | Operator::I64Store8 { memarg }
+        | Operator::I32AtomicStore8 { memarg }
+        | Operator::I64AtomicStore8 { memarg } => (memarg, 1, AccessKind::Store),
+        Operator::I32Store16 { memarg }
+        | Operator::I64Store16 { memarg }
+        | Operator::I32AtomicStore16 { memarg }
+        | Operator::I64AtomicStore16 { memarg } => (memarg, 2, AccessKind::Store),
+        Operator::I32Store { memarg }
+        | Operator::F32Store { memarg }
+        | Operator::I64Store32 { memarg }
+        | Operator::I32AtomicStore { memarg }
+        | Operator::I64AtomicStore32 { memarg } => (memarg, 4, AccessKind::Store),
+        Operator::I64Store { memarg }
+        | Operator::F64Store { memarg }
+        | Operator::I64AtomicStore { memarg } => (memarg, 8, AccessKind::Store),
+        _ => return None,
+    };
+    Some((memarg.memory, memarg.offset as u32, len, kind))
 }
 
 struct SpecialLabelSet {
@@ -97,6 +218,7 @@ struct SpecialLabelSet {
     table_access_oob: Label,
     indirect_call_null: Label,
     bad_signature: Label,
+    call_depth_exceeded: Label,
 }
 
 /// Metadata about a floating-point value.
@@ -283,6 +405,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             } else {
                 self.stack_offset.0 += 8;
                 delta_stack_offset += 8;
+                self.perf_counters.spilled_values += 1;
+                self.perf_counters.max_stack_offset =
+                    cmp::max(self.perf_counters.max_stack_offset, self.stack_offset.0);
                 self.machine.local_on_stack(self.stack_offset.0 as i32)
             };
             if let Location::GPR(x) = loc {
@@ -295,6 +420,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.state.stack_values.push(mv.clone());
             }
             self.state.wasm_stack.push(WasmAbstractValue::Runtime);
+            if self.config.enable_stack_maps && (*ty == WpType::ExternRef || *ty == WpType::FuncRef)
+            {
+                self.ref_locations.push(loc);
+            }
             ret.push(loc);
         }
 
@@ -315,6 +444,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         let mut delta_stack_offset: usize = 0;
 
         for loc in locs.iter().rev() {
+            if self.config.enable_stack_maps {
+                self.ref_locations.retain(|x| x != loc);
+            }
             match *loc {
                 Location::GPR(ref x) => {
                     self.machine.release_gpr(*x);
@@ -355,6 +487,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         let locs: &[Location<M::GPR, M::SIMD>] = &self.value_stack[stack_depth..];
 
         for loc in locs.iter().rev() {
+            if self.config.enable_stack_maps {
+                self.ref_locations.retain(|x| x != loc);
+            }
             match *loc {
                 Location::GPR(ref x) => {
                     self.machine.release_gpr(*x);
@@ -393,6 +528,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
     fn release_locations_only_regs(&mut self, locs: &[Location<M::GPR, M::SIMD>]) {
         for loc in locs.iter().rev() {
+            if self.config.enable_stack_maps {
+                self.ref_locations.retain(|x| x != loc);
+            }
             match *loc {
                 Location::GPR(ref x) => {
                     self.machine.release_gpr(*x);
@@ -414,6 +552,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         let mut delta_stack_offset: usize = 0;
 
         for loc in locs.iter().rev() {
+            if self.config.enable_stack_maps {
+                self.ref_locations.retain(|x| x != loc);
+            }
             if let Location::Memory(y, x) = *loc {
                 if y == self.machine.local_pointer() {
                     if x >= 0 {
@@ -481,9 +622,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         calling_convention: CallingConvention,
     ) -> Vec<Location<M::GPR, M::SIMD>> {
         // How many machine stack slots will all the locals use?
-        let num_mem_slots = (0..n)
-            .filter(|&x| self.machine.is_local_on_stack(x))
-            .count();
+        let num_mem_slots = (0..n).filter(|&x| self.is_local_on_stack(x)).count();
 
         // Total size (in bytes) of the pre-allocated "static area" for this function's
         // locals and callee-saved registers.
@@ -493,7 +632,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         // Keep this consistent with the "Save callee-saved registers" code below.
         for i in 0..n {
             // If a local is not stored on stack, then it is allocated to a callee-saved register.
-            if !self.machine.is_local_on_stack(i) {
+            if !self.is_local_on_stack(i) {
                 static_area_size += 8;
             }
         }
@@ -509,7 +648,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
         // Now we can determine concrete locations for locals.
         let locations: Vec<Location<M::GPR, M::SIMD>> = (0..n)
-            .map(|i| self.machine.get_local_location(i, callee_saved_regs_size))
+            .map(|i| self.get_local_location(i, callee_saved_regs_size))
             .collect();
 
         // Add size of locals on stack.
@@ -631,6 +770,26 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         locations
     }
 
+    /// Whether the local `idx` is stored on the stack rather than in a
+    /// callee-saved register. With stack maps, all the locals are, so
+    /// that the references they hold are found in memory during calls.
+    fn is_local_on_stack(&self, idx: usize) -> bool {
+        self.config.enable_stack_maps || self.machine.is_local_on_stack(idx)
+    }
+
+    fn get_local_location(
+        &mut self,
+        idx: usize,
+        callee_saved_regs_size: usize,
+    ) -> Location<M::GPR, M::SIMD> {
+        if self.config.enable_stack_maps {
+            self.machine
+                .local_on_stack((callee_saved_regs_size + (idx + 1) * 8) as i32)
+        } else {
+            self.machine.get_local_location(idx, callee_saved_regs_size)
+        }
+    }
+
     fn finalize_locals(&mut self, calling_convention: CallingConvention) {
         // Unwind stack to the "save area".
         self.machine
@@ -718,6 +877,55 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         );
     }
 
+    /// Records the stack map of the call that was just emitted by
+    /// `emit_call_native`, after saving the used registers
+    /// (`used_gpr_stack` bytes for the GPRs, `used_stack` bytes in
+    /// total) and reserving `call_stack` bytes for the arguments.
+    fn record_stack_map(
+        &mut self,
+        used_gprs: &[M::GPR],
+        used_gpr_stack: usize,
+        used_stack: usize,
+        call_stack: usize,
+    ) {
+        let frame_stack = self.machine.round_stack_adjust(self.get_stack_offset());
+        let below_gprs = used_stack - used_gpr_stack + self.machine.round_stack_adjust(call_stack);
+        // The distance between the stack pointer at the call and the
+        // base of the frame.
+        let depth = frame_stack + used_gpr_stack + below_gprs;
+        let local_pointer = self.machine.local_pointer();
+
+        let ref_locals = self
+            .locals
+            .iter()
+            .zip(self.local_types.iter())
+            .filter(|(_, ty)| **ty == WpType::ExternRef || **ty == WpType::FuncRef)
+            .map(|(loc, _)| *loc);
+        let mut live_slots = ref_locals
+            .chain(self.ref_locations.iter().cloned())
+            .filter_map(|loc| match loc {
+                Location::Memory(reg, offset) if reg == local_pointer => {
+                    Some(depth - (-offset) as usize)
+                }
+                // Registers are pushed from the highest address down.
+                Location::GPR(gpr) => used_gprs
+                    .iter()
+                    .position(|x| *x == gpr)
+                    .map(|k| below_gprs + used_gpr_stack - 8 * (k + 1)),
+                _ => None,
+            })
+            .map(|slot| slot as u32)
+            .collect::<Vec<_>>();
+        live_slots.sort_unstable();
+        live_slots.dedup();
+
+        self.stack_maps.push(StackMap {
+            code_offset: self.machine.assembler_get_offset().0 as CodeOffset,
+            mapped_bytes: depth as u32,
+            live_slots,
+        });
+    }
+
     /// Emits a Native ABI call sequence.
     ///
     /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
@@ -746,7 +954,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
         // Save used GPRs. Preserve correct stack alignment
         let used_gprs = self.machine.get_used_gprs();
-        let mut used_stack = self.machine.push_used_gpr(&used_gprs);
+        let used_gpr_stack = self.machine.push_used_gpr(&used_gprs);
+        let mut used_stack = used_gpr_stack;
         for r in used_gprs.iter() {
             let content = self.state.register_values[self.machine.index_from_gpr(*r).0].clone();
             if content == MachineValue::Undefined {
@@ -904,6 +1113,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 .wasm_offset_to_target_offset
                 .insert(self.state.wasm_inst_offset, SuspendOffset::Call(offset));
         }
+        if self.config.enable_stack_maps {
+            self.record_stack_map(
+                &used_gprs,
+                used_gpr_stack,
+                used_stack,
+                stack_offset + stack_padding,
+            );
+        }
 
         // Restore stack.
         if stack_offset + stack_padding > 0 {
@@ -943,6 +1160,82 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         Ok(())
     }
 
+    /// Reports the access of the load or store about to be emitted to
+    /// the memory access tracer of the instance, its address being on
+    /// the value stack, under the value to store if any.
+    fn emit_memory_access_trace(
+        &mut self,
+        memory: u32,
+        offset: u32,
+        len: u32,
+        kind: AccessKind,
+    ) -> Result<(), CodegenError> {
+        let depth = match kind {
+            AccessKind::Load => 1,
+            AccessKind::Store => 2,
+        };
+        // The builtin takes a 64-bit address: zero-extend the 32-bit one
+        // where it is, its upper half being unused by the access.
+        let addr = match self.value_stack[self.value_stack.len() - depth] {
+            Location::Imm32(addr) => Location::Imm64(addr as u64),
+            Location::GPR(gpr) => {
+                self.machine
+                    .move_location(Size::S32, Location::GPR(gpr), Location::GPR(gpr));
+                Location::GPR(gpr)
+            }
+            Location::Memory(base, addr_offset) => {
+                self.machine.move_location(
+                    Size::S32,
+                    Location::Imm32(0),
+                    Location::Memory(base, addr_offset + 4),
+                );
+                Location::Memory(base, addr_offset)
+            }
+            addr => {
+                return Err(CodegenError {
+                    message: format!("emit_memory_access_trace: unexpected address {:?}", addr),
+                })
+            }
+        };
+
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets
+                    .vmctx_builtin_function(VMBuiltinFunctionIndex::get_memory_access_trace_index())
+                    as i32,
+            ),
+            Location::GPR(self.machine.get_grp_for_call()),
+        );
+
+        self.emit_call_native(
+            |this| {
+                this.machine
+                    .emit_call_register(this.machine.get_grp_for_call());
+            },
+            // [vmctx, memory, addr, offset, len, kind]
+            [
+                Location::Imm32(memory),
+                addr,
+                Location::Imm32(offset),
+                Location::Imm32(len),
+                Location::Imm32(kind as u32),
+            ]
+            .iter()
+            .cloned(),
+            [
+                WpType::I32,
+                WpType::I64,
+                WpType::I32,
+                WpType::I32,
+                WpType::I32,
+            ]
+            .iter()
+            .cloned(),
+        )
+    }
+
     /// Emits a Native ABI call sequence, specialized for labels as the call target.
     fn _emit_call_native_label<
         I: Iterator<Item = Location<M::GPR, M::SIMD>>,
@@ -961,24 +1254,27 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         Ok(())
     }
 
-    /// Emits a memory operation.
-    fn op_memory<F: FnOnce(&mut Self, bool, bool, i32, Label)>(&mut self, cb: F) {
-        let need_check = match self.memory_styles[MemoryIndex::new(0)] {
+    /// Emits a memory operation on the memory `memory`.
+    fn op_memory<F: FnOnce(&mut Self, bool, bool, i32, Label)>(&mut self, memory: u32, cb: F) {
+        let memory_index = MemoryIndex::new(memory as usize);
+        let need_check = match self.memory_styles[memory_index] {
             MemoryStyle::Static { .. } => false,
             MemoryStyle::Dynamic { .. } => true,
         };
 
-        let offset = if self.module.num_imported_memories != 0 {
-            self.vmoffsets
-                .vmctx_vmmemory_import_definition(MemoryIndex::new(0))
-        } else {
-            self.vmoffsets
-                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0))
+        let local_memory_index = self.module.local_memory_index(memory_index);
+        let offset = match local_memory_index {
+            Some(local_memory_index) => {
+                self.vmoffsets.vmctx_vmmemory_definition(local_memory_index)
+            }
+            None => self
+                .vmoffsets
+                .vmctx_vmmemory_import_definition(memory_index),
         };
         cb(
             self,
             need_check,
-            self.module.num_imported_memories != 0,
+            local_memory_index.is_none(),
             offset as i32,
             self.special_labels.heap_access_oob,
         );
@@ -998,6 +1294,146 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         id
     }
 
+    /// Emits a check of the epoch counter against the threshold of the
+    /// epoch deadline, calling the builtin trapping if the deadline is
+    /// reached or the call interrupted once the counter reaches it.
+    fn emit_epoch_check(&mut self) -> Result<(), CodegenError> {
+        let counter = self.machine.acquire_temp_gpr().unwrap();
+        let threshold = self.machine.acquire_temp_gpr().unwrap();
+
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_counter_ptr() as i32,
+            ),
+            Location::GPR(counter),
+        );
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(counter, 0),
+            Location::GPR(counter),
+        );
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_deadline_ptr() as i32,
+            ),
+            Location::GPR(threshold),
+        );
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(threshold, 0),
+            Location::GPR(threshold),
+        );
+        let continue_label = self.machine.get_label();
+        self.machine
+            .location_cmp(Size::S64, Location::GPR(counter), Location::GPR(threshold));
+        self.machine.jmp_on_above(continue_label);
+
+        self.machine.release_gpr(threshold);
+        self.machine.release_gpr(counter);
+
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets.vmctx_builtin_function(
+                    VMBuiltinFunctionIndex::get_epoch_deadline_reached_index(),
+                ) as i32,
+            ),
+            Location::GPR(self.machine.get_grp_for_call()),
+        );
+        self.emit_call_native(
+            |this| {
+                this.machine
+                    .emit_call_register(this.machine.get_grp_for_call());
+            },
+            // [vmctx]
+            iter::empty(),
+            iter::empty(),
+        )?;
+
+        self.machine.emit_label(continue_label);
+        Ok(())
+    }
+
+    /// Emits the decrement of the call depth left, trapping if it is
+    /// already exhausted.
+    fn emit_call_depth_enter(&mut self) {
+        let left_ptr = self.machine.acquire_temp_gpr().unwrap();
+        let left = self.machine.acquire_temp_gpr().unwrap();
+
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets.vmctx_call_depth_left_ptr() as i32,
+            ),
+            Location::GPR(left_ptr),
+        );
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(left_ptr, 0),
+            Location::GPR(left),
+        );
+        self.machine
+            .location_cmp(Size::S64, Location::Imm32(0), Location::GPR(left));
+        self.machine
+            .jmp_on_equal(self.special_labels.call_depth_exceeded);
+        self.machine
+            .location_sub(Size::S64, Location::Imm32(1), Location::GPR(left), false);
+        self.machine.move_location(
+            Size::S64,
+            Location::GPR(left),
+            Location::Memory(left_ptr, 0),
+        );
+
+        self.machine.release_gpr(left);
+        self.machine.release_gpr(left_ptr);
+    }
+
+    /// Emits the increment of the call depth left, when returning.
+    fn emit_call_depth_exit(&mut self) {
+        // The return value is already in its register, which must not
+        // be used as a temporary.
+        let ret = self.machine.get_gpr_for_ret();
+        let reserve_ret = !self.machine.get_used_gprs().contains(&ret);
+        if reserve_ret {
+            self.machine.reserve_gpr(ret);
+        }
+        let left_ptr = self.machine.acquire_temp_gpr().unwrap();
+        let left = self.machine.acquire_temp_gpr().unwrap();
+
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(
+                self.machine.get_vmctx_reg(),
+                self.vmoffsets.vmctx_call_depth_left_ptr() as i32,
+            ),
+            Location::GPR(left_ptr),
+        );
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(left_ptr, 0),
+            Location::GPR(left),
+        );
+        self.machine
+            .location_add(Size::S64, Location::Imm32(1), Location::GPR(left), false);
+        self.machine.move_location(
+            Size::S64,
+            Location::GPR(left),
+            Location::Memory(left_ptr, 0),
+        );
+
+        self.machine.release_gpr(left);
+        self.machine.release_gpr(left_ptr);
+        if reserve_ret {
+            self.machine.release_gpr(ret);
+        }
+    }
+
     fn emit_head(&mut self) -> Result<(), CodegenError> {
         self.machine.emit_function_prolog();
 
@@ -1007,6 +1443,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             self.signature.clone(),
             self.calling_convention,
         );
+        self.perf_counters.max_stack_offset = self.stack_offset.0;
 
         // Mark vmctx register. The actual loading of the vmctx value is handled by init_local.
         self.state.register_values[self.machine.index_from_gpr(self.machine.get_vmctx_reg()).0] =
@@ -1037,6 +1474,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         });
 
         // TODO: Full preemption by explicit signal checking
+        if self.config.enable_epoch_interruption {
+            self.emit_epoch_check()?;
+        }
+        if self.config.enable_call_depth_limit {
+            self.emit_call_depth_enter();
+        }
 
         // We insert set StackOverflow as the default trap that can happen
         // anywhere in the function prologue.
@@ -1080,6 +1523,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             table_access_oob: machine.get_label(),
             indirect_call_null: machine.get_label(),
             bad_signature: machine.get_label(),
+            call_depth_exceeded: machine.get_label(),
         };
 
         let fsm = FunctionStateMap::new(
@@ -1113,6 +1557,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             relocations: vec![],
             special_labels,
             calling_convention,
+            perf_counters: PerfCounters::default(),
+            ref_locations: vec![],
+            stack_maps: vec![],
         };
         fg.emit_head()?;
         Ok(fg)
@@ -1126,6 +1573,10 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         assert!(self.fp_stack.len() <= self.value_stack.len());
 
         self.state.wasm_inst_offset = self.state.wasm_inst_offset.wrapping_add(1);
+        self.perf_counters.operators += 1;
+        if is_softfloat_operator(&op) {
+            self.perf_counters.softfloat_operators += 1;
+        }
 
         //println!("{:?} {}", op, self.value_stack.len());
         let was_unreachable;
@@ -1159,6 +1610,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             was_unreachable = false;
         }
 
+        if self.config.enable_memory_access_tracing {
+            if let Some((memory, offset, len, kind)) = memory_access(&op) {
+                self.emit_memory_access_trace(memory, offset, len, kind)?;
+            }
+        }
+
         match op {
             Operator::GlobalGet { global_index } => {
                 let global_index = GlobalIndex::from_u32(global_index);
@@ -1254,8 +1711,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::LocalGet { local_index } => {
                 let local_index = local_index as usize;
+                let ty = match self.local_types[local_index] {
+                    ty @ WpType::ExternRef | ty @ WpType::FuncRef => ty,
+                    _ => WpType::I64,
+                };
                 let ret = self.acquire_locations(
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
                 )[0];
                 self.machine
@@ -2961,8 +3422,16 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     } else {
                         None
                     };
+                let ty = match op {
+                    Operator::TypedSelect { ty }
+                        if ty == WpType::ExternRef || ty == WpType::FuncRef =>
+                    {
+                        ty
+                    }
+                    _ => WpType::I64,
+                };
                 let ret = self.acquire_locations(
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
                     false,
                 )[0];
                 self.value_stack.push(ret);
@@ -3052,22 +3521,29 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 });
                 self.machine.emit_label(label);
 
-                // TODO: Re-enable interrupt signal check without branching
+                if self.config.enable_epoch_interruption {
+                    self.emit_epoch_check()?;
+                }
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::new(mem as usize);
+                let (memory_size_index, memory_index) =
+                    match self.module.local_memory_index(memory_index) {
+                        Some(local_memory_index) => (
+                            VMBuiltinFunctionIndex::get_memory32_size_index(),
+                            local_memory_index.as_u32(),
+                        ),
+                        None => (
+                            VMBuiltinFunctionIndex::get_imported_memory32_size_index(),
+                            memory_index.as_u32(),
+                        ),
+                    };
                 self.machine.move_location(
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_memory_index(memory_index).is_some() {
-                                VMBuiltinFunctionIndex::get_memory32_size_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_memory32_size_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(memory_size_index) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 );
@@ -3077,7 +3553,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call());
                     },
                     // [vmctx, memory_index]
-                    iter::once(Location::Imm32(memory_index.index() as u32)),
+                    iter::once(Location::Imm32(memory_index)),
                     iter::once(WpType::I64),
                 )?;
                 let ret = self.acquire_locations(
@@ -3161,26 +3637,31 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?;
             }
             Operator::MemoryCopy { src, dst } => {
-                // ignore until we support multiple memories
-                let _dst = dst;
                 let len = self.value_stack.pop().unwrap();
                 let src_pos = self.value_stack.pop().unwrap();
                 let dst_pos = self.value_stack.pop().unwrap();
                 self.release_locations_only_regs(&[len, src_pos, dst_pos]);
 
+                // A copy between two different memories names both of
+                // them, see `wasmer_vm_memory32_copy_between`.
                 let memory_index = MemoryIndex::new(src as usize);
-                let (memory_copy_index, memory_index) =
-                    if self.module.local_memory_index(memory_index).is_some() {
-                        (
+                let (memory_copy_index, memory_indices) = if src != dst {
+                    (
+                        VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+                        vec![Location::Imm32(dst), Location::Imm32(src)],
+                    )
+                } else {
+                    match self.module.local_memory_index(memory_index) {
+                        Some(local_memory_index) => (
                             VMBuiltinFunctionIndex::get_memory_copy_index(),
-                            memory_index,
-                        )
-                    } else {
-                        (
+                            vec![Location::Imm32(local_memory_index.as_u32())],
+                        ),
+                        None => (
                             VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
-                            memory_index,
-                        )
-                    };
+                            vec![Location::Imm32(memory_index.as_u32())],
+                        ),
+                    }
+                };
 
                 self.machine.move_location(
                     Size::S64,
@@ -3199,18 +3680,16 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                         this.machine
                             .emit_call_register(this.machine.get_grp_for_call());
                     },
-                    // [vmctx, memory_index, dst, src, len]
-                    [
-                        Location::Imm32(memory_index.index() as u32),
-                        dst_pos,
-                        src_pos,
-                        len,
-                    ]
-                    .iter()
-                    .cloned(),
-                    [WpType::I32, WpType::I64, WpType::I64, WpType::I64]
+                    // [vmctx, memory_index, dst, src, len], or
+                    // [vmctx, dst_memory_index, src_memory_index, dst, src, len]
+                    memory_indices
                         .iter()
-                        .cloned(),
+                        .cloned()
+                        .chain([dst_pos, src_pos, len].iter().cloned()),
+                    memory_indices
+                        .iter()
+                        .map(|_| WpType::I32)
+                        .chain([WpType::I64, WpType::I64, WpType::I64].iter().cloned()),
                 )?;
                 self.release_locations_only_stack(&[dst_pos, src_pos, len]);
             }
@@ -3222,16 +3701,15 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
                 let memory_index = MemoryIndex::new(mem as usize);
                 let (memory_fill_index, memory_index) =
-                    if self.module.local_memory_index(memory_index).is_some() {
-                        (
+                    match self.module.local_memory_index(memory_index) {
+                        Some(local_memory_index) => (
                             VMBuiltinFunctionIndex::get_memory_fill_index(),
-                            memory_index,
-                        )
-                    } else {
-                        (
+                            local_memory_index.as_u32(),
+                        ),
+                        None => (
                             VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
-                            memory_index,
-                        )
+                            memory_index.as_u32(),
+                        ),
                     };
 
                 self.machine.move_location(
@@ -3252,7 +3730,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call());
                     },
                     // [vmctx, memory_index, dst, src, len]
-                    [Location::Imm32(memory_index.index() as u32), dst, val, len]
+                    [Location::Imm32(memory_index), dst, val, len]
                         .iter()
                         .cloned(),
                     [WpType::I32, WpType::I64, WpType::I64, WpType::I64]
@@ -3263,6 +3741,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::MemoryGrow { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::new(mem as usize);
+                let (memory_grow_index, memory_index) =
+                    match self.module.local_memory_index(memory_index) {
+                        Some(local_memory_index) => (
+                            VMBuiltinFunctionIndex::get_memory32_grow_index(),
+                            local_memory_index.as_u32(),
+                        ),
+                        None => (
+                            VMBuiltinFunctionIndex::get_imported_memory32_grow_index(),
+                            memory_index.as_u32(),
+                        ),
+                    };
                 let param_pages = self.value_stack.pop().unwrap();
 
                 self.release_locations_only_regs(&[param_pages]);
@@ -3271,13 +3760,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_memory_index(memory_index).is_some() {
-                                VMBuiltinFunctionIndex::get_memory32_grow_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(memory_grow_index) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 );
@@ -3290,8 +3773,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call());
                     },
                     // [vmctx, val, memory_index]
-                    iter::once(param_pages)
-                        .chain(iter::once(Location::Imm32(memory_index.index() as u32))),
+                    iter::once(param_pages).chain(iter::once(Location::Imm32(memory_index))),
                     [WpType::I64, WpType::I64].iter().cloned(),
                 )?;
 
@@ -3308,6 +3790,123 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     ret,
                 );
             }
+            Operator::MemoryAtomicWait32 { ref memarg }
+            | Operator::MemoryAtomicWait64 { ref memarg } => {
+                let (wait_index, expected_type) = match op {
+                    Operator::MemoryAtomicWait32 { .. } => (
+                        VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+                        WpType::I32,
+                    ),
+                    _ => (
+                        VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                        WpType::I64,
+                    ),
+                };
+                let timeout = self.value_stack.pop().unwrap();
+                let expected = self.value_stack.pop().unwrap();
+                let addr = self.value_stack.pop().unwrap();
+                self.release_locations_only_regs(&[timeout, expected, addr]);
+
+                self.machine.move_location(
+                    Size::S64,
+                    Location::Memory(
+                        self.machine.get_vmctx_reg(),
+                        self.vmoffsets.vmctx_builtin_function(wait_index) as i32,
+                    ),
+                    Location::GPR(self.machine.get_grp_for_call()),
+                );
+
+                self.release_locations_only_osr_state(1);
+
+                self.emit_call_native(
+                    |this| {
+                        this.machine
+                            .emit_call_register(this.machine.get_grp_for_call());
+                    },
+                    // [vmctx, memory_index, addr, offset, expected, timeout]
+                    [
+                        Location::Imm32(memarg.memory),
+                        addr,
+                        Location::Imm32(memarg.offset as u32),
+                        expected,
+                        timeout,
+                    ]
+                    .iter()
+                    .cloned(),
+                    [
+                        WpType::I32,
+                        WpType::I32,
+                        WpType::I32,
+                        expected_type,
+                        WpType::I64,
+                    ]
+                    .iter()
+                    .cloned(),
+                )?;
+
+                self.release_locations_only_stack(&[timeout, expected, addr]);
+
+                let ret = self.acquire_locations(
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                self.machine.move_location(
+                    Size::S64,
+                    Location::GPR(self.machine.get_gpr_for_ret()),
+                    ret,
+                );
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let count = self.value_stack.pop().unwrap();
+                let addr = self.value_stack.pop().unwrap();
+                self.release_locations_only_regs(&[count, addr]);
+
+                self.machine.move_location(
+                    Size::S64,
+                    Location::Memory(
+                        self.machine.get_vmctx_reg(),
+                        self.vmoffsets.vmctx_builtin_function(
+                            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                        ) as i32,
+                    ),
+                    Location::GPR(self.machine.get_grp_for_call()),
+                );
+
+                self.release_locations_only_osr_state(1);
+
+                self.emit_call_native(
+                    |this| {
+                        this.machine
+                            .emit_call_register(this.machine.get_grp_for_call());
+                    },
+                    // [vmctx, memory_index, addr, offset, count]
+                    [
+                        Location::Imm32(memarg.memory),
+                        addr,
+                        Location::Imm32(memarg.offset as u32),
+                        count,
+                    ]
+                    .iter()
+                    .cloned(),
+                    [WpType::I32, WpType::I32, WpType::I32, WpType::I32]
+                        .iter()
+                        .cloned(),
+                )?;
+
+                self.release_locations_only_stack(&[count, addr]);
+
+                let ret = self.acquire_locations(
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+                self.machine.move_location(
+                    Size::S64,
+                    Location::GPR(self.machine.get_gpr_for_ret()),
+                    ret,
+                );
+            }
             Operator::I32Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.acquire_locations(
@@ -3316,6 +3915,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load(
                             target,
@@ -3339,6 +3939,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f32_load(
                             target,
@@ -3360,6 +3961,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_8u(
                             target,
@@ -3381,6 +3983,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_8s(
                             target,
@@ -3402,6 +4005,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_16u(
                             target,
@@ -3423,6 +4027,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_load_16s(
                             target,
@@ -3440,6 +4045,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save(
                             target_value,
@@ -3459,6 +4065,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f32_save(
                             target_value,
@@ -3477,6 +4084,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save_8(
                             target_value,
@@ -3494,6 +4102,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_save_16(
                             target_value,
@@ -3515,6 +4124,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load(
                             target,
@@ -3538,6 +4148,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.fp_stack
                     .push(FloatValue::new(self.value_stack.len() - 1));
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f64_load(
                             target,
@@ -3559,6 +4170,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_8u(
                             target,
@@ -3580,6 +4192,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_8s(
                             target,
@@ -3601,6 +4214,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_16u(
                             target,
@@ -3622,6 +4236,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_16s(
                             target,
@@ -3643,6 +4258,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_32u(
                             target,
@@ -3664,6 +4280,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_load_32s(
                             target,
@@ -3682,6 +4299,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_addr = self.pop_value_released();
 
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save(
                             target_value,
@@ -3701,6 +4319,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let fp = self.fp_stack.pop1()?;
                 let config_nan_canonicalization = self.config.enable_nan_canonicalization;
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.f64_save(
                             target_value,
@@ -3719,6 +4338,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_8(
                             target_value,
@@ -3736,6 +4356,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_16(
                             target_value,
@@ -3753,6 +4374,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_save_32(
                             target_value,
@@ -3995,6 +4617,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
                 if self.control_stack.is_empty() {
                     self.machine.emit_label(frame.label);
+                    if self.config.enable_call_depth_limit {
+                        self.emit_call_depth_exit();
+                    }
                     self.finalize_locals(self.calling_convention);
                     self.machine.emit_function_epilog();
 
@@ -4065,6 +4690,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load(
                             target,
@@ -4086,6 +4712,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_8u(
                             target,
@@ -4107,6 +4734,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_16u(
                             target,
@@ -4124,6 +4752,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save(
                             target_value,
@@ -4141,6 +4770,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_8(
                             target_value,
@@ -4158,6 +4788,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_16(
                             target_value,
@@ -4179,6 +4810,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load(
                             target,
@@ -4200,6 +4832,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_8u(
                             target,
@@ -4221,6 +4854,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_16u(
                             target,
@@ -4242,6 +4876,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_32u(
                             target,
@@ -4259,6 +4894,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save(
                             target_value,
@@ -4276,6 +4912,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_8(
                             target_value,
@@ -4293,6 +4930,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_16(
                             target_value,
@@ -4310,6 +4948,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_32(
                             target_value,
@@ -4332,6 +4971,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add(
                             loc,
@@ -4355,6 +4995,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add(
                             loc,
@@ -4378,6 +5019,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_8u(
                             loc,
@@ -4401,6 +5043,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_16u(
                             loc,
@@ -4424,6 +5067,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_8u(
                             loc,
@@ -4447,6 +5091,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_16u(
                             loc,
@@ -4470,6 +5115,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_32u(
                             loc,
@@ -4493,6 +5139,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub(
                             loc,
@@ -4516,6 +5163,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub(
                             loc,
@@ -4539,6 +5187,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_8u(
                             loc,
@@ -4562,6 +5211,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_16u(
                             loc,
@@ -4585,6 +5235,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_8u(
                             loc,
@@ -4608,6 +5259,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_16u(
                             loc,
@@ -4631,6 +5283,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_32u(
                             loc,
@@ -4654,6 +5307,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and(
                             loc,
@@ -4677,6 +5331,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and(
                             loc,
@@ -4700,6 +5355,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_8u(
                             loc,
@@ -4723,6 +5379,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_16u(
                             loc,
@@ -4746,6 +5403,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_8u(
                             loc,
@@ -4769,6 +5427,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_16u(
                             loc,
@@ -4792,6 +5451,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_32u(
                             loc,
@@ -4815,6 +5475,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or(
                             loc,
@@ -4838,6 +5499,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or(
                             loc,
@@ -4861,6 +5523,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_8u(
                             loc,
@@ -4884,6 +5547,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_16u(
                             loc,
@@ -4907,6 +5571,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_8u(
                             loc,
@@ -4930,6 +5595,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_16u(
                             loc,
@@ -4953,6 +5619,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_32u(
                             loc,
@@ -4976,6 +5643,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor(
                             loc,
@@ -4999,6 +5667,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor(
                             loc,
@@ -5022,6 +5691,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_8u(
                             loc,
@@ -5045,6 +5715,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_16u(
                             loc,
@@ -5068,6 +5739,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_8u(
                             loc,
@@ -5091,6 +5763,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_16u(
                             loc,
@@ -5114,6 +5787,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_32u(
                             loc,
@@ -5137,6 +5811,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg(
                             loc,
@@ -5160,6 +5835,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg(
                             loc,
@@ -5183,6 +5859,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_8u(
                             loc,
@@ -5206,6 +5883,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_16u(
                             loc,
@@ -5229,6 +5907,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_8u(
                             loc,
@@ -5252,6 +5931,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_16u(
                             loc,
@@ -5275,6 +5955,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_32u(
                             loc,
@@ -5299,6 +5980,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg(
                             new,
@@ -5324,6 +6006,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg(
                             new,
@@ -5349,6 +6032,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_8u(
                             new,
@@ -5374,6 +6058,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_16u(
                             new,
@@ -5399,6 +6084,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_8u(
                             new,
@@ -5424,6 +6110,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_16u(
                             new,
@@ -5449,6 +6136,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )[0];
                 self.value_stack.push(ret);
                 self.op_memory(
+                    memarg.memory,
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_32u(
                             new,
@@ -5859,6 +6547,62 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         Ok(())
     }
 
+    /// The performance diagnostics of the function, once all its
+    /// operators have been fed.
+    pub fn perf_diagnostics(&self) -> Vec<PerfDiagnostic> {
+        let mut diagnostics = vec![];
+        if !self.config.enable_perf_diagnostics {
+            return diagnostics;
+        }
+        let func_index = self
+            .module
+            .func_index(LocalFunctionIndex::new(self.fsm.local_function_id));
+        let counters = &self.perf_counters;
+
+        if counters.spilled_values > SPILLED_VALUES_THRESHOLD {
+            diagnostics.push(PerfDiagnostic {
+                func_index,
+                code: PerfDiagnosticCode::SpilledValues,
+                message: format!(
+                    "{} values were spilled to the stack because of register pressure",
+                    counters.spilled_values
+                ),
+                weight: counters.spilled_values,
+            });
+        }
+        let frame_size = counters.max_stack_offset as u64;
+        if frame_size > LARGE_STACK_FRAME_THRESHOLD {
+            diagnostics.push(PerfDiagnostic {
+                func_index,
+                code: PerfDiagnosticCode::LargeStackFrame,
+                message: format!("the stack frame is {} bytes large", frame_size),
+                weight: frame_size,
+            });
+        }
+        if counters.softfloat_operators >= SOFTFLOAT_OPERATORS_THRESHOLD
+            && counters.softfloat_operators * 100
+                >= counters.operators * SOFTFLOAT_DENSITY_THRESHOLD
+        {
+            diagnostics.push(PerfDiagnostic {
+                func_index,
+                code: PerfDiagnosticCode::SoftfloatDensity,
+                message: format!(
+                    "{} of the {} operators are floating point operations lowered to software sequences",
+                    counters.softfloat_operators, counters.operators
+                ),
+                weight: counters.softfloat_operators,
+            });
+        }
+
+        diagnostics
+    }
+
+    /// Takes the stack maps of the calls emitted so far, which are
+    /// empty unless stack maps are enabled.
+    pub fn take_stack_maps(&mut self) -> Vec<StackMap> {
+        std::mem::take(&mut self.stack_maps)
+    }
+
     pub fn finalize(mut self, data: &FunctionBodyData) -> (CompiledFunction, Option<UnwindFrame>) {
         // Generate actual code for special labels.
         self.machine
@@ -5895,6 +6639,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .mark_address_with_trap_code(TrapCode::BadSignature);
         self.machine.emit_illegal_op();
 
+        self.machine
+            .emit_label(self.special_labels.call_depth_exceeded);
+        self.machine
+            .mark_address_with_trap_code(TrapCode::CallDepthExceeded);
+        self.machine.emit_illegal_op();
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.machine.finalize_function();
 
@@ -17,9 +17,10 @@ use crate::unwind::{create_systemv_cie, UnwindFrame};
 #[cfg(feature = "unwind")]
 use gimli::write::{EhFrame, FrameTable};
 use loupe::MemoryUsage;
-#[cfg(feature = "rayon")]
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
+use std::time::Instant;
+#[cfg(feature = "rayon")]
+use wasmer_compiler::CompileThreadPool;
 use wasmer_compiler::{
     Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
     CompiledFunction, Compiler, CompilerConfig, CpuFeature, Dwarf, FunctionBinaryReader,
@@ -29,7 +30,7 @@ use wasmer_compiler::{
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
-    FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, ModuleInfo, TableIndex,
+    FunctionIndex, FunctionType, IndexType, LocalFunctionIndex, MemoryIndex, ModuleInfo, TableIndex,
 };
 use wasmer_vm::{TrapCode, VMOffsets};
 
@@ -38,35 +39,48 @@ use wasmer_vm::{TrapCode, VMOffsets};
 #[derive(MemoryUsage)]
 pub struct SinglepassCompiler {
     config: Singlepass,
+    #[cfg(feature = "rayon")]
+    thread_pool: CompileThreadPool,
 }
 
 impl SinglepassCompiler {
     /// Creates a new Singlepass compiler
     pub fn new(config: Singlepass) -> Self {
-        Self { config }
+        Self {
+            #[cfg(feature = "rayon")]
+            thread_pool: CompileThreadPool::new("singlepass", config.num_compile_threads),
+            config,
+        }
+    }
+
+    /// Maps the `items` with `f`, in the thread pool of the compiler
+    /// with the `rayon` feature, one after the other on the current
+    /// thread without it.
+    fn map_in_pool<T: Send, R: Send>(
+        &self,
+        items: Vec<T>,
+        f: impl Fn(T) -> R + Send + Sync,
+    ) -> Vec<R> {
+        #[cfg(feature = "rayon")]
+        return self.thread_pool.map_init(items, || (), |_, item| f(item));
+        #[cfg(not(feature = "rayon"))]
+        return items.into_iter().map(f).collect();
     }
 
     /// Gets the config for this Compiler
     fn config(&self) -> &Singlepass {
         &self.config
     }
-}
 
-impl Compiler for SinglepassCompiler {
-    /// Get the middlewares for this compiler
-    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
-        &self.config.middlewares
-    }
-
-    /// Compile the module using Singlepass, producing a compilation result with
-    /// associated relocations.
-    fn compile_module(
+    /// Compile the module, in the thread pool the current thread
+    /// belongs to.
+    fn compile_module_in_pool(
         &self,
         target: &Target,
         compile_info: &CompileModuleInfo,
-        _module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError> {
+        let start = Instant::now();
         match target.triple().architecture {
             Architecture::X86_64 => {}
             Architecture::Aarch64(_) => {}
@@ -94,6 +108,23 @@ impl Compiler for SinglepassCompiler {
         if compile_info.features.multi_value {
             return Err(CompileError::UnsupportedFeature("multivalue".to_string()));
         }
+        // The memory accesses are only emitted for 32-bit addresses.
+        if compile_info
+            .module
+            .memories
+            .values()
+            .any(|ty| ty.index_type == IndexType::I64)
+        {
+            return Err(CompileError::UnsupportedFeature(
+                "64-bit memories".to_string(),
+            ));
+        }
+        if self.config.enable_stack_maps && target.triple().architecture != Architecture::X86_64 {
+            return Err(CompileError::UnsupportedFeature(format!(
+                "stack maps on {}",
+                target.triple().architecture
+            )));
+        }
         let calling_convention = match target.triple().default_calling_convention() {
             Ok(CallingConvention::WindowsFastcall) => CallingConvention::WindowsFastcall,
             Ok(CallingConvention::SystemV) => CallingConvention::SystemV,
@@ -128,27 +159,26 @@ impl Compiler for SinglepassCompiler {
         let table_styles = &compile_info.table_styles;
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
-        let mut custom_sections: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
-            .map(FunctionIndex::new)
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|i| {
-                gen_import_call_trampoline(
-                    &vmoffsets,
-                    i,
-                    &module.signatures[module.functions[i]],
-                    target,
-                    calling_convention,
-                )
-            })
-            .collect::<Vec<_>>()
+        let mut custom_sections: PrimaryMap<SectionIndex, _> = self
+            .map_in_pool(
+                (0..module.num_imported_functions)
+                    .map(FunctionIndex::new)
+                    .collect(),
+                |i| {
+                    gen_import_call_trampoline(
+                        &vmoffsets,
+                        i,
+                        &module.signatures[module.functions[i]],
+                        target,
+                        calling_convention,
+                    )
+                },
+            )
             .into_iter()
             .collect();
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .into_par_iter_if_rayon()
-            .map(|(i, input)| {
+        let (functions, reports): (Vec<_>, Vec<_>) = self
+            .map_in_pool(function_body_inputs.iter().collect(), |(i, input)| {
+                let start = self.config.enable_function_timings.then(Instant::now);
                 let middleware_chain = self
                     .config
                     .middlewares
@@ -169,7 +199,8 @@ impl Compiler for SinglepassCompiler {
 
                 match target.triple().architecture {
                     Architecture::X86_64 => {
-                        let machine = MachineX86_64::new(simd_arch);
+                        let mut machine = MachineX86_64::new(simd_arch);
+                        machine.set_trap_unaligned_atomics(self.config.trap_unaligned_atomics);
                         let mut generator = FuncGen::new(
                             module,
                             &self.config,
@@ -188,10 +219,15 @@ impl Compiler for SinglepassCompiler {
                             generator.feed_operator(op).map_err(to_compile_error)?;
                         }
 
-                        Ok(generator.finalize(&input))
+                        let perf_diagnostics = generator.perf_diagnostics();
+                        let stack_maps = generator.take_stack_maps();
+                        let compiled = generator.finalize(&input);
+                        let compile_time = start.map(|start| start.elapsed());
+                        Ok((compiled, (perf_diagnostics, (compile_time, stack_maps))))
                     }
                     Architecture::Aarch64(_) => {
-                        let machine = MachineARM64::new();
+                        let mut machine = MachineARM64::new();
+                        machine.set_trap_unaligned_atomics(self.config.trap_unaligned_atomics);
                         let mut generator = FuncGen::new(
                             module,
                             &self.config,
@@ -210,30 +246,32 @@ impl Compiler for SinglepassCompiler {
                             generator.feed_operator(op).map_err(to_compile_error)?;
                         }
 
-                        Ok(generator.finalize(&input))
+                        let perf_diagnostics = generator.perf_diagnostics();
+                        let stack_maps = generator.take_stack_maps();
+                        let compiled = generator.finalize(&input);
+                        let compile_time = start.map(|start| start.elapsed());
+                        Ok((compiled, (perf_diagnostics, (compile_time, stack_maps))))
                     }
                     _ => unimplemented!(),
                 }
             })
+            .into_iter()
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .unzip();
+        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = functions.into_iter().unzip();
+        let (perf_diagnostics, reports): (Vec<_>, Vec<_>) = reports.into_iter().unzip();
+        let (function_compile_times, stack_maps): (Vec<_>, Vec<_>) = reports.into_iter().unzip();
 
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| gen_std_trampoline(&func_type, target, calling_convention))
-            .collect::<Vec<_>>()
+        let function_call_trampolines = self
+            .map_in_pool(module.signatures.values().collect(), |func_type| {
+                gen_std_trampoline(&func_type, target, calling_convention)
+            })
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
 
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| {
+        let dynamic_function_trampolines = self
+            .map_in_pool(module.imported_function_types().collect(), |func_type| {
                 gen_std_dynamic_import_trampoline(
                     &vmoffsets,
                     &func_type,
@@ -241,7 +279,6 @@ impl Compiler for SinglepassCompiler {
                     calling_convention,
                 )
             })
-            .collect::<Vec<_>>()
             .into_iter()
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
@@ -266,13 +303,71 @@ impl Compiler for SinglepassCompiler {
         #[cfg(not(feature = "unwind"))]
         let dwarf = None;
 
+        let function_compile_times = if self.config.enable_function_timings {
+            function_compile_times.into_iter().collect()
+        } else {
+            None
+        };
+        let stack_maps = if self.config.enable_stack_maps {
+            Some(stack_maps.into_iter().collect())
+        } else {
+            None
+        };
         Ok(Compilation::new(
             functions.into_iter().collect(),
             custom_sections,
             function_call_trampolines,
             dynamic_function_trampolines,
             dwarf,
-        ))
+        )
+        .with_perf_diagnostics(perf_diagnostics.into_iter().flatten().collect())
+        .with_compile_time(start.elapsed(), function_compile_times)
+        .with_stack_maps(stack_maps))
+    }
+}
+
+impl Compiler for SinglepassCompiler {
+    /// Get the middlewares for this compiler
+    fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
+        &self.config.middlewares
+    }
+
+    fn deterministic_id(&self) -> String {
+        let mut config = self.config.clone();
+        let middlewares = std::mem::take(&mut config.middlewares);
+        format!(
+            "singlepass-{:?}-[{}]",
+            config,
+            middlewares.deterministic_id()
+        )
+    }
+
+    fn retains_wasm_bytes(&self) -> bool {
+        self.config.retain_wasm_bytes
+    }
+
+    fn emits_epoch_checks(&self) -> bool {
+        self.config.enable_epoch_interruption
+    }
+
+    /// Compile the module using Singlepass, producing a compilation result with
+    /// associated relocations.
+    fn compile_module(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Result<Compilation, CompileError> {
+        #[cfg(feature = "rayon")]
+        {
+            self.thread_pool
+                .install(|| self.compile_module_in_pool(target, compile_info, function_body_inputs))
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.compile_module_in_pool(target, compile_info, function_body_inputs)
+        }
     }
 }
 
@@ -290,25 +385,6 @@ fn to_compile_error<T: ToCompileError>(x: T) -> CompileError {
     x.to_compile_error()
 }
 
-trait IntoParIterIfRayon {
-    type Output;
-    fn into_par_iter_if_rayon(self) -> Self::Output;
-}
-
-impl<T: Send> IntoParIterIfRayon for Vec<T> {
-    #[cfg(not(feature = "rayon"))]
-    type Output = std::vec::IntoIter<T>;
-    #[cfg(feature = "rayon")]
-    type Output = rayon::vec::IntoIter<T>;
-
-    fn into_par_iter_if_rayon(self) -> Self::Output {
-        #[cfg(not(feature = "rayon"))]
-        return self.into_iter();
-        #[cfg(feature = "rayon")]
-        return self.into_par_iter();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
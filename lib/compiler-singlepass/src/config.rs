@@ -7,10 +7,43 @@ use std::sync::Arc;
 use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target};
 use wasmer_types::Features;
 
+/// The code generation options of [`Singlepass`], given to
+/// [`Singlepass::new_with_config`].
+///
+/// The default options are the ones of [`Singlepass::new`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinglepassConfig {
+    /// Canonicalize the NaNs produced by the floating point
+    /// operations, see [`Singlepass::canonicalize_nans`].
+    pub enable_nan_canonicalization: bool,
+    /// Check the depth of the stack on entry to each function, see
+    /// [`Singlepass::enable_stack_check`].
+    pub enable_stack_check: bool,
+}
+
+impl Default for SinglepassConfig {
+    fn default() -> Self {
+        Self {
+            enable_nan_canonicalization: true,
+            enable_stack_check: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, MemoryUsage)]
 pub struct Singlepass {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_stack_check: bool,
+    pub(crate) enable_epoch_interruption: bool,
+    pub(crate) enable_call_depth_limit: bool,
+    pub(crate) trap_unaligned_atomics: bool,
+    pub(crate) enable_memory_access_tracing: bool,
+    pub(crate) enable_perf_diagnostics: bool,
+    pub(crate) enable_function_timings: bool,
+    pub(crate) enable_stack_maps: bool,
+    pub(crate) num_compile_threads: Option<usize>,
+    pub(crate) retain_wasm_bytes: bool,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -22,15 +55,58 @@ impl Singlepass {
         Self {
             enable_nan_canonicalization: true,
             enable_stack_check: false,
+            enable_epoch_interruption: false,
+            enable_call_depth_limit: false,
+            trap_unaligned_atomics: true,
+            enable_memory_access_tracing: false,
+            enable_perf_diagnostics: false,
+            enable_function_timings: false,
+            enable_stack_maps: false,
+            num_compile_threads: None,
+            retain_wasm_bytes: false,
             middlewares: vec![],
         }
     }
 
+    /// Creates a new configuration object with the code generation
+    /// options of `config`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// use wasmer_compiler_singlepass::{Singlepass, SinglepassConfig};
+    ///
+    /// let mut config = SinglepassConfig::default();
+    /// config.enable_nan_canonicalization = false;
+    /// let compiler = Singlepass::new_with_config(config);
+    /// ```
+    pub fn new_with_config(config: SinglepassConfig) -> Self {
+        let mut singlepass = Self::new();
+        singlepass
+            .canonicalize_nans(config.enable_nan_canonicalization)
+            .enable_stack_check(config.enable_stack_check);
+        singlepass
+    }
+
+    /// Returns the code generation options of this configuration.
+    pub fn config(&self) -> SinglepassConfig {
+        SinglepassConfig {
+            enable_nan_canonicalization: self.enable_nan_canonicalization,
+            enable_stack_check: self.enable_stack_check,
+        }
+    }
+
     /// Enable stack check.
     ///
     /// When enabled, an explicit stack depth check will be performed on entry
     /// to each function to prevent stack overflow.
     ///
+    /// The explicit check isn't implemented yet, this currently
+    /// doesn't change the generated code: a stack overflow traps with
+    /// `TrapCode::StackOverflow` either way, the frames being probed
+    /// page by page so that they can't skip the guard page of the
+    /// stack.
+    ///
     /// Note that this doesn't guarantee deterministic execution across
     /// different platforms.
     pub fn enable_stack_check(&mut self, enable: bool) -> &mut Self {
@@ -38,6 +114,94 @@ impl Singlepass {
         self
     }
 
+    /// Enable epoch-based interruption.
+    ///
+    /// When enabled, the generated code checks the epoch counter of
+    /// the store at every function entry and loop header.
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.enable_epoch_interruption = enable;
+        self
+    }
+
+    /// Enable the call depth limit.
+    ///
+    /// When enabled, the generated code counts the wasm frames active
+    /// in the store on entry and return of every function.
+    pub fn call_depth_limit(&mut self, enable: bool) -> &mut Self {
+        self.enable_call_depth_limit = enable;
+        self
+    }
+
+    /// Trap on the atomic accesses to unaligned addresses, as the
+    /// threads proposal requires. Enabled by default.
+    ///
+    /// When disabled, the alignment of the atomic accesses isn't
+    /// checked, which is only meant for testing.
+    pub fn trap_unaligned_atomics(&mut self, enable: bool) -> &mut Self {
+        self.trap_unaligned_atomics = enable;
+        self
+    }
+
+    /// Enable memory access tracing.
+    ///
+    /// When enabled, the generated code reports every load and store
+    /// to the memory access tracer of the store before doing it, with
+    /// a call into the host. This makes memory-heavy code several times
+    /// slower and is only meant to debug the memory corruptions of a
+    /// guest.
+    pub fn memory_access_tracing(&mut self, enable: bool) -> &mut Self {
+        self.enable_memory_access_tracing = enable;
+        self
+    }
+
+    /// Enable performance diagnostics.
+    ///
+    /// When enabled, functions spilling many values to the stack, with
+    /// a large stack frame, or made of many floating point operations
+    /// without a direct machine instruction are reported.
+    pub fn perf_diagnostics(&mut self, enable: bool) -> &mut Self {
+        self.enable_perf_diagnostics = enable;
+        self
+    }
+
+    /// Enable measuring the time spent compiling each function.
+    pub fn function_timings(&mut self, enable: bool) -> &mut Self {
+        self.enable_function_timings = enable;
+        self
+    }
+
+    /// Enable stack maps.
+    ///
+    /// When enabled, the stack slots holding live references
+    /// (`externref`s and `funcref`s) at each call are reported, both
+    /// for the locals and for the values of the operand stack,
+    /// including the ones saved around the call.
+    ///
+    /// To keep every reference in memory, all the locals are stored
+    /// on the stack instead of the first ones being kept in
+    /// callee-saved registers. This makes accessing them slower and
+    /// the code larger, by about one memory operand per access of
+    /// these locals, on top of the memory used by the stack maps
+    /// themselves.
+    ///
+    /// Stack maps are only supported on x86_64; compiling for another
+    /// architecture with stack maps enabled fails.
+    pub fn enable_stack_maps(&mut self, enable: bool) -> &mut Self {
+        self.enable_stack_maps = enable;
+        self
+    }
+
+    /// Compile the functions of a module in a thread pool of
+    /// `threads` threads rather than in the global thread pool, see
+    /// [`CompilerConfig::num_compile_threads`].
+    ///
+    /// This has no effect without the `rayon` feature, the functions
+    /// are then compiled one after the other on the current thread.
+    pub fn num_compile_threads(&mut self, threads: usize) -> &mut Self {
+        self.num_compile_threads = Some(threads);
+        self
+    }
+
     fn enable_nan_canonicalization(&mut self) {
         self.enable_nan_canonicalization = true;
     }
@@ -54,6 +218,42 @@ impl CompilerConfig for Singlepass {
         // PIC code.
     }
 
+    fn epoch_interruption(&mut self, enable: bool) {
+        self.enable_epoch_interruption = enable;
+    }
+
+    fn call_depth_limit(&mut self, enable: bool) {
+        self.enable_call_depth_limit = enable;
+    }
+
+    fn trap_unaligned_atomics(&mut self, enable: bool) {
+        self.trap_unaligned_atomics = enable;
+    }
+
+    fn memory_access_tracing(&mut self, enable: bool) {
+        self.enable_memory_access_tracing = enable;
+    }
+
+    fn enable_perf_diagnostics(&mut self, enable: bool) {
+        self.enable_perf_diagnostics = enable;
+    }
+
+    fn enable_function_timings(&mut self, enable: bool) {
+        self.enable_function_timings = enable;
+    }
+
+    fn enable_stack_maps(&mut self, enable: bool) {
+        self.enable_stack_maps = enable;
+    }
+
+    fn num_compile_threads(&mut self, threads: usize) {
+        self.num_compile_threads = Some(threads);
+    }
+
+    fn retain_wasm_bytes(&mut self, retain: bool) {
+        self.retain_wasm_bytes = retain;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(SinglepassCompiler::new(*self))
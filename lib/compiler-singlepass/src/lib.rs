@@ -28,4 +28,4 @@ mod unwind_winx64;
 mod x64_decl;
 
 pub use crate::compiler::SinglepassCompiler;
-pub use crate::config::Singlepass;
+pub use crate::config::{Singlepass, SinglepassConfig};
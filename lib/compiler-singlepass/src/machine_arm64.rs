@@ -112,6 +112,8 @@ pub struct MachineARM64 {
     pushed: bool,
     /// Vector of unwind operations with offset
     unwind_ops: Vec<(usize, UnwindOps)>,
+    /// Whether the atomic accesses to unaligned addresses trap.
+    trap_unaligned_atomics: bool,
 }
 
 #[allow(dead_code)]
@@ -146,8 +148,15 @@ impl MachineARM64 {
             src_loc: 0,
             pushed: false,
             unwind_ops: vec![],
+            trap_unaligned_atomics: true,
         }
     }
+
+    /// Trap on the atomic accesses to unaligned addresses, which is
+    /// the default.
+    pub fn set_trap_unaligned_atomics(&mut self, enable: bool) {
+        self.trap_unaligned_atomics = enable;
+    }
     fn compatible_imm(&self, imm: i64, ty: ImmType) -> bool {
         match ty {
             ImmType::None => false,
@@ -978,15 +987,19 @@ impl MachineARM64 {
         self.release_gpr(tmp_bound);
         self.release_gpr(tmp_base);
 
-        let align = memarg.align;
-        if check_alignment && align != 1 {
+        // atomic accesses must be aligned on their size
+        let align = value_size as u32;
+        if check_alignment && self.trap_unaligned_atomics && align != 1 {
             self.assembler.emit_tst(
                 Size::S64,
-                Location::Imm32((align - 1).into()),
+                Location::Imm32(align - 1),
                 Location::GPR(tmp_addr),
             );
-            self.assembler
-                .emit_bcond_label_far(Condition::Ne, heap_access_oob);
+            let aligned = self.get_label();
+            self.assembler.emit_bcond_label_far(Condition::Eq, aligned);
+            self.mark_address_with_trap_code(TrapCode::UnalignedAtomic);
+            self.emit_illegal_op();
+            self.emit_label(aligned);
         }
         let begin = self.assembler.get_offset().0;
         cb(self, tmp_addr);
@@ -118,6 +118,8 @@ pub struct MachineX86_64 {
     src_loc: u32,
     /// Vector of unwind operations with offset
     unwind_ops: Vec<(usize, UnwindOps)>,
+    /// Whether the atomic accesses to unaligned addresses trap.
+    trap_unaligned_atomics: bool,
 }
 
 impl MachineX86_64 {
@@ -130,8 +132,14 @@ impl MachineX86_64 {
             instructions_address_map: vec![],
             src_loc: 0,
             unwind_ops: vec![],
+            trap_unaligned_atomics: true,
         }
     }
+    /// Trap on the atomic accesses to unaligned addresses, which is
+    /// the default.
+    pub fn set_trap_unaligned_atomics(&mut self, enable: bool) {
+        self.trap_unaligned_atomics = enable;
+    }
     pub fn emit_relaxed_binop(
         &mut self,
         op: fn(&mut AssemblerX64, Size, Location, Location),
@@ -524,8 +532,9 @@ impl MachineX86_64 {
         self.release_gpr(tmp_bound);
         self.release_gpr(tmp_base);
 
-        let align = memarg.align;
-        if check_alignment && align != 1 {
+        // atomic accesses must be aligned on their size
+        let align = value_size as u32;
+        if check_alignment && self.trap_unaligned_atomics && align != 1 {
             let tmp_aligncheck = self.acquire_temp_gpr().unwrap();
             self.assembler.emit_mov(
                 Size::S32,
@@ -534,11 +543,14 @@ impl MachineX86_64 {
             );
             self.assembler.emit_and(
                 Size::S64,
-                Location::Imm32((align - 1).into()),
+                Location::Imm32(align - 1),
                 Location::GPR(tmp_aligncheck),
             );
-            self.assembler
-                .emit_jmp(Condition::NotEqual, heap_access_oob);
+            let aligned = self.get_label();
+            self.assembler.emit_jmp(Condition::Equal, aligned);
+            self.mark_address_with_trap_code(TrapCode::UnalignedAtomic);
+            self.emit_illegal_op();
+            self.emit_label(aligned);
             self.release_gpr(tmp_aligncheck);
         }
         let begin = self.assembler.get_offset().0;
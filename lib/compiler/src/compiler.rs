@@ -1,21 +1,37 @@
 //! This module mainly outputs the `Compiler` trait that custom
 //! compilers will need to implement.
 
-use crate::error::CompileError;
+use crate::error::{CompileError, ValidationError};
 use crate::function::Compilation;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::sync::Arc;
+use crate::lib::std::vec::Vec;
 use crate::module::CompileModuleInfo;
 use crate::target::Target;
 use crate::translator::ModuleMiddleware;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use crate::SectionIndex;
+use crate::StreamedFunctionBody;
 use loupe::MemoryUsage;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{Features, FunctionIndex, LocalFunctionIndex, SignatureIndex};
 use wasmparser::{Validator, WasmFeatures};
 
+/// An optimization level of the generated code, common to the
+/// compilers, see [`CompilerConfig::set_opt_level`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    /// No optimizations, to compile as fast as possible.
+    None,
+    /// Optimize for the speed of the generated code. This is the
+    /// default of the compilers.
+    Speed,
+    /// Optimize for the size of the generated code: `speed_and_size`
+    /// with Cranelift, the equivalent of `-Oz` with LLVM.
+    Size,
+}
+
 /// The compiler configuration options.
 pub trait CompilerConfig {
     /// Enable Position Independent Code (PIC).
@@ -56,6 +72,114 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Enable epoch-based interruption.
+    ///
+    /// When enabled, the generated code checks the epoch counter of
+    /// the store at every function entry and loop header, and traps
+    /// with `TrapCode::EpochDeadlineExceeded` once it has gone past the
+    /// deadline of the store.
+    fn epoch_interruption(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support emitting epoch checks.
+    }
+
+    /// Enable the call depth limit.
+    ///
+    /// When enabled, the generated code counts the wasm frames active
+    /// in the store, and traps with `TrapCode::CallDepthExceeded` when
+    /// a call would go past the maximum call depth of the store.
+    fn call_depth_limit(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support counting the call depth.
+    }
+
+    /// Trap on the atomic accesses to unaligned addresses.
+    ///
+    /// The threads proposal requires these accesses to trap, with
+    /// `TrapCode::UnalignedAtomic`, which is the default of the
+    /// compilers. Disabling it skips the alignment checks, and is only
+    /// meant for testing.
+    fn trap_unaligned_atomics(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support skipping the alignment checks.
+    }
+
+    /// Report the loads and stores of the generated code to the memory
+    /// access tracer of the store, see `Store::set_memory_access_tracer`.
+    ///
+    /// Every access then calls into the host, which makes the code
+    /// several times slower: this is only meant for debugging.
+    fn memory_access_tracing(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they support tracing the memory accesses.
+    }
+
+    /// Enable performance diagnostics.
+    ///
+    /// When enabled, the compiler reports the functions showing
+    /// performance anti-patterns as
+    /// [`PerfDiagnostic`](crate::PerfDiagnostic)s, which are then
+    /// available with `Module::perf_diagnostics`.
+    fn enable_perf_diagnostics(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they report diagnostics.
+    }
+
+    /// Enable measuring the time spent compiling each function.
+    ///
+    /// When enabled, the times are reported in the
+    /// [`CompilationMetrics`](crate::CompilationMetrics) of the
+    /// compilation, available with `Module::new_with_metrics`.
+    fn enable_function_timings(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they measure the compilation of each function.
+    }
+
+    /// Enable stack maps.
+    ///
+    /// When enabled, the compiler reports the stack slots holding live
+    /// references at each call of a function as
+    /// [`StackMap`](crate::StackMap)s, which are then available with
+    /// `Module::stack_maps`.
+    fn enable_stack_maps(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they emit stack maps.
+    }
+
+    /// Bound the number of threads compiling the functions of a
+    /// module.
+    ///
+    /// The compilers compiling the functions in parallel do it in a
+    /// thread pool of `threads` threads built once with the compiler
+    /// and shared by all its modules, instead of the global thread pool
+    /// using all the cores. `0` compiles the functions one after the
+    /// other, on the thread compiling the module.
+    fn num_compile_threads(&mut self, _threads: usize) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they compile the functions in parallel.
+    }
+
+    /// Set the optimization level of the generated code.
+    ///
+    /// Each backend maps the level to its own settings, see
+    /// [`OptLevel`]. The backends without optimization levels ignore
+    /// it.
+    fn set_opt_level(&mut self, _level: OptLevel) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they have optimization levels.
+    }
+
+    /// Keep the binary of the modules in their artifacts.
+    ///
+    /// When enabled, the engines supporting it keep the exact binary
+    /// a module was compiled from, which is then available with
+    /// `Module::wasm_bytes` and serialized along with the module,
+    /// making the serialized module larger by the size of the binary.
+    fn retain_wasm_bytes(&mut self, _retain: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they keep the binary.
+    }
+
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
@@ -77,8 +201,55 @@ where
     }
 }
 
+/// Validates a module against the given features, without compiling
+/// it.
+///
+/// This runs the full validation pass of the WebAssembly
+/// specification: well-formedness of the binary, type checking of
+/// the function bodies, and validity of the imports, exports and
+/// other sections.
+pub fn validate_module(features: &Features, data: &[u8]) -> Result<(), ValidationError> {
+    module_validator(features)
+        .validate_all(data)
+        .map_err(|e| ValidationError::new(e.message(), e.offset()))?;
+    Ok(())
+}
+
+/// Creates a validator for modules using the given `features`, e.g.
+/// to validate a module progressively while it is received.
+///
+/// The function references proposal (`call_ref` and typed function
+/// references) has no feature: the version of `wasmparser` used to
+/// decode modules predates it, so modules using it are rejected as
+/// malformed, and the compilers have no `call_ref` to translate.
+pub fn module_validator(features: &Features) -> Validator {
+    let mut validator = Validator::new();
+    let wasm_features = WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        module_linking: features.module_linking,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+        extended_const: features.extended_const,
+        relaxed_simd: features.relaxed_simd,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        sign_extension: true,
+    };
+    validator.wasm_features(wasm_features);
+    validator
+}
+
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
-pub trait Compiler: Send + MemoryUsage {
+///
+/// A compiler is shared by the threads compiling with its engine.
+pub trait Compiler: Send + Sync + MemoryUsage {
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
@@ -87,29 +258,7 @@ pub trait Compiler: Send + MemoryUsage {
         features: &Features,
         data: &'data [u8],
     ) -> Result<(), CompileError> {
-        let mut validator = Validator::new();
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            module_linking: features.module_linking,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            deterministic_only: false,
-            extended_const: features.extended_const,
-            relaxed_simd: features.relaxed_simd,
-            mutable_global: true,
-            saturating_float_to_int: true,
-            sign_extension: true,
-        };
-        validator.wasm_features(wasm_features);
-        validator
-            .validate_all(data)
-            .map_err(|e| CompileError::Validate(format!("{}", e)))?;
+        validate_module(features, data)?;
         Ok(())
     }
 
@@ -125,6 +274,29 @@ pub trait Compiler: Send + MemoryUsage {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Compiles a parsed module whose function bodies are received
+    /// progressively, e.g. while the module is downloaded.
+    ///
+    /// `function_bodies` yields the bodies of the local functions in
+    /// order, blocking until the next one is available. The default
+    /// implementation waits for all of them before calling
+    /// [`Compiler::compile_module`]. Compilers able to compile
+    /// functions independently start as soon as each body arrives.
+    fn compile_module_streaming(
+        &self,
+        target: &Target,
+        module: &CompileModuleInfo,
+        module_translation: &ModuleTranslationState,
+        function_bodies: &mut (dyn Iterator<Item = StreamedFunctionBody> + Send),
+    ) -> Result<Compilation, CompileError> {
+        let function_bodies = function_bodies.collect::<Vec<_>>();
+        let function_body_inputs = function_bodies
+            .iter()
+            .map(StreamedFunctionBody::as_function_body_data)
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        self.compile_module(target, module, module_translation, function_body_inputs)
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -144,6 +316,25 @@ pub trait Compiler: Send + MemoryUsage {
 
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>];
+
+    /// An identifier of the compiler and of its configuration,
+    /// middlewares included, stable across processes: compilers with
+    /// the same identifier compile a binary to the same artifact.
+    fn deterministic_id(&self) -> String;
+
+    /// Whether the artifacts of the modules compiled with this
+    /// compiler keep their binary, see
+    /// [`CompilerConfig::retain_wasm_bytes`].
+    fn retains_wasm_bytes(&self) -> bool {
+        false
+    }
+
+    /// Whether the functions compiled with this compiler check the
+    /// epoch deadline of the store, see
+    /// [`CompilerConfig::epoch_interruption`].
+    fn emits_epoch_checks(&self) -> bool {
+        false
+    }
 }
 
 /// The kinds of wasmer_types objects that might be found in a native object file.
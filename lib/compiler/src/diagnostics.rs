@@ -0,0 +1,65 @@
+//! Performance diagnostics reported by the compilers, see
+//! [`CompilerConfig::enable_perf_diagnostics`].
+//!
+//! [`CompilerConfig::enable_perf_diagnostics`]: crate::CompilerConfig::enable_perf_diagnostics
+
+use crate::lib::std::fmt;
+use crate::lib::std::string::String;
+use loupe::MemoryUsage;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use wasmer_types::FunctionIndex;
+
+/// The kind of performance anti-pattern a [`PerfDiagnostic`] reports.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+pub enum PerfDiagnosticCode {
+    /// The function keeps many values on the stack because it ran out
+    /// of registers (Singlepass).
+    SpilledValues,
+    /// The stack frame of the function is very large (Singlepass).
+    LargeStackFrame,
+    /// A large share of the function is made of floating point
+    /// operations lowered to long software sequences instead of a
+    /// single instruction (Singlepass).
+    SoftfloatDensity,
+    /// A memory access could not rely on the guard pages because its
+    /// static offset is larger than them, so an explicit bounds check
+    /// is emitted (Cranelift).
+    BoundsCheckNotElided,
+}
+
+impl PerfDiagnosticCode {
+    /// The stable, textual code of the diagnostic, e.g.
+    /// `"spilled-values"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SpilledValues => "spilled-values",
+            Self::LargeStackFrame => "large-stack-frame",
+            Self::SoftfloatDensity => "softfloat-density",
+            Self::BoundsCheckNotElided => "bounds-check-not-elided",
+        }
+    }
+}
+
+impl fmt::Display for PerfDiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A performance warning about a compiled function.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct PerfDiagnostic {
+    /// The function the diagnostic is about.
+    pub func_index: FunctionIndex,
+    /// What the diagnostic is about.
+    pub code: PerfDiagnosticCode,
+    /// A human readable description of the problem.
+    pub message: String,
+    /// How bad the problem is, in a unit that depends on `code` (the
+    /// number of spilled values, the frame size in bytes, ...).
+    /// Diagnostics with the same code can be ranked by weight.
+    pub weight: u64,
+}
@@ -1,3 +1,4 @@
+use crate::lib::std::format;
 use crate::lib::std::string::String;
 #[cfg(feature = "std")]
 use thiserror::Error;
@@ -48,6 +49,36 @@ impl From<WasmError> for CompileError {
     }
 }
 
+impl From<ValidationError> for CompileError {
+    fn from(original: ValidationError) -> Self {
+        Self::Validate(format!(
+            "{} (at offset {})",
+            original.message, original.offset
+        ))
+    }
+}
+
+/// An error produced when validating a WebAssembly module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[cfg_attr(feature = "std", error("{message} (at offset {offset})"))]
+pub struct ValidationError {
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The offset in the module bytes where the error occurred.
+    pub offset: usize,
+}
+
+impl ValidationError {
+    /// Create a new `ValidationError`
+    pub fn new<M: Into<String>>(message: M, offset: usize) -> Self {
+        Self {
+            message: message.into(),
+            offset,
+        }
+    }
+}
+
 /// A error in the middleware.
 #[derive(Debug)]
 #[cfg_attr(feature = "std", derive(Error))]
@@ -4,8 +4,12 @@
 //! A `Compilation` contains the compiled function bodies for a WebAssembly
 //! module (`CompiledFunction`).
 
+use crate::diagnostics::PerfDiagnostic;
+use crate::lib::std::time::Duration;
 use crate::lib::std::vec::Vec;
+use crate::metrics::CompilationMetrics;
 use crate::section::{CustomSection, SectionIndex};
+use crate::stack_maps::StackMap;
 use crate::trap::TrapInformation;
 use crate::{CompiledFunctionUnwindInfo, FunctionAddressMap, Relocation};
 use loupe::MemoryUsage;
@@ -152,6 +156,20 @@ pub struct Compilation {
 
     /// Section ids corresponding to the Dwarf debug info
     debug: Option<Dwarf>,
+
+    /// Performance diagnostics, when enabled in the compiler config.
+    perf_diagnostics: Vec<PerfDiagnostic>,
+
+    /// The time spent generating code.
+    compile_time: Duration,
+
+    /// The time spent compiling each function, when enabled in the
+    /// compiler config.
+    function_compile_times: Option<PrimaryMap<LocalFunctionIndex, Duration>>,
+
+    /// The stack maps of each function, when enabled in the compiler
+    /// config.
+    stack_maps: Option<PrimaryMap<LocalFunctionIndex, Vec<StackMap>>>,
 }
 
 impl Compilation {
@@ -169,9 +187,40 @@ impl Compilation {
             function_call_trampolines,
             dynamic_function_trampolines,
             debug,
+            perf_diagnostics: Vec::new(),
+            compile_time: Duration::default(),
+            function_compile_times: None,
+            stack_maps: None,
         }
     }
 
+    /// Attaches performance diagnostics to the compilation.
+    pub fn with_perf_diagnostics(mut self, perf_diagnostics: Vec<PerfDiagnostic>) -> Self {
+        self.perf_diagnostics = perf_diagnostics;
+        self
+    }
+
+    /// Attaches the time spent generating code to the compilation, and
+    /// optionally the time spent compiling each function.
+    pub fn with_compile_time(
+        mut self,
+        compile_time: Duration,
+        function_compile_times: Option<PrimaryMap<LocalFunctionIndex, Duration>>,
+    ) -> Self {
+        self.compile_time = compile_time;
+        self.function_compile_times = function_compile_times;
+        self
+    }
+
+    /// Attaches the stack maps of each function to the compilation.
+    pub fn with_stack_maps(
+        mut self,
+        stack_maps: Option<PrimaryMap<LocalFunctionIndex, Vec<StackMap>>>,
+    ) -> Self {
+        self.stack_maps = stack_maps;
+        self
+    }
+
     /// Gets the bytes of a single function
     pub fn get(&self, func: LocalFunctionIndex) -> &CompiledFunction {
         &self.functions[func]
@@ -238,6 +287,46 @@ impl Compilation {
     pub fn get_debug(&self) -> Option<Dwarf> {
         self.debug.clone()
     }
+
+    /// Gets the performance diagnostics.
+    pub fn get_perf_diagnostics(&self) -> Vec<PerfDiagnostic> {
+        self.perf_diagnostics.clone()
+    }
+
+    /// Gets the stack maps of each function, if enabled.
+    pub fn get_stack_maps(&self) -> Option<PrimaryMap<LocalFunctionIndex, Vec<StackMap>>> {
+        self.stack_maps.clone()
+    }
+
+    /// Gets statistics about the generated code.
+    pub fn get_metrics(&self) -> CompilationMetrics {
+        let trampolines = self
+            .function_call_trampolines
+            .values()
+            .chain(self.dynamic_function_trampolines.values());
+        CompilationMetrics {
+            compile_time: self.compile_time,
+            function_count: self.functions.len(),
+            total_code_bytes: self
+                .functions
+                .values()
+                .map(|function| &function.body)
+                .chain(trampolines)
+                .map(|body| body.body.len())
+                .sum(),
+            relocation_count: self
+                .functions
+                .values()
+                .map(|function| function.relocations.len())
+                .chain(
+                    self.custom_sections
+                        .values()
+                        .map(|section| section.relocations.len()),
+                )
+                .sum(),
+            function_compile_times: self.function_compile_times.clone(),
+        }
+    }
 }
 
 impl<'a> IntoIterator for &'a Compilation {
@@ -38,25 +38,29 @@ extern crate alloc;
 mod lib {
     #[cfg(feature = "core")]
     pub mod std {
-        pub use alloc::{borrow, boxed, str, string, sync, vec};
-        pub use core::fmt;
+        pub use alloc::{borrow, boxed, format, str, string, sync, vec};
+        pub use core::{fmt, time};
         pub use hashbrown as collections;
     }
 
     #[cfg(feature = "std")]
     pub mod std {
-        pub use std::{borrow, boxed, collections, fmt, str, string, sync, vec};
+        pub use std::{borrow, boxed, collections, fmt, format, str, string, sync, time, vec};
     }
 }
 
 mod address_map;
 #[cfg(feature = "translator")]
 mod compiler;
+mod diagnostics;
 mod error;
 mod function;
+mod metrics;
 mod module;
 mod relocation;
 mod target;
+#[cfg(feature = "rayon")]
+mod thread_pool;
 mod trap;
 mod unwind;
 #[cfg(feature = "translator")]
@@ -64,30 +68,38 @@ mod unwind;
 mod translator;
 mod section;
 mod sourceloc;
+mod stack_maps;
 
 pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
 #[cfg(feature = "translator")]
-pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
+pub use crate::compiler::{
+    module_validator, validate_module, Compiler, CompilerConfig, OptLevel, Symbol, SymbolRegistry,
+};
+pub use crate::diagnostics::{PerfDiagnostic, PerfDiagnosticCode};
 pub use crate::error::{
-    CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
+    CompileError, MiddlewareError, ParseCpuFeatureError, ValidationError, WasmError, WasmResult,
 };
 pub use crate::function::{
     Compilation, CompiledFunction, CompiledFunctionFrameInfo, CustomSections, Dwarf, FunctionBody,
     Functions,
 };
+pub use crate::metrics::CompilationMetrics;
 pub use crate::module::CompileModuleInfo;
 pub use crate::relocation::{Relocation, RelocationKind, RelocationTarget, Relocations};
 pub use crate::section::{CustomSection, CustomSectionProtection, SectionBody, SectionIndex};
 pub use crate::sourceloc::SourceLoc;
+pub use crate::stack_maps::StackMap;
 pub use crate::target::{
     Architecture, BinaryFormat, CallingConvention, CpuFeature, Endianness, OperatingSystem,
     PointerWidth, Target, Triple,
 };
+#[cfg(feature = "rayon")]
+pub use crate::thread_pool::CompileThreadPool;
 #[cfg(feature = "translator")]
 pub use crate::translator::{
-    translate_module, wptype_to_type, FunctionBinaryReader, FunctionBodyData, FunctionMiddleware,
-    MiddlewareBinaryReader, MiddlewareReaderState, ModuleEnvironment, ModuleMiddleware,
-    ModuleMiddlewareChain, ModuleTranslationState,
+    features_required, translate_module, wptype_to_type, FunctionBinaryReader, FunctionBodyData,
+    FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleEnvironment,
+    ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState, StreamedFunctionBody,
 };
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::CompiledFunctionUnwindInfo;
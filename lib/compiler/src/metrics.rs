@@ -0,0 +1,30 @@
+//! Statistics about the compilation of a module, see
+//! [`Compilation::get_metrics`].
+//!
+//! [`Compilation::get_metrics`]: crate::Compilation::get_metrics
+
+use crate::lib::std::time::Duration;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::LocalFunctionIndex;
+
+/// Statistics reported by the compiler about the code it generated.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CompilationMetrics {
+    /// The time the compiler spent generating code, excluding the
+    /// parsing of the module.
+    pub compile_time: Duration,
+    /// The number of functions defined by the module.
+    pub function_count: usize,
+    /// The size of the generated code, in bytes: the bodies of the
+    /// functions and of the trampolines.
+    pub total_code_bytes: usize,
+    /// The number of relocations to apply to the generated code.
+    pub relocation_count: usize,
+    /// The time spent compiling each function, only measured when
+    /// enabled with
+    /// [`CompilerConfig::enable_function_timings`](crate::CompilerConfig::enable_function_timings).
+    pub function_compile_times: Option<PrimaryMap<LocalFunctionIndex, Duration>>,
+}
@@ -0,0 +1,30 @@
+//! Stack maps reported by the compilers, see
+//! [`CompilerConfig::enable_stack_maps`].
+//!
+//! [`CompilerConfig::enable_stack_maps`]: crate::CompilerConfig::enable_stack_maps
+
+use crate::lib::std::vec::Vec;
+use crate::CodeOffset;
+use loupe::MemoryUsage;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// The stack slots holding live references at a safepoint of a
+/// function, i.e. a call.
+///
+/// While the callee runs, the references held by the caller are only
+/// found in these slots, so that a garbage collector can find them,
+/// and update them if it moves the objects they refer to.
+#[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct StackMap {
+    /// The offset of the safepoint from the start of the function
+    /// body: the return address of the call.
+    pub code_offset: CodeOffset,
+    /// The size, in bytes, of the area of the stack frame described
+    /// by the map, starting at the stack pointer at the safepoint.
+    pub mapped_bytes: u32,
+    /// The offsets, in bytes from the stack pointer at the safepoint,
+    /// of the slots holding live references, in increasing order.
+    pub live_slots: Vec<u32>,
+}
@@ -0,0 +1,125 @@
+//! The thread pools compiling the functions of the modules.
+
+use crate::error::CompileError;
+use loupe::MemoryUsage;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// The thread pool a compiler compiles the functions of the modules
+/// in.
+///
+/// Without a bound on the number of threads, it's the global thread
+/// pool of rayon. Otherwise, the pool is built once, with the
+/// compiler, and shared by all the modules the compiler compiles, see
+/// [`CompilerConfig::num_compile_threads`]. With `0` threads, there's
+/// no pool: the functions are compiled one after the other on the
+/// thread compiling the module.
+///
+/// The threads of the pools aren't duplicated by `fork`, so a child
+/// process compiling with a compiler of its parent, like
+/// `wasmer::sandboxed_compile` does, would wait forever for them. The
+/// functions are compiled on a single thread of a pool of its own in
+/// such a child instead.
+///
+/// [`CompilerConfig::num_compile_threads`]: crate::CompilerConfig::num_compile_threads
+#[derive(MemoryUsage)]
+pub struct CompileThreadPool {
+    /// The name of the compiler, naming the threads.
+    compiler: &'static str,
+    /// The pool of bounded size, or the error building it, reported
+    /// by each compilation.
+    #[loupe(skip)]
+    pool: Option<Result<Arc<rayon::ThreadPool>, String>>,
+    /// Whether the functions are compiled on the calling thread.
+    current_thread: bool,
+    /// The process the pool was built in.
+    process_id: u32,
+}
+
+impl CompileThreadPool {
+    /// Builds the pool of `num_threads` threads, named after the
+    /// `compiler`, or uses the global thread pool if `num_threads` is
+    /// `None`.
+    ///
+    /// `0` threads compile on the calling thread, without a pool.
+    pub fn new(compiler: &'static str, num_threads: Option<usize>) -> Self {
+        let current_thread = num_threads == Some(0);
+        let pool = num_threads
+            .filter(|threads| *threads > 0)
+            .map(|threads| build(compiler, threads).map(Arc::new));
+        Self {
+            compiler,
+            pool,
+            current_thread,
+            process_id: std::process::id(),
+        }
+    }
+
+    /// Runs `f` in the pool, the current thread waiting for it.
+    ///
+    /// The parallel iterators used by `f` run in the pool. Without
+    /// threads, `f` runs on the current thread, and must map the
+    /// functions with [`CompileThreadPool::map_init`] for them to be
+    /// compiled there too.
+    pub fn install<R: Send>(
+        &self,
+        f: impl FnOnce() -> Result<R, CompileError> + Send,
+    ) -> Result<R, CompileError> {
+        if self.current_thread {
+            return f();
+        }
+        if std::process::id() != self.process_id {
+            // A forked child: neither this pool nor the global one
+            // have threads anymore.
+            return build(self.compiler, 1).map_err(pool_error)?.install(f);
+        }
+        match &self.pool {
+            None => f(),
+            Some(Ok(pool)) => pool.install(f),
+            Some(Err(message)) => Err(pool_error(message.clone())),
+        }
+    }
+
+    /// Whether the functions are compiled one after the other on the
+    /// calling thread, see [`CompileThreadPool::new`].
+    pub fn is_current_thread(&self) -> bool {
+        self.current_thread
+    }
+
+    /// Maps the `items` with `f`, given the state `init` creates for
+    /// each thread, in parallel in the pool `install` runs in, or one
+    /// after the other on the current thread if the pool has no
+    /// threads.
+    pub fn map_init<T, S, R>(
+        &self,
+        items: Vec<T>,
+        init: impl Fn() -> S + Send + Sync,
+        f: impl Fn(&mut S, T) -> R + Send + Sync,
+    ) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+    {
+        if self.current_thread {
+            let mut state = init();
+            return items.into_iter().map(|item| f(&mut state, item)).collect();
+        }
+        items.into_par_iter().map_init(init, f).collect()
+    }
+}
+
+/// Builds a pool of `threads` threads named after the `compiler`.
+fn build(compiler: &'static str, threads: usize) -> Result<rayon::ThreadPool, String> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |index| format!("wasmer-{}-{}", compiler, index))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn pool_error(message: String) -> CompileError {
+    CompileError::Resource(format!(
+        "failed to build the compilation thread pool: {}",
+        message
+    ))
+}
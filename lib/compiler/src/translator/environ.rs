@@ -28,6 +28,27 @@ pub struct FunctionBodyData<'a> {
     pub module_offset: usize,
 }
 
+/// Owned function data, received while the module is compiled with
+/// [`Compiler::compile_module_streaming`](crate::Compiler::compile_module_streaming).
+#[derive(Debug, Clone)]
+pub struct StreamedFunctionBody {
+    /// Function body bytecode.
+    pub data: Vec<u8>,
+
+    /// Body offset relative to the module file.
+    pub module_offset: usize,
+}
+
+impl StreamedFunctionBody {
+    /// Borrows the body as a `FunctionBodyData`.
+    pub fn as_function_body_data(&self) -> FunctionBodyData<'_> {
+        FunctionBodyData {
+            data: &self.data,
+            module_offset: self.module_offset,
+        }
+    }
+}
+
 /// Trait for iterating over the operators of a Wasm Function
 pub trait FunctionBinaryReader<'a> {
     /// Read a `count` indicating the number of times to call `read_local_decl`.
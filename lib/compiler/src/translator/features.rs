@@ -0,0 +1,295 @@
+//! Finding the WebAssembly proposals a module uses, see
+//! [`features_required`].
+
+use wasmer_types::Features;
+use wasmparser::{
+    BinaryReaderError, DataKind, ElementItem, ElementKind, ExternalKind, ImportSectionEntryType,
+    InitExpr, MemoryType, Operator, Parser, Payload, TableType, Type, TypeDef, TypeOrFuncType,
+};
+
+/// The prefix byte of the SIMD instructions.
+const SIMD_PREFIX: u8 = 0xfd;
+
+/// The prefix byte of the atomic instructions of the threads proposal.
+const THREADS_PREFIX: u8 = 0xfe;
+
+/// Find the features the WebAssembly module `data` uses.
+///
+/// The sections and the instructions of the module are scanned
+/// without validating them, so that every feature the module uses is
+/// reported, whichever features validation would accept. Only a
+/// malformed binary is reported as an error.
+///
+/// The sign-extension operators and the non-trapping float-to-int
+/// conversions are part of the baseline of [`Features`] and are not
+/// reported, and relaxed SIMD instructions are reported as SIMD.
+pub fn features_required(data: &[u8]) -> Result<Features, BinaryReaderError> {
+    let mut features = Features::none();
+    let mut num_tables = 0;
+    let mut num_memories = 0;
+
+    for payload in Parser::new(0).parse_all(data) {
+        match payload? {
+            Payload::TypeSection(types) => {
+                for ty in types {
+                    match ty? {
+                        TypeDef::Func(ty) => {
+                            if ty.returns.len() > 1 {
+                                features.multi_value = true;
+                            }
+                            for ty in ty.params.iter().chain(ty.returns.iter()) {
+                                value_type(&mut features, *ty);
+                            }
+                        }
+                        _ => features.module_linking = true,
+                    }
+                }
+            }
+
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    let import = import?;
+                    // Single-level imports come from module linking.
+                    if import.field.is_none() {
+                        features.module_linking = true;
+                    }
+                    match import.ty {
+                        ImportSectionEntryType::Function(_) => {}
+                        ImportSectionEntryType::Table(ref table) => {
+                            num_tables += 1;
+                            table_type(&mut features, table);
+                        }
+                        ImportSectionEntryType::Memory(ref memory) => {
+                            num_memories += 1;
+                            memory_type(&mut features, memory);
+                        }
+                        ImportSectionEntryType::Global(ref global) => {
+                            value_type(&mut features, global.content_type);
+                        }
+                        ImportSectionEntryType::Tag(_) => features.exceptions = true,
+                        ImportSectionEntryType::Module(_) | ImportSectionEntryType::Instance(_) => {
+                            features.module_linking = true;
+                        }
+                    }
+                }
+            }
+
+            Payload::TableSection(tables) => {
+                for table in tables {
+                    num_tables += 1;
+                    table_type(&mut features, &table?);
+                }
+            }
+
+            Payload::MemorySection(memories) => {
+                for memory in memories {
+                    num_memories += 1;
+                    memory_type(&mut features, &memory?);
+                }
+            }
+
+            Payload::GlobalSection(globals) => {
+                for global in globals {
+                    let global = global?;
+                    value_type(&mut features, global.ty.content_type);
+                    init_expr(&mut features, &global.init_expr)?;
+                }
+            }
+
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    match export?.kind {
+                        ExternalKind::Tag => features.exceptions = true,
+                        ExternalKind::Type | ExternalKind::Module | ExternalKind::Instance => {
+                            features.module_linking = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            Payload::ElementSection(elements) => {
+                for element in elements {
+                    let element = element?;
+                    if element.ty != Type::FuncRef {
+                        features.reference_types = true;
+                    }
+                    match element.kind {
+                        ElementKind::Active {
+                            table_index,
+                            init_expr: ref offset,
+                        } => {
+                            if table_index != 0 {
+                                features.reference_types = true;
+                            }
+                            init_expr(&mut features, offset)?;
+                        }
+                        ElementKind::Passive => features.bulk_memory = true,
+                        ElementKind::Declared => features.reference_types = true,
+                    }
+                    for item in element.items.get_items_reader()? {
+                        // Element expressions come with passive segments.
+                        if let ElementItem::Expr(_) = item? {
+                            features.bulk_memory = true;
+                        }
+                    }
+                }
+            }
+
+            Payload::DataSection(segments) => {
+                for segment in segments {
+                    match segment?.kind {
+                        DataKind::Active {
+                            memory_index,
+                            init_expr: ref offset,
+                        } => {
+                            if memory_index != 0 {
+                                features.multi_memory = true;
+                            }
+                            init_expr(&mut features, offset)?;
+                        }
+                        DataKind::Passive => features.bulk_memory = true,
+                    }
+                }
+            }
+
+            Payload::DataCountSection { .. } => features.bulk_memory = true,
+
+            Payload::TagSection(_) => features.exceptions = true,
+
+            Payload::InstanceSection(_)
+            | Payload::AliasSection(_)
+            | Payload::ModuleSectionStart { .. }
+            | Payload::ModuleSectionEntry { .. } => features.module_linking = true,
+
+            Payload::CodeSectionEntry(body) => {
+                let mut locals = body.get_locals_reader()?;
+                for _ in 0..locals.get_count() {
+                    let (_, ty) = locals.read()?;
+                    value_type(&mut features, ty);
+                }
+                let mut operators = body.get_operators_reader()?;
+                while !operators.eof() {
+                    let (operator, offset) = operators.read_with_offset()?;
+                    operator_features(&mut features, &operator, data[offset]);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    if num_tables > 1 {
+        features.reference_types = true;
+    }
+    if num_memories > 1 {
+        features.multi_memory = true;
+    }
+
+    Ok(features)
+}
+
+fn value_type(features: &mut Features, ty: Type) {
+    match ty {
+        Type::V128 => features.simd = true,
+        Type::ExternRef | Type::FuncRef => features.reference_types = true,
+        _ => {}
+    }
+}
+
+fn table_type(features: &mut Features, table: &TableType) {
+    // Tables of `funcref`s are part of the MVP.
+    if table.element_type != Type::FuncRef {
+        features.reference_types = true;
+    }
+}
+
+fn memory_type(features: &mut Features, memory: &MemoryType) {
+    if memory.shared {
+        features.threads = true;
+    }
+    if memory.memory64 {
+        features.memory64 = true;
+    }
+}
+
+/// Constant expressions other than a single constant or `global.get`
+/// come from the extended constant expressions proposal.
+fn init_expr(features: &mut Features, expr: &InitExpr) -> Result<(), BinaryReaderError> {
+    let mut operators = expr.get_operators_reader();
+    while !operators.eof() {
+        match operators.read()? {
+            Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::F32Const { .. }
+            | Operator::F64Const { .. }
+            | Operator::GlobalGet { .. }
+            | Operator::RefNull { .. }
+            | Operator::RefFunc { .. }
+            | Operator::End => {}
+            Operator::V128Const { .. } => features.simd = true,
+            _ => features.extended_const = true,
+        }
+    }
+    Ok(())
+}
+
+/// Record the feature `operator` comes from, `prefix` being the first
+/// byte of its encoding.
+fn operator_features(features: &mut Features, operator: &Operator, prefix: u8) {
+    match prefix {
+        SIMD_PREFIX => features.simd = true,
+        THREADS_PREFIX => features.threads = true,
+        _ => {}
+    }
+
+    match *operator {
+        Operator::Block { ty }
+        | Operator::Loop { ty }
+        | Operator::If { ty }
+        | Operator::Try { ty } => {
+            if let Operator::Try { .. } = *operator {
+                features.exceptions = true;
+            }
+            match ty {
+                // Block types with parameters or several results.
+                TypeOrFuncType::FuncType(_) => features.multi_value = true,
+                TypeOrFuncType::Type(ty) => value_type(features, ty),
+            }
+        }
+
+        Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
+            features.tail_call = true;
+        }
+
+        Operator::Catch { .. }
+        | Operator::Throw { .. }
+        | Operator::Rethrow { .. }
+        | Operator::Delegate { .. }
+        | Operator::CatchAll { .. } => features.exceptions = true,
+
+        Operator::RefNull { .. }
+        | Operator::RefIsNull { .. }
+        | Operator::RefFunc { .. }
+        | Operator::TypedSelect { .. }
+        | Operator::TableGet { .. }
+        | Operator::TableSet { .. }
+        | Operator::TableGrow { .. }
+        | Operator::TableSize { .. }
+        | Operator::TableFill { .. } => features.reference_types = true,
+
+        Operator::CallIndirect { table_index, .. } if table_index != 0 => {
+            features.reference_types = true;
+        }
+
+        Operator::MemoryInit { .. }
+        | Operator::DataDrop { .. }
+        | Operator::MemoryCopy { .. }
+        | Operator::MemoryFill { .. }
+        | Operator::TableInit { .. }
+        | Operator::ElemDrop { .. }
+        | Operator::TableCopy { .. } => features.bulk_memory = true,
+
+        _ => {}
+    }
+}
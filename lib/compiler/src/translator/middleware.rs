@@ -26,6 +26,15 @@ pub trait ModuleMiddleware: Debug + Send + Sync + MemoryUsage {
 
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// An identifier of the middleware and of its configuration, part
+    /// of [`Compiler::deterministic_id`]. It's the name of its type by
+    /// default, which middlewares with settings extend with them.
+    ///
+    /// [`Compiler::deterministic_id`]: crate::Compiler::deterministic_id
+    fn deterministic_id(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
 }
 
 /// A function middleware specialized for a single function.
@@ -71,6 +80,10 @@ pub trait ModuleMiddlewareChain {
 
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+
+    /// The identifiers of the middlewares of the chain, in order, see
+    /// [`ModuleMiddleware::deterministic_id`].
+    fn deterministic_id(&self) -> String;
 }
 
 impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
@@ -90,6 +103,13 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
             item.transform_module_info(module_info);
         }
     }
+
+    fn deterministic_id(&self) -> String {
+        self.iter()
+            .map(|x| x.deterministic_id())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 }
 
 impl<'a> MiddlewareReaderState<'a> {
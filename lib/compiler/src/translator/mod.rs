@@ -6,6 +6,7 @@
 //!
 //! [cranelift-wasm]: https://crates.io/crates/cranelift-wasm/
 mod environ;
+mod features;
 mod middleware;
 mod module;
 mod state;
@@ -13,7 +14,10 @@ mod state;
 mod error;
 mod sections;
 
-pub use self::environ::{FunctionBinaryReader, FunctionBodyData, ModuleEnvironment};
+pub use self::environ::{
+    FunctionBinaryReader, FunctionBodyData, ModuleEnvironment, StreamedFunctionBody,
+};
+pub use self::features::features_required;
 pub use self::middleware::{
     FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleMiddleware,
     ModuleMiddlewareChain,
@@ -22,7 +22,7 @@ use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    IndexType, MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
 };
 use wasmparser::{
     self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
@@ -111,21 +111,9 @@ pub fn parse_import_section<'data>(
             ImportSectionEntryType::Tag(_) => {
                 unimplemented!("exception handling not implemented yet")
             }
-            ImportSectionEntryType::Memory(WPMemoryType {
-                shared,
-                memory64,
-                initial,
-                maximum,
-            }) => {
-                if memory64 {
-                    unimplemented!("64bit memory not implemented yet");
-                }
+            ImportSectionEntryType::Memory(ty) => {
                 environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(initial as u32),
-                        maximum: maximum.map(|p| Pages(p as u32)),
-                        shared,
-                    },
+                    memory_type(ty)?,
                     module_name,
                     field_name.unwrap_or_default(),
                 )?;
@@ -206,25 +194,32 @@ pub fn parse_memory_section(
     environ.reserve_memories(memories.get_count())?;
 
     for entry in memories {
-        let WPMemoryType {
-            shared,
-            memory64,
-            initial,
-            maximum,
-        } = entry?;
-        if memory64 {
-            unimplemented!("64bit memory not implemented yet");
-        }
-        environ.declare_memory(MemoryType {
-            minimum: Pages(initial as u32),
-            maximum: maximum.map(|p| Pages(p as u32)),
-            shared,
-        })?;
+        environ.declare_memory(memory_type(entry?)?)?;
     }
 
     Ok(())
 }
 
+/// Helper function translating a wasmparser memory type to a
+/// `MemoryType`, whose limits must fit in `Pages`.
+fn memory_type(ty: WPMemoryType) -> WasmResult<MemoryType> {
+    let pages = |count: u64| {
+        u32::try_from(count)
+            .map(Pages)
+            .map_err(|_| WasmError::ImplLimitExceeded)
+    };
+    Ok(MemoryType {
+        minimum: pages(ty.initial)?,
+        maximum: ty.maximum.map(pages).transpose()?,
+        shared: ty.shared,
+        index_type: if ty.memory64 {
+            IndexType::I64
+        } else {
+            IndexType::I32
+        },
+    })
+}
+
 /// Parses the Global section of the wasm module.
 pub fn parse_global_section(
     globals: GlobalSectionReader,
@@ -418,6 +413,11 @@ pub fn parse_data_section<'data>(
                 let mut init_expr_reader = init_expr.get_binary_reader();
                 let (base, offset) = match init_expr_reader.read_operator()? {
                     Operator::I32Const { value } => (None, value as u32 as usize),
+                    // The offsets of 64-bit memories past the address
+                    // space are out of bounds at instantiation.
+                    Operator::I64Const { value } => {
+                        (None, usize::try_from(value as u64).unwrap_or(usize::MAX))
+                    }
                     Operator::GlobalGet { global_index } => {
                         (Some(GlobalIndex::from_u32(global_index)), 0)
                     }
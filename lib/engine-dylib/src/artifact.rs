@@ -143,6 +143,7 @@ impl DylibArtifact {
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
         middlewares.apply_on_module_info(&mut module);
+        module.epoch_interruption = compiler.emits_epoch_checks();
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -1,11 +1,15 @@
 use crate::DylibEngine;
-use wasmer_compiler::{CompilerConfig, Features, Target};
+use wasmer_compiler::{CompilerConfig, Features, OptLevel, Target};
 
 /// The Dylib builder
 pub struct Dylib {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    #[cfg(feature = "compiler")]
+    deterministic: bool,
+    #[allow(dead_code)]
+    opt_level: Option<OptLevel>,
 }
 
 impl Dylib {
@@ -22,6 +26,9 @@ impl Dylib {
             compiler_config: Some(compiler_config),
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
+            opt_level: None,
         }
     }
 
@@ -31,6 +38,9 @@ impl Dylib {
             compiler_config: None,
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
+            opt_level: None,
         }
     }
 
@@ -46,16 +56,64 @@ impl Dylib {
         self
     }
 
+    /// Enable the deterministic mode, for executions giving the same
+    /// results on every machine and with every compiler.
+    ///
+    /// The compiler canonicalizes the NaNs produced by the
+    /// floating-point instructions, and the proposals with
+    /// nondeterministic instructions are disabled whatever the
+    /// features, see [`Features::disable_nondeterministic`]. None of
+    /// the compilers relax the rounding of the floating-point
+    /// instructions, with or without this mode.
+    ///
+    /// This doesn't cover the artifacts deserialized by the engine,
+    /// nor the limits of the host, such as how far memories can grow
+    /// or how deep calls can go. Only the engines compiling modules,
+    /// with the `compiler` feature, have a deterministic mode.
+    #[cfg(feature = "compiler")]
+    pub fn deterministic(mut self, enable: bool) -> Self {
+        self.deterministic = enable;
+        self
+    }
+
+    /// Bound the number of threads compiling the functions of the
+    /// modules to `threads`, instead of using all the cores.
+    ///
+    /// The compiler compiles the functions in a thread pool of its own,
+    /// built once with the engine, the thread creating a module waiting
+    /// for them. `0` compiles the functions on a single thread, like
+    /// `1`. See [`CompilerConfig::num_compile_threads`].
+    pub fn num_compile_threads(mut self, threads: usize) -> Self {
+        if let Some(compiler_config) = self.compiler_config.as_mut() {
+            compiler_config.num_compile_threads(threads);
+        }
+        self
+    }
+
+    /// Set the optimization level of the generated code, see
+    /// [`CompilerConfig::set_opt_level`].
+    pub fn opt_level(mut self, level: OptLevel) -> Self {
+        self.opt_level = Some(level);
+        self
+    }
+
     /// Build the `DylibEngine` for this configuration
     pub fn engine(self) -> DylibEngine {
         if let Some(_compiler_config) = self.compiler_config {
             #[cfg(feature = "compiler")]
             {
-                let compiler_config = _compiler_config;
+                let mut compiler_config = _compiler_config;
                 let target = self.target.unwrap_or_default();
-                let features = self
+                let mut features = self
                     .features
                     .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                if self.deterministic {
+                    compiler_config.canonicalize_nans(true);
+                    features.disable_nondeterministic();
+                }
+                if let Some(level) = self.opt_level {
+                    compiler_config.set_opt_level(level);
+                }
                 let compiler = compiler_config.compiler();
                 DylibEngine::new(compiler, target, features)
             }
@@ -9,7 +9,9 @@ use std::sync::Mutex;
 use wasmer_compiler::{CompileError, Target};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{Compiler, Triple};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{
+    Artifact, DeserializeError, Engine, EngineEpochController, EngineId, InstanceRegistry, Tunables,
+};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
@@ -24,6 +26,8 @@ pub struct DylibEngine {
     /// The target for the compiler
     target: Arc<Target>,
     engine_id: EngineId,
+    epoch_controller: EngineEpochController,
+    instance_registry: InstanceRegistry,
 }
 
 impl DylibEngine {
@@ -46,6 +50,8 @@ impl DylibEngine {
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -78,6 +84,8 @@ impl DylibEngine {
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -131,6 +139,11 @@ impl Engine for DylibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The WebAssembly features
+    fn features(&self) -> Features {
+        self.inner().features().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -179,9 +192,27 @@ impl Engine for DylibEngine {
         &self.engine_id
     }
 
+    fn deterministic_id(&self) -> String {
+        #[cfg(feature = "compiler")]
+        {
+            if let Ok(compiler) = self.inner().compiler() {
+                return format!("dylib-{}", compiler.deterministic_id());
+            }
+        }
+        "dylib-headless".to_string()
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
+
+    fn epoch_controller(&self) -> EngineEpochController {
+        self.epoch_controller.clone()
+    }
+
+    fn instance_registry(&self) -> InstanceRegistry {
+        self.instance_registry.clone()
+    }
 }
 
 #[derive(Clone, Copy, MemoryUsage)]
@@ -6,6 +6,8 @@ pub struct Staticlib {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    #[cfg(feature = "compiler")]
+    deterministic: bool,
 }
 
 impl Staticlib {
@@ -22,6 +24,8 @@ impl Staticlib {
             compiler_config: Some(compiler_config),
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
         }
     }
 
@@ -31,6 +35,8 @@ impl Staticlib {
             compiler_config: None,
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
         }
     }
 
@@ -46,16 +52,40 @@ impl Staticlib {
         self
     }
 
+    /// Enable the deterministic mode, for executions giving the same
+    /// results on every machine and with every compiler.
+    ///
+    /// The compiler canonicalizes the NaNs produced by the
+    /// floating-point instructions, and the proposals with
+    /// nondeterministic instructions are disabled whatever the
+    /// features, see [`Features::disable_nondeterministic`]. None of
+    /// the compilers relax the rounding of the floating-point
+    /// instructions, with or without this mode.
+    ///
+    /// This doesn't cover the artifacts deserialized by the engine,
+    /// nor the limits of the host, such as how far memories can grow
+    /// or how deep calls can go. Only the engines compiling modules,
+    /// with the `compiler` feature, have a deterministic mode.
+    #[cfg(feature = "compiler")]
+    pub fn deterministic(mut self, enable: bool) -> Self {
+        self.deterministic = enable;
+        self
+    }
+
     /// Build the `StaticlibEngine` for this configuration
     pub fn engine(self) -> StaticlibEngine {
         if let Some(_compiler_config) = self.compiler_config {
             #[cfg(feature = "compiler")]
             {
-                let compiler_config = _compiler_config;
+                let mut compiler_config = _compiler_config;
                 let target = self.target.unwrap_or_default();
-                let features = self
+                let mut features = self
                     .features
                     .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                if self.deterministic {
+                    compiler_config.canonicalize_nans(true);
+                    features.disable_nondeterministic();
+                }
                 let compiler = compiler_config.compiler();
                 StaticlibEngine::new(compiler, target, features)
             }
@@ -6,7 +6,9 @@ use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::Compiler;
 use wasmer_compiler::{CompileError, Target};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{
+    Artifact, DeserializeError, Engine, EngineEpochController, EngineId, InstanceRegistry, Tunables,
+};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
@@ -21,6 +23,8 @@ pub struct StaticlibEngine {
     /// The target for the compiler
     target: Arc<Target>,
     engine_id: EngineId,
+    epoch_controller: EngineEpochController,
+    instance_registry: InstanceRegistry,
 }
 
 impl StaticlibEngine {
@@ -37,6 +41,8 @@ impl StaticlibEngine {
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -66,6 +72,8 @@ impl StaticlibEngine {
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -120,6 +128,11 @@ impl Engine for StaticlibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The WebAssembly features
+    fn features(&self) -> Features {
+        self.inner().features().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -172,9 +185,27 @@ impl Engine for StaticlibEngine {
         &self.engine_id
     }
 
+    fn deterministic_id(&self) -> String {
+        #[cfg(feature = "compiler")]
+        {
+            if let Ok(compiler) = self.inner().compiler() {
+                return format!("staticlib-{}", compiler.deterministic_id());
+            }
+        }
+        "staticlib-headless".to_string()
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
+
+    fn epoch_controller(&self) -> EngineEpochController {
+        self.epoch_controller.clone()
+    }
+
+    fn instance_registry(&self) -> InstanceRegistry {
+        self.instance_registry.clone()
+    }
 }
 
 /// The inner contents of `StaticlibEngine`
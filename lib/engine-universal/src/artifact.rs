@@ -2,7 +2,7 @@
 //! done as separate steps.
 
 use crate::engine::{UniversalEngine, UniversalEngineInner};
-use crate::link::link_module;
+use crate::link::{is_position_independent, link_module};
 #[cfg(feature = "compiler")]
 use crate::serialize::SerializableCompilation;
 use crate::serialize::SerializableModule;
@@ -10,13 +10,19 @@ use crate::trampoline::{libcall_trampoline_len, make_libcall_trampolines};
 use enumset::EnumSet;
 use loupe::MemoryUsage;
 use std::mem;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use wasmer_compiler::{CompileError, CpuFeature, Features, Triple};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment, ModuleMiddlewareChain};
+use wasmer_compiler::{
+    Compilation, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    ModuleMiddlewareChain, ModuleTranslationState,
+};
+use wasmer_compiler::{
+    CompilationMetrics, CompileError, CpuFeature, Features, PerfDiagnostic, StackMap, Triple,
+};
 use wasmer_engine::{
     register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
-    MetadataHeader, SerializeError,
+    InstantiationError, LinkError, MetadataHeader, SerializeError,
 };
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
@@ -42,10 +48,22 @@ pub struct UniversalArtifact {
     func_data_registry: Arc<FuncDataRegistry>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    /// Whether the artifact was compiled for a target other than the
+    /// host, in which case its code is never loaded into memory.
+    is_cross_compiled: bool,
+    /// The performance diagnostics of the compiler, which are not
+    /// serialized.
+    perf_diagnostics: Vec<PerfDiagnostic>,
+    /// The metrics of the compilation, which are not serialized.
+    #[loupe(skip)]
+    compilation_metrics: Option<CompilationMetrics>,
+    /// The stack maps of each function, when enabled in the compiler,
+    /// which are not serialized.
+    stack_maps: PrimaryMap<LocalFunctionIndex, Vec<StackMap>>,
 }
 
 impl UniversalArtifact {
-    const MAGIC_HEADER: &'static [u8; 16] = b"wasmer-universal";
+    pub(crate) const MAGIC_HEADER: &'static [u8; 16] = b"wasmer-universal";
 
     /// Check if the provided bytes look like a serialized `UniversalArtifact`.
     pub fn is_deserializable(bytes: &[u8]) -> bool {
@@ -60,17 +78,84 @@ impl UniversalArtifact {
         tunables: &dyn Tunables,
     ) -> Result<Self, CompileError> {
         let environ = ModuleEnvironment::new();
+        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+
+        let compile_info = {
+            let inner_engine = engine.inner();
+            Self::compile_info(
+                inner_engine.compiler()?,
+                inner_engine.features(),
+                translation.module,
+                tunables,
+            )
+        };
+
+        let data_initializers = translation
+            .data_initializers
+            .iter()
+            .map(OwnedDataInitializer::new)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Self::from_translation(
+            engine,
+            compile_info,
+            // SAFETY: Calling `unwrap` is correct since
+            // `environ.translate()` above will write some data into
+            // `module_translation_state`.
+            translation.module_translation_state.as_ref().unwrap(),
+            translation.function_body_inputs,
+            data_initializers,
+            data,
+        )
+    }
+
+    /// Compile the function bodies of a translated module.
+    ///
+    /// `binary` is the module the translation comes from, kept in
+    /// the artifact if the compiler retains the binaries.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn from_translation(
+        engine: &UniversalEngine,
+        compile_info: CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        data_initializers: Box<[OwnedDataInitializer]>,
+        binary: &[u8],
+    ) -> Result<Self, CompileError> {
         let mut inner_engine = engine.inner_mut();
-        let features = inner_engine.features();
 
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        // Compile the Module
+        let compilation = inner_engine.compiler()?.compile_module(
+            &engine.target(),
+            &compile_info,
+            module_translation_state,
+            function_body_inputs,
+        )?;
 
-        let compiler = inner_engine.compiler()?;
+        Self::from_compilation(
+            engine,
+            &mut inner_engine,
+            compile_info,
+            compilation,
+            data_initializers,
+            binary,
+        )
+    }
 
+    /// Apply the middlewares of `compiler` to a translated module, and
+    /// pick the styles of its memories and tables.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn compile_info(
+        compiler: &dyn Compiler,
+        features: &Features,
+        mut module: ModuleInfo,
+        tunables: &dyn Tunables,
+    ) -> CompileModuleInfo {
         // We try to apply the middleware first
-        let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
         middlewares.apply_on_module_info(&mut module);
+        module.epoch_interruption = compiler.emits_epoch_checks();
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -83,33 +168,32 @@ impl UniversalArtifact {
             .map(|table_type| tunables.table_style(table_type))
             .collect();
 
-        let compile_info = CompileModuleInfo {
+        CompileModuleInfo {
             module: Arc::new(module),
             features: features.clone(),
             memory_styles,
             table_styles,
-        };
+        }
+    }
 
-        // Compile the Module
-        let compilation = compiler.compile_module(
-            &engine.target(),
-            &compile_info,
-            // SAFETY: Calling `unwrap` is correct since
-            // `environ.translate()` above will write some data into
-            // `module_translation_state`.
-            translation.module_translation_state.as_ref().unwrap(),
-            translation.function_body_inputs,
-        )?;
+    /// Build a `UniversalArtifact` out of the result of a compilation.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn from_compilation(
+        engine: &UniversalEngine,
+        inner_engine: &mut UniversalEngineInner,
+        compile_info: CompileModuleInfo,
+        compilation: Compilation,
+        data_initializers: Box<[OwnedDataInitializer]>,
+        binary: &[u8],
+    ) -> Result<Self, CompileError> {
+        let wasm_bytes = if inner_engine.compiler()?.retains_wasm_bytes() {
+            Some(binary.to_vec())
+        } else {
+            None
+        };
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
 
-        let data_initializers = translation
-            .data_initializers
-            .iter()
-            .map(OwnedDataInitializer::new)
-            .collect::<Vec<_>>()
-            .into_boxed_slice();
-
         let frame_infos = compilation.get_frame_info();
 
         // Synthesize a custom section to hold the libcall trampolines.
@@ -137,8 +221,21 @@ impl UniversalArtifact {
             compile_info,
             data_initializers,
             cpu_features: engine.target().cpu_features().as_u64(),
+            triple: engine.target().triple().to_string(),
+            wasm_bytes,
         };
-        Self::from_parts(&mut inner_engine, serializable)
+
+        // Code compiled for another target can be serialized, but it
+        // can't be linked or published into this host's memory.
+        let mut artifact = if !Self::is_compatible_with_host(engine.target().triple()) {
+            Self::cross_compiled(inner_engine, serializable)
+        } else {
+            Self::from_parts(inner_engine, serializable)?
+        };
+        artifact.perf_diagnostics = compilation.get_perf_diagnostics();
+        artifact.compilation_metrics = Some(compilation.get_metrics());
+        artifact.stack_maps = compilation.get_stack_maps().unwrap_or_default();
+        Ok(artifact)
     }
 
     /// Compile a data buffer into a `UniversalArtifact`, which may then be instantiated.
@@ -167,6 +264,19 @@ impl UniversalArtifact {
         let metadata_len = MetadataHeader::parse(bytes)?;
         let metadata_slice: &[u8] = &bytes[MetadataHeader::LEN..][..metadata_len];
         let serializable = SerializableModule::deserialize(metadata_slice)?;
+        let triple = Triple::from_str(&serializable.triple).map_err(|e| {
+            DeserializeError::CorruptedBinary(format!(
+                "invalid target triple `{}`: {}",
+                serializable.triple, e
+            ))
+        })?;
+        if !Self::is_compatible_with_host(&triple) {
+            return Err(DeserializeError::Incompatible(format!(
+                "the module was compiled for `{}`, but the host is `{}`",
+                triple,
+                Triple::host()
+            )));
+        }
         Self::from_parts(&mut universal.inner_mut(), serializable)
             .map_err(DeserializeError::Compiler)
     }
@@ -189,6 +299,16 @@ impl UniversalArtifact {
             &serializable.compilation.custom_sections,
         )?;
 
+        // The code is shared under a name computed before it is linked,
+        // see `SharedCodeDir`.
+        let shared_code_key = inner_engine.shared_code_key(|| {
+            is_position_independent(
+                &serializable.compilation.function_relocations,
+                &serializable.compilation.custom_sections,
+                &serializable.compilation.custom_section_relocations,
+            )
+        });
+
         link_module(
             &serializable.compile_info.module,
             &finished_functions,
@@ -226,7 +346,7 @@ impl UniversalArtifact {
         };
 
         // Make all code compiled thus far executable.
-        inner_engine.publish_compiled_code();
+        inner_engine.publish_compiled_code(shared_code_key)?;
 
         inner_engine.publish_eh_frame(eh_frame)?;
 
@@ -256,9 +376,52 @@ impl UniversalArtifact {
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
             func_data_registry,
+            is_cross_compiled: false,
+            perf_diagnostics: Vec::new(),
+            compilation_metrics: None,
+            stack_maps: PrimaryMap::new(),
         })
     }
 
+    /// Construct a `UniversalArtifact` for a module compiled for a
+    /// different target than the host.
+    ///
+    /// The compiled code is kept as is so it can be serialized, but
+    /// nothing is allocated, linked or published into memory, so the
+    /// resulting artifact can't be instantiated.
+    #[cfg(feature = "compiler")]
+    fn cross_compiled(
+        inner_engine: &UniversalEngineInner,
+        serializable: SerializableModule,
+    ) -> Self {
+        Self {
+            serializable,
+            finished_functions: PrimaryMap::new().into_boxed_slice(),
+            finished_function_call_trampolines: PrimaryMap::new().into_boxed_slice(),
+            finished_dynamic_function_trampolines: PrimaryMap::new().into_boxed_slice(),
+            signatures: PrimaryMap::new().into_boxed_slice(),
+            frame_info_registration: Mutex::new(None),
+            finished_function_lengths: PrimaryMap::new().into_boxed_slice(),
+            func_data_registry: inner_engine.func_data().clone(),
+            is_cross_compiled: true,
+            perf_diagnostics: Vec::new(),
+            compilation_metrics: None,
+            stack_maps: PrimaryMap::new(),
+        }
+    }
+
+    /// Check whether code compiled for the given triple can be loaded
+    /// and run on the host.
+    pub(crate) fn is_compatible_with_host(triple: &Triple) -> bool {
+        let host = Triple::host();
+        triple.architecture == host.architecture && triple.operating_system == host.operating_system
+    }
+
+    /// Returns the target triple this artifact was compiled for.
+    pub fn triple(&self) -> &str {
+        &self.serializable.triple
+    }
+
     /// Get the default extension when serializing this artifact
     pub fn get_default_extension(_triple: &Triple) -> &'static str {
         // `.wasmu` is the default extension for all the triples. It
@@ -281,6 +444,10 @@ impl Artifact for UniversalArtifact {
     }
 
     fn register_frame_info(&self) {
+        if self.is_cross_compiled {
+            return;
+        }
+
         let mut info = self.frame_info_registration.lock().unwrap();
 
         if info.is_some() {
@@ -343,6 +510,34 @@ impl Artifact for UniversalArtifact {
     fn func_data_registry(&self) -> &FuncDataRegistry {
         &self.func_data_registry
     }
+
+    fn perf_diagnostics(&self) -> &[PerfDiagnostic] {
+        &self.perf_diagnostics
+    }
+
+    fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        self.compilation_metrics.as_ref()
+    }
+
+    fn stack_maps(&self, function: LocalFunctionIndex) -> &[StackMap] {
+        self.stack_maps.get(function).map_or(&[], Vec::as_slice)
+    }
+
+    fn wasm_bytes(&self) -> Option<&[u8]> {
+        self.serializable.wasm_bytes.as_deref()
+    }
+
+    fn preinstantiate(&self) -> Result<(), InstantiationError> {
+        if self.is_cross_compiled {
+            return Err(InstantiationError::Link(LinkError::Resource(format!(
+                "the module was compiled for `{}` and can't be instantiated on `{}`",
+                self.serializable.triple,
+                Triple::host()
+            ))));
+        }
+        Ok(())
+    }
+
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         let serialized_data = self.serializable.serialize()?;
         assert!(mem::align_of::<SerializableModule>() <= MetadataHeader::ALIGN);
@@ -1,5 +1,10 @@
+#[cfg(unix)]
+use crate::shared_code::SharedCodeDir;
 use crate::UniversalEngine;
-use wasmer_compiler::{CompilerConfig, Features, Target};
+use loupe::MemoryUsage;
+#[cfg(unix)]
+use std::path::PathBuf;
+use wasmer_compiler::{CompilerConfig, Features, OptLevel, Target};
 
 /// The Universal builder
 pub struct Universal {
@@ -7,6 +12,13 @@ pub struct Universal {
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    #[cfg(feature = "compiler")]
+    deterministic: bool,
+    compilation_strategy: CompilationStrategy,
+    #[allow(dead_code)]
+    opt_level: Option<OptLevel>,
+    #[cfg(unix)]
+    shared_code_dir: Option<PathBuf>,
 }
 
 impl Universal {
@@ -19,6 +31,12 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
+            compilation_strategy: CompilationStrategy::default(),
+            opt_level: None,
+            #[cfg(unix)]
+            shared_code_dir: None,
         }
     }
 
@@ -28,6 +46,12 @@ impl Universal {
             compiler_config: None,
             target: None,
             features: None,
+            #[cfg(feature = "compiler")]
+            deterministic: false,
+            compilation_strategy: CompilationStrategy::default(),
+            opt_level: None,
+            #[cfg(unix)]
+            shared_code_dir: None,
         }
     }
 
@@ -43,24 +67,141 @@ impl Universal {
         self
     }
 
+    /// Enable the deterministic mode, for executions giving the same
+    /// results on every machine and with every compiler.
+    ///
+    /// The compiler canonicalizes the NaNs produced by the
+    /// floating-point instructions, and the proposals with
+    /// nondeterministic instructions are disabled whatever the
+    /// features, see [`Features::disable_nondeterministic`]. None of
+    /// the compilers relax the rounding of the floating-point
+    /// instructions, with or without this mode.
+    ///
+    /// This doesn't cover the artifacts deserialized by the engine,
+    /// nor the limits of the host, such as how far memories can grow
+    /// or how deep calls can go. Only the engines compiling modules,
+    /// with the `compiler` feature, have a deterministic mode.
+    #[cfg(feature = "compiler")]
+    pub fn deterministic(mut self, enable: bool) -> Self {
+        self.deterministic = enable;
+        self
+    }
+
+    /// Set when the functions of the modules are compiled, see
+    /// [`CompilationStrategy`].
+    pub fn compilation_strategy(mut self, strategy: CompilationStrategy) -> Self {
+        self.compilation_strategy = strategy;
+        self
+    }
+
+    /// Bound the number of threads compiling the functions of the
+    /// modules to `threads`, instead of using all the cores.
+    ///
+    /// The compiler compiles the functions in a thread pool of its own,
+    /// built once with the engine, the thread creating a module waiting
+    /// for them. `0` compiles the functions on a single thread, like
+    /// `1`. See [`CompilerConfig::num_compile_threads`].
+    pub fn num_compile_threads(mut self, threads: usize) -> Self {
+        if let Some(compiler_config) = self.compiler_config.as_mut() {
+            compiler_config.num_compile_threads(threads);
+        }
+        self
+    }
+
+    /// Set the optimization level of the generated code, see
+    /// [`CompilerConfig::set_opt_level`].
+    pub fn opt_level(mut self, level: OptLevel) -> Self {
+        self.opt_level = Some(level);
+        self
+    }
+
+    /// Share the compiled code through the files of a directory.
+    ///
+    /// The executable pages of each artifact, once linked, are written
+    /// to a read-only file named after the hash of their content. An
+    /// artifact with the same code, in this or any process using the
+    /// same directory, maps that file instead of keeping its own copy
+    /// of the code. Code resolving its relocations to different
+    /// addresses (for instance the libcalls of processes that don't
+    /// share their address space layout) can't be shared.
+    ///
+    /// The files are never removed by the engine.
+    #[cfg(unix)]
+    pub fn with_shared_code_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.shared_code_dir = Some(path.into());
+        self
+    }
+
     /// Build the `UniversalEngine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> UniversalEngine {
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
-            let features = self
+        let engine = if let Some(mut compiler_config) = self.compiler_config {
+            let mut features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+            if self.deterministic {
+                compiler_config.canonicalize_nans(true);
+                features.disable_nondeterministic();
+            }
+            if let Some(level) = self.opt_level {
+                compiler_config.set_opt_level(level);
+            }
             let compiler = compiler_config.compiler();
             UniversalEngine::new(compiler, target, features)
         } else {
             UniversalEngine::headless()
-        }
+        };
+        engine.inner_mut().compilation_strategy = self.compilation_strategy;
+        #[cfg(unix)]
+        Self::set_shared_code_dir(&engine, self.shared_code_dir);
+        engine
     }
 
     /// Build the `UniversalEngine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> UniversalEngine {
-        UniversalEngine::headless()
+        let engine = UniversalEngine::headless();
+        #[cfg(unix)]
+        Self::set_shared_code_dir(&engine, self.shared_code_dir);
+        engine
+    }
+
+    #[cfg(unix)]
+    fn set_shared_code_dir(engine: &UniversalEngine, shared_code_dir: Option<PathBuf>) {
+        engine.inner_mut().shared_code = shared_code_dir.map(SharedCodeDir::new);
+    }
+}
+
+/// When the `UniversalEngine` compiles the functions of a module, see
+/// [`Universal::compilation_strategy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+pub enum CompilationStrategy {
+    /// The functions are compiled when the module is created.
+    Eager,
+    /// The module is only validated and translated when it is
+    /// created, and its functions are compiled the first time it is
+    /// instantiated or serialized.
+    ///
+    /// The compiled functions call each other directly, so they are
+    /// all compiled together rather than one by one on their first
+    /// call: what is saved is the compilation of the modules which
+    /// are never instantiated. A compilation error, which only comes
+    /// from the middlewares or the limits of the compiler since the
+    /// module is validated up front, is returned by the instantiation
+    /// as an [`InstantiationError::Compile`].
+    ///
+    /// The serialized modules are compiled, and so are the modules
+    /// deserialized or compiled for another target than the host.
+    /// The compilation metrics, diagnostics and stack maps of a module
+    /// are only available once it is compiled.
+    ///
+    /// [`InstantiationError::Compile`]: wasmer_engine::InstantiationError::Compile
+    Lazy,
+}
+
+impl Default for CompilationStrategy {
+    fn default() -> Self {
+        Self::Eager
     }
 }
@@ -2,8 +2,14 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 //! Memory management for executable code.
+#[cfg(unix)]
+use crate::shared_code::SharedCodeDir;
 use crate::unwind::UnwindRegistry;
 use loupe::MemoryUsage;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
 use wasmer_compiler::{CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
@@ -153,6 +159,49 @@ impl CodeMemory {
         .expect("unable to make memory readonly and executable");
     }
 
+    /// The executable pages, up to the end of the last page.
+    pub(crate) fn executable_pages(&self) -> &[u8] {
+        if self.mmap.is_empty() {
+            return &[];
+        }
+        let len = round_up(self.start_of_nonexecutable_pages, region::page::size());
+        &self.mmap.as_slice()[..len]
+    }
+
+    /// Replace the executable pages, once published, with a mapping
+    /// of the file of `shared_code` named `key` holding the same code,
+    /// so that the pages are shared with the other artifacts mapping
+    /// it.
+    ///
+    /// The code stays at the same address, so the pointers to it, and
+    /// the unwind information registered for it, remain valid.
+    #[cfg(unix)]
+    pub(crate) fn publish_shared(
+        &mut self,
+        shared_code: &SharedCodeDir,
+        key: &blake3::Hash,
+    ) -> io::Result<()> {
+        if self.mmap.is_empty() || self.start_of_nonexecutable_pages == 0 {
+            return Ok(());
+        }
+        let len = round_up(self.start_of_nonexecutable_pages, region::page::size());
+        let file = shared_code.open(key, &self.mmap.as_slice()[..len])?;
+        let ptr = unsafe {
+            libc::mmap(
+                self.mmap.as_mut_ptr() as *mut libc::c_void,
+                len,
+                libc::PROT_READ | libc::PROT_EXEC,
+                libc::MAP_SHARED | libc::MAP_FIXED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
     /// Calculates the allocation size of the given compiled function.
     fn function_allocation_size(func: &FunctionBody) -> usize {
         match &func.unwind_info {
@@ -0,0 +1,248 @@
+//! Named metadata sections attached by the embedder to a serialized
+//! [`UniversalArtifact`], see
+//! [`UniversalArtifact::patch_embedder_metadata`].
+//!
+//! The sections are stored after the serialized module, which
+//! deserialization ignores:
+//!
+//! ```text
+//! magic: b"WASMERMD"
+//! count: u32
+//! count times:
+//!     name_len: u32, name: [u8; name_len]
+//!     data_len: u64, data: [u8; data_len]
+//! checksum: [u8; 32]
+//! ```
+//!
+//! All the integers are little-endian, the checksum is the BLAKE3
+//! hash of the bytes preceding it, from the magic. Patching the
+//! sections rewrites the bytes following the serialized module only,
+//! the code and data of the module are left byte-identical.
+
+use crate::UniversalArtifact;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use wasmer_engine::MetadataHeader;
+
+const MAGIC: &[u8; 8] = b"WASMERMD";
+
+const CHECKSUM_LEN: usize = 32;
+
+/// The length of the magic header and the metadata header at the
+/// start of a serialized artifact, giving the length of the module.
+const HEADER_LEN: usize = UniversalArtifact::MAGIC_HEADER.len() + MetadataHeader::LEN;
+
+/// An error reading or patching the embedder metadata of a serialized
+/// artifact.
+#[derive(Debug)]
+pub enum PatchError {
+    /// The bytes aren't a serialized `UniversalArtifact` of this
+    /// version of Wasmer.
+    Incompatible(String),
+    /// The embedder metadata following the serialized module is
+    /// malformed.
+    Corrupted(String),
+    /// The artifact file couldn't be read or written.
+    Io(io::Error),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Incompatible(message) => {
+                write!(f, "not a compatible serialized artifact: {}", message)
+            }
+            Self::Corrupted(message) => write!(f, "corrupted embedder metadata: {}", message),
+            Self::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PatchError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// The 16-byte aligned copy of a metadata header, as expected by
+/// [`MetadataHeader::parse`].
+#[repr(C, align(16))]
+struct AlignedHeader([u8; MetadataHeader::LEN]);
+
+/// Returns the length of the serialized module at the start of an
+/// artifact of `total_len` bytes starting with `bytes`, after which
+/// the embedder metadata starts.
+fn module_len(bytes: &[u8], total_len: u64) -> Result<u64, PatchError> {
+    if !UniversalArtifact::is_deserializable(bytes) {
+        return Err(PatchError::Incompatible(
+            "the bytes are not wasmer-universal".to_string(),
+        ));
+    }
+    let header_start = UniversalArtifact::MAGIC_HEADER.len();
+    let header = bytes
+        .get(header_start..header_start + MetadataHeader::LEN)
+        .ok_or_else(|| PatchError::Incompatible("truncated metadata header".to_string()))?;
+    let header = AlignedHeader(header.try_into().unwrap());
+    let payload_len =
+        MetadataHeader::parse(&header.0).map_err(|e| PatchError::Incompatible(e.to_string()))?;
+    let len = HEADER_LEN as u64 + payload_len as u64;
+    if len > total_len {
+        return Err(PatchError::Incompatible(
+            "the serialized module is truncated".to_string(),
+        ));
+    }
+    Ok(len)
+}
+
+/// Reads the sections of the embedder metadata `bytes`.
+fn parse_sections(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, PatchError> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    if bytes.len() < CHECKSUM_LEN {
+        return Err(PatchError::Corrupted("unexpected end of data".to_string()));
+    }
+    let (mut bytes, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if blake3::hash(bytes).as_bytes() != checksum {
+        return Err(PatchError::Corrupted("checksum mismatch".to_string()));
+    }
+
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], PatchError> {
+        if bytes.len() < len {
+            return Err(PatchError::Corrupted("unexpected end of data".to_string()));
+        }
+        let (taken, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(taken)
+    }
+    fn take_len(bytes: &mut &[u8], width: usize) -> Result<usize, PatchError> {
+        let mut le_bytes = [0; 8];
+        le_bytes[..width].copy_from_slice(take(bytes, width)?);
+        u64::from_le_bytes(le_bytes)
+            .try_into()
+            .map_err(|_| PatchError::Corrupted("length out of range".to_string()))
+    }
+
+    if take(&mut bytes, MAGIC.len())? != MAGIC {
+        return Err(PatchError::Corrupted(
+            "unexpected bytes after the serialized module".to_string(),
+        ));
+    }
+    let count = take_len(&mut bytes, 4)?;
+    let mut sections = Vec::new();
+    for _ in 0..count {
+        let name_len = take_len(&mut bytes, 4)?;
+        let name = String::from_utf8(take(&mut bytes, name_len)?.to_vec())
+            .map_err(|_| PatchError::Corrupted("section name is not UTF-8".to_string()))?;
+        let data_len = take_len(&mut bytes, 8)?;
+        let data = take(&mut bytes, data_len)?.to_vec();
+        sections.push((name, data));
+    }
+    if !bytes.is_empty() {
+        return Err(PatchError::Corrupted(
+            "unexpected bytes after the last section".to_string(),
+        ));
+    }
+    Ok(sections)
+}
+
+/// Replaces the sections of `existing` named in `sections`, or adds
+/// them after the existing ones, and encodes the result.
+fn patch_sections(mut existing: Vec<(String, Vec<u8>)>, sections: &[(&str, &[u8])]) -> Vec<u8> {
+    for (name, data) in sections {
+        match existing.iter_mut().find(|(other, _)| other == name) {
+            Some((_, data_before)) => *data_before = data.to_vec(),
+            None => existing.push((name.to_string(), data.to_vec())),
+        }
+    }
+
+    let mut output = Vec::with_capacity(
+        MAGIC.len()
+            + 4
+            + existing
+                .iter()
+                .map(|(name, data)| 12 + name.len() + data.len())
+                .sum::<usize>()
+            + CHECKSUM_LEN,
+    );
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&(existing.len() as u32).to_le_bytes());
+    for (name, data) in &existing {
+        output.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        output.extend_from_slice(name.as_bytes());
+        output.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        output.extend_from_slice(data);
+    }
+    let checksum = blake3::hash(&output);
+    output.extend_from_slice(checksum.as_bytes());
+    output
+}
+
+impl UniversalArtifact {
+    /// Returns the embedder metadata sections of the serialized
+    /// artifact `bytes`, in order, see
+    /// [`UniversalArtifact::patch_embedder_metadata`].
+    pub fn embedder_metadata(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, PatchError> {
+        let module_len = module_len(bytes, bytes.len() as u64)? as usize;
+        parse_sections(&bytes[module_len..])
+    }
+
+    /// Returns a copy of the serialized artifact `bytes` whose
+    /// embedder metadata sections named in `sections` are replaced,
+    /// or added after the existing ones, with the given data.
+    ///
+    /// The serialized module is copied as is, without being
+    /// deserialized: patching is cheap even for large artifacts, and
+    /// the patched artifact deserializes to the same module.
+    pub fn patch_embedder_metadata(
+        bytes: &[u8],
+        sections: &[(&str, &[u8])],
+    ) -> Result<Vec<u8>, PatchError> {
+        let module_len = module_len(bytes, bytes.len() as u64)? as usize;
+        let metadata = patch_sections(parse_sections(&bytes[module_len..])?, sections);
+        let mut output = Vec::with_capacity(module_len + metadata.len());
+        output.extend_from_slice(&bytes[..module_len]);
+        output.extend_from_slice(&metadata);
+        Ok(output)
+    }
+
+    /// Like [`UniversalArtifact::patch_embedder_metadata`], but
+    /// patches the artifact serialized in the file at `path` in place.
+    ///
+    /// Only the embedder metadata, at the end of the file, is read and
+    /// rewritten: the serialized module isn't read at all.
+    pub fn patch_embedder_metadata_file(
+        path: impl AsRef<Path>,
+        sections: &[(&str, &[u8])],
+    ) -> Result<(), PatchError> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        (&mut file)
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut header)?;
+        let module_len = module_len(&header, file_len)?;
+
+        file.seek(SeekFrom::Start(module_len))?;
+        let mut metadata = Vec::new();
+        file.read_to_end(&mut metadata)?;
+        let metadata = patch_sections(parse_sections(&metadata)?, sections);
+
+        file.seek(SeekFrom::Start(module_len))?;
+        file.write_all(&metadata)?;
+        file.set_len(module_len + metadata.len() as u64)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
 //! Universal compilation.
 
-use crate::{CodeMemory, UniversalArtifact};
+#[cfg(feature = "compiler")]
+use crate::lazy::LazyArtifact;
+#[cfg(unix)]
+use crate::shared_code::{SharedCodeDir, SharedCodeStats};
+#[cfg(feature = "compiler")]
+use crate::streaming::UniversalStreamingCompilation;
+use crate::{CodeMemory, CompilationStrategy, UniversalArtifact};
 use loupe::MemoryUsage;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
@@ -8,7 +14,10 @@ use wasmer_compiler::Compiler;
 use wasmer_compiler::{
     CompileError, CustomSection, CustomSectionProtection, FunctionBody, SectionIndex, Target,
 };
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, FunctionExtent, Tunables};
+use wasmer_engine::{
+    Artifact, DeserializeError, Engine, EngineEpochController, EngineId, FunctionExtent,
+    InstanceRegistry, StreamingCompilation, Tunables,
+};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
     Features, FunctionIndex, FunctionType, LocalFunctionIndex, ModuleInfo, SignatureIndex,
@@ -25,6 +34,8 @@ pub struct UniversalEngine {
     /// The target for the compiler
     target: Arc<Target>,
     engine_id: EngineId,
+    epoch_controller: EngineEpochController,
+    instance_registry: InstanceRegistry,
 }
 
 impl UniversalEngine {
@@ -33,14 +44,19 @@ impl UniversalEngine {
     pub fn new(compiler: Box<dyn Compiler>, target: Target, features: Features) -> Self {
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
-                compiler: Some(compiler),
+                compiler: Some(Arc::from(compiler)),
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features,
+                compilation_strategy: CompilationStrategy::default(),
+                #[cfg(unix)]
+                shared_code: None,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -66,9 +82,14 @@ impl UniversalEngine {
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features: Features::default(),
+                compilation_strategy: CompilationStrategy::default(),
+                #[cfg(unix)]
+                shared_code: None,
             })),
             target: Arc::new(Target::default()),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
+            instance_registry: InstanceRegistry::new(),
         }
     }
 
@@ -79,6 +100,18 @@ impl UniversalEngine {
     pub(crate) fn inner_mut(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
         self.inner.lock().unwrap()
     }
+
+    /// How the code published by this engine has been shared, if it
+    /// has been built with [`Universal::with_shared_code_dir`].
+    ///
+    /// [`Universal::with_shared_code_dir`]: crate::Universal::with_shared_code_dir
+    #[cfg(unix)]
+    pub fn shared_code_stats(&self) -> Option<SharedCodeStats> {
+        self.inner()
+            .shared_code
+            .as_ref()
+            .map(|shared_code| shared_code.stats())
+    }
 }
 
 impl Engine for UniversalEngine {
@@ -104,6 +137,11 @@ impl Engine for UniversalEngine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The WebAssembly features
+    fn features(&self) -> Features {
+        self.inner().features().clone()
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -116,9 +154,33 @@ impl Engine for UniversalEngine {
         binary: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
+        // Code compiled for another target can't be instantiated, so
+        // there is no instantiation to wait for.
+        if self.inner().compilation_strategy == CompilationStrategy::Lazy
+            && UniversalArtifact::is_compatible_with_host(self.target().triple())
+        {
+            return Ok(Arc::new(LazyArtifact::new(&self, binary, tunables)?));
+        }
         Ok(Arc::new(UniversalArtifact::new(&self, binary, tunables)?))
     }
 
+    /// Compile a WebAssembly binary while it is received
+    #[cfg(feature = "compiler")]
+    fn compile_streaming(
+        &self,
+        tunables: Arc<dyn Tunables + Send + Sync>,
+    ) -> Option<Box<dyn StreamingCompilation>> {
+        // The lazily compiled modules are translated from the
+        // complete binary.
+        if self.inner().compilation_strategy == CompilationStrategy::Lazy {
+            return None;
+        }
+        Some(Box::new(UniversalStreamingCompilation::new(
+            self.clone(),
+            tunables,
+        )))
+    }
+
     /// Compile a WebAssembly binary
     #[cfg(not(feature = "compiler"))]
     fn compile(
@@ -141,9 +203,27 @@ impl Engine for UniversalEngine {
         &self.engine_id
     }
 
+    fn deterministic_id(&self) -> String {
+        #[cfg(feature = "compiler")]
+        {
+            if let Ok(compiler) = self.inner().compiler() {
+                return format!("universal-{}", compiler.deterministic_id());
+            }
+        }
+        "universal-headless".to_string()
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
+
+    fn epoch_controller(&self) -> EngineEpochController {
+        self.epoch_controller.clone()
+    }
+
+    fn instance_registry(&self) -> InstanceRegistry {
+        self.instance_registry.clone()
+    }
 }
 
 /// The inner contents of `UniversalEngine`
@@ -151,7 +231,7 @@ impl Engine for UniversalEngine {
 pub struct UniversalEngineInner {
     /// The compiler
     #[cfg(feature = "compiler")]
-    compiler: Option<Box<dyn Compiler>>,
+    compiler: Option<Arc<dyn Compiler>>,
     /// The features to compile the Wasm module with
     features: Features,
     /// The code memory is responsible of publishing the compiled
@@ -164,6 +244,12 @@ pub struct UniversalEngineInner {
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
     /// It also guarantees that the `VMFuncRef`s stay valid until the engine is dropped.
     func_data: Arc<FuncDataRegistry>,
+    /// When the functions of the modules are compiled.
+    pub(crate) compilation_strategy: CompilationStrategy,
+    /// The directory the published code is shared through, if any.
+    #[cfg(unix)]
+    #[loupe(skip)]
+    pub(crate) shared_code: Option<SharedCodeDir>,
 }
 
 impl UniversalEngineInner {
@@ -176,6 +262,14 @@ impl UniversalEngineInner {
         Ok(&**self.compiler.as_ref().unwrap())
     }
 
+    /// Gets a handle to the compiler associated to this engine, to
+    /// compile without holding the engine locked.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn shared_compiler(&self) -> Result<Arc<dyn Compiler>, CompileError> {
+        self.compiler()?;
+        Ok(self.compiler.clone().unwrap())
+    }
+
     /// Validate the module
     #[cfg(feature = "compiler")]
     pub fn validate<'data>(&self, data: &'data [u8]) -> Result<(), CompileError> {
@@ -289,9 +383,41 @@ impl UniversalEngineInner {
         ))
     }
 
-    /// Make memory containing compiled code executable.
-    pub(crate) fn publish_compiled_code(&mut self) {
-        self.code_memory.last_mut().unwrap().publish();
+    /// The name the code allocated last is shared under, if this
+    /// engine shares its code and the code is `position_independent`,
+    /// see [`SharedCodeDir`].
+    ///
+    /// It is computed before the code is linked.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) fn shared_code_key(
+        &self,
+        position_independent: impl FnOnce() -> bool,
+    ) -> Option<blake3::Hash> {
+        #[cfg(unix)]
+        if self.shared_code.is_some() && position_independent() {
+            let code_memory = self.code_memory.last().unwrap();
+            return Some(SharedCodeDir::key(code_memory.executable_pages()));
+        }
+        None
+    }
+
+    /// Make memory containing compiled code executable, and share it
+    /// under `shared_code_key`, if any.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) fn publish_compiled_code(
+        &mut self,
+        shared_code_key: Option<blake3::Hash>,
+    ) -> Result<(), CompileError> {
+        let code_memory = self.code_memory.last_mut().unwrap();
+        code_memory.publish();
+
+        #[cfg(unix)]
+        if let (Some(shared_code), Some(key)) = (&self.shared_code, shared_code_key) {
+            code_memory.publish_shared(shared_code, &key).map_err(|e| {
+                CompileError::Resource(format!("failed to share the compiled code: {}", e))
+            })?;
+        }
+        Ok(())
     }
 
     /// Register DWARF-type exception handling information associated with the code.
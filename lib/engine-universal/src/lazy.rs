@@ -0,0 +1,211 @@
+//! Define `LazyArtifact`, the artifact of the modules compiled with
+//! [`CompilationStrategy::Lazy`].
+//!
+//! [`CompilationStrategy::Lazy`]: crate::CompilationStrategy::Lazy
+
+use crate::engine::UniversalEngine;
+use crate::UniversalArtifact;
+use enumset::EnumSet;
+use loupe::MemoryUsage;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+use wasmer_compiler::{
+    CompilationMetrics, CompileError, CompileModuleInfo, CpuFeature, Features, ModuleEnvironment,
+    PerfDiagnostic, StackMap,
+};
+use wasmer_engine::{Artifact, Engine, InstantiationError, SerializeError, Tunables};
+use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::{
+    FunctionIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo, OwnedDataInitializer,
+    SignatureIndex, TableIndex,
+};
+use wasmer_vm::{
+    FuncDataRegistry, FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex,
+    VMTrampoline,
+};
+
+/// A module validated and translated but not compiled yet, see
+/// [`CompilationStrategy::Lazy`].
+///
+/// [`CompilationStrategy::Lazy`]: crate::CompilationStrategy::Lazy
+#[derive(MemoryUsage)]
+pub(crate) struct LazyArtifact {
+    #[loupe(skip)]
+    engine: UniversalEngine,
+    binary: Vec<u8>,
+    retain_wasm_bytes: bool,
+    compile_info: CompileModuleInfo,
+    data_initializers: Box<[OwnedDataInitializer]>,
+    cpu_features: u64,
+    #[loupe(skip)]
+    compiled: OnceCell<UniversalArtifact>,
+}
+
+impl LazyArtifact {
+    /// Validate and translate `binary`, recording what is needed to
+    /// compile it later.
+    pub(crate) fn new(
+        engine: &UniversalEngine,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        let inner_engine = engine.inner();
+        inner_engine.validate(binary)?;
+
+        let translation = ModuleEnvironment::new()
+            .translate(binary)
+            .map_err(CompileError::Wasm)?;
+        let retain_wasm_bytes = inner_engine.compiler()?.retains_wasm_bytes();
+        let compile_info = UniversalArtifact::compile_info(
+            inner_engine.compiler()?,
+            inner_engine.features(),
+            translation.module,
+            tunables,
+        );
+        let data_initializers = translation
+            .data_initializers
+            .iter()
+            .map(OwnedDataInitializer::new)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Ok(Self {
+            engine: engine.clone(),
+            binary: binary.to_vec(),
+            retain_wasm_bytes,
+            compile_info,
+            data_initializers,
+            cpu_features: engine.target().cpu_features().as_u64(),
+            compiled: OnceCell::new(),
+        })
+    }
+
+    /// Compile the module, unless it has already been compiled.
+    fn compiled(&self) -> Result<&UniversalArtifact, CompileError> {
+        self.compiled.get_or_try_init(|| {
+            // The translation is deterministic, only the function
+            // bodies are taken from it, the module was already
+            // translated and processed by the middlewares.
+            let translation = ModuleEnvironment::new()
+                .translate(&self.binary)
+                .map_err(CompileError::Wasm)?;
+            UniversalArtifact::from_translation(
+                &self.engine,
+                self.compile_info.clone(),
+                // SAFETY: Calling `unwrap` is correct since
+                // `translate()` above will write some data into
+                // `module_translation_state`.
+                translation.module_translation_state.as_ref().unwrap(),
+                translation.function_body_inputs,
+                self.data_initializers.clone(),
+                &self.binary,
+            )
+        })
+    }
+
+    /// The compiled module, once instantiated.
+    fn expect_compiled(&self) -> &UniversalArtifact {
+        self.compiled
+            .get()
+            .expect("the module is compiled before being instantiated")
+    }
+}
+
+impl Artifact for LazyArtifact {
+    fn module(&self) -> Arc<ModuleInfo> {
+        self.compile_info.module.clone()
+    }
+
+    fn module_ref(&self) -> &ModuleInfo {
+        &self.compile_info.module
+    }
+
+    fn module_mut(&mut self) -> Option<&mut ModuleInfo> {
+        // Once compiled, the module is shared with the compiled
+        // artifact.
+        Arc::get_mut(&mut self.compile_info.module)
+    }
+
+    fn register_frame_info(&self) {
+        if let Some(compiled) = self.compiled.get() {
+            compiled.register_frame_info();
+        }
+    }
+
+    fn features(&self) -> &Features {
+        &self.compile_info.features
+    }
+
+    fn cpu_features(&self) -> EnumSet<CpuFeature> {
+        EnumSet::from_u64(self.cpu_features)
+    }
+
+    fn data_initializers(&self) -> &[OwnedDataInitializer] {
+        &*self.data_initializers
+    }
+
+    fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
+        &self.compile_info.memory_styles
+    }
+
+    fn table_styles(&self) -> &PrimaryMap<TableIndex, TableStyle> {
+        &self.compile_info.table_styles
+    }
+
+    fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr> {
+        self.expect_compiled().finished_functions()
+    }
+
+    fn finished_function_call_trampolines(&self) -> &BoxedSlice<SignatureIndex, VMTrampoline> {
+        self.expect_compiled().finished_function_call_trampolines()
+    }
+
+    fn finished_dynamic_function_trampolines(&self) -> &BoxedSlice<FunctionIndex, FunctionBodyPtr> {
+        self.expect_compiled()
+            .finished_dynamic_function_trampolines()
+    }
+
+    fn signatures(&self) -> &BoxedSlice<SignatureIndex, VMSharedSignatureIndex> {
+        self.expect_compiled().signatures()
+    }
+
+    fn func_data_registry(&self) -> &FuncDataRegistry {
+        self.expect_compiled().func_data_registry()
+    }
+
+    fn perf_diagnostics(&self) -> &[PerfDiagnostic] {
+        self.compiled
+            .get()
+            .map_or(&[], |compiled| compiled.perf_diagnostics())
+    }
+
+    fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        self.compiled.get()?.compilation_metrics()
+    }
+
+    fn stack_maps(&self, function: LocalFunctionIndex) -> &[StackMap] {
+        self.compiled
+            .get()
+            .map_or(&[], |compiled| compiled.stack_maps(function))
+    }
+
+    fn wasm_bytes(&self) -> Option<&[u8]> {
+        if self.retain_wasm_bytes {
+            Some(&self.binary)
+        } else {
+            None
+        }
+    }
+
+    fn preinstantiate(&self) -> Result<(), InstantiationError> {
+        self.compiled()
+            .map_err(InstantiationError::Compile)?
+            .preinstantiate()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        self.compiled()
+            .map_err(|e| SerializeError::Generic(e.to_string()))?
+            .serialize()
+    }
+}
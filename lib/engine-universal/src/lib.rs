@@ -27,17 +27,27 @@
 mod artifact;
 mod builder;
 mod code_memory;
+mod embedder_metadata;
 mod engine;
+#[cfg(feature = "compiler")]
+mod lazy;
 mod link;
 mod serialize;
+#[cfg(unix)]
+mod shared_code;
+#[cfg(feature = "compiler")]
+mod streaming;
 mod trampoline;
 mod unwind;
 
 pub use crate::artifact::UniversalArtifact;
-pub use crate::builder::Universal;
+pub use crate::builder::{CompilationStrategy, Universal};
 pub use crate::code_memory::CodeMemory;
+pub use crate::embedder_metadata::PatchError;
 pub use crate::engine::UniversalEngine;
 pub use crate::link::link_module;
+#[cfg(unix)]
+pub use crate::shared_code::SharedCodeStats;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
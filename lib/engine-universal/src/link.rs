@@ -2,7 +2,10 @@
 
 use crate::trampoline::get_libcall_trampoline;
 use std::ptr::{read_unaligned, write_unaligned};
-use wasmer_compiler::{Relocation, RelocationKind, RelocationTarget, Relocations, SectionIndex};
+use wasmer_compiler::{
+    CustomSection, CustomSectionProtection, Relocation, RelocationKind, RelocationTarget,
+    Relocations, SectionIndex,
+};
 use wasmer_engine::FunctionExtent;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{LocalFunctionIndex, ModuleInfo};
@@ -78,6 +81,35 @@ fn apply_relocation(
     }
 }
 
+/// Whether the executable code of a module links to the same content
+/// wherever it is allocated: its relocations are relative to the code,
+/// except for the absolute addresses of the libcalls.
+pub(crate) fn is_position_independent(
+    function_relocations: &Relocations,
+    custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+    section_relocations: &PrimaryMap<SectionIndex, Vec<Relocation>>,
+) -> bool {
+    let executable_section_relocations = section_relocations
+        .iter()
+        .filter(|(i, _)| custom_sections[*i].protection == CustomSectionProtection::ReadExecute)
+        .map(|(_, relocations)| relocations);
+    function_relocations
+        .values()
+        .chain(executable_section_relocations)
+        .flatten()
+        .all(|r| {
+            let libcall = matches!(r.reloc_target, RelocationTarget::LibCall(_));
+            match r.kind {
+                RelocationKind::Abs8 => libcall,
+                // Relative to a libcall, outside of the code.
+                RelocationKind::X86PCRel8 => !libcall,
+                // Other libcall relocations go through the trampolines,
+                // allocated with the code.
+                _ => true,
+            }
+        })
+}
+
 /// Links a module, patching the allocated functions with the
 /// required relocations and jump tables.
 pub fn link_module(
@@ -116,3 +148,75 @@ pub fn link_module(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_compiler::SectionBody;
+    use wasmer_vm::libcalls::LibCall;
+
+    fn relocation(kind: RelocationKind, reloc_target: RelocationTarget) -> Relocation {
+        Relocation {
+            kind,
+            reloc_target,
+            offset: 0,
+            addend: 0,
+        }
+    }
+
+    fn section(protection: CustomSectionProtection) -> CustomSection {
+        CustomSection {
+            protection,
+            bytes: SectionBody::new_with_vec(vec![0; 8]),
+            relocations: vec![],
+        }
+    }
+
+    #[test]
+    fn position_independence() {
+        let mut custom_sections = PrimaryMap::new();
+        let code = custom_sections.push(section(CustomSectionProtection::ReadExecute));
+        let data = custom_sections.push(section(CustomSectionProtection::Read));
+        let is_position_independent =
+            |function: Relocation, section: (SectionIndex, Relocation)| {
+                let mut function_relocations = PrimaryMap::new();
+                function_relocations.push(vec![function]);
+                let mut section_relocations = PrimaryMap::new();
+                section_relocations.push(vec![]);
+                section_relocations.push(vec![]);
+                section_relocations[section.0].push(section.1);
+                super::is_position_independent(
+                    &function_relocations,
+                    &custom_sections,
+                    &section_relocations,
+                )
+            };
+        let call = relocation(
+            RelocationKind::X86CallPCRel4,
+            RelocationTarget::LocalFunc(LocalFunctionIndex::from_u32(0)),
+        );
+        let libcall = relocation(
+            RelocationKind::Abs8,
+            RelocationTarget::LibCall(LibCall::RaiseTrap),
+        );
+        let absolute = relocation(RelocationKind::Abs8, RelocationTarget::CustomSection(code));
+
+        assert!(is_position_independent(
+            call.clone(),
+            (code, libcall.clone())
+        ));
+        assert!(is_position_independent(
+            libcall.clone(),
+            (data, absolute.clone())
+        ));
+        assert!(!is_position_independent(absolute.clone(), (code, libcall)));
+        assert!(!is_position_independent(call.clone(), (code, absolute)));
+        assert!(!is_position_independent(
+            relocation(
+                RelocationKind::X86PCRel8,
+                RelocationTarget::LibCall(LibCall::RaiseTrap)
+            ),
+            (data, call)
+        ));
+    }
+}
@@ -38,6 +38,11 @@ pub struct SerializableModule {
     pub compile_info: CompileModuleInfo,
     pub data_initializers: Box<[OwnedDataInitializer]>,
     pub cpu_features: u64,
+    /// The target triple this module was compiled for.
+    pub triple: String,
+    /// The binary the module was compiled from, if the compiler
+    /// retains it.
+    pub wasm_bytes: Option<Vec<u8>>,
 }
 
 fn to_serialize_error(err: impl std::error::Error) -> SerializeError {
@@ -0,0 +1,131 @@
+//! Sharing the compiled code of identical artifacts between engines
+//! and processes, see [`Universal::with_shared_code_dir`].
+//!
+//! [`Universal::with_shared_code_dir`]: crate::Universal::with_shared_code_dir
+
+use enum_iterator::IntoEnumIterator;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use wasmer_vm::libcalls::LibCall;
+
+/// How the code published by an engine has been shared, see
+/// [`UniversalEngine::shared_code_stats`].
+///
+/// [`UniversalEngine::shared_code_stats`]: crate::UniversalEngine::shared_code_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SharedCodeStats {
+    /// The number of code files written to the directory.
+    pub written: u64,
+    /// The number of code files already in the directory, and mapped
+    /// instead of keeping a private copy of the code.
+    pub reused: u64,
+    /// The number of code files whose content didn't match their
+    /// checksum, and which have been written again.
+    pub corrupted: u64,
+}
+
+/// A directory of compiled code, shared by the engines of any process
+/// using it.
+///
+/// Each file holds the executable pages of an artifact, once linked.
+/// The linked code depends on where it is mapped, and on where the
+/// libcalls are in the process, so files are named after the code
+/// before it is linked, and the addresses of the libcalls, see
+/// [`SharedCodeDir::key`]. Only position-independent code is shared,
+/// linking to the same content wherever it is mapped: the same file
+/// can only be mapped for identical code, and a file which doesn't
+/// hold the code being shared is detected as corrupted.
+#[derive(Debug)]
+pub(crate) struct SharedCodeDir {
+    path: PathBuf,
+    written: AtomicU64,
+    reused: AtomicU64,
+    corrupted: AtomicU64,
+}
+
+impl SharedCodeDir {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            written: AtomicU64::new(0),
+            reused: AtomicU64::new(0),
+            corrupted: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> SharedCodeStats {
+        SharedCodeStats {
+            written: self.written.load(Ordering::Relaxed),
+            reused: self.reused.load(Ordering::Relaxed),
+            corrupted: self.corrupted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The name of the file holding `code` once linked, computed from
+    /// the `code` before it is linked.
+    pub(crate) fn key(code: &[u8]) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(code);
+        for libcall in LibCall::into_enum_iter() {
+            hasher.update(&(libcall.function_pointer() as u64).to_le_bytes());
+        }
+        hasher.finalize()
+    }
+
+    /// Open the file named `key` holding the linked `code`, writing it
+    /// first if it doesn't exist or is corrupted.
+    pub(crate) fn open(&self, key: &blake3::Hash, code: &[u8]) -> io::Result<File> {
+        let path = self.path.join(format!("{}.code", key.to_hex()));
+
+        match File::open(&path) {
+            Ok(mut file) => {
+                let mut content = Vec::with_capacity(code.len());
+                file.read_to_end(&mut content)?;
+                if content == code {
+                    self.reused.fetch_add(1, Ordering::Relaxed);
+                    return Ok(file);
+                }
+                self.corrupted.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        fs::create_dir_all(&self.path)?;
+        self.write(&path, code)?;
+        self.written.fetch_add(1, Ordering::Relaxed);
+        File::open(&path)
+    }
+
+    /// Write `code` to `path`, atomically: the file is written under
+    /// a temporary name first, then renamed, so that other processes
+    /// never see a partial file.
+    fn write(&self, path: &Path, code: &[u8]) -> io::Result<()> {
+        static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+        let temp_path = path.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            // The file is never modified once written.
+            .mode(0o444)
+            .open(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(code)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp_path, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
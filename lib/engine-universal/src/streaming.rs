@@ -0,0 +1,225 @@
+//! Compilation of WebAssembly binaries while they are received, see
+//! [`Engine::compile_streaming`].
+
+use crate::{UniversalArtifact, UniversalEngine};
+use std::panic;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use wasmer_compiler::wasmparser::{
+    BinaryReaderError, Chunk, Parser, Payload, ValidPayload, Validator,
+};
+use wasmer_compiler::{
+    module_validator, Compilation, CompileError, CompileModuleInfo, ModuleEnvironment,
+    StreamedFunctionBody, ValidationError,
+};
+use wasmer_engine::{Artifact, Engine, StreamingCompilation, Tunables};
+use wasmer_types::OwnedDataInitializer;
+
+/// A compilation started with [`UniversalEngine::compile_streaming`].
+///
+/// The binary is parsed and validated as it is fed. Once the code
+/// section is reached, the sections before it are translated and a
+/// thread hands the function bodies to the compiler as they arrive,
+/// with [`Compiler::compile_module_streaming`].
+///
+/// [`Compiler::compile_module_streaming`]: wasmer_compiler::Compiler::compile_module_streaming
+pub(crate) struct UniversalStreamingCompilation {
+    engine: UniversalEngine,
+    tunables: Arc<dyn Tunables + Send + Sync>,
+    /// The bytes received so far.
+    bytes: Vec<u8>,
+    parser: Parser,
+    validator: Validator,
+    /// The number of bytes of `bytes` consumed by `parser`.
+    parsed: usize,
+    /// Whether the parser reached the end of the binary.
+    ended: bool,
+    /// The compilation of the function bodies, once the code section
+    /// is reached.
+    code: Option<CodeCompilation>,
+}
+
+struct CodeCompilation {
+    bodies: Sender<StreamedFunctionBody>,
+    /// The compilation of the bodies, handing back the module it was
+    /// compiled against.
+    compilation: JoinHandle<Result<(Compilation, CompileModuleInfo), CompileError>>,
+}
+
+/// What to do with a payload, once the borrow of the bytes it was
+/// parsed from is released.
+enum Step {
+    Continue,
+    StartCode { prefix_len: usize },
+    Body(StreamedFunctionBody),
+    End,
+}
+
+impl UniversalStreamingCompilation {
+    pub(crate) fn new(engine: UniversalEngine, tunables: Arc<dyn Tunables + Send + Sync>) -> Self {
+        let validator = module_validator(engine.inner().features());
+        Self {
+            engine,
+            tunables,
+            bytes: Vec::new(),
+            parser: Parser::new(0),
+            validator,
+            parsed: 0,
+            ended: false,
+            code: None,
+        }
+    }
+
+    /// Parse the payloads available in the bytes received so far.
+    fn parse(&mut self, eof: bool) -> Result<(), CompileError> {
+        while !self.ended {
+            let start = self.parsed;
+            let (consumed, payload) = match self.parser.parse(&self.bytes[start..], eof)? {
+                Chunk::NeedMoreData(_) => return Ok(()),
+                Chunk::Parsed { consumed, payload } => (consumed, payload),
+            };
+            self.parsed += consumed;
+
+            let step = match self.validator.payload(&payload).map_err(validation_error)? {
+                ValidPayload::Func(mut validator, body) => {
+                    validator.validate(&body).map_err(validation_error)?;
+                    let mut reader = body.get_binary_reader();
+                    let module_offset = reader.original_position();
+                    let size = reader.bytes_remaining();
+                    Step::Body(StreamedFunctionBody {
+                        data: reader.read_bytes(size)?.to_vec(),
+                        module_offset,
+                    })
+                }
+                _ => match payload {
+                    Payload::CodeSectionStart { .. } => Step::StartCode { prefix_len: start },
+                    Payload::End => Step::End,
+                    _ => Step::Continue,
+                },
+            };
+
+            match step {
+                Step::Continue => {}
+                Step::StartCode { prefix_len } => self.start_code(prefix_len)?,
+                Step::Body(body) => {
+                    let code = self.code.as_ref().expect("the code section has started");
+                    // If the compiler stopped early, its error is
+                    // reported by `finish`.
+                    let _ = code.bodies.send(body);
+                }
+                Step::End => self.ended = true,
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate the sections before the code section, and start
+    /// compiling the function bodies in a separate thread.
+    fn start_code(&mut self, prefix_len: usize) -> Result<(), CompileError> {
+        // The sections before the code section form a module on their
+        // own, without function bodies.
+        let translation = ModuleEnvironment::new()
+            .translate(&self.bytes[..prefix_len])
+            .map_err(CompileError::Wasm)?;
+        let module_translation_state = translation.module_translation_state.unwrap();
+        // The middlewares transform the module once, here: some of them
+        // refuse to transform several modules.
+        let compile_info = {
+            let inner_engine = self.engine.inner();
+            UniversalArtifact::compile_info(
+                inner_engine.compiler()?,
+                inner_engine.features(),
+                translation.module,
+                &*self.tunables,
+            )
+        };
+
+        // The bodies arrive as they are received: the engine isn't
+        // locked meanwhile, so that it can compile and instantiate other
+        // modules.
+        let compiler = self.engine.inner().shared_compiler()?;
+        let (bodies, receiver) = mpsc::channel();
+        let engine = self.engine.clone();
+        let compilation = thread::spawn(move || {
+            let compilation = compiler.compile_module_streaming(
+                engine.target(),
+                &compile_info,
+                &module_translation_state,
+                &mut receiver.into_iter(),
+            )?;
+            Ok((compilation, compile_info))
+        });
+        self.code = Some(CodeCompilation {
+            bodies,
+            compilation,
+        });
+        Ok(())
+    }
+}
+
+impl StreamingCompilation for UniversalStreamingCompilation {
+    fn feed(&mut self, bytes: &[u8]) -> Result<(), CompileError> {
+        self.bytes.extend_from_slice(bytes);
+        self.parse(false)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<Arc<dyn Artifact>, CompileError> {
+        self.parse(true)?;
+        if self.parsed != self.bytes.len() {
+            return Err(CompileError::Validate(
+                "unexpected data after the end of the module".to_string(),
+            ));
+        }
+
+        let code = match self.code.take() {
+            Some(code) => code,
+            // Without function bodies, there is nothing to compile
+            // ahead of time.
+            None => {
+                return Ok(Arc::new(UniversalArtifact::new(
+                    &self.engine,
+                    &self.bytes,
+                    &*self.tunables,
+                )?))
+            }
+        };
+        drop(code.bodies);
+        let (compilation, mut compile_info) = code
+            .compilation
+            .join()
+            .unwrap_or_else(|e| panic::resume_unwind(e))?;
+
+        // The sections after the code section (data, names, custom
+        // sections) are only known now.
+        let translation = ModuleEnvironment::new()
+            .translate(&self.bytes)
+            .map_err(CompileError::Wasm)?;
+        let module = Arc::make_mut(&mut compile_info.module);
+        module.name = translation.module.name;
+        module.function_names = translation.module.function_names;
+        module.passive_data = translation.module.passive_data;
+        module.custom_sections = translation.module.custom_sections;
+        module.custom_sections_data = translation.module.custom_sections_data;
+        let data_initializers = translation
+            .data_initializers
+            .iter()
+            .map(OwnedDataInitializer::new)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        let mut inner_engine = self.engine.inner_mut();
+        Ok(Arc::new(UniversalArtifact::from_compilation(
+            &self.engine,
+            &mut inner_engine,
+            compile_info,
+            compilation,
+            data_initializers,
+            &self.bytes,
+        )?))
+    }
+}
+
+fn validation_error(e: BinaryReaderError) -> CompileError {
+    ValidationError::new(e.message(), e.offset()).into()
+}
@@ -1,6 +1,7 @@
+use crate::observer::observe;
 use crate::{
-    resolve_imports, DeserializeError, InstantiationError, Resolver, RuntimeError, SerializeError,
-    Tunables,
+    resolve_imports, DeserializeError, InstantiationError, InstantiationObserver,
+    InstantiationPhase, Resolver, RuntimeError, SerializeError, Tunables,
 };
 use enumset::EnumSet;
 use loupe::MemoryUsage;
@@ -9,7 +10,7 @@ use std::convert::TryInto;
 use std::path::Path;
 use std::sync::Arc;
 use std::{fs, mem};
-use wasmer_compiler::{CpuFeature, Features};
+use wasmer_compiler::{CompilationMetrics, CpuFeature, Features, PerfDiagnostic, StackMap};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 use wasmer_types::{
     DataInitializer, FunctionIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
@@ -76,6 +77,40 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
     /// Get the func data registry
     fn func_data_registry(&self) -> &FuncDataRegistry;
 
+    /// Returns the performance diagnostics reported by the compiler,
+    /// when enabled in its config.
+    ///
+    /// Diagnostics are not serialized: artifacts that are deserialized
+    /// have none.
+    fn perf_diagnostics(&self) -> &[PerfDiagnostic] {
+        &[]
+    }
+
+    /// Returns the statistics reported by the compiler about the code
+    /// it generated, if the artifact has just been compiled.
+    ///
+    /// Like diagnostics, metrics are not serialized.
+    fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        None
+    }
+
+    /// Returns the stack maps of a function, when enabled in the
+    /// config of the compiler.
+    ///
+    /// Like diagnostics, stack maps are not serialized.
+    fn stack_maps(&self, _function: LocalFunctionIndex) -> &[StackMap] {
+        &[]
+    }
+
+    /// Returns the binary the artifact was compiled from, when the
+    /// compiler retains it, see `CompilerConfig::retain_wasm_bytes`.
+    ///
+    /// Unlike diagnostics, the binary is serialized with the
+    /// artifact, by the engines which support it.
+    fn wasm_bytes(&self) -> Option<&[u8]> {
+        None
+    }
+
     /// Serializes an artifact into bytes
     fn serialize(&self) -> Result<Vec<u8>, SerializeError>;
 
@@ -93,6 +128,9 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
 
     /// Crate an `Instance` from this `Artifact`.
     ///
+    /// The phases of the instantiation are reported to `observer`, if
+    /// any.
+    ///
     /// # Safety
     ///
     /// See [`InstanceHandle::new`].
@@ -101,6 +139,7 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
         host_state: Box<dyn Any>,
+        observer: Option<&dyn InstantiationObserver>,
     ) -> Result<InstanceHandle, InstantiationError> {
         // Validate the CPU features this module was compiled with against the
         // host CPU features.
@@ -116,14 +155,16 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
 
         let module = self.module();
         let (imports, import_function_envs) = {
-            let mut imports = resolve_imports(
-                &module,
-                resolver,
-                &self.finished_dynamic_function_trampolines(),
-                self.memory_styles(),
-                self.table_styles(),
-            )
-            .map_err(InstantiationError::Link)?;
+            let mut imports = observe(observer, InstantiationPhase::ResolveImports, || {
+                resolve_imports(
+                    &module,
+                    resolver,
+                    &self.finished_dynamic_function_trampolines(),
+                    self.memory_styles(),
+                    self.table_styles(),
+                )
+                .map_err(InstantiationError::Link)
+            })?;
 
             // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
             // to the envs to call it on.
@@ -137,14 +178,18 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
 
         let (allocator, memory_definition_locations, table_definition_locations) =
             InstanceAllocator::new(&*module);
-        let finished_memories = tunables
-            .create_memories(&module, self.memory_styles(), &memory_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
-        let finished_tables = tunables
-            .create_tables(&module, self.table_styles(), &table_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
+        let finished_memories = observe(observer, InstantiationPhase::AllocateMemories, || {
+            tunables
+                .create_memories(&module, self.memory_styles(), &memory_definition_locations)
+                .map_err(InstantiationError::Link)
+        })?
+        .into_boxed_slice();
+        let finished_tables = observe(observer, InstantiationPhase::AllocateTables, || {
+            tunables
+                .create_tables(&module, self.table_styles(), &table_definition_locations)
+                .map_err(InstantiationError::Link)
+        })?
+        .into_boxed_slice();
         let finished_globals = tunables
             .create_globals(&module)
             .map_err(InstantiationError::Link)?
@@ -171,6 +216,9 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
 
     /// Finishes the instantiation of a just created `InstanceHandle`.
     ///
+    /// The phases of the instantiation are reported to `observer`, if
+    /// any.
+    ///
     /// # Safety
     ///
     /// See [`InstanceHandle::finish_instantiation`].
@@ -178,6 +226,7 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
         &self,
         trap_handler: &(dyn TrapHandler + 'static),
         handle: &InstanceHandle,
+        observer: Option<&dyn InstantiationObserver>,
     ) -> Result<(), InstantiationError> {
         let data_initializers = self
             .data_initializers()
@@ -187,9 +236,36 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
                 data: &*init.data,
             })
             .collect::<Vec<_>>();
-        handle
-            .finish_instantiation(trap_handler, &data_initializers)
-            .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))
+        let start_error = |trap| InstantiationError::Start(RuntimeError::from_trap(trap));
+
+        let elements = self
+            .module_ref()
+            .table_initializers
+            .iter()
+            .map(|init| init.elements.len())
+            .sum();
+        observe(
+            observer,
+            InstantiationPhase::ApplyElementSegments { elements },
+            || handle.initialize_tables().map_err(start_error),
+        )?;
+
+        let bytes = data_initializers.iter().map(|init| init.data.len()).sum();
+        observe(
+            observer,
+            InstantiationPhase::ApplyDataSegments { bytes },
+            || {
+                handle
+                    .initialize_memories(&data_initializers)
+                    .map_err(start_error)
+            },
+        )?;
+
+        observe(observer, InstantiationPhase::Start, || {
+            handle
+                .invoke_start_function(trap_handler)
+                .map_err(start_error)
+        })
     }
 }
 
@@ -243,7 +319,7 @@ pub struct MetadataHeader {
 impl MetadataHeader {
     /// Current ABI version. Increment this any time breaking changes are made
     /// to the format of the serialized data.
-    const CURRENT_VERSION: u32 = 1;
+    const CURRENT_VERSION: u32 = 4;
 
     /// Magic number to identify wasmer metadata.
     const MAGIC: [u8; 8] = *b"WASMER\0\0";
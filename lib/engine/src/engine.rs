@@ -1,13 +1,14 @@
 //! Engine trait and associated types.
 
 use crate::tunables::Tunables;
-use crate::{Artifact, DeserializeError};
-use loupe::MemoryUsage;
+use crate::{Artifact, DeserializeError, InstanceDescriptor, InstanceRegistry};
+use loupe::{MemoryUsage, MemoryUsageTracker};
 use memmap2::Mmap;
+use std::mem;
 use std::path::Path;
-use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
-use std::sync::Arc;
-use wasmer_compiler::{CompileError, Target};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Mutex, Weak};
+use wasmer_compiler::{CompileError, Features, Target};
 use wasmer_types::FunctionType;
 use wasmer_vm::{VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
 
@@ -30,6 +31,9 @@ pub trait Engine: MemoryUsage {
     /// Lookup a signature
     fn lookup_signature(&self, sig: VMSharedSignatureIndex) -> Option<FunctionType>;
 
+    /// Gets the WebAssembly features enabled in this engine
+    fn features(&self) -> Features;
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError>;
 
@@ -40,6 +44,19 @@ pub trait Engine: MemoryUsage {
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError>;
 
+    /// Starts compiling a WebAssembly binary whose bytes are received
+    /// progressively, see [`StreamingCompilation`].
+    ///
+    /// Returns `None` if the engine can only compile complete
+    /// binaries, in which case they must be buffered and passed to
+    /// [`Engine::compile`].
+    fn compile_streaming(
+        &self,
+        _tunables: Arc<dyn Tunables + Send + Sync>,
+    ) -> Option<Box<dyn StreamingCompilation>> {
+        None
+    }
+
     /// Deserializes a WebAssembly module
     ///
     /// # Safety
@@ -68,8 +85,130 @@ pub trait Engine: MemoryUsage {
     /// of trait representation.
     fn id(&self) -> &EngineId;
 
+    /// An identifier of the engine, of its compiler and of the
+    /// configuration of the compiler, stable across processes: unlike
+    /// [`Engine::id`], it's shared by the engines compiling a binary to
+    /// the same artifact.
+    fn deterministic_id(&self) -> String;
+
     /// Clone the engine
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync>;
+
+    /// A handle to the epoch counter of this engine.
+    ///
+    /// Engines not overriding it don't support epoch interruption:
+    /// each call returns a new counter, which the stores of the
+    /// engine don't use.
+    fn epoch_controller(&self) -> EngineEpochController {
+        EngineEpochController::new()
+    }
+
+    /// Increments the epoch counter of this engine, see
+    /// [`EngineEpochController::increment`].
+    fn increment_epoch(&self) {
+        self.epoch_controller().increment();
+    }
+
+    /// A handle to the registry of the live instances of this engine.
+    ///
+    /// Engines not overriding it don't track their instances: each
+    /// call returns a new registry, which the instances are registered
+    /// in and forgotten with.
+    fn instance_registry(&self) -> InstanceRegistry {
+        InstanceRegistry::new()
+    }
+
+    /// Describes the live instances of this engine, see
+    /// [`InstanceRegistry::live_instances`].
+    fn live_instances(&self) -> Vec<InstanceDescriptor> {
+        self.instance_registry().live_instances()
+    }
+}
+
+/// A compilation started with [`Engine::compile_streaming`], fed with
+/// the bytes of a WebAssembly binary as they arrive.
+pub trait StreamingCompilation: Send {
+    /// Feeds the next bytes of the binary.
+    ///
+    /// Errors in the bytes received so far are reported as early as
+    /// possible, after which the compilation can't be fed anymore.
+    fn feed(&mut self, bytes: &[u8]) -> Result<(), CompileError>;
+
+    /// Signals the end of the binary, waits for the compilation to
+    /// complete and returns the resulting artifact.
+    fn finish(self: Box<Self>) -> Result<Arc<dyn Artifact>, CompileError>;
+}
+
+/// A handle to the epoch counter of an [`Engine`].
+///
+/// The counter is shared by all the stores using the engine. Code
+/// compiled with epoch interruption enabled traps with
+/// `TrapCode::EpochDeadlineExceeded` once the counter goes past the
+/// deadline of its store. The handle can be sent to another thread,
+/// e.g. a watchdog that increments the counter periodically.
+///
+/// A store starting at an epoch of its own has a counter following
+/// the one of the engine, see [`EngineEpochController::follower`].
+#[derive(Debug, Clone, Default)]
+pub struct EngineEpochController {
+    counter: Arc<AtomicU64>,
+    /// The counters incremented along with this one.
+    followers: Arc<Mutex<Vec<Weak<AtomicU64>>>>,
+}
+
+impl EngineEpochController {
+    /// Creates a new counter, starting at epoch 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a counter starting at `epoch`, incremented along with
+    /// this one for as long as it lives.
+    ///
+    /// Setting either counter, or incrementing the follower, doesn't
+    /// affect the other one.
+    pub fn follower(&self, epoch: u64) -> Self {
+        let follower = Self {
+            counter: Arc::new(AtomicU64::new(epoch)),
+            followers: Arc::default(),
+        };
+        let mut followers = self.followers.lock().unwrap();
+        followers.retain(|counter| counter.strong_count() > 0);
+        followers.push(Arc::downgrade(&follower.counter));
+        follower
+    }
+
+    /// Increments the epoch counter, and the counters following it.
+    pub fn increment(&self) {
+        self.counter.fetch_add(1, SeqCst);
+        let followers = self.followers.lock().unwrap();
+        for counter in followers.iter().filter_map(Weak::upgrade) {
+            counter.fetch_add(1, SeqCst);
+        }
+    }
+
+    /// Sets the epoch counter to `epoch`.
+    pub fn set(&self, epoch: u64) {
+        self.counter.store(epoch, SeqCst);
+    }
+
+    /// Returns the current epoch.
+    pub fn current(&self) -> u64 {
+        self.counter.load(SeqCst)
+    }
+
+    /// Returns a pointer to the counter, to be handed to instances.
+    pub fn as_ptr(&self) -> *const AtomicU64 {
+        &*self.counter
+    }
+}
+
+impl MemoryUsage for EngineEpochController {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + mem::size_of::<AtomicU64>()
+            + mem::size_of::<Mutex<Vec<Weak<AtomicU64>>>>()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, MemoryUsage)]
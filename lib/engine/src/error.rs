@@ -1,8 +1,9 @@
 //! The WebAssembly possible errors
 use crate::trap::RuntimeError;
+use enumset::EnumSet;
 use std::io;
 use thiserror::Error;
-use wasmer_compiler::CompileError;
+use wasmer_compiler::{CompileError, CpuFeature};
 use wasmer_types::ExternType;
 
 /// The Serialize error can occur when serializing a
@@ -33,6 +34,19 @@ pub enum DeserializeError {
     /// The provided binary is corrupted
     #[error("corrupted binary: {0}")]
     CorruptedBinary(String),
+    /// The binary was compiled for CPU features the host doesn't
+    /// support, so running its code could crash with an illegal
+    /// instruction.
+    #[error(
+        "incompatible target: the module requires the CPU features {:?}, which the host doesn't support",
+        .required.difference(*.available)
+    )]
+    IncompatibleTarget {
+        /// The CPU features the binary was compiled for.
+        required: EnumSet<CpuFeature>,
+        /// The CPU features of the host.
+        available: EnumSet<CpuFeature>,
+    },
     /// The binary was valid, but we got an error when
     /// trying to allocate the required resources.
     #[error(transparent)]
@@ -99,4 +113,9 @@ pub enum InstantiationError {
     /// A runtime error occured while invoking the start function
     #[error(transparent)]
     Start(RuntimeError),
+
+    /// The module, compiled on its first instantiation, failed to
+    /// compile.
+    #[error(transparent)]
+    Compile(CompileError),
 }
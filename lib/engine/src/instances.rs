@@ -0,0 +1,94 @@
+//! The registry of the live instances of an [`Engine`].
+//!
+//! [`Engine`]: crate::Engine
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+
+/// A description of a live instance, as returned by
+/// [`Engine::live_instances`].
+///
+/// [`Engine::live_instances`]: crate::Engine::live_instances
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceDescriptor {
+    /// The hexadecimal BLAKE3 hash of the binary the module of the
+    /// instance was compiled from, if it is known.
+    pub module_hash: Option<String>,
+    /// The name of the module of the instance, if any.
+    pub module_name: Option<String>,
+    /// The tags of the instance.
+    pub tags: BTreeMap<String, String>,
+    /// The number of memories of the instance, imported or not.
+    pub memories: usize,
+    /// The sum of the sizes in bytes of these memories.
+    pub memory_bytes: u64,
+    /// When the instance was created.
+    pub created_at: SystemTime,
+}
+
+/// An instance registered in an [`InstanceRegistry`].
+pub trait LiveInstance: Send + Sync {
+    /// Describes the instance.
+    fn describe(&self) -> InstanceDescriptor;
+}
+
+/// The live instances of an [`Engine`], see
+/// [`Engine::instance_registry`].
+///
+/// The registry only keeps weak references to the instances, which
+/// leave it once they are dropped. It is shared by the clones of the
+/// engine.
+///
+/// [`Engine`]: crate::Engine
+/// [`Engine::instance_registry`]: crate::Engine::instance_registry
+#[derive(Clone, Default)]
+pub struct InstanceRegistry {
+    instances: Arc<Mutex<Vec<Weak<dyn LiveInstance>>>>,
+}
+
+impl InstanceRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an instance, for as long as it is alive.
+    pub fn register(&self, instance: Weak<dyn LiveInstance>) {
+        let mut instances = self.instances.lock().unwrap();
+        instances.retain(|instance| instance.strong_count() > 0);
+        instances.push(instance);
+    }
+
+    /// Describes the instances still alive, in the order they were
+    /// registered.
+    ///
+    /// An instance dropped concurrently is either described or left
+    /// out, and never appears again once it is dropped.
+    pub fn live_instances(&self) -> Vec<InstanceDescriptor> {
+        let instances = {
+            let mut instances = self.instances.lock().unwrap();
+            instances.retain(|instance| instance.strong_count() > 0);
+            instances
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+        // The instances are described without holding the lock, and
+        // may be dropped by this thread once described.
+        instances
+            .iter()
+            .map(|instance| instance.describe())
+            .collect()
+    }
+}
+
+impl MemoryUsage for InstanceRegistry {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + mem::size_of::<Mutex<Vec<Weak<dyn LiveInstance>>>>()
+            + self.instances.lock().unwrap().capacity() * mem::size_of::<Weak<dyn LiveInstance>>()
+    }
+}
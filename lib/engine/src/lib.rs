@@ -24,16 +24,20 @@ mod artifact;
 mod engine;
 mod error;
 mod export;
+mod instances;
+mod observer;
 mod resolver;
 mod trap;
 mod tunables;
 
 pub use crate::artifact::{Artifact, MetadataHeader};
-pub use crate::engine::{Engine, EngineId};
+pub use crate::engine::{Engine, EngineEpochController, EngineId, StreamingCompilation};
 pub use crate::error::{
     DeserializeError, ImportError, InstantiationError, LinkError, SerializeError,
 };
 pub use crate::export::{Export, ExportFunction, ExportFunctionMetadata};
+pub use crate::instances::{InstanceDescriptor, InstanceRegistry, LiveInstance};
+pub use crate::observer::{InstantiationEvent, InstantiationObserver, InstantiationPhase};
 pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
     Resolver,
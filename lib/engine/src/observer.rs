@@ -0,0 +1,77 @@
+//! Observe the phases of the instantiation of a module.
+
+use crate::InstantiationError;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// A phase of the instantiation of a module, reported to an
+/// [`InstantiationObserver`] in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstantiationPhase {
+    /// The imports are resolved and checked against the types the
+    /// module expects.
+    ResolveImports,
+    /// The memories defined by the module are allocated.
+    AllocateMemories,
+    /// The tables defined by the module are allocated.
+    AllocateTables,
+    /// The element segments are written into the tables.
+    ApplyElementSegments {
+        /// The number of elements of the segments.
+        elements: usize,
+    },
+    /// The data segments are written into the memories.
+    ApplyDataSegments {
+        /// The number of bytes of the segments.
+        bytes: usize,
+    },
+    /// The start function of the module is invoked. The phase is
+    /// reported even if the module has no start function.
+    Start,
+}
+
+/// A phase of an instantiation which ended, as reported to an
+/// [`InstantiationObserver`].
+#[derive(Debug, Clone, Copy)]
+pub struct InstantiationEvent<'a> {
+    /// The phase which ended.
+    pub phase: InstantiationPhase,
+    /// How long the phase took.
+    pub duration: Duration,
+    /// The error the phase failed with, if it failed. It's the last
+    /// event of the instantiation then.
+    pub error: Option<&'a (dyn Error + 'static)>,
+}
+
+/// Receives an [`InstantiationEvent`] at the end of each phase of the
+/// instantiations of a store, to trace or profile them.
+///
+/// The events are sent synchronously, from the thread instantiating
+/// the module.
+pub trait InstantiationObserver: Send + Sync {
+    /// Called when the phase of `event` ended.
+    fn on_event(&self, event: &InstantiationEvent<'_>);
+}
+
+/// Runs `f`, the body of `phase`, and reports it to `observer`.
+pub(crate) fn observe<T>(
+    observer: Option<&dyn InstantiationObserver>,
+    phase: InstantiationPhase,
+    f: impl FnOnce() -> Result<T, InstantiationError>,
+) -> Result<T, InstantiationError> {
+    let observer = match observer {
+        Some(observer) => observer,
+        None => return f(),
+    };
+    let start = Instant::now();
+    let result = f();
+    observer.on_event(&InstantiationEvent {
+        phase,
+        duration: start.elapsed(),
+        error: result
+            .as_ref()
+            .err()
+            .map(|error| error as &(dyn Error + 'static)),
+    });
+    result
+}
@@ -1,5 +1,6 @@
-use super::frame_info::{FrameInfo, GlobalFrameInfo, FRAME_INFO};
+use super::frame_info::{FrameInfo, FrameLocation, GlobalFrameInfo, FRAME_INFO};
 use backtrace::Backtrace;
+use once_cell::sync::OnceCell;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
@@ -7,6 +8,9 @@ use wasmer_vm::{raise_user_trap, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
+///
+/// It's a single pointer, so that returning a `Result` with it stays
+/// cheap on the successful paths.
 #[derive(Clone)]
 pub struct RuntimeError {
     inner: Arc<RuntimeErrorInner>,
@@ -35,8 +39,12 @@ impl fmt::Display for RuntimeErrorSource {
 struct RuntimeErrorInner {
     /// The source error (this can be a custom user `Error` or a [`TrapCode`])
     source: RuntimeErrorSource,
-    /// The reconstructed Wasm trace (from the native trace and the `GlobalFrameInfo`).
-    wasm_trace: Vec<FrameInfo>,
+    /// The locations of the Wasm frames of the native trace, found in
+    /// the `GlobalFrameInfo` while their modules are alive.
+    wasm_locations: Vec<FrameLocation>,
+    /// The reconstructed Wasm trace, symbolized from `wasm_locations`
+    /// the first time it's needed.
+    wasm_trace: OnceCell<Vec<FrameInfo>>,
     /// The native backtrace
     native_trace: Backtrace,
 }
@@ -48,6 +56,10 @@ fn _assert_trap_is_sync_and_send(t: &Trap) -> (&dyn Sync, &dyn Send) {
 impl RuntimeError {
     /// Creates a new generic `RuntimeError` with the given `message`.
     ///
+    /// This is how a host function traps: the error returned by the
+    /// host function unwinds the WebAssembly frames up to the host,
+    /// where the call returns it with its message.
+    ///
     /// # Example
     /// ```
     /// let trap = wasmer_engine::RuntimeError::new("unexpected error");
@@ -65,6 +77,7 @@ impl RuntimeError {
     }
 
     /// Create a new RuntimeError from a Trap.
+    #[cold]
     pub fn from_trap(trap: Trap) -> Self {
         let info = FRAME_INFO.read().unwrap();
         match trap {
@@ -132,6 +145,7 @@ impl RuntimeError {
         }
     }
 
+    #[cold]
     fn new_with_trace(
         info: &GlobalFrameInfo,
         trap_pc: Option<usize>,
@@ -162,16 +176,17 @@ impl RuntimeError {
             })
             .collect();
 
-        // Let's construct the trace
-        let wasm_trace = frames
+        // Let's locate the frames, the names are copied by `trace`
+        let wasm_locations = frames
             .into_iter()
-            .filter_map(|pc| info.lookup_frame_info(pc))
+            .filter_map(|pc| info.lookup_frame_location(pc))
             .collect::<Vec<_>>();
 
         Self {
             inner: Arc::new(RuntimeErrorInner {
                 source,
-                wasm_trace,
+                wasm_locations,
+                wasm_trace: OnceCell::new(),
                 native_trace,
             }),
         }
@@ -185,7 +200,13 @@ impl RuntimeError {
     /// Returns a list of function frames in WebAssembly code that led to this
     /// trap happening.
     pub fn trace(&self) -> &[FrameInfo] {
-        &self.inner.wasm_trace
+        self.inner.wasm_trace.get_or_init(|| {
+            self.inner
+                .wasm_locations
+                .iter()
+                .map(FrameLocation::symbolize)
+                .collect()
+        })
     }
 
     /// Attempts to downcast the `RuntimeError` to a concrete type.
@@ -225,7 +246,7 @@ impl fmt::Debug for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeError")
             .field("source", &self.inner.source)
-            .field("wasm_trace", &self.inner.wasm_trace)
+            .field("wasm_trace", &self.trace())
             .field("native_trace", &self.inner.native_trace)
             .finish()
     }
@@ -277,3 +298,25 @@ impl From<Trap> for RuntimeError {
         Self::from_trap(trap)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of;
+
+    #[test]
+    fn errors_are_small() {
+        assert_eq!(size_of::<RuntimeError>(), size_of::<usize>());
+        assert_eq!(size_of::<Result<(), RuntimeError>>(), size_of::<usize>());
+        assert_eq!(size_of::<Option<RuntimeError>>(), size_of::<usize>());
+        assert_eq!(size_of::<Result<(), Trap>>(), size_of::<Trap>());
+        assert!(size_of::<Result<(), Trap>>() <= 64);
+    }
+
+    #[test]
+    fn traces_without_wasm_frames_are_empty() {
+        let error = RuntimeError::new("unexpected error");
+        assert!(error.trace().is_empty());
+        assert_eq!(error.to_string(), "RuntimeError: unexpected error");
+    }
+}
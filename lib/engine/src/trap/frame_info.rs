@@ -88,6 +88,14 @@ impl GlobalFrameInfo {
     /// Returns an object if this `pc` is known to some previously registered
     /// module, or returns `None` if no information can be found.
     pub fn lookup_frame_info(&self, pc: usize) -> Option<FrameInfo> {
+        self.lookup_frame_location(pc)
+            .map(|location| location.symbolize())
+    }
+
+    /// Fetches the location of a program counter in a backtrace, like
+    /// [`Self::lookup_frame_info`] but without copying the names of
+    /// the module and of the function yet.
+    pub(crate) fn lookup_frame_location(&self, pc: usize) -> Option<FrameLocation> {
         let module = self.module_info(pc)?;
         let func = module.function_info(pc)?;
 
@@ -130,13 +138,11 @@ impl GlobalFrameInfo {
             // start offset of the function.
             None => instr_map.start_srcloc,
         };
-        let func_index = module.module.func_index(func.local_index);
-        Some(FrameInfo {
-            module_name: module.module.name(),
-            func_index: func_index.index() as u32,
-            function_name: module.module.function_names.get(&func_index).cloned(),
-            instr,
+        Some(FrameLocation {
+            module: module.module.clone(),
+            local_index: func.local_index,
             func_start: instr_map.start_srcloc,
+            instr,
         })
     }
 
@@ -242,6 +248,30 @@ pub fn register(
     Some(GlobalFrameInfoRegistration { key: max })
 }
 
+/// The location of a frame of a backtrace, found while the module of
+/// the frame is registered, and turned into a [`FrameInfo`] later on.
+#[derive(Debug)]
+pub(crate) struct FrameLocation {
+    module: Arc<ModuleInfo>,
+    local_index: LocalFunctionIndex,
+    func_start: SourceLoc,
+    instr: SourceLoc,
+}
+
+impl FrameLocation {
+    /// Copies the names of the module and of the function of the frame.
+    pub(crate) fn symbolize(&self) -> FrameInfo {
+        let func_index = self.module.func_index(self.local_index);
+        FrameInfo {
+            module_name: self.module.name(),
+            func_index: func_index.index() as u32,
+            function_name: self.module.function_names.get(&func_index).cloned(),
+            instr: self.instr,
+            func_start: self.func_start,
+        }
+    }
+}
+
 /// Description of a frame in a backtrace for a [`RuntimeError::trace`](crate::RuntimeError::trace).
 ///
 /// Whenever a WebAssembly trap occurs an instance of [`RuntimeError`]
@@ -0,0 +1,525 @@
+//! `indirect_calls` is a middleware for counting the `call_indirect`
+//! dispatches of an instance by target: which slots of which tables
+//! are called through, and how often, to find the hot spots of the
+//! indirect calls.
+//!
+//! Each `call_indirect` is preceded by a call to a host recorder,
+//! set with [`record_indirect_calls`] once the module is
+//! instantiated. Until then, or once the recorder is dropped, the
+//! calls are not counted and only cost a check of a global. The
+//! counts of the current recorder can also be read from the instance,
+//! with [`IndirectCallStatsExt::indirect_call_stats`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::{imports, CompilerConfig, Cranelift, Instance, Module, Store, Universal};
+//! use wasmer_middlewares::indirect_calls::{
+//!     record_indirect_calls, IndirectCallStatsExt, IndirectCallTarget,
+//! };
+//! use wasmer_middlewares::IndirectCallCounter;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut compiler_config = Cranelift::default();
+//! compiler_config.push_middleware(Arc::new(IndirectCallCounter::new()));
+//! let store = Store::new(&Universal::new(compiler_config).engine());
+//! let module = Module::new(&store, r#"
+//!     (module
+//!         (type $unary (func (result i32)))
+//!         (table 2 funcref)
+//!         (elem (i32.const 0) $zero $one)
+//!         (func $zero (result i32) (i32.const 0))
+//!         (func $one (result i32) (i32.const 1))
+//!         (func (export "dispatch") (param i32) (result i32)
+//!             (call_indirect (type $unary) (local.get 0))))
+//! "#)?;
+//! let instance = Instance::new(&module, &imports! {})?;
+//!
+//! let stats = record_indirect_calls(&instance);
+//! let dispatch = instance.exports.get_native_function::<i32, i32>("dispatch")?;
+//! dispatch.call(1)?;
+//! dispatch.call(1)?;
+//!
+//! assert_eq!(stats.count(IndirectCallTarget { table: 0, slot: 1 }), 2);
+//! assert_eq!(stats.count(IndirectCallTarget { table: 0, slot: 0 }), 0);
+//! assert_eq!(instance.indirect_call_stats(), Some(stats.counts()));
+//! # Ok(())
+//! # }
+//! ```
+
+use lazy_static::lazy_static;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Mutex, Weak};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    ExportIndex, Function, FunctionMiddleware, FunctionType, Global, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, Table, TableType, Type, Val,
+};
+use wasmer_types::{GlobalIndex, ModuleInfo, SignatureIndex, TableIndex};
+
+/// The indexes in the current module used to call the recorder.
+#[derive(Clone, Debug, MemoryUsage)]
+struct IndirectCallIndexes {
+    /// The signature of the recorder, `(i32 slot, i32 table) -> ()`.
+    signature: SignatureIndex,
+
+    /// The table holding the recorder as its single element.
+    table: TableIndex,
+
+    /// An i32 global, holding the token of the recorder when it's
+    /// set, 0 otherwise.
+    recording: GlobalIndex,
+
+    /// An i32 global holding the slot of the current indirect call
+    /// while it's recorded.
+    slot: GlobalIndex,
+}
+
+/// The module-level indirect call counting middleware.
+///
+/// # Panic
+///
+/// An instance of `IndirectCallCounter` should _not_ be shared among
+/// different modules, since it tracks module-specific information
+/// like the index of the table holding the recorder. Attempts to use
+/// an `IndirectCallCounter` instance from multiple modules will
+/// result in a panic.
+#[derive(Debug, Default)]
+pub struct IndirectCallCounter {
+    indexes: Mutex<Option<IndirectCallIndexes>>,
+}
+
+/// The function-level indirect call counting middleware.
+#[derive(Debug)]
+pub struct FunctionIndirectCallCounter {
+    indexes: IndirectCallIndexes,
+}
+
+impl IndirectCallCounter {
+    /// Creates an `IndirectCallCounter` middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ModuleMiddleware for IndirectCallCounter {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionIndirectCallCounter {
+            indexes: self.indexes.lock().unwrap().clone().unwrap(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut indexes = self.indexes.lock().unwrap();
+
+        if indexes.is_some() {
+            panic!("IndirectCallCounter::transform_module_info: Attempting to use an `IndirectCallCounter` middleware from multiple modules.");
+        }
+
+        // Append a table holding the recorder, to be set by
+        // `record_indirect_calls` once the module is instantiated.
+        let signature = module_info
+            .signatures
+            .push(FunctionType::new(vec![Type::I32, Type::I32], vec![]));
+        let table = module_info
+            .tables
+            .push(TableType::new(Type::FuncRef, 1, Some(1)));
+        module_info.exports.insert(
+            "wasmer_indirect_calls_recorder".to_string(),
+            ExportIndex::Table(table),
+        );
+
+        // Append a global telling whether the recorder is set, so that
+        // the calls don't trap before it is.
+        let recording = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+        module_info.exports.insert(
+            "wasmer_indirect_calls_recording".to_string(),
+            ExportIndex::Global(recording),
+        );
+
+        // Append a global to save the slot while it's recorded.
+        let slot = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        *indexes = Some(IndirectCallIndexes {
+            signature,
+            table,
+            recording,
+            slot,
+        });
+    }
+}
+
+impl MemoryUsage for IndirectCallCounter {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.indexes.size_of_val(tracker) - mem::size_of_val(&self.indexes)
+    }
+}
+
+impl FunctionMiddleware for FunctionIndirectCallCounter {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Operator::CallIndirect { table_index, .. } = operator {
+            let slot = self.indexes.slot.as_u32();
+            state.extend(&[
+                // globals[slot] = <the slot on top of the stack>;
+                // if globals[recording] { record(globals[slot], table_index); }
+                Operator::GlobalSet { global_index: slot },
+                Operator::GlobalGet {
+                    global_index: self.indexes.recording.as_u32(),
+                },
+                Operator::If {
+                    ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+                },
+                Operator::GlobalGet { global_index: slot },
+                Operator::I32Const {
+                    value: table_index as i32,
+                },
+                Operator::I32Const { value: 0 },
+                Operator::CallIndirect {
+                    index: self.indexes.signature.as_u32(),
+                    table_index: self.indexes.table.as_u32(),
+                },
+                Operator::End,
+                Operator::GlobalGet { global_index: slot },
+            ]);
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// The target of a `call_indirect`: a slot of a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IndirectCallTarget {
+    /// The index of the table in the module.
+    pub table: u32,
+
+    /// The slot of the table called through.
+    pub slot: u32,
+}
+
+type Counts = Mutex<HashMap<IndirectCallTarget, u64>>;
+
+/// The number of times each target of the `call_indirect`s of an
+/// [`Instance`][wasmer::Instance] was called through, returned by
+/// [`record_indirect_calls`].
+///
+/// The calls are recorded as long as this value is alive. A call is
+/// recorded when it's dispatched, even if the slot is empty or the
+/// signature doesn't match and the call traps.
+#[must_use = "the calls are no longer recorded when this value is dropped"]
+pub struct IndirectCallStats {
+    /// Identifies this recorder in the `recording` global while it's
+    /// the one set.
+    token: i32,
+
+    table: Table,
+
+    recording: Global,
+
+    /// The host function the table refers to, kept alive as long as
+    /// the table refers to it.
+    function: Function,
+
+    counts: Arc<Counts>,
+}
+
+impl IndirectCallStats {
+    /// Returns the number of calls through each target called at
+    /// least once, ordered by table and slot.
+    pub fn counts(&self) -> BTreeMap<IndirectCallTarget, u64> {
+        self.counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(target, count)| (*target, *count))
+            .collect()
+    }
+
+    /// Returns the number of calls through `target`.
+    pub fn count(&self, target: IndirectCallTarget) -> u64 {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&target)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the total number of indirect calls.
+    pub fn total(&self) -> u64 {
+        self.counts.lock().unwrap().values().sum()
+    }
+
+    /// Forgets the calls recorded so far.
+    pub fn reset(&self) {
+        self.counts.lock().unwrap().clear();
+    }
+}
+
+impl fmt::Debug for IndirectCallStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndirectCallStats")
+            .field("function", &self.function)
+            .field("counts", &self.counts())
+            .finish()
+    }
+}
+
+impl Drop for IndirectCallStats {
+    fn drop(&mut self) {
+        RECORDERS.lock().unwrap().remove(&self.token);
+        // A recorder set since this one replaced it, and keeps
+        // recording.
+        if self.recording.get().unwrap_i32() != self.token {
+            return;
+        }
+        // Neither can fail: the global is a mutable i32 and the table
+        // has a single `funcref` element.
+        self.recording.set(Val::I32(0)).unwrap();
+        self.table.set(0, Val::FuncRef(None)).unwrap();
+    }
+}
+
+lazy_static! {
+    /// The counts of the live recorders, by token, for
+    /// [`IndirectCallStatsExt::indirect_call_stats`].
+    static ref RECORDERS: Mutex<HashMap<i32, Weak<Counts>>> = Mutex::new(HashMap::new());
+}
+
+fn recorder_exports(instance: &Instance) -> (Table, Global) {
+    let table = instance
+        .exports
+        .get_table("wasmer_indirect_calls_recorder")
+        .expect("Can't get `wasmer_indirect_calls_recorder` from Instance")
+        .clone();
+    let recording = instance
+        .exports
+        .get_global("wasmer_indirect_calls_recording")
+        .expect("Can't get `wasmer_indirect_calls_recording` from Instance")
+        .clone();
+    (table, recording)
+}
+
+/// Reads the indirect calls recorded for an
+/// [`Instance`][wasmer::Instance].
+pub trait IndirectCallStatsExt {
+    /// Returns the number of calls through each target called at least
+    /// once, ordered by table and slot, as recorded by the current
+    /// [`IndirectCallStats`], or `None` if no calls are recorded.
+    ///
+    /// # Panic
+    ///
+    /// The instance must have been processed with the
+    /// [`IndirectCallCounter`] middleware at compile time, otherwise
+    /// this will panic.
+    fn indirect_call_stats(&self) -> Option<BTreeMap<IndirectCallTarget, u64>>;
+}
+
+impl IndirectCallStatsExt for Instance {
+    fn indirect_call_stats(&self) -> Option<BTreeMap<IndirectCallTarget, u64>> {
+        let (_, recording) = recorder_exports(self);
+        let token = recording.get().unwrap_i32();
+        if token == 0 {
+            return None;
+        }
+        let counts = RECORDERS.lock().unwrap().get(&token)?.upgrade()?;
+        let counts = counts.lock().unwrap();
+        Some(
+            counts
+                .iter()
+                .map(|(target, count)| (*target, *count))
+                .collect(),
+        )
+    }
+}
+
+/// Start recording the targets of the `call_indirect`s of an
+/// [`Instance`][wasmer::Instance].
+///
+/// The calls are recorded synchronously, from the thread making them,
+/// as long as the returned [`IndirectCallStats`] is alive. Recording
+/// again replaces the previous recorder, which no longer receives
+/// the calls.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`IndirectCallCounter`] middleware at compile time,
+/// otherwise this will panic.
+pub fn record_indirect_calls(instance: &Instance) -> IndirectCallStats {
+    let (table, recording) = recorder_exports(instance);
+
+    let token = crate::next_token();
+    let counts: Arc<Counts> = Arc::new(Mutex::new(HashMap::new()));
+    RECORDERS
+        .lock()
+        .unwrap()
+        .insert(token, Arc::downgrade(&counts));
+    let function = Function::new(
+        table.store(),
+        FunctionType::new(vec![Type::I32, Type::I32], vec![]),
+        {
+            let counts = counts.clone();
+            move |arguments| {
+                let target = IndirectCallTarget {
+                    slot: arguments[0].unwrap_i32() as u32,
+                    table: arguments[1].unwrap_i32() as u32,
+                };
+                *counts.lock().unwrap().entry(target).or_insert(0) += 1;
+                Ok(vec![])
+            }
+        },
+    );
+
+    table
+        .set(0, Val::FuncRef(Some(function.clone())))
+        .expect("Can't set `wasmer_indirect_calls_recorder` in Instance");
+    recording
+        .set(Val::I32(token))
+        .expect("Can't set `wasmer_indirect_calls_recording` in Instance");
+
+    IndirectCallStats {
+        token,
+        table,
+        recording,
+        function,
+        counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (type $binary (func (param i32 i32) (result i32)))
+            (table $ops 4 funcref)
+            (elem (i32.const 0) $add $sub $mul)
+            (func $add (type $binary) (i32.add (local.get 0) (local.get 1)))
+            (func $sub (type $binary) (i32.sub (local.get 0) (local.get 1)))
+            (func $mul (type $binary) (i32.mul (local.get 0) (local.get 1)))
+            (func (export "apply") (param $op i32) (param $a i32) (param $b i32) (result i32)
+                (call_indirect (type $binary) (local.get $a) (local.get $b) (local.get $op))))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instance() -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(IndirectCallCounter::new()));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        Instance::new(&module, &imports! {}).unwrap()
+    }
+
+    fn apply(instance: &Instance, op: i32, a: i32, b: i32) -> Result<i32, wasmer::RuntimeError> {
+        instance
+            .exports
+            .get_native_function::<(i32, i32, i32), i32>("apply")
+            .unwrap()
+            .call(op, a, b)
+    }
+
+    fn target(slot: u32) -> IndirectCallTarget {
+        IndirectCallTarget { table: 0, slot }
+    }
+
+    #[test]
+    fn counts_the_calls_by_slot() {
+        let instance = instance();
+        let stats = record_indirect_calls(&instance);
+
+        assert_eq!(apply(&instance, 0, 6, 3).unwrap(), 9);
+        assert_eq!(apply(&instance, 2, 6, 3).unwrap(), 18);
+        assert_eq!(apply(&instance, 2, 2, 2).unwrap(), 4);
+        assert_eq!(apply(&instance, 2, 1, 5).unwrap(), 5);
+        // The slot is recorded even if the call traps.
+        assert!(apply(&instance, 3, 1, 1).is_err());
+
+        let expected: BTreeMap<_, _> = vec![(target(0), 1), (target(2), 3), (target(3), 1)]
+            .into_iter()
+            .collect();
+        assert_eq!(stats.counts(), expected);
+        assert_eq!(stats.count(target(1)), 0);
+        assert_eq!(stats.total(), 5);
+
+        stats.reset();
+        assert_eq!(stats.total(), 0);
+        assert_eq!(apply(&instance, 1, 6, 3).unwrap(), 3);
+        assert_eq!(stats.count(target(1)), 1);
+    }
+
+    #[test]
+    fn calls_are_not_counted_without_a_recorder() {
+        let instance = instance();
+        assert_eq!(apply(&instance, 0, 1, 2).unwrap(), 3);
+
+        let stats = record_indirect_calls(&instance);
+        assert_eq!(apply(&instance, 0, 1, 2).unwrap(), 3);
+        let counts = stats.counts.clone();
+        drop(stats);
+
+        // Once the recorder is dropped, the calls still work but are
+        // no longer recorded.
+        assert_eq!(apply(&instance, 0, 1, 2).unwrap(), 3);
+        assert_eq!(counts.lock().unwrap().get(&target(0)), Some(&1));
+        assert_eq!(instance.indirect_call_stats(), None);
+    }
+
+    #[test]
+    fn the_stats_are_read_from_the_instance() {
+        let instance = instance();
+        assert_eq!(instance.indirect_call_stats(), None);
+
+        let stats = record_indirect_calls(&instance);
+        assert_eq!(apply(&instance, 2, 6, 3).unwrap(), 18);
+        let expected: BTreeMap<_, _> = vec![(target(2), 1)].into_iter().collect();
+        assert_eq!(instance.indirect_call_stats(), Some(expected));
+        assert_eq!(instance.indirect_call_stats(), Some(stats.counts()));
+    }
+
+    #[test]
+    fn dropping_a_replaced_recorder_keeps_the_current_one() {
+        let instance = instance();
+        let first = record_indirect_calls(&instance);
+        let second = record_indirect_calls(&instance);
+        drop(first);
+
+        assert_eq!(apply(&instance, 1, 6, 3).unwrap(), 3);
+        assert_eq!(second.count(target(1)), 1);
+        assert_eq!(instance.indirect_call_stats(), Some(second.counts()));
+
+        drop(second);
+        assert_eq!(apply(&instance, 1, 6, 3).unwrap(), 3);
+        assert_eq!(instance.indirect_call_stats(), None);
+    }
+}
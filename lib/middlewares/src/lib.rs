@@ -1,6 +1,24 @@
+pub mod indirect_calls;
 pub mod metering;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use indirect_calls::IndirectCallCounter;
 pub use metering::Metering;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The next token identifying a host callback set in an instance.
+static NEXT_TOKEN: AtomicU32 = AtomicU32::new(1);
+
+/// Returns a new token identifying a host callback set in an
+/// instance, never 0 so that 0 means that no callback is set.
+pub(crate) fn next_token() -> i32 {
+    loop {
+        let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed) as i32;
+        if token != 0 {
+            return token;
+        }
+    }
+}
@@ -3,22 +3,32 @@
 //! operators executed. The WebAssemblt instance execution is stopped
 //! when the limit is reached.
 //!
+//! When configured with [`OnExhaustion::Refill`], a host callback
+//! set with [`set_refill_callback`] is called instead, and can top up
+//! the points so that the execution continues where it stopped. A
+//! single call can also yield to the host when it runs out of points,
+//! with [`call_with_yield`].
+//!
 //! # Example
 //!
 //! [See the `metering` detailed and complete
 //! example](https://github.com/wasmerio/wasmer/blob/master/examples/metering.rs).
 
+use lazy_static::lazy_static;
 use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fmt;
 use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
 use wasmer::{
-    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
-    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    ExportIndex, Function, FunctionMiddleware, FunctionType, Global, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, RuntimeError, Table, TableType, Type, Val,
 };
-use wasmer_types::{GlobalIndex, ModuleInfo};
+use wasmer_types::{GlobalIndex, ModuleInfo, SignatureIndex, TableIndex};
 
 #[derive(Clone, MemoryUsage)]
 struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
@@ -48,6 +58,36 @@ impl fmt::Debug for MeteringGlobalIndexes {
     }
 }
 
+/// The indexes in the current module used to call the refill
+/// callback, see [`OnExhaustion::Refill`].
+#[derive(Clone, Debug, MemoryUsage)]
+struct MeteringRefillIndexes {
+    /// The signature of the callback, `(i64) -> i32`.
+    signature: SignatureIndex,
+
+    /// The table holding the callback as its single element.
+    table: TableIndex,
+}
+
+/// What happens when an instance runs out of metering points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MemoryUsage)]
+pub enum OnExhaustion {
+    /// The execution traps. This is the default.
+    Trap,
+
+    /// The refill callback set with [`set_refill_callback`] is called
+    /// right where the points are found to be exhausted, before any
+    /// further operator executes. It decides whether the execution
+    /// continues with more points, or traps.
+    Refill,
+}
+
+impl Default for OnExhaustion {
+    fn default() -> Self {
+        Self::Trap
+    }
+}
+
 /// The module-level metering middleware.
 ///
 /// # Panic
@@ -91,6 +131,13 @@ pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
 
     /// The global indexes for metering points.
     global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
+
+    /// What happens when the points are exhausted.
+    on_exhaustion: OnExhaustion,
+
+    /// The indexes used to call the refill callback, with
+    /// [`OnExhaustion::Refill`].
+    refill_indexes: Mutex<Option<MeteringRefillIndexes>>,
 }
 
 /// The function-level metering middleware.
@@ -101,6 +148,9 @@ pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// The global indexes for metering points.
     global_indexes: MeteringGlobalIndexes,
 
+    /// The indexes used to call the refill callback, if any.
+    refill_indexes: Option<MeteringRefillIndexes>,
+
     /// Accumulated cost of the current basic block.
     accumulated_cost: u64,
 }
@@ -131,8 +181,17 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
             initial_limit,
             cost_function: Arc::new(cost_function),
             global_indexes: Mutex::new(None),
+            on_exhaustion: OnExhaustion::default(),
+            refill_indexes: Mutex::new(None),
         }
     }
+
+    /// Sets what happens when the points are exhausted, see
+    /// [`OnExhaustion`].
+    pub fn on_exhaustion(mut self, on_exhaustion: OnExhaustion) -> Self {
+        self.on_exhaustion = on_exhaustion;
+        self
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
@@ -141,6 +200,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
             .field("global_indexes", &self.global_indexes)
+            .field("on_exhaustion", &self.on_exhaustion)
             .finish()
     }
 }
@@ -151,6 +211,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
         Box::new(FunctionMetering {
             cost_function: self.cost_function.clone(),
             global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            refill_indexes: self.refill_indexes.lock().unwrap().clone(),
             accumulated_cost: 0,
         })
     }
@@ -194,7 +255,49 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
         *global_indexes = Some(MeteringGlobalIndexes(
             remaining_points_global_index,
             points_exhausted_global_index,
-        ))
+        ));
+
+        if self.on_exhaustion == OnExhaustion::Refill {
+            // Append a table holding the refill callback, to be set
+            // by `set_refill_callback` once the module is instantiated.
+            let signature = module_info
+                .signatures
+                .push(FunctionType::new(vec![Type::I64], vec![Type::I32]));
+            let table = module_info
+                .tables
+                .push(TableType::new(Type::FuncRef, 1, Some(1)));
+
+            module_info.exports.insert(
+                "wasmer_metering_refill".to_string(),
+                ExportIndex::Table(table),
+            );
+
+            // Append a global holding the token of the callback in
+            // the table, 0 if there's none.
+            let owner = module_info
+                .globals
+                .push(GlobalType::new(Type::I32, Mutability::Var));
+            module_info
+                .global_initializers
+                .push(GlobalInit::I32Const(0));
+            module_info.exports.insert(
+                "wasmer_metering_refill_owner".to_string(),
+                ExportIndex::Global(owner),
+            );
+
+            *self.refill_indexes.lock().unwrap() = Some(MeteringRefillIndexes { signature, table });
+        }
+    }
+
+    /// The cost function is identified by its type, and the initial
+    /// limit and the exhaustion behavior by their values.
+    fn deterministic_id(&self) -> String {
+        format!(
+            "{}({}, {:?})",
+            std::any::type_name::<Self>(),
+            self.initial_limit,
+            self.on_exhaustion
+        )
     }
 }
 
@@ -202,6 +305,8 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> MemoryUsage for Metering<F
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
         mem::size_of_val(self) + self.global_indexes.size_of_val(tracker)
             - mem::size_of_val(&self.global_indexes)
+            + self.refill_indexes.size_of_val(tracker)
+            - mem::size_of_val(&self.refill_indexes)
     }
 }
 
@@ -210,6 +315,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionMetering<F> {
         f.debug_struct("FunctionMetering")
             .field("cost_function", &"<function>")
             .field("global_indexes", &self.global_indexes)
+            .field("refill_indexes", &self.refill_indexes)
             .finish()
     }
 }
@@ -238,17 +344,53 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
             | Operator::Return // end of function - branch source
             => {
                 if self.accumulated_cost > 0 {
-                    state.extend(&[
-                        // if unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) { throw(); }
-                        Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
-                        Operator::I64Const { value: self.accumulated_cost as i64 },
-                        Operator::I64LtU,
-                        Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
-                        Operator::I32Const { value: 1 },
-                        Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
-                        Operator::Unreachable,
-                        Operator::End,
+                    match self.refill_indexes {
+                        None => state.extend(&[
+                            // if unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) { throw(); }
+                            Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+                            Operator::I64Const { value: self.accumulated_cost as i64 },
+                            Operator::I64LtU,
+                            Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+                            Operator::I32Const { value: 1 },
+                            Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
+                            Operator::Unreachable,
+                            Operator::End,
+                        ]),
+                        Some(ref refill_indexes) => state.extend(&[
+                            // while unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) {
+                            //     globals[points_exhausted_index] = 1;
+                            //     if !refill(self.accumulated_cost) { throw(); }
+                            //     globals[points_exhausted_index] = 0;
+                            // }
+                            //
+                            // The points are marked as exhausted while
+                            // the callback decides, so that they stay
+                            // exhausted if the callback traps or isn't
+                            // set.
+                            Operator::Block { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+                            Operator::Loop { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+                            Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+                            Operator::I64Const { value: self.accumulated_cost as i64 },
+                            Operator::I64GeU,
+                            Operator::BrIf { relative_depth: 1 },
+                            Operator::I32Const { value: 1 },
+                            Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
+                            Operator::I64Const { value: self.accumulated_cost as i64 },
+                            Operator::I32Const { value: 0 },
+                            Operator::CallIndirect { index: refill_indexes.signature.as_u32(), table_index: refill_indexes.table.as_u32() },
+                            Operator::I32Eqz,
+                            Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+                            Operator::Unreachable,
+                            Operator::End,
+                            Operator::I32Const { value: 0 },
+                            Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
+                            Operator::Br { relative_depth: 0 },
+                            Operator::End,
+                            Operator::End,
+                        ]),
+                    }
 
+                    state.extend(&[
                         // globals[remaining_points_index] -= self.accumulated_cost;
                         Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
                         Operator::I64Const { value: self.accumulated_cost as i64 },
@@ -356,6 +498,282 @@ pub fn set_remaining_points(instance: &Instance, points: u64) {
         .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
 }
 
+/// The state of the metering when the refill callback is called, see
+/// [`set_refill_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exhaustion {
+    /// The points the execution needs to continue.
+    pub required: u64,
+
+    /// The points left, fewer than `required`.
+    pub remaining: u64,
+
+    /// The points consumed since the callback was set.
+    pub consumed: u64,
+}
+
+/// The decision of the refill callback, see [`set_refill_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExhaustionDecision {
+    /// Add the given points to the remaining points, and continue
+    /// the execution. If the points are still fewer than required,
+    /// the callback is called again.
+    Refill(u64),
+
+    /// Stop the execution, as with [`OnExhaustion::Trap`].
+    Trap,
+}
+
+/// The refill callback of an [`Instance`][wasmer::Instance], returned
+/// by [`set_refill_callback`].
+///
+/// The callback is removed from the instance when this value is
+/// dropped, unless another callback replaced it since: the execution
+/// then traps when the points are exhausted, and
+/// [`get_remaining_points`] returns [`MeteringPoints::Exhausted`].
+#[must_use = "the refill callback is removed when this value is dropped"]
+pub struct RefillCallback {
+    /// Identifies this callback in the `owner` global while it's the
+    /// one in the table.
+    token: i32,
+
+    table: Table,
+
+    owner: Global,
+
+    /// The host function the table refers to, kept alive as long as
+    /// the table refers to it.
+    function: Function,
+}
+
+impl fmt::Debug for RefillCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefillCallback")
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+impl Drop for RefillCallback {
+    fn drop(&mut self) {
+        let mut live = LIVE_CALLBACKS.lock().unwrap();
+        live.remove(&self.token);
+        // A callback set since this one replaced it, and stays.
+        if self.owner.get().unwrap_i32() != self.token {
+            return;
+        }
+        // Neither can fail: the table has a single `funcref` element
+        // and the global is a mutable i32.
+        self.table.set(0, Val::FuncRef(None)).unwrap();
+        self.owner.set(Val::I32(0)).unwrap();
+    }
+}
+
+lazy_static! {
+    /// The tokens of the live refill callbacks. It's locked while a
+    /// callback is set or removed, so that [`call_with_yield`] only
+    /// restores a callback still alive.
+    static ref LIVE_CALLBACKS: Mutex<HashSet<i32>> = Mutex::new(HashSet::new());
+}
+
+fn refill_exports(instance: &Instance) -> (Table, Global) {
+    let table = instance
+        .exports
+        .get_table("wasmer_metering_refill")
+        .expect("Can't get `wasmer_metering_refill` from Instance")
+        .clone();
+    let owner = instance
+        .exports
+        .get_global("wasmer_metering_refill_owner")
+        .expect("Can't get `wasmer_metering_refill_owner` from Instance")
+        .clone();
+    (table, owner)
+}
+
+/// Set the callback deciding what happens when the points of an
+/// [`Instance`][wasmer::Instance] are exhausted.
+///
+/// The callback is called synchronously, right where the execution
+/// runs out of points, and the execution continues from there if it
+/// refills the points. The callback stays set as long as the returned
+/// [`RefillCallback`] is alive, or until another callback replaces
+/// it.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Metering`] middleware configured with
+/// [`OnExhaustion::Refill`] at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::Instance;
+/// use wasmer_middlewares::metering::{
+///     set_refill_callback, ExhaustionDecision, RefillCallback,
+/// };
+///
+/// /// Grant 1000 more points at a time, up to 10000 points in total.
+/// fn set_budget(instance: &Instance) -> RefillCallback {
+///     set_refill_callback(instance, |exhaustion| {
+///         if exhaustion.consumed < 10_000 {
+///             ExhaustionDecision::Refill(1000)
+///         } else {
+///             ExhaustionDecision::Trap
+///         }
+///     })
+/// }
+/// ```
+pub fn set_refill_callback<F>(instance: &Instance, callback: F) -> RefillCallback
+where
+    F: Fn(&Exhaustion) -> ExhaustionDecision + Send + Sync + 'static,
+{
+    let (table, owner) = refill_exports(instance);
+
+    // The global is weakly referenced, as the instance refers to
+    // the function through the table.
+    let remaining_points: Global = instance
+        .exports
+        .get_with_generics_weak("wasmer_metering_remaining_points")
+        .expect("Can't get `wasmer_metering_remaining_points` from Instance");
+    let remaining = |global: &Global| -> u64 {
+        global
+            .get()
+            .try_into()
+            .expect("`wasmer_metering_remaining_points` from Instance has wrong type")
+    };
+
+    // The points granted since the callback was set, to compute the
+    // consumed points.
+    let granted = AtomicU64::new(remaining(&remaining_points));
+
+    let function = Function::new(
+        table.store(),
+        FunctionType::new(vec![Type::I64], vec![Type::I32]),
+        move |arguments| {
+            let left = remaining(&remaining_points);
+            let exhaustion = Exhaustion {
+                required: arguments[0].unwrap_i64() as u64,
+                remaining: left,
+                consumed: granted.load(Ordering::SeqCst).saturating_sub(left),
+            };
+
+            let refilled = match callback(&exhaustion) {
+                ExhaustionDecision::Refill(points) => {
+                    granted.fetch_add(points, Ordering::SeqCst);
+                    remaining_points.set(left.saturating_add(points).into())?;
+                    1
+                }
+                ExhaustionDecision::Trap => 0,
+            };
+
+            Ok(vec![Val::I32(refilled)])
+        },
+    );
+
+    let token = crate::next_token();
+    let mut live = LIVE_CALLBACKS.lock().unwrap();
+    live.insert(token);
+    table
+        .set(0, Val::FuncRef(Some(function.clone())))
+        .expect("Can't set `wasmer_metering_refill` in Instance");
+    owner
+        .set(Val::I32(token))
+        .expect("Can't set `wasmer_metering_refill_owner` in Instance");
+    drop(live);
+
+    RefillCallback {
+        token,
+        table,
+        owner,
+        function,
+    }
+}
+
+/// The decision of a call made with [`call_with_yield`] when it yields
+/// to the host, out of points.
+pub type YieldDecision = ExhaustionDecision;
+
+/// Call `function` of `instance` with `params`, yielding to `on_yield`
+/// each time the call runs out of points.
+///
+/// The call is paused, without unwinding, while `on_yield` decides: it
+/// resumes where it stopped with [`YieldDecision::Refill`], and traps
+/// with [`YieldDecision::Trap`]. The refill callback of the instance,
+/// if any, is replaced by `on_yield` during the call, and restored
+/// afterwards if it's still alive and no other callback was set during
+/// the call.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Metering`] middleware configured with
+/// [`OnExhaustion::Refill`] at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{Function, Instance, RuntimeError, Val};
+/// use wasmer_middlewares::metering::{call_with_yield, YieldDecision};
+///
+/// /// Call `function`, granting 1000 more points at a time, up to
+/// /// 10000 points in total.
+/// fn call_with_budget(
+///     instance: &Instance,
+///     function: &Function,
+/// ) -> Result<Box<[Val]>, RuntimeError> {
+///     call_with_yield(instance, function, &[], |exhaustion| {
+///         if exhaustion.consumed < 10_000 {
+///             YieldDecision::Refill(1000)
+///         } else {
+///             YieldDecision::Trap
+///         }
+///     })
+/// }
+/// ```
+pub fn call_with_yield<F>(
+    instance: &Instance,
+    function: &Function,
+    params: &[Val],
+    on_yield: F,
+) -> Result<Box<[Val]>, RuntimeError>
+where
+    F: Fn(&Exhaustion) -> YieldDecision + Send + Sync + 'static,
+{
+    let (table, owner) = refill_exports(instance);
+    let previous = {
+        let _live = LIVE_CALLBACKS.lock().unwrap();
+        (table.get(0), owner.get())
+    };
+
+    let callback = set_refill_callback(instance, on_yield);
+    let result = function.call(params);
+
+    // Restore the previous callback before dropping `on_yield`, which
+    // then leaves the table alone. A callback set during the call
+    // stays, and a previous callback dropped during the call isn't
+    // restored.
+    let live = LIVE_CALLBACKS.lock().unwrap();
+    if owner.get().unwrap_i32() == callback.token {
+        let (function, token) = match previous {
+            (Some(function), Val::I32(token)) if live.contains(&token) => (function, token),
+            _ => (Val::FuncRef(None), 0),
+        };
+        table
+            .set(0, function)
+            .expect("Can't set `wasmer_metering_refill` in Instance");
+        owner
+            .set(Val::I32(token))
+            .expect("Can't set `wasmer_metering_refill_owner` in Instance");
+    }
+    drop(live);
+    drop(callback);
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -387,6 +805,41 @@ mod tests {
         .into()
     }
 
+    fn loop_bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func (export "sum") (param $n i32) (result i32)
+                (local $sum i32)
+                (loop $continue
+                    (local.set $sum (i32.add (local.get $sum) (local.get $n)))
+                    (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                    (br_if $continue (local.get $n)))
+                local.get $sum))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn loop_instance(initial_limit: u64, on_exhaustion: OnExhaustion) -> Instance {
+        let metering =
+            Arc::new(Metering::new(initial_limit, |_: &Operator| 1).on_exhaustion(on_exhaustion));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, loop_bytecode()).unwrap();
+        Instance::new(&module, &imports! {}).unwrap()
+    }
+
+    fn call_sum(instance: &Instance) -> Result<i32, wasmer::RuntimeError> {
+        instance
+            .exports
+            .get_native_function::<i32, i32>("sum")
+            .unwrap()
+            .call(100)
+    }
+
     #[test]
     fn get_remaining_points_works() {
         let metering = Arc::new(Metering::new(10, cost_function));
@@ -485,4 +938,163 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    #[test]
+    fn refill_callback_resumes_execution() {
+        // The cost of a call to `sum`.
+        let limit = 1_000_000;
+        let instance = loop_instance(limit, OnExhaustion::Refill);
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+        let cost = match get_remaining_points(&instance) {
+            MeteringPoints::Remaining(points) => limit - points,
+            MeteringPoints::Exhausted => unreachable!(),
+        };
+
+        // With a quarter of the cost to start with, and a bit more than
+        // a quarter at each refill, the call needs exactly 3 refills.
+        let refill = cost / 4 + 1;
+        let instance = loop_instance(cost / 4, OnExhaustion::Refill);
+        let exhaustions = Arc::new(Mutex::new(Vec::new()));
+        let _callback = set_refill_callback(&instance, {
+            let exhaustions = exhaustions.clone();
+            move |exhaustion| {
+                exhaustions.lock().unwrap().push(*exhaustion);
+                ExhaustionDecision::Refill(refill)
+            }
+        });
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+
+        let exhaustions = exhaustions.lock().unwrap();
+        assert_eq!(exhaustions.len(), 3);
+        for exhaustion in exhaustions.iter() {
+            assert!(exhaustion.remaining < exhaustion.required);
+        }
+        for pair in exhaustions.windows(2) {
+            assert!(pair[0].consumed < pair[1].consumed);
+        }
+        assert_eq!(
+            get_remaining_points(&instance),
+            MeteringPoints::Remaining(cost / 4 + 3 * refill - cost)
+        );
+    }
+
+    #[test]
+    fn refill_callback_can_trap() {
+        let instance = loop_instance(10, OnExhaustion::Refill);
+
+        // Without a callback, the execution traps.
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+
+        set_remaining_points(&instance, 10);
+        let callback = set_refill_callback(&instance, |_| ExhaustionDecision::Trap);
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+
+        // The callback is removed when dropped.
+        drop(callback);
+        set_remaining_points(&instance, 10);
+        let refilled = set_refill_callback(&instance, |_| ExhaustionDecision::Refill(1000));
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+        drop(refilled);
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+    }
+
+    #[test]
+    fn call_with_yield_resumes_the_call() {
+        let instance = loop_instance(10, OnExhaustion::Refill);
+        let sum = instance.exports.get_function("sum").unwrap();
+        let _callback = set_refill_callback(&instance, |_| ExhaustionDecision::Trap);
+
+        let yields = Arc::new(Mutex::new(Vec::new()));
+        let result = call_with_yield(&instance, sum, &[Val::I32(100)], {
+            let yields = yields.clone();
+            move |exhaustion| {
+                let mut yields = yields.lock().unwrap();
+                yields.push(*exhaustion);
+                if yields.len() < 3 {
+                    YieldDecision::Refill(200)
+                } else {
+                    YieldDecision::Refill(1_000_000)
+                }
+            }
+        });
+        assert_eq!(&*result.unwrap(), &[Val::I32(5050)]);
+
+        let yields = yields.lock().unwrap();
+        assert_eq!(yields.len(), 3);
+        for pair in yields.windows(2) {
+            assert!(pair[0].consumed < pair[1].consumed);
+        }
+
+        // The callback set before the call is restored.
+        set_remaining_points(&instance, 10);
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+
+        set_remaining_points(&instance, 10);
+        let result = call_with_yield(&instance, sum, &[Val::I32(100)], |_| YieldDecision::Trap);
+        assert!(result.is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+    }
+
+    #[test]
+    fn overlapping_refill_callbacks() {
+        let instance = loop_instance(10, OnExhaustion::Refill);
+        let first = set_refill_callback(&instance, |_| ExhaustionDecision::Trap);
+        let second = set_refill_callback(&instance, |_| ExhaustionDecision::Refill(1000));
+
+        // Dropping the replaced callback leaves the current one set.
+        drop(first);
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+
+        let third = set_refill_callback(&instance, |_| ExhaustionDecision::Refill(1000));
+        drop(second);
+        set_remaining_points(&instance, 10);
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+
+        drop(third);
+        set_remaining_points(&instance, 10);
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+    }
+
+    #[test]
+    fn call_with_yield_does_not_restore_a_dropped_callback() {
+        let instance = loop_instance(10, OnExhaustion::Refill);
+        let sum = instance.exports.get_function("sum").unwrap();
+        let previous = Arc::new(Mutex::new(Some(set_refill_callback(&instance, |_| {
+            ExhaustionDecision::Refill(1000)
+        }))));
+
+        let result = call_with_yield(&instance, sum, &[Val::I32(100)], {
+            let previous = previous.clone();
+            move |_| {
+                previous.lock().unwrap().take();
+                YieldDecision::Refill(1_000_000)
+            }
+        });
+        assert_eq!(&*result.unwrap(), &[Val::I32(5050)]);
+
+        // The previous callback was dropped during the call: none is
+        // set anymore.
+        set_remaining_points(&instance, 10);
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+    }
+
+    #[test]
+    fn trap_on_exhaustion_is_unchanged() {
+        let instance = loop_instance(10, OnExhaustion::Trap);
+        assert!(instance
+            .exports
+            .get_table("wasmer_metering_refill")
+            .is_err());
+        assert!(call_sum(&instance).is_err());
+        assert_eq!(get_remaining_points(&instance), MeteringPoints::Exhausted);
+
+        set_remaining_points(&instance, 1_000_000);
+        assert_eq!(call_sum(&instance).unwrap(), 5050);
+    }
 }
@@ -1,10 +1,10 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::ptr;
 use std::sync::atomic;
 
 /// This type does not do reference counting automatically, reference counting can be done with
 /// [`Self::ref_clone`] and [`Self::ref_drop`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct VMExternRef(*const VMExternRefInner);
 
@@ -136,15 +136,24 @@ impl VMExternRef {
         }
     }
 
-    #[allow(dead_code)]
     /// Get the number of strong references to this data.
-    fn strong_count(&self) -> usize {
+    pub fn strong_count(&self) -> usize {
         if self.0.is_null() {
             0
         } else {
             unsafe { (&*self.0).strong.load(atomic::Ordering::SeqCst) }
         }
     }
+
+    /// Get the `TypeId` of the type of the data, or `None` for a null
+    /// reference.
+    pub fn data_type_id(&self) -> Option<TypeId> {
+        if self.0.is_null() {
+            None
+        } else {
+            unsafe { Some((*(&*self.0).data).type_id()) }
+        }
+    }
 }
 
 #[derive(Debug)]
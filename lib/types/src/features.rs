@@ -64,6 +64,25 @@ impl Features {
         }
     }
 
+    /// Create a new feature set with all the proposals disabled,
+    /// including the ones enabled by default.
+    pub fn none() -> Self {
+        Self {
+            threads: false,
+            reference_types: false,
+            simd: false,
+            bulk_memory: false,
+            multi_value: false,
+            tail_call: false,
+            module_linking: false,
+            multi_memory: false,
+            memory64: false,
+            exceptions: false,
+            relaxed_simd: false,
+            extended_const: false,
+        }
+    }
+
     /// Configures whether the WebAssembly threads proposal will be enabled.
     ///
     /// The [WebAssembly threads proposal][threads] is not currently fully
@@ -236,6 +255,27 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Disables the proposals whose instructions may give different
+    /// results on different machines, for the deterministic mode of
+    /// the engines:
+    ///
+    /// - the threads proposal, since the interleaving of the accesses
+    ///   of several threads to a shared memory depends on their
+    ///   scheduling, and `memory.atomic.wait` on timing;
+    /// - the relaxed SIMD proposal, whose instructions (such as
+    ///   `f32x4.relaxed_fma` or `i32x4.relaxed_trunc_f32x4_s`) have
+    ///   results defined by the implementation.
+    ///
+    /// Modules using them are then rejected by the validation. The
+    /// instructions of the other proposals are deterministic, except
+    /// for the bit patterns of the NaNs they produce, which the
+    /// deterministic mode canonicalizes.
+    pub fn disable_nondeterministic(&mut self) -> &mut Self {
+        self.threads = false;
+        self.relaxed_simd = false;
+        self
+    }
 }
 
 impl Default for Features {
@@ -285,6 +325,25 @@ mod test_features {
         assert!(features.bulk_memory);
     }
 
+    #[test]
+    fn no_features() {
+        let none = Features::none();
+        assert!(!none.reference_types);
+        assert!(!none.bulk_memory);
+        assert_ne!(none, Features::default());
+    }
+
+    #[test]
+    fn disable_nondeterministic() {
+        let mut features = Features::new();
+        features.threads(true).simd(true).relaxed_simd = true;
+        features.disable_nondeterministic();
+        assert!(!features.threads);
+        assert!(!features.relaxed_simd);
+        assert!(features.simd);
+        assert!(features.bulk_memory);
+    }
+
     #[test]
     fn enable_simd() {
         let mut features = Features::new();
@@ -61,6 +61,12 @@ where
 {
     /// Creates a new MemoryView given a `pointer` and `length`.
     pub unsafe fn new(ptr: *mut T, length: u32) -> Self {
+        Self::new64(ptr, length.into())
+    }
+
+    /// Creates a new MemoryView given a `pointer` and a 64-bit `length`,
+    /// for the views of 64-bit memories.
+    pub unsafe fn new64(ptr: *mut T, length: u64) -> Self {
         Self {
             ptr,
             length: length as usize,
@@ -70,12 +76,18 @@ where
 
     /// Creates a subarray view from this `MemoryView`.
     pub fn subarray(&self, start: u32, end: u32) -> Self {
+        self.subarray64(start.into(), end.into())
+    }
+
+    /// Creates a subarray view from this `MemoryView`, given 64-bit
+    /// offsets, for the views of 64-bit memories.
+    pub fn subarray64(&self, start: u64, end: u64) -> Self {
         assert!(
-            (start as usize) < self.length,
+            start < self.length as u64,
             "The range start is bigger than current length"
         );
         assert!(
-            (end as usize) < self.length,
+            end < self.length as u64,
             "The range end is bigger than current length"
         );
 
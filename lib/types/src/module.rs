@@ -8,10 +8,10 @@ use crate::entity::{EntityRef, PrimaryMap};
 #[cfg(feature = "enable-rkyv")]
 use crate::ArchivableIndexMap;
 use crate::{
-    CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex,
-    FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, LocalFunctionIndex,
-    LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType, SignatureIndex,
-    TableIndex, TableInitializer, TableType,
+    CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, Features,
+    FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType,
+    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    MemoryType, SignatureIndex, TableIndex, TableInitializer, TableType, Type,
 };
 use indexmap::IndexMap;
 use loupe::MemoryUsage;
@@ -72,6 +72,10 @@ pub struct ModuleInfo {
     /// The name of this wasm module, often found in the wasm file.
     pub name: Option<String>,
 
+    /// The hexadecimal BLAKE3 hash of the binary this module was
+    /// compiled from, if it is known.
+    pub hash: Option<String>,
+
     /// Imported entities with the (module, field, index_of_the_import)
     ///
     /// Keeping the `index_of_the_import` is important, as there can be
@@ -132,6 +136,10 @@ pub struct ModuleInfo {
 
     /// Number of imported globals in the module.
     pub num_imported_globals: usize,
+
+    /// Whether the functions of the module check the epoch deadline of
+    /// the store, see `CompilerConfig::epoch_interruption`.
+    pub epoch_interruption: bool,
 }
 
 /// Mirror version of ModuleInfo that can derive rkyv traits
@@ -139,6 +147,7 @@ pub struct ModuleInfo {
 #[derive(RkyvSerialize, RkyvDeserialize, Archive)]
 pub struct ArchivableModuleInfo {
     name: Option<String>,
+    hash: Option<String>,
     imports: ArchivableIndexMap<(String, String, u32), ImportIndex>,
     exports: ArchivableIndexMap<String, ExportIndex>,
     start_function: Option<FunctionIndex>,
@@ -158,6 +167,7 @@ pub struct ArchivableModuleInfo {
     num_imported_tables: usize,
     num_imported_memories: usize,
     num_imported_globals: usize,
+    epoch_interruption: bool,
 }
 
 #[cfg(feature = "enable-rkyv")]
@@ -165,6 +175,7 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
     fn from(it: ModuleInfo) -> ArchivableModuleInfo {
         ArchivableModuleInfo {
             name: it.name,
+            hash: it.hash,
             imports: ArchivableIndexMap::from(it.imports),
             exports: ArchivableIndexMap::from(it.exports),
             start_function: it.start_function,
@@ -184,6 +195,7 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            epoch_interruption: it.epoch_interruption,
         }
     }
 }
@@ -194,6 +206,7 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
         ModuleInfo {
             id: Default::default(),
             name: it.name,
+            hash: it.hash,
             imports: it.imports.into(),
             exports: it.exports.into(),
             start_function: it.start_function,
@@ -213,6 +226,7 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            epoch_interruption: it.epoch_interruption,
         }
     }
 }
@@ -258,6 +272,7 @@ impl<D: Fallible + ?Sized + SharedDeserializeRegistry> RkyvDeserialize<ModuleInf
 impl PartialEq for ModuleInfo {
     fn eq(&self, other: &ModuleInfo) -> bool {
         self.name == other.name
+            && self.hash == other.hash
             && self.imports == other.imports
             && self.exports == other.exports
             && self.start_function == other.start_function
@@ -277,6 +292,7 @@ impl PartialEq for ModuleInfo {
             && self.num_imported_tables == other.num_imported_tables
             && self.num_imported_memories == other.num_imported_memories
             && self.num_imported_globals == other.num_imported_globals
+            && self.epoch_interruption == other.epoch_interruption
     }
 }
 
@@ -467,6 +483,55 @@ impl ModuleInfo {
             .take(self.num_imported_functions)
             .map(move |sig_index| self.signatures[*sig_index].clone())
     }
+
+    /// Get the features the module requires, as far as they can be
+    /// told from its types, memories, tables and segments.
+    ///
+    /// The function bodies are not kept, so that proposals only used
+    /// by instructions (threads atomics, tail calls, bulk memory
+    /// operations, ...) are not reported: this is a subset of the
+    /// features found by scanning the binary of the module.
+    pub fn required_features(&self) -> Features {
+        let mut features = Features::none();
+        let value_type = |features: &mut Features, ty: Type| match ty {
+            Type::V128 => features.simd = true,
+            Type::ExternRef | Type::FuncRef => features.reference_types = true,
+            _ => {}
+        };
+
+        for signature in self.signatures.values() {
+            if signature.results().len() > 1 {
+                features.multi_value = true;
+            }
+            for ty in signature.params().iter().chain(signature.results()) {
+                value_type(&mut features, *ty);
+            }
+        }
+        for global in self.globals.values() {
+            value_type(&mut features, global.ty);
+        }
+        for table in self.tables.values() {
+            if table.ty != Type::FuncRef {
+                features.reference_types = true;
+            }
+        }
+        if self.tables.len() > 1 {
+            features.reference_types = true;
+        }
+        for memory in self.memories.values() {
+            if memory.shared {
+                features.threads = true;
+            }
+        }
+        if self.memories.len() > 1 {
+            features.multi_memory = true;
+        }
+        if !self.passive_data.is_empty() || !self.passive_elements.is_empty() {
+            features.bulk_memory = true;
+        }
+
+        features
+    }
 }
 
 impl fmt::Display for ModuleInfo {
@@ -178,11 +178,13 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
         minimum: exported_minimum,
         maximum: exported_maximum,
         shared: exported_shared,
+        index_type: exported_index_type,
     } = exported;
     let MemoryType {
         minimum: imported_minimum,
         maximum: imported_maximum,
         shared: imported_shared,
+        index_type: imported_index_type,
     } = imported;
 
     imported_minimum <= exported_minimum
@@ -190,6 +192,7 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
             || (!exported_maximum.is_none()
                 && imported_maximum.unwrap() >= exported_maximum.unwrap()))
         && exported_shared == imported_shared
+        && exported_index_type == imported_index_type
 }
 
 macro_rules! accessors {
@@ -236,6 +239,17 @@ impl ExternType {
     }
 }
 
+impl fmt::Display for ExternType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Function(ty) => write!(f, "function {}", ty),
+            Self::Global(ty) => write!(f, "global {}", ty),
+            Self::Table(ty) => write!(f, "table {}", ty),
+            Self::Memory(ty) => write!(f, "memory {}", ty),
+        }
+    }
+}
+
 // TODO: `shrink_to_fit` these or change it to `Box<[Type]>` if not using
 // Cow or something else
 /// The signature of a function that is either implemented
@@ -517,6 +531,37 @@ impl fmt::Display for TableType {
 
 // Memory Types
 
+/// The type of the addresses of a memory: 32-bit, or 64-bit as
+/// proposed by the [memory64 proposal].
+///
+/// [memory64 proposal]: https://github.com/WebAssembly/memory64
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, MemoryUsage)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+pub enum IndexType {
+    /// The memory is addressed with `i32`s.
+    I32,
+    /// The memory is addressed with `i64`s.
+    I64,
+}
+
+impl IndexType {
+    /// Returns the largest number of pages a memory addressed with
+    /// this type can have.
+    ///
+    /// 32-bit memories are limited to 65,536 pages (4GiB), 64-bit
+    /// memories to the largest value the `Pages` type represents.
+    pub const fn max_pages(self) -> Pages {
+        match self {
+            Self::I32 => Pages::max_value(),
+            Self::I64 => Pages(u32::MAX),
+        }
+    }
+}
+
 /// A descriptor for a WebAssembly memory type.
 ///
 /// Memories are described in units of pages (64KB) and represent contiguous
@@ -534,6 +579,8 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// The type of the addresses of the memory.
+    pub index_type: IndexType,
 }
 
 impl MemoryType {
@@ -547,6 +594,19 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            index_type: IndexType::I32,
+        }
+    }
+
+    /// Creates a new descriptor for a 64-bit WebAssembly memory given
+    /// the specified limits of the memory.
+    pub fn new64<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>, shared: bool) -> Self
+    where
+        IntoPages: Into<Pages>,
+    {
+        Self {
+            index_type: IndexType::I64,
+            ..Self::new(minimum, maximum, shared)
         }
     }
 }
@@ -554,10 +614,18 @@ impl MemoryType {
 impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
+        let index_type = match self.index_type {
+            IndexType::I32 => "",
+            IndexType::I64 => "i64 ",
+        };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(
+                f,
+                "{}{} ({:?}..{:?})",
+                index_type, shared, self.minimum, maximum
+            )
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{}{} ({:?}..)", index_type, shared, self.minimum)
         }
     }
 }
@@ -39,8 +39,8 @@ impl Pages {
         Self(WASM_MAX_PAGES)
     }
 
-    /// Checked addition. Computes `self + rhs`,
-    /// returning `None` if overflow occurred.
+    /// Checked addition. Computes `self + rhs`, returning `None` if
+    /// the result exceeds the 65,536 pages of a 32-bit memory.
     pub fn checked_add(self, rhs: Self) -> Option<Self> {
         let added = (self.0 as usize) + (rhs.0 as usize);
         if added <= (WASM_MAX_PAGES as usize) {
@@ -54,6 +54,16 @@ impl Pages {
     pub fn bytes(self) -> Bytes {
         self.into()
     }
+
+    /// Checked conversion to bytes, returning `None` if the number of
+    /// bytes doesn't fit in a `usize`, as with the pages of 64-bit
+    /// memories on 32-bit hosts.
+    pub fn checked_bytes(self) -> Option<Bytes> {
+        usize::try_from(self.0)
+            .ok()?
+            .checked_mul(WASM_PAGE_SIZE)
+            .map(Bytes)
+    }
 }
 
 impl fmt::Debug for Pages {
@@ -183,4 +193,25 @@ mod tests {
         let result = Pages::try_from(Bytes(usize::MAX));
         assert_eq!(result.unwrap_err(), PageCountOutOfRange);
     }
+
+    #[test]
+    fn convert_pages_to_bytes() {
+        assert_eq!(Pages(0).checked_bytes(), Some(Bytes(0)));
+        assert_eq!(Pages(3).checked_bytes(), Some(Bytes(3 * WASM_PAGE_SIZE)));
+        assert_eq!(
+            Pages::max_value().checked_bytes(),
+            (WASM_MAX_PAGES as usize)
+                .checked_mul(WASM_PAGE_SIZE)
+                .map(Bytes)
+        );
+
+        // The pages of 64-bit memories go past 4GiB.
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            Pages(u32::MAX).checked_bytes(),
+            Some(Bytes(u32::MAX as usize * WASM_PAGE_SIZE))
+        );
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(Pages(u32::MAX).checked_bytes(), None);
+    }
 }
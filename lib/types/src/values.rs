@@ -4,6 +4,8 @@ use crate::lib::std::fmt;
 use crate::lib::std::ptr;
 use crate::lib::std::string::{String, ToString};
 use crate::types::Type;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Possible runtime values that a WebAssembly module can either consume or
 /// produce.
@@ -342,6 +344,62 @@ where
     }
 }
 
+/// The serialized form of a [`Value`].
+///
+/// The floats are serialized as their bits, to keep their NaN
+/// payloads, infinities and negative zeros whatever the format. The
+/// references only live as long as the process and aren't
+/// serialized: they are deserialized as null references.
+#[cfg(feature = "enable-serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Value")]
+enum SerializedValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    ExternRef,
+    FuncRef,
+    V128([u8; 16]),
+}
+
+#[cfg(feature = "enable-serde")]
+impl<T> Serialize for Value<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::I32(value) => SerializedValue::I32(*value),
+            Self::I64(value) => SerializedValue::I64(*value),
+            Self::F32(value) => SerializedValue::F32(value.to_bits()),
+            Self::F64(value) => SerializedValue::F64(value.to_bits()),
+            Self::ExternRef(_) => SerializedValue::ExternRef,
+            Self::FuncRef(_) => SerializedValue::FuncRef,
+            Self::V128(value) => SerializedValue::V128(value.to_le_bytes()),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "enable-serde")]
+impl<'de, T> Deserialize<'de> for Value<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match SerializedValue::deserialize(deserializer)? {
+            SerializedValue::I32(value) => Self::I32(value),
+            SerializedValue::I64(value) => Self::I64(value),
+            SerializedValue::F32(bits) => Self::F32(f32::from_bits(bits)),
+            SerializedValue::F64(bits) => Self::F64(f64::from_bits(bits)),
+            SerializedValue::ExternRef => Self::ExternRef(ExternRef::null()),
+            SerializedValue::FuncRef => Self::FuncRef(None),
+            SerializedValue::V128(bytes) => Self::V128(u128::from_le_bytes(bytes)),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,232 @@
+//! The epoch deadlines interrupting the code compiled with epoch
+//! interruption, see [`EpochDeadline`], and the interruption of a
+//! single call, see [`CallInterrupt`].
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Mutex};
+
+/// The epoch deadline of the instances of a store.
+///
+/// The code compiled with epoch interruption compares the epoch
+/// counter with a threshold, the first field of this struct, at every
+/// function entry and loop header, and calls the
+/// `wasmer_vm_epoch_deadline_reached` builtin once the counter reaches
+/// it. The builtin traps if the counter is past the deadline, or if the
+/// call it's called from is interrupted by a [`CallInterrupt`].
+///
+/// The threshold is the epoch following the deadline, unless calls are
+/// being interrupted: it's then 0, so that the code running in the
+/// store checks whether it's interrupted, and goes on otherwise.
+#[derive(Debug)]
+#[repr(C)]
+pub struct EpochDeadline {
+    /// The epoch from which the builtin is called. It must stay the
+    /// first field, loaded by the compiled code.
+    threshold: AtomicU64,
+    /// The epoch after which the code traps.
+    deadline: AtomicU64,
+    /// The number of calls being interrupted.
+    interrupts: AtomicUsize,
+}
+
+impl EpochDeadline {
+    /// Creates a deadline which is never reached.
+    pub const fn new() -> Self {
+        Self {
+            threshold: AtomicU64::new(u64::MAX),
+            deadline: AtomicU64::new(u64::MAX),
+            interrupts: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the epoch after which the code traps.
+    pub fn get(&self) -> u64 {
+        self.deadline.load(SeqCst)
+    }
+
+    /// Sets the epoch after which the code traps.
+    pub fn set(&self, epoch: u64) {
+        self.deadline.store(epoch, SeqCst);
+        self.update_threshold();
+    }
+
+    /// Whether the code traps at `epoch`, whether its call is
+    /// interrupted or not.
+    pub fn is_reached(&self, epoch: u64) -> bool {
+        epoch > self.get()
+    }
+
+    fn begin_interrupt(&self) {
+        self.interrupts.fetch_add(1, SeqCst);
+        self.update_threshold();
+    }
+
+    fn end_interrupt(&self) {
+        self.interrupts.fetch_sub(1, SeqCst);
+        self.update_threshold();
+    }
+
+    /// Stores the threshold matching the deadline and the interrupts,
+    /// again if either changed meanwhile: the last update is always
+    /// made after the last change.
+    fn update_threshold(&self) {
+        loop {
+            let threshold = self.expected_threshold();
+            self.threshold.store(threshold, SeqCst);
+            if self.expected_threshold() == threshold {
+                return;
+            }
+        }
+    }
+
+    fn expected_threshold(&self) -> u64 {
+        if self.interrupts.load(SeqCst) > 0 {
+            0
+        } else {
+            self.deadline.load(SeqCst).saturating_add(1)
+        }
+    }
+}
+
+impl Default for EpochDeadline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    /// The interrupts of the calls running on this thread, innermost
+    /// last.
+    static CALL_INTERRUPTS: RefCell<Vec<Arc<CallInterrupt>>> = RefCell::new(Vec::new());
+}
+
+/// A flag interrupting a single call into the code compiled with epoch
+/// interruption of a store, see [`CallInterrupt::run`] and
+/// [`CallInterrupt::interrupt`].
+///
+/// Unlike moving the [`EpochDeadline`] of the store, interrupting a
+/// call leaves the other calls into the store running meanwhile: they
+/// only check that they aren't interrupted at their next function
+/// entries and loop headers, until the interrupted call returns. The
+/// epoch counter isn't changed.
+#[derive(Debug)]
+pub struct CallInterrupt {
+    /// The deadline of the store the call runs in.
+    deadline: Arc<EpochDeadline>,
+    interrupted: AtomicBool,
+    state: Mutex<CallInterruptState>,
+}
+
+#[derive(Debug)]
+struct CallInterruptState {
+    /// Whether the deadline is lowered for this call.
+    lowered: bool,
+    /// Whether the call has returned.
+    returned: bool,
+}
+
+impl CallInterrupt {
+    /// Creates a flag interrupting a call into the store with the
+    /// epoch `deadline`.
+    pub fn new(deadline: Arc<EpochDeadline>) -> Self {
+        Self {
+            deadline,
+            interrupted: AtomicBool::new(false),
+            state: Mutex::new(CallInterruptState {
+                lowered: false,
+                returned: false,
+            }),
+        }
+    }
+
+    /// Returns the deadline of the store whose calls this flag
+    /// interrupts.
+    pub fn deadline(&self) -> &Arc<EpochDeadline> {
+        &self.deadline
+    }
+
+    /// Runs the call `f` on the current thread, interrupted by
+    /// [`CallInterrupt::interrupt`].
+    ///
+    /// The calls into the same store nested in `f`, e.g. by a host
+    /// function it calls, are interrupted along with it. A flag runs a single call: `f` isn't
+    /// interrupted anymore once it has returned.
+    pub fn run<R>(self: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        CALL_INTERRUPTS.with(|interrupts| interrupts.borrow_mut().push(self.clone()));
+        // Forget the interrupt once `f` returns, even on panic.
+        struct Returned<'a>(&'a CallInterrupt);
+        impl Drop for Returned<'_> {
+            fn drop(&mut self) {
+                CALL_INTERRUPTS.with(|interrupts| interrupts.borrow_mut().pop());
+                let mut state = self.0.state.lock().unwrap();
+                state.returned = true;
+                if state.lowered {
+                    state.lowered = false;
+                    self.0.deadline.end_interrupt();
+                }
+            }
+        }
+        let _returned = Returned(self);
+        f()
+    }
+
+    /// Interrupts the call: its code traps with
+    /// `TrapCode::EpochDeadlineExceeded` at the next function entry or
+    /// loop header it runs.
+    ///
+    /// Returns `false` if the call has already returned.
+    pub fn interrupt(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.returned {
+            return false;
+        }
+        if !state.lowered {
+            self.interrupted.store(true, SeqCst);
+            state.lowered = true;
+            self.deadline.begin_interrupt();
+        }
+        true
+    }
+
+    /// Whether [`CallInterrupt::interrupt`] interrupted the call.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(SeqCst)
+    }
+}
+
+/// Whether a call running on the current thread is interrupted.
+pub(crate) fn is_current_call_interrupted() -> bool {
+    CALL_INTERRUPTS.with(|interrupts| {
+        interrupts
+            .borrow()
+            .iter()
+            .any(|interrupt| interrupt.is_interrupted())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupts_lower_the_threshold_until_the_call_returns() {
+        let deadline = Arc::new(EpochDeadline::new());
+        deadline.set(5);
+        assert_eq!(deadline.threshold.load(SeqCst), 6);
+
+        let interrupt = Arc::new(CallInterrupt::new(deadline.clone()));
+        interrupt.run(|| {
+            assert!(!is_current_call_interrupted());
+            assert!(interrupt.interrupt());
+            assert!(is_current_call_interrupted());
+            assert_eq!(deadline.threshold.load(SeqCst), 0);
+            // The deadline itself is left as is.
+            assert_eq!(deadline.get(), 5);
+        });
+        assert!(!is_current_call_interrupted());
+        assert_eq!(deadline.threshold.load(SeqCst), 6);
+        assert!(!interrupt.interrupt());
+        assert!(interrupt.is_interrupted());
+    }
+}
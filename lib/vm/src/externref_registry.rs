@@ -0,0 +1,79 @@
+//! The `externref`s held by the elements of the tables of a store,
+//! see [`ExternRefRegistry`].
+
+use crate::VMExternRef;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Mutex;
+
+/// The address of a table, identifying it in an [`ExternRefRegistry`].
+pub type TableAddress = usize;
+
+/// The `externref`s held by the elements of the tables recording them
+/// in this registry, see [`Table::set_externref_registry`].
+///
+/// The tables update the registry as their elements are written and
+/// dropped, before releasing the references they held: a reference is
+/// alive as long as it is in the registry.
+///
+/// [`Table::set_externref_registry`]: crate::Table::set_externref_registry
+#[derive(Debug, Default)]
+pub struct ExternRefRegistry {
+    /// The elements holding each reference.
+    held: Mutex<HashMap<VMExternRef, Vec<(TableAddress, u32)>>>,
+}
+
+/// This is correct because the references are only used by the
+/// tables holding them, and by `for_each` while they can't be
+/// released.
+unsafe impl Send for ExternRefRegistry {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for ExternRefRegistry {}
+
+impl ExternRefRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the elements `indices` of `table` hold
+    /// `extern_ref`.
+    pub(crate) fn hold(&self, extern_ref: VMExternRef, table: TableAddress, indices: Range<u32>) {
+        if extern_ref.is_null() || indices.is_empty() {
+            return;
+        }
+        let mut held = self.held.lock().unwrap();
+        held.entry(extern_ref)
+            .or_default()
+            .extend(indices.map(|index| (table, index)));
+    }
+
+    /// Records that the element `index` of `table` doesn't hold
+    /// `extern_ref` anymore.
+    pub(crate) fn release(&self, extern_ref: VMExternRef, table: TableAddress, index: u32) {
+        if extern_ref.is_null() {
+            return;
+        }
+        let mut held = self.held.lock().unwrap();
+        if let Some(holders) = held.get_mut(&extern_ref) {
+            if let Some(position) = holders.iter().position(|holder| *holder == (table, index)) {
+                holders.swap_remove(position);
+            }
+            if holders.is_empty() {
+                held.remove(&extern_ref);
+            }
+        }
+    }
+
+    /// Calls `f` with each reference held, and the elements holding
+    /// it.
+    ///
+    /// The registry is locked during the calls, so that the references
+    /// can't be released by the tables meanwhile.
+    pub fn for_each(&self, mut f: impl FnMut(VMExternRef, &[(TableAddress, u32)])) {
+        let held = self.held.lock().unwrap();
+        for (extern_ref, holders) in held.iter() {
+            f(*extern_ref, holders);
+        }
+    }
+}
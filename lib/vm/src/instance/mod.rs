@@ -11,13 +11,14 @@ mod allocator;
 mod r#ref;
 
 pub use allocator::InstanceAllocator;
-pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
+pub use r#ref::{InstanceRef, ReentrancyGuard, WeakInstanceRef, WeakOrStrongInstanceRef};
 
+use crate::epoch::{is_current_call_interrupted, EpochDeadline};
 use crate::export::VMExtern;
 use crate::func_data_registry::VMFuncRef;
 use crate::global::Global;
 use crate::imports::Imports;
-use crate::memory::{Memory, MemoryError};
+use crate::memory::{AccessKind, Memory, MemoryAccessTracer, MemoryError};
 use crate::table::{Table, TableElement};
 use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
@@ -26,6 +27,7 @@ use crate::vmcontext::{
     VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport,
     VMTrampoline,
 };
+use crate::waiters;
 use crate::{FunctionBodyPtr, VMOffsets};
 use crate::{VMFunction, VMGlobal, VMMemory, VMTable};
 use loupe::{MemoryUsage, MemoryUsageTracker};
@@ -40,12 +42,14 @@ use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
 use std::slice;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer,
+    IndexType, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryIndex, ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer,
 };
 
 /// The function pointer to call with data and an [`Instance`] pointer to
@@ -107,6 +111,17 @@ pub(crate) struct Instance {
     /// functions from other Wasm modules.
     imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
 
+    /// The number of calls into this instance currently on the
+    /// stack, see [`InstanceRef::enter`].
+    #[loupe(skip)]
+    reentrancy_depth: AtomicUsize,
+
+    /// The tracer of the memory accesses of the code compiled with
+    /// memory access tracing, see [`InstanceHandle::set_memory_access_tracer`].
+    /// The code of the instance may run on several threads at once.
+    #[loupe(skip)]
+    memory_access_tracer: RwLock<Option<Arc<dyn MemoryAccessTracer>>>,
+
     /// Additional context used by compiled WebAssembly code. This
     /// field is last, and represents a dynamically-sized array that
     /// extends beyond the nominal end of the struct (similar to a
@@ -209,6 +224,17 @@ impl fmt::Debug for Instance {
     }
 }
 
+/// The epoch counter observed by instances that haven't been given one.
+static DEFAULT_EPOCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The epoch deadline of instances that haven't been given one. It is
+/// never reached.
+static DEFAULT_EPOCH_DEADLINE: EpochDeadline = EpochDeadline::new();
+
+/// The call depth left of instances that haven't been given one. It
+/// is never exhausted.
+static DEFAULT_CALL_DEPTH_LEFT: AtomicU64 = AtomicU64::new(u64::MAX);
+
 #[allow(clippy::cast_ptr_alignment)]
 impl Instance {
     /// Helper function to access various locations offset from our `*mut
@@ -324,6 +350,17 @@ impl Instance {
         }
     }
 
+    /// Get a reference to a locally defined or imported memory, to
+    /// load its length atomically.
+    fn memory_definition(&self, index: MemoryIndex) -> &VMMemoryDefinition {
+        if let Some(local_index) = self.module.local_memory_index(index) {
+            unsafe { self.memory_ptr(local_index).as_ref() }
+        } else {
+            let import = self.imported_memory(index);
+            unsafe { import.definition.as_ref() }
+        }
+    }
+
     /// Return the indexed `VMMemoryDefinition`.
     fn memory(&self, index: LocalMemoryIndex) -> VMMemoryDefinition {
         unsafe { *self.memory_ptr(index).as_ref() }
@@ -378,6 +415,34 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
     }
 
+    /// Return a pointer to the pointer to the epoch counter.
+    fn epoch_counter_ptr(&self) -> *mut *const AtomicU64 {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_epoch_counter_ptr()) }
+    }
+
+    /// Return a pointer to the pointer to the epoch deadline.
+    fn epoch_deadline_ptr(&self) -> *mut *const EpochDeadline {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_epoch_deadline_ptr()) }
+    }
+
+    /// Whether the code of the instance must trap at an epoch check:
+    /// the epoch counter is past the deadline, or the call running on
+    /// the current thread is interrupted, see [`crate::CallInterrupt`].
+    pub(crate) fn is_epoch_deadline_reached(&self) -> bool {
+        if is_current_call_interrupted() {
+            return true;
+        }
+        unsafe {
+            let counter = (**self.epoch_counter_ptr()).load(Ordering::SeqCst);
+            (**self.epoch_deadline_ptr()).is_reached(counter)
+        }
+    }
+
+    /// Return a pointer to the pointer to the call depth left.
+    fn call_depth_left_ptr(&self) -> *mut *const AtomicU64 {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_call_depth_left_ptr()) }
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     fn vmctx(&self) -> &VMContext {
         &self.vmctx
@@ -465,6 +530,21 @@ impl Instance {
         index
     }
 
+    /// Reports an access of the guest to its memory to the memory
+    /// access tracer of the instance, if any.
+    pub(crate) fn trace_memory_access(
+        &self,
+        memory: MemoryIndex,
+        addr: u64,
+        len: u32,
+        kind: AccessKind,
+    ) {
+        let tracer = self.memory_access_tracer.read().unwrap().clone();
+        if let Some(tracer) = tracer {
+            tracer.on_access(memory, addr, len, kind);
+        }
+    }
+
     /// Grow memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
@@ -759,6 +839,100 @@ impl Instance {
         unsafe { memory.memory_copy(dst, src, len) }
     }
 
+    /// Perform a `memory.copy` from the memory `src_index` to the
+    /// memory `dst_index`, whether they are imported or locally
+    /// defined.
+    pub(crate) fn memory_copy_between(
+        &self,
+        dst_index: MemoryIndex,
+        src_index: MemoryIndex,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let dst_memory = self.get_memory(dst_index);
+        let src_memory = self.get_memory(src_index);
+        // The following memory copy is not synchronized and is not atomic:
+        unsafe { dst_memory.memory_copy_from(&src_memory, dst, src, len) }
+    }
+
+    /// Returns the host address of the `size` bytes at `addr + offset`
+    /// of the memory `memory_index`, for `memory.atomic.wait32`,
+    /// `memory.atomic.wait64` and `memory.atomic.notify`.
+    fn atomic_address(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u32,
+        offset: u32,
+        size: u32,
+    ) -> Result<*mut u8, Trap> {
+        // Another thread may be growing the shared memory, so `base`
+        // is read after the length, loaded atomically.
+        let memory = self.memory_definition(memory_index);
+        let current_length = memory.load_current_length();
+        let addr = u64::from(addr) + u64::from(offset);
+        if addr + u64::from(size) > current_length as u64 {
+            return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        }
+        if addr % u64::from(size) != 0 {
+            return Err(Trap::lib(TrapCode::UnalignedAtomic));
+        }
+        Ok(unsafe { memory.base.add(addr as usize) })
+    }
+
+    /// Perform `memory.atomic.wait32` or `memory.atomic.wait64`,
+    /// waiting while the `size` bytes at `addr + offset` of the shared
+    /// memory `memory_index` are `expected`, for at most `timeout`
+    /// nanoseconds if it isn't negative.
+    ///
+    /// With epoch interruption, the wait traps with
+    /// `TrapCode::EpochDeadlineExceeded` once the epoch deadline is
+    /// reached or the call is interrupted, like an epoch check would.
+    pub(crate) fn memory_atomic_wait(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u32,
+        offset: u32,
+        size: u32,
+        expected: u64,
+        timeout: i64,
+    ) -> Result<u32, Trap> {
+        let host_addr = self.atomic_address(memory_index, addr, offset, size)?;
+        if !self.module.memories[memory_index].shared {
+            return Err(Trap::lib(TrapCode::UnsharedMemoryWait));
+        }
+        // The address is aligned and in bounds.
+        let is_expected = || unsafe {
+            if size == 4 {
+                u64::from((*(host_addr as *const AtomicU32)).load(Ordering::SeqCst)) == expected
+            } else {
+                (*(host_addr as *const AtomicU64)).load(Ordering::SeqCst) == expected
+            }
+        };
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_nanos);
+        if !self.module.epoch_interruption {
+            return Ok(waiters::atomic_wait(host_addr as usize, is_expected, timeout) as u32);
+        }
+        waiters::atomic_wait_interruptible(host_addr as usize, is_expected, timeout, || {
+            self.is_epoch_deadline_reached()
+        })
+        .map(|result| result as u32)
+        .ok_or_else(|| Trap::lib(TrapCode::EpochDeadlineExceeded))
+    }
+
+    /// Perform `memory.atomic.notify`, waking at most `count` of the
+    /// threads waiting at `addr + offset` of the memory `memory_index`.
+    pub(crate) fn memory_atomic_notify(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u32,
+        offset: u32,
+        count: u32,
+    ) -> Result<u32, Trap> {
+        let host_addr = self.atomic_address(memory_index, addr, offset, 4)?;
+        Ok(waiters::atomic_notify(host_addr as usize, count))
+    }
+
     /// Perform the `memory.fill` operation on a locally defined memory.
     ///
     /// # Errors
@@ -936,6 +1110,8 @@ impl InstanceHandle {
                 host_state,
                 funcrefs,
                 imported_function_envs,
+                reentrancy_depth: AtomicUsize::new(0),
+                memory_access_tracer: RwLock::new(None),
                 vmctx: VMContext {},
             };
 
@@ -997,6 +1173,9 @@ impl InstanceHandle {
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        ptr::write(instance.epoch_counter_ptr(), &DEFAULT_EPOCH_COUNTER);
+        ptr::write(instance.epoch_deadline_ptr(), &DEFAULT_EPOCH_DEADLINE);
+        ptr::write(instance.call_depth_left_ptr(), &DEFAULT_CALL_DEPTH_LEFT);
 
         // Perform infallible initialization in this constructor, while fallible
         // initialization is deferred to the `initialize` method.
@@ -1021,16 +1200,50 @@ impl InstanceHandle {
         trap_handler: &(dyn TrapHandler + 'static),
         data_initializers: &[DataInitializer<'_>],
     ) -> Result<(), Trap> {
-        let instance = self.instance().as_ref();
+        self.initialize_tables()?;
+        self.initialize_memories(data_initializers)?;
+        self.invoke_start_function(trap_handler)
+    }
 
-        // Apply the initializers.
-        initialize_tables(instance)?;
-        initialize_memories(instance, data_initializers)?;
+    /// Applies the element segments of the module, the first step of
+    /// [`InstanceHandle::finish_instantiation`].
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation.
+    pub unsafe fn initialize_tables(&self) -> Result<(), Trap> {
+        initialize_tables(self.instance().as_ref())
+    }
 
+    /// Applies the data segments of the module, the second step of
+    /// [`InstanceHandle::finish_instantiation`].
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after
+    /// [`InstanceHandle::initialize_tables`].
+    pub unsafe fn initialize_memories(
+        &self,
+        data_initializers: &[DataInitializer<'_>],
+    ) -> Result<(), Trap> {
+        initialize_memories(self.instance().as_ref(), data_initializers)
+    }
+
+    /// Invokes the start function of the module, if any, the last step
+    /// of [`InstanceHandle::finish_instantiation`].
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after
+    /// [`InstanceHandle::initialize_memories`].
+    pub unsafe fn invoke_start_function(
+        &self,
+        trap_handler: &(dyn TrapHandler + 'static),
+    ) -> Result<(), Trap> {
         // The WebAssembly spec specifies that the start function is
         // invoked automatically at instantiation time.
-        instance.invoke_start_function(trap_handler)?;
-        Ok(())
+        let _guard = self.instance().enter(usize::MAX)?;
+        self.instance().as_ref().invoke_start_function(trap_handler)
     }
 
     /// Return a reference to the vmctx used by compiled wasm code.
@@ -1043,6 +1256,61 @@ impl InstanceHandle {
         self.instance().as_ref().vmctx_ptr()
     }
 
+    /// Set the epoch counter and deadline observed by the epoch
+    /// checks of compiled wasm code.
+    ///
+    /// Until this is called, the instance observes a counter that
+    /// never reaches its deadline.
+    ///
+    /// # Safety
+    ///
+    /// Both the counter and the deadline must outlive the instance.
+    pub unsafe fn set_epoch(&self, counter: *const AtomicU64, deadline: *const EpochDeadline) {
+        let instance = self.instance().as_ref();
+        ptr::write(instance.epoch_counter_ptr(), counter);
+        ptr::write(instance.epoch_deadline_ptr(), deadline);
+    }
+
+    /// Set the number of wasm calls the instance can still make before
+    /// trapping with `TrapCode::CallDepthExceeded`, decremented on
+    /// entry of every function and incremented on return by code
+    /// compiled with the call depth limit enabled.
+    ///
+    /// Until this is called, the instance has no limit.
+    ///
+    /// # Safety
+    ///
+    /// The atomic must outlive the instance.
+    pub unsafe fn set_call_depth_left(&self, call_depth_left: *const AtomicU64) {
+        let instance = self.instance().as_ref();
+        ptr::write(instance.call_depth_left_ptr(), call_depth_left);
+    }
+
+    /// Set the tracer called on the loads and stores of the code of
+    /// the instance compiled with memory access tracing, see
+    /// [`MemoryAccessTracer`].
+    pub fn set_memory_access_tracer(&self, tracer: Option<Arc<dyn MemoryAccessTracer>>) {
+        *self
+            .instance()
+            .as_ref()
+            .memory_access_tracer
+            .write()
+            .unwrap() = tracer;
+    }
+
+    /// Return the number of calls into this instance currently on the
+    /// stack, across all threads.
+    ///
+    /// It is `0` when the instance is idle, `1` while one of its
+    /// functions runs, and grows by one each time a host function
+    /// called by the instance calls back into it.
+    pub fn reentrancy_depth(&self) -> usize {
+        self.instance()
+            .as_ref()
+            .reentrancy_depth
+            .load(Ordering::SeqCst)
+    }
+
     /// Return a reference to the `VMOffsets` to get offsets in the
     /// `Self::vmctx_ptr` region. Be careful when doing pointer
     /// arithmetic!
@@ -1271,14 +1539,19 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
     let mut start = init.location.offset;
 
     if let Some(base) = init.location.base {
-        let val = unsafe {
+        let global = unsafe {
             if let Some(def_index) = instance.module.local_global_index(base) {
-                instance.global(def_index).to_u32()
+                instance.global(def_index)
             } else {
-                instance.imported_global(base).definition.as_ref().to_u32()
+                instance.imported_global(base).definition.as_ref().clone()
             }
         };
-        start += usize::try_from(val).unwrap();
+        // The bases of 64-bit memories are `i64` globals.
+        let val = match instance.module.memories[init.location.memory_index].index_type {
+            IndexType::I32 => u64::from(global.to_u32()),
+            IndexType::I64 => global.to_u64(),
+        };
+        start = start.saturating_add(usize::try_from(val).unwrap_or(usize::MAX));
     }
 
     start
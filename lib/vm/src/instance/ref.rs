@@ -1,10 +1,13 @@
 use super::Instance;
+use crate::trap::{Trap, TrapCode};
 use loupe::{MemoryUsage, MemoryUsageTracker};
 use std::alloc::Layout;
 use std::convert::TryFrom;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Weak};
+use wasmer_types::ModuleInfo;
 
 /// Dynamic instance allocation.
 ///
@@ -155,6 +158,52 @@ impl InstanceRef {
         let ptr: *mut InstanceInner = Arc::as_ptr(&self.0) as *mut _;
         (&mut *ptr).as_mut()
     }
+
+    /// Get the `ModuleInfo` of the instance.
+    pub fn module_ref(&self) -> &ModuleInfo {
+        self.as_ref().module_ref()
+    }
+
+    /// Record a call into the instance for as long as the returned
+    /// guard is alive, see [`InstanceHandle::reentrancy_depth`].
+    ///
+    /// Fails with [`TrapCode::ReentrancyLimitExceeded`] if that would
+    /// make more than `max_depth` calls into the instance active at
+    /// once.
+    ///
+    /// [`InstanceHandle::reentrancy_depth`]: super::InstanceHandle::reentrancy_depth
+    pub fn enter(&self, max_depth: usize) -> Result<ReentrancyGuard, Trap> {
+        let depth = self
+            .as_ref()
+            .reentrancy_depth
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+        let guard = ReentrancyGuard {
+            instance: self.clone(),
+        };
+
+        if depth > max_depth {
+            return Err(Trap::lib(TrapCode::ReentrancyLimitExceeded));
+        }
+
+        Ok(guard)
+    }
+}
+
+/// A call into an instance, created by [`InstanceRef::enter`]. The
+/// call ends when the guard is dropped.
+#[derive(Debug)]
+pub struct ReentrancyGuard {
+    instance: InstanceRef,
+}
+
+impl Drop for ReentrancyGuard {
+    fn drop(&mut self) {
+        self.instance
+            .as_ref()
+            .reentrancy_depth
+            .fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 /// A weak instance ref. This type does not keep the underlying `Instance` alive
@@ -21,7 +21,9 @@
     )
 )]
 
+mod epoch;
 mod export;
+mod externref_registry;
 mod func_data_registry;
 mod global;
 mod imports;
@@ -34,18 +36,24 @@ mod table;
 mod trap;
 mod vmcontext;
 mod vmoffsets;
+mod waiters;
 
 pub mod libcalls;
 
+pub use crate::epoch::{CallInterrupt, EpochDeadline};
 pub use crate::export::*;
+pub use crate::externref_registry::{ExternRefRegistry, TableAddress};
 pub use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
-    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
-    WeakOrStrongInstanceRef,
+    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle, InstanceRef,
+    ReentrancyGuard, WeakOrStrongInstanceRef,
+};
+pub use crate::memory::{
+    AccessKind, GrowGuard, LinearMemory, Memory, MemoryAccessTracer, MemoryError,
+    MemoryGrowCallback, MemoryStyle,
 };
-pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
 pub use crate::mmap::Mmap;
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
@@ -58,6 +66,7 @@ pub use crate::vmcontext::{
     VMTableImport, VMTrampoline,
 };
 pub use crate::vmoffsets::{TargetSharedSignatureIndex, VMOffsets};
+pub use crate::waiters::{atomic_notify, atomic_wait, atomic_wait_interruptible, WaitResult};
 use loupe::MemoryUsage;
 pub use wasmer_types::VMExternRef;
 #[deprecated(
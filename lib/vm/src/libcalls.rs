@@ -38,6 +38,7 @@
 #![allow(missing_docs)] // For some reason lint fails saying that `LibCall` is not documented, when it actually is
 
 use crate::func_data_registry::VMFuncRef;
+use crate::memory::AccessKind;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
@@ -588,6 +589,114 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_copy(
     }
 }
 
+/// Implementation of `memory.copy` between two different memories,
+/// imported or locally defined.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_copy_between(
+    vmctx: *mut VMContext,
+    dst_memory_index: u32,
+    src_memory_index: u32,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let result = {
+        let dst_memory_index = MemoryIndex::from_u32(dst_memory_index);
+        let src_memory_index = MemoryIndex::from_u32(src_memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_copy_between(dst_memory_index, src_memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.atomic.wait32`, for locally defined and
+/// imported memories.
+///
+/// `addr` is the dynamic address of the wait and `offset` the static
+/// offset of the instruction. Returns `0` when woken, `1` when the
+/// value isn't `expected`, and `2` when `timeout`, in nanoseconds,
+/// expired.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_wait32(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u32,
+    offset: u32,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let result = on_host_stack(|| {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, offset, 4, expected.into(), timeout)
+    });
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.wait64`, for locally defined and
+/// imported memories, see [`wasmer_vm_memory32_atomic_wait32`].
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_wait64(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u32,
+    offset: u32,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let result = on_host_stack(|| {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, offset, 8, expected, timeout)
+    });
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.notify`, for locally defined and
+/// imported memories, returning how many waiters were woken.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory32_atomic_notify(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u32,
+    offset: u32,
+    count: u32,
+) -> u32 {
+    let result = on_host_stack(|| {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_notify(memory_index, addr, offset, count)
+    });
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
 /// Implementation of `memory.fill` for locally defined memories.
 ///
 /// # Safety
@@ -673,6 +782,63 @@ pub unsafe extern "C" fn wasmer_vm_data_drop(vmctx: *mut VMContext, data_index:
     })
 }
 
+/// Reports a load or a store of the guest to the memory access tracer
+/// of the instance, see [`MemoryAccessTracer`].
+///
+/// `memory_index` is the memory accessed, `addr` the dynamic address of
+/// the access and `offset` the static offset of the instruction; `kind`
+/// encodes an [`AccessKind`].
+///
+/// [`MemoryAccessTracer`]: crate::MemoryAccessTracer
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory_access_trace(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    offset: u32,
+    len: u32,
+    kind: u32,
+) {
+    on_host_stack(|| {
+        let instance = (&*vmctx).instance();
+        if let Some(kind) = AccessKind::from_u32(kind) {
+            instance.trace_memory_access(
+                MemoryIndex::from_u32(memory_index),
+                addr.saturating_add(u64::from(offset)),
+                len,
+                kind,
+            );
+        }
+    })
+}
+
+/// Called by the epoch checks of the code compiled with epoch
+/// interruption once the epoch counter reaches the threshold of the
+/// [`EpochDeadline`] of the instance: traps with
+/// `TrapCode::EpochDeadlineExceeded` if the deadline is reached or the
+/// call is interrupted, see [`CallInterrupt`], and returns otherwise.
+///
+/// [`EpochDeadline`]: crate::EpochDeadline
+/// [`CallInterrupt`]: crate::CallInterrupt
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_epoch_deadline_reached(vmctx: *mut VMContext) {
+    let reached = on_host_stack(|| {
+        let instance = (&*vmctx).instance();
+        instance.is_epoch_deadline_reached()
+    });
+    if reached {
+        raise_lib_trap(Trap::lib(TrapCode::EpochDeadlineExceeded))
+    }
+}
+
 /// Implementation for raising a trap
 ///
 /// # Safety
@@ -17,9 +17,9 @@ use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
-use wasmer_types::{Bytes, MemoryType, Pages};
+use wasmer_types::{Bytes, MemoryIndex, MemoryType, Pages};
 
 /// Error type describing things that can go wrong when operating on Wasm Memories.
 #[derive(Error, Debug, Clone, PartialEq, Hash)]
@@ -58,6 +58,45 @@ pub enum MemoryError {
         /// The number of pages requested as the maximum amount of memory.
         max_allowed: Pages,
     },
+    /// The [`GrowGuard`] of the memory denied the operation.
+    #[error("The memory could not grow: denied by the grow guard, current size {} pages, requested increase: {} pages", current.0, attempted_delta.0)]
+    GrowDenied {
+        /// The current size in pages.
+        current: Pages,
+        /// The attempted amount to grow by in pages.
+        attempted_delta: Pages,
+    },
+    /// The memory limit of the store denied the operation, because the
+    /// memories of the store would use more than `limit` bytes.
+    #[error("The memory could not grow: the memory limit of the store is {} bytes, current size {} pages, requested increase: {} pages", limit, current.0, attempted_delta.0)]
+    MemoryLimitExceeded {
+        /// The current size in pages.
+        current: Pages,
+        /// The attempted amount to grow by in pages.
+        attempted_delta: Pages,
+        /// The limit in bytes.
+        limit: u64,
+    },
+    /// The range of bytes is out of bounds of a memory, or of the data
+    /// given to `Memory::init_from_data_segment`.
+    #[error("out of bounds access of {len} bytes at offset {offset}, out of {size} bytes")]
+    AccessOutOfBounds {
+        /// The start of the range.
+        offset: u32,
+        /// The number of bytes of the range.
+        len: u32,
+        /// The number of bytes of the accessed memory or data.
+        size: u64,
+    },
+    /// The range of bytes viewed as values of a type isn't aligned
+    /// for the type.
+    #[error("misaligned access at offset {offset}, expected an alignment of {align} bytes")]
+    MisalignedAccess {
+        /// The start of the range.
+        offset: u32,
+        /// The alignment of the type, in bytes.
+        align: usize,
+    },
     /// A user defined error value, used for error cases not listed above.
     #[error("A user-defined error occurred: {0}")]
     Generic(String),
@@ -120,6 +159,109 @@ pub trait Memory: fmt::Debug + Send + Sync + MemoryUsage {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Register a callback to be called with the previous and the new
+    /// size of the memory every time it grows.
+    ///
+    /// By default, memories don't support callbacks and return an
+    /// error.
+    fn on_grow(&self, _callback: MemoryGrowCallback) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "the memory does not support grow callbacks".to_string(),
+        ))
+    }
+
+    /// Install a guard consulted before the memory grows, replacing
+    /// the previous one.
+    ///
+    /// By default, memories don't support guards and return an error.
+    fn set_grow_guard(&self, _guard: Arc<dyn GrowGuard>) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "the memory does not support grow guards".to_string(),
+        ))
+    }
+}
+
+/// Decides whether a memory may grow, see [`Memory::set_grow_guard`].
+///
+/// A guard is consulted before the memory grows by a non-zero amount,
+/// whether the growth comes from the host or from a `memory.grow`
+/// instruction, and on top of the maximum of the memory.
+pub trait GrowGuard: Send + Sync {
+    /// Called before a memory of `current` pages grows by `delta`
+    /// pages. Returning `Err` denies the growth: `memory.grow` returns
+    /// `-1` to the guest, and [`Memory::grow`] fails with
+    /// [`MemoryError::GrowDenied`].
+    ///
+    /// The memory is locked during the call, so the guard must not
+    /// access it.
+    #[allow(clippy::result_unit_err)]
+    fn check_grow(&self, current: Pages, delta: Pages) -> Result<(), ()>;
+}
+
+/// The kind of a memory access reported to a [`MemoryAccessTracer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum AccessKind {
+    /// The memory is read, e.g. by `i32.load`.
+    Load = 0,
+    /// The memory is written, e.g. by `i32.store`.
+    Store = 1,
+}
+
+impl AccessKind {
+    /// Returns the kind encoded as `kind`, as passed by compiled code.
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Load),
+            1 => Some(Self::Store),
+            _ => None,
+        }
+    }
+}
+
+/// Observes the loads and stores of the guest, to debug memory
+/// corruptions.
+///
+/// Only the code compiled with memory access tracing enabled, which
+/// only Singlepass supports, reports its accesses. Every load and
+/// store then calls into the host, making the code several times
+/// slower: this is a debugging aid, not a security boundary, the
+/// tracer can't deny an access.
+pub trait MemoryAccessTracer: Send + Sync {
+    /// Called before the guest accesses the `len` bytes at `addr` of
+    /// its memory `memory`, the address being the offset in the
+    /// memory, static offset of the instruction included. The access is
+    /// reported even if it's out of bounds and traps.
+    fn on_access(&self, memory: MemoryIndex, addr: u64, len: u32, kind: AccessKind);
+}
+
+/// A callback called with the previous and the new size of a memory
+/// after it grows, see [`Memory::on_grow`].
+pub type MemoryGrowCallback = Box<dyn Fn(Pages, Pages) + Send + Sync>;
+
+/// The callbacks registered with [`Memory::on_grow`].
+#[derive(Default)]
+struct GrowCallbacks(RwLock<Vec<MemoryGrowCallback>>);
+
+impl fmt::Debug for GrowCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrowCallbacks")
+            .field("len", &self.0.read().unwrap().len())
+            .finish()
+    }
+}
+
+/// The guard installed with [`Memory::set_grow_guard`].
+#[derive(Default)]
+struct InstalledGrowGuard(RwLock<Option<Arc<dyn GrowGuard>>>);
+
+impl fmt::Debug for InstalledGrowGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstalledGrowGuard")
+            .field("installed", &self.0.read().unwrap().is_some())
+            .finish()
+    }
 }
 
 /// A linear memory instance.
@@ -147,6 +289,14 @@ pub struct LinearMemory {
     // Records whether we're using a bounds-checking strategy which requires
     // handlers to catch trapping accesses.
     pub(crate) needs_signal_handlers: bool,
+
+    /// Called every time the memory grows.
+    #[loupe(skip)]
+    grow_callbacks: GrowCallbacks,
+
+    /// Consulted before the memory grows.
+    #[loupe(skip)]
+    grow_guard: InstalledGrowGuard,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -212,18 +362,19 @@ impl LinearMemory {
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
     ) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
+        // 32-bit memories cannot have more than `65536` pages.
+        let max_pages = memory.index_type.max_pages();
+        if memory.minimum > max_pages || memory.minimum.checked_bytes().is_none() {
             return Err(MemoryError::MinimumMemoryTooLarge {
                 min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
+                max_allowed: max_pages,
             });
         }
-        // `maximum` cannot be set to more than `65536` pages.
         if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
+            if max > max_pages {
                 return Err(MemoryError::MaximumMemoryTooLarge {
                     max_requested: max,
-                    max_allowed: Pages::max_value(),
+                    max_allowed: max_pages,
                 });
             }
             if max < memory.minimum {
@@ -290,6 +441,8 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
+            grow_callbacks: GrowCallbacks::default(),
+            grow_guard: InstalledGrowGuard::default(),
         })
     }
 
@@ -338,6 +491,47 @@ impl Memory for LinearMemory {
     /// Returns `None` if memory can't be grown by the specified amount
     /// of wasm pages.
     fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let prev_pages = self.grow_mmap(delta)?;
+
+        // The memory isn't locked anymore: the callbacks can inspect it.
+        if delta.0 > 0 {
+            for callback in self.grow_callbacks.0.read().unwrap().iter() {
+                callback(prev_pages, prev_pages + delta);
+            }
+        }
+
+        Ok(prev_pages)
+    }
+
+    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        let _mmap_guard = self.mmap.lock().unwrap();
+        unsafe { self.get_vm_memory_definition() }
+    }
+
+    /// Register a callback to be called with the previous and the new
+    /// size of the memory every time it grows.
+    ///
+    /// Callbacks are called synchronously by the thread growing the
+    /// memory, in the order they were registered. They must not
+    /// register other callbacks on the same memory.
+    fn on_grow(&self, callback: MemoryGrowCallback) -> Result<(), MemoryError> {
+        self.grow_callbacks.0.write().unwrap().push(callback);
+        Ok(())
+    }
+
+    /// Install a guard consulted before the memory grows, replacing
+    /// the previous one.
+    fn set_grow_guard(&self, guard: Arc<dyn GrowGuard>) -> Result<(), MemoryError> {
+        *self.grow_guard.0.write().unwrap() = Some(guard);
+        Ok(())
+    }
+}
+
+impl LinearMemory {
+    /// Grow the underlying allocation by the specified amount of wasm
+    /// pages, and return the previous size.
+    fn grow_mmap(&self, delta: Pages) -> Result<Pages, MemoryError> {
         let mut mmap_guard = self.mmap.lock().unwrap();
         let mmap = mmap_guard.borrow_mut();
         // Optimization of memory.grow 0 calls.
@@ -345,13 +539,18 @@ impl Memory for LinearMemory {
             return Ok(mmap.size);
         }
 
-        let new_pages = mmap
-            .size
-            .checked_add(delta)
-            .ok_or(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            })?;
+        if let Some(guard) = self.grow_guard.0.read().unwrap().as_ref() {
+            guard
+                .check_grow(mmap.size, delta)
+                .map_err(|()| MemoryError::GrowDenied {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                })?;
+        }
+
+        // An overflow saturates to `u32::MAX` pages, past the limit of
+        // either index type, which is checked below.
+        let new_pages = Pages(mmap.size.0.saturating_add(delta.0));
         let prev_pages = mmap.size;
 
         if let Some(maximum) = self.maximum {
@@ -364,19 +563,20 @@ impl Memory for LinearMemory {
         }
 
         // Wasm linear memories are never allowed to grow beyond what is
-        // indexable. If the memory has no maximum, enforce the greatest
-        // limit here.
-        if new_pages >= Pages::max_value() {
-            // Linear memory size would exceed the index range.
-            return Err(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            });
-        }
-
+        // indexable, nor beyond the address space of the host. If the
+        // memory has no maximum, enforce the greatest limit here.
+        let new_bytes = match new_pages.checked_bytes() {
+            Some(new_bytes) if new_pages < self.memory.index_type.max_pages() => new_bytes.0,
+            _ => {
+                // Linear memory size would exceed the index range.
+                return Err(MemoryError::CouldNotGrow {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                });
+            }
+        };
         let delta_bytes = delta.bytes().0;
         let prev_bytes = prev_pages.bytes().0;
-        let new_bytes = new_pages.bytes().0;
 
         if new_bytes > mmap.alloc.len() - self.offset_guard_size {
             // If the new size is within the declared maximum, but needs more memory than we
@@ -410,16 +610,12 @@ impl Memory for LinearMemory {
         unsafe {
             let mut md_ptr = self.get_vm_memory_definition();
             let md = md_ptr.as_mut();
-            md.current_length = new_pages.bytes().0.try_into().unwrap();
+            // The base is set first so that a length read atomically
+            // comes with a base valid for it.
             md.base = mmap.alloc.as_mut_ptr() as _;
+            md.store_current_length(new_pages.bytes().0.try_into().unwrap());
         }
 
         Ok(prev_pages)
     }
-
-    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
-    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
-        let _mmap_guard = self.mmap.lock().unwrap();
-        unsafe { self.get_vm_memory_definition() }
-    }
 }
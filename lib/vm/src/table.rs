@@ -5,6 +5,7 @@
 //!
 //! `Table` is to WebAssembly tables what `LinearMemory` is to WebAssembly linear memories.
 
+use crate::externref_registry::{ExternRefRegistry, TableAddress};
 use crate::func_data_registry::VMFuncRef;
 use crate::trap::{Trap, TrapCode};
 use crate::vmcontext::VMTableDefinition;
@@ -18,7 +19,7 @@ use std::cell::UnsafeCell;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use wasmer_types::{ExternRef, TableType, Type as ValType};
 
 /// Implementation styles for WebAssembly tables.
@@ -64,6 +65,13 @@ pub trait Table: fmt::Debug + Send + Sync + MemoryUsage {
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
 
+    /// Records the `externref`s held by the elements of this table in
+    /// `registry`, from now on, instead of the registry it recorded
+    /// them in before, if any.
+    ///
+    /// By default, the table doesn't record its elements.
+    fn set_externref_registry(&self, _registry: &Arc<ExternRefRegistry>) {}
+
     /// Copy `len` elements from `src_table[src_index..]` into `dst_table[dst_index..]`.
     ///
     /// # Errors
@@ -185,6 +193,10 @@ pub struct LinearTable {
     /// Our chosen implementation style.
     style: TableStyle,
     vm_table_definition: VMTableDefinitionOwnership,
+    /// The registry the `externref`s held by the elements are
+    /// recorded in, if any, locked after `vec`.
+    #[loupe(skip)]
+    externref_registry: Mutex<Option<Arc<ExternRefRegistry>>>,
 }
 
 /// A type to help manage who is responsible for the backing table of the
@@ -205,6 +217,29 @@ unsafe impl Send for LinearTable {}
 /// This is correct because all internal mutability is protected by a mutex.
 unsafe impl Sync for LinearTable {}
 
+impl Drop for LinearTable {
+    /// Releases the references held by the elements of a table of
+    /// `externref`s.
+    fn drop(&mut self) {
+        if self.table.ty == ValType::ExternRef {
+            let address = self.address();
+            let registry = self
+                .externref_registry
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner());
+            let vec = self.vec.get_mut().unwrap_or_else(|e| e.into_inner());
+            for (index, element) in vec.iter_mut().enumerate() {
+                unsafe {
+                    if let Some(registry) = registry {
+                        registry.release(element.extern_ref, address, index as u32);
+                    }
+                    element.extern_ref.ref_drop();
+                }
+            }
+        }
+    }
+}
+
 impl LinearTable {
     /// Create a new linear table instance with specified minimum and maximum number of elements.
     ///
@@ -262,6 +297,7 @@ impl LinearTable {
                 maximum: table.maximum,
                 table: *table,
                 style: style.clone(),
+                externref_registry: Mutex::new(None),
                 vm_table_definition: if let Some(table_loc) = vm_table_location {
                     {
                         let mut ptr = table_loc;
@@ -282,6 +318,11 @@ impl LinearTable {
         }
     }
 
+    /// The address of this table in the [`ExternRefRegistry`].
+    fn address(&self) -> TableAddress {
+        self as *const Self as TableAddress
+    }
+
     /// Get the `VMTableDefinition`.
     ///
     /// # Safety
@@ -344,6 +385,9 @@ impl Table for LinearTable {
                 (new_len as usize)
                     .checked_sub(size as usize + 1)
                     .map(|val| extern_ref.ref_inc_by(val));
+                if let Some(registry) = &*self.externref_registry.lock().unwrap() {
+                    registry.hold(extern_ref, self.address(), size..new_len);
+                }
                 RawTableElement { extern_ref }
             }
             TableElement::FuncRef(func_ref) => RawTableElement { func_ref },
@@ -389,8 +433,13 @@ impl Table for LinearTable {
                 match (self.table.ty, reference) {
                     (ValType::ExternRef, TableElement::ExternRef(extern_ref)) => {
                         let extern_ref = extern_ref.into();
+                        let registry = self.externref_registry.lock().unwrap();
                         unsafe {
                             let elem = &mut *slot;
+                            if let Some(registry) = &*registry {
+                                registry.release(elem.extern_ref, self.address(), index);
+                                registry.hold(extern_ref, self.address(), index..index + 1);
+                            }
                             elem.extern_ref.ref_drop();
                             elem.extern_ref = extern_ref
                         }
@@ -420,4 +469,29 @@ impl Table for LinearTable {
         let _vec_guard = self.vec.lock().unwrap();
         unsafe { self.get_vm_table_definition() }
     }
+
+    /// Records the `externref`s held by the elements of this table in
+    /// `registry`, from now on.
+    fn set_externref_registry(&self, registry: &Arc<ExternRefRegistry>) {
+        if self.table.ty != ValType::ExternRef {
+            return;
+        }
+        let vec = self.vec.lock().unwrap();
+        let mut current = self.externref_registry.lock().unwrap();
+        if let Some(current) = &*current {
+            if Arc::ptr_eq(current, registry) {
+                return;
+            }
+        }
+        let address = self.address();
+        for (index, element) in vec.iter().enumerate() {
+            let index = index as u32;
+            let extern_ref = unsafe { element.extern_ref };
+            if let Some(current) = &*current {
+                current.release(extern_ref, address, index);
+            }
+            registry.hold(extern_ref, address, index..index + 1);
+        }
+        *current = Some(registry.clone());
+    }
 }
@@ -63,6 +63,18 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 11,
+
+    /// The epoch counter went past the deadline of the store.
+    EpochDeadlineExceeded = 12,
+
+    /// Too many calls into the same instance were active at once.
+    ReentrancyLimitExceeded = 13,
+
+    /// A call would go past the maximum call depth of the store.
+    CallDepthExceeded = 14,
+
+    /// A thread waited on a memory which isn't shared.
+    UnsharedMemoryWait = 15,
 }
 
 impl TrapCode {
@@ -81,6 +93,10 @@ impl TrapCode {
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::EpochDeadlineExceeded => "epoch deadline exceeded",
+            Self::ReentrancyLimitExceeded => "re-entrancy limit exceeded",
+            Self::CallDepthExceeded => "call depth exceeded",
+            Self::UnsharedMemoryWait => "expected shared memory",
         }
     }
 }
@@ -100,6 +116,10 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
+            Self::EpochDeadlineExceeded => "epoch_deadline",
+            Self::ReentrancyLimitExceeded => "reentrancy_limit",
+            Self::CallDepthExceeded => "call_depth",
+            Self::UnsharedMemoryWait => "unshared_wait",
         };
         f.write_str(identifier)
     }
@@ -122,6 +142,10 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(TrapCode::BadConversionToInteger),
             "unreachable" => Ok(TrapCode::UnreachableCodeReached),
             "unalign_atom" => Ok(TrapCode::UnalignedAtomic),
+            "epoch_deadline" => Ok(TrapCode::EpochDeadlineExceeded),
+            "reentrancy_limit" => Ok(TrapCode::ReentrancyLimitExceeded),
+            "call_depth" => Ok(TrapCode::CallDepthExceeded),
+            "unshared_wait" => Ok(TrapCode::UnsharedMemoryWait),
             _ => Err(()),
         }
     }
@@ -132,7 +156,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 12] = [
+    const CODES: [TrapCode; 16] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -145,6 +169,10 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::EpochDeadlineExceeded,
+        TrapCode::ReentrancyLimitExceeded,
+        TrapCode::CallDepthExceeded,
+        TrapCode::UnsharedMemoryWait,
     ];
 
     #[test]
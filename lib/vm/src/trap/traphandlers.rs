@@ -854,9 +854,14 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
         Ok(f())
     });
 
-    // Ensure that YIELDER is reset on exit even if the coroutine panics,
+    // Ensure that YIELDER is restored on exit even if the coroutine
+    // panics. Calls into Wasm can be nested (Wasm calling a host
+    // function calling back into Wasm), so restore the yielder of the
+    // enclosing call rather than clearing it: it is still needed to
+    // unwind that call if it traps later on.
+    let prev_yielder = YIELDER.with(|cell| cell.get());
     defer! {
-        YIELDER.with(|cell| cell.set(None));
+        YIELDER.with(|cell| cell.set(prev_yielder));
     }
 
     // Set up metadata for the trap handler for the duration of the coroutine
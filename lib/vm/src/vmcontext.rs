@@ -17,6 +17,7 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::u32;
 
@@ -370,6 +371,24 @@ impl MemoryUsage for VMMemoryDefinition {
 }
 
 impl VMMemoryDefinition {
+    /// Loads `current_length` atomically, with the `Acquire` ordering
+    /// matching the `Release` store of
+    /// [`VMMemoryDefinition::store_current_length`].
+    ///
+    /// A length read while another thread grows the memory is never
+    /// torn, and `base` is at least as recent as the length read.
+    pub fn load_current_length(&self) -> usize {
+        unsafe { &*(&self.current_length as *const usize as *const AtomicUsize) }
+            .load(Ordering::Acquire)
+    }
+
+    /// Stores `current_length` atomically, with the `Release`
+    /// ordering, once `base` is up to date.
+    pub fn store_current_length(&mut self, length: usize) {
+        unsafe { &*(&mut self.current_length as *mut usize as *const AtomicUsize) }
+            .store(length, Ordering::Release)
+    }
+
     /// Do an unsynchronized, non-atomic `memory.copy` for the memory.
     ///
     /// # Errors
@@ -404,6 +423,42 @@ impl VMMemoryDefinition {
         Ok(())
     }
 
+    /// Do an unsynchronized, non-atomic `memory.copy` from the memory
+    /// `src_memory` to this memory, for the multi-memory proposal.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source range is out of the bounds
+    /// of `src_memory`, or the destination range out of the bounds of
+    /// this memory.
+    ///
+    /// # Safety
+    /// The memory is not copied atomically and is not synchronized: it's the
+    /// caller's responsibility to synchronize.
+    pub(crate) unsafe fn memory_copy_from(
+        &self,
+        src_memory: &Self,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        if src.checked_add(len).map_or(true, |n| {
+            usize::try_from(n).unwrap() > src_memory.current_length
+        }) || dst
+            .checked_add(len)
+            .map_or(true, |m| usize::try_from(m).unwrap() > self.current_length)
+        {
+            return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        }
+
+        let dst = self.base.add(usize::try_from(dst).unwrap());
+        let src = src_memory.base.add(usize::try_from(src).unwrap());
+        // The memories may be the same one, imported twice.
+        ptr::copy(src, dst, len as usize);
+
+        Ok(())
+    }
+
     /// Perform the `memory.fill` operation for the memory in an unsynchronized,
     /// non-atomic way.
     ///
@@ -1002,9 +1057,39 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_externref_dec_index() -> Self {
         Self(25)
     }
+    /// Returns an index for a function to report a memory access to the
+    /// memory access tracer.
+    pub const fn get_memory_access_trace_index() -> Self {
+        Self(26)
+    }
+    /// Returns an index for wasm's `memory.copy` between two different
+    /// memories.
+    pub const fn get_memory_copy_between_index() -> Self {
+        Self(27)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait32`.
+    pub const fn get_memory_atomic_wait32_index() -> Self {
+        Self(28)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait64`.
+    pub const fn get_memory_atomic_wait64_index() -> Self {
+        Self(29)
+    }
+    /// Returns an index for wasm's `memory.atomic.notify`.
+    pub const fn get_memory_atomic_notify_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for a function to check whether the epoch
+    /// deadline is reached, once the epoch counter reaches the
+    /// threshold of the [`EpochDeadline`].
+    ///
+    /// [`EpochDeadline`]: crate::EpochDeadline
+    pub const fn get_epoch_deadline_reached_index() -> Self {
+        Self(31)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        26
+        32
     }
 
     /// Return the index as an u32 number.
@@ -1086,6 +1171,18 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_externref_inc as usize;
         ptrs[VMBuiltinFunctionIndex::get_externref_dec_index().index() as usize] =
             wasmer_vm_externref_dec as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_access_trace_index().index() as usize] =
+            wasmer_vm_memory_access_trace as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_copy_between_index().index() as usize] =
+            wasmer_vm_memory32_copy_between as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait32_index().index() as usize] =
+            wasmer_vm_memory32_atomic_wait32 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait64_index().index() as usize] =
+            wasmer_vm_memory32_atomic_wait64 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_notify_index().index() as usize] =
+            wasmer_vm_memory32_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_epoch_deadline_reached_index().index() as usize] =
+            wasmer_vm_epoch_deadline_reached as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
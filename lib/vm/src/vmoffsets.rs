@@ -453,10 +453,8 @@ impl VMOffsets {
             .unwrap()
     }
 
-    /// Return the size of the [`VMContext`] allocation.
-    ///
-    /// [`VMContext`]: crate::vmcontext::VMContext
-    pub fn size_of_vmctx(&self) -> u32 {
+    /// The offset of the pointer to the epoch counter.
+    pub fn vmctx_epoch_counter_ptr(&self) -> u32 {
         self.vmctx_builtin_functions_begin()
             .checked_add(
                 VMBuiltinFunctionIndex::builtin_functions_total_number()
@@ -466,6 +464,29 @@ impl VMOffsets {
             .unwrap()
     }
 
+    /// The offset of the pointer to the epoch deadline.
+    pub fn vmctx_epoch_deadline_ptr(&self) -> u32 {
+        self.vmctx_epoch_counter_ptr()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap()
+    }
+
+    /// The offset of the pointer to the call depth left.
+    pub fn vmctx_call_depth_left_ptr(&self) -> u32 {
+        self.vmctx_epoch_deadline_ptr()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap()
+    }
+
+    /// Return the size of the [`VMContext`] allocation.
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub fn size_of_vmctx(&self) -> u32 {
+        self.vmctx_call_depth_left_ptr()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap()
+    }
+
     /// Return the offset to [`VMSharedSignatureIndex`] index `index`.
     ///
     /// [`VMSharedSignatureIndex`]: crate::vmcontext::VMSharedSignatureIndex
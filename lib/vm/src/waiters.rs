@@ -0,0 +1,175 @@
+//! The threads waiting with `memory.atomic.wait32` and
+//! `memory.atomic.wait64`, or their host counterparts, until woken by
+//! `memory.atomic.notify`.
+//!
+//! The waiters are keyed by the host address they wait on, so that
+//! the guests and the host sharing a memory wake each other. A guest
+//! waiting can also be interrupted, see
+//! [`atomic_wait_interruptible`].
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The outcome of [`atomic_wait`], i.e. the value returned by
+/// `memory.atomic.wait32` and `memory.atomic.wait64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum WaitResult {
+    /// The waiter was woken by [`atomic_notify`] (`0`).
+    Woken = 0,
+    /// The loaded value didn't match the expected one, so the waiter
+    /// didn't wait (`1`).
+    NotEqual = 1,
+    /// The timeout expired before the waiter was woken (`2`).
+    TimedOut = 2,
+}
+
+/// A thread waiting on an address.
+#[derive(Default)]
+struct Waiter {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+/// How often an interruptible waiter checks whether it's interrupted.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+lazy_static::lazy_static! {
+    /// The threads waiting on each host address, in the order they
+    /// started waiting.
+    static ref WAIT_QUEUES: Mutex<HashMap<usize, VecDeque<Arc<Waiter>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Wait on the host address `addr` until another thread calls
+/// [`atomic_notify`] on it, if `is_expected` returns `true`.
+///
+/// `is_expected` is called with the wait queues locked, so that a
+/// notification following a store can't be missed. Without a
+/// `timeout`, waits forever.
+pub fn atomic_wait(
+    addr: usize,
+    is_expected: impl FnOnce() -> bool,
+    timeout: Option<Duration>,
+) -> WaitResult {
+    wait(addr, is_expected, timeout, None).unwrap()
+}
+
+/// Wait like [`atomic_wait`], but stop waiting and return `None` once
+/// `is_interrupted` returns `true`.
+///
+/// `is_interrupted` is called on the waiting thread every few
+/// milliseconds while it waits, so that the waiter is interrupted
+/// along with its call.
+pub fn atomic_wait_interruptible(
+    addr: usize,
+    is_expected: impl FnOnce() -> bool,
+    timeout: Option<Duration>,
+    is_interrupted: impl Fn() -> bool,
+) -> Option<WaitResult> {
+    wait(addr, is_expected, timeout, Some(&is_interrupted))
+}
+
+fn wait(
+    addr: usize,
+    is_expected: impl FnOnce() -> bool,
+    timeout: Option<Duration>,
+    is_interrupted: Option<&dyn Fn() -> bool>,
+) -> Option<WaitResult> {
+    let waiter = {
+        let mut queues = WAIT_QUEUES.lock().unwrap();
+        if !is_expected() {
+            return Some(WaitResult::NotEqual);
+        }
+        let waiter = Arc::new(Waiter::default());
+        queues.entry(addr).or_default().push_back(waiter.clone());
+        waiter
+    };
+
+    // A deadline too far away to be represented is no deadline.
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let mut woken = waiter.woken.lock().unwrap();
+    let mut interrupted = false;
+    while !*woken {
+        if is_interrupted.map_or(false, |is_interrupted| is_interrupted()) {
+            interrupted = true;
+            break;
+        }
+        let left = match deadline {
+            None => None,
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                Some(deadline - now)
+            }
+        };
+        let poll = is_interrupted.map(|_| INTERRUPT_POLL_INTERVAL);
+        woken = match (left, poll) {
+            (None, None) => waiter.condvar.wait(woken).unwrap(),
+            (Some(wait), None) | (None, Some(wait)) => {
+                waiter.condvar.wait_timeout(woken, wait).unwrap().0
+            }
+            (Some(left), Some(poll)) => {
+                waiter
+                    .condvar
+                    .wait_timeout(woken, left.min(poll))
+                    .unwrap()
+                    .0
+            }
+        };
+    }
+    if *woken {
+        return Some(WaitResult::Woken);
+    }
+    drop(woken);
+
+    // The waiter may be notified between the timeout or the
+    // interruption and the removal from the queue, in which case it
+    // counts as woken up.
+    let mut queues = WAIT_QUEUES.lock().unwrap();
+    let queue = match queues.get_mut(&addr) {
+        Some(queue) => queue,
+        None => return Some(WaitResult::Woken),
+    };
+    let position = match queue.iter().position(|w| Arc::ptr_eq(w, &waiter)) {
+        Some(position) => position,
+        None => return Some(WaitResult::Woken),
+    };
+    queue.remove(position);
+    if queue.is_empty() {
+        queues.remove(&addr);
+    }
+    if interrupted {
+        None
+    } else {
+        Some(WaitResult::TimedOut)
+    }
+}
+
+/// Wake up to `count` of the threads waiting on the host address
+/// `addr`, in the order they started waiting, and return the number
+/// of threads woken up.
+pub fn atomic_notify(addr: usize, count: u32) -> u32 {
+    let mut queues = WAIT_QUEUES.lock().unwrap();
+    let queue = match queues.get_mut(&addr) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+    let mut woken = 0;
+    while woken < count {
+        let waiter = match queue.pop_front() {
+            Some(waiter) => waiter,
+            None => break,
+        };
+        *waiter.woken.lock().unwrap() = true;
+        waiter.condvar.notify_one();
+        woken += 1;
+    }
+    if queue.is_empty() {
+        queues.remove(&addr);
+    }
+    woken
+}
@@ -42,9 +42,12 @@ mod utils;
 
 use crate::syscalls::*;
 
+#[cfg(feature = "async-io")]
+pub use crate::state::{AsyncReadEnd, AsyncWriteEnd};
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFs, WasiState, WasiStateBuilder, WasiStateCreationError,
-    ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    DeniedSyscallBehavior, Fd, Pipe, ReadEnd, Stderr, Stdin, Stdout, Wasi, WasiBuilder, WasiFs,
+    WasiState, WasiStateBuilder, WasiStateCreationError, WasiSyscall, WriteEnd, ALL_RIGHTS,
+    VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
 pub use crate::utils::{get_wasi_version, get_wasi_versions, is_wasi_module, WasiVersion};
@@ -56,10 +59,12 @@ pub use wasmer_vfs::{FsError, VirtualFile};
 
 use thiserror::Error;
 use wasmer::{
-    imports, ChainableNamedResolver, Function, ImportObject, LazyInit, Memory, Module,
-    NamedResolver, Store, WasmerEnv,
+    ChainableNamedResolver, Exports, Extern, Function, FunctionType, ImportObject, LazyInit,
+    Memory, Module, NamedResolver, RuntimeError, Store, Val, WasmerEnv,
 };
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 
 /// This is returned in `RuntimeError`.
@@ -70,6 +75,36 @@ pub enum WasiError {
     Exit(syscalls::types::__wasi_exitcode_t),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    #[error("The WASI syscall `{0}` is denied")]
+    SyscallDenied(WasiSyscall),
+    #[error("`{0}` is not a WASI syscall")]
+    UnknownSyscall(String),
+    #[error(
+        "The module imports `{name}` from `{namespace}`, which is not a WASI syscall{}",
+        did_you_mean(.suggestion)
+    )]
+    UnknownImport {
+        namespace: String,
+        name: String,
+        /// The syscall with the closest name, if any is close enough.
+        suggestion: Option<WasiSyscall>,
+    },
+    #[error(
+        "The override of the WASI syscall `{syscall}` must be a function {expected}, not {found}"
+    )]
+    IncompatibleSyscallOverride {
+        syscall: WasiSyscall,
+        expected: FunctionType,
+        found: FunctionType,
+    },
+}
+
+/// The hint of [`WasiError::UnknownImport`].
+fn did_you_mean(suggestion: &Option<WasiSyscall>) -> String {
+    match suggestion {
+        Some(syscall) => format!(" (did you mean `{}`?)", syscall),
+        None => String::new(),
+    }
 }
 
 /// The environment provided to the WASI imports.
@@ -84,6 +119,9 @@ pub struct WasiEnv {
     pub state: Arc<Mutex<WasiState>>,
     #[wasmer(export)]
     memory: LazyInit<Memory>,
+    last_errno: Arc<AtomicU16>,
+    syscall_overrides: HashMap<WasiSyscall, Function>,
+    syscall_functions_built: Arc<AtomicUsize>,
 }
 
 impl WasiEnv {
@@ -91,17 +129,46 @@ impl WasiEnv {
         Self {
             state: Arc::new(Mutex::new(state)),
             memory: LazyInit::new(),
+            last_errno: Arc::new(AtomicU16::new(types::__WASI_ESUCCESS)),
+            syscall_overrides: HashMap::new(),
+            syscall_functions_built: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Replace the syscall `name` with `function` in the import
+    /// objects generated from this `WasiEnv` afterwards, for instance
+    /// to mock a syscall in tests. The syscalls are listed by
+    /// [`WasiSyscall::ALL`].
+    ///
+    /// `function` must have the type of the syscall it replaces. It
+    /// runs instead of the syscall, so it neither records the
+    /// [`WasiEnv::last_errno`] nor sees the [`WasiState`] unless it
+    /// keeps a clone of this `WasiEnv`. A syscall denied with
+    /// [`WasiStateBuilder::deny_syscalls`] stays denied.
+    pub fn override_syscall(&mut self, name: &str, function: Function) -> Result<(), WasiError> {
+        let syscall = WasiSyscall::from_name(name)
+            .ok_or_else(|| WasiError::UnknownSyscall(name.to_string()))?;
+        let expected = syscall_function(function.store(), self, WasiVersion::Snapshot1, syscall)
+            .ty()
+            .clone();
+        if function.ty() != &expected {
+            return Err(WasiError::IncompatibleSyscallOverride {
+                syscall,
+                expected,
+                found: function.ty().clone(),
+            });
+        }
+        self.syscall_overrides.insert(syscall, function);
+        Ok(())
+    }
+
     /// Get an `ImportObject` for a specific version of WASI detected in the module.
+    ///
+    /// Only the syscalls the module imports are provided, see
+    /// [`generate_import_object_for_module`].
     pub fn import_object(&mut self, module: &Module) -> Result<ImportObject, WasiError> {
         let wasi_version = get_wasi_version(module, false).ok_or(WasiError::UnknownWasiVersion)?;
-        Ok(generate_import_object_from_env(
-            module.store(),
-            self.clone(),
-            wasi_version,
-        ))
+        generate_import_object_for_module(module.store(), self.clone(), module, wasi_version)
     }
 
     /// Like `import_object` but containing all the WASI versions detected in
@@ -117,7 +184,7 @@ impl WasiEnv {
             { Box::new(()) as Box<dyn NamedResolver + Send + Sync> };
         for version in wasi_versions.iter() {
             let new_import_object =
-                generate_import_object_from_env(module.store(), self.clone(), *version);
+                generate_import_object_for_module(module.store(), self.clone(), module, *version)?;
             resolver = Box::new(new_import_object.chain_front(resolver));
         }
         Ok(resolver)
@@ -138,6 +205,32 @@ impl WasiEnv {
             .expect("Memory should be set on `WasiEnv` first")
     }
 
+    /// Get the return code of the most recent WASI syscall.
+    ///
+    /// This is shared by all the clones of this `WasiEnv`, so it can
+    /// be read from the host after the guest made a syscall.
+    pub fn last_errno(&self) -> types::__wasi_errno_t {
+        self.last_errno.load(Ordering::SeqCst)
+    }
+
+    /// Set the value returned by [`WasiEnv::last_errno`].
+    pub fn set_last_errno(&self, errno: types::__wasi_errno_t) {
+        self.last_errno.store(errno, Ordering::SeqCst);
+    }
+
+    /// Get the number of syscall functions built for the import
+    /// objects generated from this `WasiEnv` and its clones, leaving
+    /// out the syscalls replaced with [`WasiEnv::override_syscall`].
+    pub fn syscall_functions_built(&self) -> usize {
+        self.syscall_functions_built.load(Ordering::SeqCst)
+    }
+
+    /// Get the number of times the guest called each of the syscalls
+    /// denied with [`WasiStateBuilder::deny_syscalls`].
+    pub fn denied_syscall_stats(&self) -> HashMap<WasiSyscall, u64> {
+        self.state().syscall_policy.denied_calls.clone()
+    }
+
     pub(crate) fn get_memory_and_wasi_state(
         &self,
         _mem_index: u32,
@@ -156,116 +249,209 @@ pub fn generate_import_object_from_env(
     wasi_env: WasiEnv,
     version: WasiVersion,
 ) -> ImportObject {
-    match version {
-        WasiVersion::Snapshot0 => generate_import_object_snapshot0(store, wasi_env),
-        WasiVersion::Snapshot1 | WasiVersion::Latest => {
-            generate_import_object_snapshot1(store, wasi_env)
+    generate_import_object_with_syscalls(store, wasi_env, version, WasiSyscall::ALL)
+}
+
+/// Create an [`ImportObject`] with an existing [`WasiEnv`], providing
+/// only the syscalls of `version` that `module` imports.
+///
+/// Importing anything else from the namespace of `version` fails
+/// with [`WasiError::UnknownImport`], suggesting the syscall with the
+/// closest name.
+pub fn generate_import_object_for_module(
+    store: &Store,
+    wasi_env: WasiEnv,
+    module: &Module,
+    version: WasiVersion,
+) -> Result<ImportObject, WasiError> {
+    let namespace = version.get_namespace_str();
+    let mut syscalls = vec![];
+    for import in module.imports() {
+        if import.module() != namespace {
+            continue;
+        }
+        let syscall =
+            WasiSyscall::from_name(import.name()).ok_or_else(|| WasiError::UnknownImport {
+                namespace: namespace.to_string(),
+                name: import.name().to_string(),
+                suggestion: WasiSyscall::nearest(import.name()),
+            })?;
+        if !syscalls.contains(&syscall) {
+            syscalls.push(syscall);
         }
     }
+    Ok(generate_import_object_with_syscalls(
+        store, wasi_env, version, &syscalls,
+    ))
 }
 
-/// Combines a state generating function with the import list for legacy WASI
-fn generate_import_object_snapshot0(store: &Store, env: WasiEnv) -> ImportObject {
-    imports! {
-        "wasi_unstable" => {
-            "args_get" => Function::new_native_with_env(store, env.clone(), args_get),
-            "args_sizes_get" => Function::new_native_with_env(store, env.clone(), args_sizes_get),
-            "clock_res_get" => Function::new_native_with_env(store, env.clone(), clock_res_get),
-            "clock_time_get" => Function::new_native_with_env(store, env.clone(), clock_time_get),
-            "environ_get" => Function::new_native_with_env(store, env.clone(), environ_get),
-            "environ_sizes_get" => Function::new_native_with_env(store, env.clone(), environ_sizes_get),
-            "fd_advise" => Function::new_native_with_env(store, env.clone(), fd_advise),
-            "fd_allocate" => Function::new_native_with_env(store, env.clone(), fd_allocate),
-            "fd_close" => Function::new_native_with_env(store, env.clone(), fd_close),
-            "fd_datasync" => Function::new_native_with_env(store, env.clone(), fd_datasync),
-            "fd_fdstat_get" => Function::new_native_with_env(store, env.clone(), fd_fdstat_get),
-            "fd_fdstat_set_flags" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_flags),
-            "fd_fdstat_set_rights" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_rights),
-            "fd_filestat_get" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::fd_filestat_get),
-            "fd_filestat_set_size" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_size),
-            "fd_filestat_set_times" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_times),
-            "fd_pread" => Function::new_native_with_env(store, env.clone(), fd_pread),
-            "fd_prestat_get" => Function::new_native_with_env(store, env.clone(), fd_prestat_get),
-            "fd_prestat_dir_name" => Function::new_native_with_env(store, env.clone(), fd_prestat_dir_name),
-            "fd_pwrite" => Function::new_native_with_env(store, env.clone(), fd_pwrite),
-            "fd_read" => Function::new_native_with_env(store, env.clone(), fd_read),
-            "fd_readdir" => Function::new_native_with_env(store, env.clone(), fd_readdir),
-            "fd_renumber" => Function::new_native_with_env(store, env.clone(), fd_renumber),
-            "fd_seek" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::fd_seek),
-            "fd_sync" => Function::new_native_with_env(store, env.clone(), fd_sync),
-            "fd_tell" => Function::new_native_with_env(store, env.clone(), fd_tell),
-            "fd_write" => Function::new_native_with_env(store, env.clone(), fd_write),
-            "path_create_directory" => Function::new_native_with_env(store, env.clone(), path_create_directory),
-            "path_filestat_get" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::path_filestat_get),
-            "path_filestat_set_times" => Function::new_native_with_env(store, env.clone(), path_filestat_set_times),
-            "path_link" => Function::new_native_with_env(store, env.clone(), path_link),
-            "path_open" => Function::new_native_with_env(store, env.clone(), path_open),
-            "path_readlink" => Function::new_native_with_env(store, env.clone(), path_readlink),
-            "path_remove_directory" => Function::new_native_with_env(store, env.clone(), path_remove_directory),
-            "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
-            "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
-            "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
-            "poll_oneoff" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::poll_oneoff),
-            "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
-            "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
-            "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
-            "sched_yield" => Function::new_native_with_env(store, env.clone(), sched_yield),
-            "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
-            "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
-            "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
-        },
+/// Combines the functions of `syscalls`, or their overrides, in the
+/// namespace of `version`, denying the syscalls the [`WasiState`] of
+/// `env` denies.
+fn generate_import_object_with_syscalls(
+    store: &Store,
+    env: WasiEnv,
+    version: WasiVersion,
+    syscalls: &[WasiSyscall],
+) -> ImportObject {
+    let mut namespace = Exports::new();
+    for &syscall in syscalls {
+        let function = match env.syscall_overrides.get(&syscall) {
+            Some(function) => function.clone(),
+            None => {
+                env.syscall_functions_built.fetch_add(1, Ordering::SeqCst);
+                syscall_function(store, &env, version, syscall)
+            }
+        };
+        namespace.insert(syscall.name(), function);
     }
+    deny_syscalls(store, &env, &mut namespace);
+
+    let mut import_object = ImportObject::new();
+    import_object.register(version.get_namespace_str(), namespace);
+    import_object
 }
 
-/// Combines a state generating function with the import list for snapshot 1
-fn generate_import_object_snapshot1(store: &Store, env: WasiEnv) -> ImportObject {
-    imports! {
-        "wasi_snapshot_preview1" => {
-            "args_get" => Function::new_native_with_env(store, env.clone(), args_get),
-            "args_sizes_get" => Function::new_native_with_env(store, env.clone(), args_sizes_get),
-            "clock_res_get" => Function::new_native_with_env(store, env.clone(), clock_res_get),
-            "clock_time_get" => Function::new_native_with_env(store, env.clone(), clock_time_get),
-            "environ_get" => Function::new_native_with_env(store, env.clone(), environ_get),
-            "environ_sizes_get" => Function::new_native_with_env(store, env.clone(), environ_sizes_get),
-            "fd_advise" => Function::new_native_with_env(store, env.clone(), fd_advise),
-            "fd_allocate" => Function::new_native_with_env(store, env.clone(), fd_allocate),
-            "fd_close" => Function::new_native_with_env(store, env.clone(), fd_close),
-            "fd_datasync" => Function::new_native_with_env(store, env.clone(), fd_datasync),
-            "fd_fdstat_get" => Function::new_native_with_env(store, env.clone(), fd_fdstat_get),
-            "fd_fdstat_set_flags" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_flags),
-            "fd_fdstat_set_rights" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_rights),
-            "fd_filestat_get" => Function::new_native_with_env(store, env.clone(), fd_filestat_get),
-            "fd_filestat_set_size" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_size),
-            "fd_filestat_set_times" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_times),
-            "fd_pread" => Function::new_native_with_env(store, env.clone(), fd_pread),
-            "fd_prestat_get" => Function::new_native_with_env(store, env.clone(), fd_prestat_get),
-            "fd_prestat_dir_name" => Function::new_native_with_env(store, env.clone(), fd_prestat_dir_name),
-            "fd_pwrite" => Function::new_native_with_env(store, env.clone(), fd_pwrite),
-            "fd_read" => Function::new_native_with_env(store, env.clone(), fd_read),
-            "fd_readdir" => Function::new_native_with_env(store, env.clone(), fd_readdir),
-            "fd_renumber" => Function::new_native_with_env(store, env.clone(), fd_renumber),
-            "fd_seek" => Function::new_native_with_env(store, env.clone(), fd_seek),
-            "fd_sync" => Function::new_native_with_env(store, env.clone(), fd_sync),
-            "fd_tell" => Function::new_native_with_env(store, env.clone(), fd_tell),
-            "fd_write" => Function::new_native_with_env(store, env.clone(), fd_write),
-            "path_create_directory" => Function::new_native_with_env(store, env.clone(), path_create_directory),
-            "path_filestat_get" => Function::new_native_with_env(store, env.clone(), path_filestat_get),
-            "path_filestat_set_times" => Function::new_native_with_env(store, env.clone(), path_filestat_set_times),
-            "path_link" => Function::new_native_with_env(store, env.clone(), path_link),
-            "path_open" => Function::new_native_with_env(store, env.clone(), path_open),
-            "path_readlink" => Function::new_native_with_env(store, env.clone(), path_readlink),
-            "path_remove_directory" => Function::new_native_with_env(store, env.clone(), path_remove_directory),
-            "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
-            "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
-            "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
-            "poll_oneoff" => Function::new_native_with_env(store, env.clone(), poll_oneoff),
-            "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
-            "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
-            "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
-            "sched_yield" => Function::new_native_with_env(store, env.clone(), sched_yield),
-            "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
-            "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
-            "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
+macro_rules! impl_track_errno {
+    ($($name:ident($($x:ident),*);)*) => {$(
+        /// Wraps a syscall so that its return code is recorded as the
+        /// last errno of the `WasiEnv` it is called with.
+        #[allow(non_snake_case)]
+        fn $name<F, $($x),*>(
+            syscall: F,
+        ) -> impl Fn(&WasiEnv $(, $x)*) -> types::__wasi_errno_t + Send + 'static
+        where
+            F: Fn(&WasiEnv $(, $x)*) -> types::__wasi_errno_t + Send + 'static,
+            $($x: 'static,)*
+        {
+            move |env: &WasiEnv $(, $x: $x)*| {
+                let errno = syscall(env $(, $x)*);
+                env.set_last_errno(errno);
+                errno
+            }
         }
+    )*};
+}
+
+impl_track_errno! {
+    track_errno0();
+    track_errno1(A1);
+    track_errno2(A1, A2);
+    track_errno3(A1, A2, A3);
+    track_errno4(A1, A2, A3, A4);
+    track_errno5(A1, A2, A3, A4, A5);
+    track_errno6(A1, A2, A3, A4, A5, A6);
+    track_errno7(A1, A2, A3, A4, A5, A6, A7);
+    track_errno9(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+}
+
+/// Replaces the syscalls denied by the [`WasiState`] of `env` in
+/// `namespace` with functions of the same type that don't run them.
+fn deny_syscalls(store: &Store, env: &WasiEnv, namespace: &mut Exports) {
+    let (denied, behavior) = {
+        let state = env.state();
+        let policy = &state.syscall_policy;
+        (policy.denied.clone(), policy.behavior)
+    };
+
+    let mut denied_functions = vec![];
+    for (name, export) in namespace.iter() {
+        let (syscall, function) = match (WasiSyscall::from_name(name), export) {
+            (Some(syscall), Extern::Function(function)) if denied.contains(&syscall) => {
+                (syscall, function)
+            }
+            _ => continue,
+        };
+        let returns_errno = !function.ty().results().is_empty();
+
+        let denied_function = Function::new_with_env(
+            store,
+            function.ty().clone(),
+            env.clone(),
+            move |env: &WasiEnv, _args: &[Val]| {
+                tracing::debug!("wasi::{} is denied", syscall);
+                env.state().syscall_policy.record_denied_call(syscall);
+
+                match behavior {
+                    DeniedSyscallBehavior::Trap => Err(RuntimeError::user(Box::new(
+                        WasiError::SyscallDenied(syscall),
+                    ))),
+                    DeniedSyscallBehavior::Errno if returns_errno => {
+                        env.set_last_errno(types::__WASI_ENOTCAPABLE);
+                        Ok(vec![Val::I32(types::__WASI_ENOTCAPABLE as i32)])
+                    }
+                    DeniedSyscallBehavior::Errno => Ok(vec![]),
+                }
+            },
+        );
+        denied_functions.push((name.clone(), denied_function));
+    }
+
+    for (name, denied_function) in denied_functions {
+        namespace.insert(name, denied_function);
+    }
+}
+
+/// Builds the function of `syscall` in `version` of WASI.
+#[rustfmt::skip]
+fn syscall_function(
+    store: &Store,
+    env: &WasiEnv,
+    version: WasiVersion,
+    syscall: WasiSyscall,
+) -> Function {
+    // A few syscalls changed their types in snapshot 1.
+    let snapshot0 = matches!(version, WasiVersion::Snapshot0);
+    let env = env.clone();
+    match syscall {
+        WasiSyscall::ArgsGet => Function::new_native_with_env(store, env, track_errno2(args_get)),
+        WasiSyscall::ArgsSizesGet => Function::new_native_with_env(store, env, track_errno2(args_sizes_get)),
+        WasiSyscall::ClockResGet => Function::new_native_with_env(store, env, track_errno2(clock_res_get)),
+        WasiSyscall::ClockTimeGet => Function::new_native_with_env(store, env, track_errno3(clock_time_get)),
+        WasiSyscall::EnvironGet => Function::new_native_with_env(store, env, track_errno2(environ_get)),
+        WasiSyscall::EnvironSizesGet => Function::new_native_with_env(store, env, track_errno2(environ_sizes_get)),
+        WasiSyscall::FdAdvise => Function::new_native_with_env(store, env, track_errno4(fd_advise)),
+        WasiSyscall::FdAllocate => Function::new_native_with_env(store, env, track_errno3(fd_allocate)),
+        WasiSyscall::FdClose => Function::new_native_with_env(store, env, track_errno1(fd_close)),
+        WasiSyscall::FdDatasync => Function::new_native_with_env(store, env, track_errno1(fd_datasync)),
+        WasiSyscall::FdFdstatGet => Function::new_native_with_env(store, env, track_errno2(fd_fdstat_get)),
+        WasiSyscall::FdFdstatSetFlags => Function::new_native_with_env(store, env, track_errno2(fd_fdstat_set_flags)),
+        WasiSyscall::FdFdstatSetRights => Function::new_native_with_env(store, env, track_errno3(fd_fdstat_set_rights)),
+        WasiSyscall::FdFilestatGet if snapshot0 => Function::new_native_with_env(store, env, track_errno2(legacy::snapshot0::fd_filestat_get)),
+        WasiSyscall::FdFilestatGet => Function::new_native_with_env(store, env, track_errno2(fd_filestat_get)),
+        WasiSyscall::FdFilestatSetSize => Function::new_native_with_env(store, env, track_errno2(fd_filestat_set_size)),
+        WasiSyscall::FdFilestatSetTimes => Function::new_native_with_env(store, env, track_errno4(fd_filestat_set_times)),
+        WasiSyscall::FdPread => Function::new_native_with_env(store, env, track_errno5(fd_pread)),
+        WasiSyscall::FdPrestatGet => Function::new_native_with_env(store, env, track_errno2(fd_prestat_get)),
+        WasiSyscall::FdPrestatDirName => Function::new_native_with_env(store, env, track_errno3(fd_prestat_dir_name)),
+        WasiSyscall::FdPwrite => Function::new_native_with_env(store, env, track_errno5(fd_pwrite)),
+        WasiSyscall::FdRead => Function::new_native_with_env(store, env, track_errno4(fd_read)),
+        WasiSyscall::FdReaddir => Function::new_native_with_env(store, env, track_errno5(fd_readdir)),
+        WasiSyscall::FdRenumber => Function::new_native_with_env(store, env, track_errno2(fd_renumber)),
+        WasiSyscall::FdSeek if snapshot0 => Function::new_native_with_env(store, env, track_errno4(legacy::snapshot0::fd_seek)),
+        WasiSyscall::FdSeek => Function::new_native_with_env(store, env, track_errno4(fd_seek)),
+        WasiSyscall::FdSync => Function::new_native_with_env(store, env, track_errno1(fd_sync)),
+        WasiSyscall::FdTell => Function::new_native_with_env(store, env, track_errno2(fd_tell)),
+        WasiSyscall::FdWrite => Function::new_native_with_env(store, env, track_errno4(fd_write)),
+        WasiSyscall::PathCreateDirectory => Function::new_native_with_env(store, env, track_errno3(path_create_directory)),
+        WasiSyscall::PathFilestatGet if snapshot0 => Function::new_native_with_env(store, env, track_errno5(legacy::snapshot0::path_filestat_get)),
+        WasiSyscall::PathFilestatGet => Function::new_native_with_env(store, env, track_errno5(path_filestat_get)),
+        WasiSyscall::PathFilestatSetTimes => Function::new_native_with_env(store, env, track_errno7(path_filestat_set_times)),
+        WasiSyscall::PathLink => Function::new_native_with_env(store, env, track_errno7(path_link)),
+        WasiSyscall::PathOpen => Function::new_native_with_env(store, env, track_errno9(path_open)),
+        WasiSyscall::PathReadlink => Function::new_native_with_env(store, env, track_errno6(path_readlink)),
+        WasiSyscall::PathRemoveDirectory => Function::new_native_with_env(store, env, track_errno3(path_remove_directory)),
+        WasiSyscall::PathRename => Function::new_native_with_env(store, env, track_errno6(path_rename)),
+        WasiSyscall::PathSymlink => Function::new_native_with_env(store, env, track_errno5(path_symlink)),
+        WasiSyscall::PathUnlinkFile => Function::new_native_with_env(store, env, track_errno3(path_unlink_file)),
+        WasiSyscall::PollOneoff if snapshot0 => Function::new_native_with_env(store, env, track_errno4(legacy::snapshot0::poll_oneoff)),
+        WasiSyscall::PollOneoff => Function::new_native_with_env(store, env, track_errno4(poll_oneoff)),
+        WasiSyscall::ProcExit => Function::new_native_with_env(store, env, proc_exit),
+        WasiSyscall::ProcRaise => Function::new_native_with_env(store, env, track_errno1(proc_raise)),
+        WasiSyscall::RandomGet => Function::new_native_with_env(store, env, track_errno2(random_get)),
+        WasiSyscall::SchedYield => Function::new_native_with_env(store, env, track_errno0(sched_yield)),
+        WasiSyscall::SockRecv => Function::new_native_with_env(store, env, track_errno6(sock_recv)),
+        WasiSyscall::SockSend => Function::new_native_with_env(store, env, track_errno5(sock_send)),
+        WasiSyscall::SockShutdown => Function::new_native_with_env(store, env, track_errno2(sock_shutdown)),
     }
 }
@@ -0,0 +1,134 @@
+//! Adapters of the ends of a [`Pipe`] to the asynchronous IO traits
+//! of `futures`, and of `tokio` with the `async-io-tokio` feature.
+//!
+//! [`Pipe`]: super::Pipe
+
+use super::types::{ReadEnd, WriteEnd};
+use futures_io::{AsyncRead, AsyncWrite};
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl ReadEnd {
+    /// Converts this end into an [`AsyncReadEnd`], to read the bytes
+    /// as they are written, e.g. to copy the `stdout` of a program to
+    /// the body of an HTTP response while it runs.
+    pub fn into_async_read(self) -> AsyncReadEnd {
+        AsyncReadEnd { end: self }
+    }
+}
+
+impl WriteEnd {
+    /// Converts this end into an [`AsyncWriteEnd`], e.g. to feed the
+    /// `stdin` of a program from an asynchronous stream.
+    pub fn into_async_write(self) -> AsyncWriteEnd {
+        AsyncWriteEnd { end: self }
+    }
+}
+
+/// The end of a [`Pipe`] the bytes are read from, reading
+/// asynchronously, see [`ReadEnd::into_async_read`].
+///
+/// Unlike [`ReadEnd`], reading waits for the bytes to be written: the
+/// task is woken by the next write, from whichever thread it comes,
+/// and the end of the stream is only reached once the write end is
+/// dropped or closed.
+///
+/// [`Pipe`]: super::Pipe
+#[derive(Debug)]
+pub struct AsyncReadEnd {
+    end: ReadEnd,
+}
+
+impl AsyncReadEnd {
+    fn poll_read_bytes(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<usize> {
+        let mut buffer = self.end.buffer.lock().unwrap();
+        if buffer.bytes.is_empty() && !buffer.closed && !buf.is_empty() {
+            // The waker is registered with the lock held, a write can't
+            // happen in between and be missed.
+            buffer.reader = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let amt = std::cmp::min(buf.len(), buffer.bytes.len());
+        for (i, byte) in buffer.bytes.drain(..amt).enumerate() {
+            buf[i] = byte;
+        }
+        Poll::Ready(amt)
+    }
+}
+
+impl AsyncRead for AsyncReadEnd {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_bytes(cx, buf).map(Ok)
+    }
+}
+
+#[cfg(feature = "async-io-tokio")]
+impl tokio::io::AsyncRead for AsyncReadEnd {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.get_mut()
+            .poll_read_bytes(cx, buf.initialize_unfilled())
+            .map(|amt| {
+                buf.advance(amt);
+                Ok(())
+            })
+    }
+}
+
+/// The end of a [`Pipe`] the bytes are written to, writing
+/// asynchronously, see [`WriteEnd::into_async_write`].
+///
+/// Writing never waits, the pipe isn't bounded. Closing this end, or
+/// dropping it, closes the pipe.
+///
+/// [`Pipe`]: super::Pipe
+#[derive(Debug)]
+pub struct AsyncWriteEnd {
+    end: WriteEnd,
+}
+
+impl AsyncWrite for AsyncWriteEnd {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().end.write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().end.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async-io-tokio")]
+impl tokio::io::AsyncWrite for AsyncWriteEnd {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
@@ -1,10 +1,14 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{default_fs_backing, WasiFs, WasiState};
+use crate::state::{
+    default_fs_backing, DeniedSyscallBehavior, SyscallPolicy, WasiFs, WasiState, WasiSyscall,
+};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
-use crate::WasiEnv;
+use crate::{generate_import_object_from_env, WasiEnv, WasiVersion};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
+use wasmer::{ImportObject, Store};
 use wasmer_vfs::{FsError, VirtualFile};
 
 /// Creates an empty [`WasiStateBuilder`].
@@ -45,6 +49,8 @@ pub struct WasiStateBuilder {
     stderr_override: Option<Box<dyn VirtualFile>>,
     stdin_override: Option<Box<dyn VirtualFile>>,
     fs_override: Option<Box<dyn wasmer_vfs::FileSystem>>,
+    denied_syscalls: HashSet<WasiSyscall>,
+    denied_syscall_behavior: DeniedSyscallBehavior,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -58,6 +64,8 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("denied_syscalls", &self.denied_syscalls)
+            .field("denied_syscall_behavior", &self.denied_syscall_behavior)
             .finish()
     }
 }
@@ -237,6 +245,14 @@ impl WasiStateBuilder {
     }
 
     /// Preopen a directory with a different name exposed to the WASI.
+    ///
+    /// The host directory `po_dir` is mounted at the guest path
+    /// `alias`, e.g. mapping `/data` to `/host/data` makes the WASI
+    /// program read `/host/data/file.txt` when it opens
+    /// `/data/file.txt`. The alias can have several components, and
+    /// when the aliases of several mapped directories prefix a path,
+    /// the longest one wins: with `/data` and `/data/cache` mapped,
+    /// `/data/cache/file.txt` is looked up in the latter.
     pub fn map_dir<FilePath>(
         &mut self,
         alias: &str,
@@ -319,6 +335,38 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Deny some syscalls to the guest.
+    ///
+    /// The imports generated for a denied syscall don't run it; they
+    /// behave as configured with [`Self::denied_syscall_behavior`]
+    /// instead, and count the attempt in
+    /// [`WasiEnv::denied_syscall_stats`].
+    ///
+    /// For example, to deny all the `path_*` syscalls:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{WasiState, WasiSyscall};
+    /// let path_syscalls = WasiSyscall::ALL
+    ///     .iter()
+    ///     .copied()
+    ///     .filter(|syscall| syscall.name().starts_with("path_"))
+    ///     .collect::<Vec<_>>();
+    /// WasiState::new("wasi-prog-name").deny_syscalls(&path_syscalls);
+    /// ```
+    pub fn deny_syscalls(&mut self, syscalls: &[WasiSyscall]) -> &mut Self {
+        self.denied_syscalls.extend(syscalls);
+
+        self
+    }
+
+    /// Set what the syscalls denied with [`Self::deny_syscalls`] do
+    /// when called. By default, they return `__WASI_ENOTCAPABLE`.
+    pub fn denied_syscall_behavior(&mut self, behavior: DeniedSyscallBehavior) -> &mut Self {
+        self.denied_syscall_behavior = behavior;
+
+        self
+    }
+
     /// Consumes the [`WasiStateBuilder`] and produces a [`WasiState`]
     ///
     /// Returns the error from `WasiFs::new` if there's an error
@@ -448,6 +496,11 @@ impl WasiStateBuilder {
                     env
                 })
                 .collect(),
+            syscall_policy: SyscallPolicy {
+                denied: self.denied_syscalls.clone(),
+                behavior: self.denied_syscall_behavior,
+                ..SyscallPolicy::default()
+            },
         })
     }
 
@@ -509,7 +562,7 @@ impl PreopenDirBuilder {
     pub fn alias(&mut self, alias: &str) -> &mut Self {
         // We mount at preopened dirs at `/` by default and multiple `/` in a row
         // are equal to a single `/`.
-        let alias = alias.trim_start_matches('/');
+        let alias = alias.trim_start_matches('/').trim_end_matches('/');
         self.alias = Some(alias.to_string());
 
         self
@@ -574,6 +627,188 @@ impl PreopenDirBuilder {
     }
 }
 
+/// Entry point of [`WasiBuilder`].
+pub struct Wasi;
+
+impl Wasi {
+    /// Create a [`WasiBuilder`] for a program named `program_name`.
+    ///
+    /// Usage:
+    /// ```
+    /// # use wasmer::{Instance, Module, Store};
+    /// # use wasmer_wasi::Wasi;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let store = Store::default();
+    /// let module = Module::new(
+    ///     &store,
+    ///     r#"(module (import "wasi_snapshot_preview1" "proc_exit" (func (param i32))))"#,
+    /// )?;
+    /// let (wasi_env, import_object) = Wasi::builder("wasi-prog-name")
+    ///     .args(&["--verbose"])
+    ///     .env("ENV_VAR", "ENV_VAL")
+    ///     .preopen_dir("src")
+    ///     .finalize(&store)?;
+    /// let instance = Instance::new(&module, &import_object)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(program_name: impl AsRef<str>) -> WasiBuilder {
+        WasiBuilder {
+            state_builder: create_wasi_state(program_name.as_ref()),
+            error: None,
+        }
+    }
+}
+
+/// A [`WasiStateBuilder`] taking and returning itself by value, so
+/// that WASI can be set up in a single expression. Created with
+/// [`Wasi::builder`].
+///
+/// The errors of the configuration methods are reported by
+/// [`WasiBuilder::finalize`].
+#[derive(Debug)]
+pub struct WasiBuilder {
+    state_builder: WasiStateBuilder,
+    error: Option<WasiStateCreationError>,
+}
+
+impl WasiBuilder {
+    /// Add an argument, see [`WasiStateBuilder::arg`].
+    pub fn arg<Arg>(mut self, arg: Arg) -> Self
+    where
+        Arg: AsRef<[u8]>,
+    {
+        self.state_builder.arg(arg);
+
+        self
+    }
+
+    /// Add multiple arguments, see [`WasiStateBuilder::args`].
+    pub fn args<I, Arg>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = Arg>,
+        Arg: AsRef<[u8]>,
+    {
+        self.state_builder.args(args);
+
+        self
+    }
+
+    /// Add an environment variable pair, see [`WasiStateBuilder::env`].
+    pub fn env<Key, Value>(mut self, key: Key, value: Value) -> Self
+    where
+        Key: AsRef<[u8]>,
+        Value: AsRef<[u8]>,
+    {
+        self.state_builder.env(key, value);
+
+        self
+    }
+
+    /// Add multiple environment variable pairs, see
+    /// [`WasiStateBuilder::envs`].
+    pub fn envs<I, Key, Value>(mut self, env_pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+        Key: AsRef<[u8]>,
+        Value: AsRef<[u8]>,
+    {
+        self.state_builder.envs(env_pairs);
+
+        self
+    }
+
+    /// Preopen a directory, see [`WasiStateBuilder::preopen_dir`].
+    pub fn preopen_dir<FilePath>(mut self, po_dir: FilePath) -> Self
+    where
+        FilePath: AsRef<Path>,
+    {
+        if let Err(e) = self.state_builder.preopen_dir(po_dir) {
+            self.error.get_or_insert(e);
+        }
+
+        self
+    }
+
+    /// Preopen a directory and configure it, see
+    /// [`WasiStateBuilder::preopen`].
+    pub fn preopen<F>(mut self, inner: F) -> Self
+    where
+        F: Fn(&mut PreopenDirBuilder) -> &mut PreopenDirBuilder,
+    {
+        if let Err(e) = self.state_builder.preopen(inner) {
+            self.error.get_or_insert(e);
+        }
+
+        self
+    }
+
+    /// Preopen a directory with a different name exposed to the WASI,
+    /// see [`WasiStateBuilder::map_dir`].
+    pub fn map_dir<FilePath>(mut self, alias: &str, po_dir: FilePath) -> Self
+    where
+        FilePath: AsRef<Path>,
+    {
+        if let Err(e) = self.state_builder.map_dir(alias, po_dir) {
+            self.error.get_or_insert(e);
+        }
+
+        self
+    }
+
+    /// Overwrite the default WASI `stdin`.
+    pub fn stdin(mut self, new_file: Box<dyn VirtualFile>) -> Self {
+        self.state_builder.stdin(new_file);
+
+        self
+    }
+
+    /// Overwrite the default WASI `stdout`.
+    pub fn stdout(mut self, new_file: Box<dyn VirtualFile>) -> Self {
+        self.state_builder.stdout(new_file);
+
+        self
+    }
+
+    /// Overwrite the default WASI `stderr`.
+    pub fn stderr(mut self, new_file: Box<dyn VirtualFile>) -> Self {
+        self.state_builder.stderr(new_file);
+
+        self
+    }
+
+    /// Deny some syscalls to the guest, see
+    /// [`WasiStateBuilder::deny_syscalls`].
+    pub fn deny_syscalls(mut self, syscalls: &[WasiSyscall]) -> Self {
+        self.state_builder.deny_syscalls(syscalls);
+
+        self
+    }
+
+    /// Produce a [`WasiEnv`] and the [`ImportObject`] providing it
+    /// to the modules of `store`.
+    ///
+    /// The imports are the ones of the latest WASI version,
+    /// `wasi_snapshot_preview1`. Use [`WasiEnv::import_object`] to get
+    /// the imports of the version a module uses.
+    ///
+    /// Returns the first error of the configuration methods, if any.
+    pub fn finalize(
+        mut self,
+        store: &Store,
+    ) -> Result<(WasiEnv, ImportObject), WasiStateCreationError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let wasi_env = self.state_builder.finalize()?;
+        let import_object =
+            generate_import_object_from_env(store, wasi_env.clone(), WasiVersion::Latest);
+
+        Ok((wasi_env, import_object))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -15,10 +15,17 @@
 
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
+#[cfg(feature = "async-io")]
+mod async_pipe;
 mod builder;
+mod policy;
 mod types;
 
+#[cfg(feature = "async-io")]
+pub use self::async_pipe::{AsyncReadEnd, AsyncWriteEnd};
 pub use self::builder::*;
+pub(crate) use self::policy::SyscallPolicy;
+pub use self::policy::{DeniedSyscallBehavior, WasiSyscall};
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -319,8 +326,6 @@ impl WasiFs {
                         | __WASI_RIGHT_PATH_READLINK
                         | __WASI_RIGHT_PATH_FILESTAT_GET
                         | __WASI_RIGHT_FD_FILESTAT_GET
-                        | __WASI_RIGHT_PATH_LINK_SOURCE
-                        | __WASI_RIGHT_PATH_RENAME_SOURCE
                         | __WASI_RIGHT_POLL_FD_READWRITE
                         | __WASI_RIGHT_SOCK_SHUTDOWN;
                 }
@@ -331,6 +336,8 @@ impl WasiFs {
                         | __WASI_RIGHT_FD_SYNC
                         | __WASI_RIGHT_FD_ALLOCATE
                         | __WASI_RIGHT_PATH_OPEN
+                        | __WASI_RIGHT_PATH_LINK_SOURCE
+                        | __WASI_RIGHT_PATH_RENAME_SOURCE
                         | __WASI_RIGHT_PATH_RENAME_TARGET
                         | __WASI_RIGHT_PATH_FILESTAT_SET_SIZE
                         | __WASI_RIGHT_PATH_FILESTAT_SET_TIMES
@@ -723,9 +730,16 @@ impl WasiFs {
         let path: &Path = Path::new(path);
 
         let mut cur_inode = base_dir.inode;
-        let n_components = path.components().count();
+        let components = path.components().collect::<Vec<_>>();
+        let n_components = components.len();
+        // the components already consumed by the alias of a mapped directory
+        let mut skipped_components = 0;
         // TODO: rights checks
-        'path_iter: for (i, component) in path.components().enumerate() {
+        'path_iter: for (i, component) in components.iter().enumerate() {
+            if skipped_components > 0 {
+                skipped_components -= 1;
+                continue 'path_iter;
+            }
             // used to terminate symlink resolution properly
             let last_component = i + 1 == n_components;
             // for each component traverse file structure
@@ -890,10 +904,19 @@ impl WasiFs {
                             _ => (),
                         }
 
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = *entry;
+                        // the aliases of mapped directories can span several
+                        // components, the longest one prefixing the path wins
+                        let mapped_dir = (i + 1..=n_components).rev().find_map(|end| {
+                            let alias = components[i..end]
+                                .iter()
+                                .map(|part| part.as_os_str().to_string_lossy())
+                                .collect::<Vec<_>>()
+                                .join("/");
+                            entries.get(&alias).map(|entry| (*entry, end - i - 1))
+                        });
+                        if let Some((entry, alias_components)) = mapped_dir {
+                            cur_inode = entry;
+                            skipped_components = alias_components;
                         } else {
                             return Err(__WASI_ENOENT);
                         }
@@ -1561,6 +1584,8 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) syscall_policy: SyscallPolicy,
 }
 
 impl WasiState {
@@ -0,0 +1,183 @@
+//! Denying individual WASI syscalls, see
+//! [`WasiStateBuilder::deny_syscalls`](super::WasiStateBuilder::deny_syscalls).
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+macro_rules! wasi_syscalls {
+    ($($variant:ident => $name:literal,)*) => {
+        /// A function of the WASI API, as imported by a module.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum WasiSyscall {
+            $(
+                #[doc = concat!("`", $name, "`")]
+                $variant,
+            )*
+        }
+
+        impl WasiSyscall {
+            /// All the WASI syscalls.
+            pub const ALL: &'static [WasiSyscall] = &[$(WasiSyscall::$variant,)*];
+
+            /// The name the syscall is imported with, e.g. `"fd_read"`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(WasiSyscall::$variant => $name,)*
+                }
+            }
+
+            /// Look up a syscall by the name it is imported with.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(WasiSyscall::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+wasi_syscalls! {
+    ArgsGet => "args_get",
+    ArgsSizesGet => "args_sizes_get",
+    ClockResGet => "clock_res_get",
+    ClockTimeGet => "clock_time_get",
+    EnvironGet => "environ_get",
+    EnvironSizesGet => "environ_sizes_get",
+    FdAdvise => "fd_advise",
+    FdAllocate => "fd_allocate",
+    FdClose => "fd_close",
+    FdDatasync => "fd_datasync",
+    FdFdstatGet => "fd_fdstat_get",
+    FdFdstatSetFlags => "fd_fdstat_set_flags",
+    FdFdstatSetRights => "fd_fdstat_set_rights",
+    FdFilestatGet => "fd_filestat_get",
+    FdFilestatSetSize => "fd_filestat_set_size",
+    FdFilestatSetTimes => "fd_filestat_set_times",
+    FdPread => "fd_pread",
+    FdPrestatGet => "fd_prestat_get",
+    FdPrestatDirName => "fd_prestat_dir_name",
+    FdPwrite => "fd_pwrite",
+    FdRead => "fd_read",
+    FdReaddir => "fd_readdir",
+    FdRenumber => "fd_renumber",
+    FdSeek => "fd_seek",
+    FdSync => "fd_sync",
+    FdTell => "fd_tell",
+    FdWrite => "fd_write",
+    PathCreateDirectory => "path_create_directory",
+    PathFilestatGet => "path_filestat_get",
+    PathFilestatSetTimes => "path_filestat_set_times",
+    PathLink => "path_link",
+    PathOpen => "path_open",
+    PathReadlink => "path_readlink",
+    PathRemoveDirectory => "path_remove_directory",
+    PathRename => "path_rename",
+    PathSymlink => "path_symlink",
+    PathUnlinkFile => "path_unlink_file",
+    PollOneoff => "poll_oneoff",
+    ProcExit => "proc_exit",
+    ProcRaise => "proc_raise",
+    RandomGet => "random_get",
+    SchedYield => "sched_yield",
+    SockRecv => "sock_recv",
+    SockSend => "sock_send",
+    SockShutdown => "sock_shutdown",
+}
+
+impl WasiSyscall {
+    /// The syscall whose name is the closest to `name`, if it's close
+    /// enough for `name` to be a misspelling of it.
+    pub(crate) fn nearest(name: &str) -> Option<Self> {
+        let max_distance = (name.len() / 3).max(1);
+        WasiSyscall::ALL
+            .iter()
+            .map(|syscall| (edit_distance(name, syscall.name()), *syscall))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, syscall)| syscall)
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + (a != *b) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl fmt::Display for WasiSyscall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a denied syscall does when the guest calls it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeniedSyscallBehavior {
+    /// Return `__WASI_ENOTCAPABLE` without doing anything. Syscalls
+    /// without a return code, like `proc_exit`, just return.
+    Errno,
+    /// Trap with [`WasiError::SyscallDenied`](crate::WasiError::SyscallDenied).
+    Trap,
+}
+
+impl Default for DeniedSyscallBehavior {
+    fn default() -> Self {
+        Self::Errno
+    }
+}
+
+/// The syscalls denied to a guest, and the number of times it tried
+/// to call them.
+#[derive(Debug, Default)]
+pub(crate) struct SyscallPolicy {
+    pub(crate) denied: HashSet<WasiSyscall>,
+    pub(crate) behavior: DeniedSyscallBehavior,
+    pub(crate) denied_calls: HashMap<WasiSyscall, u64>,
+}
+
+impl SyscallPolicy {
+    pub(crate) fn record_denied_call(&mut self, syscall: WasiSyscall) {
+        *self.denied_calls.entry(syscall).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syscall_names() {
+        for syscall in WasiSyscall::ALL {
+            assert_eq!(WasiSyscall::from_name(syscall.name()), Some(*syscall));
+        }
+        assert_eq!(WasiSyscall::ALL.len(), 45);
+        assert_eq!(
+            WasiSyscall::from_name("path_open"),
+            Some(WasiSyscall::PathOpen)
+        );
+        assert_eq!(WasiSyscall::from_name("not_a_syscall"), None);
+    }
+
+    #[test]
+    fn nearest_syscall() {
+        assert_eq!(edit_distance("fd_wirte", "fd_write"), 2);
+        assert_eq!(edit_distance("", "fd_read"), 7);
+        assert_eq!(WasiSyscall::nearest("fd_wirte"), Some(WasiSyscall::FdWrite));
+        assert_eq!(
+            WasiSyscall::nearest("path_opne"),
+            Some(WasiSyscall::PathOpen)
+        );
+        assert_eq!(WasiSyscall::nearest("fd_red"), Some(WasiSyscall::FdRead));
+        assert_eq!(WasiSyscall::nearest("not_a_syscall"), None);
+    }
+}
@@ -7,6 +7,8 @@ use std::convert::TryInto;
 use std::{
     collections::VecDeque,
     io::{self, Read, Seek, Write},
+    sync::{Arc, Mutex},
+    task::Waker,
 };
 
 #[cfg(feature = "host-fs")]
@@ -229,74 +231,204 @@ pub(crate) fn poll(
 
 pub trait WasiPath {}
 
-/// For piping stdio. Stores all output / input in a byte-vector.
+/// An in-memory pipe, to stream bytes between the host and the
+/// `stdin`, `stdout` or `stderr` of a WASI program without going
+/// through the operating system.
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use wasmer_wasi::Pipe;
+/// let (mut reader, mut writer) = Pipe::new();
+/// writer.write_all(b"hello")?;
+///
+/// let mut buffer = String::new();
+/// reader.read_to_string(&mut buffer)?;
+/// assert_eq!(buffer, "hello");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Pipe;
+
+impl Pipe {
+    /// Creates a pipe, returning its two ends: the bytes written to
+    /// the [`WriteEnd`] are read from the [`ReadEnd`].
+    ///
+    /// To feed the `stdin` of a program, give it the read end with
+    /// [`WasiStateBuilder::stdin`] and write to the write end. To
+    /// collect its `stdout`, give it the write end with
+    /// [`WasiStateBuilder::stdout`] and read from the read end.
+    ///
+    /// [`WasiStateBuilder::stdin`]: crate::WasiStateBuilder::stdin
+    /// [`WasiStateBuilder::stdout`]: crate::WasiStateBuilder::stdout
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> (ReadEnd, WriteEnd) {
+        let buffer = Arc::new(Mutex::new(PipeBuffer::default()));
+        (
+            ReadEnd {
+                buffer: buffer.clone(),
+            },
+            WriteEnd { buffer },
+        )
+    }
+}
+
+/// The bytes of a [`Pipe`], shared by its two ends.
 #[derive(Debug, Default)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
-pub struct Pipe {
-    buffer: VecDeque<u8>,
+pub(crate) struct PipeBuffer {
+    /// The bytes written and not read yet.
+    pub(crate) bytes: VecDeque<u8>,
+    /// Whether the write end has been dropped or closed.
+    pub(crate) closed: bool,
+    /// The task waiting for bytes on the read end, woken by the next
+    /// write, see `AsyncReadEnd`.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub(crate) reader: Option<Waker>,
 }
 
-impl Pipe {
-    pub fn new() -> Self {
-        Self::default()
+impl PipeBuffer {
+    /// Wakes the task waiting for bytes, once the lock is released.
+    fn wake_reader(buffer: &Mutex<Self>) {
+        let reader = buffer.lock().unwrap().reader.take();
+        if let Some(reader) = reader {
+            reader.wake();
+        }
+    }
+}
+
+/// The end of a [`Pipe`] the bytes are read from.
+///
+/// Reading never blocks: it returns the bytes written so far, and 0
+/// once they have all been read, which a WASI program sees as the
+/// end of the file.
+#[derive(Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ReadEnd {
+    pub(crate) buffer: Arc<Mutex<PipeBuffer>>,
+}
+
+/// The end of a [`Pipe`] the bytes are written to.
+///
+/// The pipe is closed when this end is dropped.
+#[derive(Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct WriteEnd {
+    pub(crate) buffer: Arc<Mutex<PipeBuffer>>,
+}
+
+impl WriteEnd {
+    /// Closes the pipe: the read end reaches the end of the stream
+    /// once the bytes written so far are read, and the next writes
+    /// fail.
+    pub(crate) fn close(&mut self) {
+        self.buffer.lock().unwrap().closed = true;
+        PipeBuffer::wake_reader(&self.buffer);
     }
 }
 
-impl Read for Pipe {
+impl Drop for WriteEnd {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn wrong_end(operation: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("can not {} this end of a pipe", operation),
+    )
+}
+
+impl Read for ReadEnd {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let amt = std::cmp::min(buf.len(), self.buffer.len());
-        for (i, byte) in self.buffer.drain(..amt).enumerate() {
+        let mut buffer = self.buffer.lock().unwrap();
+        let amt = std::cmp::min(buf.len(), buffer.bytes.len());
+        for (i, byte) in buffer.bytes.drain(..amt).enumerate() {
             buf[i] = byte;
         }
         Ok(amt)
     }
 }
 
-impl Write for Pipe {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.buffer.extend(buf);
-        Ok(buf.len())
+impl Write for ReadEnd {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(wrong_end("write to"))
     }
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
 }
 
-impl Seek for Pipe {
-    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "can not seek in a pipe",
-        ))
+impl Read for WriteEnd {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(wrong_end("read from"))
     }
 }
 
-#[cfg_attr(feature = "enable-serde", typetag::serde)]
-impl VirtualFile for Pipe {
-    fn last_accessed(&self) -> u64 {
-        0
-    }
-    fn last_modified(&self) -> u64 {
-        0
-    }
-    fn created_time(&self) -> u64 {
-        0
-    }
-    fn size(&self) -> u64 {
-        self.buffer.len() as u64
-    }
-    fn set_len(&mut self, len: u64) -> Result<(), FsError> {
-        self.buffer.resize(len as usize, 0);
-        Ok(())
+impl Write for WriteEnd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+            if buffer.closed {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "can not write to a closed pipe",
+                ));
+            }
+            buffer.bytes.extend(buf);
+        }
+        // The guest writes from its own thread, `Waker`s are `Send`
+        // and can be woken from any thread.
+        PipeBuffer::wake_reader(&self.buffer);
+        Ok(buf.len())
     }
-    fn unlink(&mut self) -> Result<(), FsError> {
+    fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
-    fn bytes_available(&self) -> Result<usize, FsError> {
-        Ok(self.buffer.len())
-    }
 }
 
+macro_rules! impl_pipe_end {
+    ($end:ty) => {
+        impl Seek for $end {
+            fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "can not seek in a pipe",
+                ))
+            }
+        }
+
+        #[cfg_attr(feature = "enable-serde", typetag::serde)]
+        impl VirtualFile for $end {
+            fn last_accessed(&self) -> u64 {
+                0
+            }
+            fn last_modified(&self) -> u64 {
+                0
+            }
+            fn created_time(&self) -> u64 {
+                0
+            }
+            fn size(&self) -> u64 {
+                self.buffer.lock().unwrap().bytes.len() as u64
+            }
+            fn set_len(&mut self, len: u64) -> Result<(), FsError> {
+                self.buffer.lock().unwrap().bytes.resize(len as usize, 0);
+                Ok(())
+            }
+            fn unlink(&mut self) -> Result<(), FsError> {
+                Ok(())
+            }
+            fn bytes_available(&self) -> Result<usize, FsError> {
+                Ok(self.buffer.lock().unwrap().bytes.len())
+            }
+        }
+    };
+}
+
+impl_pipe_end!(ReadEnd);
+impl_pipe_end!(WriteEnd);
+
 /*
 TODO: Think about using this
 trait WasiFdBacking: std::fmt::Debug {
@@ -58,11 +58,27 @@ fn write_bytes_inner<T: Write>(
     for iov in iovs_arr_cell {
         let iov_inner = iov.get();
         let bytes = WasmPtr::<u8, Array>::new(iov_inner.buf).deref(memory, 0, iov_inner.buf_len)?;
-        write_loc
-            .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
-
-        // TODO: handle failure more accurately
+        let bytes = bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>();
+
+        // A writer that fills up, such as a bounded capture buffer,
+        // ends the write short; it only fails if nothing was written.
+        let mut written = 0;
+        while written < bytes.len() {
+            let error = match write_loc.write(&bytes[written..]) {
+                Ok(0) => __WASI_EIO,
+                Ok(n) => {
+                    written += n;
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => __WASI_EAGAIN,
+                Err(_) => __WASI_EIO,
+            };
+            if bytes_written == 0 && written == 0 {
+                return Err(error);
+            }
+            return Ok(bytes_written + written as u32);
+        }
         bytes_written += iov_inner.buf_len;
     }
     Ok(bytes_written)
@@ -93,15 +109,18 @@ fn read_bytes<T: Read>(
         let iov_inner = iov.get();
         raw_bytes.clear();
         raw_bytes.resize(iov_inner.buf_len as usize, 0);
-        bytes_read += reader.read(&mut raw_bytes).map_err(|_| __WASI_EIO)? as u32;
+        let read = reader.read(&mut raw_bytes).map_err(|_| __WASI_EIO)?;
+        bytes_read += read as u32;
         unsafe {
             memory
                 .uint8view()
-                .subarray(
-                    iov_inner.buf as u32,
-                    iov_inner.buf as u32 + iov_inner.buf_len as u32,
-                )
-                .copy_from(&raw_bytes);
+                .subarray(iov_inner.buf as u32, iov_inner.buf as u32 + read as u32)
+                .copy_from(&raw_bytes[..read]);
+        }
+        // A short read means no more data is available right now;
+        // reading the next buffers could block on a pipe.
+        if read < raw_bytes.len() {
+            break;
         }
     }
     Ok(bytes_read)
@@ -1704,6 +1723,7 @@ pub fn path_open(
     // - __WASI_O_TRUNC (truncate size to 0)
 
     let working_dir = wasi_try!(state.fs.get_fd(dirfd));
+    let working_dir_rights = working_dir.rights;
     let working_dir_rights_inheriting = working_dir.rights_inheriting;
 
     // ASSUMPTION: open rights apply recursively
@@ -1748,6 +1768,14 @@ pub fn path_open(
                     return __WASI_EEXIST;
                 }
                 let write_permission = adjusted_rights & __WASI_RIGHT_FD_WRITE != 0;
+                // asking for write access in a read-only directory is an error,
+                // not a silent downgrade to read-only access
+                if !write_permission
+                    && (fs_rights_base & __WASI_RIGHT_FD_WRITE != 0
+                        || o_flags & __WASI_O_TRUNC != 0)
+                {
+                    return __WASI_EACCES;
+                }
                 // append, truncate, and create all require the permission to write
                 let (append_permission, truncate_permission, create_permission) =
                     if write_permission {
@@ -1805,6 +1833,13 @@ pub fn path_open(
             if o_flags & __WASI_O_DIRECTORY != 0 {
                 return __WASI_ENOTDIR;
             }
+            // creating a file needs the right to create files, or to
+            // write in the directory
+            if !has_rights(working_dir_rights, __WASI_RIGHT_PATH_CREATE_FILE)
+                && !has_rights(working_dir_rights, __WASI_RIGHT_FD_WRITE)
+            {
+                return __WASI_EACCES;
+            }
             debug!("Creating file");
             // strip end file name
 
@@ -1957,6 +1992,9 @@ pub fn path_remove_directory(
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd), __WASI_EBADF);
+    if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_REMOVE_DIRECTORY) {
+        return __WASI_EACCES;
+    }
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
 
     let inode = wasi_try!(state.fs.get_inode_at_path(fd, &path_str, false));
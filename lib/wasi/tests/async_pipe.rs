@@ -0,0 +1,198 @@
+#![cfg(all(feature = "sys", feature = "async-io"))]
+
+use futures_io::{AsyncRead, AsyncWrite};
+use std::error::Error;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use wasmer::{Exports, Function, FunctionType, Instance, Module, Store};
+use wasmer_wasi::{Pipe, WasiState, WasiStateBuilder};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Prints `chunk 0\n`, `chunk 1\n` and `chunk 2\n`, calling
+/// `host.wait` after each of them.
+const SLOW_PRINTER: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "host" "wait" (func $wait))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "chunk 0\n")
+  (func (export "_start")
+    (local $i i32)
+    (loop $loop
+      (i32.store8 (i32.const 22) (i32.add (i32.const 48) (local.get $i)))
+      (i32.store (i32.const 0) (i32.const 16))
+      (i32.store (i32.const 4) (i32.const 8))
+      (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+      (call $wait)
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $loop (i32.lt_u (local.get $i) (i32.const 3))))))
+"#;
+
+/// Copies its `stdin` to its `stdout`.
+const ECHO: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "_start")
+    (block $done
+      (loop $loop
+        (i32.store (i32.const 0) (i32.const 64))
+        (i32.store (i32.const 4) (i32.const 1024))
+        (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (br_if $done (i32.eqz (i32.load (i32.const 8))))
+        (i32.store (i32.const 4) (i32.load (i32.const 8)))
+        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (br $loop)))))
+"#;
+
+/// Runs the `_start` function of `wat` on another thread, with the
+/// `host` imports, and closes its `stdout` once it returns.
+fn spawn_guest<F>(
+    wat: &'static str,
+    mut state: WasiStateBuilder,
+    host: F,
+) -> thread::JoinHandle<Result<()>>
+where
+    F: FnOnce(&Store) -> Exports + Send + 'static,
+{
+    thread::spawn(move || {
+        let store = Store::default();
+        let module = Module::new(&store, wat)?;
+        let mut wasi_env = state.finalize()?;
+        let mut import_object = wasi_env.import_object(&module)?;
+        import_object.register("host", host(&store));
+        let instance = Instance::new(&module, &import_object)?;
+        instance.exports.get_function("_start")?.call(&[])?;
+        wasi_env.state().fs.stdout_mut()?.take();
+        Ok(())
+    })
+}
+
+/// A future calling `f` until it's ready.
+struct PollFn<F>(F);
+
+impl<T, F> Future for PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T> + Unpin,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.0)(cx)
+    }
+}
+
+/// Runs a future to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn async_read_delivers_the_output_as_it_is_written() -> Result<()> {
+    let (stdout_reader, stdout_writer) = Pipe::new();
+    let (received, wait_received) = mpsc::channel::<()>();
+    let mut state = WasiState::new("slow-printer");
+    state.stdout(Box::new(stdout_writer));
+    let guest = spawn_guest(SLOW_PRINTER, state, move |store| {
+        let wait_received = Mutex::new(wait_received);
+        let mut host = Exports::new();
+        host.insert(
+            "wait",
+            Function::new(store, FunctionType::new(vec![], vec![]), move |_| {
+                // The next chunk is only printed once this one has
+                // been received.
+                wait_received.lock().unwrap().recv().unwrap();
+                Ok(vec![])
+            }),
+        );
+        host
+    });
+
+    let mut stdout = stdout_reader.into_async_read();
+    let chunks = block_on(async {
+        let mut chunks = Vec::new();
+        let mut buf = [0; 64];
+        loop {
+            let read = PollFn(|cx: &mut Context<'_>| Pin::new(&mut stdout).poll_read(cx, &mut buf))
+                .await?;
+            if read == 0 {
+                break;
+            }
+            chunks.push(String::from_utf8(buf[..read].to_vec()).unwrap());
+            received.send(()).unwrap();
+        }
+        io::Result::Ok(chunks)
+    })?;
+    guest.join().unwrap()?;
+
+    assert_eq!(chunks, ["chunk 0\n", "chunk 1\n", "chunk 2\n"]);
+    Ok(())
+}
+
+#[test]
+fn async_write_feeds_stdin_and_async_read_collects_the_echo() -> Result<()> {
+    let (stdin_reader, stdin_writer) = Pipe::new();
+    let (stdout_reader, stdout_writer) = Pipe::new();
+    let mut stdin = stdin_writer.into_async_write();
+    let mut stdout = stdout_reader.into_async_read();
+
+    let request = b"GET /echo\nhello from the host\n";
+    block_on(async {
+        let written =
+            PollFn(|cx: &mut Context<'_>| Pin::new(&mut stdin).poll_write(cx, request)).await?;
+        assert_eq!(written, request.len());
+        PollFn(|cx: &mut Context<'_>| Pin::new(&mut stdin).poll_close(cx)).await
+    })?;
+    let error = block_on(PollFn(|cx: &mut Context<'_>| {
+        Pin::new(&mut stdin).poll_write(cx, b"late")
+    }))
+    .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+
+    let mut state = WasiState::new("echo");
+    state
+        .stdin(Box::new(stdin_reader))
+        .stdout(Box::new(stdout_writer));
+    let guest = spawn_guest(ECHO, state, |_| Exports::new());
+
+    let response = block_on(async {
+        let mut response = Vec::new();
+        let mut buf = [0; 8];
+        loop {
+            let read = PollFn(|cx: &mut Context<'_>| Pin::new(&mut stdout).poll_read(cx, &mut buf))
+                .await?;
+            if read == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..read]);
+        }
+        io::Result::Ok(response)
+    })?;
+    guest.join().unwrap()?;
+
+    assert_eq!(response, request);
+    Ok(())
+}
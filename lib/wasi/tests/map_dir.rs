@@ -0,0 +1,106 @@
+#![cfg(all(feature = "sys", feature = "host-fs"))]
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use wasmer::{Instance, Memory, Module, Store};
+use wasmer_wasi::types::__WASI_ENOENT;
+use wasmer_wasi::WasiState;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Opens the path of `len` bytes at `path` from the root, and reads
+/// the file to the buffer at 1024, returning the number of bytes read,
+/// or the negated WASI error.
+const READER: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "read") (param $path i32) (param $len i32) (result i32)
+    (local $errno i32)
+    (local.set $errno
+      (call $path_open (i32.const 3) (i32.const 0) (local.get $path) (local.get $len)
+        (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 0)))
+    (if (local.get $errno) (then (return (i32.sub (i32.const 0) (local.get $errno)))))
+    (i32.store (i32.const 8) (i32.const 1024))
+    (i32.store (i32.const 12) (i32.const 1024))
+    (local.set $errno
+      (call $fd_read (i32.load (i32.const 0)) (i32.const 8) (i32.const 1) (i32.const 16)))
+    (if (local.get $errno) (then (return (i32.sub (i32.const 0) (local.get $errno)))))
+    (i32.load (i32.const 16))))
+"#;
+
+/// A directory of the host, removed when dropped.
+struct HostDir(PathBuf);
+
+impl HostDir {
+    fn new(name: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "wasmer-wasi-map-dir-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    fn write(&self, file: &str, contents: &str) -> Result<()> {
+        let path = self.0.join(file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl Drop for HostDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Reads the file at the guest `path`, or returns the WASI error.
+fn read(instance: &Instance, path: &str) -> Result<std::result::Result<String, u16>> {
+    let memory: &Memory = instance.exports.get_memory("memory")?;
+    let read = instance
+        .exports
+        .get_native_function::<(i32, i32), i32>("read")?;
+    unsafe { memory.data_unchecked_mut()[256..256 + path.len()].copy_from_slice(path.as_bytes()) };
+    let read = read.call(256, path.len() as i32)?;
+    if read < 0 {
+        return Ok(Err(-read as u16));
+    }
+    let contents = unsafe { &memory.data_unchecked()[1024..1024 + read as usize] };
+    Ok(Ok(String::from_utf8(contents.to_vec())?))
+}
+
+#[test]
+fn mapped_dirs_are_resolved_by_their_longest_prefix() -> Result<()> {
+    let dir_a = HostDir::new("a")?;
+    dir_a.write("a.txt", "in a")?;
+    dir_a.write("nested/which.txt", "in the nested dir of a")?;
+    let dir_b = HostDir::new("b")?;
+    dir_b.write("b.txt", "in b")?;
+    let dir_nested = HostDir::new("nested")?;
+    dir_nested.write("which.txt", "in the mapped nested dir")?;
+
+    let store = Store::default();
+    let module = Module::new(&store, READER)?;
+    let mut wasi_env = WasiState::new("map-dir")
+        .map_dir("/a", &dir_a.0)?
+        .map_dir("/b/", &dir_b.0)?
+        .map_dir("/a/nested", &dir_nested.0)?
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    assert_eq!(read(&instance, "a/a.txt")?, Ok("in a".to_string()));
+    assert_eq!(read(&instance, "b/b.txt")?, Ok("in b".to_string()));
+    assert_eq!(
+        read(&instance, "a/nested/which.txt")?,
+        Ok("in the mapped nested dir".to_string())
+    );
+    assert_eq!(read(&instance, "a/b.txt")?, Err(__WASI_ENOENT));
+    assert_eq!(read(&instance, "c/a.txt")?, Err(__WASI_ENOENT));
+    Ok(())
+}
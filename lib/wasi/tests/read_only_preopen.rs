@@ -0,0 +1,239 @@
+#![cfg(all(feature = "sys", feature = "host-fs"))]
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use wasmer::{Instance, Memory, Module, Store};
+use wasmer_wasi::types::{
+    __WASI_EACCES, __WASI_ESUCCESS, __WASI_O_CREAT, __WASI_O_TRUNC, __WASI_RIGHT_FD_READ,
+    __WASI_RIGHT_FD_WRITE,
+};
+use wasmer_wasi::WasiState;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Opens the path of `len` bytes at `path` from the directory `dir`,
+/// and renames or links paths between directories, returning the WASI
+/// error.
+const OPENER: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_link" (func $path_link (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "open") (param $dir i32) (param $path i32) (param $len i32) (param $oflags i32) (param $rights i64) (result i32)
+    (call $path_open (local.get $dir) (i32.const 0) (local.get $path) (local.get $len)
+      (local.get $oflags) (local.get $rights) (i64.const 0) (i32.const 0) (i32.const 0)))
+  (func (export "rename") (param $old_dir i32) (param $old_path i32) (param $old_len i32) (param $new_dir i32) (param $new_path i32) (param $new_len i32) (result i32)
+    (call $path_rename (local.get $old_dir) (local.get $old_path) (local.get $old_len)
+      (local.get $new_dir) (local.get $new_path) (local.get $new_len)))
+  (func (export "link") (param $old_dir i32) (param $old_path i32) (param $old_len i32) (param $new_dir i32) (param $new_path i32) (param $new_len i32) (result i32)
+    (call $path_link (local.get $old_dir) (i32.const 0) (local.get $old_path) (local.get $old_len)
+      (local.get $new_dir) (local.get $new_path) (local.get $new_len))))
+"#;
+
+/// The file descriptors of the preopened directories, after the
+/// standard streams and the root.
+const READ_ONLY_DIR: i32 = 4;
+const READ_WRITE_DIR: i32 = 5;
+const NO_CREATE_DIR: i32 = 6;
+
+/// A directory of the host, removed when dropped.
+struct HostDir(PathBuf);
+
+impl HostDir {
+    fn new(name: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "wasmer-wasi-read-only-preopen-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("existing.txt"), "existing")?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for HostDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Opens `path` in the directory `dir`, returning the WASI error.
+fn open(instance: &Instance, dir: i32, path: &str, oflags: u16, rights: u64) -> Result<u16> {
+    let memory: &Memory = instance.exports.get_memory("memory")?;
+    let open = instance
+        .exports
+        .get_native_function::<(i32, i32, i32, i32, i64), i32>("open")?;
+    unsafe { memory.data_unchecked_mut()[256..256 + path.len()].copy_from_slice(path.as_bytes()) };
+    let errno = open.call(dir, 256, path.len() as i32, oflags as i32, rights as i64)?;
+    Ok(errno as u16)
+}
+
+/// Renames or links, with the function `name`, `old_path` in the
+/// directory `old_dir` to `new_path` in `new_dir`, returning the WASI
+/// error.
+fn move_between(
+    instance: &Instance,
+    name: &str,
+    (old_dir, old_path): (i32, &str),
+    (new_dir, new_path): (i32, &str),
+) -> Result<u16> {
+    let memory: &Memory = instance.exports.get_memory("memory")?;
+    let function = instance
+        .exports
+        .get_native_function::<(i32, i32, i32, i32, i32, i32), i32>(name)?;
+    let new_offset = 256 + old_path.len();
+    unsafe {
+        let data = memory.data_unchecked_mut();
+        data[256..new_offset].copy_from_slice(old_path.as_bytes());
+        data[new_offset..new_offset + new_path.len()].copy_from_slice(new_path.as_bytes());
+    }
+    let errno = function.call(
+        old_dir,
+        256,
+        old_path.len() as i32,
+        new_dir,
+        new_offset as i32,
+        new_path.len() as i32,
+    )?;
+    Ok(errno as u16)
+}
+
+#[test]
+fn files_stay_in_read_only_preopens() -> Result<()> {
+    let read_only = HostDir::new("rename-read-only")?;
+    let read_write = HostDir::new("rename-read-write")?;
+
+    let store = Store::default();
+    let module = Module::new(&store, OPENER)?;
+    let mut wasi_env = WasiState::new("read-only-preopen")
+        .preopen(|p| p.directory(&read_only.0).read(true))?
+        .preopen(|p| {
+            p.directory(&read_write.0)
+                .read(true)
+                .write(true)
+                .create(true)
+        })?
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    // Moving a file out of the read-only preopen would remove it.
+    for name in ["rename", "link"] {
+        assert_eq!(
+            move_between(
+                &instance,
+                name,
+                (READ_ONLY_DIR, "existing.txt"),
+                (READ_WRITE_DIR, "moved.txt"),
+            )?,
+            __WASI_EACCES
+        );
+    }
+    assert!(read_only.0.join("existing.txt").exists());
+    assert!(!read_write.0.join("moved.txt").exists());
+
+    // Nor can a file be moved into it.
+    assert_eq!(
+        move_between(
+            &instance,
+            "rename",
+            (READ_WRITE_DIR, "existing.txt"),
+            (READ_ONLY_DIR, "moved.txt"),
+        )?,
+        __WASI_EACCES
+    );
+    assert!(!read_only.0.join("moved.txt").exists());
+
+    // The read-write preopen still renames its files.
+    assert_eq!(
+        move_between(
+            &instance,
+            "rename",
+            (READ_WRITE_DIR, "existing.txt"),
+            (READ_WRITE_DIR, "moved.txt"),
+        )?,
+        __WASI_ESUCCESS
+    );
+    assert!(read_write.0.join("moved.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn only_read_only_preopens_refuse_writes() -> Result<()> {
+    let read_only = HostDir::new("read-only")?;
+    let read_write = HostDir::new("read-write")?;
+    let no_create = HostDir::new("no-create")?;
+
+    let store = Store::default();
+    let module = Module::new(&store, OPENER)?;
+    let mut wasi_env = WasiState::new("read-only-preopen")
+        .preopen(|p| p.directory(&read_only.0).read(true))?
+        .preopen(|p| {
+            p.directory(&read_write.0)
+                .read(true)
+                .write(true)
+                .create(true)
+        })?
+        .preopen(|p| p.directory(&no_create.0).read(true).write(true))?
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    // The read-only preopen reads, and refuses everything else.
+    assert_eq!(
+        open(
+            &instance,
+            READ_ONLY_DIR,
+            "existing.txt",
+            0,
+            __WASI_RIGHT_FD_READ
+        )?,
+        __WASI_ESUCCESS
+    );
+    assert_eq!(
+        open(
+            &instance,
+            READ_ONLY_DIR,
+            "existing.txt",
+            0,
+            __WASI_RIGHT_FD_WRITE
+        )?,
+        __WASI_EACCES
+    );
+    assert_eq!(
+        open(&instance, READ_ONLY_DIR, "existing.txt", __WASI_O_TRUNC, 0)?,
+        __WASI_EACCES
+    );
+    assert_eq!(
+        open(&instance, READ_ONLY_DIR, "new.txt", __WASI_O_CREAT, 0)?,
+        __WASI_EACCES
+    );
+    assert!(!read_only.0.join("new.txt").exists());
+
+    // The other preopens are unaffected.
+    assert_eq!(
+        open(
+            &instance,
+            READ_WRITE_DIR,
+            "existing.txt",
+            0,
+            __WASI_RIGHT_FD_WRITE
+        )?,
+        __WASI_ESUCCESS
+    );
+    assert_eq!(
+        open(&instance, READ_WRITE_DIR, "new.txt", __WASI_O_CREAT, 0)?,
+        __WASI_ESUCCESS
+    );
+    assert!(read_write.0.join("new.txt").exists());
+    assert_eq!(
+        open(&instance, NO_CREATE_DIR, "new.txt", __WASI_O_CREAT, 0)?,
+        __WASI_ESUCCESS
+    );
+
+    Ok(())
+}
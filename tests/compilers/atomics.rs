@@ -0,0 +1,390 @@
+use anyhow::Result;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasmer::*;
+
+fn threads_store(mut config: crate::Config) -> Store {
+    let mut features = Features::default();
+    features.threads(true);
+    if config.compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    config.store()
+}
+
+/// A module importing and re-exporting a shared memory, and
+/// incrementing the `u32` at address 0 of it.
+const WAT: &str = r#"(module
+    (import "env" "memory" (memory 1 1 shared))
+    (export "memory" (memory 0))
+    (func (export "add") (param $times i32)
+        (loop $continue
+            (drop (i32.atomic.rmw.add (i32.const 0) (i32.const 1)))
+            (local.set $times (i32.sub (local.get $times) (i32.const 1)))
+            (br_if $continue (local.get $times))))
+    (func (export "load") (result i32)
+        (i32.atomic.load (i32.const 0))))"#;
+
+#[compiler_test(atomics)]
+fn atomics_shared_between_instances(config: crate::Config) -> Result<()> {
+    let store = threads_store(config);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let module = Module::new(&store, WAT)?;
+    let import_object = imports! {
+        "env" => {
+            "memory" => memory.clone(),
+        },
+    };
+    let first = Instance::new(&module, &import_object)?;
+    let second = Instance::new(&module, &import_object)?;
+
+    let add: NativeFunc<i32, ()> = first.exports.get_native_function("add")?;
+    let adding = thread::spawn(move || add.call(10_000));
+    let add: NativeFunc<i32, ()> = second.exports.get_native_function("add")?;
+    add.call(10_000)?;
+    adding.join().unwrap()?;
+
+    // The host sees the increments of both instances, and the
+    // instances see the ones of the host.
+    assert_eq!(memory.atomic_load_u32(0)?, 20_000);
+    assert_eq!(memory.atomic_add_u32(0, 5)?, 20_000);
+    let load: NativeFunc<(), i32> = first.exports.get_native_function("load")?;
+    assert_eq!(load.call()?, 20_005);
+    Ok(())
+}
+
+#[compiler_test(atomics)]
+fn atomics_wait_and_notify(config: crate::Config) -> Result<()> {
+    let store = threads_store(config);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let module = Module::new(&store, WAT)?;
+    let import_object = imports! {
+        "env" => {
+            "memory" => memory.clone(),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let exported = instance.exports.get_memory("memory")?.clone();
+
+    // The value differs from the expected one: no waiting.
+    assert_eq!(
+        memory.atomic_wait32(0, 1, None)?,
+        AtomicWaitResult::NotEqual
+    );
+    assert_eq!(
+        memory.atomic_wait32(0, 0, Some(Duration::from_millis(10)))?,
+        AtomicWaitResult::TimedOut
+    );
+
+    // The waiter waits on the host memory, the notification goes
+    // through the memory as seen by the instance. It is retried until
+    // the waiter has started waiting.
+    let waiter = thread::spawn(move || memory.atomic_wait32(0, 0, None));
+    let mut woken = 0;
+    while woken == 0 {
+        woken = exported.atomic_notify(0, 1)?;
+        thread::yield_now();
+    }
+    assert_eq!(woken, 1);
+    assert_eq!(waiter.join().unwrap()?, AtomicWaitResult::Woken);
+
+    // Nobody is waiting anymore.
+    assert_eq!(exported.atomic_notify(0, 1)?, 0);
+    Ok(())
+}
+
+/// A module importing a shared memory, and incrementing the `u32` at
+/// address 4 of it with plain loads and stores, under a spin lock at
+/// address 0.
+const LOCK_WAT: &str = r#"(module
+    (import "env" "memory" (memory 1 1 shared))
+    (func (export "locked_add") (param $times i32)
+        (loop $continue
+            (loop $acquire
+                (br_if $acquire
+                    (i32.atomic.rmw.cmpxchg (i32.const 0) (i32.const 0) (i32.const 1))))
+            (i32.store (i32.const 4) (i32.add (i32.load (i32.const 4)) (i32.const 1)))
+            (i32.atomic.store (i32.const 0) (i32.const 0))
+            (local.set $times (i32.sub (local.get $times) (i32.const 1)))
+            (br_if $continue (local.get $times)))))"#;
+
+#[compiler_test(atomics)]
+fn atomics_lock_shared_with_the_host(config: crate::Config) -> Result<()> {
+    let store = threads_store(config);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let module = Module::new(&store, LOCK_WAT)?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory.clone(),
+            },
+        },
+    )?;
+
+    let locked_add: NativeFunc<i32, ()> = instance.exports.get_native_function("locked_add")?;
+    let adding = thread::spawn(move || locked_add.call(10_000));
+    for _ in 0..10_000 {
+        while memory.atomic_compare_exchange_u32(0, 0, 1)? != 0 {
+            thread::yield_now();
+        }
+        let count = unsafe { &mut memory.data_unchecked_mut()[4..8] };
+        let incremented = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) + 1;
+        count.copy_from_slice(&incremented.to_le_bytes());
+        memory.atomic_store_u32(0, 0)?;
+    }
+    adding.join().unwrap()?;
+
+    // No increment was lost, by the guest or by the host.
+    assert_eq!(memory.atomic_load_u32(4)?, 20_000);
+    assert_eq!(memory.atomic_load_u32(0)?, 0);
+    Ok(())
+}
+
+#[compiler_test(atomics)]
+fn atomics_operations(config: crate::Config) -> Result<()> {
+    let store = threads_store(config);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+
+    assert_eq!(memory.atomic_xchg_u8(0, 0xf0)?, 0);
+    assert_eq!(memory.atomic_or_u8(0, 0x0f)?, 0xf0);
+    assert_eq!(memory.atomic_and_u8(0, 0x3c)?, 0xff);
+    assert_eq!(memory.atomic_xor_u8(0, 0xff)?, 0x3c);
+    assert_eq!(memory.atomic_load_u8(0)?, 0xc3);
+
+    memory.atomic_store_u16(2, 1)?;
+    assert_eq!(memory.atomic_sub_u16(2, 2)?, 1);
+    assert_eq!(memory.atomic_load_u16(2)?, u16::MAX);
+
+    assert_eq!(memory.atomic_compare_exchange_u32(4, 1, 2)?, 0);
+    assert_eq!(memory.atomic_load_u32(4)?, 0);
+    assert_eq!(memory.atomic_compare_exchange_u32(4, 0, 2)?, 0);
+    assert_eq!(memory.atomic_load_u32(4)?, 2);
+
+    assert_eq!(memory.atomic_add_u64(8, u64::MAX)?, 0);
+    assert_eq!(
+        memory.atomic_compare_exchange_u64(8, u64::MAX, 3)?,
+        u64::MAX
+    );
+    assert_eq!(memory.atomic_load_u64(8)?, 3);
+
+    // The accesses are checked.
+    assert_eq!(
+        memory.atomic_load_u32(2),
+        Err(MemoryAccessError::Unaligned { offset: 2, size: 4 })
+    );
+    assert_eq!(
+        memory.atomic_load_u64(0x1_0000),
+        Err(MemoryAccessError::OutOfBounds {
+            offset: 0x1_0000,
+            size: 8
+        })
+    );
+    let unshared = Memory::new(&store, MemoryType::new(1, None, false))?;
+    assert_eq!(
+        unshared.atomic_wait32(0, 0, None),
+        Err(MemoryAccessError::Unshared)
+    );
+    assert_eq!(unshared.atomic_notify(0, 1)?, 0);
+    Ok(())
+}
+
+/// A module doing atomic accesses to the address it's given.
+const UNALIGNED_WAT: &str = r#"(module
+    (memory 1 1 shared)
+    (data (i32.const 0) "\01\02\03\04\05\06\07\08")
+    (func (export "load8") (param i32) (result i32)
+        (i32.atomic.load8_u (local.get 0)))
+    (func (export "load32") (param i32) (result i32)
+        (i32.atomic.load (local.get 0)))
+    (func (export "add64") (param i32) (result i64)
+        (i64.atomic.rmw.add (local.get 0) (i64.const 0))))"#;
+
+#[compiler_test(atomics)]
+fn atomics_unaligned_trap(config: crate::Config) -> Result<()> {
+    let store = threads_store(config);
+    let module = Module::new(&store, UNALIGNED_WAT)?;
+    let instance = Instance::new(&module, &ImportObject::new())?;
+    let load8: NativeFunc<i32, i32> = instance.exports.get_native_function("load8")?;
+    let load32: NativeFunc<i32, i32> = instance.exports.get_native_function("load32")?;
+    let add64: NativeFunc<i32, i64> = instance.exports.get_native_function("add64")?;
+
+    assert_eq!(load8.call(3)?, 0x04);
+    assert_eq!(load32.call(4)?, 0x08070605);
+    assert_eq!(add64.call(0)?, 0x0807060504030201);
+
+    // The accesses must be aligned on their size, not only on 2 bytes.
+    for address in [1, 2, 3] {
+        let error = load32.call(address).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::UnalignedAtomic));
+    }
+    let error = add64.call(4).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnalignedAtomic));
+    Ok(())
+}
+
+// Unaligned atomic instructions fault on aarch64 even when they aren't
+// checked.
+#[cfg(target_arch = "x86_64")]
+#[compiler_test(atomics)]
+fn atomics_unaligned_relaxed(mut config: crate::Config) -> Result<()> {
+    config.set_trap_unaligned_atomics(false);
+    let store = threads_store(config);
+    let module = Module::new(&store, UNALIGNED_WAT)?;
+    let instance = Instance::new(&module, &ImportObject::new())?;
+    let load32: NativeFunc<i32, i32> = instance.exports.get_native_function("load32")?;
+
+    assert_eq!(load32.call(1)?, 0x05040302);
+    Ok(())
+}
+
+/// A module importing a shared memory, and waiting on or notifying
+/// the `u32` at the address it's given.
+const WAIT_WAT: &str = r#"(module
+    (import "env" "memory" (memory 1 1 shared))
+    (func (export "wait32") (param i32 i32 i64) (result i32)
+        (memory.atomic.wait32 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "wait64") (param i32 i64 i64) (result i32)
+        (memory.atomic.wait64 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "notify") (param i32 i32) (result i32)
+        (memory.atomic.notify (local.get 0) (local.get 1))))"#;
+
+#[compiler_test(atomics)]
+fn atomics_guest_wait_and_notify(config: crate::Config) -> Result<()> {
+    // LLVM doesn't compile `memory.atomic.wait` and `notify` yet.
+    if config.compiler == crate::Compiler::LLVM {
+        return Ok(());
+    }
+    let store = threads_store(config);
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let module = Module::new(&store, WAIT_WAT)?;
+    let import_object = imports! {
+        "env" => {
+            "memory" => memory.clone(),
+        },
+    };
+    let waiting = Instance::new(&module, &import_object)?;
+    let notifying = Instance::new(&module, &import_object)?;
+    let wait32: NativeFunc<(i32, i32, i64), i32> = waiting.exports.get_native_function("wait32")?;
+    let wait64: NativeFunc<(i32, i64, i64), i32> = waiting.exports.get_native_function("wait64")?;
+    let notify: NativeFunc<(i32, i32), i32> = notifying.exports.get_native_function("notify")?;
+
+    // The value differs from the expected one, or nobody notifies.
+    assert_eq!(wait32.call(0, 1, -1)?, 1);
+    assert_eq!(wait64.call(8, 1, -1)?, 1);
+    assert_eq!(wait32.call(0, 0, 10_000_000)?, 2);
+    assert_eq!(wait64.call(8, 0, 0)?, 2);
+
+    // Both instances wait and notify on the same memory, whichever
+    // of them is running the instructions. The notification is retried
+    // until the waiter has started waiting.
+    let waiter = thread::spawn(move || wait32.call(0, 0, -1));
+    let mut woken = 0;
+    while woken == 0 {
+        woken = notify.call(0, 1)?;
+        thread::yield_now();
+    }
+    assert_eq!(woken, 1);
+    assert_eq!(waiter.join().unwrap()?, 0);
+
+    // The host and the guests share the waiters.
+    let waiter = thread::spawn(move || memory.atomic_wait32(4, 0, None));
+    let mut woken = 0;
+    while woken == 0 {
+        woken = notify.call(4, 1)?;
+        thread::yield_now();
+    }
+    assert_eq!(woken, 1);
+    assert_eq!(waiter.join().unwrap()?, AtomicWaitResult::Woken);
+    assert_eq!(notify.call(4, 1)?, 0);
+
+    // The addresses are checked.
+    let error = notify.call(2, 1).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnalignedAtomic));
+    let error = notify.call(0x1_0000, 1).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    Ok(())
+}
+
+#[compiler_test(atomics)]
+fn atomics_guest_wait_is_interrupted(mut config: crate::Config) -> Result<()> {
+    if config.compiler == crate::Compiler::LLVM {
+        return Ok(());
+    }
+    config.set_epoch_interruption(true);
+    let mut features = Features::default();
+    features.threads(true);
+    if config.compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    let compiler_config = config.compiler_config(config.canonicalize_nans);
+    let engine = config.engine(compiler_config);
+    let store = Store::new_with_epoch(&*engine, 0);
+    let controller = engine.epoch_controller();
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let module = Module::new(&store, WAIT_WAT)?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory,
+            },
+        },
+    )?;
+    let wait32 = instance.exports.get_function("wait32")?;
+
+    // Nobody notifies, so only the timeout of the call ends the wait.
+    let start = Instant::now();
+    let error = wait32
+        .call_with_timeout(
+            &[Val::I32(0), Val::I32(0), Val::I64(-1)],
+            Duration::from_millis(50),
+        )
+        .unwrap_err();
+    assert!(error.is::<CallTimeout>());
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // Nor does the epoch deadline wait for the timeout of the wait.
+    store.set_epoch_deadline(1);
+    let watchdog = thread::spawn(move || {
+        for _ in 0..2 {
+            thread::sleep(Duration::from_millis(50));
+            controller.increment();
+        }
+    });
+    let start = Instant::now();
+    let error = wait32
+        .call(&[Val::I32(0), Val::I32(0), Val::I64(60_000_000_000)])
+        .unwrap_err();
+    watchdog.join().unwrap();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
+
+#[compiler_test(atomics)]
+fn atomics_guest_wait_on_unshared_memory(config: crate::Config) -> Result<()> {
+    if config.compiler == crate::Compiler::LLVM {
+        return Ok(());
+    }
+    let store = threads_store(config);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory 1)
+            (func (export "wait") (result i32)
+                (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const -1)))
+            (func (export "notify") (result i32)
+                (memory.atomic.notify (i32.const 0) (i32.const 1))))"#,
+    )?;
+    let instance = Instance::new(&module, &ImportObject::new())?;
+    let wait: NativeFunc<(), i32> = instance.exports.get_native_function("wait")?;
+    let notify: NativeFunc<(), i32> = instance.exports.get_native_function("notify")?;
+
+    let error = wait.call().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnsharedMemoryWait));
+    assert_eq!(notify.call()?, 0);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Branch protection only changes the code generated for aarch64,
+//! where it signs the return addresses.
+#![cfg(all(feature = "cranelift", feature = "universal", target_arch = "aarch64"))]
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_compiler_cranelift::{Cranelift, CraneliftBranchProtection};
+use wasmer_engine_universal::Universal;
+
+#[test]
+fn branch_protection_calls_and_traps() -> Result<()> {
+    let mut compiler = Cranelift::new();
+    compiler.branch_protection(CraneliftBranchProtection::Pac);
+    let store = Store::new(&Universal::new(compiler).engine());
+    let wat = r#"(module
+        (type $unary (func (param i32) (result i32)))
+        (import "env" "double" (func $double (param i32) (result i32)))
+        (table funcref (elem $double $increment $fail))
+        (func $increment (param i32) (result i32)
+            (i32.add (local.get 0) (i32.const 1)))
+        (func $fail (param i32) (result i32)
+            unreachable)
+        (func (export "call") (param i32 i32) (result i32)
+            (call_indirect (type $unary) (local.get 1) (local.get 0))))"#;
+    let module = Module::new(&store, wat)?;
+    let import_object = imports! {
+        "env" => {
+            "double" => Function::new_native(&store, |x: i32| x * 2),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let call = instance
+        .exports
+        .get_native_function::<(i32, i32), i32>("call")?;
+
+    assert_eq!(call.call(0, 21)?, 42);
+    assert_eq!(call.call(1, 41)?, 42);
+    // Unwinding goes through the frames with signed return addresses.
+    let error = call.call(2, 0).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+    assert_eq!(error.trace().len(), 2);
+    Ok(())
+}
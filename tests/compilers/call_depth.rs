@@ -0,0 +1,92 @@
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+
+/// `rec n` calls itself until `n` frames of it are active.
+const WAT: &str = r#"(module
+    (func $rec (export "rec") (param $n i32)
+        (if (i32.gt_u (local.get $n) (i32.const 1))
+            (then (call $rec (i32.sub (local.get $n) (i32.const 1))))))
+    (func (export "add") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1))))"#;
+
+fn call_depth_store(mut config: crate::Config) -> Store {
+    config.set_call_depth_limit(true);
+    config.store()
+}
+
+#[compiler_test(call_depth)]
+fn call_depth_traps_at_the_limit(config: crate::Config) -> Result<()> {
+    let store = call_depth_store(config);
+    store.max_call_depth(100);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let rec: NativeFunc<i32, ()> = instance.exports.get_native_function("rec")?;
+
+    rec.call(100)?;
+    let error = rec.call(101).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::CallDepthExceeded));
+
+    // The frames unwound by the trap don't count anymore.
+    rec.call(100)?;
+    let add: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("add")?;
+    assert_eq!(add.call(1, 2)?, 3);
+    Ok(())
+}
+
+#[compiler_test(call_depth)]
+fn call_depth_counts_frames_across_host_calls(config: crate::Config) -> Result<()> {
+    let store = call_depth_store(config);
+    store.max_call_depth(10);
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "env" "host" (func $host (param i32)))
+    (func (export "rec") (param $n i32)
+        (if (i32.gt_u (local.get $n) (i32.const 1))
+            (then (call $host (i32.sub (local.get $n) (i32.const 1)))))))"#,
+    )?;
+
+    #[derive(WasmerEnv, Clone, Default)]
+    struct Env {
+        instance: Arc<Mutex<Option<Instance>>>,
+    }
+    fn host(env: &Env, n: i32) -> Result<(), RuntimeError> {
+        let instance = env.instance.lock().unwrap().clone().unwrap();
+        let rec: NativeFunc<i32, ()> = instance.exports.get_native_function("rec").unwrap();
+        rec.call(n)
+    }
+    let env = Env::default();
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "host" => Function::new_native_with_env(&store, env.clone(), host),
+            },
+        },
+    )?;
+    *env.instance.lock().unwrap() = Some(instance.clone());
+    let rec: NativeFunc<i32, ()> = instance.exports.get_native_function("rec")?;
+
+    rec.call(10)?;
+    let error = rec.call(11).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::CallDepthExceeded));
+    rec.call(10)?;
+    Ok(())
+}
+
+#[compiler_test(call_depth)]
+fn call_depth_unlimited_by_default(config: crate::Config) -> Result<()> {
+    let store = call_depth_store(config);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let rec: NativeFunc<i32, ()> = instance.exports.get_native_function("rec")?;
+
+    rec.call(1000)?;
+    store.max_call_depth(999);
+    assert_eq!(
+        rec.call(1000).unwrap_err().to_trap(),
+        Some(TrapCode::CallDepthExceeded)
+    );
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use loupe::MemoryUsage;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use wasmer::*;
+
+/// Records the names of the threads compiling the functions.
+#[derive(Debug, Default, MemoryUsage)]
+struct CompileThreads {
+    #[loupe(skip)]
+    names: Mutex<HashSet<Option<String>>>,
+}
+
+#[derive(Debug)]
+struct PassThrough;
+
+impl ModuleMiddleware for CompileThreads {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        let name = thread::current().name().map(str::to_string);
+        self.names.lock().unwrap().insert(name);
+        Box::new(PassThrough)
+    }
+}
+
+impl FunctionMiddleware for PassThrough {}
+
+fn many_functions() -> String {
+    let functions = (0..64)
+        .map(|i| {
+            format!(
+                "(func (export \"f{}\") (param i32) (result i32) (i32.add (local.get 0) (i32.const {})))",
+                i, i
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("(module {})", functions.join("\n"))
+}
+
+fn compile_threads(mut config: crate::Config, threads: usize) -> Result<HashSet<Option<String>>> {
+    let recorder = Arc::new(CompileThreads::default());
+    config.set_middlewares(vec![recorder.clone()]);
+    config.set_compile_threads(threads);
+    let store = config.store();
+    let module = Module::new(&store, many_functions())?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let f63: NativeFunc<i32, i32> = instance.exports.get_native_function("f63")?;
+    assert_eq!(f63.call(1)?, 64);
+
+    let names = recorder.names.lock().unwrap().clone();
+    Ok(names)
+}
+
+#[compiler_test(compile_threads)]
+fn single_compile_thread(config: crate::Config) -> Result<()> {
+    // Every function is compiled by the only thread of the pool of
+    // the module, none by the global thread pool.
+    let names = compile_threads(config, 1)?;
+    assert_eq!(names.len(), 1);
+    let name = names.into_iter().next().unwrap().unwrap();
+    assert!(name.starts_with("wasmer-"), "{}", name);
+    Ok(())
+}
+
+#[compiler_test(compile_threads)]
+fn zero_compile_threads(config: crate::Config) -> Result<()> {
+    // Every function is compiled on the thread creating the module,
+    // without a pool.
+    let names = compile_threads(config, 0)?;
+    let caller = thread::current().name().map(str::to_string);
+    assert_eq!(names, vec![caller].into_iter().collect());
+    Ok(())
+}
+
+#[compiler_test(compile_threads)]
+fn bounded_compile_threads(config: crate::Config) -> Result<()> {
+    let names = compile_threads(config, 2)?;
+    assert!(!names.is_empty() && names.len() <= 2, "{:?}", names);
+    for name in names {
+        assert!(name.unwrap().starts_with("wasmer-"));
+    }
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use wasmer::{CompilerConfig, Engine as WasmerEngine, Features, ModuleMiddleware, Store};
+use wasmer::{CompilerConfig, Engine as WasmerEngine, Features, ModuleMiddleware, OptLevel, Store};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Compiler {
@@ -21,6 +21,17 @@ pub struct Config {
     pub features: Option<Features>,
     pub middlewares: Vec<Arc<dyn ModuleMiddleware>>,
     pub canonicalize_nans: bool,
+    pub deterministic: bool,
+    pub lazy_compilation: bool,
+    pub epoch_interruption: bool,
+    pub call_depth_limit: bool,
+    pub perf_diagnostics: bool,
+    pub function_timings: bool,
+    pub stack_maps: bool,
+    pub compile_threads: Option<usize>,
+    pub opt_level: Option<OptLevel>,
+    pub retain_wasm_bytes: bool,
+    pub trap_unaligned_atomics: bool,
 }
 
 impl Config {
@@ -30,6 +41,17 @@ impl Config {
             engine,
             features: None,
             canonicalize_nans: false,
+            deterministic: false,
+            lazy_compilation: false,
+            epoch_interruption: false,
+            call_depth_limit: false,
+            perf_diagnostics: false,
+            function_timings: false,
+            stack_maps: false,
+            compile_threads: None,
+            opt_level: None,
+            retain_wasm_bytes: false,
+            trap_unaligned_atomics: true,
             middlewares: vec![],
         }
     }
@@ -46,6 +68,50 @@ impl Config {
         self.canonicalize_nans = canonicalize_nans;
     }
 
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    pub fn set_lazy_compilation(&mut self, lazy_compilation: bool) {
+        self.lazy_compilation = lazy_compilation;
+    }
+
+    pub fn set_epoch_interruption(&mut self, epoch_interruption: bool) {
+        self.epoch_interruption = epoch_interruption;
+    }
+
+    pub fn set_call_depth_limit(&mut self, call_depth_limit: bool) {
+        self.call_depth_limit = call_depth_limit;
+    }
+
+    pub fn set_perf_diagnostics(&mut self, perf_diagnostics: bool) {
+        self.perf_diagnostics = perf_diagnostics;
+    }
+
+    pub fn set_function_timings(&mut self, function_timings: bool) {
+        self.function_timings = function_timings;
+    }
+
+    pub fn set_stack_maps(&mut self, stack_maps: bool) {
+        self.stack_maps = stack_maps;
+    }
+
+    pub fn set_compile_threads(&mut self, compile_threads: usize) {
+        self.compile_threads = Some(compile_threads);
+    }
+
+    pub fn set_opt_level(&mut self, opt_level: OptLevel) {
+        self.opt_level = Some(opt_level);
+    }
+
+    pub fn set_retain_wasm_bytes(&mut self, retain_wasm_bytes: bool) {
+        self.retain_wasm_bytes = retain_wasm_bytes;
+    }
+
+    pub fn set_trap_unaligned_atomics(&mut self, trap_unaligned_atomics: bool) {
+        self.trap_unaligned_atomics = trap_unaligned_atomics;
+    }
+
     pub fn store(&self) -> Store {
         let compiler_config = self.compiler_config(self.canonicalize_nans);
         let engine = self.engine(compiler_config);
@@ -63,7 +129,14 @@ impl Config {
         match &self.engine {
             #[cfg(feature = "dylib")]
             Engine::Dylib => {
-                let mut engine = wasmer_engine_dylib::Dylib::new(compiler_config);
+                let mut engine = wasmer_engine_dylib::Dylib::new(compiler_config)
+                    .deterministic(self.deterministic);
+                if let Some(threads) = self.compile_threads {
+                    engine = engine.num_compile_threads(threads);
+                }
+                if let Some(level) = self.opt_level {
+                    engine = engine.opt_level(level);
+                }
                 if let Some(ref features) = self.features {
                     engine = engine.features(features.clone())
                 }
@@ -71,7 +144,18 @@ impl Config {
             }
             #[cfg(feature = "universal")]
             Engine::Universal => {
-                let mut engine = wasmer_engine_universal::Universal::new(compiler_config);
+                let mut engine = wasmer_engine_universal::Universal::new(compiler_config)
+                    .deterministic(self.deterministic);
+                if let Some(threads) = self.compile_threads {
+                    engine = engine.num_compile_threads(threads);
+                }
+                if let Some(level) = self.opt_level {
+                    engine = engine.opt_level(level);
+                }
+                if self.lazy_compilation {
+                    engine = engine
+                        .compilation_strategy(wasmer_engine_universal::CompilationStrategy::Lazy);
+                }
                 if let Some(ref features) = self.features {
                     engine = engine.features(features.clone())
                 }
@@ -105,6 +189,13 @@ impl Config {
             Compiler::Cranelift => {
                 let mut compiler = wasmer_compiler_cranelift::Cranelift::new();
                 compiler.canonicalize_nans(canonicalize_nans);
+                compiler.epoch_interruption(self.epoch_interruption);
+                compiler.call_depth_limit(self.call_depth_limit);
+                compiler.enable_perf_diagnostics(self.perf_diagnostics);
+                compiler.enable_function_timings(self.function_timings);
+                compiler.enable_stack_maps(self.stack_maps);
+                compiler.retain_wasm_bytes(self.retain_wasm_bytes);
+                compiler.trap_unaligned_atomics(self.trap_unaligned_atomics);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
                 Box::new(compiler)
@@ -113,6 +204,13 @@ impl Config {
             Compiler::LLVM => {
                 let mut compiler = wasmer_compiler_llvm::LLVM::new();
                 compiler.canonicalize_nans(canonicalize_nans);
+                compiler.epoch_interruption(self.epoch_interruption);
+                compiler.call_depth_limit(self.call_depth_limit);
+                compiler.enable_perf_diagnostics(self.perf_diagnostics);
+                compiler.enable_function_timings(self.function_timings);
+                compiler.enable_stack_maps(self.stack_maps);
+                compiler.retain_wasm_bytes(self.retain_wasm_bytes);
+                compiler.trap_unaligned_atomics(self.trap_unaligned_atomics);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
                 Box::new(compiler)
@@ -121,6 +219,13 @@ impl Config {
             Compiler::Singlepass => {
                 let mut compiler = wasmer_compiler_singlepass::Singlepass::new();
                 compiler.canonicalize_nans(canonicalize_nans);
+                compiler.epoch_interruption(self.epoch_interruption);
+                compiler.call_depth_limit(self.call_depth_limit);
+                compiler.enable_perf_diagnostics(self.perf_diagnostics);
+                compiler.enable_function_timings(self.function_timings);
+                compiler.enable_stack_maps(self.stack_maps);
+                compiler.retain_wasm_bytes(self.retain_wasm_bytes);
+                compiler.trap_unaligned_atomics(self.trap_unaligned_atomics);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
                 Box::new(compiler)
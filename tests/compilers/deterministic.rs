@@ -1,5 +1,5 @@
 use anyhow::Result;
-use wasmer::{wat2wasm, Module};
+use wasmer::{imports, wat2wasm, Features, Instance, Module, Store};
 
 fn compile_and_compare(wasm: &[u8]) -> Result<()> {
     let store = Default::default();
@@ -42,3 +42,213 @@ fn deterministic_table() -> Result<()> {
 
     compile_and_compare(&wasm_bytes)
 }
+
+#[test]
+fn deterministic_across_engines() -> Result<()> {
+    // Names and passive segments end up in hash maps, which are
+    // serialized in the order of their keys.
+    let wasm_bytes = wat2wasm(
+        br#"
+(module
+  (table 3 funcref)
+  (memory 1)
+  (func $first (result i32) (i32.const 1))
+  (func $second (result i32) (i32.const 2))
+  (func $third (result i32) (i32.const 3))
+  (func $init
+    (table.init 0 (i32.const 0) (i32.const 0) (i32.const 3))
+    (memory.init 0 (i32.const 0) (i32.const 0) (i32.const 4))
+    (memory.init 1 (i32.const 4) (i32.const 0) (i32.const 4)))
+  (elem func $first $second $third)
+  (data "abcd")
+  (data "efgh"))
+"#,
+    )?;
+
+    // Each store comes with its own engine.
+    let compile = || -> Result<Vec<u8>> {
+        let store = Store::default();
+        Ok(Module::new(&store, &wasm_bytes)?.serialize()?)
+    };
+    let first = compile()?;
+    let second = compile()?;
+
+    assert!(first == second);
+
+    Ok(())
+}
+
+/// Functions producing NaNs, returning their bits.
+const NANS: &str = r#"(module
+    (func (export "div") (param f32 f32) (result i32)
+        (i32.reinterpret_f32 (f32.div (local.get 0) (local.get 1))))
+    (func (export "add") (param i32) (result i32)
+        (i32.reinterpret_f32 (f32.add (f32.reinterpret_i32 (local.get 0)) (f32.const 1))))
+    (func (export "promote") (param i32) (result i64)
+        (i64.reinterpret_f64 (f64.promote_f32 (f32.reinterpret_i32 (local.get 0)))))
+    (func (export "sqrt") (param f64) (result i64)
+        (i64.reinterpret_f64 (f64.sqrt (local.get 0))))
+    (func (export "min") (param i64 f64) (result i64)
+        (i64.reinterpret_f64 (f64.min (f64.reinterpret_i64 (local.get 0)) (local.get 1)))))"#;
+
+const CANONICAL_F32_NAN: i32 = 0x7fc0_0000;
+const CANONICAL_F64_NAN: i64 = 0x7ff8_0000_0000_0000;
+
+/// Calls the functions of [`NANS`] with NaNs of various signs and
+/// payloads, returning the bits of the results.
+fn nan_results(store: &Store) -> Result<Vec<i64>> {
+    let module = Module::new(store, NANS)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let div = instance
+        .exports
+        .get_native_function::<(f32, f32), i32>("div")?;
+    let add = instance.exports.get_native_function::<i32, i32>("add")?;
+    let promote = instance
+        .exports
+        .get_native_function::<i32, i64>("promote")?;
+    let sqrt = instance.exports.get_native_function::<f64, i64>("sqrt")?;
+    let min = instance
+        .exports
+        .get_native_function::<(i64, f64), i64>("min")?;
+
+    let mut results = vec![
+        div.call(0.0, 0.0)? as i64,
+        div.call(f32::INFINITY, f32::NEG_INFINITY)? as i64,
+        sqrt.call(-1.0)?,
+        min.call(0x7ff0_0000_0000_0001, 1.0)?,
+        min.call(-1, 1.0)?,
+    ];
+    for nan in [0x7fa0_0001, 0xffc0_0000u32 as i32, 0xff80_0001u32 as i32] {
+        results.push(add.call(nan)? as i64);
+        results.push(promote.call(nan)?);
+    }
+    Ok(results)
+}
+
+#[compiler_test(deterministic)]
+fn deterministic_mode_canonicalizes_nans(mut config: crate::Config) -> Result<()> {
+    config.set_deterministic(true);
+    let store = config.store();
+
+    let f32_nan = CANONICAL_F32_NAN as i64;
+    assert_eq!(
+        nan_results(&store)?,
+        vec![
+            f32_nan,
+            f32_nan,
+            CANONICAL_F64_NAN,
+            CANONICAL_F64_NAN,
+            CANONICAL_F64_NAN,
+            f32_nan,
+            CANONICAL_F64_NAN,
+            f32_nan,
+            CANONICAL_F64_NAN,
+            f32_nan,
+            CANONICAL_F64_NAN,
+        ]
+    );
+    Ok(())
+}
+
+/// Functions returning the NaNs they produce to the host as floats,
+/// without reinterpreting them.
+const FLOAT_NANS: &str = r#"(module
+    (func (export "add32") (param f32) (result f32)
+        (f32.add (local.get 0) (f32.const 0)))
+    (func (export "mul64") (param f64) (result f64)
+        (f64.mul (local.get 0) (f64.const 1))))"#;
+
+/// Functions producing NaNs in the lanes of vectors, returning the
+/// bits of the first and the last lanes.
+const SIMD_NANS: &str = r#"(module
+    (func (export "add32x4") (param i32) (result i64)
+        (local v128)
+        (local.set 1 (f32x4.add (i32x4.splat (local.get 0)) (f32x4.splat (f32.const 0))))
+        (i64.or
+            (i64.shl (i64.extend_i32_u (i32x4.extract_lane 0 (local.get 1))) (i64.const 32))
+            (i64.extend_i32_u (i32x4.extract_lane 3 (local.get 1)))))
+    (func (export "mul64x2") (param i64) (result i64 i64)
+        (local v128)
+        (local.set 1 (f64x2.mul (i64x2.splat (local.get 0)) (f64x2.splat (f64.const 1))))
+        (i64x2.extract_lane 0 (local.get 1))
+        (i64x2.extract_lane 1 (local.get 1))))"#;
+
+#[compiler_test(deterministic)]
+fn nan_canonicalization_quiets_signaling_nans(mut config: crate::Config) -> Result<()> {
+    config.set_nan_canonicalization(true);
+    let compiler = config.compiler.clone();
+    let store = config.store();
+
+    let signaling_f32_nan = 0x7fa0_0000u32;
+    let signaling_f64_nan = 0x7ff4_0000_0000_0000u64;
+
+    let module = Module::new(&store, FLOAT_NANS)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add32 = instance.exports.get_native_function::<f32, f32>("add32")?;
+    let mul64 = instance.exports.get_native_function::<f64, f64>("mul64")?;
+    assert_eq!(
+        add32.call(f32::from_bits(signaling_f32_nan))?.to_bits(),
+        CANONICAL_F32_NAN as u32
+    );
+    assert_eq!(
+        mul64.call(f64::from_bits(signaling_f64_nan))?.to_bits(),
+        CANONICAL_F64_NAN as u64
+    );
+
+    // Singlepass doesn't support SIMD.
+    if compiler != crate::Compiler::Singlepass {
+        let module = Module::new(&store, SIMD_NANS)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let add32x4 = instance
+            .exports
+            .get_native_function::<i32, i64>("add32x4")?;
+        let mul64x2 = instance
+            .exports
+            .get_native_function::<i64, (i64, i64)>("mul64x2")?;
+        let f32_nan = CANONICAL_F32_NAN as u32 as i64;
+        assert_eq!(
+            add32x4.call(signaling_f32_nan as i32)?,
+            (f32_nan << 32) | f32_nan
+        );
+        assert_eq!(
+            mul64x2.call(signaling_f64_nan as i64)?,
+            (CANONICAL_F64_NAN, CANONICAL_F64_NAN)
+        );
+    }
+    Ok(())
+}
+
+#[compiler_test(deterministic)]
+fn deterministic_mode_rejects_nondeterministic_features(mut config: crate::Config) -> Result<()> {
+    let mut features = Features::default();
+    features.threads(true);
+    if config.compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    config.set_deterministic(true);
+    let store = config.store();
+
+    let shared_memory = r#"(module
+        (memory 1 1 shared)
+        (func (export "load") (result i32)
+            (i32.atomic.load (i32.const 0))))"#;
+    assert!(Module::new(&store, shared_memory).is_err());
+    assert!(Module::new(&store, NANS).is_ok());
+    Ok(())
+}
+
+#[cfg(all(feature = "universal", feature = "singlepass", feature = "cranelift"))]
+#[test]
+fn deterministic_mode_agrees_across_compilers() -> Result<()> {
+    let results = |compiler| {
+        let mut config = crate::Config::new(crate::Engine::Universal, compiler);
+        config.set_deterministic(true);
+        nan_results(&config.store())
+    };
+    assert_eq!(
+        results(crate::Compiler::Singlepass)?,
+        results(crate::Compiler::Cranelift)?
+    );
+    Ok(())
+}
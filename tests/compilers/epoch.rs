@@ -0,0 +1,389 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasmer::*;
+
+fn epoch_store(mut config: crate::Config, initial_epoch: u64) -> (Store, EngineEpochController) {
+    config.set_epoch_interruption(true);
+    let compiler_config = config.compiler_config(config.canonicalize_nans);
+    let engine = config.engine(compiler_config);
+    let store = Store::new_with_epoch(&*engine, initial_epoch);
+    (store, engine.epoch_controller())
+}
+
+#[compiler_test(epoch)]
+fn epoch_deadline_interrupts_loop(config: crate::Config) -> Result<()> {
+    let (store, controller) = epoch_store(config, 0);
+    store.set_epoch_deadline(2);
+    let wat = r#"(module
+        (func (export "spin")
+           (loop
+            (br 0)
+           )
+        )
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let spin: NativeFunc<(), ()> = instance.exports.get_native_function("spin")?;
+
+    let watchdog = thread::spawn(move || {
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(50));
+            controller.increment();
+        }
+    });
+    let start = Instant::now();
+    let error = spin.call().unwrap_err();
+    watchdog.join().unwrap();
+
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn epoch_deadline_checked_on_function_entry(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 5);
+    let wat = r#"(module
+        (func (export "nop"))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let nop: NativeFunc<(), ()> = instance.exports.get_native_function("nop")?;
+
+    // Without a deadline, the function runs normally.
+    nop.call()?;
+
+    store.set_epoch_deadline(4);
+    let error = nop.call().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+
+    store.set_epoch_deadline(5);
+    nop.call()?;
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn epoch_deadline_relative_to_current_epoch(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 10);
+    let wat = r#"(module
+        (func (export "nop"))
+        (func (export "spin")
+           (loop
+            (br 0)
+           )
+        )
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let nop: NativeFunc<(), ()> = instance.exports.get_native_function("nop")?;
+    let spin: NativeFunc<(), ()> = instance.exports.get_native_function("spin")?;
+
+    // The deadline is one tick after the current epoch, 10.
+    store.set_epoch_deadline_after(1);
+    let engine = store.engine().clone();
+    engine.increment_epoch();
+    nop.call()?;
+    engine.increment_epoch();
+    let error = nop.call().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+
+    // A watchdog ticking until the infinite loop is interrupted.
+    store.set_epoch_deadline_after(2);
+    let done = Arc::new(AtomicBool::new(false));
+    let watchdog = thread::spawn({
+        let done = done.clone();
+        move || {
+            while !done.load(SeqCst) {
+                thread::sleep(Duration::from_millis(10));
+                engine.increment_epoch();
+            }
+        }
+    });
+    let start = Instant::now();
+    let error = spin.call().unwrap_err();
+    done.store(true, SeqCst);
+    watchdog.join().unwrap();
+
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn initial_epoch_is_per_store(config: crate::Config) -> Result<()> {
+    let (store, controller) = epoch_store(config, 10);
+    let other_store = Store::new(store.engine().as_ref());
+    let later_store = Store::new_with_epoch(store.engine().as_ref(), 20);
+
+    // The epoch of the engine, and of its other stores, isn't reset.
+    assert_eq!(controller.current(), 0);
+    assert_eq!(store.current_epoch(), 10);
+    assert_eq!(other_store.current_epoch(), 0);
+    assert_eq!(later_store.current_epoch(), 20);
+
+    controller.increment();
+    assert_eq!(controller.current(), 1);
+    assert_eq!(store.current_epoch(), 11);
+    assert_eq!(other_store.current_epoch(), 1);
+    assert_eq!(later_store.current_epoch(), 21);
+
+    // Each store traps against its own epoch.
+    let wat = r#"(module
+        (func (export "nop"))
+)"#;
+    for (store, traps) in [(&store, true), (&other_store, false)] {
+        store.set_epoch_deadline(5);
+        let module = Module::new(store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let nop: NativeFunc<(), ()> = instance.exports.get_native_function("nop")?;
+        assert_eq!(nop.call().is_err(), traps);
+    }
+    Ok(())
+}
+
+/// A module calling the host function `sleep`, then spinning forever.
+const SLEEP_WAT: &str = r#"(module
+    (import "env" "sleep" (func $sleep (param i32)))
+    (func (export "sleep") (param $ms i32)
+        (call $sleep (local.get $ms)))
+    (func (export "sleep_then_spin") (param $ms i32)
+        (call $sleep (local.get $ms))
+        (loop
+            (br 0))))"#;
+
+fn sleep_instance(store: &Store) -> Result<Instance> {
+    let module = Module::new(store, SLEEP_WAT)?;
+    let sleep = Function::new_native(store, |ms: i32| {
+        thread::sleep(Duration::from_millis(ms as u64))
+    });
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "sleep" => sleep,
+            },
+        },
+    )?;
+    Ok(instance)
+}
+
+#[compiler_test(epoch)]
+fn call_with_timeout_interrupts_loop(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 0);
+    let instance = sleep_instance(&store)?;
+    let sleep = instance.exports.get_function("sleep")?;
+    let sleep_then_spin = instance.exports.get_function("sleep_then_spin")?;
+
+    let start = Instant::now();
+    let error = sleep_then_spin
+        .call_with_timeout(&[Val::I32(0)], Duration::from_millis(50))
+        .unwrap_err();
+    assert!(error.is::<CallTimeout>());
+    assert_eq!(error.message(), "call timed out after 50 ms");
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // The deadline of the store and the epoch of the engine are left
+    // as they are.
+    assert_eq!(store.epoch_deadline(), u64::MAX);
+    assert_eq!(store.current_epoch(), 0);
+    sleep.call(&[Val::I32(0)])?;
+    sleep.call_with_timeout(&[Val::I32(0)], Duration::from_secs(10))?;
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_with_timeout_waits_for_host_functions(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 0);
+    let instance = sleep_instance(&store)?;
+    let sleep_then_spin = instance.exports.get_function("sleep_then_spin")?;
+
+    // The timeout expires while the host function sleeps, and the call
+    // is interrupted once it returns.
+    let start = Instant::now();
+    let error = sleep_then_spin
+        .call_with_timeout(&[Val::I32(200)], Duration::from_millis(20))
+        .unwrap_err();
+    assert!(error.is::<CallTimeout>());
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_with_timeout_nested(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 0);
+    let wat = r#"(module
+    (import "env" "reenter" (func $reenter (param i32)))
+    (func (export "reenter") (param $timeout_ms i32)
+        (call $reenter (local.get $timeout_ms)))
+    (func (export "spin")
+        (loop
+            (br 0))))"#;
+    let module = Module::new(&store, wat)?;
+    // Calls `spin` back with a timeout of the argument in ms.
+    let reenter = Function::new_with_caller(
+        &store,
+        FunctionType::new(vec![Type::I32], vec![]),
+        0u32,
+        |caller, _env, args| {
+            match caller.get_export("spin") {
+                Some(Extern::Function(spin)) => {
+                    let timeout = Duration::from_millis(args[0].unwrap_i32() as u64);
+                    spin.call_with_timeout(&[], timeout)?;
+                }
+                _ => panic!("`spin` is exported"),
+            }
+            Ok(vec![])
+        },
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "reenter" => reenter,
+            },
+        },
+    )?;
+    let reenter = instance.exports.get_function("reenter")?;
+
+    // The inner call is interrupted by its own timeout...
+    let start = Instant::now();
+    let error = reenter
+        .call_with_timeout(&[Val::I32(1)], Duration::from_secs(10))
+        .unwrap_err();
+    assert_eq!(error.message(), "call timed out after 1 ms");
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(store.epoch_deadline(), u64::MAX);
+
+    // ...or by the outer one.
+    let error = reenter
+        .call_with_timeout(&[Val::I32(10_000)], Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(error.message(), "call timed out after 50 ms");
+    assert_eq!(store.epoch_deadline(), u64::MAX);
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_with_timeout_keeps_deadline_set_during_call(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 0);
+    let module = Module::new(&store, SLEEP_WAT)?;
+    let deadline_store = store.clone();
+    let set_deadline = Function::new(
+        &store,
+        FunctionType::new(vec![Type::I32], vec![]),
+        move |_args| {
+            deadline_store.set_epoch_deadline(100);
+            Ok(vec![])
+        },
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "sleep" => set_deadline,
+            },
+        },
+    )?;
+    let sleep = instance.exports.get_function("sleep")?;
+
+    sleep.call_with_timeout(&[Val::I32(0)], Duration::from_secs(10))?;
+    assert_eq!(store.epoch_deadline(), 100);
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_with_timeout_needs_epoch_interruption(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let instance = sleep_instance(&store)?;
+    let sleep_then_spin = instance.exports.get_function("sleep_then_spin")?;
+
+    let error = sleep_then_spin
+        .call_with_timeout(&[Val::I32(0)], Duration::from_millis(1))
+        .unwrap_err();
+    assert!(!error.is::<CallTimeout>());
+    assert!(error.message().contains("epoch interruption"));
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_interruptible_interrupts_only_its_call(config: crate::Config) -> Result<()> {
+    let (store, _controller) = epoch_store(config, 0);
+    let wat = r#"(module
+    (import "env" "sleep" (func $sleep (param i32)))
+    (func (export "sleep_then_spin") (param $ms i32)
+        (call $sleep (local.get $ms))
+        (loop
+            (br 0)))
+    (func (export "count") (param $n i32)
+        (loop $continue
+            (br_if $continue
+                (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))))))"#;
+    let module = Module::new(&store, wat)?;
+    let sleep = Function::new_native(&store, |ms: i32| {
+        thread::sleep(Duration::from_millis(ms as u64))
+    });
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "sleep" => sleep,
+            },
+        },
+    )?;
+    let sleep_then_spin = instance.exports.get_function("sleep_then_spin")?.clone();
+    let count = instance.exports.get_function("count")?;
+
+    // The call is interrupted while its host function sleeps...
+    let interrupt = store.new_call_interrupt();
+    let start = Instant::now();
+    let interrupted = thread::spawn({
+        let interrupt = interrupt.clone();
+        move || sleep_then_spin.call_interruptible(&[Val::I32(200)], &interrupt)
+    });
+    thread::sleep(Duration::from_millis(20));
+    assert!(interrupt.interrupt());
+
+    // ...meanwhile, the other calls into the store keep running.
+    count.call(&[Val::I32(1000)])?;
+
+    let error = interrupted.join().unwrap().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineExceeded));
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert!(interrupt.is_interrupted());
+
+    // The interrupt is done with once its call has returned.
+    assert!(!interrupt.interrupt());
+    assert_eq!(store.epoch_deadline(), u64::MAX);
+    assert_eq!(store.current_epoch(), 0);
+    count.call(&[Val::I32(1000)])?;
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn call_interruptible_checks_its_interrupt(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let instance = sleep_instance(&store)?;
+    let sleep = instance.exports.get_function("sleep")?;
+    assert!(!sleep.is_interruptible());
+    let error = sleep
+        .call_interruptible(&[Val::I32(0)], &store.new_call_interrupt())
+        .unwrap_err();
+    assert!(error.message().contains("epoch interruption"));
+
+    let (epoch_store, _controller) = epoch_store(config, 0);
+    let instance = sleep_instance(&epoch_store)?;
+    let sleep = instance.exports.get_function("sleep")?;
+    assert!(sleep.is_interruptible());
+    let error = sleep
+        .call_interruptible(&[Val::I32(0)], &store.new_call_interrupt())
+        .unwrap_err();
+    assert_eq!(
+        error.message(),
+        "the call interrupt belongs to another store"
+    );
+    sleep.call_interruptible(&[Val::I32(0)], &epoch_store.new_call_interrupt())?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Compiling the modules on their first instantiation, with
+//! `CompilationStrategy::Lazy`.
+
+#![cfg(feature = "universal")]
+
+use anyhow::Result;
+use loupe::MemoryUsage;
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+
+const WAT: &str = r#"
+(module
+    (func $double (param i32) (result i32)
+        (i32.add (local.get 0) (local.get 0)))
+    (func (export "quadruple") (param i32) (result i32)
+        (call $double (call $double (local.get 0))))
+    (func (export "crash")
+        unreachable))
+"#;
+
+/// A store compiling lazily, with the universal engine only.
+fn lazy_store(mut config: crate::Config) -> Option<Store> {
+    if config.engine != crate::Engine::Universal {
+        return None;
+    }
+    config.set_lazy_compilation(true);
+    Some(config.store())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazy_modules_are_compiled_on_instantiation(config: crate::Config) -> Result<()> {
+    let store = match lazy_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let module = Module::new(&store, WAT)?;
+    // Nothing has been compiled yet.
+    assert!(Module::new_with_metrics(&store, WAT).is_err());
+
+    let instance = Instance::new(&module, &imports! {})?;
+    let quadruple = instance
+        .exports
+        .get_native_function::<i32, i32>("quadruple")?;
+    assert_eq!(quadruple.call(3)?, 12);
+
+    // The next instances share the compiled code.
+    let instance = Instance::new(&module, &imports! {})?;
+    let crash = instance.exports.get_function("crash")?;
+    let error = crash.call(&[]).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+    assert_eq!(error.trace().len(), 1);
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazy_modules_are_validated_up_front(config: crate::Config) -> Result<()> {
+    let store = match lazy_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let error = Module::new(
+        &store,
+        "(module (func (export \"f\") (result i32) (i64.const 1)))",
+    )
+    .unwrap_err();
+    assert!(matches!(error, CompileError::Validate(_)), "{:?}", error);
+    Ok(())
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazy_modules_serialize_compiled(config: crate::Config) -> Result<()> {
+    let store = match lazy_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let module = Module::new(&store, WAT)?;
+    let serialized = module.serialize()?;
+
+    for module in [module, unsafe { Module::deserialize(&store, &serialized)? }] {
+        let instance = Instance::new(&module, &imports! {})?;
+        let quadruple = instance
+            .exports
+            .get_native_function::<i32, i32>("quadruple")?;
+        assert_eq!(quadruple.call(5)?, 20);
+    }
+    Ok(())
+}
+
+/// Rejects the functions using `i32.add`, when they are compiled.
+#[derive(Debug, MemoryUsage)]
+struct RejectAdd;
+
+#[derive(Debug)]
+struct RejectAddFunction;
+
+impl ModuleMiddleware for RejectAdd {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(RejectAddFunction)
+    }
+}
+
+impl FunctionMiddleware for RejectAddFunction {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Operator::I32Add = operator {
+            return Err(MiddlewareError::new("reject_add", "i32.add is rejected"));
+        }
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+#[compiler_test(lazy_compilation)]
+fn lazy_compilation_errors_are_returned_by_instantiation(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![Arc::new(RejectAdd) as Arc<dyn ModuleMiddleware>]);
+    let store = match lazy_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let module = Module::new(&store, WAT)?;
+
+    for _ in 0..2 {
+        match Instance::new(&module, &imports! {}) {
+            Err(InstantiationError::Compile(error)) => {
+                assert!(
+                    error.to_string().contains("i32.add is rejected"),
+                    "{}",
+                    error
+                )
+            }
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("the module should fail to compile"),
+        }
+    }
+    assert!(module.serialize().is_err());
+    Ok(())
+}
@@ -5,15 +5,30 @@
 #[macro_use]
 extern crate compiler_test_derive;
 
+mod atomics;
+mod branch_protection;
+mod call_depth;
+mod compile_threads;
 mod config;
 mod deterministic;
+mod epoch;
 mod imports;
 mod issues;
+mod lazy_compilation;
+mod memory64;
 mod metering;
+mod metrics;
 mod middlewares;
+mod multi_memory;
 // mod multi_value_imports;
 mod native_functions;
+mod opt_level;
+mod perf_diagnostics;
+mod reentrancy;
 mod serialize;
+mod shared_code;
+mod singlepass_config;
+mod stack_maps;
 mod traps;
 mod wasi;
 mod wast;
@@ -0,0 +1,185 @@
+//! 64-bit memories, which only Cranelift compiles yet: the tests
+//! run with Cranelift, and check that the other compilers reject them.
+#![cfg(any(feature = "cranelift", feature = "singlepass", feature = "llvm"))]
+
+use anyhow::Result;
+use wasmer::*;
+
+/// The configurations of `compiler` with each of the engines enabled.
+fn configs(compiler: crate::Compiler) -> Vec<crate::Config> {
+    let engines = vec![
+        #[cfg(feature = "universal")]
+        crate::Engine::Universal,
+        #[cfg(feature = "dylib")]
+        crate::Engine::Dylib,
+    ];
+    engines
+        .into_iter()
+        .map(|engine| crate::Config::new(engine, compiler.clone()))
+        .collect()
+}
+
+fn memory64_store(mut config: crate::Config) -> Store {
+    let mut features = Features::default();
+    features.memory64(true);
+    if config.compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    config.store()
+}
+
+/// A module defining a 64-bit memory, accessed at `i64` addresses.
+const WAT: &str = r#"(module
+    (memory (export "memory") i64 1 3)
+    (data (i64.const 65530) "hello")
+    (func (export "load") (param i64) (result i32)
+        (i32.load8_u (local.get 0)))
+    (func (export "load_far") (param i64) (result i32)
+        (i32.load8_u offset=0x100000000 (local.get 0)))
+    (func (export "store") (param i64 i32)
+        (i32.store8 (local.get 0) (local.get 1)))
+    (func (export "size") (result i64)
+        (memory.size))
+    (func (export "grow") (param i64) (result i64)
+        (memory.grow (local.get 0))))"#;
+
+#[cfg(feature = "cranelift")]
+#[test]
+fn memory64_loads_stores_and_grows() -> Result<()> {
+    for config in configs(crate::Compiler::Cranelift) {
+        loads_stores_and_grows(config)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cranelift")]
+fn loads_stores_and_grows(config: crate::Config) -> Result<()> {
+    let store = memory64_store(config);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.ty(), MemoryType::new64(1, Some(3), false));
+    assert_eq!(memory.ty().index_type, IndexType::I64);
+
+    let load: NativeFunc<i64, i32> = instance.exports.get_native_function("load")?;
+    let load_far: NativeFunc<i64, i32> = instance.exports.get_native_function("load_far")?;
+    let store_byte: NativeFunc<(i64, i32), ()> = instance.exports.get_native_function("store")?;
+    let size: NativeFunc<(), i64> = instance.exports.get_native_function("size")?;
+    let grow: NativeFunc<i64, i64> = instance.exports.get_native_function("grow")?;
+
+    assert_eq!(load.call(65530)?, i32::from(b'h'));
+    assert_eq!(size.call()?, 1);
+
+    // The addresses aren't truncated to 32 bits, and the offsets
+    // don't wrap around.
+    for address in [65536, 0x1_0000_0000, -1] {
+        let error = load.call(address).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+    for address in [0, -1] {
+        let error = load_far.call(address).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+
+    assert_eq!(grow.call(1)?, 1);
+    assert_eq!(size.call()?, 2);
+    assert_eq!(memory.size(), Pages(2));
+    store_byte.call(65537, 42)?;
+    assert_eq!(memory.view::<u8>()[65537].get(), 42);
+    assert_eq!(memory.view::<u8>().subarray64(65536, 65538)[1].get(), 42);
+
+    // Past the maximum, including by more than `u32::MAX` pages.
+    assert_eq!(grow.call(2)?, -1);
+    assert_eq!(grow.call(0x1_0000_0001)?, -1);
+    assert_eq!(size.call()?, 2);
+
+    // The host grows the memory too.
+    assert_eq!(memory.grow(1)?, Pages(2));
+    assert_eq!(size.call()?, 3);
+    assert_eq!(memory.view::<u8>().len(), 3 * WASM_PAGE_SIZE);
+    Ok(())
+}
+
+#[cfg(feature = "singlepass")]
+#[test]
+fn memory64_rejected_by_singlepass() -> Result<()> {
+    for config in configs(crate::Compiler::Singlepass) {
+        rejected(config)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "llvm")]
+#[test]
+fn memory64_rejected_by_llvm() -> Result<()> {
+    for config in configs(crate::Compiler::LLVM) {
+        rejected(config)?;
+    }
+    Ok(())
+}
+
+/// Check that the compiler of `config` rejects the 64-bit memories,
+/// defined or imported, and only them.
+#[cfg(any(feature = "singlepass", feature = "llvm"))]
+fn rejected(config: crate::Config) -> Result<()> {
+    let store = memory64_store(config);
+    for wat in [WAT, r#"(module (import "env" "memory" (memory i64 1)))"#] {
+        let result = Module::new(&store, wat);
+        assert!(
+            matches!(&result, Err(CompileError::UnsupportedFeature(feature)) if feature == "64-bit memories"),
+            "{:?}",
+            result.map(|_| ())
+        );
+    }
+    assert!(Module::new(&store, "(module (memory 1))").is_ok());
+    Ok(())
+}
+
+#[cfg(feature = "cranelift")]
+#[test]
+fn memory64_imports() -> Result<()> {
+    for config in configs(crate::Compiler::Cranelift) {
+        imports(config)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cranelift")]
+fn imports(config: crate::Config) -> Result<()> {
+    let store = memory64_store(config);
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "env" "memory" (memory i64 1))
+            (data (i64.const 4) "hi")
+            (func (export "load") (param i64) (result i32)
+                (i32.load8_u (local.get 0))))"#,
+    )?;
+
+    let memory = Memory::new(&store, MemoryType::new64(1, None, false))?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory.clone(),
+            },
+        },
+    )?;
+    let load: NativeFunc<i64, i32> = instance.exports.get_native_function("load")?;
+    assert_eq!(load.call(5)?, i32::from(b'i'));
+    assert_eq!(memory.view::<u8>()[4].get(), b'h');
+
+    // The index types of the memories must match.
+    let memory32 = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let result = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory32,
+            },
+        },
+    );
+    assert!(matches!(result, Err(InstantiationError::Link(_))));
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use wasmer::*;
+
+const WAT: &str = r#"
+(module
+    (func $add_one (export "add_one") (param i32) (result i32)
+        (i32.add (local.get 0) (i32.const 1)))
+    (func (export "add_two") (param i32) (result i32)
+        (call $add_one (call $add_one (local.get 0)))))
+"#;
+
+#[compiler_test(metrics)]
+fn compilation_metrics(config: crate::Config) -> Result<()> {
+    // Metrics are reported by the universal engine only.
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = config.store();
+
+    let (_module, metrics) = Module::new_with_metrics(&store, WAT)?;
+    assert_eq!(metrics.function_count, 2);
+    assert!(metrics.total_code_bytes > 0);
+    // The calls between the two functions are relocated.
+    assert!(metrics.relocation_count > 0);
+    assert!(metrics.compile_time > std::time::Duration::default());
+    assert_eq!(metrics.function_compile_times, None);
+
+    let (_module, metrics) = Module::new_with_metrics(&store, "(module)")?;
+    assert_eq!(metrics.function_count, 0);
+    assert_eq!(metrics.relocation_count, 0);
+
+    Ok(())
+}
+
+#[compiler_test(metrics)]
+fn function_compile_times(mut config: crate::Config) -> Result<()> {
+    // Functions are timed by Singlepass and Cranelift only.
+    if config.compiler == crate::Compiler::LLVM || config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    config.set_function_timings(true);
+    let store = config.store();
+
+    let (_module, metrics) = Module::new_with_metrics(&store, WAT)?;
+    let times = metrics.function_compile_times.unwrap();
+    assert_eq!(times.len(), 2);
+    // The functions may be compiled in parallel: only each of them
+    // fits in the compilation of the module.
+    for time in times.values() {
+        assert!(*time <= metrics.compile_time);
+    }
+
+    Ok(())
+}
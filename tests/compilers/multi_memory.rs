@@ -0,0 +1,202 @@
+use anyhow::Result;
+use wasmer::*;
+
+fn multi_memory_store(mut config: crate::Config) -> Store {
+    let mut features = Features::default();
+    features.multi_memory(true);
+    if config.compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    config.store()
+}
+
+/// A module importing a memory, and defining two more of different
+/// sizes, so that the local memories don't start at index 0.
+const WAT: &str = r#"(module
+    (import "env" "memory" (memory $imported 1))
+    (memory $small (export "small") 1)
+    (memory $large (export "large") 2)
+    (data (memory $large) (i32.const 65536) "hello")
+    (func (export "load_imported") (param i32) (result i32)
+        (i32.load8_u $imported (local.get 0)))
+    (func (export "load_small") (param i32) (result i32)
+        (i32.load8_u $small (local.get 0)))
+    (func (export "load_large") (param i32) (result i32)
+        (i32.load8_u $large offset=1 (i32.sub (local.get 0) (i32.const 1))))
+    (func (export "store_small") (param i32 i32)
+        (i32.store8 $small (local.get 0) (local.get 1)))
+    (func (export "copy_large_to_small") (param i32 i32 i32)
+        (memory.copy $small $large (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy_small_to_large") (param i32 i32 i32)
+        (memory.copy $large $small (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy_small_to_imported") (param i32 i32 i32)
+        (memory.copy $imported $small (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy_within_large") (param i32 i32 i32)
+        (memory.copy $large $large (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "fill_large") (param i32 i32 i32)
+        (memory.fill $large (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "size_large") (result i32)
+        (memory.size $large))
+    (func (export "grow_small") (param i32) (result i32)
+        (memory.grow $small (local.get 0))))"#;
+
+/// Reads `len` bytes at `offset` of `memory`.
+fn read(memory: &Memory, offset: usize, len: usize) -> Vec<u8> {
+    memory.view::<u8>()[offset..offset + len]
+        .iter()
+        .map(|cell| cell.get())
+        .collect()
+}
+
+#[compiler_test(multi_memory)]
+fn multiple_memories(config: crate::Config) -> Result<()> {
+    let compiler = config.compiler.clone();
+    let store = multi_memory_store(config);
+    if compiler == crate::Compiler::LLVM {
+        // LLVM only compiles a single memory yet.
+        let result = Module::new(&store, WAT);
+        assert!(
+            matches!(result, Err(CompileError::UnsupportedFeature(_))),
+            "{:?}",
+            result.map(|_| ())
+        );
+        assert!(Module::new(&store, "(module (memory 1))").is_ok());
+        return Ok(());
+    }
+    let module = Module::new(&store, WAT)?;
+    let imported = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => imported.clone(),
+            },
+        },
+    )?;
+    let small = instance.exports.get_memory("small")?;
+    let large = instance.exports.get_memory("large")?;
+    assert_eq!(small.size(), Pages(1));
+    assert_eq!(large.size(), Pages(2));
+    assert_eq!(read(large, 65536, 5), b"hello");
+
+    let load = |name: &str, addr: i32| -> Result<i32> {
+        let load = instance.exports.get_native_function::<i32, i32>(name)?;
+        Ok(load.call(addr)?)
+    };
+    let copy = |name: &str, dst: i32, src: i32, len: i32| -> Result<(), RuntimeError> {
+        let copy = instance
+            .exports
+            .get_native_function::<(i32, i32, i32), ()>(name)
+            .unwrap();
+        copy.call(dst, src, len)
+    };
+
+    // Loads and stores go to the memory of their `memarg`.
+    assert_eq!(load("load_large", 65536)?, i32::from(b'h'));
+    assert_eq!(load("load_small", 65536 - 1)?, 0);
+    instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store_small")?
+        .call(7, 42)?;
+    assert_eq!(load("load_small", 7)?, 42);
+    assert_eq!(load("load_imported", 7)?, 0);
+    assert_eq!(read(large, 7, 1), [0]);
+
+    // The source range is checked against the source memory, and the
+    // destination range against the destination memory.
+    copy("copy_large_to_small", 0, 65536, 5)?;
+    assert_eq!(read(small, 0, 5), b"hello");
+    copy("copy_small_to_imported", 10, 0, 5)?;
+    assert_eq!(read(&imported, 10, 5), b"hello");
+    assert_eq!(read(large, 0, 5), [0; 5]);
+    copy("copy_small_to_large", 100_000, 0, 5)?;
+    assert_eq!(read(large, 100_000, 5), b"hello");
+    copy("copy_large_to_small", 0, 100_000, 2)?;
+    assert!(copy("copy_large_to_small", 65534, 65536, 5).is_err());
+    assert!(copy("copy_small_to_large", 0, 65534, 5).is_err());
+    assert!(copy("copy_large_to_small", 0, 131070, 5).is_err());
+    assert!(copy("copy_small_to_imported", 65534, 0, 5).is_err());
+
+    // Overlapping copies within a memory still work.
+    copy("copy_within_large", 65537, 65536, 4)?;
+    assert_eq!(read(large, 65536, 5), b"hhell");
+
+    instance
+        .exports
+        .get_native_function::<(i32, i32, i32), ()>("fill_large")?
+        .call(0, 0x2a, 3)?;
+    assert_eq!(read(large, 0, 4), [0x2a, 0x2a, 0x2a, 0]);
+    assert_eq!(read(small, 0, 1), b"h");
+
+    let grow_small = instance
+        .exports
+        .get_native_function::<i32, i32>("grow_small")?;
+    assert_eq!(grow_small.call(1)?, 1);
+    assert_eq!(small.size(), Pages(2));
+    assert_eq!(imported.size(), Pages(1));
+    let size_large = instance
+        .exports
+        .get_native_function::<(), i32>("size_large")?;
+    assert_eq!(size_large.call()?, 2);
+
+    Ok(())
+}
+
+#[compiler_test(multi_memory)]
+fn multiple_memories_need_the_feature(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let result = Module::new(&store, "(module (memory 1) (memory 2))");
+    assert!(matches!(result, Err(CompileError::Validate(_))));
+
+    Ok(())
+}
+
+#[compiler_test(multi_memory)]
+fn memory64_mixed_with_memory32(mut config: crate::Config) -> Result<()> {
+    let compiler = config.compiler.clone();
+    let mut features = Features::default();
+    features.multi_memory(true).memory64(true);
+    if compiler == crate::Compiler::Singlepass {
+        features.multi_value(false);
+    }
+    config.set_features(features);
+    let store = config.store();
+
+    // Each memory is bounds-checked with its own index type.
+    let wat = r#"(module
+        (memory $small 1)
+        (memory $large i64 1)
+        (func (export "load32") (param i32) (result i32)
+            (i32.load8_u $small (local.get 0)))
+        (func (export "load64") (param i64) (result i32)
+            (i32.load8_u $large (local.get 0))))"#;
+    if compiler != crate::Compiler::Cranelift {
+        // Only Cranelift compiles 64-bit memories yet.
+        let result = Module::new(&store, wat);
+        assert!(
+            matches!(result, Err(CompileError::UnsupportedFeature(_))),
+            "{:?}",
+            result.map(|_| ())
+        );
+        if compiler == crate::Compiler::Singlepass {
+            assert!(Module::new(&store, "(module (memory 1) (memory 2))").is_ok());
+        }
+        return Ok(());
+    }
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let load32: NativeFunc<i32, i32> = instance.exports.get_native_function("load32")?;
+    let load64: NativeFunc<i64, i32> = instance.exports.get_native_function("load64")?;
+
+    assert_eq!(load32.call(0xffff)?, 0);
+    assert_eq!(load64.call(0xffff)?, 0);
+    let error = load32.call(0x1_0000).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    for address in [0x1_0000, 0x1_0000_0000] {
+        let error = load64.call(address).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    }
+
+    Ok(())
+}
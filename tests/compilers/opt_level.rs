@@ -0,0 +1,58 @@
+use anyhow::Result;
+use wasmer::*;
+
+/// A module with loops the compilers unroll or vectorize when
+/// optimizing for speed.
+const WAT: &str = r#"
+    (module
+        (memory 1)
+        (func (export "sum") (param $len i32) (result i32)
+            (local $i i32) (local $sum i32)
+            (block $done
+                (loop $loop
+                    (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                    (local.set $sum
+                        (i32.add (local.get $sum) (i32.load (i32.shl (local.get $i) (i32.const 2)))))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $loop)))
+            (local.get $sum))
+        (func (export "fill") (param $len i32) (param $value i32)
+            (local $i i32)
+            (block $done
+                (loop $loop
+                    (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                    (i32.store (i32.shl (local.get $i) (i32.const 2))
+                        (i32.mul (local.get $value) (local.get $i)))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $loop)))))
+"#;
+
+/// Compile and run [`WAT`] at `level`, and return the length of the
+/// serialized module.
+fn serialized_len(mut config: crate::Config, level: OptLevel) -> Result<usize> {
+    config.set_opt_level(level);
+    let store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let fill: NativeFunc<(i32, i32), ()> = instance.exports.get_native_function("fill")?;
+    let sum: NativeFunc<i32, i32> = instance.exports.get_native_function("sum")?;
+    fill.call(100, 2)?;
+    assert_eq!(sum.call(100)?, 9900);
+    Ok(module.serialize()?.len())
+}
+
+#[compiler_test(opt_level)]
+fn opt_level_size(config: crate::Config) -> Result<()> {
+    let speed = serialized_len(config.clone(), OptLevel::Speed)?;
+    let size = serialized_len(config.clone(), OptLevel::Size)?;
+    serialized_len(config.clone(), OptLevel::None)?;
+    if config.compiler == crate::Compiler::LLVM {
+        // The vectorized and unrolled loops are much larger.
+        assert!(size < speed, "{} >= {}", size, speed);
+    } else {
+        // Singlepass has no optimization levels, Cranelift only a
+        // few transformations reducing the size.
+        assert!(size <= speed, "{} > {}", size, speed);
+    }
+    Ok(())
+}
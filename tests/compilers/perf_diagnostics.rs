@@ -0,0 +1,113 @@
+use anyhow::Result;
+use wasmer::*;
+
+fn perf_diagnostics_store(mut config: crate::Config) -> Store {
+    config.set_perf_diagnostics(true);
+    config.store()
+}
+
+fn codes(module: &Module) -> Vec<(u32, PerfDiagnosticCode)> {
+    module
+        .perf_diagnostics()
+        .iter()
+        .map(|diagnostic| (diagnostic.func_index.as_u32(), diagnostic.code))
+        .collect()
+}
+
+#[compiler_test(perf_diagnostics)]
+fn singlepass_perf_diagnostics(config: crate::Config) -> Result<()> {
+    // Diagnostics are kept by the universal engine only.
+    if config.compiler != crate::Compiler::Singlepass || config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = perf_diagnostics_store(config);
+
+    // Keeps 100 values alive on the value stack, more than there are
+    // registers.
+    let mut spills = String::new();
+    for _ in 0..100 {
+        spills.push_str("(i32.add (local.get 0) (local.get 0))\n");
+    }
+    for _ in 0..99 {
+        spills.push_str("i32.add\n");
+    }
+    // 32 KiB of locals.
+    let large_frame = " i64".repeat(4096);
+    // 20 of the 42 operators are `f64.min`.
+    let softfloat = "(f64.min (local.get 1))\n".repeat(20);
+
+    let wat = format!(
+        r#"(module
+  (func (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1)))
+  (func (param i32) (result i32)
+    {})
+  (func (local{})
+    (local.set 4000 (i64.const 1)))
+  (func (param f64 f64) (result f64)
+    (local.get 0)
+    {}))"#,
+        spills, large_frame, softfloat
+    );
+    let module = Module::new(&store, wat)?;
+
+    assert_eq!(
+        codes(&module),
+        vec![
+            (1, PerfDiagnosticCode::SpilledValues),
+            (2, PerfDiagnosticCode::LargeStackFrame),
+            (3, PerfDiagnosticCode::SoftfloatDensity),
+        ]
+    );
+    assert!(module.perf_diagnostics()[0].weight > 32);
+    assert!(module.perf_diagnostics()[1].weight >= 32 * 1024);
+    assert_eq!(module.perf_diagnostics()[2].weight, 20);
+    Ok(())
+}
+
+#[compiler_test(perf_diagnostics)]
+fn cranelift_perf_diagnostics(config: crate::Config) -> Result<()> {
+    // Diagnostics are kept by the universal engine only.
+    if config.compiler != crate::Compiler::Cranelift || config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let store = perf_diagnostics_store(config);
+    let memory_style = store
+        .tunables()
+        .memory_style(&MemoryType::new(1, None, false));
+    if memory_style.offset_guard_size() == 0 {
+        return Ok(());
+    }
+
+    let wat = format!(
+        r#"(module
+  (memory 1)
+  (func (param i32) (result i32)
+    (i32.load offset=16 (local.get 0)))
+  (func (param i32) (result i32)
+    (i32.load offset={} (local.get 0))))"#,
+        memory_style.offset_guard_size()
+    );
+    let module = Module::new(&store, wat)?;
+
+    assert_eq!(
+        codes(&module),
+        vec![(1, PerfDiagnosticCode::BoundsCheckNotElided)]
+    );
+    assert_eq!(module.perf_diagnostics()[0].weight, 1);
+    Ok(())
+}
+
+#[compiler_test(perf_diagnostics)]
+fn perf_diagnostics_disabled_by_default(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+  (memory 1)
+  (func (param i32) (result i32)
+    (i32.load offset=0x80000000 (local.get 0)))
+  (func (local i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64 i64)))"#;
+    let module = Module::new(&store, wat)?;
+
+    assert!(module.perf_diagnostics().is_empty());
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use wasmer::*;
+
+/// `ping(n)` calls the host function `pong(n - 1)`, which calls back
+/// into `ping`, until `n` reaches zero.
+fn ping_pong_wat(bottom: &str) -> String {
+    format!(
+        r#"(module
+  (import "env" "pong" (func $pong (param i32) (result i32)))
+  (func (export "ping") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then {})
+      (else
+        (i32.add
+          (call $pong (i32.sub (local.get 0) (i32.const 1)))
+          (i32.const 1)))))
+  (func (export "answer") (result i32)
+    (i32.const 42)))"#,
+        bottom
+    )
+}
+
+#[derive(WasmerEnv, Clone, Default)]
+struct Env {
+    instance: Arc<Mutex<Option<Instance>>>,
+    max_depth: Arc<AtomicUsize>,
+}
+
+fn pong(env: &Env, n: i32) -> Result<i32, RuntimeError> {
+    // Don't hold the lock while calling back into the instance.
+    let instance = env.instance.lock().unwrap().clone().unwrap();
+    env.max_depth.fetch_max(instance.reentrancy_depth(), SeqCst);
+    let ping: NativeFunc<i32, i32> = instance.exports.get_native_function("ping").unwrap();
+    ping.call(n)
+}
+
+fn ping_pong(store: &Store, bottom: &str) -> Result<(Instance, Env)> {
+    let module = Module::new(store, ping_pong_wat(bottom))?;
+    let env = Env::default();
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "pong" => Function::new_native_with_env(store, env.clone(), pong),
+            },
+        },
+    )?;
+    *env.instance.lock().unwrap() = Some(instance.clone());
+    Ok((instance, env))
+}
+
+/// Each level of the ping-pong uses some host stack: run deep
+/// recursions on a thread with a stack large enough for them.
+fn on_large_stack(f: impl FnOnce() -> Result<()> + Send + 'static) -> Result<()> {
+    thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(f)?
+        .join()
+        .unwrap()
+}
+
+#[compiler_test(reentrancy)]
+fn ping_pong_1000_levels(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    on_large_stack(move || {
+        let (instance, env) = ping_pong(&store, "(i32.const 0)")?;
+        let ping: NativeFunc<i32, i32> = instance.exports.get_native_function("ping")?;
+
+        assert_eq!(instance.reentrancy_depth(), 0);
+        assert_eq!(ping.call(1000)?, 1000);
+        assert_eq!(env.max_depth.load(SeqCst), 1000);
+        assert_eq!(instance.reentrancy_depth(), 0);
+
+        // Break the cycle between the instance and its env.
+        env.instance.lock().unwrap().take();
+        Ok(())
+    })
+}
+
+#[compiler_test(reentrancy)]
+fn ping_pong_unwinds_innermost_trap(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    on_large_stack(move || {
+        let (instance, env) = ping_pong(&store, "(unreachable)")?;
+        let ping: NativeFunc<i32, i32> = instance.exports.get_native_function("ping")?;
+        let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+
+        // The trap goes through every level, and can happen again.
+        for _ in 0..2 {
+            let error = ping.call(100).unwrap_err();
+            assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+            assert_eq!(instance.reentrancy_depth(), 0);
+        }
+
+        // The instance is still usable.
+        assert_eq!(answer.call()?, 42);
+
+        env.instance.lock().unwrap().take();
+        Ok(())
+    })
+}
+
+#[compiler_test(reentrancy)]
+fn max_reentrancy_depth(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let (instance, env) = ping_pong(&store, "(i32.const 0)")?;
+    let ping: NativeFunc<i32, i32> = instance.exports.get_native_function("ping")?;
+
+    // `ping(n)` nests `n + 1` calls into the instance.
+    store.max_reentrancy_depth(10);
+    assert_eq!(ping.call(9)?, 9);
+
+    let error = ping.call(10).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::ReentrancyLimitExceeded));
+    assert_eq!(instance.reentrancy_depth(), 0);
+
+    store.max_reentrancy_depth(usize::MAX);
+    assert_eq!(ping.call(10)?, 10);
+
+    env.instance.lock().unwrap().take();
+    Ok(())
+}
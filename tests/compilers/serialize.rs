@@ -74,3 +74,147 @@ fn test_deserialize(config: crate::Config) -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[test]
+#[cfg(all(feature = "universal", feature = "singlepass"))]
+fn test_universal_cross_compilation() -> Result<()> {
+    use std::str::FromStr;
+
+    // Pick a target whose architecture differs from the host.
+    let triple = if Triple::host().architecture == Architecture::X86_64 {
+        Triple::from_str("aarch64-unknown-linux-gnu")
+    } else {
+        Triple::from_str("x86_64-unknown-linux-gnu")
+    }
+    .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let target = Target::new(triple.clone(), CpuFeature::set());
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::new())
+            .target(target)
+            .engine();
+    let store = Store::new(&engine);
+    let wat = r#"
+        (module $name
+            (func (export "sum") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    assert_eq!(module.name(), Some("name"));
+    assert_eq!(module.exports().count(), 1);
+
+    // The artifact is well-formed and embeds the target triple...
+    let serialized_bytes = module.serialize()?;
+    assert!(wasmer_engine_universal::UniversalArtifact::is_deserializable(&serialized_bytes));
+    assert!(serialized_bytes
+        .windows(triple.to_string().len())
+        .any(|window| window == triple.to_string().as_bytes()));
+
+    // ...but it can't be loaded or run on this host.
+    let headless_store = Store::new(&wasmer_engine_universal::Universal::headless().engine());
+    match unsafe { Module::deserialize(&headless_store, &serialized_bytes) } {
+        Err(DeserializeError::Incompatible(_)) => {}
+        Err(e) => panic!("Unexpected deserialization error: {}", e),
+        Ok(_) => panic!("A cross-compiled module shouldn't be deserializable on the host"),
+    }
+    assert!(Instance::new(&module, &imports! {}).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "universal", feature = "singlepass"))]
+fn test_deserialize_incompatible_cpu_features() -> Result<()> {
+    use enumset::EnumSet;
+
+    // Pretend the module is compiled for a CPU feature the host lacks.
+    let available = CpuFeature::for_host();
+    let missing = match EnumSet::<CpuFeature>::all()
+        .difference(available)
+        .iter()
+        .next()
+    {
+        Some(missing) => missing,
+        None => return Ok(()),
+    };
+    let target = Target::new(Triple::host(), available | missing);
+    let engine =
+        wasmer_engine_universal::Universal::new(wasmer_compiler_singlepass::Singlepass::new())
+            .target(target)
+            .engine();
+    let store = Store::new(&engine);
+    let module = Module::new(&store, "(module (func (export \"run\")))")?;
+    let serialized_bytes = module.serialize()?;
+
+    let headless_store = Store::new(&wasmer_engine_universal::Universal::headless().engine());
+    match unsafe { Module::deserialize(&headless_store, &serialized_bytes) } {
+        Err(DeserializeError::IncompatibleTarget {
+            required,
+            available: host,
+        }) => {
+            assert_eq!(required, available | missing);
+            assert_eq!(host, available);
+            assert_eq!(required.difference(host), EnumSet::only(missing));
+        }
+        Err(e) => panic!("Unexpected deserialization error: {}", e),
+        Ok(_) => panic!("The module shouldn't be deserializable on the host"),
+    }
+
+    // The check can be skipped, but the module still can't be run.
+    let module =
+        unsafe { Module::deserialize_unchecked_target(&headless_store, &serialized_bytes)? };
+    assert_eq!(module.exports().count(), 1);
+    assert!(matches!(
+        Instance::new(&module, &imports! {}),
+        Err(InstantiationError::CpuFeature(_))
+    ));
+    Ok(())
+}
+
+/// A module with a name section, which the compilers only keep in
+/// part.
+const RETAINED_WAT: &str = r#"
+    (module $retained
+        (func $add (export "add") (param $a i32) (param $b i32) (result i32)
+            (i32.add (local.get $a) (local.get $b))))
+"#;
+
+#[compiler_test(serialize)]
+fn test_retain_wasm_bytes(mut config: crate::Config) -> Result<()> {
+    if config.engine == crate::Engine::Dylib {
+        // The dylib engine doesn't retain the binaries.
+        return Ok(());
+    }
+    let wasm = wat2wasm(RETAINED_WAT.as_bytes())?.into_owned();
+
+    let store = config.store();
+    let module = Module::new(&store, &wasm)?;
+    assert_eq!(module.wasm_bytes(), None);
+    let without_bytes = module.serialize()?;
+
+    config.set_retain_wasm_bytes(true);
+    let store = config.store();
+    let module = Module::new(&store, &wasm)?;
+    assert_eq!(module.wasm_bytes(), Some(&wasm[..]));
+    let with_bytes = module.serialize()?;
+
+    // The binary is stored as is, next to a few bytes of padding and
+    // length.
+    assert!(with_bytes.len() >= without_bytes.len() + wasm.len());
+    assert!(with_bytes.len() <= without_bytes.len() + wasm.len() + 32);
+
+    let headless_store = config.headless_store();
+    let deserialized = unsafe { Module::deserialize(&headless_store, &with_bytes)? };
+    assert_eq!(deserialized.wasm_bytes(), Some(&wasm[..]));
+    assert_eq!(deserialized.hash(), module.hash());
+    assert!(deserialized.hash().is_some());
+
+    // Without retention, the binary isn't serialized, but its hash
+    // is.
+    let deserialized = unsafe { Module::deserialize(&headless_store, &without_bytes)? };
+    assert_eq!(deserialized.wasm_bytes(), None);
+    assert_eq!(deserialized.hash(), module.hash());
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Sharing the compiled code of the universal engine through a
+//! directory.
+
+#![cfg(all(feature = "universal", feature = "singlepass", unix))]
+
+use anyhow::Result;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use wasmer::*;
+use wasmer_engine_universal::SharedCodeStats;
+
+const WAT: &str = r#"
+(module
+    (func (export "add") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1))))
+"#;
+
+fn engine(dir: &Path) -> UniversalEngine {
+    Universal::new(Singlepass::new())
+        .with_shared_code_dir(dir)
+        .engine()
+}
+
+fn run(engine: &UniversalEngine) -> Result<()> {
+    let store = Store::new(engine);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add = instance
+        .exports
+        .get_native_function::<(i32, i32), i32>("add")?;
+    assert_eq!(add.call(1, 2)?, 3);
+    Ok(())
+}
+
+fn code_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn shared_code_is_mapped_by_other_engines() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    let first = engine(dir.path());
+    run(&first)?;
+    assert_eq!(
+        first.shared_code_stats(),
+        Some(SharedCodeStats {
+            written: 1,
+            reused: 0,
+            corrupted: 0
+        })
+    );
+
+    let files = code_files(dir.path())?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].extension().unwrap(), "code");
+    assert_eq!(fs::metadata(&files[0])?.permissions().mode() & 0o777, 0o444);
+
+    // The second engine maps the file written by the first one.
+    let second = engine(dir.path());
+    run(&second)?;
+    assert_eq!(
+        second.shared_code_stats(),
+        Some(SharedCodeStats {
+            written: 0,
+            reused: 1,
+            corrupted: 0
+        })
+    );
+    assert_eq!(code_files(dir.path())?, files);
+
+    assert_eq!(
+        Universal::new(Singlepass::new())
+            .engine()
+            .shared_code_stats(),
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn corrupted_shared_code_is_written_again() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    run(&engine(dir.path()))?;
+
+    let files = code_files(dir.path())?;
+    assert_eq!(files.len(), 1);
+    let mut content = fs::read(&files[0])?;
+    content[0] ^= 0xff;
+    fs::set_permissions(&files[0], fs::Permissions::from_mode(0o644))?;
+    fs::write(&files[0], &content)?;
+
+    // The corruption is detected, and the code compiled again replaces
+    // the file.
+    let second = engine(dir.path());
+    run(&second)?;
+    assert_eq!(
+        second.shared_code_stats(),
+        Some(SharedCodeStats {
+            written: 1,
+            reused: 0,
+            corrupted: 1
+        })
+    );
+    assert_eq!(code_files(dir.path())?, files);
+    assert_ne!(fs::read(&files[0])?, content);
+
+    let third = engine(dir.path());
+    run(&third)?;
+    assert_eq!(third.shared_code_stats().unwrap().reused, 1);
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! The code generation options of Singlepass.
+#![cfg(all(feature = "singlepass", feature = "universal"))]
+
+use anyhow::Result;
+#[cfg(feature = "memory-access-tracing")]
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+use wasmer_compiler_singlepass::{Singlepass, SinglepassConfig};
+use wasmer_engine_universal::Universal;
+
+fn instance(config: SinglepassConfig, wat: &str) -> Result<Instance> {
+    let store = Store::new(&Universal::new(Singlepass::new_with_config(config)).engine());
+    let module = Module::new(&store, wat)?;
+    Ok(Instance::new(&module, &imports! {})?)
+}
+
+#[test]
+fn new_with_config() {
+    let mut config = SinglepassConfig::default();
+    assert_eq!(config, Singlepass::new().config());
+    config.enable_nan_canonicalization = false;
+    config.enable_stack_check = true;
+    assert_eq!(Singlepass::new_with_config(config.clone()).config(), config);
+}
+
+#[test]
+fn nan_canonicalization() -> Result<()> {
+    // Adding zero to a signaling NaN quiets it, keeping its payload
+    // unless NaNs are canonicalized.
+    let wat = r#"(module
+        (func (export "add") (param i32) (result i32)
+            (i32.reinterpret_f32
+                (f32.add (f32.reinterpret_i32 (local.get 0)) (f32.const 0)))))"#;
+    let nan = 0x7fa0_0000_u32 as i32;
+
+    let mut config = SinglepassConfig::default();
+    config.enable_nan_canonicalization = true;
+    let add = instance(config.clone(), wat)?
+        .exports
+        .get_native_function::<i32, i32>("add")?;
+    assert_eq!(add.call(nan)? as u32, 0x7fc0_0000);
+
+    config.enable_nan_canonicalization = false;
+    let add = instance(config, wat)?
+        .exports
+        .get_native_function::<i32, i32>("add")?;
+    assert_eq!(add.call(nan)? as u32, 0x7fe0_0000);
+    Ok(())
+}
+
+#[test]
+fn stack_check() -> Result<()> {
+    let wat = r#"(module
+        (func $recurse (export "recurse") (param i64) (result i64)
+            (local i64 i64 i64 i64 i64 i64 i64 i64)
+            (i64.add (call $recurse (local.get 0)) (i64.const 1))))"#;
+    let mut config = SinglepassConfig::default();
+    config.enable_stack_check = true;
+    let recurse = instance(config, wat)?
+        .exports
+        .get_native_function::<i64, i64>("recurse")?;
+    let error = recurse.call(0).unwrap_err();
+    assert!(error.message().contains("call stack exhausted"));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "memory-access-tracing")]
+fn memory_access_tracing() -> Result<()> {
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(MemoryIndex, u64, u32, AccessKind)>>);
+
+    impl MemoryAccessTracer for Recorder {
+        fn on_access(&self, memory: MemoryIndex, addr: u64, len: u32, kind: AccessKind) {
+            self.0.lock().unwrap().push((memory, addr, len, kind));
+        }
+    }
+
+    let wat = r#"(module
+        (memory 1)
+        (func (export "copy") (param $src i32) (param $dst i32)
+            (i32.store offset=4 (local.get $dst) (i32.load8_u offset=1 (local.get $src)))))"#;
+    let copy_with = |compiler: Singlepass| -> Result<Arc<Recorder>> {
+        let store = Store::new(&Universal::new(compiler).engine());
+        let recorder = Arc::new(Recorder::default());
+        store.set_memory_access_tracer(Some(recorder.clone() as Arc<dyn MemoryAccessTracer>));
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &imports! {})?;
+        let copy = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("copy")?;
+        copy.call(10, 20)?;
+        // The out of bounds accesses are reported before trapping.
+        assert!(copy.call(0xffff, 0).is_err());
+        // The address is unsigned.
+        assert!(copy.call(-1, 0).is_err());
+        Ok(recorder)
+    };
+
+    let mut compiler = Singlepass::new();
+    compiler.memory_access_tracing(true);
+    let recorder = copy_with(compiler)?;
+    let memory = MemoryIndex::from_u32(0);
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            (memory, 11, 1, AccessKind::Load),
+            (memory, 24, 4, AccessKind::Store),
+            (memory, 0x1_0000, 1, AccessKind::Load),
+            (memory, 0x1_0000_0000, 1, AccessKind::Load),
+        ]
+    );
+
+    // The code compiled without tracing doesn't report its accesses.
+    let recorder = copy_with(Singlepass::new())?;
+    assert!(recorder.0.lock().unwrap().is_empty());
+    Ok(())
+}
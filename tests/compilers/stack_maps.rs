@@ -0,0 +1,86 @@
+use anyhow::Result;
+use wasmer::*;
+
+const WAT: &str = r#"
+(module
+    (import "env" "gc" (func $gc))
+    (func (export "keep") (param externref) (result externref)
+        (call $gc)
+        (local.get 0))
+    (func (export "drop") (param externref)
+        (call $gc))
+    (func (export "operand") (param externref) (result externref)
+        (local.get 0)
+        (call $gc)))
+"#;
+
+#[compiler_test(stack_maps)]
+fn stack_maps(mut config: crate::Config) -> Result<()> {
+    // Stack maps are only emitted by Cranelift.
+    if config.compiler != crate::Compiler::Cranelift || config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    config.set_stack_maps(true);
+    let store = config.store();
+    let module = Module::new(&store, WAT)?;
+
+    // The reference is live across the call in `keep`...
+    let keep = module.stack_maps(LocalFunctionIndex::from_u32(0));
+    assert_eq!(keep.len(), 1);
+    assert_eq!(keep[0].live_slots.len(), 1);
+    assert!(keep[0].live_slots[0] < keep[0].mapped_bytes);
+    assert!(keep[0].code_offset > 0);
+
+    // ...but not in `drop`.
+    assert!(module
+        .stack_maps(LocalFunctionIndex::from_u32(1))
+        .iter()
+        .all(|map| map.live_slots.is_empty()));
+
+    Ok(())
+}
+
+#[compiler_test(stack_maps)]
+fn singlepass_stack_maps(mut config: crate::Config) -> Result<()> {
+    // Singlepass only emits stack maps on x86_64.
+    if config.compiler != crate::Compiler::Singlepass
+        || config.engine != crate::Engine::Universal
+        || !cfg!(target_arch = "x86_64")
+    {
+        return Ok(());
+    }
+    config.set_stack_maps(true);
+    let store = config.store();
+    let module = Module::new(&store, WAT)?;
+
+    // Singlepass keeps the locals holding references on the stack for
+    // the whole function...
+    for index in 0..2 {
+        let maps = module.stack_maps(LocalFunctionIndex::from_u32(index));
+        assert_eq!(maps.len(), 1);
+        assert_eq!(maps[0].live_slots.len(), 1);
+        assert!(maps[0].live_slots[0] < maps[0].mapped_bytes);
+    }
+
+    // ...and the reference on the operand stack is saved around the
+    // call, next to the local it was read from.
+    let operand = module.stack_maps(LocalFunctionIndex::from_u32(2));
+    assert_eq!(operand.len(), 1);
+    assert_eq!(operand[0].live_slots.len(), 2);
+    assert!(operand[0]
+        .live_slots
+        .iter()
+        .all(|slot| *slot < operand[0].mapped_bytes));
+
+    Ok(())
+}
+
+#[compiler_test(stack_maps)]
+fn stack_maps_are_disabled_by_default(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, WAT)?;
+    assert!(module
+        .stack_maps(LocalFunctionIndex::from_u32(0))
+        .is_empty());
+    Ok(())
+}
@@ -36,6 +36,64 @@ fn test_trap_return(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(traps)]
+fn host_traps_unwind_the_wasm_frames(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (import "host" "consume" (func $consume (param i32)))
+            (global $completed (export "completed") (mut i32) (i32.const 0))
+            (func $step (param i32)
+                (call $consume (local.get 0))
+                (global.set $completed (i32.add (global.get $completed) (i32.const 1))))
+            (func (export "run") (param i32)
+                (loop $continue
+                    (call $step (i32.const 10))
+                    (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                    (br_if $continue (local.get 0)))))
+    "#;
+
+    #[derive(Clone, WasmerEnv)]
+    struct Quota {
+        remaining: std::sync::Arc<std::sync::atomic::AtomicI32>,
+    }
+
+    fn consume(quota: &Quota, amount: i32) -> Result<(), RuntimeError> {
+        let remaining = quota
+            .remaining
+            .fetch_sub(amount, std::sync::atomic::Ordering::SeqCst);
+        if remaining < amount {
+            return Err(RuntimeError::new("quota exceeded"));
+        }
+        Ok(())
+    }
+
+    let module = Module::new(&store, wat)?;
+    let quota = Quota {
+        remaining: std::sync::Arc::new(std::sync::atomic::AtomicI32::new(25)),
+    };
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "consume" => Function::new_native_with_env(&store, quota, consume),
+            },
+        },
+    )?;
+    let run = instance.exports.get_function("run")?;
+
+    let error = run.call(&[Value::I32(5)]).unwrap_err();
+    assert_eq!(error.message(), "quota exceeded");
+    // The third step trapped before completing, and the loop didn't
+    // go on.
+    assert_eq!(
+        instance.exports.get_global("completed")?.get(),
+        Value::I32(2)
+    );
+
+    Ok(())
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn test_trap_trace(config: crate::Config) -> Result<()> {
@@ -176,6 +234,43 @@ RuntimeError: unreachable
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn trap_display_after_module_drop(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $m
+            (func $die unreachable)
+            (func call $die)
+            (func $foo call 1)
+            (func (export "bar") call $foo)
+        )
+    "#;
+    let expected = "\
+RuntimeError: unreachable
+    at die (m[0]:0x23)
+    at <unnamed> (m[1]:0x27)
+    at foo (m[2]:0x2c)
+    at <unnamed> (m[3]:0x31)";
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let bar = instance.exports.get_function("bar")?;
+    let first = bar.call(&[]).err().expect("error calling function");
+    let second = bar.call(&[]).err().expect("error calling function");
+
+    // The frames are symbolized when the error is first formatted,
+    // the same way whether the module is still alive or not.
+    assert_eq!(first.to_string(), expected);
+    drop(instance);
+    drop(module);
+    drop(store);
+    assert_eq!(second.to_string(), expected);
+    assert_eq!(second.trace().len(), 4);
+    assert_eq!(second.trace()[2].function_name(), Some("foo"));
+    Ok(())
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn trap_display_multi_module(config: crate::Config) -> Result<()> {
@@ -255,7 +350,8 @@ fn trap_start_function_import(config: crate::Config) -> Result<()> {
     match err {
         InstantiationError::Link(_)
         | InstantiationError::HostEnvInitialization(_)
-        | InstantiationError::CpuFeature(_) => {
+        | InstantiationError::CpuFeature(_)
+        | InstantiationError::Compile(_) => {
             panic!("It should be a start error")
         }
         InstantiationError::Start(err) => {
@@ -469,3 +565,45 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn large_offset_within_memory_guard(config: crate::Config) -> Result<()> {
+    // Dynamic memories, whose default guard of 64 KiB doesn't cover the
+    // offset of the access below, but the configured one of 1 MiB does.
+    let compiler_config = config.compiler_config(config.canonicalize_nans);
+    let engine = config.engine(compiler_config);
+    let tunables = BaseTunables {
+        static_memory_bound: Pages(0),
+        static_memory_offset_guard_size: 0x1_0000,
+        dynamic_memory_offset_guard_size: 0x1_0000,
+    };
+    let mut store = Store::new_with_tunables(&*engine, tunables);
+    store.memory_guard_size(0x10_0000);
+
+    let memory_type = MemoryType::new(1, None, false);
+    let style = store.tunables().memory_style(&memory_type);
+    assert!(matches!(style, vm::MemoryStyle::Dynamic { .. }));
+    assert_eq!(style.offset_guard_size(), 0x10_0000);
+
+    let wat = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "load") (result i32)
+            i32.const 0
+            i32.load offset=0x80000))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let load = instance.exports.get_native_function::<(), i32>("load")?;
+
+    // The access lands in the guard region.
+    let error = load.call().unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+
+    // Once the memory covers it, the same access succeeds.
+    let memory = instance.exports.get_memory("memory")?;
+    memory.grow(8)?;
+    assert_eq!(load.call()?, 0);
+
+    Ok(())
+}
@@ -1,5 +1,13 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+use wasmer_wasi::types::{__WASI_ENOTCAPABLE, __WASI_ESUCCESS};
+use wasmer_wasi::{
+    generate_import_object_from_env, DeniedSyscallBehavior, Pipe, Wasi, WasiError, WasiState,
+    WasiSyscall, WasiVersion,
+};
 use wasmer_wast::{WasiFileSystemKind, WasiTest};
 
 // The generated tests (from build.rs) look like:
@@ -40,3 +48,382 @@ pub fn run_wasi(
 
     Ok(())
 }
+
+#[compiler_test(wasi)]
+fn last_errno_after_failing_path_open(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "does-not-exist.txt")
+        (func (export "open_missing") (result i32)
+            (call $path_open
+                (i32.const 3)  ;; dirfd: the root directory
+                (i32.const 0)  ;; dirflags
+                (i32.const 0)  ;; path
+                (i32.const 18) ;; path_len
+                (i32.const 0)  ;; o_flags
+                (i64.const 0)  ;; fs_rights_base
+                (i64.const 0)  ;; fs_rights_inheriting
+                (i32.const 0)  ;; fs_flags
+                (i32.const 32) ;; fd
+            )
+        )
+)"#;
+    let module = Module::new(&store, wat)?;
+    let mut wasi_env = WasiState::new("last_errno").finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let open_missing: NativeFunc<(), i32> = instance.exports.get_native_function("open_missing")?;
+
+    assert_eq!(wasi_env.last_errno(), __WASI_ESUCCESS);
+    let errno = open_missing.call()?;
+    assert_ne!(errno, __WASI_ESUCCESS as i32);
+    assert_eq!(wasi_env.last_errno() as i32, errno);
+    Ok(())
+}
+
+const RANDOM_AND_EXIT_WAT: &str = r#"(module
+    (import "wasi_snapshot_preview1" "random_get"
+        (func $random_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit"
+        (func $proc_exit (param i32)))
+    (memory (export "memory") 1)
+    (func (export "random") (result i32)
+        (call $random_get (i32.const 0) (i32.const 8)))
+    (func (export "exit") (result i32)
+        (call $proc_exit (i32.const 1))
+        (i32.const 42))
+)"#;
+
+#[compiler_test(wasi)]
+fn denied_syscalls_return_enotcapable(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, RANDOM_AND_EXIT_WAT)?;
+    let mut wasi_env = WasiState::new("deny")
+        .deny_syscalls(&[WasiSyscall::RandomGet, WasiSyscall::ProcExit])
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let random: NativeFunc<(), i32> = instance.exports.get_native_function("random")?;
+    let exit: NativeFunc<(), i32> = instance.exports.get_native_function("exit")?;
+
+    assert_eq!(random.call()?, __WASI_ENOTCAPABLE as i32);
+    assert_eq!(wasi_env.last_errno(), __WASI_ENOTCAPABLE);
+    assert_eq!(random.call()?, __WASI_ENOTCAPABLE as i32);
+    // `proc_exit` has no return code: it returns without exiting.
+    assert_eq!(exit.call()?, 42);
+
+    let expected: HashMap<_, _> = vec![(WasiSyscall::RandomGet, 2), (WasiSyscall::ProcExit, 1)]
+        .into_iter()
+        .collect();
+    assert_eq!(wasi_env.denied_syscall_stats(), expected);
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn denied_syscalls_trap(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, RANDOM_AND_EXIT_WAT)?;
+    let mut wasi_env = WasiState::new("deny")
+        .deny_syscalls(&[WasiSyscall::RandomGet, WasiSyscall::ProcExit])
+        .denied_syscall_behavior(DeniedSyscallBehavior::Trap)
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let random: NativeFunc<(), i32> = instance.exports.get_native_function("random")?;
+    let exit: NativeFunc<(), i32> = instance.exports.get_native_function("exit")?;
+
+    let error = random.call().unwrap_err().downcast::<WasiError>()?;
+    assert!(matches!(
+        error,
+        WasiError::SyscallDenied(WasiSyscall::RandomGet)
+    ));
+    let error = exit.call().unwrap_err().downcast::<WasiError>()?;
+    assert!(matches!(
+        error,
+        WasiError::SyscallDenied(WasiSyscall::ProcExit)
+    ));
+
+    let expected: HashMap<_, _> = vec![(WasiSyscall::RandomGet, 1), (WasiSyscall::ProcExit, 1)]
+        .into_iter()
+        .collect();
+    assert_eq!(wasi_env.denied_syscall_stats(), expected);
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn wasi_builder_instantiates_module(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (import "wasi_snapshot_preview1" "args_sizes_get"
+            (func $args_sizes_get (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "argc") (result i32)
+            (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+            (i32.load (i32.const 0)))
+)"#;
+    let module = Module::new(&store, wat)?;
+
+    let (wasi_env, import_object) = Wasi::builder("builder")
+        .args(&["a", "b"])
+        .env("KEY", "VALUE")
+        .finalize(&store)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let argc: NativeFunc<(), i32> = instance.exports.get_native_function("argc")?;
+
+    assert_eq!(argc.call()?, 3);
+    assert_eq!(wasi_env.last_errno(), __WASI_ESUCCESS);
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn wasi_builder_reports_configuration_errors(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let result = Wasi::builder("builder")
+        .preopen_dir("/this/directory/does/not/exist")
+        .arg("a")
+        .finalize(&store);
+
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn override_syscall_captures_fd_write(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        ;; One iovec pointing to the string.
+        (data (i32.const 0) "\10\00\00\00\05\00\00\00")
+        (data (i32.const 16) "hello")
+        (func (export "greet") (result i32)
+            (call $fd_write
+                (i32.const 1) ;; fd: stdout
+                (i32.const 0) ;; iovs
+                (i32.const 1) ;; iovs_len
+                (i32.const 8) ;; nwritten
+            )
+        )
+)"#;
+    let module = Module::new(&store, wat)?;
+
+    #[derive(WasmerEnv, Clone, Default)]
+    struct Captured {
+        fd: Arc<Mutex<Vec<u32>>>,
+        bytes: Arc<Mutex<Vec<u8>>>,
+    }
+    let captured = Captured::default();
+    let fd_write = Function::new_with_caller(
+        &store,
+        FunctionType::new(vec![Type::I32; 4], vec![Type::I32]),
+        captured.clone(),
+        |caller, captured, args| {
+            let memory = caller.memory(0).unwrap();
+            let view = memory.view::<u32>();
+            let iovs = args[1].unwrap_i32() as usize / 4;
+            let (buf, len) = (view[iovs].get() as usize, view[iovs + 1].get() as usize);
+            let bytes = memory.view::<u8>()[buf..buf + len]
+                .iter()
+                .map(|byte| byte.get())
+                .collect::<Vec<_>>();
+            view[args[3].unwrap_i32() as usize / 4].set(len as u32);
+
+            captured
+                .fd
+                .lock()
+                .unwrap()
+                .push(args[0].unwrap_i32() as u32);
+            captured.bytes.lock().unwrap().extend(bytes);
+            Ok(vec![Val::I32(__WASI_ESUCCESS as i32)])
+        },
+    );
+
+    let mut wasi_env = WasiState::new("override").finalize()?;
+    wasi_env.override_syscall("fd_write", fd_write)?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let greet: NativeFunc<(), i32> = instance.exports.get_native_function("greet")?;
+
+    assert_eq!(greet.call()?, __WASI_ESUCCESS as i32);
+    assert_eq!(*captured.fd.lock().unwrap(), vec![1]);
+    assert_eq!(captured.bytes.lock().unwrap().as_slice(), b"hello");
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.view::<u32>()[2].get(), 5);
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn override_syscall_checks_the_signature(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let mut wasi_env = WasiState::new("override").finalize()?;
+
+    let error = wasi_env
+        .override_syscall("fd_write", Function::new_native(&store, |_: i32| 0i32))
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        WasiError::IncompatibleSyscallOverride {
+            syscall: WasiSyscall::FdWrite,
+            ..
+        }
+    ));
+    let error = wasi_env
+        .override_syscall("fd_scribble", Function::new_native(&store, || {}))
+        .unwrap_err();
+    assert!(matches!(error, WasiError::UnknownSyscall(name) if name == "fd_scribble"));
+    Ok(())
+}
+
+/// The WAT imports of `syscalls` from snapshot 1, with the types of
+/// their functions.
+fn syscall_imports(store: &Store, syscalls: &[WasiSyscall]) -> anyhow::Result<String> {
+    let wasi_env = WasiState::new("types").finalize()?;
+    let import_object = generate_import_object_from_env(store, wasi_env, WasiVersion::Snapshot1);
+    let namespace = import_object
+        .get_namespace_exports("wasi_snapshot_preview1")
+        .unwrap();
+    let mut imports = String::new();
+    for syscall in syscalls {
+        let ty = namespace.get_function(syscall.name())?.ty();
+        let types = |types: &[Type]| {
+            types
+                .iter()
+                .map(|ty| format!(" {}", ty.to_string().to_lowercase()))
+                .collect::<String>()
+        };
+        imports.push_str(&format!(
+            "(import \"wasi_snapshot_preview1\" \"{}\" (func (param{}) (result{})))\n",
+            syscall,
+            types(ty.params()),
+            types(ty.results()),
+        ));
+    }
+    Ok(imports)
+}
+
+#[compiler_test(wasi)]
+fn import_object_builds_the_imported_syscalls(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    for syscalls in &[
+        &WasiSyscall::ALL[..3],
+        &WasiSyscall::ALL[..20],
+        WasiSyscall::ALL,
+    ] {
+        let wat = format!(
+            "(module {} (memory (export \"memory\") 1))",
+            syscall_imports(&store, syscalls)?
+        );
+        let module = Module::new(&store, wat)?;
+        let mut wasi_env = WasiState::new("imports").finalize()?;
+        let import_object = wasi_env.import_object(&module)?;
+
+        assert_eq!(wasi_env.syscall_functions_built(), syscalls.len());
+        let namespace = import_object
+            .get_namespace_exports("wasi_snapshot_preview1")
+            .unwrap();
+        assert_eq!(namespace.len(), syscalls.len());
+        Instance::new(&module, &import_object)?;
+    }
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn import_object_runs_the_imported_syscalls(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, RANDOM_AND_EXIT_WAT)?;
+    let mut wasi_env = WasiState::new("imports").finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let random: NativeFunc<(), i32> = instance.exports.get_native_function("random")?;
+    let exit: NativeFunc<(), i32> = instance.exports.get_native_function("exit")?;
+
+    assert_eq!(wasi_env.syscall_functions_built(), 2);
+    assert_eq!(random.call()?, __WASI_ESUCCESS as i32);
+    let error = exit.call().unwrap_err().downcast::<WasiError>()?;
+    assert!(matches!(error, WasiError::Exit(1)));
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn import_object_suggests_misspelled_syscalls(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = format!(
+        "(module {} (import \"wasi_snapshot_preview1\" \"fd_wirte\" (func (param i32 i32 i32 i32) (result i32))))",
+        syscall_imports(&store, &[WasiSyscall::FdRead, WasiSyscall::FdClose])?
+    );
+    let module = Module::new(&store, wat)?;
+    let mut wasi_env = WasiState::new("imports").finalize()?;
+
+    let error = wasi_env.import_object(&module).unwrap_err();
+    assert!(matches!(
+        &error,
+        WasiError::UnknownImport {
+            name,
+            suggestion: Some(WasiSyscall::FdWrite),
+            ..
+        } if name == "fd_wirte"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "The module imports `fd_wirte` from `wasi_snapshot_preview1`, which is not a WASI syscall (did you mean `fd_write`?)"
+    );
+    assert_eq!(wasi_env.syscall_functions_built(), 0);
+    Ok(())
+}
+
+#[compiler_test(wasi)]
+fn pipes_round_trip_stdin_to_stdout(config: crate::Config) -> anyhow::Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (import "wasi_snapshot_preview1" "fd_read"
+            (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        ;; Copy `stdin` to `stdout` 16 bytes at a time, until the end
+        ;; of `stdin`.
+        (func (export "_start")
+            (i32.store (i32.const 0) (i32.const 64))
+            (loop $echo
+                (i32.store (i32.const 4) (i32.const 16))
+                (if (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8))
+                    (then unreachable))
+                (if (i32.load (i32.const 8))
+                    (then
+                        (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                        (if (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))
+                            (then unreachable))
+                        (br $echo)))))
+)"#;
+    let module = Module::new(&store, wat)?;
+
+    let (stdin_reader, mut stdin_writer) = Pipe::new();
+    let (mut stdout_reader, stdout_writer) = Pipe::new();
+    let mut wasi_env = WasiState::new("echo")
+        .stdin(Box::new(stdin_reader))
+        .stdout(Box::new(stdout_writer))
+        .finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let start: NativeFunc<(), ()> = instance.exports.get_native_function("_start")?;
+
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(3);
+    stdin_writer.write_all(&input)?;
+    start.call()?;
+
+    let mut output = Vec::new();
+    stdout_reader.read_to_end(&mut output)?;
+    assert_eq!(output, input);
+
+    // The host can't use the ends the other way around.
+    assert!(stdout_reader.write_all(b"x").is_err());
+    assert!(stdin_writer.read(&mut [0; 1]).is_err());
+
+    fn assert_send<T: Send>(_: &T) {}
+    assert_send(&stdout_reader);
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Tests for the `inspect` subcommand
+
+use anyhow::bail;
+use serde_json::{json, Value};
+use std::process::Command;
+use wasmer_integration_tests_cli::{ASSET_PATH, WASMER_PATH};
+
+fn test_inspect_wasm_path() -> String {
+    format!("{}/{}", ASSET_PATH, "inspect.wasm")
+}
+
+#[test]
+fn inspect_json_schema() -> anyhow::Result<()> {
+    let output = Command::new(WASMER_PATH)
+        .arg("inspect")
+        .arg("--format")
+        .arg("json")
+        .arg(test_inspect_wasm_path())
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "inspect failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    let description: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(description["type"], "wasm");
+    assert_eq!(
+        description["size"],
+        std::fs::metadata(test_inspect_wasm_path())?.len()
+    );
+    assert_eq!(
+        description["required_features"],
+        json!(["simd", "bulk-memory"])
+    );
+
+    assert_eq!(
+        description["imports"],
+        json!([
+            {
+                "module": "env",
+                "name": "log",
+                "kind": "function",
+                "type": { "params": ["i32", "i64"], "results": ["f32"] }
+            },
+            {
+                "module": "env",
+                "name": "memory",
+                "kind": "memory",
+                "type": { "minimum": 1, "maximum": 2, "shared": false }
+            },
+            {
+                "module": "env",
+                "name": "table",
+                "kind": "table",
+                "type": { "element": "funcref", "minimum": 3, "maximum": null }
+            },
+            {
+                "module": "env",
+                "name": "counter",
+                "kind": "global",
+                "type": { "value": "i32", "mutable": true }
+            }
+        ])
+    );
+    assert_eq!(
+        description["exports"],
+        json!([
+            {
+                "name": "splat",
+                "kind": "function",
+                "type": { "params": ["i32"], "results": ["v128"] }
+            },
+            {
+                "name": "fill",
+                "kind": "function",
+                "type": { "params": ["i32", "i32", "i32"], "results": [] }
+            },
+            {
+                "name": "answer",
+                "kind": "global",
+                "type": { "value": "i64", "mutable": false }
+            }
+        ])
+    );
+    assert_eq!(
+        description["custom_sections"],
+        json!([
+            { "name": "first", "size": 3 },
+            { "name": "producers", "size": 5 }
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn inspect_rejects_unknown_formats() -> anyhow::Result<()> {
+    let output = Command::new(WASMER_PATH)
+        .arg("inspect")
+        .arg("--format")
+        .arg("yaml")
+        .arg(test_inspect_wasm_path())
+        .output()?;
+
+    assert_eq!(output.status.success(), false);
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("unknown format `yaml`"), "{}", stderr);
+    Ok(())
+}
@@ -1,6 +1,7 @@
 //! Basic tests for the `run` subcommand
 
 use anyhow::bail;
+use std::fs;
 use std::process::Command;
 use wasmer_integration_tests_cli::{ASSET_PATH, C_ASSET_PATH, WASMER_PATH};
 
@@ -16,6 +17,10 @@ fn test_no_start_wat_path() -> String {
     format!("{}/{}", ASSET_PATH, "no_start.wat")
 }
 
+fn test_echo_wat_path() -> String {
+    format!("{}/{}", ASSET_PATH, "echo.wat")
+}
+
 #[test]
 fn run_wasi_works() -> anyhow::Result<()> {
     let output = Command::new(WASMER_PATH)
@@ -75,3 +80,91 @@ fn run_no_start_wasm_report_error() -> anyhow::Result<()> {
     assert_eq!(result.contains("Can not find any export functions."), true);
     Ok(())
 }
+
+#[test]
+fn run_wasi_redirects_stdio_to_files() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let stdin = dir.path().join("stdin.txt");
+    let stdout = dir.path().join("stdout.txt");
+    let stderr = dir.path().join("stderr.txt");
+    fs::write(&stdin, "hello from a file\n")?;
+    fs::write(&stdout, "previous output which is truncated\n")?;
+
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(test_echo_wat_path())
+        .arg("--stdin")
+        .arg(&stdin)
+        .arg("--stdout")
+        .arg(&stdout)
+        .arg("--stderr")
+        .arg(&stderr)
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "running failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(&stdout)?, "hello from a file\n");
+    assert_eq!(fs::read_to_string(&stderr)?, "hello from a file\n");
+    Ok(())
+}
+
+#[test]
+fn run_wasi_appends_stdio_to_files() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let stdin = dir.path().join("stdin.txt");
+    let stdout = dir.path().join("stdout.txt");
+    fs::write(&stdin, "second\n")?;
+    fs::write(&stdout, "first\n")?;
+
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(test_echo_wat_path())
+        .arg("--stdin")
+        .arg(&stdin)
+        .arg("--stdout")
+        .arg(&stdout)
+        .arg("--append")
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&stdout)?, "first\nsecond\n");
+    assert_eq!(std::str::from_utf8(&output.stderr)?, "second\n");
+    Ok(())
+}
+
+#[test]
+fn run_wasi_reports_stdio_errors_before_compiling() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let stdin = dir.path().join("missing.txt");
+    let stdout = dir.path().join("stdout.txt");
+
+    // The module is invalid, but the missing stdin file is reported
+    // first.
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(format!("{}/{}", ASSET_PATH, "invalid.wat"))
+        .arg("--stdin")
+        .arg(&stdin)
+        .arg("--stdout")
+        .arg(&stdout)
+        .output()?;
+
+    assert_eq!(output.status.success(), false);
+    let result = std::str::from_utf8(&output.stderr).unwrap().to_string();
+    assert!(
+        result.contains("failed to open the stdin file"),
+        "unexpected error: {}",
+        result
+    );
+    assert!(!stdout.exists());
+    Ok(())
+}
@@ -4,7 +4,9 @@ use crate::DummyArtifact;
 use loupe::MemoryUsage;
 use std::sync::Arc;
 use wasmer_compiler::{CompileError, Features, Target};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{
+    Artifact, DeserializeError, Engine, EngineEpochController, EngineId, Tunables,
+};
 use wasmer_types::FunctionType;
 use wasmer_vm::{
     FuncDataRegistry, SignatureRegistry, VMCallerCheckedAnyfunc, VMContext, VMFuncRef,
@@ -28,6 +30,7 @@ pub struct DummyEngine {
     features: Arc<Features>,
     target: Arc<Target>,
     engine_id: EngineId,
+    epoch_controller: EngineEpochController,
 }
 
 impl DummyEngine {
@@ -39,6 +42,7 @@ impl DummyEngine {
             features: Arc::new(Default::default()),
             target: Arc::new(Default::default()),
             engine_id: EngineId::default(),
+            epoch_controller: EngineEpochController::new(),
         }
     }
 
@@ -72,6 +76,11 @@ impl Engine for DummyEngine {
         self.signatures.lookup(sig)
     }
 
+    /// The WebAssembly features
+    fn features(&self) -> Features {
+        (*self.features).clone()
+    }
+
     #[cfg(feature = "compiler")]
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
@@ -129,7 +138,15 @@ impl Engine for DummyEngine {
         &self.engine_id
     }
 
+    fn deterministic_id(&self) -> String {
+        "dummy".to_string()
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
+
+    fn epoch_controller(&self) -> EngineEpochController {
+        self.epoch_controller.clone()
+    }
 }
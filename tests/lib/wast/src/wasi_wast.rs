@@ -148,7 +148,8 @@ impl<'a> WasiTest<'a> {
     ) -> anyhow::Result<(WasiEnv, Vec<tempfile::TempDir>)> {
         let mut builder = WasiState::new(self.wasm_path);
 
-        let stdin_pipe = Pipe::new();
+        // Nothing is written to `stdin`: the program reads its end.
+        let (stdin_pipe, _) = Pipe::new();
         builder.stdin(Box::new(stdin_pipe));
 
         for (name, value) in &self.envs {